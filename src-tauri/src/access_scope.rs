@@ -0,0 +1,382 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, DragDropEvent, Manager, Window, WindowEvent};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogKind};
+
+use crate::assets::{document_asset_roots, is_sensitive_root};
+use crate::error::{CommandError, CommandResult};
+use crate::{app_store, safe_mode};
+
+// Folders approved with request_scope or by opening a document in them, per workspace root
+// ("" when no workspace is open)
+pub const APPROVED_ROOTS_KEY: &str = "approved_roots";
+
+// Where the file commands may read and write: the open workspace, folders the user approved,
+// and the documents they opened (a dialog, the command line, a file association) together
+// with their folders. Everything is stored canonicalized, so `..` and symlinks can't lead
+// out of it.
+#[derive(Debug, Default)]
+pub struct AccessScope {
+  workspace: Option<PathBuf>,
+  roots: Vec<PathBuf>,
+  files: Vec<PathBuf>,
+}
+
+impl AccessScope {
+  // Add a canonical folder; false if it was already inside the scope
+  fn add_root(&mut self, root: PathBuf) -> bool {
+    if self.roots.iter().any(|approved| root.starts_with(approved)) {
+      return false;
+    }
+    self.roots.retain(|approved| !approved.starts_with(&root));
+    self.roots.push(root);
+    true
+  }
+
+  // Approve a folder and everything below it. Filesystem roots are refused.
+  pub fn allow_folder(&mut self, dir: &Path) -> CommandResult<PathBuf> {
+    let canonical = dir
+      .canonicalize()
+      .map_err(|e| CommandError::from_io(&e, dir, "Invalid path"))?;
+    if !canonical.is_dir() {
+      return Err(CommandError::invalid_path(dir, "Not a folder"));
+    }
+    if is_sensitive_root(&canonical, None) {
+      return Err(CommandError::invalid_path(
+        &canonical,
+        "Refusing to allow access to",
+      ));
+    }
+    self.add_root(canonical.clone());
+    Ok(canonical)
+  }
+
+  // Approve a document (which may not exist yet, for Save As) and the folder its assets are
+  // in. Returns the folders added, for persisting.
+  pub fn allow_document(&mut self, document: &Path, home_dir: Option<&Path>) -> Vec<PathBuf> {
+    if !document.is_absolute() {
+      return Vec::new();
+    }
+    let file = crate::normalize_partially_existing(document);
+    if !self.files.contains(&file) {
+      self.files.push(file);
+    }
+    // A document in the home folder without an assets folder gets no folder at all
+    document_asset_roots(document, home_dir)
+      .unwrap_or_default()
+      .into_iter()
+      .filter(|root| self.add_root(root.clone()))
+      .collect()
+  }
+
+  // The open workspace's folder, replacing the previous workspace's
+  pub fn set_workspace(&mut self, root: Option<PathBuf>) {
+    self.workspace = root;
+  }
+
+  pub fn workspace(&self) -> Option<&Path> {
+    self.workspace.as_deref()
+  }
+
+  pub fn is_allowed(&self, path: &Path) -> bool {
+    if !path.is_absolute() {
+      return false;
+    }
+    let path = crate::normalize_partially_existing(path);
+    self
+      .workspace
+      .iter()
+      .chain(&self.roots)
+      .any(|root| path.starts_with(root))
+      || self.files.contains(&path)
+  }
+
+  pub fn check(&self, path: &Path) -> CommandResult<()> {
+    if self.is_allowed(path) {
+      Ok(())
+    } else {
+      Err(CommandError::ScopeDenied {
+        path: path.to_string_lossy().to_string(),
+      })
+    }
+  }
+
+  // A scope with just these folders approved, for the commands' tests
+  #[cfg(test)]
+  pub fn with_folders(folders: &[impl AsRef<Path>]) -> Self {
+    let mut scope = Self::default();
+    for folder in folders {
+      scope.allow_folder(folder.as_ref()).unwrap();
+    }
+    scope
+  }
+}
+
+pub struct AccessScopeState(pub Mutex<AccessScope>);
+
+impl AccessScopeState {
+  pub fn check(&self, path: &Path) -> CommandResult<()> {
+    self.0.lock().unwrap().check(path)
+  }
+}
+
+fn workspace_key(workspace: Option<&Path>) -> String {
+  workspace
+    .map(|root| root.to_string_lossy().to_string())
+    .unwrap_or_default()
+}
+
+fn load_approved(app: &AppHandle) -> HashMap<String, Vec<String>> {
+  app_store::open_store(app)
+    .ok()
+    .and_then(|store| store.get(APPROVED_ROOTS_KEY))
+    .and_then(|value| serde_json::from_value(value).ok())
+    .unwrap_or_default()
+}
+
+// Remember folders approved while `workspace` is open. Not in safe mode, which leaves the
+// store alone.
+fn persist_approved(app: &AppHandle, workspace: Option<&Path>, roots: &[PathBuf]) {
+  if roots.is_empty() || !safe_mode::startup(app).store {
+    return;
+  }
+  let mut approved = load_approved(app);
+  let entry = approved.entry(workspace_key(workspace)).or_default();
+  for root in roots {
+    let root = root.to_string_lossy().to_string();
+    if !entry.contains(&root) {
+      entry.push(root);
+    }
+  }
+  if let Ok(value) = serde_json::to_value(&approved) {
+    let _ = app_store::write_key(app, "save approved folders", APPROVED_ROOTS_KEY, value);
+  }
+}
+
+// The folders approved while `workspace` was open in an earlier session
+fn restore_approved(app: &AppHandle, scope: &mut AccessScope, workspace: Option<&Path>) {
+  if !safe_mode::startup(app).store {
+    return;
+  }
+  let approved = load_approved(app);
+  for root in approved
+    .get(&workspace_key(workspace))
+    .into_iter()
+    .flatten()
+  {
+    // Folders that were removed since are just skipped
+    let _ = scope.allow_folder(Path::new(root));
+  }
+}
+
+// The scope at startup: what was approved without a workspace, plus the files the app was
+// launched with
+pub fn init(app: &AppHandle, documents: &[&Path]) {
+  let mut scope = AccessScope::default();
+  restore_approved(app, &mut scope, None);
+  app.manage(AccessScopeState(Mutex::new(scope)));
+  allow_documents(app, documents);
+}
+
+// Approve documents the user opened outside the webview (dialogs, the command line, file
+// associations, the Dock and tray menus)
+pub fn allow_documents(app: &AppHandle, documents: &[&Path]) {
+  let Some(state) = app.try_state::<AccessScopeState>() else {
+    return;
+  };
+  let home_dir = app.path().home_dir().ok();
+  let (added, workspace) = {
+    let mut scope = state.0.lock().unwrap();
+    let added: Vec<PathBuf> = documents
+      .iter()
+      .flat_map(|document| scope.allow_document(document, home_dir.as_deref()))
+      .collect();
+    (added, scope.workspace().map(Path::to_path_buf))
+  };
+  persist_approved(app, workspace.as_deref(), &added);
+}
+
+// Files dropped on a window were picked by the user too
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+  if let WindowEvent::DragDrop(DragDropEvent::Drop { paths, .. }) = event {
+    let documents: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+    allow_documents(window.app_handle(), &documents);
+  }
+}
+
+// Switch the scope to a newly opened workspace (None when it was closed), with the folders
+// approved while it was open before
+pub fn open_workspace(app: &AppHandle, root: Option<&Path>) -> CommandResult<()> {
+  let Some(state) = app.try_state::<AccessScopeState>() else {
+    return Ok(());
+  };
+  let mut scope = state.0.lock().unwrap();
+  let Some(root) = root else {
+    scope.set_workspace(None);
+    return Ok(());
+  };
+  let home_dir = app
+    .path()
+    .home_dir()
+    .ok()
+    .and_then(|home| home.canonicalize().ok());
+  let root = root
+    .canonicalize()
+    .map_err(|e| CommandError::from_io(&e, root, "Invalid path"))?;
+  if is_sensitive_root(&root, home_dir.as_deref()) {
+    return Err(CommandError::invalid_path(
+      &root,
+      "Refusing to open as a workspace",
+    ));
+  }
+  restore_approved(app, &mut scope, Some(&root));
+  scope.set_workspace(Some(root));
+  Ok(())
+}
+
+// Ask the user whether the file commands may use `folder`. Blocks until they answer.
+pub fn confirm_access(app: &AppHandle, folder: &Path) -> bool {
+  app
+    .dialog()
+    .message(format!(
+      "Allow Markdowner to open and save files in \"{}\"?",
+      folder.display()
+    ))
+    .title("Allow Access")
+    .kind(MessageDialogKind::Warning)
+    .buttons(MessageDialogButtons::OkCancelCustom(
+      "Allow".to_string(),
+      "Don't Allow".to_string(),
+    ))
+    .blocking_show()
+}
+
+// Ask the user to let the file commands use `path` (a folder, or a document and its folder),
+// e.g. after a recent file came back with a scope_denied error. Returns whether access was
+// given; approvals are remembered for the open workspace.
+#[tauri::command]
+pub async fn request_scope(
+  app: AppHandle,
+  state: tauri::State<'_, AccessScopeState>,
+  path: String,
+) -> CommandResult<bool> {
+  let path = PathBuf::from(&path);
+  if !path.is_absolute() {
+    return Err(CommandError::invalid_path(
+      &path,
+      "File path must be absolute",
+    ));
+  }
+  if state.0.lock().unwrap().is_allowed(&path) {
+    return Ok(true);
+  }
+  let is_folder = path.is_dir();
+  let shown = if is_folder {
+    path.clone()
+  } else {
+    path
+      .parent()
+      .map(Path::to_path_buf)
+      .unwrap_or_else(|| path.clone())
+  };
+  if !confirm_access(&app, &shown) {
+    return Ok(false);
+  }
+  if is_folder {
+    let (root, workspace) = {
+      let mut scope = state.0.lock().unwrap();
+      (
+        scope.allow_folder(&path)?,
+        scope.workspace().map(Path::to_path_buf),
+      )
+    };
+    persist_approved(&app, workspace.as_deref(), &[root]);
+  } else {
+    allow_documents(&app, &[path.as_path()]);
+  }
+  Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  fn notes_folder() -> (TempDir, PathBuf) {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    fs::create_dir_all(notes.join("assets")).unwrap();
+    fs::write(notes.join("a.md"), "# A").unwrap();
+    fs::create_dir_all(dir.path().join("private")).unwrap();
+    fs::write(dir.path().join("private/secret.md"), "secret").unwrap();
+    (dir, notes)
+  }
+
+  #[test]
+  fn test_traversal_out_of_an_approved_folder_is_denied() {
+    let (dir, notes) = notes_folder();
+    let mut scope = AccessScope::default();
+    scope.allow_folder(&notes).unwrap();
+
+    assert!(scope.is_allowed(&notes.join("a.md")));
+    // New files can be created inside, even in folders that don't exist yet
+    assert!(scope.is_allowed(&notes.join("drafts/new.md")));
+    assert!(!scope.is_allowed(&notes.join("../private/secret.md")));
+    assert!(!scope.is_allowed(&notes.join("missing/../../private/secret.md")));
+    assert!(!scope.is_allowed(&notes.join("../../../../../../../../etc/passwd")));
+    assert!(!scope.is_allowed(Path::new("notes/a.md")));
+    let error = scope
+      .check(&dir.path().join("private/secret.md"))
+      .unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_symlinks_cannot_escape_an_approved_folder() {
+    let (dir, notes) = notes_folder();
+    std::os::unix::fs::symlink(dir.path().join("private"), notes.join("link")).unwrap();
+    std::os::unix::fs::symlink(dir.path().join("private/secret.md"), notes.join("b.md")).unwrap();
+    let mut scope = AccessScope::default();
+    scope.allow_folder(&notes).unwrap();
+
+    assert!(!scope.is_allowed(&notes.join("link/secret.md")));
+    assert!(!scope.is_allowed(&notes.join("link/new.md")));
+    assert!(!scope.is_allowed(&notes.join("b.md")));
+
+    // An approved folder reached through a symlink is still the folder itself
+    let mut scope = AccessScope::default();
+    scope.allow_folder(&notes.join("link")).unwrap();
+    assert!(scope.is_allowed(&dir.path().join("private/secret.md")));
+    assert!(!scope.is_allowed(&notes.join("a.md")));
+  }
+
+  #[test]
+  fn test_documents_bring_their_folder_and_workspaces_replace_each_other() {
+    let (dir, notes) = notes_folder();
+    let mut scope = AccessScope::default();
+    let added = scope.allow_document(&notes.join("a.md"), None);
+    assert_eq!(added, vec![notes.canonicalize().unwrap()]);
+    assert!(scope.is_allowed(&notes.join("assets/pic.png")));
+    // Already covered, so nothing new to persist
+    assert!(scope.allow_document(&notes.join("b.md"), None).is_empty());
+
+    // In the home folder only the document and its assets folder are approved
+    let mut scope = AccessScope::default();
+    let home = notes.canonicalize().unwrap();
+    scope.allow_document(&notes.join("a.md"), Some(&home));
+    assert!(scope.is_allowed(&notes.join("a.md")));
+    assert!(scope.is_allowed(&notes.join("assets/pic.png")));
+    assert!(!scope.is_allowed(&notes.join("other.md")));
+
+    let private = dir.path().join("private").canonicalize().unwrap();
+    scope.set_workspace(Some(private.clone()));
+    assert!(scope.is_allowed(&private.join("secret.md")));
+    scope.set_workspace(None);
+    assert!(!scope.is_allowed(&private.join("secret.md")));
+
+    assert!(scope.allow_folder(Path::new("/")).is_err());
+  }
+}
@@ -0,0 +1,347 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::access_scope::{AccessScopeState, APPROVED_ROOTS_KEY};
+use crate::app_store;
+use crate::atomic_write;
+use crate::error::{CommandError, CommandResult};
+use crate::settings::{Settings, SettingsState, SETTINGS_CHANGED_EVENT};
+use crate::RecentFilesState;
+
+// Identifies our export files and the layout version they were written with
+const APP_DATA_FORMAT: &str = "markdowner-app-data";
+const APP_DATA_VERSION: u32 = 1;
+
+// Versioned snapshot of everything the app persists
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppDataExport {
+  pub format: String,
+  pub version: u32,
+  pub exported_at: u64,
+  pub store: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+  // Keep existing data and add/overwrite with the imported entries
+  Merge,
+  // Drop all existing data first
+  Replace,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkippedEntry {
+  pub key: String,
+  pub reason: String,
+}
+
+// What an import did, so the UI can summarize it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportReport {
+  pub imported: Vec<String>,
+  pub skipped: Vec<SkippedEntry>,
+}
+
+fn unix_now() -> u64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or(0)
+}
+
+fn build_export(entries: Vec<(String, Value)>) -> AppDataExport {
+  AppDataExport {
+    format: APP_DATA_FORMAT.to_string(),
+    version: APP_DATA_VERSION,
+    exported_at: unix_now(),
+    store: entries.into_iter().collect(),
+  }
+}
+
+// Parse an export file, rejecting other files and versions we can't read
+fn parse_export(bytes: &[u8]) -> CommandResult<AppDataExport> {
+  let export: AppDataExport = serde_json::from_slice(bytes)
+    .map_err(|e| CommandError::invalid_data(format!("Not a valid app data file: {}", e)))?;
+  if export.format != APP_DATA_FORMAT {
+    return Err(CommandError::invalid_data(format!(
+      "Unrecognized app data format: {}",
+      export.format
+    )));
+  }
+  if export.version == 0 || export.version > APP_DATA_VERSION {
+    return Err(CommandError::invalid_data(format!(
+      "Unsupported app data version {} (this version of Markdowner reads up to {})",
+      export.version, APP_DATA_VERSION
+    )));
+  }
+  Ok(export)
+}
+
+// Compute the store entries to write for an import. Recents are merged as a list (existing
+// entries first); any other key is simply overwritten by the imported value. Approved folders
+// are never imported, and a replace keeps the ones already granted on this machine.
+fn plan_import(
+  existing: &Map<String, Value>,
+  export: AppDataExport,
+  mode: ImportMode,
+) -> (Map<String, Value>, ImportReport) {
+  let mut result = match mode {
+    ImportMode::Merge => existing.clone(),
+    ImportMode::Replace => existing
+      .iter()
+      .filter(|(key, _)| key.as_str() == APPROVED_ROOTS_KEY)
+      .map(|(key, value)| (key.clone(), value.clone()))
+      .collect(),
+  };
+  let mut report = ImportReport {
+    imported: Vec::new(),
+    skipped: Vec::new(),
+  };
+
+  for (key, value) in export.store {
+    if let Err(reason) = app_store::validate_store_value(&key, &value) {
+      report.skipped.push(SkippedEntry { key, reason });
+      continue;
+    }
+
+    let value = match (key.as_str(), result.get(&key)) {
+      (crate::RECENT_FILES_KEY, Some(current)) => merge_recents(current, &value),
+      _ => value,
+    };
+    result.insert(key.clone(), value);
+    report.imported.push(key);
+  }
+
+  report.imported.sort();
+  (result, report)
+}
+
+// Combine two recents lists without duplicates, capped to the usual maximum. Paths that
+// don't exist on this machine are kept; the recents list flags them as missing.
+fn merge_recents(current: &Value, imported: &Value) -> Value {
+  let current: Vec<String> = serde_json::from_value(current.clone()).unwrap_or_default();
+  let imported: Vec<String> = serde_json::from_value(imported.clone()).unwrap_or_default();
+
+  let mut merged = current;
+  for path in imported {
+    if !merged.iter().any(|p| crate::recent_paths_equal(p, &path)) {
+      merged.push(path);
+    }
+  }
+  merged.truncate(crate::MAX_RECENT_FILES);
+  Value::from(merged)
+}
+
+// Export all persisted app data to a JSON file. Prompts for a destination when no path
+// is given, and a given path has to be inside the access scope; returns the written path,
+// or None if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_app_data(
+  app: AppHandle,
+  scope: tauri::State<'_, AccessScopeState>,
+  output_path: Option<String>,
+) -> CommandResult<Option<String>> {
+  let output_path = match output_path {
+    Some(path) => crate::export::requested_output_path(&scope, path)?,
+    None => {
+      let picked = app
+        .dialog()
+        .file()
+        .add_filter("JSON", &["json"])
+        .set_file_name("markdowner-data.json")
+        .blocking_save_file();
+      match picked.map(crate::dialog_result_to_path).transpose()? {
+        Some(path) => path,
+        None => return Ok(None),
+      }
+    }
+  };
+
+  if !output_path.is_absolute() {
+    return Err(CommandError::invalid_path(
+      &output_path,
+      "File path must be absolute",
+    ));
+  }
+
+  let store = app_store::open_store(&app)?;
+  let export = build_export(store.entries());
+  let bytes = serde_json::to_vec_pretty(&export)
+    .map_err(|e| CommandError::io("Failed to serialize app data", e))?;
+  atomic_write::write_atomically(&output_path, &bytes)
+    .map_err(|e| CommandError::from_io(&e, &output_path, "Failed to write app data"))?;
+
+  Ok(Some(output_path.to_string_lossy().to_string()))
+}
+
+// Import app data from a file written by export_app_data. Prompts for the file when no
+// path is given, and a given path has to be inside the access scope; returns None if the
+// dialog was cancelled.
+#[tauri::command]
+pub async fn import_app_data(
+  app: AppHandle,
+  state: tauri::State<'_, RecentFilesState>,
+  settings: tauri::State<'_, SettingsState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  path: Option<String>,
+  mode: ImportMode,
+) -> CommandResult<Option<ImportReport>> {
+  let path = match path {
+    Some(path) => {
+      let path = PathBuf::from(path);
+      scope.check(&path)?;
+      path
+    }
+    None => {
+      let picked = app
+        .dialog()
+        .file()
+        .add_filter("JSON", &["json"])
+        .blocking_pick_file();
+      match picked.map(crate::dialog_result_to_path).transpose()? {
+        Some(path) => path,
+        None => return Ok(None),
+      }
+    }
+  };
+
+  let bytes = std::fs::read(&path)
+    .map_err(|e| CommandError::from_io(&e, &path, "Failed to read app data"))?;
+  let export = parse_export(&bytes)?;
+
+  let store = app_store::open_store(&app)?;
+  let existing: Map<String, Value> = store.entries().into_iter().collect();
+  let (entries, report) = plan_import(&existing, export, mode);
+
+  if mode == ImportMode::Replace {
+    store.clear();
+  }
+  for (key, value) in entries {
+    store.set(key, value);
+  }
+  app_store::save_store(&app, &store)?;
+
+  // Refresh the in-memory recents and settings from what was just written
+  let recents = store
+    .get(crate::RECENT_FILES_KEY)
+    .and_then(|v| serde_json::from_value::<Vec<String>>(v).ok())
+    .unwrap_or_default();
+  *state.0.lock().unwrap() = recents;
+  let imported_settings: Settings = store
+    .get(crate::settings::SETTINGS_KEY)
+    .and_then(|v| serde_json::from_value(v).ok())
+    .unwrap_or_default();
+  *settings.0.lock().unwrap() = imported_settings.clone();
+  crate::menu_toggles::sync(&app, &imported_settings);
+  let _ = app.emit(SETTINGS_CHANGED_EVENT, imported_settings);
+
+  Ok(Some(report))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  fn export_with(store: Value) -> AppDataExport {
+    AppDataExport {
+      format: APP_DATA_FORMAT.to_string(),
+      version: APP_DATA_VERSION,
+      exported_at: 0,
+      store: store.as_object().unwrap().clone(),
+    }
+  }
+
+  #[test]
+  fn test_export_round_trip() {
+    let export = build_export(vec![("recent_files".to_string(), json!(["/notes/a.md"]))]);
+    let bytes = serde_json::to_vec(&export).unwrap();
+    assert_eq!(parse_export(&bytes).unwrap(), export);
+  }
+
+  #[test]
+  fn test_parse_export_rejects_other_files() {
+    assert_eq!(
+      parse_export(b"not json").unwrap_err().code(),
+      "invalid_data"
+    );
+    assert!(
+      parse_export(br#"{"format": "other", "version": 1, "exported_at": 0, "store": {}}"#)
+        .unwrap_err()
+        .to_string()
+        .contains("format")
+    );
+    let future = format!(
+      r#"{{"format": "{}", "version": 99, "exported_at": 0, "store": {{}}}}"#,
+      APP_DATA_FORMAT
+    );
+    assert!(parse_export(future.as_bytes())
+      .unwrap_err()
+      .to_string()
+      .contains("version"));
+  }
+
+  #[test]
+  fn test_import_skips_invalid_and_unknown_entries() {
+    let export = export_with(json!({
+      "recent_files": "not a list",
+      "mystery_key": 42,
+    }));
+
+    let (entries, report) = plan_import(&Map::new(), export, ImportMode::Merge);
+    assert!(entries.is_empty());
+    assert!(report.imported.is_empty());
+    let skipped: Vec<&str> = report.skipped.iter().map(|s| s.key.as_str()).collect();
+    assert!(skipped.contains(&"recent_files"));
+    assert!(skipped.contains(&"mystery_key"));
+  }
+
+  #[test]
+  fn test_import_never_grants_approved_folders() {
+    let existing = json!({"approved_roots": {"/home/me/notes": ["/home/me/notes"]}});
+    let export = export_with(json!({
+      "approved_roots": {"/": ["/"]},
+      "recent_files": ["/notes/a.md"],
+    }));
+
+    let (entries, report) = plan_import(&Map::new(), export.clone(), ImportMode::Merge);
+    assert_eq!(report.imported, vec!["recent_files"]);
+    assert_eq!(report.skipped.len(), 1);
+    assert_eq!(report.skipped[0].key, "approved_roots");
+    assert!(!entries.contains_key("approved_roots"));
+
+    // Replacing everything else keeps what this machine had approved
+    let (entries, _) = plan_import(existing.as_object().unwrap(), export, ImportMode::Replace);
+    assert_eq!(entries["approved_roots"], existing["approved_roots"]);
+  }
+
+  #[test]
+  fn test_merge_combines_recents() {
+    let existing = json!({"recent_files": ["/local/a.md", "/shared/b.md"]});
+    let export = export_with(json!({"recent_files": ["/shared/b.md", "/elsewhere/c.md"]}));
+
+    let (entries, report) = plan_import(existing.as_object().unwrap(), export, ImportMode::Merge);
+    assert_eq!(report.imported, vec!["recent_files"]);
+    assert_eq!(
+      entries["recent_files"],
+      json!(["/local/a.md", "/shared/b.md", "/elsewhere/c.md"])
+    );
+  }
+
+  #[test]
+  fn test_replace_drops_existing_entries() {
+    let existing = json!({"recent_files": ["/local/a.md"]});
+    let export = export_with(json!({"recent_files": ["/missing/on/this/machine.md"]}));
+
+    let (entries, _) = plan_import(existing.as_object().unwrap(), export, ImportMode::Replace);
+    assert_eq!(
+      entries["recent_files"],
+      json!(["/missing/on/this/machine.md"])
+    );
+  }
+}
@@ -0,0 +1,675 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+use tauri_plugin_store::{Store, StoreExt};
+
+use crate::atomic_write::write_atomically;
+use crate::error::{CommandError, CommandResult};
+
+// Persistent store file (relative to the app data dir); go through store_path for it.
+// Plain pretty-printed JSON, so users can read and fix it.
+const STORE_FILE: &str = "settings.json";
+
+// What the store file was called before; migrate_legacy_store moves it to STORE_FILE
+const LEGACY_STORE_FILE: &str = "app_data.bin";
+
+// Keys we know how to use; when the store is corrupted we look for these explicitly
+const KNOWN_STORE_KEYS: &[&str] = &[
+  crate::RECENT_FILES_KEY,
+  crate::LAST_SAVE_DIRECTORY_KEY,
+  crate::settings::SETTINGS_KEY,
+  crate::view_state::VIEW_STATES_KEY,
+  crate::frequent::FREQUENT_FILES_KEY,
+  crate::session::TAB_SESSION_KEY,
+  crate::bookmarks::BOOKMARKS_KEY,
+  crate::access_scope::APPROVED_ROOTS_KEY,
+  crate::recent_workspaces::RECENT_WORKSPACES_KEY,
+];
+
+// Check that a value has the shape the app expects for a store key (used when importing)
+pub fn validate_store_value(key: &str, value: &Value) -> Result<(), String> {
+  match key {
+    crate::RECENT_FILES_KEY => serde_json::from_value::<Vec<String>>(value.clone())
+      .map(|_| ())
+      .map_err(|_| "expected a list of file paths".to_string()),
+    crate::LAST_SAVE_DIRECTORY_KEY => value
+      .as_str()
+      .map(|_| ())
+      .ok_or_else(|| "expected a directory path".to_string()),
+    crate::settings::SETTINGS_KEY => {
+      serde_json::from_value::<crate::settings::Settings>(value.clone())
+        .map(|_| ())
+        .map_err(|e| format!("invalid settings: {}", e))
+    }
+    crate::view_state::VIEW_STATES_KEY => {
+      serde_json::from_value::<Vec<crate::view_state::ViewStateEntry>>(value.clone())
+        .map(|_| ())
+        .map_err(|e| format!("invalid view states: {}", e))
+    }
+    crate::frequent::FREQUENT_FILES_KEY => {
+      serde_json::from_value::<Vec<crate::frequent::FrequencyEntry>>(value.clone())
+        .map(|_| ())
+        .map_err(|e| format!("invalid open counts: {}", e))
+    }
+    crate::session::TAB_SESSION_KEY => {
+      serde_json::from_value::<crate::session::TabSession>(value.clone())
+        .map(|_| ())
+        .map_err(|e| format!("invalid tab session: {}", e))
+    }
+    crate::bookmarks::BOOKMARKS_KEY => {
+      serde_json::from_value::<Vec<crate::bookmarks::BookmarkEntry>>(value.clone())
+        .map(|_| ())
+        .map_err(|e| format!("invalid bookmarks: {}", e))
+    }
+    // Folder approvals only come from the user picking folders on this machine
+    crate::access_scope::APPROVED_ROOTS_KEY => {
+      Err("approved folders can't be imported".to_string())
+    }
+    crate::recent_workspaces::RECENT_WORKSPACES_KEY => {
+      serde_json::from_value::<Vec<String>>(value.clone())
+        .map(|_| ())
+        .map_err(|_| "expected a list of folder paths".to_string())
+    }
+    _ => Err("unknown key".to_string()),
+  }
+}
+
+// Event emitted after a corrupted store was replaced with a fresh one
+pub const STORE_RECOVERED_EVENT: &str = "store-recovered";
+
+// Details about a store recovery, shown once by the frontend
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StoreRecovery {
+  pub backup_path: String,
+  pub salvaged_keys: Vec<String>,
+}
+
+// Recovery that happened during startup, kept until the frontend asks for it
+// (the store-recovered event fires before the webview is listening)
+pub struct StoreRecoveryState(pub Mutex<Option<StoreRecovery>>);
+
+// Event emitted when the store can't be saved (a PersistenceError), e.g. the disk is full
+pub const PERSISTENCE_ERROR_EVENT: &str = "persistence-error";
+
+// Pause before retrying a store write that failed in a way that may pass
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+
+// A store write that failed, even after a retry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PersistenceError {
+  pub operation: String,
+  pub key: String,
+  // CommandError code, e.g. `permission_denied`
+  pub code: String,
+  pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PersistenceStatus {
+  // Keys changed in memory that the store file doesn't have yet
+  pub unflushed_keys: Vec<String>,
+  // The most recent failure, until a save succeeds
+  pub last_error: Option<PersistenceError>,
+}
+
+impl PersistenceStatus {
+  fn failed(&mut self, failure: PersistenceError) {
+    if !self.unflushed_keys.contains(&failure.key) {
+      self.unflushed_keys.push(failure.key.clone());
+    }
+    self.last_error = Some(failure);
+  }
+
+  fn flushed(&mut self) {
+    *self = PersistenceStatus::default();
+  }
+}
+
+#[derive(Default)]
+pub struct PersistenceState(Mutex<PersistenceStatus>);
+
+// Where the store file is
+pub fn store_path(app: &AppHandle) -> CommandResult<PathBuf> {
+  tauri_plugin_store::resolve_store_path(app, STORE_FILE)
+    .map_err(|e| CommandError::io("Failed to resolve store path", e))
+}
+
+// Open the app store. Auto-save is disabled because every write goes through
+// `save_store`, which replaces the file atomically.
+pub fn open_store(app: &AppHandle) -> CommandResult<Arc<Store<Wry>>> {
+  app
+    .store_builder(store_path(app)?)
+    .disable_auto_save()
+    .build()
+    .map_err(|e| CommandError::io("Failed to open store", e))
+}
+
+// Errors a second attempt may not hit: the write was cut short, or a virus scanner or
+// indexer had the file open (which Windows reports as access denied)
+fn is_transient(error: &io::Error) -> bool {
+  matches!(
+    error.kind(),
+    io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+  ) || (cfg!(windows) && error.kind() == io::ErrorKind::PermissionDenied)
+}
+
+// Persist the store by writing a temp file and renaming it over the old one, so a crash
+// mid-write leaves either the old or the new contents on disk, never a truncated file.
+// Every entry is written, so this also saves whatever earlier failed writes left unsaved.
+pub fn save_store(app: &AppHandle, store: &Store<Wry>) -> CommandResult<()> {
+  let path = store_path(app)?;
+  let entries: HashMap<String, Value> = store.entries().into_iter().collect();
+  let bytes = serde_json::to_vec_pretty(&entries)
+    .map_err(|e| CommandError::io("Failed to serialize store", e))?;
+  let mut result = write_atomically(&path, &bytes);
+  if result.as_ref().is_err_and(is_transient) {
+    std::thread::sleep(RETRY_DELAY);
+    result = write_atomically(&path, &bytes);
+  }
+  result.map_err(|e| CommandError::from_io(&e, &path, "Failed to save store"))?;
+  if let Some(state) = app.try_state::<PersistenceState>() {
+    state.0.lock().unwrap().flushed();
+  }
+  Ok(())
+}
+
+// Set `key` and save the store. `operation` says what was being saved (`save settings`) for
+// PERSISTENCE_ERROR_EVENT, which is sent if that fails. The value stays in the in-memory
+// store either way, so the next save (or the one at exit) tries again.
+pub fn write_key(app: &AppHandle, operation: &str, key: &str, value: Value) -> CommandResult<()> {
+  open_store(app)
+    .and_then(|store| {
+      store.set(key, value);
+      save_store(app, &store)
+    })
+    .inspect_err(|e| report_failure(app, operation, key, e))
+}
+
+// Remove `key` and save the store, if it was there; failures are reported as for write_key
+pub fn delete_key(app: &AppHandle, operation: &str, key: &str) -> CommandResult<()> {
+  open_store(app)
+    .and_then(|store| match store.delete(key) {
+      true => save_store(app, &store),
+      false => Ok(()),
+    })
+    .inspect_err(|e| report_failure(app, operation, key, e))
+}
+
+// The list stored under `key`, or an empty one if there's none or it doesn't parse
+pub fn read_list<T: DeserializeOwned>(app: &AppHandle, key: &str) -> CommandResult<Vec<T>> {
+  let store = open_store(app)?;
+  Ok(
+    store
+      .get(key)
+      .and_then(|value| serde_json::from_value(value).ok())
+      .unwrap_or_default(),
+  )
+}
+
+// Store `entries` under `key` as write_key does. `what` names them in errors (`bookmarks`).
+pub fn write_list<T: Serialize>(
+  app: &AppHandle,
+  key: &str,
+  what: &str,
+  entries: &[T],
+) -> CommandResult<()> {
+  let value = serde_json::to_value(entries)
+    .map_err(|e| CommandError::io(&format!("Failed to serialize {}", what), e))?;
+  write_key(app, &format!("save {}", what), key, value)
+}
+
+fn report_failure(app: &AppHandle, operation: &str, key: &str, error: &CommandError) {
+  log::error!("Failed to {}: {}", operation, error);
+  let failure = PersistenceError {
+    operation: operation.to_string(),
+    key: key.to_string(),
+    code: error.code().to_string(),
+    message: error.to_string(),
+  };
+  if let Some(state) = app.try_state::<PersistenceState>() {
+    state.0.lock().unwrap().failed(failure.clone());
+  }
+  let _ = app.emit(PERSISTENCE_ERROR_EVENT, failure);
+}
+
+// Save the store at exit if a write failed since it was last saved
+pub fn flush_unsaved(app: &AppHandle) {
+  let Some(state) = app.try_state::<PersistenceState>() else {
+    return;
+  };
+  if state.0.lock().unwrap().unflushed_keys.is_empty() {
+    return;
+  }
+  match open_store(app).and_then(|store| save_store(app, &store)) {
+    Ok(()) => log::info!("Saved the store changes that failed to save earlier"),
+    Err(e) => log::error!("Failed to save the store at exit: {}", e),
+  }
+}
+
+#[tauri::command]
+pub async fn persistence_status(
+  state: tauri::State<'_, PersistenceState>,
+) -> CommandResult<PersistenceStatus> {
+  Ok(state.0.lock().unwrap().clone())
+}
+
+// Check the store file before the plugin loads it. The plugin silently starts empty on a
+// parse error and would overwrite the corrupt file on the next save, so instead we move the
+// file aside, salvage what we can into a fresh store file, and report what happened.
+pub fn recover_store_file(path: &Path) -> Option<StoreRecovery> {
+  let bytes = std::fs::read(path).ok()?;
+  if serde_json::from_slice::<HashMap<String, Value>>(&bytes).is_ok() {
+    return None;
+  }
+
+  let salvaged = salvage_store_entries(&bytes);
+  let backup_path = backup_path(path, "corrupt");
+  if let Err(e) = std::fs::rename(path, &backup_path) {
+    log::error!("Failed to move corrupted store aside: {}", e);
+    return None;
+  }
+
+  if !salvaged.is_empty() {
+    match serde_json::to_vec_pretty(&salvaged) {
+      Ok(bytes) => {
+        if let Err(e) = write_atomically(path, &bytes) {
+          log::error!("Failed to write salvaged store: {}", e);
+        }
+      }
+      Err(e) => log::error!("Failed to serialize salvaged store: {}", e),
+    }
+  }
+
+  let mut salvaged_keys: Vec<String> = salvaged.into_keys().collect();
+  salvaged_keys.sort();
+  Some(StoreRecovery {
+    backup_path: backup_path.to_string_lossy().to_string(),
+    salvaged_keys,
+  })
+}
+
+// `settings.json` -> `settings.json.<label>-<unix seconds>`
+fn backup_path(path: &Path, label: &str) -> PathBuf {
+  let timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|d| d.as_secs())
+    .unwrap_or(0);
+  let mut name = path.file_name().unwrap_or_default().to_os_string();
+  name.push(format!(".{}-{}", label, timestamp));
+  path.with_file_name(name)
+}
+
+// Copy the store file to `settings.json.<label>-<unix seconds>` before part of it is reset,
+// so nothing is lost for good. Returns the copy's path, or None if there's no store file yet.
+pub fn archive_store_file(app: &AppHandle, label: &str) -> CommandResult<Option<PathBuf>> {
+  let path = store_path(app)?;
+  if !path.exists() {
+    return Ok(None);
+  }
+  let archive = backup_path(&path, label);
+  std::fs::copy(&path, &archive)
+    .map_err(|e| CommandError::from_io(&e, &archive, "Failed to archive store"))?;
+  Ok(Some(archive))
+}
+
+// What migrate_legacy_store found
+#[derive(Debug, Clone, PartialEq)]
+enum StoreMigration {
+  // No legacy store file
+  NotNeeded,
+  // The legacy store's entries were copied to the new file
+  Migrated { keys: usize },
+  // Both files were there (a migration stopped before the rename): the new one is kept
+  KeptExisting,
+}
+
+// `app_data.bin` -> `app_data.bin.migrated`
+fn migrated_path(legacy: &Path) -> PathBuf {
+  let mut name = legacy.file_name().unwrap_or_default().to_os_string();
+  name.push(".migrated");
+  legacy.with_file_name(name)
+}
+
+// Move the legacy store in `dir` to the new store file: copy its entries over, read the
+// copy back to check it, then rename the legacy file to `app_data.bin.migrated`. A legacy
+// file that doesn't parse is copied as it is, for recover_store_file to salvage.
+fn migrate_store_in(dir: &Path) -> CommandResult<StoreMigration> {
+  let legacy = dir.join(LEGACY_STORE_FILE);
+  let path = dir.join(STORE_FILE);
+  let bytes = match std::fs::read(&legacy) {
+    Ok(bytes) => bytes,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(StoreMigration::NotNeeded),
+    Err(e) => {
+      return Err(CommandError::from_io(
+        &e,
+        &legacy,
+        "Failed to read old store",
+      ))
+    }
+  };
+  let retire = || {
+    std::fs::rename(&legacy, migrated_path(&legacy))
+      .map_err(|e| CommandError::from_io(&e, &legacy, "Failed to rename old store"))
+  };
+  if path.exists() {
+    retire()?;
+    return Ok(StoreMigration::KeptExisting);
+  }
+
+  let entries = serde_json::from_slice::<HashMap<String, Value>>(&bytes).ok();
+  let copy = match &entries {
+    Some(entries) => serde_json::to_vec_pretty(entries)
+      .map_err(|e| CommandError::io("Failed to serialize store", e))?,
+    None => bytes,
+  };
+  write_atomically(&path, &copy)
+    .map_err(|e| CommandError::from_io(&e, &path, "Failed to write store"))?;
+  let written =
+    std::fs::read(&path).map_err(|e| CommandError::from_io(&e, &path, "Failed to read store"))?;
+  let verified = match &entries {
+    Some(entries) => {
+      serde_json::from_slice::<HashMap<String, Value>>(&written)
+        .ok()
+        .as_ref()
+        == Some(entries)
+    }
+    None => written == copy,
+  };
+  if !verified {
+    // The old file stays, so the next start tries again
+    let _ = std::fs::remove_file(&path);
+    return Err(CommandError::invalid_data(format!(
+      "The copy of the store at {} doesn't match the original",
+      path.display()
+    )));
+  }
+  retire()?;
+  Ok(StoreMigration::Migrated {
+    keys: entries.map_or(0, |entries| entries.len()),
+  })
+}
+
+// Move the store from the binary-named `app_data.bin` of older versions to settings.json,
+// once. Runs in setup before anything opens the store.
+pub fn migrate_legacy_store(app: &AppHandle) {
+  let dir = match store_path(app) {
+    Ok(path) => path.parent().map(Path::to_path_buf).unwrap_or(path),
+    Err(e) => {
+      log::error!("Failed to migrate the store: {}", e);
+      return;
+    }
+  };
+  match migrate_store_in(&dir) {
+    Ok(StoreMigration::NotNeeded) => {}
+    Ok(StoreMigration::Migrated { keys }) => {
+      log::info!(
+        "Migrated {} store keys from {} to {}",
+        keys,
+        LEGACY_STORE_FILE,
+        STORE_FILE
+      )
+    }
+    Ok(StoreMigration::KeptExisting) => log::warn!(
+      "Found both {} and {}; kept {} and set the old file aside",
+      LEGACY_STORE_FILE,
+      STORE_FILE,
+      STORE_FILE
+    ),
+    Err(e) => log::error!("Failed to migrate the store: {}", e),
+  }
+}
+
+// Recover whatever entries are still readable from a damaged store file: first every
+// complete top-level entry before the damage (handles truncation), then any known key
+// that appears later in the file.
+fn salvage_store_entries(bytes: &[u8]) -> HashMap<String, Value> {
+  let text = String::from_utf8_lossy(bytes);
+  let mut salvaged = salvage_leading_entries(&text);
+
+  for key in KNOWN_STORE_KEYS {
+    if salvaged.contains_key(*key) {
+      continue;
+    }
+    let needle = format!("\"{}\"", key);
+    for (index, _) in text.match_indices(&needle) {
+      let rest = text[index + needle.len()..].trim_start();
+      let Some(rest) = rest.strip_prefix(':') else {
+        continue;
+      };
+      if let Some(Ok(value)) = serde_json::Deserializer::from_str(rest)
+        .into_iter::<Value>()
+        .next()
+      {
+        salvaged.insert(key.to_string(), value);
+        break;
+      }
+    }
+  }
+
+  salvaged
+}
+
+// Parse `{"key": value, ...` entry by entry, stopping at the first one that doesn't parse
+fn salvage_leading_entries(text: &str) -> HashMap<String, Value> {
+  let mut entries = HashMap::new();
+  let Some(mut rest) = text.trim_start().strip_prefix('{') else {
+    return entries;
+  };
+
+  loop {
+    let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<String>();
+    let Some(Ok(key)) = stream.next() else {
+      break;
+    };
+    rest = rest[stream.byte_offset()..].trim_start();
+    let Some(after_colon) = rest.strip_prefix(':') else {
+      break;
+    };
+    rest = after_colon;
+
+    let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+    let Some(Ok(value)) = stream.next() else {
+      break;
+    };
+    rest = rest[stream.byte_offset()..].trim_start();
+    entries.insert(key, value);
+
+    match rest.strip_prefix(',') {
+      Some(after_comma) => rest = after_comma,
+      None => break,
+    }
+  }
+
+  entries
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+  use std::fs;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_persistence_status_tracks_failed_keys() {
+    let failure = |key: &str| PersistenceError {
+      operation: "save settings".to_string(),
+      key: key.to_string(),
+      code: "io".to_string(),
+      message: "No space left on device".to_string(),
+    };
+    let mut status = PersistenceStatus::default();
+    status.failed(failure("settings"));
+    status.failed(failure("recent_files"));
+    status.failed(failure("settings"));
+    assert_eq!(status.unflushed_keys, vec!["settings", "recent_files"]);
+    assert_eq!(status.last_error, Some(failure("settings")));
+    status.flushed();
+    assert_eq!(status, PersistenceStatus::default());
+
+    let interrupted = io::Error::from(io::ErrorKind::Interrupted);
+    assert!(is_transient(&interrupted));
+    assert!(!is_transient(&io::Error::from(io::ErrorKind::StorageFull)));
+  }
+
+  fn backups_in(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+      .unwrap()
+      .map(|e| e.unwrap().path())
+      .filter(|p| p.to_string_lossy().contains(".corrupt-"))
+      .collect()
+  }
+
+  #[test]
+  fn test_valid_store_is_left_alone() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join(STORE_FILE);
+    fs::write(&path, r#"{"recent_files": ["/a.md"]}"#).unwrap();
+
+    assert!(recover_store_file(&path).is_none());
+    assert!(backups_in(dir.path()).is_empty());
+  }
+
+  #[test]
+  fn test_missing_store_needs_no_recovery() {
+    let dir = TempDir::new().unwrap();
+    assert!(recover_store_file(&dir.path().join(STORE_FILE)).is_none());
+  }
+
+  #[test]
+  fn test_garbage_bytes_are_moved_aside() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join(STORE_FILE);
+    fs::write(&path, [0u8, 159, 146, 150, 0xff, 0x00, 0x13]).unwrap();
+
+    let recovery = recover_store_file(&path).unwrap();
+    assert!(recovery.salvaged_keys.is_empty());
+    assert!(!path.exists());
+    let backups = backups_in(dir.path());
+    assert_eq!(backups.len(), 1);
+    assert_eq!(recovery.backup_path, backups[0].to_string_lossy());
+  }
+
+  #[test]
+  fn test_truncated_store_salvages_complete_entries() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join(STORE_FILE);
+    fs::write(
+      &path,
+      r#"{"recent_files": ["/notes/a.md", "/notes/b.md"], "theme": "dark", "other": {"nested": [1, 2"#,
+    )
+    .unwrap();
+
+    let recovery = recover_store_file(&path).unwrap();
+    assert_eq!(recovery.salvaged_keys, vec!["recent_files", "theme"]);
+
+    let restored: HashMap<String, Value> =
+      serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+    assert_eq!(
+      restored["recent_files"],
+      json!(["/notes/a.md", "/notes/b.md"])
+    );
+    assert_eq!(restored["theme"], json!("dark"));
+  }
+
+  #[test]
+  fn test_known_key_recovered_after_damage() {
+    let text = r#"{"broken": [1, 2 ### garbage ###, "recent_files": ["/x.md"]}"#;
+    let salvaged = salvage_store_entries(text.as_bytes());
+    assert_eq!(salvaged.len(), 1);
+    assert_eq!(salvaged["recent_files"], json!(["/x.md"]));
+  }
+
+  #[test]
+  fn test_legacy_store_is_migrated() {
+    let dir = TempDir::new().unwrap();
+    let legacy = dir.path().join(LEGACY_STORE_FILE);
+    fs::write(
+      &legacy,
+      r#"{"recent_files":["/a.md"],"settings":{"theme":"dark"}}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+      migrate_store_in(dir.path()).unwrap(),
+      StoreMigration::Migrated { keys: 2 }
+    );
+    let text = fs::read_to_string(dir.path().join(STORE_FILE)).unwrap();
+    assert!(
+      text.contains("\n  \"recent_files\""),
+      "not pretty: {}",
+      text
+    );
+    let migrated: HashMap<String, Value> = serde_json::from_str(&text).unwrap();
+    assert_eq!(migrated["recent_files"], json!(["/a.md"]));
+    assert_eq!(migrated["settings"], json!({"theme": "dark"}));
+    assert!(!legacy.exists());
+    assert!(dir.path().join("app_data.bin.migrated").exists());
+
+    // Only once
+    assert_eq!(
+      migrate_store_in(dir.path()).unwrap(),
+      StoreMigration::NotNeeded
+    );
+  }
+
+  #[test]
+  fn test_existing_store_wins_over_legacy() {
+    let dir = TempDir::new().unwrap();
+    fs::write(
+      dir.path().join(LEGACY_STORE_FILE),
+      r#"{"recent_files":["/old.md"]}"#,
+    )
+    .unwrap();
+    fs::write(
+      dir.path().join(STORE_FILE),
+      r#"{"recent_files":["/new.md"]}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+      migrate_store_in(dir.path()).unwrap(),
+      StoreMigration::KeptExisting
+    );
+    assert_eq!(
+      fs::read_to_string(dir.path().join(STORE_FILE)).unwrap(),
+      r#"{"recent_files":["/new.md"]}"#
+    );
+    assert_eq!(
+      fs::read_to_string(dir.path().join("app_data.bin.migrated")).unwrap(),
+      r#"{"recent_files":["/old.md"]}"#
+    );
+  }
+
+  #[test]
+  fn test_nothing_to_migrate() {
+    let dir = TempDir::new().unwrap();
+    assert_eq!(
+      migrate_store_in(dir.path()).unwrap(),
+      StoreMigration::NotNeeded
+    );
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+  }
+
+  #[test]
+  fn test_unreadable_legacy_store_is_copied_for_recovery() {
+    let dir = TempDir::new().unwrap();
+    let damaged = r#"{"recent_files": ["/a.md"], "other": [1"#;
+    fs::write(dir.path().join(LEGACY_STORE_FILE), damaged).unwrap();
+
+    assert_eq!(
+      migrate_store_in(dir.path()).unwrap(),
+      StoreMigration::Migrated { keys: 0 }
+    );
+    let path = dir.path().join(STORE_FILE);
+    assert_eq!(fs::read_to_string(&path).unwrap(), damaged);
+    let recovery = recover_store_file(&path).unwrap();
+    assert_eq!(recovery.salvaged_keys, vec!["recent_files"]);
+  }
+}
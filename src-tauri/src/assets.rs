@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::http::{header, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager, UriSchemeContext, Wry};
+use urlencoding::{decode, encode};
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::error::{CommandError, CommandResult};
+
+// Custom URI scheme serving local files referenced by open documents.
+// Tauri's built-in asset protocol scope can only grow at runtime, so we keep our own scope
+// that can be revoked when a document is closed.
+pub const ASSET_SCHEME: &str = "mdasset";
+
+// Directories the preview may load assets from, per open document
+#[derive(Debug, Default)]
+pub struct AssetScope {
+  documents: HashMap<PathBuf, Vec<PathBuf>>,
+}
+
+impl AssetScope {
+  pub fn allow(&mut self, document: PathBuf, roots: Vec<PathBuf>) {
+    self.documents.insert(document, roots);
+  }
+
+  pub fn revoke(&mut self, document: &Path) -> bool {
+    self.documents.remove(document).is_some()
+  }
+
+  // `path` must already be canonicalized so `..` and symlinks can't escape a root
+  pub fn is_allowed(&self, path: &Path) -> bool {
+    self
+      .documents
+      .values()
+      .flatten()
+      .any(|root| path.starts_with(root))
+  }
+}
+
+pub struct AssetScopeState(pub Mutex<AssetScope>);
+
+// Scoping these would expose far more than a document's assets
+pub(crate) fn is_sensitive_root(dir: &Path, home_dir: Option<&Path>) -> bool {
+  dir.parent().is_none() || home_dir.is_some_and(|home| dir == home)
+}
+
+// Work out which directories to scope for a document: its folder (recursively) and, when the
+// folder itself is too broad to expose (e.g. a note saved directly in the home directory),
+// just its `assets` subfolder
+pub(crate) fn document_asset_roots(
+  document: &Path,
+  home_dir: Option<&Path>,
+) -> CommandResult<Vec<PathBuf>> {
+  if !document.is_absolute() {
+    return Err(CommandError::invalid_path(
+      document,
+      "File path must be absolute",
+    ));
+  }
+  let parent = document
+    .parent()
+    .ok_or_else(|| CommandError::invalid_path(document, "Document has no parent directory"))?;
+  let parent = parent
+    .canonicalize()
+    .map_err(|e| CommandError::from_io(&e, parent, "Invalid path"))?;
+  let home_dir = home_dir.and_then(|home| home.canonicalize().ok());
+
+  if !is_sensitive_root(&parent, home_dir.as_deref()) {
+    return Ok(vec![parent]);
+  }
+
+  match parent.join("assets").canonicalize() {
+    Ok(assets) if assets.is_dir() && assets.starts_with(&parent) => Ok(vec![assets]),
+    _ => Err(CommandError::invalid_path(
+      &parent,
+      "Refusing to allow asset access to",
+    )),
+  }
+}
+
+// Build the URL the webview uses to load a local file through our scheme. This mirrors
+// Tauri's convertFileSrc: the whole path is percent-encoded as a single segment.
+fn asset_url_for(path: &Path) -> String {
+  let encoded = encode(&path.to_string_lossy()).into_owned();
+  if cfg!(windows) {
+    format!("http://{}.localhost/{}", ASSET_SCHEME, encoded)
+  } else {
+    format!("{}://localhost/{}", ASSET_SCHEME, encoded)
+  }
+}
+
+// Resolve a reference from a document (as written in markdown) to an absolute path
+pub(crate) fn resolve_reference(document: &Path, reference: &str) -> Option<PathBuf> {
+  // Query strings and fragments aren't part of the file name
+  let reference = reference.split(['?', '#']).next()?.trim();
+  if reference.is_empty() || reference.contains("://") || reference.starts_with("data:") {
+    return None;
+  }
+  // Markdown links are often percent-encoded (`my%20image.png`)
+  let reference = decode(reference)
+    .map(|r| r.into_owned())
+    .unwrap_or_else(|_| reference.to_string());
+
+  let reference = Path::new(&reference);
+  let joined = if reference.is_absolute() {
+    reference.to_path_buf()
+  } else {
+    document.parent()?.join(reference)
+  };
+  Some(crate::lexical_normalize(&joined))
+}
+
+pub(crate) fn mime_type_for(path: &Path) -> &'static str {
+  let extension = path
+    .extension()
+    .map(|e| e.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+  match extension.as_str() {
+    "png" => "image/png",
+    "jpg" | "jpeg" => "image/jpeg",
+    "gif" => "image/gif",
+    "svg" => "image/svg+xml",
+    "webp" => "image/webp",
+    "avif" => "image/avif",
+    "bmp" => "image/bmp",
+    "ico" => "image/x-icon",
+    "mp4" => "video/mp4",
+    "webm" => "video/webm",
+    "mp3" => "audio/mpeg",
+    "wav" => "audio/wav",
+    "pdf" => "application/pdf",
+    _ => "application/octet-stream",
+  }
+}
+
+fn asset_response(status: StatusCode, mime: &str, body: Vec<u8>) -> Response<Vec<u8>> {
+  Response::builder()
+    .status(status)
+    .header(header::CONTENT_TYPE, mime)
+    .body(body)
+    .unwrap_or_default()
+}
+
+// Serve a file for the mdasset:// scheme if it lies inside the scope of an open document
+pub fn handle_asset_request(
+  ctx: UriSchemeContext<'_, Wry>,
+  request: Request<Vec<u8>>,
+) -> Response<Vec<u8>> {
+  let encoded = request.uri().path().trim_start_matches('/');
+  let Ok(decoded) = decode(encoded) else {
+    return asset_response(StatusCode::BAD_REQUEST, "text/plain", Vec::new());
+  };
+  let Ok(path) = PathBuf::from(decoded.as_ref()).canonicalize() else {
+    return asset_response(StatusCode::NOT_FOUND, "text/plain", Vec::new());
+  };
+
+  let allowed = ctx
+    .app_handle()
+    .try_state::<AssetScopeState>()
+    .is_some_and(|scope| scope.0.lock().unwrap().is_allowed(&path));
+  if !allowed {
+    return asset_response(StatusCode::FORBIDDEN, "text/plain", Vec::new());
+  }
+
+  match std::fs::read(&path) {
+    Ok(bytes) => asset_response(StatusCode::OK, mime_type_for(&path), bytes),
+    Err(_) => asset_response(StatusCode::NOT_FOUND, "text/plain", Vec::new()),
+  }
+}
+
+// document_asset_roots for a document the user opened or approved the folder of
+fn scoped_asset_roots(
+  scope: &AccessScope,
+  document: &Path,
+  home_dir: Option<&Path>,
+) -> CommandResult<Vec<PathBuf>> {
+  scope.check(document)?;
+  document_asset_roots(document, home_dir)
+}
+
+// Allow the preview to load files from the document's folder (and its assets subfolder)
+#[tauri::command]
+pub async fn allow_document_assets(
+  app: AppHandle,
+  state: tauri::State<'_, AssetScopeState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  document_path: String,
+) -> CommandResult<()> {
+  let document = PathBuf::from(&document_path);
+  let home_dir = app.path().home_dir().ok();
+  let roots = scoped_asset_roots(&scope.0.lock().unwrap(), &document, home_dir.as_deref())?;
+  state.0.lock().unwrap().allow(document, roots);
+  Ok(())
+}
+
+// Drop the asset scope of a document that was closed
+#[tauri::command]
+pub async fn revoke_document_assets(
+  state: tauri::State<'_, AssetScopeState>,
+  document_path: String,
+) -> CommandResult<bool> {
+  Ok(state.0.lock().unwrap().revoke(Path::new(&document_path)))
+}
+
+// Get the URL for a reference relative to the document; None for remote/data URLs
+#[tauri::command]
+pub async fn resolve_asset_url(
+  document_path: String,
+  relative: String,
+) -> CommandResult<Option<String>> {
+  let document = PathBuf::from(&document_path);
+  if !document.is_absolute() {
+    return Err(CommandError::invalid_path(
+      &document,
+      "File path must be absolute",
+    ));
+  }
+  Ok(resolve_reference(&document, &relative).map(|path| asset_url_for(&path)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_scope_allows_files_under_document_folder() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path().canonicalize().unwrap();
+    let doc = root.join("doc.md");
+    let mut scope = AssetScope::default();
+    scope.allow(doc.clone(), document_asset_roots(&doc, None).unwrap());
+
+    assert!(scope.is_allowed(&root.join("assets").join("pic.png")));
+    assert!(!scope.is_allowed(&PathBuf::from("/etc/passwd")));
+
+    assert!(scope.revoke(&doc));
+    assert!(!scope.is_allowed(&root.join("assets").join("pic.png")));
+  }
+
+  #[test]
+  fn test_documents_outside_the_scope_get_no_assets() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    fs::create_dir(&notes).unwrap();
+    let secrets = dir.path().join("secrets").join("x.md");
+    fs::create_dir(secrets.parent().unwrap()).unwrap();
+    let scope = AccessScope::with_folders(&[&notes]);
+
+    let error = scoped_asset_roots(&scope, &secrets, None).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    let roots = scoped_asset_roots(&scope, &notes.join("doc.md"), None).unwrap();
+    assert_eq!(roots, vec![notes.canonicalize().unwrap()]);
+  }
+
+  #[test]
+  fn test_refuses_home_directory_without_assets_folder() {
+    let home = TempDir::new().unwrap();
+    let doc = home.path().join("note.md");
+    let result = document_asset_roots(&doc, Some(home.path()));
+    assert!(result.unwrap_err().to_string().contains("Refusing"));
+  }
+
+  #[test]
+  fn test_home_directory_document_gets_only_assets_folder() {
+    let home = TempDir::new().unwrap();
+    fs::create_dir(home.path().join("assets")).unwrap();
+    let doc = home.path().join("note.md");
+
+    let roots = document_asset_roots(&doc, Some(home.path())).unwrap();
+    assert_eq!(
+      roots,
+      vec![home.path().join("assets").canonicalize().unwrap()]
+    );
+  }
+
+  #[test]
+  fn test_refuses_filesystem_root() {
+    assert!(is_sensitive_root(Path::new("/"), None));
+    assert!(!is_sensitive_root(Path::new("/home/me/notes"), None));
+  }
+
+  #[test]
+  fn test_resolve_reference() {
+    let doc = Path::new("/notes/project/doc.md");
+    assert_eq!(
+      resolve_reference(doc, "./assets/pic.png"),
+      Some(PathBuf::from("/notes/project/assets/pic.png"))
+    );
+    assert_eq!(
+      resolve_reference(doc, "../shared/my%20pic.png?raw=1"),
+      Some(PathBuf::from("/notes/shared/my pic.png"))
+    );
+    assert_eq!(resolve_reference(doc, "https://example.com/a.png"), None);
+    assert_eq!(resolve_reference(doc, "data:image/png;base64,AAAA"), None);
+    assert_eq!(resolve_reference(doc, "#heading"), None);
+  }
+
+  #[cfg(not(windows))]
+  #[test]
+  fn test_asset_url_percent_encodes_path() {
+    assert_eq!(
+      asset_url_for(Path::new("/notes/my pic#1.png")),
+      "mdasset://localhost/%2Fnotes%2Fmy%20pic%231.png"
+    );
+  }
+
+  #[test]
+  fn test_mime_types() {
+    assert_eq!(mime_type_for(Path::new("a.PNG")), "image/png");
+    assert_eq!(mime_type_for(Path::new("a.svg")), "image/svg+xml");
+    assert_eq!(
+      mime_type_for(Path::new("a.unknown")),
+      "application/octet-stream"
+    );
+  }
+}
@@ -0,0 +1,710 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::access_scope::AccessScopeState;
+use crate::assets::resolve_reference;
+use crate::atomic_write::write_atomically;
+use crate::error::{CommandError, CommandResult};
+use crate::export::html_references;
+use crate::includes::is_fence;
+use crate::open_with::{ExternalChange, FILE_CHANGED_EXTERNALLY_EVENT};
+use crate::wiki::{self, WikiIndexState};
+use crate::RecentFilesState;
+
+// Sent to every window for each file batch_rename renamed, so one showing it can follow
+pub const FILE_RENAMED_EVENT: &str = "file-renamed";
+
+// Characters that can't stay as they are in a link destination
+pub(crate) const ENCODED_NAME_CHARS: &[char] = &[' ', '(', ')', '<', '>', '%', '#', '?'];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternKind {
+  // Matches the whole name; `*` and `?` in the replacement stand for what the same
+  // wildcards matched, in order (`*.markdown` -> `*.md`)
+  #[default]
+  Glob,
+  // Replaced wherever it matches in the name; `$1` in the replacement is a group
+  Regex,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BatchRenameOptions {
+  pub pattern: PatternKind,
+  // Only plan: return the renames and link changes without touching any file
+  pub dry_run: bool,
+  // Point links and images in the workspace's notes at the new names
+  pub update_links: bool,
+}
+
+impl Default for BatchRenameOptions {
+  fn default() -> Self {
+    BatchRenameOptions {
+      pattern: PatternKind::Glob,
+      dry_run: false,
+      update_links: true,
+    }
+  }
+}
+
+// Why a rename can't be done; batch_rename refuses to apply a plan with any of these
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RenameProblem {
+  // Another file already has the new name
+  Exists,
+  // Several files would get the same new name
+  Duplicate,
+  // The new name is empty or has a path separator in it
+  InvalidName,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlannedRename {
+  pub old_path: String,
+  pub new_path: String,
+  pub problem: Option<RenameProblem>,
+}
+
+// A note whose links were pointed at new names; `path` is where it is after the renames
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RewrittenFile {
+  pub path: String,
+  pub links: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchRenameReport {
+  pub renames: Vec<PlannedRename>,
+  pub rewritten: Vec<RewrittenFile>,
+  // False for a dry run
+  pub applied: bool,
+}
+
+// Payload of FILE_RENAMED_EVENT
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileRenamed {
+  pub old_path: String,
+  pub new_path: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rename {
+  old: PathBuf,
+  new: PathBuf,
+  problem: Option<RenameProblem>,
+}
+
+// Everything a batch rename would do
+#[derive(Debug, Default)]
+struct RenamePlan {
+  renames: Vec<Rename>,
+  // Notes with their new text
+  rewrites: Vec<(PathBuf, String, usize)>,
+}
+
+// Key for comparing paths. macOS and Windows filesystems are case-insensitive by default, so
+// there `Notes.md` and `notes.md` are one file.
+fn path_key(path: &Path) -> String {
+  let path = path.to_string_lossy();
+  if cfg!(any(target_os = "macos", target_os = "windows")) {
+    path.to_lowercase()
+  } else {
+    path.to_string()
+  }
+}
+
+// `*` matches any run of characters, `?` any one; each is a group for the replacement
+pub(crate) fn glob_to_regex(glob: &str) -> String {
+  let mut pattern = String::from("^");
+  for c in glob.chars() {
+    match c {
+      '*' => pattern.push_str("(.*)"),
+      '?' => pattern.push_str("(.)"),
+      c => pattern.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+    }
+  }
+  pattern.push('$');
+  pattern
+}
+
+// `*` and `?` in a glob replacement take what the wildcards matched, in order. Wildcards
+// beyond those in the pattern are dropped.
+fn expand_glob_replacement(replace: &str, pieces: &[&str]) -> String {
+  let mut pieces = pieces.iter();
+  let mut expanded = String::with_capacity(replace.len());
+  for c in replace.chars() {
+    match c {
+      '*' | '?' => expanded.push_str(pieces.next().copied().unwrap_or_default()),
+      c => expanded.push(c),
+    }
+  }
+  expanded
+}
+
+struct Renamer {
+  regex: Regex,
+  replace: String,
+  kind: PatternKind,
+}
+
+impl Renamer {
+  fn new(find: &str, replace: &str, kind: PatternKind) -> CommandResult<Self> {
+    if find.is_empty() {
+      return Err(CommandError::invalid_data("Pattern is empty"));
+    }
+    let pattern = match kind {
+      PatternKind::Glob => glob_to_regex(find),
+      PatternKind::Regex => find.to_string(),
+    };
+    let regex = Regex::new(&pattern)
+      .map_err(|e| CommandError::invalid_data(format!("Invalid pattern: {}", e)))?;
+    Ok(Renamer {
+      regex,
+      replace: replace.to_string(),
+      kind,
+    })
+  }
+
+  // The new name for `name`; None if the pattern doesn't match or changes nothing
+  fn rename(&self, name: &str) -> Option<String> {
+    let renamed = match self.kind {
+      PatternKind::Glob => {
+        let captures = self.regex.captures(name)?;
+        let pieces: Vec<&str> = captures
+          .iter()
+          .skip(1)
+          .map(|piece| piece.map_or("", |piece| piece.as_str()))
+          .collect();
+        expand_glob_replacement(&self.replace, &pieces)
+      }
+      PatternKind::Regex => {
+        if !self.regex.is_match(name) {
+          return None;
+        }
+        self
+          .regex
+          .replace_all(name, self.replace.as_str())
+          .into_owned()
+      }
+    };
+    (renamed != name).then_some(renamed)
+  }
+}
+
+// The renames `rename` asks for among `files`, each flagged with what stands in its way
+fn plan_renames(files: &[PathBuf], rename: impl Fn(&str) -> Option<String>) -> Vec<Rename> {
+  let mut renames: Vec<Rename> = files
+    .iter()
+    .filter_map(|old| {
+      let new_name = rename(old.file_name()?.to_str()?)?;
+      let invalid = new_name.trim().is_empty()
+        || new_name.contains(['/', '\\'])
+        || new_name == "."
+        || new_name == "..";
+      Some(Rename {
+        new: old.with_file_name(&new_name),
+        old: old.clone(),
+        problem: invalid.then_some(RenameProblem::InvalidName),
+      })
+    })
+    .collect();
+  renames.sort_by(|a, b| a.old.cmp(&b.old));
+
+  // A target that's itself renamed away is free (this covers case-only renames too)
+  let sources: HashSet<String> = renames.iter().map(|r| path_key(&r.old)).collect();
+  let mut targets: HashMap<String, usize> = HashMap::new();
+  for rename in renames.iter().filter(|r| r.problem.is_none()) {
+    *targets.entry(path_key(&rename.new)).or_insert(0) += 1;
+  }
+  for rename in renames.iter_mut().filter(|r| r.problem.is_none()) {
+    let key = path_key(&rename.new);
+    if targets[&key] > 1 {
+      rename.problem = Some(RenameProblem::Duplicate);
+    } else if !sources.contains(&key) && rename.new.symlink_metadata().is_ok() {
+      rename.problem = Some(RenameProblem::Exists);
+    }
+  }
+  renames
+}
+
+// Byte range of the link destination starting at `start`: what's inside `<...>`, or up to
+// whitespace or the `)` closing the link
+fn destination_range(line: &str, start: usize) -> Option<Range<usize>> {
+  let rest = &line[start..];
+  if let Some(inner) = rest.strip_prefix('<') {
+    let end = inner.find('>')?;
+    return Some(start + 1..start + 1 + end);
+  }
+  let mut depth = 0;
+  let mut end = rest.len();
+  for (index, c) in rest.char_indices() {
+    match c {
+      '(' => depth += 1,
+      ')' if depth == 0 => {
+        end = index;
+        break;
+      }
+      ')' => depth -= 1,
+      c if c.is_whitespace() => {
+        end = index;
+        break;
+      }
+      _ => {}
+    }
+  }
+  (end > 0).then(|| start..start + end)
+}
+
+// Destinations of the inline links and images, link reference definitions and
+// `src`/`href` attributes on a line, in order
+fn link_destinations(line: &str) -> Vec<Range<usize>> {
+  let after_spaces = |index: usize| index + line[index..].len() - line[index..].trim_start().len();
+  let mut destinations: Vec<Range<usize>> = line
+    .match_indices("](")
+    .filter_map(|(index, _)| destination_range(line, after_spaces(index + 2)))
+    .collect();
+  if line.trim_start().starts_with('[') {
+    if let Some(index) = line.find("]:") {
+      destinations.extend(destination_range(line, after_spaces(index + 2)));
+    }
+  }
+  destinations.extend(html_references(line).into_iter().map(|(range, _)| range));
+  destinations.sort_by_key(|range| range.start);
+  destinations
+}
+
+// `dir/old%20name.png#part` with the file name swapped for `new_name`, percent-encoded when
+// the old one was or when it has to be
+fn replace_file_name(destination: &str, new_name: &str, angle_brackets: bool) -> String {
+  let path_end = destination.find(['?', '#']).unwrap_or(destination.len());
+  let (path, suffix) = destination.split_at(path_end);
+  let name_start = path.rfind(['/', '\\']).map_or(0, |index| index + 1);
+  let encode =
+    !angle_brackets && (path[name_start..].contains('%') || new_name.contains(ENCODED_NAME_CHARS));
+  let new_name = if encode {
+    urlencoding::encode(new_name).into_owned()
+  } else {
+    new_name.to_string()
+  };
+  format!("{}{}{}", &path[..name_start], new_name, suffix)
+}
+
+// Replace the link and image destinations in `content` that `replace` returns a new one
+// for. `replace` gets each destination and whether it's in angle brackets; code blocks are
+// skipped. Returns the new text and the number of destinations changed.
+pub(crate) fn rewrite_destinations(
+  content: &str,
+  mut replace: impl FnMut(&str, bool) -> Option<String>,
+) -> (String, usize) {
+  let mut rewritten = String::with_capacity(content.len());
+  let mut count = 0;
+  let mut in_fence = false;
+  for line in content.split_inclusive('\n') {
+    if is_fence(line) {
+      in_fence = !in_fence;
+    }
+    if in_fence {
+      rewritten.push_str(line);
+      continue;
+    }
+    let mut last = 0;
+    for range in link_destinations(line) {
+      if range.start < last {
+        continue;
+      }
+      let angle_brackets = line[..range.start].ends_with('<') && line[range.end..].starts_with('>');
+      let Some(replacement) = replace(&line[range.clone()], angle_brackets) else {
+        continue;
+      };
+      rewritten.push_str(&line[last..range.start]);
+      rewritten.push_str(&replacement);
+      last = range.end;
+      count += 1;
+    }
+    rewritten.push_str(&line[last..]);
+  }
+  (rewritten, count)
+}
+
+// Point the references in `content` (the text of `note`) that resolve to a renamed file
+// at its new name. `renamed` maps the path_key of each old path to the new file name.
+// Returns the new text and the number of references changed.
+fn rewrite_references(
+  content: &str,
+  note: &Path,
+  renamed: &HashMap<String, String>,
+) -> (String, usize) {
+  rewrite_destinations(content, |destination, angle_brackets| {
+    let new_name =
+      resolve_reference(note, destination).and_then(|path| renamed.get(&path_key(&path)))?;
+    Some(replace_file_name(destination, new_name, angle_brackets))
+  })
+}
+
+// Work out the renames and, when `update_links`, the notes whose links need to follow
+fn plan(
+  files: &[PathBuf],
+  rename: impl Fn(&str) -> Option<String>,
+  update_links: bool,
+) -> RenamePlan {
+  let renames = plan_renames(files, rename);
+  let renamed: HashMap<String, String> = renames
+    .iter()
+    .filter_map(|r| {
+      let name = r.new.file_name()?.to_string_lossy().to_string();
+      Some((path_key(&r.old), name))
+    })
+    .collect();
+  if !update_links || renamed.is_empty() {
+    return RenamePlan {
+      renames,
+      rewrites: Vec::new(),
+    };
+  }
+  let rewrites = files
+    .iter()
+    .filter(|path| wiki::is_note(path))
+    .filter_map(|note| {
+      // Unreadable notes keep their links
+      let content = std::fs::read_to_string(note).ok()?;
+      let (rewritten, count) = rewrite_references(&content, note, &renamed);
+      (count > 0).then(|| (note.clone(), rewritten, count))
+    })
+    .collect();
+  RenamePlan { renames, rewrites }
+}
+
+// Where `path` ends up after the renames
+fn final_path(renames: &[Rename], path: &Path) -> PathBuf {
+  renames
+    .iter()
+    .find(|r| r.old == path)
+    .map_or_else(|| path.to_path_buf(), |r| r.new.clone())
+}
+
+fn report(plan: &RenamePlan, applied: bool) -> BatchRenameReport {
+  BatchRenameReport {
+    renames: plan
+      .renames
+      .iter()
+      .map(|r| PlannedRename {
+        old_path: r.old.to_string_lossy().to_string(),
+        new_path: r.new.to_string_lossy().to_string(),
+        problem: r.problem,
+      })
+      .collect(),
+    rewritten: plan
+      .rewrites
+      .iter()
+      .map(|(path, _, links)| RewrittenFile {
+        path: final_path(&plan.renames, path)
+          .to_string_lossy()
+          .to_string(),
+        links: *links,
+      })
+      .collect(),
+    applied,
+  }
+}
+
+// Write the rewritten notes, then rename. Every file first moves to a temporary name, so
+// swaps, chains (`a` -> `b`, `b` -> `c`) and case-only renames on case-insensitive
+// filesystems don't trip over each other.
+fn apply(plan: &RenamePlan) -> CommandResult<()> {
+  if let Some(rename) = plan.renames.iter().find(|r| r.problem.is_some()) {
+    return Err(match rename.problem {
+      Some(RenameProblem::InvalidName) => {
+        CommandError::invalid_path(&rename.new, "Not a valid file name")
+      }
+      _ => CommandError::AlreadyExists {
+        path: rename.new.to_string_lossy().to_string(),
+      },
+    });
+  }
+  for (path, content, _) in &plan.rewrites {
+    write_atomically(path, content.as_bytes())
+      .map_err(|e| CommandError::from_io(&e, path, "Failed to update links"))?;
+  }
+
+  let temporary: Vec<PathBuf> = plan
+    .renames
+    .iter()
+    .enumerate()
+    .map(|(index, r)| {
+      let name = r.old.file_name().unwrap_or_default().to_string_lossy();
+      r.old
+        .with_file_name(format!(".{}.renaming-{}", name, index))
+    })
+    .collect();
+  for (index, (rename, temporary_path)) in plan.renames.iter().zip(&temporary).enumerate() {
+    if let Err(e) = std::fs::rename(&rename.old, temporary_path) {
+      // Put back what already moved, so a failed batch leaves the names as they were
+      for (moved, moved_to) in plan.renames.iter().zip(&temporary).take(index) {
+        let _ = std::fs::rename(moved_to, &moved.old);
+      }
+      return Err(CommandError::from_io(&e, &rename.old, "Failed to rename"));
+    }
+  }
+  for (rename, temporary_path) in plan.renames.iter().zip(&temporary) {
+    std::fs::rename(temporary_path, &rename.new)
+      .map_err(|e| CommandError::from_io(&e, &rename.new, "Failed to rename"))?;
+  }
+  Ok(())
+}
+
+// Tell windows about renamed and rewritten files, and keep recents pointing at them
+fn announce(app: &AppHandle, plan: &RenamePlan, root: &Path) {
+  for rename in &plan.renames {
+    let renamed = FileRenamed {
+      old_path: rename.old.to_string_lossy().to_string(),
+      new_path: rename.new.to_string_lossy().to_string(),
+    };
+    let _ = app.emit(FILE_RENAMED_EVENT, renamed);
+  }
+  for (path, _, _) in &plan.rewrites {
+    let path = final_path(&plan.renames, path);
+    let change = ExternalChange {
+      mtime: std::fs::metadata(&path)
+        .ok()
+        .and_then(|metadata| crate::file_mtime_millis(&metadata))
+        .unwrap_or(0),
+      path: path.to_string_lossy().to_string(),
+    };
+    let _ = app.emit(FILE_CHANGED_EXTERNALLY_EVENT, change);
+  }
+
+  let recents_state = app.state::<RecentFilesState>();
+  let mut recents = recents_state.0.lock().unwrap();
+  let mut changed = false;
+  for recent in recents.iter_mut() {
+    if let Some(rename) = plan
+      .renames
+      .iter()
+      .find(|r| crate::recent_paths_equal(recent, &r.old.to_string_lossy()))
+    {
+      *recent = crate::normalize_recent_path(&rename.new.to_string_lossy());
+      changed = true;
+    }
+  }
+  if changed {
+    crate::save_recent_files_to_store(app, &recents);
+  }
+  app.state::<WikiIndexState>().invalidate(root);
+}
+
+// Rename the files under `root` whose names match `find`, and point the workspace's links
+// and images at the new names. Run with `dry_run` first: the report lists every rename
+// with anything that stands in its way, and the notes whose links would change.
+#[tauri::command]
+pub async fn batch_rename(
+  app: AppHandle,
+  root: String,
+  find: String,
+  replace: String,
+  options: Option<BatchRenameOptions>,
+) -> CommandResult<BatchRenameReport> {
+  let options = options.unwrap_or_default();
+  let root = PathBuf::from(root);
+  if !root.is_absolute() || !root.is_dir() {
+    return Err(CommandError::invalid_path(
+      &root,
+      "Workspace must be an absolute folder path",
+    ));
+  }
+  app.state::<AccessScopeState>().check(&root)?;
+  let root = crate::lexical_normalize(&root);
+  let renamer = Renamer::new(&find, &replace, options.pattern)?;
+
+  let files: Vec<PathBuf> = wiki::walk_files(&root, |_| true)
+    .into_iter()
+    .map(|file| file.path)
+    .collect();
+  let plan = plan(&files, |name| renamer.rename(name), options.update_links);
+  if options.dry_run || plan.renames.is_empty() {
+    return Ok(report(&plan, false));
+  }
+  apply(&plan)?;
+  announce(&app, &plan, &root);
+  Ok(report(&plan, true))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  fn workspace(files: &[(&str, &str)]) -> (TempDir, Vec<PathBuf>) {
+    let dir = TempDir::new().unwrap();
+    let paths = files
+      .iter()
+      .map(|(name, content)| {
+        let path = dir.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+      })
+      .collect();
+    (dir, paths)
+  }
+
+  fn glob(find: &str, replace: &str) -> Renamer {
+    Renamer::new(find, replace, PatternKind::Glob).unwrap()
+  }
+
+  #[test]
+  fn test_glob_wildcards_carry_into_the_replacement() {
+    let renamer = glob("*.markdown", "*.md");
+    assert_eq!(
+      renamer.rename("notes.markdown").as_deref(),
+      Some("notes.md")
+    );
+    assert_eq!(renamer.rename("notes.md"), None);
+    let renamer = glob("IMG_????.*", "photo-??-??.*");
+    assert_eq!(
+      renamer.rename("IMG_2024.jpg").as_deref(),
+      Some("photo-20-24.jpg")
+    );
+    // Regex characters in a glob are literal
+    assert_eq!(glob("a+b.md", "c.md").rename("aab.md"), None);
+  }
+
+  #[test]
+  fn test_regex_replaces_every_match_with_groups() {
+    let renamer = Renamer::new(r"(\d+)-(\d+)", "$2-$1", PatternKind::Regex).unwrap();
+    assert_eq!(
+      renamer.rename("10-20 and 3-4.md").as_deref(),
+      Some("20-10 and 4-3.md")
+    );
+    assert!(Renamer::new("(", "", PatternKind::Regex).is_err());
+    assert!(Renamer::new("", "x", PatternKind::Glob).is_err());
+  }
+
+  #[test]
+  fn test_plan_flags_collisions() {
+    let (_dir, files) = workspace(&[
+      ("a.txt", ""),
+      ("b.txt", ""),
+      ("a.md", ""),
+      ("c.text", ""),
+      ("c.md", ""),
+    ]);
+    let renames = plan_renames(&files, |name| {
+      let stem = name
+        .strip_suffix(".txt")
+        .or_else(|| name.strip_suffix(".text"))?;
+      Some(format!("{}.md", if stem == "b" { "a" } else { stem }))
+    });
+    let problems: Vec<(String, Option<RenameProblem>)> = renames
+      .iter()
+      .map(|r| {
+        let name = r.old.file_name().unwrap().to_string_lossy().to_string();
+        (name, r.problem)
+      })
+      .collect();
+    assert_eq!(
+      problems,
+      vec![
+        ("a.txt".to_string(), Some(RenameProblem::Duplicate)),
+        ("b.txt".to_string(), Some(RenameProblem::Duplicate)),
+        ("c.text".to_string(), Some(RenameProblem::Exists)),
+      ]
+    );
+
+    let renames = plan_renames(&files[..1], |_| Some("sub/a.md".to_string()));
+    assert_eq!(renames[0].problem, Some(RenameProblem::InvalidName));
+  }
+
+  #[test]
+  fn test_rewrite_references_follows_renamed_files() {
+    let dir = Path::new("/ws");
+    let renamed: HashMap<String, String> = [
+      (
+        path_key(&dir.join("img/old pic.png")),
+        "new pic.png".to_string(),
+      ),
+      (path_key(&dir.join("notes/old.md")), "new.md".to_string()),
+    ]
+    .into_iter()
+    .collect();
+    let content = "\
+![pic](img/old%20pic.png) ![pic](<img/old pic.png> \"Title\")
+See [old](notes/old.md#intro) and [web](https://example.com/notes/old.md).
+[ref]: ./notes/old.md
+<img src=\"img/old%20pic.png\">
+
+```
+[code](notes/old.md)
+```
+";
+    let (rewritten, count) = rewrite_references(content, &dir.join("index.md"), &renamed);
+    assert_eq!(count, 5);
+    assert_eq!(
+      rewritten,
+      "\
+![pic](img/new%20pic.png) ![pic](<img/new pic.png> \"Title\")
+See [old](notes/new.md#intro) and [web](https://example.com/notes/old.md).
+[ref]: ./notes/new.md
+<img src=\"img/new%20pic.png\">
+
+```
+[code](notes/old.md)
+```
+"
+    );
+  }
+
+  #[test]
+  fn test_apply_renames_and_updates_links() {
+    let (dir, files) = workspace(&[
+      ("index.md", "[a](a.markdown) [b](sub/b.markdown)\n"),
+      ("a.markdown", "[b](sub/b.markdown)\n"),
+      ("sub/b.markdown", "[up](../a.markdown)\n"),
+    ]);
+    let renamer = glob("*.markdown", "*.md");
+    let plan = plan(&files, |name| renamer.rename(name), true);
+    let report = report(&plan, false);
+    assert_eq!(report.renames.len(), 2);
+    assert_eq!(report.rewritten.len(), 3);
+    assert!(report.rewritten.iter().any(|f| f.path.ends_with("a.md")));
+
+    apply(&plan).unwrap();
+    let read = |name: &str| fs::read_to_string(dir.path().join(name)).unwrap();
+    assert_eq!(read("index.md"), "[a](a.md) [b](sub/b.md)\n");
+    assert_eq!(read("a.md"), "[b](sub/b.md)\n");
+    assert_eq!(read("sub/b.md"), "[up](../a.md)\n");
+    assert!(!dir.path().join("a.markdown").exists());
+  }
+
+  #[test]
+  fn test_apply_handles_swaps_and_refuses_collisions() {
+    let (dir, files) = workspace(&[("one.md", "1"), ("two.md", "2")]);
+    let plan = plan(
+      &files,
+      |name| match name {
+        "one.md" => Some("two.md".to_string()),
+        "two.md" => Some("one.md".to_string()),
+        _ => None,
+      },
+      false,
+    );
+    assert!(plan.renames.iter().all(|r| r.problem.is_none()));
+    apply(&plan).unwrap();
+    assert_eq!(fs::read_to_string(dir.path().join("one.md")).unwrap(), "2");
+
+    let plan = plan_renames(&files[..1], |_| Some("two.md".to_string()));
+    let error = apply(&RenamePlan {
+      renames: plan,
+      rewrites: Vec::new(),
+    })
+    .unwrap_err();
+    assert_eq!(error.code(), "already_exists");
+    assert_eq!(fs::read_to_string(dir.path().join("one.md")).unwrap(), "2");
+  }
+}
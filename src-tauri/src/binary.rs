@@ -0,0 +1,138 @@
+// How much of a file is inspected when deciding whether it's text
+const SNIFF_LEN: usize = 8 * 1024;
+
+// Share of control characters above which a file is treated as binary. Text files do
+// contain a few (form feeds as page breaks, ANSI escapes in pasted logs), binaries are
+// full of them.
+const MAX_CONTROL_RATIO: f64 = 0.1;
+
+// Used when a file is clearly binary but none of the signatures below match
+const UNKNOWN_BINARY_MIME: &str = "application/octet-stream";
+
+// Magic bytes of formats people are likely to end up opening by mistake: offset,
+// signature, mime type
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+  (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+  (0, b"\xff\xd8\xff", "image/jpeg"),
+  (0, b"GIF87a", "image/gif"),
+  (0, b"GIF89a", "image/gif"),
+  (8, b"WEBP", "image/webp"),
+  (0, b"II*\0", "image/tiff"),
+  (0, b"MM\0*", "image/tiff"),
+  (0, b"\0\0\x01\0", "image/x-icon"),
+  (4, b"ftypheic", "image/heic"),
+  (4, b"ftyp", "video/mp4"),
+  (0, b"OggS", "audio/ogg"),
+  (0, b"ID3", "audio/mpeg"),
+  (0, b"%PDF-", "application/pdf"),
+  (0, b"PK\x03\x04", "application/zip"),
+  (0, b"\x1f\x8b", "application/gzip"),
+  (0, b"7z\xbc\xaf\x27\x1c", "application/x-7z-compressed"),
+  (0, b"Rar!\x1a\x07", "application/vnd.rar"),
+  (0, b"SQLite format 3\0", "application/vnd.sqlite3"),
+  (0, b"\x7fELF", "application/x-elf"),
+  (0, b"MZ", "application/x-msdownload"),
+  (0, b"\0asm", "application/wasm"),
+  (0, b"wOFF", "font/woff"),
+  (0, b"wOF2", "font/woff2"),
+];
+
+// If `bytes` (a file's contents) look binary, the best guess at their mime type.
+// UTF-16 text is full of NULs, so a byte order mark marks a file as text.
+pub fn sniff_binary(bytes: &[u8]) -> Option<&'static str> {
+  let head = &bytes[..bytes.len().min(SNIFF_LEN)];
+  if head.starts_with(b"\xff\xfe") || head.starts_with(b"\xfe\xff") {
+    return None;
+  }
+  if !looks_binary(head) {
+    return None;
+  }
+  let mime = SIGNATURES
+    .iter()
+    .find(|(offset, signature, _)| {
+      head
+        .get(*offset..)
+        .is_some_and(|h| h.starts_with(signature))
+    })
+    .map(|(_, _, mime)| *mime);
+  Some(mime.unwrap_or(UNKNOWN_BINARY_MIME))
+}
+
+fn looks_binary(head: &[u8]) -> bool {
+  if head.is_empty() {
+    return false;
+  }
+  if head.contains(&0) {
+    return true;
+  }
+  let control = head.iter().filter(|b| is_binary_control(**b)).count();
+  control as f64 / head.len() as f64 > MAX_CONTROL_RATIO
+}
+
+// Control characters that don't normally appear in text: everything below space except
+// tab, newline, vertical tab, form feed, carriage return and escape, plus DEL
+fn is_binary_control(byte: u8) -> bool {
+  matches!(byte, 0x00..=0x08 | 0x0e..=0x1a | 0x1c..=0x1f | 0x7f)
+}
+
+// "a PNG image" for "image/png", for messages like "This looks like a PNG image"
+pub fn describe_mime(mime: &str) -> String {
+  let known = match mime {
+    "image/png" => "a PNG image",
+    "image/jpeg" => "a JPEG image",
+    "image/gif" => "a GIF image",
+    "image/webp" => "a WebP image",
+    "image/tiff" => "a TIFF image",
+    "image/x-icon" => "an icon file",
+    "image/heic" => "a HEIC image",
+    "video/mp4" => "an MP4 video",
+    "audio/ogg" => "an Ogg audio file",
+    "audio/mpeg" => "an MP3 file",
+    "application/pdf" => "a PDF document",
+    "application/zip" => "a ZIP archive",
+    "application/gzip" => "a gzip archive",
+    "application/x-7z-compressed" => "a 7-Zip archive",
+    "application/vnd.rar" => "a RAR archive",
+    "application/vnd.sqlite3" => "an SQLite database",
+    "application/x-elf" | "application/x-msdownload" => "a program",
+    "application/wasm" => "a WebAssembly module",
+    "font/woff" | "font/woff2" => "a web font",
+    _ => "a binary file",
+  };
+  known.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Start of a real 1x1 PNG
+  const PNG_FIXTURE: &[u8] =
+    b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x01\0\0\0\x01\x08\x06\0\0\0\x1f\x15\xc4\x89";
+  // A plain-text document that uses form feeds as page breaks
+  const FORM_FEED_FIXTURE: &[u8] =
+    b"# Chapter 1\n\nSome text.\n\x0c\n# Chapter 2\n\nMore text\twith tabs.\r\n\x0c\n";
+
+  #[test]
+  fn test_detects_binary_formats() {
+    assert_eq!(sniff_binary(PNG_FIXTURE), Some("image/png"));
+    assert_eq!(
+      sniff_binary(b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n1 0 obj\0"),
+      Some("application/pdf")
+    );
+    // Binary without a known signature
+    let noise: Vec<u8> = (0..=255u8).cycle().take(4096).collect();
+    assert_eq!(sniff_binary(&noise), Some(UNKNOWN_BINARY_MIME));
+    assert_eq!(describe_mime("image/png"), "a PNG image");
+  }
+
+  #[test]
+  fn test_text_with_control_characters_is_not_binary() {
+    assert_eq!(sniff_binary(FORM_FEED_FIXTURE), None);
+    assert_eq!(sniff_binary(b"\x1b[31mred\x1b[0m log line\n"), None);
+    assert_eq!(sniff_binary("# Überschrift\n".as_bytes()), None);
+    // UTF-16 with a byte order mark
+    assert_eq!(sniff_binary(b"\xff\xfe#\0 \0H\0i\0"), None);
+    assert_eq!(sniff_binary(b""), None);
+  }
+}
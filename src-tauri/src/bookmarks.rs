@@ -0,0 +1,400 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use tauri::AppHandle;
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::app_store;
+use crate::error::{CommandError, CommandResult};
+use crate::{normalize_recent_path, now_millis, recent_paths_equal};
+
+// Store key holding the named bookmarks of each file
+pub const BOOKMARKS_KEY: &str = "bookmarks";
+
+// How many lines above and below its stored line a bookmark's anchor is looked for
+const ANCHOR_SEARCH_LINES: usize = 200;
+
+// A named position in a file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+  pub id: String,
+  // Zero-based, as of the last time the bookmark was placed
+  pub line: u32,
+  pub name: String,
+  // The trimmed text of the bookmarked line, for finding it again after edits
+  pub anchor: String,
+  pub created_at: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookmarkEntry {
+  // Normalized like recents
+  pub path: String,
+  pub bookmarks: Vec<Bookmark>,
+}
+
+// A bookmark placed in the file as it is now
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlacedBookmark {
+  #[serde(flatten)]
+  pub bookmark: Bookmark,
+  // The anchor was found on another line, which `line` now holds
+  pub moved: bool,
+  // The anchor wasn't found nearby, so `line` is only where the bookmark used to be
+  pub lost: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileBookmarks {
+  pub path: String,
+  pub bookmarks: Vec<PlacedBookmark>,
+}
+
+// Where the line with `anchor` is now: `line` if it still matches, otherwise the nearest
+// match within ANCHOR_SEARCH_LINES (below first, since edits above push lines down).
+// None if there's no match nearby.
+fn relocate(lines: &[&str], line: usize, anchor: &str) -> Option<usize> {
+  let matches = |i: usize| lines.get(i).is_some_and(|text| text.trim() == anchor);
+  if matches(line) {
+    return Some(line);
+  }
+  (1..=ANCHOR_SEARCH_LINES).find_map(|distance| {
+    let below = line + distance;
+    let above = line.checked_sub(distance);
+    if matches(below) {
+      Some(below)
+    } else {
+      above.filter(|&i| matches(i))
+    }
+  })
+}
+
+// Place the bookmarks in `content`, updating their stored line when the anchor moved
+fn place(bookmarks: &mut [Bookmark], content: &str) -> Vec<PlacedBookmark> {
+  let lines: Vec<&str> = content.lines().collect();
+  let last_line = lines.len().saturating_sub(1) as u32;
+  bookmarks
+    .iter_mut()
+    .map(|bookmark| {
+      let found = relocate(&lines, bookmark.line as usize, &bookmark.anchor);
+      let moved = found.is_some_and(|line| line as u32 != bookmark.line);
+      match found {
+        Some(line) => bookmark.line = line as u32,
+        None => bookmark.line = bookmark.line.min(last_line),
+      }
+      PlacedBookmark {
+        bookmark: bookmark.clone(),
+        moved,
+        lost: found.is_none(),
+      }
+    })
+    .collect()
+}
+
+// The next id for a bookmark in `bookmarks`
+fn next_id(bookmarks: &[Bookmark]) -> String {
+  let max = bookmarks
+    .iter()
+    .filter_map(|bookmark| bookmark.id.strip_prefix("bookmark-")?.parse::<u64>().ok())
+    .max()
+    .unwrap_or(0);
+  format!("bookmark-{}", max + 1)
+}
+
+fn entry_index(entries: &[BookmarkEntry], path: &str) -> Option<usize> {
+  entries
+    .iter()
+    .position(|entry| recent_paths_equal(&entry.path, path))
+}
+
+fn load_entries(app: &AppHandle) -> CommandResult<Vec<BookmarkEntry>> {
+  app_store::read_list(app, BOOKMARKS_KEY)
+}
+
+fn save_entries(app: &AppHandle, entries: &[BookmarkEntry]) -> CommandResult<()> {
+  app_store::write_list(app, BOOKMARKS_KEY, "bookmarks", entries)
+}
+
+// The bookmarked file's content, or None if it no longer exists
+fn read_bookmarked(scope: &AccessScope, path: &str) -> CommandResult<Option<String>> {
+  let path = Path::new(path);
+  scope.check(path)?;
+  match crate::read_text_file(path) {
+    Ok(content) => Ok(Some(content)),
+    Err(CommandError::NotFound { .. }) => Ok(None),
+    Err(e) => Err(e),
+  }
+}
+
+// The trimmed text of `line` in the file, to anchor a new bookmark to
+fn bookmark_anchor(scope: &AccessScope, path: &str, line: u32) -> CommandResult<String> {
+  let content = read_bookmarked(scope, path)?
+    .ok_or_else(|| CommandError::invalid_path(Path::new(path), "File not found"))?;
+  let anchor = content.lines().nth(line as usize).ok_or_else(|| {
+    CommandError::invalid_data(format!("Line {} is past the end of the file", line))
+  })?;
+  Ok(anchor.trim().to_string())
+}
+
+// Place the bookmarks of entries[index], dropping the entry if its file is gone. Returns
+// whether the entries changed.
+fn refresh_entry(
+  scope: &AccessScope,
+  entries: &mut Vec<BookmarkEntry>,
+  index: usize,
+) -> CommandResult<(Vec<PlacedBookmark>, bool)> {
+  let Some(content) = read_bookmarked(scope, &entries[index].path)? else {
+    entries.remove(index);
+    return Ok((Vec::new(), true));
+  };
+  let entry = &mut entries[index];
+  let placed = place(&mut entry.bookmarks, &content);
+  let changed = placed.iter().any(|bookmark| bookmark.moved);
+  Ok((placed, changed))
+}
+
+#[tauri::command]
+pub async fn add_bookmark(
+  app: AppHandle,
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+  line: u32,
+  name: String,
+) -> CommandResult<Bookmark> {
+  let path = normalize_recent_path(&path);
+  let anchor = bookmark_anchor(&scope.0.lock().unwrap(), &path, line)?;
+
+  let mut entries = load_entries(&app)?;
+  let index = entry_index(&entries, &path).unwrap_or_else(|| {
+    entries.push(BookmarkEntry {
+      path: path.clone(),
+      bookmarks: Vec::new(),
+    });
+    entries.len() - 1
+  });
+  let bookmarks = &mut entries[index].bookmarks;
+  let bookmark = Bookmark {
+    id: next_id(bookmarks),
+    line,
+    name,
+    anchor,
+    created_at: now_millis(),
+  };
+  bookmarks.push(bookmark.clone());
+  bookmarks.sort_by_key(|bookmark| bookmark.line);
+  save_entries(&app, &entries)?;
+  Ok(bookmark)
+}
+
+// The file's bookmarks in line order, placed in its current content
+#[tauri::command]
+pub async fn list_bookmarks(
+  app: AppHandle,
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+) -> CommandResult<Vec<PlacedBookmark>> {
+  let path = normalize_recent_path(&path);
+  let mut entries = load_entries(&app)?;
+  let Some(index) = entry_index(&entries, &path) else {
+    return Ok(Vec::new());
+  };
+  let (mut placed, changed) = refresh_entry(&scope.0.lock().unwrap(), &mut entries, index)?;
+  if changed {
+    save_entries(&app, &entries)?;
+  }
+  placed.sort_by_key(|bookmark| bookmark.bookmark.line);
+  Ok(placed)
+}
+
+// Returns whether there was such a bookmark
+#[tauri::command]
+pub async fn remove_bookmark(app: AppHandle, path: String, id: String) -> CommandResult<bool> {
+  let path = normalize_recent_path(&path);
+  let mut entries = load_entries(&app)?;
+  let Some(index) = entry_index(&entries, &path) else {
+    return Ok(false);
+  };
+  let bookmarks = &mut entries[index].bookmarks;
+  let count = bookmarks.len();
+  bookmarks.retain(|bookmark| bookmark.id != id);
+  if bookmarks.len() == count {
+    return Ok(false);
+  }
+  if bookmarks.is_empty() {
+    entries.remove(index);
+  }
+  save_entries(&app, &entries)?;
+  Ok(true)
+}
+
+// Every file's bookmarks, placed in their current content. Entries whose file is gone are
+// dropped; ones that can't be read now (outside the access scope, unreadable) are left out
+// but kept for later. Returns whether the entries changed.
+fn refresh_all(
+  scope: &AccessScope,
+  entries: &mut Vec<BookmarkEntry>,
+) -> (Vec<FileBookmarks>, bool) {
+  let mut files = Vec::new();
+  let mut changed = false;
+  let mut index = 0;
+  while index < entries.len() {
+    let path = entries[index].path.clone();
+    let count = entries.len();
+    let (mut placed, entry_changed) = match refresh_entry(scope, entries, index) {
+      Ok(refreshed) => refreshed,
+      Err(e) => {
+        log::warn!("Skipping the bookmarks of {}: {}", path, e);
+        index += 1;
+        continue;
+      }
+    };
+    changed |= entry_changed;
+    if entries.len() < count {
+      continue;
+    }
+    placed.sort_by_key(|bookmark| bookmark.bookmark.line);
+    files.push(FileBookmarks {
+      path,
+      bookmarks: placed,
+    });
+    index += 1;
+  }
+  (files, changed)
+}
+
+// Every file's bookmarks, for the Bookmarks panel (see refresh_all)
+#[tauri::command]
+pub async fn list_all_bookmarks(
+  app: AppHandle,
+  scope: tauri::State<'_, AccessScopeState>,
+) -> CommandResult<Vec<FileBookmarks>> {
+  let mut entries = load_entries(&app)?;
+  let (files, changed) = refresh_all(&scope.0.lock().unwrap(), &mut entries);
+  if changed {
+    save_entries(&app, &entries)?;
+  }
+  Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use std::path::PathBuf;
+  use tempfile::TempDir;
+
+  fn bookmark(id: &str, line: u32, anchor: &str) -> Bookmark {
+    Bookmark {
+      id: id.to_string(),
+      line,
+      name: anchor.to_string(),
+      anchor: anchor.to_string(),
+      created_at: 0,
+    }
+  }
+
+  #[test]
+  fn test_bookmarks_follow_their_anchor() {
+    let mut bookmarks = vec![
+      bookmark("bookmark-1", 1, "## Setup"),
+      bookmark("bookmark-2", 3, "## Usage"),
+      bookmark("bookmark-3", 4, "## Removed"),
+    ];
+    // Two lines inserted above Setup, Usage unchanged, Removed gone
+    let content = "# Guide\nIntro\n\n  ## Setup\n## Usage\nText";
+    let placed = place(&mut bookmarks, content);
+    let positions: Vec<(u32, bool, bool)> = placed
+      .iter()
+      .map(|b| (b.bookmark.line, b.moved, b.lost))
+      .collect();
+    assert_eq!(
+      positions,
+      vec![(3, true, false), (4, true, false), (4, false, true)]
+    );
+    assert_eq!(bookmarks[0].line, 3);
+
+    // Nearest match wins, below before above
+    let lines = ["x", "a", "x", "y", "x"];
+    assert_eq!(relocate(&lines, 3, "x"), Some(4));
+    assert_eq!(relocate(&lines, 3, "a"), Some(1));
+    assert_eq!(relocate(&lines, 9, "y"), Some(3));
+    assert_eq!(relocate(&lines, 0, "z"), None);
+  }
+
+  #[test]
+  fn test_ids_and_missing_files() {
+    assert_eq!(next_id(&[]), "bookmark-1");
+    let bookmarks = [bookmark("bookmark-7", 0, ""), bookmark("bookmark-2", 0, "")];
+    assert_eq!(next_id(&bookmarks), "bookmark-8");
+
+    let dir = TempDir::new().unwrap();
+    let present = dir.path().join("present.md");
+    fs::write(&present, "# Title\n").unwrap();
+    let mut entries = vec![
+      BookmarkEntry {
+        path: dir.path().join("gone.md").to_string_lossy().to_string(),
+        bookmarks: vec![bookmark("bookmark-1", 0, "# Gone")],
+      },
+      BookmarkEntry {
+        path: present.to_string_lossy().to_string(),
+        bookmarks: vec![bookmark("bookmark-1", 0, "# Title")],
+      },
+    ];
+    let scope = AccessScope::with_folders(&[dir.path()]);
+    let (placed, changed) = refresh_entry(&scope, &mut entries, 0).unwrap();
+    assert!(placed.is_empty() && changed);
+    assert_eq!(entries.len(), 1);
+    let (placed, changed) = refresh_entry(&scope, &mut entries, 0).unwrap();
+    assert_eq!(placed.len(), 1);
+    assert!(!changed && !placed[0].moved && !placed[0].lost);
+  }
+
+  #[test]
+  fn test_files_outside_the_scope_are_denied() {
+    let dir = TempDir::new().unwrap();
+    let note = dir.path().join("note.md");
+    fs::write(&note, "# Title\n").unwrap();
+    let path = note.to_string_lossy().to_string();
+    let empty = AccessScope::default();
+
+    let error = bookmark_anchor(&empty, &path, 0).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    let mut entries = vec![BookmarkEntry {
+      path: path.clone(),
+      bookmarks: vec![bookmark("bookmark-1", 0, "# Title")],
+    }];
+    let error = refresh_entry(&empty, &mut entries, 0).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    assert_eq!(entries.len(), 1);
+
+    let scope = AccessScope::with_folders(&[dir.path()]);
+    assert_eq!(bookmark_anchor(&scope, &path, 0).unwrap(), "# Title");
+  }
+
+  #[test]
+  fn test_unreadable_files_are_skipped_not_fatal() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    fs::create_dir_all(notes.join("folder.md")).unwrap();
+    fs::write(notes.join("note.md"), "# Note\n").unwrap();
+    fs::write(dir.path().join("outside.md"), "# Outside\n").unwrap();
+    let entry = |path: PathBuf| BookmarkEntry {
+      path: path.to_string_lossy().to_string(),
+      bookmarks: vec![bookmark("bookmark-1", 0, "# Note")],
+    };
+    let mut entries = vec![
+      entry(dir.path().join("outside.md")),
+      entry(notes.join("gone.md")),
+      entry(notes.join("folder.md")),
+      entry(notes.join("note.md")),
+    ];
+
+    let scope = AccessScope::with_folders(&[&notes]);
+    let (files, changed) = refresh_all(&scope, &mut entries);
+    let listed: Vec<&str> = files.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(listed, vec![entries[2].path.as_str()]);
+    assert!(changed);
+    // Only the missing file is forgotten
+    assert_eq!(entries.len(), 3);
+    assert!(entries[2].path.ends_with("note.md"));
+  }
+}
@@ -0,0 +1,334 @@
+use serde::Serialize;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::error::{CommandError, CommandResult};
+use crate::export::{collect_assets, frontmatter_assets, DocumentAsset};
+use crate::staging::write_staged;
+
+// Name of the rendered page inside a bundle
+const BUNDLE_INDEX_FILE: &str = "index.html";
+
+// Already-compressed formats are stored as-is; deflating them again only costs time
+const STORED_EXTENSIONS: &[&str] = &[
+  "png", "jpg", "jpeg", "gif", "webp", "avif", "mp4", "webm", "mp3", "zip", "pdf",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkippedAsset {
+  pub reference: String,
+  pub reason: String,
+}
+
+// What went into a bundle (archive paths) and what was left out
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BundleResult {
+  pub output_path: String,
+  pub files: Vec<String>,
+  pub skipped: Vec<SkippedAsset>,
+}
+
+// Path of a file inside the archive: relative to the document folder, forward-slashed.
+// None if the path is outside the folder.
+fn archive_path(root: &Path, path: &Path) -> Option<String> {
+  let relative = path.strip_prefix(root).ok()?;
+  let parts: Vec<String> = relative
+    .components()
+    .map(|c| match c {
+      Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+      _ => None,
+    })
+    .collect::<Option<_>>()?;
+  (!parts.is_empty()).then(|| parts.join("/"))
+}
+
+fn file_options(name: &str) -> SimpleFileOptions {
+  let extension = Path::new(name)
+    .extension()
+    .map(|e| e.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+  let method = if STORED_EXTENSIONS.contains(&extension.as_str()) {
+    CompressionMethod::Stored
+  } else {
+    CompressionMethod::Deflated
+  };
+  SimpleFileOptions::default()
+    .compression_method(method)
+    .large_file(true)
+}
+
+// Pick the assets that may go into the bundle. Both the reference and the file it points
+// to (after following symlinks) must be inside the document's folder.
+fn bundle_entries(
+  root: &Path,
+  assets: Vec<DocumentAsset>,
+) -> (Vec<(String, PathBuf)>, Vec<SkippedAsset>) {
+  let canonical_root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+  let mut entries: Vec<(String, PathBuf)> = Vec::new();
+  let mut skipped = Vec::new();
+
+  for asset in assets {
+    let inside = archive_path(root, &asset.path).zip(asset.path.canonicalize().ok());
+    match inside {
+      Some((name, target)) if target.starts_with(&canonical_root) => {
+        if !entries.iter().any(|(existing, _)| *existing == name) {
+          entries.push((name, asset.path));
+        }
+      }
+      _ => {
+        log::warn!(
+          "Skipping asset outside the document folder: {}",
+          asset.reference
+        );
+        skipped.push(SkippedAsset {
+          reference: asset.reference,
+          reason: "outside the document folder".to_string(),
+        });
+      }
+    }
+  }
+
+  (entries, skipped)
+}
+
+fn write_bundle(
+  output_path: &Path,
+  document_name: &str,
+  markdown: &str,
+  html: Option<&str>,
+  entries: &[(String, PathBuf)],
+) -> CommandResult<()> {
+  let file = File::create(output_path)
+    .map_err(|e| CommandError::from_io(&e, output_path, "Failed to create bundle"))?;
+  let mut zip = ZipWriter::new(BufWriter::new(file));
+  let zip_error = |e: zip::result::ZipError| CommandError::io("Failed to write bundle", e);
+  let io_error =
+    |e: std::io::Error| CommandError::from_io(&e, output_path, "Failed to write bundle");
+
+  zip
+    .start_file(document_name, file_options(document_name))
+    .map_err(zip_error)?;
+  zip.write_all(markdown.as_bytes()).map_err(io_error)?;
+
+  if let Some(html) = html {
+    zip
+      .start_file(BUNDLE_INDEX_FILE, file_options(BUNDLE_INDEX_FILE))
+      .map_err(zip_error)?;
+    zip.write_all(html.as_bytes()).map_err(io_error)?;
+  }
+
+  // Assets are streamed from disk so large images are never held in memory
+  for (name, path) in entries {
+    let mut source =
+      File::open(path).map_err(|e| CommandError::from_io(&e, path, "Failed to read asset"))?;
+    zip
+      .start_file(name, file_options(name))
+      .map_err(zip_error)?;
+    std::io::copy(&mut source, &mut zip).map_err(io_error)?;
+  }
+
+  zip.finish().map_err(zip_error)?.flush().map_err(io_error)?;
+  Ok(())
+}
+
+fn export_bundle_to(
+  scope: &AccessScope,
+  document: &Path,
+  output_path: &Path,
+  html: Option<&str>,
+) -> CommandResult<BundleResult> {
+  if !output_path.is_absolute() {
+    return Err(CommandError::invalid_path(
+      output_path,
+      "File path must be absolute",
+    ));
+  }
+  scope.check(document)?;
+  scope.check(output_path)?;
+  let markdown = crate::read_text_file(document)?;
+  let root = document
+    .parent()
+    .ok_or_else(|| CommandError::invalid_path(document, "Document has no parent directory"))?;
+  let document_name = document
+    .file_name()
+    .map(|n| n.to_string_lossy().to_string())
+    .ok_or_else(|| CommandError::invalid_path(document, "Invalid document path"))?;
+
+  let mut assets = frontmatter_assets(document, &markdown);
+  for asset in collect_assets(document, &markdown) {
+    if !assets.iter().any(|a| a.path == asset.path) {
+      assets.push(asset);
+    }
+  }
+  let (mut entries, skipped) = bundle_entries(root, assets);
+  // The document and index.html are written separately
+  entries.retain(|(name, _)| *name != document_name && *name != BUNDLE_INDEX_FILE);
+
+  // Build the archive next to its destination and move it into place when complete
+  let folder = output_path.parent().ok_or_else(|| {
+    CommandError::invalid_path(output_path, "Export path has no parent directory")
+  })?;
+  write_staged(folder, output_path, |staging| {
+    let staged_path = staging.join(output_path.file_name().unwrap_or_default());
+    write_bundle(&staged_path, &document_name, &markdown, html, &entries)
+  })?;
+
+  let mut files = vec![document_name];
+  if html.is_some() {
+    files.push(BUNDLE_INDEX_FILE.to_string());
+  }
+  files.extend(entries.into_iter().map(|(name, _)| name));
+
+  Ok(BundleResult {
+    output_path: output_path.to_string_lossy().to_string(),
+    files,
+    skipped,
+  })
+}
+
+// Package a saved document, the local files it references and optionally its rendered
+// HTML (as index.html) into a zip for sharing
+#[tauri::command]
+pub async fn export_bundle(
+  scope: tauri::State<'_, AccessScopeState>,
+  document_path: String,
+  output_zip_path: String,
+  html: Option<String>,
+) -> CommandResult<BundleResult> {
+  export_bundle_to(
+    &scope.0.lock().unwrap(),
+    Path::new(&document_path),
+    Path::new(&output_zip_path),
+    html.as_deref(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use std::io::Read;
+  use tempfile::TempDir;
+  use zip::ZipArchive;
+
+  fn archive_names(path: &Path) -> Vec<String> {
+    let archive = ZipArchive::new(File::open(path).unwrap()).unwrap();
+    let mut names: Vec<String> = archive.file_names().map(String::from).collect();
+    names.sort();
+    names
+  }
+
+  #[test]
+  fn test_bundle_contains_document_assets_and_index() {
+    let dir = TempDir::new().unwrap();
+    let out = TempDir::new().unwrap();
+    fs::create_dir_all(dir.path().join("img").join("nested")).unwrap();
+    fs::write(dir.path().join("img").join("nested").join("a.png"), "png").unwrap();
+    fs::write(dir.path().join("cover.jpg"), "jpg").unwrap();
+    let doc = dir.path().join("doc.md");
+    fs::write(
+      &doc,
+      "---\ncover: cover.jpg\n---\n\n![A](img/nested/a.png)\n",
+    )
+    .unwrap();
+    let output = out.path().join("doc.zip");
+
+    let scope = AccessScope::with_folders(&[dir.path(), out.path()]);
+    let result = export_bundle_to(&scope, &doc, &output, Some("<p>rendered</p>")).unwrap();
+    assert!(result.skipped.is_empty());
+    assert_eq!(
+      archive_names(&output),
+      vec!["cover.jpg", "doc.md", "img/nested/a.png", "index.html"]
+    );
+
+    let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+    let mut index = String::new();
+    archive
+      .by_name("index.html")
+      .unwrap()
+      .read_to_string(&mut index)
+      .unwrap();
+    assert_eq!(index, "<p>rendered</p>");
+  }
+
+  #[test]
+  fn test_bundle_skips_assets_outside_document_folder() {
+    let outside = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("notes")).unwrap();
+    fs::write(dir.path().join("secret.png"), "secret").unwrap();
+    let doc = dir.path().join("notes").join("doc.md");
+    fs::write(&doc, "![](../secret.png)\n").unwrap();
+    let output = outside.path().join("doc.zip");
+
+    let scope = AccessScope::with_folders(&[dir.path(), outside.path()]);
+    let result = export_bundle_to(&scope, &doc, &output, None).unwrap();
+    assert_eq!(result.files, vec!["doc.md"]);
+    assert_eq!(result.skipped.len(), 1);
+    assert_eq!(result.skipped[0].reference, "../secret.png");
+    assert_eq!(archive_names(&output), vec!["doc.md"]);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_bundle_dereferences_symlinks_inside_folder_only() {
+    let outside = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("real.png"), "real").unwrap();
+    fs::write(outside.path().join("private.png"), "private").unwrap();
+    std::os::unix::fs::symlink(dir.path().join("real.png"), dir.path().join("link.png")).unwrap();
+    std::os::unix::fs::symlink(
+      outside.path().join("private.png"),
+      dir.path().join("escape.png"),
+    )
+    .unwrap();
+    let doc = dir.path().join("doc.md");
+    fs::write(&doc, "![](link.png) ![](escape.png)\n").unwrap();
+    let output = outside.path().join("doc.zip");
+
+    let scope = AccessScope::with_folders(&[dir.path(), outside.path()]);
+    let result = export_bundle_to(&scope, &doc, &output, None).unwrap();
+    assert_eq!(result.files, vec!["doc.md", "link.png"]);
+    assert_eq!(result.skipped[0].reference, "escape.png");
+
+    let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+    let mut content = String::new();
+    archive
+      .by_name("link.png")
+      .unwrap()
+      .read_to_string(&mut content)
+      .unwrap();
+    assert_eq!(content, "real");
+  }
+
+  #[test]
+  fn test_paths_outside_the_scope_are_denied() {
+    let dir = TempDir::new().unwrap();
+    let out = TempDir::new().unwrap();
+    let doc = dir.path().join("doc.md");
+    fs::write(&doc, "# Doc\n").unwrap();
+    let output = out.path().join("doc.zip");
+
+    let only_document = AccessScope::with_folders(&[dir.path()]);
+    let error = export_bundle_to(&only_document, &doc, &output, None).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    assert!(!output.exists());
+    let only_output = AccessScope::with_folders(&[out.path()]);
+    let error = export_bundle_to(&only_output, &doc, &output, None).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+  }
+
+  #[test]
+  fn test_archive_paths_are_relative_and_forward_slashed() {
+    let root = Path::new("/notes");
+    assert_eq!(
+      archive_path(root, &Path::new("/notes").join("img").join("a.png")),
+      Some("img/a.png".to_string())
+    );
+    assert_eq!(archive_path(root, Path::new("/other/a.png")), None);
+  }
+}
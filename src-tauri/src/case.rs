@@ -0,0 +1,241 @@
+use serde::Deserialize;
+use std::ops::Range;
+
+use crate::error::CommandResult;
+use crate::typography::protected_ranges;
+
+// Case changes for the selection, exposed under Format > Change Case
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseOperation {
+  Upper,
+  Lower,
+  // Every word capitalized except SMALL_WORDS in the middle of a title
+  Title,
+  // Only the first word of each sentence capitalized (and "I")
+  Sentence,
+}
+
+// Articles, conjunctions and short prepositions, lowercase in titles unless first or last
+const SMALL_WORDS: &[&str] = &[
+  "a", "an", "and", "as", "at", "but", "by", "en", "for", "from", "if", "in", "into", "nor", "of",
+  "on", "onto", "or", "per", "so", "the", "to", "up", "via", "vs", "with", "yet",
+];
+
+// A word outside code, URLs and the like, with what decides how it's capitalized
+struct Word {
+  range: Range<usize>,
+  first_in_line: bool,
+  last_in_line: bool,
+  // Follows a colon, like "the beginning" in "Part 1: The Beginning"
+  after_colon: bool,
+  starts_sentence: bool,
+}
+
+fn is_word_char(c: char) -> bool {
+  c.is_alphanumeric() || c == '\'' || c == '’'
+}
+
+// Whether `line` starts a new block rather than continuing the paragraph of `previous`
+fn starts_block(previous: Option<&str>, line: &str) -> bool {
+  let Some(previous) = previous else {
+    return true;
+  };
+  let trimmed = line.trim_start();
+  let list_item = trimmed
+    .trim_start_matches(|c: char| c.is_ascii_digit())
+    .strip_prefix(['.', ')'])
+    .filter(|_| trimmed.starts_with(|c: char| c.is_ascii_digit()))
+    .or_else(|| trimmed.strip_prefix(['-', '*', '+']))
+    .is_some_and(|rest| rest.starts_with([' ', '\t']));
+  previous.trim().is_empty()
+    || previous.trim_start().starts_with('#')
+    || trimmed.starts_with(['#', '>', '|'])
+    || list_item
+}
+
+// Whether the text between two words ends a sentence: `.`, `!` or `?`, possibly inside
+// closing quotes, brackets or emphasis, then a space
+fn ends_sentence(gap: &str) -> bool {
+  let Some(punctuation) = gap.find(['.', '!', '?']) else {
+    return false;
+  };
+  let after = &gap[punctuation + 1..];
+  let after = after.trim_start_matches(['.', '!', '?', '"', '\'', '”', '’', ')', ']', '*', '_']);
+  after.starts_with(char::is_whitespace)
+}
+
+// The words of `text` outside `protected`, line by line
+fn words(text: &str, protected: &[Range<usize>]) -> Vec<Word> {
+  let mut words = Vec::new();
+  let mut offset = 0;
+  let mut previous_line: Option<&str> = None;
+  let mut previous_end = 0;
+  for line in text.split_inclusive('\n') {
+    let line_start = offset;
+    offset += line.len();
+    let new_block = starts_block(previous_line, line);
+    previous_line = Some(line);
+    let first = words.len();
+
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+      let start = line_start + i;
+      if let Some(range) = protected.iter().find(|r| r.contains(&start)) {
+        // Skip to the end of the protected range
+        while chars
+          .peek()
+          .is_some_and(|(j, _)| line_start + j < range.end)
+        {
+          chars.next();
+        }
+        continue;
+      }
+      if !c.is_alphanumeric() {
+        continue;
+      }
+      let mut end = start + c.len_utf8();
+      while let Some((j, c)) = chars.peek().copied() {
+        let position = line_start + j;
+        if !is_word_char(c) || protected.iter().any(|r| r.contains(&position)) {
+          break;
+        }
+        end = position + c.len_utf8();
+        chars.next();
+      }
+      let word = text[start..end].trim_end_matches(['\'', '’']);
+      let gap = &text[previous_end..start];
+      words.push(Word {
+        range: start..start + word.len(),
+        first_in_line: words.len() == first,
+        last_in_line: false,
+        after_colon: gap.contains(':'),
+        starts_sentence: (words.len() == first && new_block) || ends_sentence(gap),
+      });
+      previous_end = end;
+    }
+    if let Some(last) = words.get_mut(first..).and_then(<[Word]>::last_mut) {
+      last.last_in_line = true;
+    }
+  }
+  words
+}
+
+fn capitalize(word: &str) -> String {
+  let mut chars = word.chars();
+  match chars.next() {
+    Some(first) => first.to_uppercase().chain(chars).collect(),
+    None => String::new(),
+  }
+}
+
+fn title_word(word: &str, info: &Word) -> String {
+  let lower = word.to_lowercase();
+  let small = SMALL_WORDS.contains(&lower.as_str());
+  if small && !info.first_in_line && !info.last_in_line && !info.after_colon {
+    return lower;
+  }
+  // Names like iPhone and McDonald keep their capitals
+  if word.chars().skip(1).any(char::is_uppercase) {
+    return word.to_string();
+  }
+  capitalize(word)
+}
+
+fn sentence_word(word: &str, info: &Word) -> String {
+  let lower = word.to_lowercase();
+  let pronoun_i = lower == "i" || lower.starts_with("i'") || lower.starts_with("i’");
+  if info.starts_sentence || pronoun_i {
+    capitalize(&lower)
+  } else {
+    lower
+  }
+}
+
+// Change the case of `text`, leaving code, math, URLs, link destinations, HTML and
+// frontmatter as they are
+pub fn transform_case(text: &str, operation: CaseOperation) -> String {
+  let protected = protected_ranges(text);
+  let mut result = String::with_capacity(text.len());
+  let mut last = 0;
+  match operation {
+    CaseOperation::Upper | CaseOperation::Lower => {
+      let copy_changed = |segment: &str, result: &mut String| match operation {
+        CaseOperation::Upper => result.push_str(&segment.to_uppercase()),
+        _ => result.push_str(&segment.to_lowercase()),
+      };
+      for range in &protected {
+        copy_changed(&text[last..range.start], &mut result);
+        result.push_str(&text[range.clone()]);
+        last = range.end;
+      }
+      copy_changed(&text[last..], &mut result);
+    }
+    CaseOperation::Title | CaseOperation::Sentence => {
+      for word in words(text, &protected) {
+        result.push_str(&text[last..word.range.start]);
+        let original = &text[word.range.clone()];
+        result.push_str(&match operation {
+          CaseOperation::Title => title_word(original, &word),
+          _ => sentence_word(original, &word),
+        });
+        last = word.range.end;
+      }
+      result.push_str(&text[last..]);
+    }
+  }
+  result
+}
+
+#[tauri::command]
+pub async fn transform_text(text: String, operation: CaseOperation) -> CommandResult<String> {
+  Ok(transform_case(&text, operation))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_upper_and_lower_skip_code_and_urls() {
+    let text = "Run `make All` at https://Example.com/Path or [the Docs](Docs/Guide.md)";
+    assert_eq!(
+      transform_case(text, CaseOperation::Upper),
+      "RUN `make All` AT https://Example.com/Path OR [THE DOCS](Docs/Guide.md)"
+    );
+    assert_eq!(
+      transform_case(text, CaseOperation::Lower),
+      "run `make All` at https://Example.com/Path or [the docs](Docs/Guide.md)"
+    );
+  }
+
+  #[test]
+  fn test_title_case() {
+    assert_eq!(
+      transform_case("the lord of the rings", CaseOperation::Title),
+      "The Lord of the Rings"
+    );
+    assert_eq!(
+      transform_case(
+        "## a guide to the iPhone: what it's for\n- read [the manual on setup](docs/the-setup.md)\n",
+        CaseOperation::Title
+      ),
+      "## A Guide to the iPhone: What It's For\n- Read [the Manual on Setup](docs/the-setup.md)\n"
+    );
+    assert_eq!(
+      transform_case("well-known `code in` names", CaseOperation::Title),
+      "Well-Known `code in` Names"
+    );
+  }
+
+  #[test]
+  fn test_sentence_case() {
+    assert_eq!(
+      transform_case(
+        "THE QUICK fox. then i'm gone! (really?) YES\nand this continues\n\n# new BLOCK\n- item ONE\n",
+        CaseOperation::Sentence
+      ),
+      "The quick fox. Then I'm gone! (Really?) Yes\nand this continues\n\n# New block\n- Item one\n"
+    );
+  }
+}
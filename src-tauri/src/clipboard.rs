@@ -0,0 +1,169 @@
+use arboard::{Clipboard, ImageData};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::error::{CommandError, CommandResult};
+use crate::now_millis;
+
+// Folder (in the app data dir) where images pasted into unsaved documents are written
+const PASTED_IMAGES_DIR: &str = "pasted-images";
+
+// Which clipboard flavor a document was created from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardSource {
+  Html,
+  Text,
+  Image,
+}
+
+// Result of new_from_clipboard: `{"kind": "empty"}` when there is nothing usable, so the
+// frontend can say so instead of showing an error
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ClipboardDocument {
+  Markdown {
+    markdown: String,
+    source: ClipboardSource,
+  },
+  Empty,
+}
+
+// Convert HTML (e.g. copied from a browser or word processor) to markdown
+pub fn html_to_markdown(html: &str) -> CommandResult<String> {
+  htmd::HtmlToMarkdown::builder()
+    .skip_tags(vec!["head", "script", "style", "meta"])
+    .build()
+    .convert(html)
+    .map(|markdown| markdown.trim().to_string())
+    .map_err(|e| CommandError::invalid_data(format!("Failed to convert HTML: {}", e)))
+}
+
+// Pick the markdown for a new document from the text flavors on the clipboard, preferring
+// HTML so formatting survives. Blank flavors are skipped.
+pub(crate) fn markdown_from_flavors(
+  html: Option<&str>,
+  text: Option<&str>,
+) -> Option<(String, ClipboardSource)> {
+  if let Some(html) = html {
+    match html_to_markdown(html) {
+      Ok(markdown) if !markdown.is_empty() => return Some((markdown, ClipboardSource::Html)),
+      Ok(_) => {}
+      Err(e) => log::warn!("{}", e),
+    }
+  }
+  text
+    .filter(|text| !text.trim().is_empty())
+    .map(|text| (text.to_string(), ClipboardSource::Text))
+}
+
+// Write pasted RGBA pixels as a PNG into `dir`, returning the new file's path
+pub fn save_pasted_image(dir: &Path, image: &ImageData) -> CommandResult<PathBuf> {
+  let too_large = |_| CommandError::invalid_data("Image is too large");
+  let width = u32::try_from(image.width).map_err(too_large)?;
+  let height = u32::try_from(image.height).map_err(too_large)?;
+  std::fs::create_dir_all(dir)
+    .map_err(|e| CommandError::from_io(&e, dir, "Failed to create image folder"))?;
+
+  let millis = now_millis();
+  let mut path = dir.join(format!("pasted-{}.png", millis));
+  let mut counter = 1;
+  while path.exists() {
+    path = dir.join(format!("pasted-{}-{}.png", millis, counter));
+    counter += 1;
+  }
+
+  let file = std::fs::File::create(&path)
+    .map_err(|e| CommandError::from_io(&e, &path, "Failed to save pasted image"))?;
+  let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), width, height);
+  encoder.set_color(png::ColorType::Rgba);
+  encoder.set_depth(png::BitDepth::Eight);
+  encoder
+    .write_header()
+    .and_then(|mut writer| writer.write_image_data(&image.bytes))
+    .map_err(|e| CommandError::io("Failed to save pasted image", e))?;
+  Ok(path)
+}
+
+// Markdown for a document consisting of a single saved image. The angle brackets let the
+// path contain spaces.
+fn image_document(path: &Path) -> String {
+  format!("![Pasted image](<{}>)\n", path.display())
+}
+
+// Build a new, unsaved document from the clipboard: HTML converted to markdown, else plain
+// text, else an image saved to disk and referenced from the document
+#[tauri::command]
+pub async fn new_from_clipboard(app: AppHandle) -> CommandResult<ClipboardDocument> {
+  let mut clipboard =
+    Clipboard::new().map_err(|e| CommandError::io("Failed to access clipboard", e))?;
+
+  let html = clipboard.get().html().ok();
+  let text = clipboard.get_text().ok();
+  if let Some((markdown, source)) = markdown_from_flavors(html.as_deref(), text.as_deref()) {
+    return Ok(ClipboardDocument::Markdown { markdown, source });
+  }
+
+  match clipboard.get_image() {
+    Ok(image) => {
+      let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| CommandError::io("Failed to resolve app data dir", e))?
+        .join(PASTED_IMAGES_DIR);
+      let path = save_pasted_image(&dir, &image)?;
+      Ok(ClipboardDocument::Markdown {
+        markdown: image_document(&path),
+        source: ClipboardSource::Image,
+      })
+    }
+    Err(arboard::Error::ContentNotAvailable) => Ok(ClipboardDocument::Empty),
+    Err(e) => Err(CommandError::io("Failed to read clipboard", e)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::borrow::Cow;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_prefers_html_flavor() {
+    let (markdown, source) = markdown_from_flavors(
+      Some("<h1>Title</h1><p>Some <strong>bold</strong> text</p>"),
+      Some("Title\nSome bold text"),
+    )
+    .unwrap();
+    assert_eq!(source, ClipboardSource::Html);
+    assert_eq!(markdown, "# Title\n\nSome **bold** text");
+  }
+
+  #[test]
+  fn test_falls_back_to_plain_text() {
+    let (markdown, source) =
+      markdown_from_flavors(Some("<style>p {}</style>"), Some("plain *text*")).unwrap();
+    assert_eq!(source, ClipboardSource::Text);
+    assert_eq!(markdown, "plain *text*");
+    assert_eq!(markdown_from_flavors(None, Some("  \n")), None);
+  }
+
+  #[test]
+  fn test_save_pasted_image_writes_png() {
+    let dir = TempDir::new().unwrap();
+    let image = ImageData {
+      width: 2,
+      height: 1,
+      bytes: Cow::Owned(vec![255, 0, 0, 255, 0, 0, 255, 255]),
+    };
+
+    let path = save_pasted_image(dir.path(), &image).unwrap();
+    let bytes = std::fs::read(&path).unwrap();
+    assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+
+    let second = save_pasted_image(dir.path(), &image).unwrap();
+    assert_ne!(path, second);
+    assert!(image_document(&path).starts_with("![Pasted image](<"));
+  }
+}
@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager, Window, WindowEvent};
+
+use crate::error::CommandResult;
+
+// Event sent to a window with unsaved changes that is about to close (or whose app is about
+// to quit). The window answers with confirm_close_response.
+pub const CONFIRM_CLOSE_EVENT: &str = "confirm-close";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfirmClose {
+  // The whole app is quitting, not just this window closing
+  pub quitting: bool,
+}
+
+// The user's answer to CONFIRM_CLOSE_EVENT
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloseAction {
+  // The window saves through its normal save flow and closes once set_dirty(false) arrives
+  Save,
+  Discard,
+  Cancel,
+}
+
+// What has to happen next for a pending close or quit
+#[derive(Debug, Clone, PartialEq)]
+enum Next {
+  Ask { label: String, quitting: bool },
+  Close(String),
+  Exit,
+  Nothing,
+}
+
+#[derive(Debug, Default)]
+struct CloseGuard {
+  // Labels of windows with unsaved changes
+  dirty: BTreeSet<String>,
+  // Windows that chose Save and close once they report they're clean
+  saving: BTreeSet<String>,
+  // A quit is asking every dirty window in turn
+  quitting: bool,
+  // Every window agreed, so the exit request this causes goes through
+  quit_approved: bool,
+}
+
+impl CloseGuard {
+  fn set_dirty(&mut self, label: &str, dirty: bool) -> Next {
+    if dirty {
+      self.dirty.insert(label.to_string());
+      return Next::Nothing;
+    }
+    self.dirty.remove(label);
+    if self.saving.remove(label) {
+      return self.after_confirmed(label);
+    }
+    Next::Nothing
+  }
+
+  fn needs_confirmation(&self, label: &str) -> bool {
+    self.dirty.contains(label)
+  }
+
+  fn request_quit(&mut self) -> Next {
+    self.quitting = true;
+    self.next_for_quit()
+  }
+
+  // Ask the next dirty window, or exit when none is left
+  fn next_for_quit(&mut self) -> Next {
+    match self.dirty.iter().next() {
+      Some(label) => Next::Ask {
+        label: label.clone(),
+        quitting: true,
+      },
+      None => {
+        self.quit_approved = true;
+        Next::Exit
+      }
+    }
+  }
+
+  fn respond(&mut self, label: &str, action: CloseAction) -> Next {
+    match action {
+      CloseAction::Cancel => {
+        self.saving.remove(label);
+        self.quitting = false;
+        Next::Nothing
+      }
+      CloseAction::Discard => {
+        self.dirty.remove(label);
+        self.saving.remove(label);
+        self.after_confirmed(label)
+      }
+      CloseAction::Save => {
+        self.saving.insert(label.to_string());
+        Next::Nothing
+      }
+    }
+  }
+
+  fn after_confirmed(&mut self, label: &str) -> Next {
+    if self.quitting {
+      self.next_for_quit()
+    } else {
+      Next::Close(label.to_string())
+    }
+  }
+
+  fn forget(&mut self, label: &str) {
+    self.dirty.remove(label);
+    self.saving.remove(label);
+  }
+}
+
+#[derive(Default)]
+pub struct CloseGuardState(Mutex<CloseGuard>);
+
+fn apply(app: &AppHandle, next: Next) {
+  match next {
+    Next::Ask { label, quitting } => {
+      if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+      }
+      let _ = app.emit_to(
+        label.as_str(),
+        CONFIRM_CLOSE_EVENT,
+        ConfirmClose { quitting },
+      );
+    }
+    Next::Close(label) => {
+      if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.close();
+      }
+    }
+    Next::Exit => app.exit(0),
+    Next::Nothing => {}
+  }
+}
+
+// Quit, asking every window with unsaved changes first. Used by the Quit menu items.
+pub fn request_quit(app: &AppHandle) {
+  let next = app
+    .state::<CloseGuardState>()
+    .0
+    .lock()
+    .unwrap()
+    .request_quit();
+  apply(app, next);
+}
+
+// Called for exit requests that didn't come through request_quit (e.g. the OS ending the
+// session). Returns whether the exit has to wait for the user.
+pub fn intercept_exit(app: &AppHandle) -> bool {
+  let next = {
+    let mut guard = app.state::<CloseGuardState>().0.lock().unwrap();
+    if guard.quit_approved || guard.dirty.is_empty() {
+      return false;
+    }
+    guard.request_quit()
+  };
+  apply(app, next);
+  true
+}
+
+// Hold back closing a window with unsaved changes until the user confirms
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+  let state = window.state::<CloseGuardState>();
+  match event {
+    WindowEvent::CloseRequested { api, .. } => {
+      if state.0.lock().unwrap().needs_confirmation(window.label()) {
+        api.prevent_close();
+        apply(
+          window.app_handle(),
+          Next::Ask {
+            label: window.label().to_string(),
+            quitting: false,
+          },
+        );
+      }
+    }
+    WindowEvent::Destroyed => state.0.lock().unwrap().forget(window.label()),
+    _ => {}
+  }
+}
+
+// Record whether a window has unsaved changes. A window that chose Save closes once it
+// reports it's clean.
+#[tauri::command]
+pub async fn set_dirty(
+  app: AppHandle,
+  state: tauri::State<'_, CloseGuardState>,
+  window_label: String,
+  dirty: bool,
+) -> CommandResult<()> {
+  #[cfg(target_os = "macos")]
+  if let Some(window) = app.get_webview_window(&window_label) {
+    crate::macos::set_document_edited(&window, dirty);
+  }
+  let next = state.0.lock().unwrap().set_dirty(&window_label, dirty);
+  apply(&app, next);
+  Ok(())
+}
+
+#[tauri::command]
+pub async fn confirm_close_response(
+  app: AppHandle,
+  state: tauri::State<'_, CloseGuardState>,
+  window_label: String,
+  action: CloseAction,
+) -> CommandResult<()> {
+  let next = state.0.lock().unwrap().respond(&window_label, action);
+  apply(&app, next);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_closing_a_dirty_window_waits_for_save() {
+    let mut guard = CloseGuard::default();
+    guard.set_dirty("main", true);
+    assert!(guard.needs_confirmation("main"));
+    assert!(!guard.needs_confirmation("main-2"));
+
+    assert_eq!(guard.respond("main", CloseAction::Save), Next::Nothing);
+    assert_eq!(
+      guard.set_dirty("main", false),
+      Next::Close("main".to_string())
+    );
+    // A later save doesn't close the window again
+    assert_eq!(guard.set_dirty("main", false), Next::Nothing);
+  }
+
+  #[test]
+  fn test_quit_asks_each_dirty_window_then_exits() {
+    let mut guard = CloseGuard::default();
+    guard.set_dirty("main", true);
+    guard.set_dirty("main-2", true);
+
+    let ask = |label: &str| Next::Ask {
+      label: label.to_string(),
+      quitting: true,
+    };
+    assert_eq!(guard.request_quit(), ask("main"));
+    assert_eq!(guard.respond("main", CloseAction::Discard), ask("main-2"));
+    assert_eq!(guard.respond("main-2", CloseAction::Save), Next::Nothing);
+    assert_eq!(guard.set_dirty("main-2", false), Next::Exit);
+    assert!(guard.quit_approved);
+  }
+
+  #[test]
+  fn test_cancel_stops_the_quit() {
+    let mut guard = CloseGuard::default();
+    guard.set_dirty("main", true);
+    guard.request_quit();
+
+    assert_eq!(guard.respond("main", CloseAction::Cancel), Next::Nothing);
+    assert!(!guard.quitting);
+    assert!(guard.needs_confirmation("main"));
+    // Cancelling after a failed save also forgets the pending close
+    guard.respond("main", CloseAction::Save);
+    guard.respond("main", CloseAction::Cancel);
+    assert_eq!(guard.set_dirty("main", false), Next::Nothing);
+  }
+}
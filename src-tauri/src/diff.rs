@@ -0,0 +1,267 @@
+use serde::Serialize;
+use similar::{ChangeTag, DiffOp, TextDiff};
+use std::path::PathBuf;
+
+use crate::access_scope::AccessScopeState;
+use crate::error::CommandResult;
+
+// Number of unchanged lines shown around each change when none is requested
+const DEFAULT_CONTEXT_LINES: usize = 3;
+
+// Kind of a single line inside a diff hunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffLineKind {
+  Context,
+  Add,
+  Remove,
+}
+
+// A single line of a hunk. `highlights` holds [start, end) offsets of the words that
+// changed within the line, in UTF-16 code units so the frontend can slice strings directly.
+// It is only populated for removed/added line pairs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffLine {
+  pub kind: DiffLineKind,
+  pub text: String,
+  pub highlights: Vec<[usize; 2]>,
+}
+
+// A unified-diff hunk; line numbers are 1-based like in `diff -u` output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffHunk {
+  pub old_start: usize,
+  pub old_lines: usize,
+  pub new_start: usize,
+  pub new_lines: usize,
+  pub lines: Vec<DiffLine>,
+}
+
+// Compute the unified-diff hunks between two texts (Myers algorithm)
+pub fn compute_hunks(old: &str, new: &str, context_lines: usize) -> Vec<DiffHunk> {
+  let diff = TextDiff::from_lines(old, new);
+
+  diff
+    .grouped_ops(context_lines)
+    .iter()
+    .filter_map(|group| build_hunk(&diff, group))
+    .collect()
+}
+
+fn build_hunk<'a>(diff: &'a TextDiff<'a, 'a, 'a, str>, ops: &[DiffOp]) -> Option<DiffHunk> {
+  let first = ops.first()?;
+  let last = ops.last()?;
+  let old_range = first.old_range().start..last.old_range().end;
+  let new_range = first.new_range().start..last.new_range().end;
+
+  let mut lines = Vec::new();
+  for op in ops {
+    for change in diff.iter_inline_changes(op) {
+      let kind = match change.tag() {
+        ChangeTag::Equal => DiffLineKind::Context,
+        ChangeTag::Insert => DiffLineKind::Add,
+        ChangeTag::Delete => DiffLineKind::Remove,
+      };
+
+      let mut text = String::new();
+      let mut highlights: Vec<[usize; 2]> = Vec::new();
+      let mut offset = 0;
+      for (emphasized, segment) in change.iter_strings_lossy() {
+        let segment = segment.trim_end_matches(['\n', '\r']);
+        let width = segment.encode_utf16().count();
+        if emphasized && width > 0 {
+          // Merge with the previous highlight when the changed words are adjacent
+          match highlights.last_mut() {
+            Some([_, end]) if *end == offset => *end = offset + width,
+            _ => highlights.push([offset, offset + width]),
+          }
+        }
+        text.push_str(segment);
+        offset += width;
+      }
+
+      lines.push(DiffLine {
+        kind,
+        text,
+        highlights,
+      });
+    }
+  }
+
+  Some(DiffHunk {
+    old_start: hunk_start(&old_range),
+    old_lines: old_range.len(),
+    new_start: hunk_start(&new_range),
+    new_lines: new_range.len(),
+    lines,
+  })
+}
+
+// Unified diff convention: an empty range starts at the line before the change
+fn hunk_start(range: &std::ops::Range<usize>) -> usize {
+  if range.is_empty() {
+    range.start
+  } else {
+    range.start + 1
+  }
+}
+
+// Diff two text buffers
+#[tauri::command]
+pub async fn diff_text(
+  old: String,
+  new: String,
+  context_lines: Option<usize>,
+) -> CommandResult<Vec<DiffHunk>> {
+  Ok(compute_hunks(
+    &old,
+    &new,
+    context_lines.unwrap_or(DEFAULT_CONTEXT_LINES),
+  ))
+}
+
+// Diff two files on disk (read with the same validation as read_file)
+#[tauri::command]
+pub async fn diff_files(
+  scope: tauri::State<'_, AccessScopeState>,
+  path_a: String,
+  path_b: String,
+  context_lines: Option<usize>,
+) -> CommandResult<Vec<DiffHunk>> {
+  let (path_a, path_b) = (PathBuf::from(&path_a), PathBuf::from(&path_b));
+  scope.check(&path_a)?;
+  scope.check(&path_b)?;
+  let old = crate::read_text_file(&path_a)?;
+  let new = crate::read_text_file(&path_b)?;
+  Ok(compute_hunks(
+    &old,
+    &new,
+    context_lines.unwrap_or(DEFAULT_CONTEXT_LINES),
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_identical_texts_have_no_hunks() {
+    let text = "# Title\n\nSame content\n";
+    assert!(compute_hunks(text, text, 3).is_empty());
+  }
+
+  #[test]
+  fn test_single_line_change() {
+    let old = "one\ntwo\nthree\nfour\nfive\n";
+    let new = "one\ntwo\nTHREE\nfour\nfive\n";
+
+    let hunks = compute_hunks(old, new, 1);
+    assert_eq!(hunks.len(), 1);
+    let hunk = &hunks[0];
+    assert_eq!(
+      (
+        hunk.old_start,
+        hunk.old_lines,
+        hunk.new_start,
+        hunk.new_lines
+      ),
+      (2, 3, 2, 3)
+    );
+
+    let kinds: Vec<DiffLineKind> = hunk.lines.iter().map(|l| l.kind).collect();
+    assert_eq!(
+      kinds,
+      vec![
+        DiffLineKind::Context,
+        DiffLineKind::Remove,
+        DiffLineKind::Add,
+        DiffLineKind::Context
+      ]
+    );
+    assert_eq!(hunk.lines[1].text, "three");
+    assert_eq!(hunk.lines[2].text, "THREE");
+  }
+
+  #[test]
+  fn test_separate_hunks_for_distant_changes() {
+    let old: String = (1..=20).map(|i| format!("line {}\n", i)).collect();
+    let new = old
+      .replace("line 2\n", "line two\n")
+      .replace("line 18\n", "line eighteen\n");
+
+    let hunks = compute_hunks(&old, &new, 2);
+    assert_eq!(hunks.len(), 2);
+    assert_eq!(hunks[0].old_start, 1);
+    assert_eq!(hunks[1].old_start, 16);
+  }
+
+  #[test]
+  fn test_pure_insertion_ranges() {
+    let hunks = compute_hunks("a\nb\n", "a\nb\nc\n", 0);
+    assert_eq!(hunks.len(), 1);
+    let hunk = &hunks[0];
+    assert_eq!((hunk.old_start, hunk.old_lines), (2, 0));
+    assert_eq!((hunk.new_start, hunk.new_lines), (3, 1));
+    assert_eq!(hunk.lines[0].kind, DiffLineKind::Add);
+    assert!(hunk.lines[0].highlights.is_empty());
+  }
+
+  #[test]
+  fn test_word_level_highlights() {
+    let hunks = compute_hunks("The quick fox\n", "The slow fox\n", 0);
+    let lines = &hunks[0].lines;
+
+    let removed = &lines[0];
+    assert_eq!(removed.kind, DiffLineKind::Remove);
+    let [start, end] = removed.highlights[0];
+    assert_eq!(&removed.text[start..end], "quick");
+
+    let added = &lines[1];
+    assert_eq!(added.kind, DiffLineKind::Add);
+    let [start, end] = added.highlights[0];
+    assert_eq!(&added.text[start..end], "slow");
+  }
+
+  #[test]
+  fn test_highlights_use_utf16_offsets() {
+    let hunks = compute_hunks("😀 old\n", "😀 new\n", 0);
+    let added = &hunks[0].lines[1];
+    // The emoji is two UTF-16 code units, plus the space
+    assert_eq!(added.highlights, vec![[3, 6]]);
+  }
+
+  #[test]
+  fn test_diff_files_reads_both_files() {
+    let dir = TempDir::new().unwrap();
+    let a = dir.path().join("a.md");
+    let b = dir.path().join("b.md");
+    fs::write(&a, "# Doc\nold line\n").unwrap();
+    fs::write(&b, "# Doc\nnew line\n").unwrap();
+
+    let hunks = tauri::async_runtime::block_on(diff_files(
+      a.to_string_lossy().to_string(),
+      b.to_string_lossy().to_string(),
+      None,
+    ))
+    .unwrap();
+    assert_eq!(hunks.len(), 1);
+    assert_eq!(hunks[0].lines.len(), 3);
+  }
+
+  #[test]
+  fn test_diff_files_missing_file() {
+    let dir = TempDir::new().unwrap();
+    let a = dir.path().join("a.md");
+    fs::write(&a, "content").unwrap();
+    let missing = dir.path().join("missing.md");
+
+    let result = tauri::async_runtime::block_on(diff_files(
+      a.to_string_lossy().to_string(),
+      missing.to_string_lossy().to_string(),
+      None,
+    ));
+    assert!(result.is_err());
+  }
+}
@@ -0,0 +1,176 @@
+use arboard::Clipboard;
+use serde::Deserialize;
+use std::path::{Component, Path, PathBuf};
+
+use crate::access_scope::AccessScopeState;
+use crate::error::{CommandError, CommandResult};
+use crate::filename::{first_heading, strip_inline_markdown};
+
+// File > Copy Path. Items are `copy_document_reference:<kind>`.
+pub const ITEM_ID_PREFIX: &str = "copy_document_reference:";
+
+// Menu item ids and labels, in menu order
+pub const ITEMS: &[(&str, &str)] = &[
+  ("copy_document_reference:path", "Path"),
+  ("copy_document_reference:filename", "File Name"),
+  ("copy_document_reference:file-url", "File URL"),
+  ("copy_document_reference:markdown-link", "Markdown Link"),
+];
+
+// What to copy for a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReferenceKind {
+  // `/notes/Project Plan.md`
+  Path,
+  // `Project Plan.md`
+  Filename,
+  // `file:///notes/Project%20Plan.md`
+  FileUrl,
+  // `[Project plan](notes/Project%20Plan.md)`, relative to the workspace when in one
+  MarkdownLink,
+}
+
+// `[` and `]` would end the link text early
+fn escape_link_text(text: &str) -> String {
+  text.replace('[', "\\[").replace(']', "\\]")
+}
+
+// `path` relative to `workspace` with `/` separators, percent-encoded for a link, or None
+// if it's outside the workspace
+fn relative_link(path: &Path, workspace: &Path) -> Option<String> {
+  let relative = path.strip_prefix(workspace).ok()?;
+  let segments: Vec<String> = relative
+    .components()
+    .map(|component| match component {
+      Component::Normal(name) => Some(crate::percent_encode_path(
+        name.to_string_lossy().as_bytes(),
+      )),
+      _ => None,
+    })
+    .collect::<Option<_>>()?;
+  (!segments.is_empty()).then(|| segments.join("/"))
+}
+
+fn markdown_link(path: &Path, content: &str, workspace: Option<&Path>) -> String {
+  let title = first_heading(content)
+    .map(|heading| strip_inline_markdown(&heading))
+    .filter(|title| !title.trim().is_empty())
+    .or_else(|| {
+      path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+    })
+    .unwrap_or_default();
+  let target = workspace
+    .and_then(|workspace| relative_link(path, workspace))
+    .unwrap_or_else(|| crate::path_to_file_url(path));
+  format!("[{}]({})", escape_link_text(title.trim()), target)
+}
+
+// The text to copy for the document at `path`. `content` is only used for markdown links.
+fn reference_text(
+  path: &Path,
+  kind: ReferenceKind,
+  content: &str,
+  workspace: Option<&Path>,
+) -> String {
+  match kind {
+    ReferenceKind::Path => path.to_string_lossy().to_string(),
+    ReferenceKind::Filename => path
+      .file_name()
+      .map(|name| name.to_string_lossy().to_string())
+      .unwrap_or_default(),
+    ReferenceKind::FileUrl => crate::path_to_file_url(path),
+    ReferenceKind::MarkdownLink => markdown_link(path, content, workspace),
+  }
+}
+
+// Put a reference to the document at `path` on the clipboard and return it. `markdown` is
+// the editor's content, so a markdown link gets the unsaved title; without it the file is
+// read.
+#[tauri::command]
+pub async fn copy_document_reference(
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+  kind: ReferenceKind,
+  markdown: Option<String>,
+) -> CommandResult<String> {
+  let path = PathBuf::from(path);
+  if !path.is_absolute() {
+    return Err(CommandError::invalid_path(
+      &path,
+      "File path must be absolute",
+    ));
+  }
+  scope.check(&path)?;
+  let content = match (kind, markdown) {
+    (ReferenceKind::MarkdownLink, None) => crate::read_text_file(&path)?,
+    (_, markdown) => markdown.unwrap_or_default(),
+  };
+  let workspace = scope.0.lock().unwrap().workspace().map(Path::to_path_buf);
+  let text = reference_text(&path, kind, &content, workspace.as_deref());
+  Clipboard::new()
+    .and_then(|mut clipboard| clipboard.set_text(text.clone()))
+    .map_err(|e| CommandError::io("Failed to copy to the clipboard", e))?;
+  Ok(text)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use super::*;
+
+  const CONTENT: &str = "---\ntitle: x\n---\n# Plan for *Q3* [draft]\n\nBody";
+
+  #[test]
+  fn test_reference_kinds() {
+    let path = Path::new("/notes/work/Project Plan (v2).md");
+    let text = |kind| reference_text(path, kind, CONTENT, None);
+    assert_eq!(
+      text(ReferenceKind::Path),
+      "/notes/work/Project Plan (v2).md"
+    );
+    assert_eq!(text(ReferenceKind::Filename), "Project Plan (v2).md");
+    assert_eq!(
+      text(ReferenceKind::FileUrl),
+      "file:///notes/work/Project%20Plan%20%28v2%29.md"
+    );
+    assert_eq!(
+      text(ReferenceKind::MarkdownLink),
+      "[Plan for Q3 draft](file:///notes/work/Project%20Plan%20%28v2%29.md)"
+    );
+    // Brackets in a file name standing in for the title are escaped
+    assert_eq!(
+      reference_text(
+        Path::new("/notes/Plan [old].md"),
+        ReferenceKind::MarkdownLink,
+        "",
+        None
+      ),
+      "[Plan \\[old\\]](file:///notes/Plan%20%5Bold%5D.md)"
+    );
+  }
+
+  #[test]
+  fn test_markdown_link_is_relative_to_the_workspace() {
+    let path = Path::new("/notes/work/Project Plan.md");
+    let link = |workspace: &str, content| {
+      reference_text(
+        path,
+        ReferenceKind::MarkdownLink,
+        content,
+        Some(Path::new(workspace)),
+      )
+    };
+    assert_eq!(link("/notes", "# Plan"), "[Plan](work/Project%20Plan.md)");
+    // No heading: the file name stands in
+    assert_eq!(
+      link("/notes/work", "no heading"),
+      "[Project Plan](Project%20Plan.md)"
+    );
+    assert_eq!(
+      link("/elsewhere", "# Plan"),
+      "[Plan](file:///notes/work/Project%20Plan.md)"
+    );
+  }
+}
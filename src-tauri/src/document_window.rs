@@ -0,0 +1,68 @@
+use std::path::Path;
+use tauri::{AppHandle, Manager};
+
+use crate::error::{CommandError, CommandResult};
+
+const UNTITLED: &str = "Untitled";
+
+// Title for a window showing `path`. macOS shows the folder through the proxy icon's path
+// menu, so the title is just the file name there; elsewhere it's `name — folder`.
+fn window_title(path: Option<&Path>, with_folder: bool) -> String {
+  let Some(path) = path else {
+    return UNTITLED.to_string();
+  };
+  let name = path
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_else(|| path.to_string_lossy().to_string());
+  let folder = path
+    .parent()
+    .and_then(Path::file_name)
+    .map(|folder| folder.to_string_lossy().to_string());
+  match folder {
+    Some(folder) if with_folder => format!("{} — {}", name, folder),
+    _ => name,
+  }
+}
+
+// Associate a window with the file it shows: its title and, on macOS, the title bar's proxy
+// icon. `path` is None for a new unsaved document.
+#[tauri::command]
+pub async fn set_window_document(
+  app: AppHandle,
+  window_label: String,
+  path: Option<String>,
+) -> CommandResult<()> {
+  let window = app
+    .get_webview_window(&window_label)
+    .ok_or_else(|| CommandError::invalid_data(format!("No window named {}", window_label)))?;
+  let path = path.as_deref().map(Path::new);
+  let title = window_title(path, !cfg!(target_os = "macos"));
+  window
+    .set_title(&title)
+    .map_err(|e| CommandError::io("Failed to set window title", e))?;
+  #[cfg(target_os = "macos")]
+  crate::macos::set_represented_file(&window, path);
+  if let Err(e) = crate::open_with::update_menu(&app, path) {
+    log::error!("Failed to update the Open With menu: {}", e);
+  }
+  // The Window menu lists windows by title
+  if let Err(e) = crate::window_menu::update_menu(&app, None) {
+    log::error!("Failed to update the Window menu: {}", e);
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_window_title() {
+    let path = Path::new("/home/me/notes/todo.md");
+    assert_eq!(window_title(Some(path), true), "todo.md — notes");
+    assert_eq!(window_title(Some(path), false), "todo.md");
+    assert_eq!(window_title(Some(Path::new("/todo.md")), true), "todo.md");
+    assert_eq!(window_title(None, true), "Untitled");
+  }
+}
@@ -0,0 +1,332 @@
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::atomic_write::write_atomically;
+use crate::error::{CommandError, CommandResult};
+use crate::now_millis;
+use crate::settings::SettingsState;
+
+// Folder (in the app data dir) holding draft snapshots of unsaved documents
+const DRAFTS_DIR: &str = "drafts";
+
+// Lists the drafts in DRAFTS_DIR, least recently saved first
+const INDEX_FILE: &str = "index.json";
+
+// Total size of compressed drafts we keep. Saving past it evicts the drafts saved longest
+// ago; the draft just saved is always kept.
+const MAX_DRAFTS_BYTES: u64 = 20 * 1024 * 1024;
+
+// How often drafts are snapshotted while autosave is off; crash recovery never stops
+const DEFAULT_DRAFT_INTERVAL_MS: u64 = 3000;
+
+// Timers fire a little early or late; a snapshot this close to the interval still counts
+const INTERVAL_SLACK_MS: u64 = 1000;
+
+// Serializes index updates: autosave timers of several windows can fire at once
+static DRAFTS_LOCK: Mutex<()> = Mutex::new(());
+
+// One draft in the index
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct DraftEntry {
+  doc_id: String,
+  original_path: Option<String>,
+  // Milliseconds since the epoch
+  saved_at: u64,
+  // Compressed size on disk
+  size: u64,
+}
+
+// A draft as listed for the frontend
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DraftInfo {
+  pub doc_id: String,
+  pub original_path: Option<String>,
+  pub saved_at: u64,
+  // Modification time of the original file, if it still exists
+  pub original_mtime: Option<u64>,
+  // The draft is newer than what's on disk (or there's nothing on disk), so it's worth
+  // offering to recover
+  pub recoverable: bool,
+}
+
+// Drafts are named after a hash of the document id, so any id (a path, a UUID) gives a
+// safe, fixed-length file name
+fn draft_file_name(doc_id: &str) -> String {
+  let digest = Sha256::digest(doc_id.as_bytes());
+  let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+  format!("{}.md.gz", hex)
+}
+
+// A missing or unreadable index means no drafts; stray draft files are then overwritten
+// or evicted eventually
+fn load_index(dir: &Path) -> Vec<DraftEntry> {
+  std::fs::read(dir.join(INDEX_FILE))
+    .ok()
+    .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+    .unwrap_or_default()
+}
+
+fn save_index(dir: &Path, entries: &[DraftEntry]) -> CommandResult<()> {
+  let path = dir.join(INDEX_FILE);
+  let bytes = serde_json::to_vec_pretty(entries)
+    .map_err(|e| CommandError::io("Failed to serialize drafts", e))?;
+  write_atomically(&path, &bytes)
+    .map_err(|e| CommandError::from_io(&e, &path, "Failed to save drafts"))
+}
+
+// Compress and store `content` as the draft of `doc_id`, replacing its previous draft, then
+// evict old drafts until all of them together fit in `max_bytes`
+fn write_draft(
+  dir: &Path,
+  doc_id: &str,
+  content: &str,
+  original_path: Option<String>,
+  max_bytes: u64,
+) -> CommandResult<()> {
+  let _guard = DRAFTS_LOCK.lock().unwrap();
+  let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+  let compressed = encoder
+    .write_all(content.as_bytes())
+    .and_then(|_| encoder.finish())
+    .map_err(|e| CommandError::io("Failed to compress draft", e))?;
+
+  let path = dir.join(draft_file_name(doc_id));
+  write_atomically(&path, &compressed)
+    .map_err(|e| CommandError::from_io(&e, &path, "Failed to save draft"))?;
+
+  let mut entries = load_index(dir);
+  entries.retain(|entry| entry.doc_id != doc_id);
+  entries.push(DraftEntry {
+    doc_id: doc_id.to_string(),
+    original_path,
+    saved_at: now_millis(),
+    size: compressed.len() as u64,
+  });
+
+  // Evict from the front (least recently saved) until we're under the cap
+  let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+  while total > max_bytes && entries.len() > 1 {
+    let evicted = entries.remove(0);
+    total -= evicted.size;
+    let _ = std::fs::remove_file(dir.join(draft_file_name(&evicted.doc_id)));
+  }
+  save_index(dir, &entries)
+}
+
+// Whether `doc_id`'s draft was saved less than `interval_ms` ago, so a new snapshot can
+// wait
+fn saved_within(dir: &Path, doc_id: &str, interval_ms: u64, now: u64) -> bool {
+  load_index(dir)
+    .iter()
+    .find(|entry| entry.doc_id == doc_id)
+    .is_some_and(|entry| now.saturating_sub(entry.saved_at) + INTERVAL_SLACK_MS < interval_ms)
+}
+
+fn read_draft_from(dir: &Path, doc_id: &str) -> CommandResult<String> {
+  let path = dir.join(draft_file_name(doc_id));
+  let file = std::fs::File::open(&path)
+    .map_err(|e| CommandError::from_io(&e, &path, "Failed to read draft"))?;
+  let mut content = String::new();
+  GzDecoder::new(file)
+    .read_to_string(&mut content)
+    .map_err(|e| CommandError::invalid_data(format!("Failed to read draft: {}", e)))?;
+  Ok(content)
+}
+
+// Discarding a draft that doesn't exist is fine: a save discards whether or not an
+// autosave has happened yet
+fn discard_draft_in(dir: &Path, doc_id: &str) -> CommandResult<()> {
+  let _guard = DRAFTS_LOCK.lock().unwrap();
+  let mut entries = load_index(dir);
+  let before = entries.len();
+  entries.retain(|entry| entry.doc_id != doc_id);
+  let _ = std::fs::remove_file(dir.join(draft_file_name(doc_id)));
+  if entries.len() != before {
+    save_index(dir, &entries)?;
+  }
+  Ok(())
+}
+
+// Newest first. Entries whose draft file has disappeared are skipped.
+fn list_drafts_in(dir: &Path) -> Vec<DraftInfo> {
+  let mut drafts: Vec<DraftInfo> = load_index(dir)
+    .into_iter()
+    .filter(|entry| dir.join(draft_file_name(&entry.doc_id)).is_file())
+    .map(|entry| {
+      let original_mtime = entry
+        .original_path
+        .as_ref()
+        .and_then(|path| std::fs::metadata(path).ok())
+        .and_then(|metadata| crate::file_mtime_millis(&metadata));
+      DraftInfo {
+        recoverable: original_mtime.is_none_or(|mtime| entry.saved_at > mtime),
+        doc_id: entry.doc_id,
+        original_path: entry.original_path,
+        saved_at: entry.saved_at,
+        original_mtime,
+      }
+    })
+    .collect();
+  drafts.reverse();
+  drafts
+}
+
+fn drafts_dir(app: &AppHandle) -> CommandResult<PathBuf> {
+  app
+    .path()
+    .app_data_dir()
+    .map(|dir| dir.join(DRAFTS_DIR))
+    .map_err(|e| CommandError::io("Failed to resolve app data dir", e))
+}
+
+// Save a draft regardless of the autosave settings, e.g. for a tab session being saved
+pub(crate) fn store_draft(
+  app: &AppHandle,
+  doc_id: &str,
+  content: &str,
+  original_path: Option<String>,
+) -> CommandResult<()> {
+  write_draft(
+    &drafts_dir(app)?,
+    doc_id,
+    content,
+    original_path,
+    MAX_DRAFTS_BYTES,
+  )
+}
+
+// Whether `doc_id` still has a draft; evicted ones don't
+pub(crate) fn has_draft(app: &AppHandle, doc_id: &str) -> bool {
+  drafts_dir(app).is_ok_and(|dir| dir.join(draft_file_name(doc_id)).is_file())
+}
+
+// Snapshot the unsaved content of a document. `doc_id` must stay the same for the
+// document's lifetime (e.g. its path, or an id generated for an untitled document).
+// Does nothing when the last snapshot is more recent than the autosave interval, or than
+// DEFAULT_DRAFT_INTERVAL_MS while autosave is off in the editor settings.
+#[tauri::command]
+pub async fn save_draft(
+  app: AppHandle,
+  settings: tauri::State<'_, SettingsState>,
+  doc_id: String,
+  content: String,
+  original_path: Option<String>,
+) -> CommandResult<()> {
+  let editor = settings.0.lock().unwrap().editor.clone();
+  let dir = drafts_dir(&app)?;
+  let interval_ms = if editor.autosave_enabled {
+    u64::from(editor.autosave_interval_secs) * 1000
+  } else {
+    DEFAULT_DRAFT_INTERVAL_MS
+  };
+  if saved_within(&dir, &doc_id, interval_ms, now_millis()) {
+    return Ok(());
+  }
+  write_draft(&dir, &doc_id, &content, original_path, MAX_DRAFTS_BYTES)
+}
+
+#[tauri::command]
+pub async fn list_drafts(app: AppHandle) -> CommandResult<Vec<DraftInfo>> {
+  Ok(list_drafts_in(&drafts_dir(&app)?))
+}
+
+#[tauri::command]
+pub async fn read_draft(app: AppHandle, doc_id: String) -> CommandResult<String> {
+  read_draft_from(&drafts_dir(&app)?, &doc_id)
+}
+
+// Called after a clean save or when a document is closed, so its draft isn't offered for
+// recovery later
+#[tauri::command]
+pub async fn discard_draft(app: AppHandle, doc_id: String) -> CommandResult<()> {
+  discard_draft_in(&drafts_dir(&app)?, &doc_id)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_draft_round_trip_replaces_previous() {
+    let dir = TempDir::new().unwrap();
+    write_draft(dir.path(), "untitled-1", "first", None, u64::MAX).unwrap();
+    write_draft(dir.path(), "untitled-1", "# Second", None, u64::MAX).unwrap();
+
+    let drafts = list_drafts_in(dir.path());
+    assert_eq!(drafts.len(), 1);
+    assert!(drafts[0].recoverable);
+    assert_eq!(
+      read_draft_from(dir.path(), "untitled-1").unwrap(),
+      "# Second"
+    );
+
+    discard_draft_in(dir.path(), "untitled-1").unwrap();
+    assert!(list_drafts_in(dir.path()).is_empty());
+    assert_eq!(
+      read_draft_from(dir.path(), "untitled-1")
+        .unwrap_err()
+        .code(),
+      "not_found"
+    );
+    discard_draft_in(dir.path(), "untitled-1").unwrap();
+  }
+
+  #[test]
+  fn test_draft_older_than_original_is_not_recoverable() {
+    let dir = TempDir::new().unwrap();
+    let original = dir.path().join("notes.md");
+    let original_path = Some(original.to_string_lossy().to_string());
+
+    write_draft(dir.path(), "notes", "draft", original_path, u64::MAX).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    fs::write(&original, "saved elsewhere").unwrap();
+
+    let drafts = list_drafts_in(dir.path());
+    assert!(drafts[0].original_mtime.is_some());
+    assert!(!drafts[0].recoverable);
+  }
+
+  #[test]
+  fn test_oldest_drafts_are_evicted_over_the_cap() {
+    let dir = TempDir::new().unwrap();
+    let size = |id: &str| {
+      fs::metadata(dir.path().join(draft_file_name(id)))
+        .unwrap()
+        .len()
+    };
+    write_draft(dir.path(), "a", "first draft", None, u64::MAX).unwrap();
+    write_draft(dir.path(), "b", "second draft", None, u64::MAX).unwrap();
+    // Room for "b" and "c" but not "a" as well
+    let cap = size("a") + size("b");
+    write_draft(dir.path(), "c", "third draft", None, cap).unwrap();
+
+    let ids: Vec<String> = list_drafts_in(dir.path())
+      .into_iter()
+      .map(|draft| draft.doc_id)
+      .collect();
+    assert_eq!(ids, vec!["c", "b"]);
+    assert!(!dir.path().join(draft_file_name("a")).exists());
+  }
+
+  #[test]
+  fn test_saved_within_the_autosave_interval() {
+    let dir = TempDir::new().unwrap();
+    assert!(!saved_within(dir.path(), "a", 10_000, now_millis()));
+    write_draft(dir.path(), "a", "draft", None, u64::MAX).unwrap();
+    let saved_at = load_index(dir.path())[0].saved_at;
+
+    assert!(saved_within(dir.path(), "a", 10_000, saved_at + 2_000));
+    // A timer firing just short of the interval isn't held back
+    assert!(!saved_within(dir.path(), "a", 10_000, saved_at + 9_500));
+    assert!(!saved_within(dir.path(), "b", 10_000, saved_at));
+  }
+}
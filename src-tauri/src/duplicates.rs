@@ -0,0 +1,343 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tauri::AppHandle;
+
+use crate::access_scope::AccessScopeState;
+use crate::error::{CommandError, CommandResult};
+use crate::task_registry;
+use crate::wiki;
+use crate::workspace;
+
+// Files hashed between two progress reports. Smaller workspaces get none; they're done
+// before a progress bar would be worth showing.
+const PROGRESS_EVERY: usize = 50;
+
+// Files with the same content, or the same text once normalized
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateGroup {
+  // SHA-256 of the content (exact) or of the normalized text (near)
+  pub hash: String,
+  // Sorted
+  pub paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateReport {
+  // Byte-for-byte identical files
+  pub exact: Vec<DuplicateGroup>,
+  // Files that only differ in whitespace or frontmatter
+  pub near: Vec<DuplicateGroup>,
+  pub scanned: usize,
+}
+
+fn to_hex(digest: &[u8]) -> String {
+  digest.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// The text of a line with whitespace runs collapsed to single spaces; empty for blank lines
+fn normalize_line(line: &[u8]) -> Vec<u8> {
+  line
+    .split(u8::is_ascii_whitespace)
+    .filter(|word| !word.is_empty())
+    .collect::<Vec<_>>()
+    .join(&b' ')
+}
+
+// Hash a file's text with its frontmatter, blank lines and whitespace differences left out,
+// and (when `exact`) its bytes as they are. Reads a line at a time, so a large file never
+// sits in memory whole.
+fn hash_reader(reader: impl Read, exact: bool) -> io::Result<(Option<String>, String)> {
+  let mut reader = BufReader::new(reader);
+  let mut exact_hasher = exact.then(Sha256::new);
+  let mut text_hasher = Sha256::new();
+  let mut line = Vec::new();
+  let mut first = true;
+  let mut in_frontmatter = false;
+  loop {
+    line.clear();
+    if reader.read_until(b'\n', &mut line)? == 0 {
+      break;
+    }
+    if let Some(hasher) = exact_hasher.as_mut() {
+      hasher.update(&line);
+    }
+    let normalized = normalize_line(&line);
+    // A UTF-8 byte order mark doesn't make a file different either
+    let text = if first {
+      normalized
+        .strip_prefix(b"\xEF\xBB\xBF")
+        .unwrap_or(&normalized)
+    } else {
+      &normalized
+    };
+    if first && text == b"---" {
+      in_frontmatter = true;
+    } else if in_frontmatter {
+      in_frontmatter = !(text == b"---" || text == b"...");
+    } else if !text.is_empty() {
+      text_hasher.update(text);
+      text_hasher.update(b"\n");
+    }
+    first = false;
+  }
+  Ok((
+    exact_hasher.map(|hasher| to_hex(&hasher.finalize())),
+    to_hex(&text_hasher.finalize()),
+  ))
+}
+
+// Groups of two or more paths sharing a key, largest first, then by first path
+fn groups<K: Eq + std::hash::Hash>(
+  keyed: impl IntoIterator<Item = (K, String, PathBuf)>,
+) -> Vec<DuplicateGroup> {
+  let mut by_key: HashMap<K, (String, Vec<String>)> = HashMap::new();
+  for (key, hash, path) in keyed {
+    by_key
+      .entry(key)
+      .or_insert_with(|| (hash, Vec::new()))
+      .1
+      .push(path.to_string_lossy().to_string());
+  }
+  let mut groups: Vec<DuplicateGroup> = by_key
+    .into_values()
+    .filter(|(_, paths)| paths.len() > 1)
+    .map(|(hash, mut paths)| {
+      paths.sort();
+      DuplicateGroup { hash, paths }
+    })
+    .collect();
+  groups.sort_by(|a, b| {
+    b.paths
+      .len()
+      .cmp(&a.paths.len())
+      .then_with(|| a.paths.cmp(&b.paths))
+  });
+  groups
+}
+
+// A file's hashes as found by the scan
+struct Hashed {
+  path: PathBuf,
+  size: u64,
+  // Only computed for files whose size another file shares
+  exact: Option<String>,
+  text: String,
+}
+
+// Find identical and near-identical files among `paths`. Only files of the same size can
+// be identical, so only those get a full content hash. `on_hashed` is called after each
+// file with the number hashed so far; once `is_cancelled` says so, the rest are skipped and
+// the scan fails with Cancelled.
+fn find_duplicates(
+  paths: Vec<PathBuf>,
+  is_cancelled: impl Fn() -> bool + Sync,
+  on_hashed: impl Fn(usize) + Sync,
+) -> CommandResult<DuplicateReport> {
+  let sized: Vec<(PathBuf, u64)> = paths
+    .into_iter()
+    .filter_map(|path| {
+      let size = std::fs::metadata(&path).ok()?.len();
+      Some((path, size))
+    })
+    .collect();
+  let mut size_counts: HashMap<u64, usize> = HashMap::new();
+  for (_, size) in &sized {
+    *size_counts.entry(*size).or_insert(0) += 1;
+  }
+
+  let hashed_count = AtomicUsize::new(0);
+  let hashed: Vec<Hashed> = sized
+    .into_par_iter()
+    .filter_map(|(path, size)| {
+      if is_cancelled() {
+        return None;
+      }
+      let exact = size_counts[&size] > 1;
+      // Unreadable files (removed mid-scan, no permission) are left out
+      let result = File::open(&path).and_then(|file| hash_reader(file, exact));
+      on_hashed(hashed_count.fetch_add(1, Ordering::SeqCst) + 1);
+      let (exact, text) = result.ok()?;
+      Some(Hashed {
+        path,
+        size,
+        exact,
+        text,
+      })
+    })
+    .collect();
+  if is_cancelled() {
+    return Err(CommandError::Cancelled);
+  }
+
+  let exact = groups(hashed.iter().filter_map(|file| {
+    let hash = file.exact.clone()?;
+    Some(((file.size, hash.clone()), hash, file.path.clone()))
+  }));
+  // A near-duplicate group is only news when its files aren't all one exact group
+  let near = groups(
+    hashed
+      .iter()
+      .map(|file| (file.text.clone(), file.text.clone(), file.path.clone())),
+  )
+  .into_iter()
+  .filter(|group| !exact.iter().any(|e| e.paths == group.paths))
+  .collect();
+
+  Ok(DuplicateReport {
+    exact,
+    near,
+    scanned: hashed.len(),
+  })
+}
+
+// SHA-256 of a file's content, as hex
+#[tauri::command]
+pub async fn hash_file(
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+) -> CommandResult<String> {
+  let path = PathBuf::from(path);
+  scope.check(&path)?;
+  let file =
+    File::open(&path).map_err(|e| CommandError::from_io(&e, &path, "Failed to open file"))?;
+  let (exact, _) =
+    hash_reader(file, true).map_err(|e| CommandError::from_io(&e, &path, "Failed to read file"))?;
+  Ok(exact.unwrap_or_default())
+}
+
+// Find markdown files under `root` with the same content, and files that only differ in
+// whitespace or frontmatter. Hashes in parallel as a registered task (see task_registry),
+// so it reports progress and can be cancelled.
+#[tauri::command]
+pub async fn find_duplicate_files(
+  app: AppHandle,
+  scope: tauri::State<'_, AccessScopeState>,
+  root: String,
+  task_id: Option<String>,
+) -> CommandResult<DuplicateReport> {
+  let root = workspace::checked_root(&scope.0.lock().unwrap(), root)?;
+  let guard = task_registry::start_task(
+    &app,
+    task_id,
+    "find_duplicate_files",
+    "Finding duplicate files",
+  )?;
+  let task = guard.task();
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    let paths: Vec<PathBuf> = wiki::walk_notes(&root)
+      .into_iter()
+      .map(|note| note.path)
+      .collect();
+    let total = paths.len();
+    find_duplicates(
+      paths,
+      || task.is_cancelled(),
+      |hashed| {
+        if total >= PROGRESS_EVERY && (hashed % PROGRESS_EVERY == 0 || hashed == total) {
+          task.progress(hashed, total);
+        }
+      },
+    )
+  })
+  .await
+  .map_err(|e| CommandError::io("Duplicate scan failed", e))
+  .and_then(|result| result);
+  guard.finish(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use std::sync::Mutex;
+  use tempfile::TempDir;
+
+  fn text_hash(content: &str) -> String {
+    hash_reader(content.as_bytes(), false).unwrap().1
+  }
+
+  #[test]
+  fn test_exact_hash_is_sha256_of_the_content() {
+    let (exact, _) = hash_reader("abc".as_bytes(), true).unwrap();
+    assert_eq!(
+      exact.unwrap(),
+      "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+  }
+
+  #[test]
+  fn test_text_hash_ignores_whitespace_and_frontmatter() {
+    let plain = text_hash("# Title\n\nSome   text here.\n");
+    assert_eq!(text_hash("# Title\r\n\r\n\r\n  Some text\there.  "), plain);
+    assert_eq!(
+      text_hash("---\ntags: [a]\n---\n# Title\nSome text here.\n"),
+      plain
+    );
+    assert_ne!(text_hash("# Title\n\nSome other text.\n"), plain);
+    // Line breaks still count: a reflowed paragraph is a different text
+    assert_ne!(text_hash("# Title\n\nSome text\nhere.\n"), plain);
+  }
+
+  #[test]
+  fn test_finds_exact_and_near_duplicates() {
+    let dir = TempDir::new().unwrap();
+    let write = |name: &str, content: &str| {
+      let path = dir.path().join(name);
+      fs::create_dir_all(path.parent().unwrap()).unwrap();
+      fs::write(&path, content).unwrap();
+      path
+    };
+    let paths = vec![
+      write("a.md", "# Note\n\nSame.\n"),
+      write("copies/a.md", "# Note\n\nSame.\n"),
+      write("b.md", "# Note\n\nSame.  \n"),
+      write("c.md", "# Other\n"),
+      write("d.md", "# Note\n\nSome.\n"),
+    ];
+
+    let progress = Mutex::new(Vec::new());
+    let report = find_duplicates(
+      paths.clone(),
+      || false,
+      |hashed| progress.lock().unwrap().push(hashed),
+    )
+    .unwrap();
+    let display = |path: &PathBuf| path.to_string_lossy().to_string();
+    assert_eq!(report.scanned, 5);
+    assert_eq!(report.exact.len(), 1);
+    assert_eq!(
+      report.exact[0].paths,
+      vec![display(&paths[0]), display(&paths[1])]
+    );
+    assert_eq!(report.near.len(), 1);
+    assert_eq!(
+      report.near[0].paths,
+      vec![display(&paths[0]), display(&paths[2]), display(&paths[1])]
+    );
+    let mut progress = progress.into_inner().unwrap();
+    progress.sort_unstable();
+    assert_eq!(progress, vec![1, 2, 3, 4, 5]);
+  }
+
+  #[test]
+  fn test_cancelled_scan_returns_cancelled() {
+    let dir = TempDir::new().unwrap();
+    let paths: Vec<PathBuf> = (0..3)
+      .map(|i| {
+        let path = dir.path().join(format!("{}.md", i));
+        fs::write(&path, "# Same\n").unwrap();
+        path
+      })
+      .collect();
+    let hashed = Mutex::new(0);
+    let error = find_duplicates(paths, || true, |_| *hashed.lock().unwrap() += 1).unwrap_err();
+    assert_eq!(error.code(), "cancelled");
+    assert_eq!(*hashed.lock().unwrap(), 0);
+  }
+}
@@ -0,0 +1,52 @@
+use tauri::menu::{MenuItem, MenuItemKind, Submenu};
+use tauri::{AppHandle, Wry};
+
+// A submenu of the menu bar by id, whether it's a top-level menu (Window) or inside one
+// (File > Open With)
+fn find_submenu(app: &AppHandle, id: &str) -> Option<Submenu<Wry>> {
+  app.menu()?.items().ok()?.into_iter().find_map(|item| {
+    let MenuItemKind::Submenu(menu) = item else {
+      return None;
+    };
+    if menu.id().0 == id {
+      return Some(menu);
+    }
+    match menu.get(id)? {
+      MenuItemKind::Submenu(submenu) => Some(submenu),
+      _ => None,
+    }
+  })
+}
+
+// Empty the submenu `id` and let `fill` add its items again. Does nothing before the menu
+// bar is set up, or on platforms without one.
+pub fn rebuild_submenu(
+  app: &AppHandle,
+  id: &str,
+  fill: impl FnOnce(&Submenu<Wry>) -> tauri::Result<()>,
+) -> tauri::Result<()> {
+  let Some(submenu) = find_submenu(app, id) else {
+    return Ok(());
+  };
+  for item in submenu.items()? {
+    submenu.remove(&item)?;
+  }
+  fill(&submenu)
+}
+
+// Every plain item of the menu bar, including those in submenus
+pub fn menu_items(app: &AppHandle) -> tauri::Result<Vec<MenuItem<Wry>>> {
+  let Some(menu) = app.menu() else {
+    return Ok(Vec::new());
+  };
+  let mut items = Vec::new();
+  let mut pending = menu.items()?;
+  while let Some(item) = pending.pop() {
+    match item {
+      MenuItemKind::MenuItem(item) => items.push(item),
+      MenuItemKind::Submenu(submenu) => pending.extend(submenu.items()?),
+      _ => {}
+    }
+  }
+  Ok(items)
+}
@@ -0,0 +1,364 @@
+use arboard::Clipboard;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::assets::{mime_type_for, resolve_reference};
+use crate::error::{CommandError, CommandResult};
+use crate::export::{html_references, inline_html_references};
+use crate::print_layout::find_ignore_case;
+
+// Images up to this size are embedded; mail servers bounce or strip much bigger messages
+const MAX_EMAIL_IMAGE_BYTES: u64 = 512 * 1024;
+
+// Elements dropped with everything in them. Mail clients strip them anyway, and scripts
+// must never ride along in a pasted message.
+const DROPPED_ELEMENTS: &[&str] = &["script", "style", "noscript", "iframe", "object"];
+
+const MONOSPACE: &str = "'SF Mono', Monaco, Inconsolata, 'Fira Code', monospace";
+
+// The preview's styles (see `.markdown-preview` in App.css), written onto each element
+// since mail clients ignore stylesheets
+const ELEMENT_STYLES: &[(&str, &str)] = &[
+  (
+    "h1",
+    "font-size: 28px; font-weight: 600; margin: 0 0 16px; padding-bottom: 8px; border-bottom: 1px solid #e0e0e0; color: #1a1a1a;",
+  ),
+  ("h2", "font-size: 22px; font-weight: 600; margin: 24px 0 12px; color: #1a1a1a;"),
+  ("h3", "font-size: 18px; font-weight: 600; margin: 20px 0 10px; color: #1a1a1a;"),
+  ("h4", "font-size: 16px; font-weight: 600; margin: 16px 0 8px; color: #1a1a1a;"),
+  ("h5", "font-size: 14px; font-weight: 600; margin: 16px 0 8px; color: #1a1a1a;"),
+  ("h6", "font-size: 13px; font-weight: 600; margin: 16px 0 8px; color: #666;"),
+  ("p", "margin: 0 0 12px;"),
+  ("ul", "margin: 0 0 12px; padding-left: 24px;"),
+  ("ol", "margin: 0 0 12px; padding-left: 24px;"),
+  ("li", "margin-bottom: 4px;"),
+  (
+    "blockquote",
+    "border-left: 4px solid #2563eb; padding-left: 16px; margin: 16px 0; color: #666; font-style: italic;",
+  ),
+  ("a", "color: #2563eb; text-decoration: none;"),
+  ("hr", "border: none; border-top: 1px solid #e0e0e0; margin: 24px 0;"),
+  ("img", "max-width: 100%; height: auto; border-radius: 4px;"),
+  ("table", "border-collapse: collapse; margin: 16px 0;"),
+  (
+    "th",
+    "border: 1px solid #e0e0e0; padding: 8px 12px; text-align: left; background-color: #f9fafb; font-weight: 600;",
+  ),
+  ("td", "border: 1px solid #e0e0e0; padding: 8px 12px; text-align: left;"),
+];
+
+// Code in running text; code inside <pre> gets PRE_CODE_STYLE instead
+const CODE_STYLE: &str = "background-color: #f3f4f6; padding: 2px 6px; border-radius: 3px; font-size: 13px; color: #e11d48;";
+const PRE_CODE_STYLE: &str =
+  "background-color: transparent; color: #24292f; padding: 0; font-size: 13px; line-height: 1.5;";
+// Outlook ignores overflow, so long lines wrap instead of running off the message
+const PRE_STYLE: &str = "background-color: #f6f8fa; padding: 16px; border-radius: 6px; margin: 16px 0; border: 1px solid #d0d7de; white-space: pre-wrap;";
+
+// Wraps the whole snippet, standing in for the preview's own font settings
+const CONTAINER_STYLE: &str = "font-size: 14px; line-height: 1.6; color: #333;";
+
+// What copy_for_email put on the clipboard
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmailCopyResult {
+  // Local images too big to embed (or unreadable), which recipients won't see
+  pub skipped_images: Vec<String>,
+}
+
+fn tag_name(tag: &str) -> String {
+  tag
+    .trim_start_matches(['<', '/'])
+    .chars()
+    .take_while(|c| c.is_ascii_alphanumeric())
+    .collect::<String>()
+    .to_ascii_lowercase()
+}
+
+fn element_style(name: &str, in_pre: bool) -> Option<String> {
+  let style = match name {
+    "code" if in_pre => PRE_CODE_STYLE,
+    "code" => CODE_STYLE,
+    "pre" => PRE_STYLE,
+    _ => ELEMENT_STYLES
+      .iter()
+      .find(|(element, _)| *element == name)
+      .map(|(_, style)| *style)?,
+  };
+  let style = match name {
+    "code" | "pre" => format!("font-family: {}; {}", MONOSPACE, style),
+    _ => style.to_string(),
+  };
+  Some(style)
+}
+
+// `tag` (a whole opening tag) with `style` added. A style the element already has (e.g.
+// a table cell's alignment) comes last, so it wins.
+fn with_style(tag: &str, style: &str) -> String {
+  if let Some(index) = find_ignore_case(tag, " style=", 0) {
+    let value_start = index + " style=".len();
+    if let Some(quote) = tag[value_start..]
+      .chars()
+      .next()
+      .filter(|c| *c == '"' || *c == '\'')
+    {
+      return format!(
+        "{}{}{} {}",
+        &tag[..value_start],
+        quote,
+        style,
+        &tag[value_start + 1..]
+      );
+    }
+  }
+  let (body, end) = match tag.strip_suffix("/>") {
+    Some(body) => (body.trim_end(), " />"),
+    None => (&tag[..tag.len() - 1], ">"),
+  };
+  format!("{} style=\"{}\"{}", body, style, end)
+}
+
+// Rendered HTML with every element styled inline, and scripts, stylesheets and comments
+// removed. Assumes the well-formed markup the markdown renderer produces.
+fn inline_styles(html: &str) -> String {
+  let mut styled = String::with_capacity(html.len() * 2);
+  let mut rest = html;
+  let mut pre_depth = 0usize;
+  while let Some(open) = rest.find('<') {
+    styled.push_str(&rest[..open]);
+    rest = &rest[open..];
+    if rest.starts_with("<!--") {
+      rest = rest.find("-->").map(|end| &rest[end + 3..]).unwrap_or("");
+      continue;
+    }
+    let Some(close) = rest.find('>') else {
+      break;
+    };
+    let tag = &rest[..=close];
+    rest = &rest[close + 1..];
+    let name = tag_name(tag);
+    let closing = tag.starts_with("</");
+
+    if !closing && DROPPED_ELEMENTS.contains(&name.as_str()) {
+      rest = find_ignore_case(rest, &format!("</{}", name), 0)
+        .and_then(|end| rest[end..].find('>').map(|close| &rest[end + close + 1..]))
+        .unwrap_or("");
+      continue;
+    }
+    if name == "pre" {
+      if closing {
+        pre_depth = pre_depth.saturating_sub(1);
+      } else {
+        pre_depth += 1;
+      }
+    }
+    match element_style(&name, pre_depth > 0).filter(|_| !closing) {
+      Some(style) => styled.push_str(&with_style(tag, &style)),
+      None => styled.push_str(tag),
+    }
+  }
+  styled.push_str(rest);
+  format!("<div style=\"{}\">{}</div>", CONTAINER_STYLE, styled)
+}
+
+// The text of rendered HTML, for pasting where HTML isn't accepted: blocks on lines of
+// their own, table cells separated by tabs
+fn html_to_text(html: &str) -> String {
+  let mut text = String::with_capacity(html.len());
+  let mut rest = html;
+  while let Some(open) = rest.find('<') {
+    // Line breaks between tags are markup layout, not text
+    let between = &rest[..open];
+    if !(between.trim().is_empty() && between.contains('\n')) {
+      text.push_str(between);
+    }
+    let close = rest[open..]
+      .find('>')
+      .map(|i| open + i)
+      .unwrap_or(rest.len() - 1);
+    let tag = &rest[open..=close];
+    rest = &rest[close + 1..];
+    let name = tag_name(tag);
+    let closing = tag.starts_with("</");
+    if !closing && DROPPED_ELEMENTS.contains(&name.as_str()) {
+      rest = find_ignore_case(rest, &format!("</{}", name), 0)
+        .and_then(|end| rest[end..].find('>').map(|close| &rest[end + close + 1..]))
+        .unwrap_or("");
+      continue;
+    }
+    match name.as_str() {
+      "br" => {
+        text.push('\n');
+        rest = rest.strip_prefix('\n').unwrap_or(rest);
+      }
+      "hr" => text.push_str("\n\n"),
+      "td" | "th" if closing => text.push('\t'),
+      "li" | "tr" | "div" if closing => text.push('\n'),
+      "p" | "pre" | "blockquote" | "table" | "ul" | "ol" | "h1" | "h2" | "h3" | "h4" | "h5"
+      | "h6"
+        if closing =>
+      {
+        text.push_str("\n\n")
+      }
+      _ => {}
+    }
+  }
+  text.push_str(rest);
+  let text = text
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&quot;", "\"")
+    .replace("&#39;", "'")
+    .replace("&nbsp;", " ")
+    .replace("&amp;", "&");
+
+  // No trailing tabs, and at most one blank line in a row
+  let mut lines: Vec<&str> = Vec::new();
+  for line in text.lines().map(str::trim_end) {
+    if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+      continue;
+    }
+    lines.push(line);
+  }
+  lines.join("\n").trim().to_string()
+}
+
+// Local images in the access scope still referenced by `html`, i.e. the ones that couldn't
+// be embedded
+fn local_images(scope: &AccessScope, html: &str, document: &Path) -> Vec<String> {
+  let mut images: Vec<String> = html_references(html)
+    .into_iter()
+    .filter_map(|(_, value)| resolve_reference(document, &value))
+    .filter(|path| scope.is_allowed(path))
+    .filter(|path| path.is_file() && mime_type_for(path).starts_with("image/"))
+    .map(|path| path.to_string_lossy().to_string())
+    .collect();
+  images.sort();
+  images.dedup();
+  images
+}
+
+// The message HTML for `html`: images up to `max_image_bytes` embedded as data URLs, all
+// styles inline. Also returns the local images left out. Only images inside the access
+// scope are read.
+fn email_html(
+  scope: &AccessScope,
+  html: &str,
+  document: Option<&Path>,
+  max_image_bytes: u64,
+) -> CommandResult<(String, Vec<String>)> {
+  let styled = inline_styles(html);
+  match document {
+    Some(document) => {
+      scope.check(document)?;
+      let embedded = inline_html_references(scope, &styled, document, max_image_bytes);
+      let skipped = local_images(scope, &embedded, document);
+      Ok((embedded, skipped))
+    }
+    // An untitled document has no folder to find relative images in
+    None => Ok((styled, Vec::new())),
+  }
+}
+
+// Put the rendered document on the clipboard ready to paste into an email: HTML with
+// inline styles and embedded images, and its text for clients that only take plain text
+#[tauri::command]
+pub async fn copy_for_email(
+  scope: tauri::State<'_, AccessScopeState>,
+  html_content: String,
+  document_path: Option<String>,
+) -> CommandResult<EmailCopyResult> {
+  let document = document_path.map(PathBuf::from);
+  let (html, skipped_images) = email_html(
+    &scope.0.lock().unwrap(),
+    &html_content,
+    document.as_deref(),
+    MAX_EMAIL_IMAGE_BYTES,
+  )?;
+  let text = html_to_text(&html_content);
+  Clipboard::new()
+    .and_then(|mut clipboard| clipboard.set_html(html, Some(text)))
+    .map_err(|e| CommandError::io("Failed to copy to the clipboard", e))?;
+  Ok(EmailCopyResult { skipped_images })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_styles_tables_and_code_inline() {
+    let html = "<table>\n<thead><tr><th style=\"text-align: right\">Qty</th></tr></thead>\n\
+                <tbody><tr><td>1</td></tr></tbody>\n</table>\n\
+                <p>Run <code>make</code></p>\n<pre><code class=\"language-sh\">a &lt; b\n</code></pre>\n";
+    let styled = inline_styles(html);
+    assert!(styled.starts_with("<div style=\"font-size: 14px;"));
+    assert!(styled.contains("<table style=\"border-collapse: collapse;"));
+    // The cell's own alignment comes after ours
+    assert!(styled.contains("font-weight: 600; text-align: right\">Qty</th>"));
+    assert!(styled.contains("<td style=\"border: 1px solid #e0e0e0;"));
+    assert!(styled.contains("<code style=\"font-family: 'SF Mono'"));
+    assert!(styled.contains("color: #e11d48;\">make</code>"));
+    assert!(styled.contains("white-space: pre-wrap;\"><code class=\"language-sh\" style="));
+    assert!(
+      styled.contains("padding: 0; font-size: 13px; line-height: 1.5;\">a &lt; b\n</code></pre>")
+    );
+    assert!(styled.contains("</tr></thead>"));
+  }
+
+  #[test]
+  fn test_strips_scripts_and_comments() {
+    let html = "<p>a</p><script type=\"module\">alert('<p>x</p>')</script><!-- note --><STYLE>p{}</STYLE><p>b</p>";
+    let styled = inline_styles(html);
+    assert!(!styled.contains("alert"));
+    assert!(!styled.contains("note"));
+    assert!(!styled.contains("p{}"));
+    assert_eq!(styled.matches("<p style=").count(), 2);
+    assert_eq!(html_to_text(html), "a\n\nb");
+  }
+
+  #[test]
+  fn test_plain_text_fallback() {
+    let html = "<h1>Title</h1>\n<p>Fish &amp; chips<br />\nnext</p>\n<ul>\n<li>one</li>\n<li>two</li>\n</ul>\n\
+                <table><tr><th>A</th><th>B</th></tr><tr><td>1</td><td>2</td></tr></table>\n";
+    assert_eq!(
+      html_to_text(html),
+      "Title\n\nFish & chips\nnext\n\none\ntwo\n\nA\tB\n1\t2"
+    );
+  }
+
+  #[test]
+  fn test_embeds_small_images_only() {
+    let dir = TempDir::new().unwrap();
+    let document = dir.path().join("note.md");
+    fs::write(dir.path().join("small.png"), [0u8; 16]).unwrap();
+    fs::write(dir.path().join("large.png"), [0u8; 64]).unwrap();
+    let html = "<p><img src=\"small.png\" alt=\"s\" /><img src=\"large.png\" alt=\"l\" /></p>";
+
+    let scope = AccessScope::with_folders(&[dir.path()]);
+    let (email, skipped) = email_html(&scope, html, Some(&document), 32).unwrap();
+    assert!(email.contains("<img src=\"data:image/png;base64,"));
+    assert!(email.contains("<img src=\"large.png\" alt=\"l\" style=\"max-width: 100%;"));
+    assert_eq!(
+      skipped,
+      vec![dir.path().join("large.png").to_string_lossy().to_string()]
+    );
+  }
+
+  #[test]
+  fn test_images_outside_the_scope_are_not_embedded() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    fs::create_dir(&notes).unwrap();
+    fs::write(dir.path().join("secret.png"), [0u8; 16]).unwrap();
+    let html = "<img src=\"../secret.png\" />";
+    let scope = AccessScope::with_folders(&[&notes]);
+
+    let (email, skipped) = email_html(&scope, html, Some(&notes.join("note.md")), 32).unwrap();
+    assert!(!email.contains("data:"));
+    assert!(skipped.is_empty());
+    let error = email_html(&scope, html, Some(&dir.path().join("note.md")), 32).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+  }
+}
@@ -0,0 +1,392 @@
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde_json::{json, Value};
+use std::fmt;
+use std::io;
+use std::path::Path;
+
+// Error returned by every command. It serializes to `{code, message, details}` so the
+// frontend can branch on `code` (e.g. offer "Choose another location" on permission_denied)
+// while `message` stays human-readable for toasts and logs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+  NotFound {
+    path: String,
+  },
+  PermissionDenied {
+    path: String,
+  },
+  // The file is marked read-only; make_writable can change that if the user asks
+  ReadOnly {
+    path: String,
+  },
+  // make_writable was asked to change a file owned by someone else
+  NotOwner {
+    path: String,
+  },
+  // A directory (or other non-file) where a file was expected
+  NotAFile {
+    path: String,
+  },
+  // Writing would replace a file the caller didn't say could be replaced
+  AlreadyExists {
+    path: String,
+  },
+  // The path is outside every folder the user opened or approved; request_scope asks them
+  ScopeDenied {
+    path: String,
+  },
+  TooLarge {
+    limit: u64,
+    actual: u64,
+  },
+  InvalidPath {
+    path: String,
+    reason: String,
+  },
+  // A picked location that isn't a local file, e.g. a content:// URI from a file dialog
+  UnsupportedLocation {
+    uri: String,
+  },
+  // A link target that can't be used, e.g. a data: URL
+  InvalidUrl {
+    url: String,
+    reason: String,
+  },
+  // The file changed on disk after the caller last saw it (milliseconds since the epoch)
+  Conflict {
+    disk_mtime: u64,
+  },
+  // Input that can't be used, e.g. a file that isn't UTF-8 or an unrecognized export file
+  InvalidData {
+    message: String,
+  },
+  // A file opened as text that looks like an image, archive etc. `mime` is the best guess
+  // at what it actually is.
+  BinaryFile {
+    path: String,
+    mime: String,
+  },
+  // The file was written but the disk didn't confirm it persisted it (durable saves only).
+  // The save may not survive the drive going away, so it must not be reported as done.
+  SyncFailed {
+    path: String,
+    reason: String,
+  },
+  // A document includes itself, directly or through other includes. `chain` runs from the
+  // document being resolved to the repeated file.
+  IncludeCycle {
+    chain: Vec<String>,
+  },
+  // The OS keychain can't be reached (e.g. no Secret Service daemon on Linux), so secrets
+  // can't be stored or read
+  KeyringUnavailable {
+    reason: String,
+  },
+  // The system's text-to-speech engine can't be used (e.g. speech-dispatcher isn't
+  // running on Linux)
+  SpeechUnavailable {
+    reason: String,
+  },
+  // A web service turned down our credentials (none saved, expired, or missing a scope)
+  AuthFailed {
+    service: String,
+    reason: String,
+  },
+  // A web service's rate limit is used up. `retry_at` (milliseconds since the epoch) is when
+  // it resets, if the service said.
+  RateLimited {
+    service: String,
+    retry_at: Option<u64>,
+  },
+  // Settings that can't be saved, one error per offending field (e.g. `editor.font_size`)
+  InvalidSettings {
+    errors: Vec<FieldError>,
+  },
+  // An external program (e.g. a terminal) couldn't be started or exited with an error
+  LaunchFailed {
+    program: String,
+    reason: String,
+  },
+  // A long-running command stopped early because cancel_task asked it to
+  Cancelled,
+  // An export that stopped (for `cause`) after writing part of its output. The partial
+  // files, listed in `cleaned_up`, were removed. Has the code of its cause.
+  ExportAborted {
+    cause: Box<CommandError>,
+    cleaned_up: Vec<String>,
+  },
+  // A request that timed out, couldn't connect, or got an unexpected error status
+  Network {
+    message: String,
+  },
+  Io {
+    message: String,
+  },
+}
+
+// Why one settings field was rejected
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct FieldError {
+  // Dotted path of the field, as in the settings JSON
+  pub field: String,
+  pub message: String,
+}
+
+pub type CommandResult<T> = Result<T, CommandError>;
+
+impl CommandError {
+  pub fn code(&self) -> &'static str {
+    match self {
+      CommandError::NotFound { .. } => "not_found",
+      CommandError::PermissionDenied { .. } => "permission_denied",
+      CommandError::ReadOnly { .. } => "read_only",
+      CommandError::NotOwner { .. } => "not_owner",
+      CommandError::NotAFile { .. } => "not_a_file",
+      CommandError::AlreadyExists { .. } => "already_exists",
+      CommandError::ScopeDenied { .. } => "scope_denied",
+      CommandError::TooLarge { .. } => "too_large",
+      CommandError::InvalidPath { .. } => "invalid_path",
+      CommandError::UnsupportedLocation { .. } => "unsupported_location",
+      CommandError::InvalidUrl { .. } => "invalid_url",
+      CommandError::Conflict { .. } => "conflict",
+      CommandError::InvalidData { .. } => "invalid_data",
+      CommandError::BinaryFile { .. } => "binary_file",
+      CommandError::SyncFailed { .. } => "sync_failed",
+      CommandError::IncludeCycle { .. } => "include_cycle",
+      CommandError::KeyringUnavailable { .. } => "keyring_unavailable",
+      CommandError::SpeechUnavailable { .. } => "speech_unavailable",
+      CommandError::AuthFailed { .. } => "auth_failed",
+      CommandError::RateLimited { .. } => "rate_limited",
+      CommandError::InvalidSettings { .. } => "invalid_settings",
+      CommandError::LaunchFailed { .. } => "launch_failed",
+      CommandError::Cancelled => "cancelled",
+      CommandError::ExportAborted { cause, .. } => cause.code(),
+      CommandError::Network { .. } => "network",
+      CommandError::Io { .. } => "io",
+    }
+  }
+
+  fn details(&self) -> Value {
+    match self {
+      CommandError::NotFound { path }
+      | CommandError::PermissionDenied { path }
+      | CommandError::ReadOnly { path }
+      | CommandError::NotOwner { path }
+      | CommandError::NotAFile { path }
+      | CommandError::AlreadyExists { path }
+      | CommandError::ScopeDenied { path } => json!({ "path": path }),
+      CommandError::TooLarge { limit, actual } => json!({ "limit": limit, "actual": actual }),
+      CommandError::InvalidPath { path, reason } | CommandError::SyncFailed { path, reason } => {
+        json!({ "path": path, "reason": reason })
+      }
+      CommandError::UnsupportedLocation { uri } => json!({ "uri": uri }),
+      CommandError::InvalidUrl { url, reason } => json!({ "url": url, "reason": reason }),
+      CommandError::Conflict { disk_mtime } => json!({ "disk_mtime": disk_mtime }),
+      CommandError::BinaryFile { path, mime } => json!({ "path": path, "mime": mime }),
+      CommandError::IncludeCycle { chain } => json!({ "chain": chain }),
+      CommandError::KeyringUnavailable { reason } | CommandError::SpeechUnavailable { reason } => {
+        json!({ "reason": reason })
+      }
+      CommandError::AuthFailed { service, reason } => {
+        json!({ "service": service, "reason": reason })
+      }
+      CommandError::RateLimited { service, retry_at } => {
+        json!({ "service": service, "retry_at": retry_at })
+      }
+      CommandError::InvalidSettings { errors } => json!({ "errors": errors }),
+      CommandError::LaunchFailed { program, reason } => {
+        json!({ "program": program, "reason": reason })
+      }
+      CommandError::ExportAborted { cause, cleaned_up } => match cause.details() {
+        Value::Object(mut details) => {
+          details.insert("cleaned_up".to_string(), json!(cleaned_up));
+          Value::Object(details)
+        }
+        _ => json!({ "cleaned_up": cleaned_up }),
+      },
+      CommandError::Cancelled
+      | CommandError::InvalidData { .. }
+      | CommandError::Network { .. }
+      | CommandError::Io { .. } => Value::Null,
+    }
+  }
+
+  // Cancelled, also when an aborted export's partial output was cleaned up
+  pub fn is_cancelled(&self) -> bool {
+    match self {
+      CommandError::Cancelled => true,
+      CommandError::ExportAborted { cause, .. } => cause.is_cancelled(),
+      _ => false,
+    }
+  }
+
+  pub fn invalid_path(path: &Path, reason: &str) -> Self {
+    CommandError::InvalidPath {
+      path: path.to_string_lossy().to_string(),
+      reason: reason.to_string(),
+    }
+  }
+
+  pub fn invalid_data(message: impl Into<String>) -> Self {
+    CommandError::InvalidData {
+      message: message.into(),
+    }
+  }
+
+  // A failure that isn't about a particular path, e.g. "Failed to open store: ..."
+  pub fn io(context: &str, error: impl fmt::Display) -> Self {
+    CommandError::Io {
+      message: format!("{}: {}", context, error),
+    }
+  }
+
+  // Map a filesystem error on `path` to the matching code, keeping `context` in the message
+  // of errors that don't have a code of their own
+  pub fn from_io(error: &io::Error, path: &Path, context: &str) -> Self {
+    let path_str = path.to_string_lossy().to_string();
+    match error.kind() {
+      io::ErrorKind::NotFound => CommandError::NotFound { path: path_str },
+      io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+        CommandError::PermissionDenied { path: path_str }
+      }
+      io::ErrorKind::IsADirectory => CommandError::NotAFile { path: path_str },
+      io::ErrorKind::InvalidData => CommandError::invalid_data(format!("{}: {}", context, error)),
+      io::ErrorKind::InvalidFilename => CommandError::invalid_path(path, &error.to_string()),
+      _ => CommandError::io(context, error),
+    }
+  }
+}
+
+impl fmt::Display for CommandError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CommandError::NotFound { path } => write!(f, "File does not exist: {}", path),
+      CommandError::PermissionDenied { path } => write!(f, "Permission denied: {}", path),
+      CommandError::ReadOnly { path } => write!(f, "File is read-only: {}", path),
+      CommandError::NotOwner { path } => {
+        write!(f, "Only the file's owner can make it writable: {}", path)
+      }
+      CommandError::NotAFile { path } => write!(f, "Path is not a file: {}", path),
+      CommandError::AlreadyExists { path } => write!(f, "File already exists: {}", path),
+      CommandError::ScopeDenied { path } => {
+        write!(f, "Access to this location was not approved: {}", path)
+      }
+      CommandError::TooLarge { limit, actual } => write!(
+        f,
+        "File is too large ({:.1}MB, max {}MB)",
+        *actual as f64 / (1024.0 * 1024.0),
+        limit / (1024 * 1024)
+      ),
+      CommandError::InvalidPath { path, reason } => write!(f, "{}: {}", reason, path),
+      CommandError::UnsupportedLocation { uri } => {
+        write!(f, "This location can't be opened as a file: {}", uri)
+      }
+      CommandError::InvalidUrl { url, reason } => write!(f, "{}: {}", reason, url),
+      CommandError::Conflict { .. } => write!(f, "File was changed on disk since it was opened"),
+      CommandError::BinaryFile { path, mime } => write!(
+        f,
+        "This looks like {}, not a text file: {}",
+        crate::binary::describe_mime(mime),
+        path
+      ),
+      CommandError::SyncFailed { path, reason } => {
+        write!(
+          f,
+          "Could not confirm the save reached the disk ({}): {}",
+          reason, path
+        )
+      }
+      CommandError::IncludeCycle { chain } => {
+        write!(f, "Document includes itself: {}", chain.join(" -> "))
+      }
+      CommandError::KeyringUnavailable { reason } => {
+        write!(f, "The system keychain is not available ({})", reason)
+      }
+      CommandError::SpeechUnavailable { reason } => {
+        write!(f, "Text to speech is not available ({})", reason)
+      }
+      CommandError::AuthFailed { service, reason } => {
+        write!(f, "{} authentication failed: {}", service, reason)
+      }
+      CommandError::RateLimited { service, .. } => {
+        write!(f, "{} rate limit reached, try again later", service)
+      }
+      CommandError::InvalidSettings { errors } => {
+        let messages: Vec<&str> = errors.iter().map(|e| e.message.as_str()).collect();
+        write!(f, "Invalid settings: {}", messages.join("; "))
+      }
+      CommandError::LaunchFailed { program, reason } => {
+        write!(f, "Could not start {}: {}", program, reason)
+      }
+      CommandError::Cancelled => write!(f, "Cancelled"),
+      CommandError::ExportAborted { cause, cleaned_up } => write!(
+        f,
+        "{} (removed {} partially written file{})",
+        cause,
+        cleaned_up.len(),
+        if cleaned_up.len() == 1 { "" } else { "s" }
+      ),
+      CommandError::InvalidData { message }
+      | CommandError::Network { message }
+      | CommandError::Io { message } => {
+        write!(f, "{}", message)
+      }
+    }
+  }
+}
+
+impl std::error::Error for CommandError {}
+
+impl Serialize for CommandError {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    let mut state = serializer.serialize_struct("CommandError", 3)?;
+    state.serialize_field("code", self.code())?;
+    state.serialize_field("message", &self.to_string())?;
+    state.serialize_field("details", &self.details())?;
+    state.end()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_serializes_code_message_and_details() {
+    let error = CommandError::TooLarge {
+      limit: 10 * 1024 * 1024,
+      actual: 11 * 1024 * 1024,
+    };
+    assert_eq!(
+      serde_json::to_value(&error).unwrap(),
+      json!({
+        "code": "too_large",
+        "message": "File is too large (11.0MB, max 10MB)",
+        "details": {"limit": 10485760, "actual": 11534336},
+      })
+    );
+
+    let error = CommandError::io("Failed to open store", "disk on fire");
+    assert_eq!(
+      serde_json::to_value(&error).unwrap(),
+      json!({"code": "io", "message": "Failed to open store: disk on fire", "details": null})
+    );
+  }
+
+  #[test]
+  fn test_maps_io_error_kinds() {
+    let path = Path::new("/notes/a.md");
+    let error = |kind| CommandError::from_io(&io::Error::from(kind), path, "Failed to read file");
+
+    assert_eq!(error(io::ErrorKind::NotFound).code(), "not_found");
+    assert_eq!(
+      error(io::ErrorKind::PermissionDenied).code(),
+      "permission_denied"
+    );
+    assert_eq!(error(io::ErrorKind::IsADirectory).code(), "not_a_file");
+    assert_eq!(error(io::ErrorKind::InvalidData).code(), "invalid_data");
+    let other = error(io::ErrorKind::TimedOut);
+    assert_eq!(other.code(), "io");
+    assert!(other.to_string().starts_with("Failed to read file: "));
+  }
+}
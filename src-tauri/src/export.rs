@@ -0,0 +1,1226 @@
+use base64::Engine;
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::assets::{mime_type_for, resolve_reference};
+use crate::atomic_write::write_atomically;
+use crate::error::{CommandError, CommandResult};
+use crate::export_defaults::{default_output_path, EffectiveExportOptions};
+use crate::filename::{
+  first_heading, sanitize_file_stem, strip_inline_markdown, FilenameSeparator,
+};
+use crate::print_layout;
+use crate::sections::{printable_section, section_text, SectionTarget};
+use crate::settings::{Palette, PrintOptions, SettingsState};
+use crate::staging::write_staged;
+use crate::styles;
+
+// Folder created next to an exported file to hold the copied assets
+pub(crate) const EXPORT_ASSETS_DIR: &str = "assets";
+
+// Larger images and media stay as references when inlining; a data URL this big would make
+// the page slow to open
+const MAX_INLINED_ASSET_BYTES: u64 = 20 * 1024 * 1024;
+
+// Markdown files linked from a document are other documents, not assets
+const DOCUMENT_EXTENSIONS: &[&str] = &["md", "markdown"];
+
+// A local file referenced by a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentAsset {
+  // The reference as written in the document
+  pub reference: String,
+  // Absolute path it resolves to
+  pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportOptions {
+  // Copy referenced local files into `assets/` next to the export and point references there
+  #[serde(default)]
+  pub copy_assets: bool,
+  // Rendered HTML of the part of the preview the user selected, exported instead of the
+  // whole document. Blank selections are ignored.
+  #[serde(default)]
+  pub selection_html: Option<String>,
+  // The look the app has when exporting, for the `match-app` theme
+  #[serde(default)]
+  pub app_theme: Palette,
+  // The document's options from get_export_defaults, used as they are
+  #[serde(default)]
+  pub defaults: Option<EffectiveExportOptions>,
+  // Write next to the document, named as `defaults` say, without asking where
+  // ("Export again")
+  #[serde(default)]
+  pub use_defaults: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PrintSectionOptions {
+  // The look the app has when printing, for the `match-app` theme
+  pub app_theme: Palette,
+  // Write here instead of asking where
+  pub output_path: Option<String>,
+}
+
+// What an export wrote, so the UI can summarize it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExportResult {
+  pub output_path: String,
+  pub copied_assets: Vec<String>,
+  // Custom stylesheets that were left out, and why
+  pub warnings: Vec<String>,
+}
+
+fn is_document_link(path: &Path) -> bool {
+  path
+    .extension()
+    .map(|e| e.to_string_lossy().to_lowercase())
+    .is_some_and(|e| DOCUMENT_EXTENSIONS.contains(&e.as_str()))
+}
+
+// Find every local file a document references: markdown images, links to non-markdown
+// files, and `src`/`href` attributes in inline HTML. Remote URLs, anchors and files that
+// don't exist are ignored; each file is listed once.
+pub fn collect_assets(document_path: &Path, content: &str) -> Vec<DocumentAsset> {
+  let mut references = Vec::new();
+  for event in Parser::new_ext(content, Options::all()) {
+    match event {
+      Event::Start(Tag::Image { dest_url, .. }) => references.push((dest_url.to_string(), true)),
+      Event::Start(Tag::Link { dest_url, .. }) => references.push((dest_url.to_string(), false)),
+      Event::Html(html) | Event::InlineHtml(html) => references.extend(
+        html_references(&html)
+          .into_iter()
+          .map(|(_, value)| (value, false)),
+      ),
+      _ => {}
+    }
+  }
+
+  let mut assets: Vec<DocumentAsset> = Vec::new();
+  for (reference, is_image) in references {
+    let Some(path) = resolve_reference(document_path, &reference) else {
+      continue;
+    };
+    if !path.is_file() || (!is_image && is_document_link(&path)) {
+      continue;
+    }
+    if !assets.iter().any(|a| a.path == path) {
+      assets.push(DocumentAsset { reference, path });
+    }
+  }
+  assets
+}
+
+// `assets` without the ones outside the access scope, which get a warning each. The
+// markdown comes from the frontend, so its references can point anywhere.
+pub(crate) fn scoped_assets(
+  scope: &AccessScope,
+  assets: Vec<DocumentAsset>,
+  warnings: &mut Vec<String>,
+) -> Vec<DocumentAsset> {
+  let (allowed, refused): (Vec<_>, Vec<_>) = assets
+    .into_iter()
+    .partition(|asset| scope.is_allowed(&asset.path));
+  warnings.extend(refused.into_iter().map(|asset| {
+    format!(
+      "{} was left out: access to its folder was not approved",
+      asset.reference
+    )
+  }));
+  allowed
+}
+
+// Local files named in a document's YAML frontmatter, e.g. `cover: images/cover.png` or an
+// `attachments:` list. Any scalar value that resolves to an existing file counts.
+pub fn frontmatter_assets(document_path: &Path, content: &str) -> Vec<DocumentAsset> {
+  let mut lines = content.lines();
+  if lines.next().map(str::trim_end) != Some("---") {
+    return Vec::new();
+  }
+
+  let mut assets: Vec<DocumentAsset> = Vec::new();
+  for line in lines {
+    let line = line.trim();
+    if line == "---" || line == "..." {
+      break;
+    }
+    let value = match line.strip_prefix("- ") {
+      Some(item) => item,
+      None => line.split_once(':').map(|(_, v)| v).unwrap_or(""),
+    };
+    let value = value.trim().trim_start_matches('[').trim_end_matches(']');
+    for candidate in value.split(',') {
+      let reference = candidate.trim().trim_matches(['"', '\'']);
+      if reference.is_empty() {
+        continue;
+      }
+      let Some(path) = resolve_reference(document_path, reference) else {
+        continue;
+      };
+      if path.is_file() && !assets.iter().any(|a| a.path == path) {
+        assets.push(DocumentAsset {
+          reference: reference.to_string(),
+          path,
+        });
+      }
+    }
+  }
+  assets
+}
+
+// Find `src="..."` and `href="..."` attribute values in HTML, returning the byte range of
+// each value (without quotes) and its entity-decoded text
+pub(crate) fn html_references(html: &str) -> Vec<(std::ops::Range<usize>, String)> {
+  let mut found = Vec::new();
+  let lower = html.to_ascii_lowercase();
+  for attribute in ["src=", "href="] {
+    for (index, _) in lower.match_indices(attribute) {
+      // Make sure we matched a whole attribute name, not e.g. `data-src=`
+      if !html[..index].ends_with(|c: char| c.is_ascii_whitespace()) {
+        continue;
+      }
+      let value_start = index + attribute.len();
+      let Some(quote) = html[value_start..]
+        .chars()
+        .next()
+        .filter(|c| *c == '"' || *c == '\'')
+      else {
+        continue;
+      };
+      let start = value_start + 1;
+      let Some(length) = html[start..].find(quote) else {
+        continue;
+      };
+      let value = html[start..start + length].replace("&amp;", "&");
+      found.push((start..start + length, value));
+    }
+  }
+  found.sort_by_key(|(range, _)| range.start);
+  found
+}
+
+pub(crate) fn content_hash(path: &Path) -> std::io::Result<String> {
+  let bytes = std::fs::read(path)?;
+  let digest = Sha256::digest(&bytes);
+  Ok(
+    digest
+      .iter()
+      .take(4)
+      .map(|b| format!("{:02x}", b))
+      .collect(),
+  )
+}
+
+// `pic.png` -> `pic-1a2b3c4d.png`
+pub(crate) fn suffixed_name(name: &str, hash: &str) -> String {
+  let path = Path::new(name);
+  let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+  match path.extension() {
+    Some(ext) => format!("{}-{}.{}", stem, hash, ext.to_string_lossy()),
+    None => format!("{}-{}", stem, hash),
+  }
+}
+
+// Copy assets into `assets_dir`, keeping file names where possible. Two different files
+// with the same name (e.g. `diagram.png` from two folders) get a content-hash suffix;
+// identical copies share one file. Returns the exported name for each source path.
+pub(crate) fn copy_assets(
+  assets: &[DocumentAsset],
+  assets_dir: &Path,
+) -> CommandResult<HashMap<PathBuf, String>> {
+  let mut exported: HashMap<PathBuf, String> = HashMap::new();
+  // Exported name -> content hash of the file written under it
+  let mut taken: HashMap<String, String> = HashMap::new();
+
+  for asset in assets {
+    let name = asset
+      .path
+      .file_name()
+      .unwrap_or_default()
+      .to_string_lossy()
+      .to_string();
+    let hash = content_hash(&asset.path)
+      .map_err(|e| CommandError::from_io(&e, &asset.path, "Failed to read asset"))?;
+
+    let name = match taken.get(&name) {
+      None => name,
+      Some(existing) if *existing == hash => {
+        exported.insert(asset.path.clone(), name);
+        continue;
+      }
+      Some(_) => suffixed_name(&name, &hash),
+    };
+    if taken.get(&name).is_some_and(|existing| *existing == hash) {
+      exported.insert(asset.path.clone(), name);
+      continue;
+    }
+
+    std::fs::create_dir_all(assets_dir)
+      .map_err(|e| CommandError::from_io(&e, assets_dir, "Failed to create assets folder"))?;
+    std::fs::copy(&asset.path, assets_dir.join(&name))
+      .map_err(|e| CommandError::from_io(&e, &asset.path, "Failed to copy asset"))?;
+    taken.insert(name.clone(), hash);
+    exported.insert(asset.path.clone(), name);
+  }
+
+  Ok(exported)
+}
+
+// Point `src`/`href` attributes that resolve to a copied asset at its exported location
+fn rewrite_html_references(
+  html: &str,
+  document_path: &Path,
+  exported: &HashMap<PathBuf, String>,
+) -> String {
+  let mut rewritten = String::with_capacity(html.len());
+  let mut last = 0;
+  for (range, value) in html_references(html) {
+    let Some(name) = resolve_reference(document_path, &value).and_then(|p| exported.get(&p)) else {
+      continue;
+    };
+    // Keep any fragment, e.g. `manual.pdf#page=3`
+    let fragment = value.find('#').map(|i| &value[i..]).unwrap_or("");
+    rewritten.push_str(&html[last..range.start]);
+    rewritten.push_str(&format!(
+      "{}/{}{}",
+      EXPORT_ASSETS_DIR,
+      urlencoding::encode(name),
+      fragment
+    ));
+    last = range.end;
+  }
+  rewritten.push_str(&html[last..]);
+  rewritten
+}
+
+// Replace references to local images, audio and video of at most `max_bytes` with data
+// URLs, so the page shows them wherever it's saved. Links to other files, and files that
+// can't be read, are bigger or are outside the access scope, are left as they are.
+pub(crate) fn inline_html_references(
+  scope: &AccessScope,
+  html: &str,
+  document_path: &Path,
+  max_bytes: u64,
+) -> String {
+  let mut inlined = String::with_capacity(html.len());
+  let mut last = 0;
+  for (range, value) in html_references(html) {
+    let Some(path) = resolve_reference(document_path, &value).filter(|p| scope.is_allowed(p))
+    else {
+      continue;
+    };
+    let mime = mime_type_for(&path);
+    if !["image/", "audio/", "video/"]
+      .iter()
+      .any(|kind| mime.starts_with(kind))
+    {
+      continue;
+    }
+    let too_large = std::fs::metadata(&path)
+      .map(|metadata| metadata.len() > max_bytes)
+      .unwrap_or(true);
+    if too_large {
+      continue;
+    }
+    let Ok(bytes) = std::fs::read(&path) else {
+      continue;
+    };
+    inlined.push_str(&html[last..range.start]);
+    inlined.push_str(&format!(
+      "data:{};base64,{}",
+      mime,
+      base64::engine::general_purpose::STANDARD.encode(bytes)
+    ));
+    last = range.end;
+  }
+  inlined.push_str(&html[last..]);
+  inlined
+}
+
+// A standalone page for `body`, laid out and colored as `print` asks (`app` resolves the
+// `match-app` theme). The user's `custom_css` comes after the built-in styles so it can
+// override them.
+fn html_document(
+  title: &str,
+  author: Option<&str>,
+  body: &str,
+  print: &PrintOptions,
+  app: Palette,
+  custom_css: &str,
+) -> String {
+  let escape = |text: &str| {
+    text
+      .replace('&', "&amp;")
+      .replace('<', "&lt;")
+      .replace('>', "&gt;")
+      .replace('"', "&quot;")
+  };
+  let meta = author
+    .map(|author| format!("<meta name=\"author\" content=\"{}\">\n", escape(author)))
+    .unwrap_or_default();
+  let (screen, paper) = print.palettes(app);
+  let mut style = print_layout::theme_style(screen, paper);
+  style.push_str(&print_layout::print_style(print));
+  if !custom_css.trim().is_empty() {
+    // The CSS can't end the style element early
+    style.push_str(&custom_css.replace("</", "<\\/"));
+    if !style.ends_with('\n') {
+      style.push('\n');
+    }
+  }
+  format!(
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n{}<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n{}\n</body>\n</html>\n",
+    meta,
+    escape(title),
+    style,
+    print_layout::print_body(body, print)
+  )
+}
+
+// Write the rendered preview as a standalone HTML file. Prompts for a destination when no
+// path is given (unless the options ask to reuse the document's defaults); returns None if
+// the dialog was cancelled.
+#[tauri::command]
+pub async fn export_html(
+  app: AppHandle,
+  settings: tauri::State<'_, SettingsState>,
+  document_path: Option<String>,
+  markdown: String,
+  html: String,
+  output_path: Option<String>,
+  options: Option<ExportOptions>,
+) -> CommandResult<Option<ExportResult>> {
+  let document = document_path.map(PathBuf::from);
+  if let Some(document) = &document {
+    app.state::<AccessScopeState>().check(document)?;
+  }
+  let options = options.unwrap_or_default();
+  let defaults = options.defaults.as_ref();
+  let output_path = match output_path {
+    Some(path) => requested_output_path(&app.state::<AccessScopeState>(), path)?,
+    None => match reused_output_path(document.as_deref(), &options, "html") {
+      Some(path) => {
+        app.state::<AccessScopeState>().check(&path)?;
+        path
+      }
+      None => {
+        let stem = defaults
+          .map(|defaults| defaults.file_name.clone())
+          .or_else(|| {
+            document
+              .as_ref()
+              .and_then(|d| d.file_stem())
+              .map(|stem| stem.to_string_lossy().to_string())
+          })
+          .unwrap_or_else(|| "Untitled".to_string());
+        let file_name = format!("{}.html", stem);
+        let picked = app
+          .dialog()
+          .file()
+          .add_filter("HTML", &["html", "htm"])
+          .set_file_name(file_name)
+          .blocking_save_file();
+        match picked.map(crate::dialog_result_to_path).transpose()? {
+          Some(path) => path,
+          None => return Ok(None),
+        }
+      }
+    },
+  };
+
+  let print = print_options(&settings.0.lock().unwrap().print, defaults);
+  let styles = export_styles(&app, document.as_deref(), Some(&markdown), defaults);
+  let mut result = export_html_to(
+    &app.state::<AccessScopeState>().0.lock().unwrap(),
+    document.as_deref(),
+    &markdown,
+    &html,
+    &output_path,
+    &options,
+    &print,
+    &styles.css,
+  )?;
+  let mut warnings = styles.warnings;
+  warnings.append(&mut result.warnings);
+  result.warnings = warnings;
+  Ok(Some(result))
+}
+
+// The settings' print options with the document's page size and table of contents
+fn print_options(print: &PrintOptions, defaults: Option<&EffectiveExportOptions>) -> PrintOptions {
+  let mut print = print.clone();
+  if let Some(defaults) = defaults {
+    print.page_size = defaults.page_size;
+    print.table_of_contents = defaults.table_of_contents;
+  }
+  print
+}
+
+// The user CSS for an export, with the document's defaults' stylesheet when given. Options
+// the defaults ignored come first in the warnings.
+fn export_styles(
+  app: &AppHandle,
+  document: Option<&Path>,
+  content: Option<&str>,
+  defaults: Option<&EffectiveExportOptions>,
+) -> styles::EffectiveStyles {
+  let Some(defaults) = defaults else {
+    return styles::effective_styles(app, document, content);
+  };
+  let mut styles = styles::styles_with(app, document, defaults.stylesheet.as_deref());
+  let mut warnings = defaults.warnings.clone();
+  warnings.append(&mut styles.warnings);
+  styles.warnings = warnings;
+  styles
+}
+
+// An export destination the frontend passed in. Unlike one the user picked in a save dialog,
+// it has to be inside the access scope.
+pub(crate) fn requested_output_path(
+  scope: &AccessScopeState,
+  path: String,
+) -> CommandResult<PathBuf> {
+  let path = PathBuf::from(path);
+  scope.check(&path)?;
+  Ok(path)
+}
+
+// Where to write without asking, when `options` ask to reuse the defaults of a saved document
+fn reused_output_path(
+  document: Option<&Path>,
+  options: &ExportOptions,
+  extension: &str,
+) -> Option<PathBuf> {
+  let defaults = options.defaults.as_ref().filter(|_| options.use_defaults)?;
+  default_output_path(document?, defaults, extension)
+}
+
+fn export_html_to(
+  scope: &AccessScope,
+  document: Option<&Path>,
+  markdown: &str,
+  html: &str,
+  output_path: &Path,
+  options: &ExportOptions,
+  print: &PrintOptions,
+  custom_css: &str,
+) -> CommandResult<ExportResult> {
+  if !output_path.is_absolute() {
+    return Err(CommandError::invalid_path(
+      output_path,
+      "File path must be absolute",
+    ));
+  }
+  if let Some(document) = document.filter(|d| !d.is_absolute()) {
+    return Err(CommandError::invalid_path(
+      document,
+      "Document path must be absolute",
+    ));
+  }
+
+  let root = output_path.parent().ok_or_else(|| {
+    CommandError::invalid_path(output_path, "Export path has no parent directory")
+  })?;
+  let file_name = output_path.file_name().unwrap_or_default();
+  let selection = options
+    .selection_html
+    .as_deref()
+    .filter(|selection| !selection.trim().is_empty());
+  // The page and its assets are moved into place together once all are written
+  write_staged(root, output_path, |staging| {
+    let mut body = selection.unwrap_or(html).to_string();
+    let mut copied_assets = Vec::new();
+    let mut warnings = Vec::new();
+    // Untitled documents have no folder to resolve relative references against
+    if let (true, Some(document)) = (options.copy_assets, document) {
+      let assets = scoped_assets(scope, collect_assets(document, markdown), &mut warnings);
+      let exported = copy_assets(&assets, &staging.join(EXPORT_ASSETS_DIR))?;
+      body = rewrite_html_references(&body, document, &exported);
+
+      let assets_dir = root.join(EXPORT_ASSETS_DIR);
+      copied_assets = exported
+        .into_values()
+        .map(|name| assets_dir.join(name).to_string_lossy().to_string())
+        .collect();
+      copied_assets.sort();
+      copied_assets.dedup();
+    }
+
+    let defaults = options.defaults.as_ref();
+    let mut title = defaults
+      .map(|defaults| defaults.title.clone())
+      .or_else(|| {
+        document
+          .and_then(|d| d.file_stem())
+          .map(|stem| stem.to_string_lossy().to_string())
+      })
+      .unwrap_or_else(|| "Untitled".to_string());
+    if selection.is_some() {
+      title.push_str(" (selection)");
+    }
+    let author = defaults.and_then(|defaults| defaults.author.as_deref());
+    let staged_path = staging.join(file_name);
+    std::fs::write(
+      &staged_path,
+      html_document(&title, author, &body, print, options.app_theme, custom_css),
+    )
+    .map_err(|e| CommandError::from_io(&e, &staged_path, "Failed to write file"))?;
+
+    Ok(ExportResult {
+      output_path: output_path.to_string_lossy().to_string(),
+      copied_assets,
+      warnings,
+    })
+  })
+}
+
+// Write the print layout of `html_content` (the print settings' page breaks and table of
+// contents) as a single HTML file, with local images inlined so it doesn't depend on the
+// document's folder
+fn export_print_html_to(
+  scope: &AccessScope,
+  document: Option<&Path>,
+  title: &str,
+  author: Option<&str>,
+  html_content: &str,
+  output_path: &Path,
+  print: &PrintOptions,
+  app_theme: Palette,
+  custom_css: &str,
+) -> CommandResult<()> {
+  if !output_path.is_absolute() {
+    return Err(CommandError::invalid_path(
+      output_path,
+      "File path must be absolute",
+    ));
+  }
+  // Untitled documents have no folder to resolve relative references against
+  let body = match document {
+    Some(document) if !document.is_absolute() => {
+      return Err(CommandError::invalid_path(
+        document,
+        "Document path must be absolute",
+      ));
+    }
+    Some(document) => {
+      scope.check(document)?;
+      inline_html_references(scope, html_content, document, MAX_INLINED_ASSET_BYTES)
+    }
+    None => html_content.to_string(),
+  };
+  write_atomically(
+    output_path,
+    html_document(title, author, &body, print, app_theme, custom_css).as_bytes(),
+  )
+  .map_err(|e| CommandError::from_io(&e, output_path, "Failed to write file"))
+}
+
+// Save the print layout as an HTML file the user picks. The save dialog asks before
+// replacing a file. Returns None if the dialog was cancelled. A custom stylesheet that
+// can't be used is reported in the result's warnings, not as an error. `defaults` from
+// get_export_defaults set the title, author, layout and file name; with `use_defaults` the
+// file is written next to the document without asking.
+#[tauri::command]
+pub async fn export_print_html(
+  app: AppHandle,
+  settings: tauri::State<'_, SettingsState>,
+  title: String,
+  html_content: String,
+  document_path: Option<String>,
+  app_theme: Option<Palette>,
+  defaults: Option<EffectiveExportOptions>,
+  use_defaults: Option<bool>,
+) -> CommandResult<Option<ExportResult>> {
+  let document = document_path.map(PathBuf::from);
+  if let Some(document) = &document {
+    app.state::<AccessScopeState>().check(document)?;
+  }
+  let options = ExportOptions {
+    defaults,
+    use_defaults: use_defaults.unwrap_or(false),
+    ..ExportOptions::default()
+  };
+  let defaults = options.defaults.as_ref();
+  let output_path = match reused_output_path(document.as_deref(), &options, "html") {
+    Some(path) => {
+      app.state::<AccessScopeState>().check(&path)?;
+      path
+    }
+    None => {
+      // An untitled document is named after its title, not the defaults' `Untitled`
+      let stem = document
+        .as_ref()
+        .and_then(|d| {
+          defaults
+            .map(|defaults| defaults.file_name.clone())
+            .or_else(|| d.file_stem().map(|stem| stem.to_string_lossy().to_string()))
+        })
+        .or_else(|| sanitize_file_stem(&title, FilenameSeparator::Space))
+        .unwrap_or_else(|| "Untitled".to_string());
+      let picked = app
+        .dialog()
+        .file()
+        .add_filter("HTML", &["html", "htm"])
+        .set_file_name(format!("{}.html", stem))
+        .blocking_save_file();
+      match picked.map(crate::dialog_result_to_path).transpose()? {
+        Some(path) => path,
+        None => return Ok(None),
+      }
+    }
+  };
+
+  let print = print_options(&settings.0.lock().unwrap().print, defaults);
+  let styles = export_styles(&app, document.as_deref(), None, defaults);
+  let title = match (&document, defaults) {
+    (Some(_), Some(defaults)) => defaults.title.as_str(),
+    _ => title.as_str(),
+  };
+  export_print_html_to(
+    &app.state::<AccessScopeState>().0.lock().unwrap(),
+    document.as_deref(),
+    title,
+    defaults.and_then(|defaults| defaults.author.as_deref()),
+    &html_content,
+    &output_path,
+    &print,
+    app_theme.unwrap_or_default(),
+    &styles.css,
+  )?;
+  Ok(Some(ExportResult {
+    output_path: output_path.to_string_lossy().to_string(),
+    copied_assets: Vec::new(),
+    warnings: styles.warnings,
+  }))
+}
+
+// Print layout of one heading's section of a document, given by `document_path` or as
+// `markdown` (the editor's content, which wins when both are given). The document's title
+// goes on top, and the footnotes and link definitions the section uses come along. A
+// single section prints without page breaks or a table of contents, to keep it short.
+// Returns None if the dialog was cancelled.
+#[tauri::command]
+pub async fn print_section(
+  app: AppHandle,
+  settings: tauri::State<'_, SettingsState>,
+  document_path: Option<String>,
+  markdown: Option<String>,
+  heading: SectionTarget,
+  options: Option<PrintSectionOptions>,
+) -> CommandResult<Option<ExportResult>> {
+  let document = document_path.map(PathBuf::from);
+  let options = options.unwrap_or_default();
+  let markdown = match (markdown, &document) {
+    (Some(markdown), _) => markdown,
+    (None, Some(document)) => {
+      app.state::<AccessScopeState>().check(document)?;
+      crate::read_text_file(document)?
+    }
+    (None, None) => {
+      return Err(CommandError::invalid_data(
+        "Either a document path or its content is needed",
+      ))
+    }
+  };
+  let title = first_heading(&markdown)
+    .map(|heading| strip_inline_markdown(&heading))
+    .or_else(|| {
+      document
+        .as_ref()
+        .and_then(|d| d.file_stem())
+        .map(|stem| stem.to_string_lossy().to_string())
+    })
+    .unwrap_or_else(|| "Untitled".to_string());
+  let section = printable_section(&markdown, &heading, &title).ok_or_else(|| {
+    CommandError::invalid_data(match &heading {
+      SectionTarget::Line(line) => format!("Line {} is not a heading", line),
+      SectionTarget::Heading(heading) => format!("No heading matches \"{}\"", heading),
+    })
+  })?;
+
+  let output_path = match options.output_path {
+    Some(path) => requested_output_path(&app.state::<AccessScopeState>(), path)?,
+    None => {
+      // `Recipes - Pancakes.html`
+      let section_title = section_text(&markdown, &heading, true)
+        .and_then(first_heading)
+        .map(|heading| strip_inline_markdown(&heading))
+        .unwrap_or_default();
+      let stem = sanitize_file_stem(
+        &format!("{} - {}", title, section_title),
+        FilenameSeparator::Space,
+      )
+      .unwrap_or_else(|| "Untitled".to_string());
+      let picked = app
+        .dialog()
+        .file()
+        .add_filter("HTML", &["html", "htm"])
+        .set_file_name(format!("{}.html", stem))
+        .blocking_save_file();
+      match picked.map(crate::dialog_result_to_path).transpose()? {
+        Some(path) => path,
+        None => return Ok(None),
+      }
+    }
+  };
+
+  let print = PrintOptions {
+    page_break_on_h1: false,
+    page_break_on_h2: false,
+    table_of_contents: false,
+    ..settings.0.lock().unwrap().print.clone()
+  };
+  let styles = export_styles(&app, document.as_deref(), Some(&markdown), None);
+  let mut body = String::new();
+  html::push_html(&mut body, Parser::new_ext(&section, Options::all()));
+  export_print_html_to(
+    &app.state::<AccessScopeState>().0.lock().unwrap(),
+    document.as_deref(),
+    &title,
+    None,
+    &body,
+    &output_path,
+    &print,
+    options.app_theme,
+    &styles.css,
+  )?;
+  Ok(Some(ExportResult {
+    output_path: output_path.to_string_lossy().to_string(),
+    copied_assets: Vec::new(),
+    warnings: styles.warnings,
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::settings::PageSize;
+  use std::fs;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_requested_output_paths_outside_the_scope_are_denied() {
+    let dir = TempDir::new().unwrap();
+    let output = dir.path().join("doc.html").to_string_lossy().to_string();
+    let state = |scope| AccessScopeState(std::sync::Mutex::new(scope));
+
+    let error = requested_output_path(&state(AccessScope::default()), output.clone()).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    let scope = state(AccessScope::with_folders(&[dir.path()]));
+    assert_eq!(
+      requested_output_path(&scope, output.clone()).unwrap(),
+      PathBuf::from(output)
+    );
+  }
+
+  #[test]
+  fn test_collect_assets_finds_local_files_only() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("img")).unwrap();
+    fs::write(dir.path().join("img").join("a.png"), "a").unwrap();
+    fs::write(dir.path().join("report.pdf"), "pdf").unwrap();
+    fs::write(dir.path().join("other.md"), "# Other").unwrap();
+    let doc = dir.path().join("doc.md");
+
+    let markdown = "![A](img/a.png)\n![again](./img/a.png)\n[Report](report.pdf)\n\
+      [Other](other.md)\n![Remote](https://example.com/x.png)\n![Missing](missing.png)\n\
+      <img src=\"img/a.png\" width=\"10\">\n";
+    let assets = collect_assets(&doc, markdown);
+
+    let paths: Vec<&Path> = assets.iter().map(|a| a.path.as_path()).collect();
+    assert_eq!(
+      paths,
+      vec![
+        dir.path().join("img").join("a.png").as_path(),
+        dir.path().join("report.pdf").as_path()
+      ]
+    );
+    assert_eq!(assets[0].reference, "img/a.png");
+  }
+
+  #[test]
+  fn test_html_document_escapes_title() {
+    let document = html_document(
+      "</title><script>x</script>",
+      Some("A \"B\""),
+      "",
+      &PrintOptions::default(),
+      Palette::Light,
+      "",
+    );
+    assert!(document.contains("<title>&lt;/title&gt;&lt;script&gt;x&lt;/script&gt;</title>"));
+    assert!(document.contains("<meta name=\"author\" content=\"A &quot;B&quot;\">"));
+  }
+
+  #[test]
+  fn test_html_document_appends_custom_css() {
+    let print = PrintOptions {
+      page_break_on_h1: true,
+      ..PrintOptions::default()
+    };
+    let document = html_document(
+      "doc",
+      None,
+      "",
+      &print,
+      Palette::Light,
+      "h1 { color: red; }\n/* </style> */",
+    );
+    let built_in = document.find("@media print").unwrap();
+    let custom = document.find("h1 { color: red; }").unwrap();
+    assert!(built_in < custom);
+    assert!(document.contains("/* <\\/style> */\n</style>"));
+  }
+
+  #[test]
+  fn test_html_document_applies_print_options() {
+    let body = "<h1>One</h1>\n<h1>Two</h1>";
+    let plain = html_document(
+      "doc",
+      None,
+      body,
+      &PrintOptions::default(),
+      Palette::Dark,
+      "",
+    );
+    assert!(!plain.contains("@media print"));
+    assert!(plain.contains("color-scheme: light;"));
+
+    let print = PrintOptions {
+      page_break_on_h1: true,
+      table_of_contents: true,
+      ..PrintOptions::default()
+    };
+    let document = html_document("doc", None, body, &print, Palette::Light, "");
+    assert!(document.contains("}\n@media print {\nh1 { break-before: page;"));
+    assert!(document.contains("<nav class=\"toc\">"));
+    assert!(document.contains("<h1 data-first-heading id=\"section-1\">One</h1>"));
+    assert!(document.contains("<h1 id=\"section-2\">Two</h1>"));
+  }
+
+  #[test]
+  fn test_export_copies_assets_and_rewrites_references() {
+    let src = TempDir::new().unwrap();
+    let out = TempDir::new().unwrap();
+    fs::write(src.path().join("my pic.png"), "png").unwrap();
+    let doc = src.path().join("doc.md");
+    let output = out.path().join("doc.html");
+
+    let result = export_html_to(
+      &AccessScope::with_folders(&[src.path()]),
+      Some(&doc),
+      "![Pic](my%20pic.png)",
+      "<p><img src=\"my%20pic.png\" alt=\"Pic\"></p>",
+      &output,
+      &ExportOptions {
+        copy_assets: true,
+        ..ExportOptions::default()
+      },
+      &PrintOptions::default(),
+      "",
+    )
+    .unwrap();
+
+    assert_eq!(
+      result.copied_assets,
+      vec![out
+        .path()
+        .join("assets")
+        .join("my pic.png")
+        .to_string_lossy()]
+    );
+    let written = fs::read_to_string(&output).unwrap();
+    assert!(written.contains("<img src=\"assets/my%20pic.png\" alt=\"Pic\">"));
+    assert!(written.contains("<title>doc</title>"));
+  }
+
+  #[test]
+  fn test_print_export_inlines_local_images() {
+    let src = TempDir::new().unwrap();
+    let out = TempDir::new().unwrap();
+    fs::write(src.path().join("dot.png"), "png").unwrap();
+    fs::write(src.path().join("report.pdf"), "pdf").unwrap();
+    let doc = src.path().join("doc.md");
+    let output = out.path().join("print.html");
+
+    let print = PrintOptions {
+      page_break_on_h1: true,
+      ..PrintOptions::default()
+    };
+    export_print_html_to(
+      &AccessScope::with_folders(&[src.path()]),
+      Some(&doc),
+      "Doc",
+      None,
+      "<h1>Doc</h1><img src=\"dot.png\"><a href=\"report.pdf\">PDF</a><img src=\"gone.png\">",
+      &output,
+      &print,
+      Palette::Light,
+      "",
+    )
+    .unwrap();
+
+    let written = fs::read_to_string(&output).unwrap();
+    assert!(written.contains("<title>Doc</title>"));
+    assert!(written.contains("h1 { break-before: page;"));
+    assert!(written.contains("<img src=\"data:image/png;base64,cG5n\">"));
+    // Only media is inlined; links and missing files keep their references
+    assert!(written.contains("<a href=\"report.pdf\">PDF</a>"));
+    assert!(written.contains("<img src=\"gone.png\">"));
+  }
+
+  #[test]
+  fn test_assets_outside_the_scope_are_left_out() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    let secrets = dir.path().join("secrets");
+    fs::create_dir(&notes).unwrap();
+    fs::create_dir(&secrets).unwrap();
+    fs::write(notes.join("pic.png"), "png").unwrap();
+    fs::write(secrets.join("key.png"), "key").unwrap();
+    let doc = notes.join("doc.md");
+    let scope = AccessScope::with_folders(&[&notes]);
+    let html = "<img src=\"pic.png\"><img src=\"../secrets/key.png\">";
+
+    let output = notes.join("doc.html");
+    let result = export_html_to(
+      &scope,
+      Some(&doc),
+      "![](pic.png) ![](../secrets/key.png)",
+      html,
+      &output,
+      &ExportOptions {
+        copy_assets: true,
+        ..ExportOptions::default()
+      },
+      &PrintOptions::default(),
+      "",
+    )
+    .unwrap();
+    assert_eq!(result.copied_assets.len(), 1);
+    assert_eq!(
+      result.warnings,
+      vec!["../secrets/key.png was left out: access to its folder was not approved"]
+    );
+    assert!(!notes.join("assets").join("key.png").exists());
+
+    let printed = notes.join("print.html");
+    let print = PrintOptions::default();
+    export_print_html_to(
+      &scope,
+      Some(&doc),
+      "Doc",
+      None,
+      html,
+      &printed,
+      &print,
+      Palette::Light,
+      "",
+    )
+    .unwrap();
+    let written = fs::read_to_string(&printed).unwrap();
+    assert!(written.contains("<img src=\"data:image/png;base64,cG5n\">"));
+    assert!(written.contains("<img src=\"../secrets/key.png\">"));
+
+    let outside = secrets.join("doc.md");
+    let error = export_print_html_to(
+      &scope,
+      Some(&outside),
+      "Doc",
+      None,
+      html,
+      &printed,
+      &print,
+      Palette::Light,
+      "",
+    )
+    .unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+  }
+
+  #[test]
+  fn test_export_selection_only() {
+    let out = TempDir::new().unwrap();
+    let output = out.path().join("doc.html");
+    let export = |selection: &str| {
+      let options = ExportOptions {
+        selection_html: Some(selection.to_string()),
+        ..ExportOptions::default()
+      };
+      export_html_to(
+        &AccessScope::default(),
+        Some(Path::new("/notes/doc.md")),
+        "# Doc\n\nPart",
+        "<h1>Doc</h1><p>Part</p>",
+        &output,
+        &options,
+        &PrintOptions::default(),
+        "",
+      )
+      .unwrap();
+      fs::read_to_string(&output).unwrap()
+    };
+
+    let written = export("<p>Part</p>");
+    assert!(written.contains("<title>doc (selection)</title>"));
+    assert!(written.contains("<body>\n<p>Part</p>\n</body>"));
+    // A blank selection exports the whole document, not an empty page
+    let written = export(" \n ");
+    assert!(written.contains("<title>doc</title>"));
+    assert!(written.contains("<h1>Doc</h1><p>Part</p>"));
+  }
+
+  #[test]
+  fn test_export_uses_document_defaults() {
+    let out = TempDir::new().unwrap();
+    let output = out.path().join("doc.html");
+    let defaults = EffectiveExportOptions {
+      page_size: PageSize::A4,
+      stylesheet: None,
+      title: "Quarterly Report".to_string(),
+      author: Some("Jane".to_string()),
+      table_of_contents: true,
+      file_name: "report 2024-06-30".to_string(),
+      warnings: Vec::new(),
+    };
+    let print = print_options(&PrintOptions::default(), Some(&defaults));
+    let options = ExportOptions {
+      defaults: Some(defaults),
+      use_defaults: true,
+      ..ExportOptions::default()
+    };
+    export_html_to(
+      &AccessScope::default(),
+      Some(Path::new("/notes/doc.md")),
+      "# Doc",
+      "<h1>Doc</h1>",
+      &output,
+      &options,
+      &print,
+      "",
+    )
+    .unwrap();
+    let written = fs::read_to_string(&output).unwrap();
+    assert!(
+      written.contains("<meta name=\"author\" content=\"Jane\">\n<title>Quarterly Report</title>")
+    );
+    assert!(written.contains("@page { size: A4; }"));
+    assert!(written.contains("<nav class=\"toc\">"));
+
+    assert_eq!(
+      reused_output_path(Some(Path::new("/notes/doc.md")), &options, "html"),
+      Some(PathBuf::from("/notes/report 2024-06-30.html"))
+    );
+    assert_eq!(reused_output_path(None, &options, "html"), None);
+    let asking = ExportOptions {
+      use_defaults: false,
+      ..options
+    };
+    assert_eq!(
+      reused_output_path(Some(Path::new("/notes/doc.md")), &asking, "html"),
+      None
+    );
+  }
+
+  #[test]
+  fn test_export_suffixes_colliding_names() {
+    let src = TempDir::new().unwrap();
+    let out = TempDir::new().unwrap();
+    for folder in ["one", "two", "three"] {
+      fs::create_dir(src.path().join(folder)).unwrap();
+    }
+    fs::write(src.path().join("one").join("diagram.png"), "first").unwrap();
+    fs::write(src.path().join("two").join("diagram.png"), "second").unwrap();
+    // Same bytes as the first one, so it can share its copy
+    fs::write(src.path().join("three").join("diagram.png"), "first").unwrap();
+    let doc = src.path().join("doc.md");
+
+    let result = export_html_to(
+      &AccessScope::with_folders(&[src.path()]),
+      Some(&doc),
+      "![](one/diagram.png) ![](two/diagram.png) ![](three/diagram.png)",
+      "<img src=\"one/diagram.png\"><img src=\"two/diagram.png\"><img src=\"three/diagram.png\">",
+      &out.path().join("doc.html"),
+      &ExportOptions {
+        copy_assets: true,
+        ..ExportOptions::default()
+      },
+      &PrintOptions::default(),
+      "",
+    )
+    .unwrap();
+
+    assert_eq!(result.copied_assets.len(), 2);
+    let written = fs::read_to_string(out.path().join("doc.html")).unwrap();
+    let suffixed = suffixed_name(
+      "diagram.png",
+      &content_hash(&src.path().join("two").join("diagram.png")).unwrap(),
+    );
+    assert!(written.contains("<img src=\"assets/diagram.png\"><img src=\"assets/"));
+    assert!(written.contains(&format!("assets/{}", suffixed)));
+    assert_eq!(
+      fs::read_to_string(out.path().join("assets").join(&suffixed)).unwrap(),
+      "second"
+    );
+  }
+
+  #[test]
+  fn test_export_without_copying_leaves_references() {
+    let src = TempDir::new().unwrap();
+    let out = TempDir::new().unwrap();
+    fs::write(src.path().join("pic.png"), "png").unwrap();
+    let output = out.path().join("doc.html");
+
+    let result = export_html_to(
+      &AccessScope::with_folders(&[src.path()]),
+      Some(&src.path().join("doc.md")),
+      "![](pic.png)",
+      "<img src=\"pic.png\">",
+      &output,
+      &ExportOptions::default(),
+      &PrintOptions::default(),
+      "",
+    )
+    .unwrap();
+
+    assert!(result.copied_assets.is_empty());
+    assert!(!out.path().join("assets").exists());
+    assert!(fs::read_to_string(&output)
+      .unwrap()
+      .contains("<img src=\"pic.png\">"));
+  }
+
+  #[test]
+  fn test_frontmatter_assets() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("cover.png"), "png").unwrap();
+    fs::write(dir.path().join("a.pdf"), "pdf").unwrap();
+    fs::write(dir.path().join("b.pdf"), "pdf").unwrap();
+    let doc = dir.path().join("doc.md");
+
+    let markdown = "---\ntitle: Notes\ncover: \"cover.png\"\nattachments:\n  - a.pdf\n\
+      related: [b.pdf, missing.pdf]\n---\n\n# Notes\n\nbody: not-frontmatter.png\n";
+    let names: Vec<String> = frontmatter_assets(&doc, markdown)
+      .into_iter()
+      .map(|a| a.reference)
+      .collect();
+    assert_eq!(names, vec!["cover.png", "a.pdf", "b.pdf"]);
+    assert!(frontmatter_assets(&doc, "# No frontmatter\ncover: cover.png\n").is_empty());
+  }
+
+  #[test]
+  fn test_html_references_skip_data_attributes() {
+    let refs = html_references("<img data-src=\"x.png\" src='y.png'> <a href=\"a&amp;b.pdf\">");
+    let values: Vec<&str> = refs.iter().map(|(_, v)| v.as_str()).collect();
+    assert_eq!(values, vec!["y.png", "a&b.pdf"]);
+  }
+}
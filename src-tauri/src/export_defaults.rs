@@ -0,0 +1,369 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::access_scope::AccessScopeState;
+use crate::error::{CommandResult, FieldError};
+use crate::filename::{sanitize_file_stem, FilenameSeparator};
+use crate::settings::{PageSize, Settings, SettingsState};
+
+// Frontmatter key holding a document's export options:
+//
+// ```yaml
+// export:
+//   page_size: a4
+//   css: styles/print.css
+//   title: Quarterly Report
+//   author: Jane Doe
+//   toc: true
+//   filename: "{stem}-{date}"
+// ```
+const EXPORT_KEY: &str = "export";
+
+const DEFAULT_FILE_NAME_PATTERN: &str = "{stem}";
+
+// Placeholders a file name pattern may use
+const FILE_NAME_PLACEHOLDERS: &[&str] = &["{stem}", "{title}", "{date}"];
+
+// Global export options; a document's `export:` frontmatter overrides them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportSettings {
+  // Written into exported pages' `author` meta tag
+  pub author: Option<String>,
+  // Name of exported files without the extension. `{stem}` is the document's file name,
+  // `{title}` the export title and `{date}` today's date (2024-06-30).
+  pub file_name_pattern: String,
+}
+
+impl Default for ExportSettings {
+  fn default() -> Self {
+    ExportSettings {
+      author: None,
+      file_name_pattern: DEFAULT_FILE_NAME_PATTERN.to_string(),
+    }
+  }
+}
+
+impl ExportSettings {
+  // What's wrong with each field, named `export.<field>`
+  pub fn validate(&self) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    if let Some(message) = pattern_error(&self.file_name_pattern) {
+      errors.push(FieldError {
+        field: "export.file_name_pattern".to_string(),
+        message: message.to_string(),
+      });
+    }
+    errors
+  }
+}
+
+// Why `pattern` can't name exported files, if it can't
+fn pattern_error(pattern: &str) -> Option<&'static str> {
+  if pattern.trim().is_empty() {
+    return Some("File name pattern can't be blank");
+  }
+  if pattern.contains(['/', '\\']) {
+    return Some("File name pattern can't contain folders");
+  }
+  let mut rest = pattern;
+  while let Some(start) = rest.find('{') {
+    let placeholder = rest[start..]
+      .find('}')
+      .map(|end| &rest[start..start + end + 1]);
+    match placeholder {
+      Some(placeholder) if FILE_NAME_PLACEHOLDERS.contains(&placeholder) => {
+        rest = &rest[start + placeholder.len()..];
+      }
+      _ => return Some("File name pattern can only use {stem}, {title} and {date}"),
+    }
+  }
+  None
+}
+
+// The options of one export: the document's frontmatter merged over the settings. Returned
+// by get_export_defaults and passed back as they are to the export commands.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EffectiveExportOptions {
+  pub page_size: PageSize,
+  // Document stylesheet relative to the document: `export.css`, else the `stylesheet:` key
+  pub stylesheet: Option<String>,
+  pub title: String,
+  pub author: Option<String>,
+  pub table_of_contents: bool,
+  // Exported file name without the extension
+  pub file_name: String,
+  // Frontmatter options that were ignored, and why
+  #[serde(default)]
+  pub warnings: Vec<String>,
+}
+
+// The options set in a document's `export:` frontmatter block
+#[derive(Debug, Default, PartialEq)]
+struct DocumentExport {
+  page_size: Option<PageSize>,
+  css: Option<String>,
+  title: Option<String>,
+  author: Option<String>,
+  toc: Option<bool>,
+  filename: Option<String>,
+  warnings: Vec<String>,
+}
+
+fn unquote(value: &str) -> &str {
+  let value = value.trim();
+  for quote in ['"', '\''] {
+    if let Some(inner) = value
+      .strip_prefix(quote)
+      .and_then(|value| value.strip_suffix(quote))
+    {
+      return inner;
+    }
+  }
+  value
+}
+
+fn parse_page_size(value: &str) -> Option<PageSize> {
+  match value.to_ascii_lowercase().as_str() {
+    "auto" => Some(PageSize::Auto),
+    "letter" => Some(PageSize::Letter),
+    "legal" => Some(PageSize::Legal),
+    "a4" => Some(PageSize::A4),
+    "a5" => Some(PageSize::A5),
+    _ => None,
+  }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+  match value.to_ascii_lowercase().as_str() {
+    "true" | "yes" | "on" => Some(true),
+    "false" | "no" | "off" => Some(false),
+    _ => None,
+  }
+}
+
+// Read the indented `key: value` lines under `export:` in `content`'s frontmatter. Keys
+// and values that can't be used become warnings.
+fn document_export(content: &str) -> DocumentExport {
+  let mut export = DocumentExport::default();
+  let mut lines = content.lines();
+  if lines.next().map(str::trim_end) != Some("---") {
+    return export;
+  }
+  let mut inside = false;
+  for line in lines {
+    let trimmed = line.trim();
+    if trimmed == "---" || trimmed == "..." {
+      break;
+    }
+    if trimmed.is_empty() || trimmed.starts_with('#') {
+      continue;
+    }
+    let indented = line.starts_with([' ', '\t']);
+    if !indented {
+      inside = false;
+      if let Some((key, value)) = line.split_once(':') {
+        if key.trim() == EXPORT_KEY {
+          inside = value.trim().is_empty();
+          if !inside {
+            export
+              .warnings
+              .push("`export` must list its options on indented lines".to_string());
+          }
+        }
+      }
+      continue;
+    }
+    if !inside {
+      continue;
+    }
+    let Some((key, value)) = trimmed.split_once(':') else {
+      export
+        .warnings
+        .push(format!("Ignored export line `{}`", trimmed));
+      continue;
+    };
+    let key = key.trim();
+    let value = unquote(value);
+    if value.is_empty() {
+      continue;
+    }
+    let text = Some(value.to_string());
+    match key.replace('-', "_").as_str() {
+      "page_size" => match parse_page_size(value) {
+        Some(size) => export.page_size = Some(size),
+        None => export.warnings.push(format!(
+          "Unknown page size `{}` (use letter, legal, a4, a5 or auto)",
+          value
+        )),
+      },
+      "toc" => match parse_bool(value) {
+        Some(toc) => export.toc = Some(toc),
+        None => export
+          .warnings
+          .push(format!("`toc` must be true or false, not `{}`", value)),
+      },
+      "css" => export.css = text,
+      "title" => export.title = text,
+      "author" => export.author = text,
+      "filename" => match pattern_error(value) {
+        None => export.filename = text,
+        Some(message) => export.warnings.push(format!("{}: `{}`", message, value)),
+      },
+      _ => export
+        .warnings
+        .push(format!("Unknown export option `{}`", key)),
+    }
+  }
+  export
+}
+
+// The name `pattern` gives an export, made safe for every platform
+fn expand_file_name(pattern: &str, stem: &str, title: &str, date: &str) -> String {
+  let expanded = pattern
+    .replace("{stem}", stem)
+    .replace("{title}", title)
+    .replace("{date}", date);
+  sanitize_file_stem(&expanded, FilenameSeparator::Space).unwrap_or_else(|| stem.to_string())
+}
+
+// Merge `document`'s frontmatter over `settings`. `content` is the document's markdown;
+// `date` is today, for `{date}` in file names.
+fn effective_options(
+  settings: &Settings,
+  document: Option<&Path>,
+  content: &str,
+  date: &str,
+) -> EffectiveExportOptions {
+  let export = document_export(content);
+  let stem = document
+    .and_then(Path::file_stem)
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_else(|| "Untitled".to_string());
+  let title = export.title.unwrap_or_else(|| stem.clone());
+  let pattern = export
+    .filename
+    .as_deref()
+    .unwrap_or(&settings.export.file_name_pattern);
+  EffectiveExportOptions {
+    page_size: export.page_size.unwrap_or(settings.print.page_size),
+    stylesheet: export
+      .css
+      .or_else(|| crate::styles::frontmatter_stylesheet(content)),
+    file_name: expand_file_name(pattern, &stem, &title, date),
+    title,
+    author: export.author.or_else(|| settings.export.author.clone()),
+    table_of_contents: export.toc.unwrap_or(settings.print.table_of_contents),
+    warnings: export.warnings,
+  }
+}
+
+// Where "export again" writes without asking: next to the document, named by the options
+pub fn default_output_path(
+  document: &Path,
+  options: &EffectiveExportOptions,
+  extension: &str,
+) -> Option<PathBuf> {
+  let folder = document.parent()?;
+  Some(folder.join(format!("{}.{}", options.file_name, extension)))
+}
+
+// How `document_path` wants to be exported: its frontmatter `export:` options merged over
+// the export and print settings. `markdown` is the editor's content when it has unsaved
+// changes; otherwise the file is read. Unknown or bad options are returned as warnings.
+#[tauri::command]
+pub async fn get_export_defaults(
+  settings: tauri::State<'_, SettingsState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  document_path: Option<String>,
+  markdown: Option<String>,
+) -> CommandResult<EffectiveExportOptions> {
+  let settings = settings.0.lock().unwrap().clone();
+  let document = document_path.map(PathBuf::from);
+  let content = match (&markdown, &document) {
+    (Some(markdown), _) => markdown.clone(),
+    (None, Some(document)) => {
+      scope.check(document)?;
+      crate::read_text_file(document)?
+    }
+    (None, None) => String::new(),
+  };
+  let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+  Ok(effective_options(
+    &settings,
+    document.as_deref(),
+    &content,
+    &date,
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DATE: &str = "2024-06-30";
+
+  #[test]
+  fn test_frontmatter_overrides_settings() {
+    let mut settings = Settings::default();
+    settings.export.author = Some("Global".to_string());
+    settings.print.table_of_contents = true;
+    let content = "---\ntitle: Not this one\nstylesheet: base.css\nexport:\n  page_size: A4\n  \
+                   css: \"print.css\"\n  author: Jane\n  toc: no\n  filename: '{title} {date}'\n\
+                   tags: [a]\n---\n# Report\n";
+    let options = effective_options(&settings, Some(Path::new("/notes/q2.md")), content, DATE);
+    assert_eq!(
+      options,
+      EffectiveExportOptions {
+        page_size: PageSize::A4,
+        stylesheet: Some("print.css".to_string()),
+        title: "q2".to_string(),
+        author: Some("Jane".to_string()),
+        table_of_contents: false,
+        file_name: "q2 2024-06-30".to_string(),
+        warnings: Vec::new(),
+      }
+    );
+
+    // Without frontmatter everything comes from the settings
+    let options = effective_options(&settings, Some(Path::new("/notes/q2.md")), "# Q2", DATE);
+    assert_eq!(options.page_size, PageSize::Auto);
+    assert_eq!(options.stylesheet, None);
+    assert_eq!(options.author.as_deref(), Some("Global"));
+    assert!(options.table_of_contents);
+    assert_eq!(options.file_name, "q2");
+    assert_eq!(
+      default_output_path(Path::new("/notes/q2.md"), &options, "html"),
+      Some(PathBuf::from("/notes/q2.html"))
+    );
+  }
+
+  #[test]
+  fn test_unknown_and_bad_options_are_warnings() {
+    let content = "---\nexport:\n  paper: a4\n  page_size: tabloid\n  toc: maybe\n  \
+                   filename: ../{stem}\n  title: Kept\n---\n";
+    let options = effective_options(&Settings::default(), None, content, DATE);
+    assert_eq!(options.title, "Kept");
+    assert_eq!(options.file_name, "Untitled");
+    assert_eq!(options.page_size, PageSize::Auto);
+    assert_eq!(options.warnings.len(), 4);
+    assert!(options.warnings[0].contains("`paper`"));
+
+    let inline = effective_options(&Settings::default(), None, "---\nexport: a4\n---\n", DATE);
+    assert_eq!(inline.warnings.len(), 1);
+  }
+
+  #[test]
+  fn test_file_name_patterns() {
+    assert_eq!(pattern_error("{stem}-{date}"), None);
+    assert!(pattern_error("").is_some());
+    assert!(pattern_error("out/{stem}").is_some());
+    assert!(pattern_error("{name}").is_some());
+    assert!(pattern_error("{stem").is_some());
+    assert_eq!(
+      expand_file_name("{title}: draft", "a", "Q2/Q3", DATE),
+      "Q2 Q3 draft"
+    );
+    assert_eq!(expand_file_name("{title}", "a", "...", DATE), "a");
+  }
+}
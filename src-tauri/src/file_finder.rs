@@ -0,0 +1,442 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Instant, UNIX_EPOCH};
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::error::CommandResult;
+use crate::wiki::{self, NoteFile};
+use crate::RecentFilesState;
+
+// Scoring, in the spirit of Sublime Text and fzf: every matched character scores, more for
+// characters that start a word or continue a run, less for gaps between matches
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 10;
+const BONUS_NAME_START: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 12;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP: i64 = 1;
+// Longest gap that still costs more; beyond it everything is equally far apart
+const MAX_GAP_PENALIZED: usize = 12;
+// A match that lies entirely in the file name beats one spread over folder names
+const BONUS_FILE_NAME: i64 = 24;
+// The most recently opened file gets this much, later recents a little less each
+const BONUS_RECENT: i64 = 40;
+const BONUS_RECENT_STEP: i64 = 4;
+
+const DEFAULT_LIMIT: usize = 50;
+
+struct IndexedFile {
+  path: PathBuf,
+  // Path relative to the workspace root with `/` separators, as shown in the finder
+  relative: Vec<char>,
+  lower: Vec<char>,
+  // Index in `relative` where the file name starts
+  name_start: usize,
+  // Milliseconds since the epoch
+  modified: u64,
+}
+
+struct FileIndex {
+  built_at: Instant,
+  files: Vec<IndexedFile>,
+}
+
+impl FileIndex {
+  fn new(root: &Path, notes: Vec<NoteFile>) -> Self {
+    let files = notes
+      .into_iter()
+      .map(|note| {
+        let relative = note
+          .path
+          .strip_prefix(root)
+          .unwrap_or(&note.path)
+          .components()
+          .map(|c| c.as_os_str().to_string_lossy())
+          .collect::<Vec<_>>()
+          .join("/");
+        let relative: Vec<char> = relative.chars().collect();
+        let name_start = relative
+          .iter()
+          .rposition(|c| *c == '/')
+          .map_or(0, |slash| slash + 1);
+        IndexedFile {
+          lower: relative.iter().flat_map(|c| c.to_lowercase()).collect(),
+          relative,
+          name_start,
+          modified: note
+            .modified
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |d| d.as_millis() as u64),
+          path: note.path,
+        }
+      })
+      // Lowercasing can change the length of exotic characters; those names only match
+      // when it didn't, so indices stay valid
+      .map(|mut file| {
+        if file.lower.len() != file.relative.len() {
+          file.lower = file.relative.clone();
+        }
+        file
+      })
+      .collect();
+    FileIndex {
+      built_at: Instant::now(),
+      files,
+    }
+  }
+}
+
+// File indexes by workspace root, like the wiki link index: built on first use and rebuilt
+// once stale, so typing in the finder only scores
+#[derive(Default)]
+pub struct FileIndexState(Mutex<HashMap<PathBuf, FileIndex>>);
+
+impl FileIndexState {
+  // Forget the index of the workspace containing `path`, e.g. after a file was written
+  pub fn invalidate(&self, path: &Path) {
+    let mut indexes = self.0.lock().unwrap();
+    indexes.retain(|root, _| !path.starts_with(root));
+  }
+
+  // The notes of the workspace at `root`, from its index
+  pub fn notes(&self, root: &Path) -> Vec<PathBuf> {
+    let mut indexes = self.0.lock().unwrap();
+    fresh_index(&mut indexes, root)
+      .files
+      .iter()
+      .map(|file| file.path.clone())
+      .collect()
+  }
+}
+
+// The index of `root`, built or rebuilt if it's missing or stale
+fn fresh_index<'a>(indexes: &'a mut HashMap<PathBuf, FileIndex>, root: &Path) -> &'a FileIndex {
+  let stale = indexes
+    .get(root)
+    .is_none_or(|index| index.built_at.elapsed() > wiki::INDEX_MAX_AGE);
+  if stale {
+    indexes.insert(
+      root.to_path_buf(),
+      FileIndex::new(root, wiki::walk_notes(root)),
+    );
+  }
+  &indexes[root]
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileMatch {
+  pub path: String,
+  pub relative_path: String,
+  pub score: i64,
+  // Character indices in `relative_path` of the matched query characters
+  pub match_indices: Vec<usize>,
+}
+
+// Positions of `query` as a subsequence of `text[from..]`: the first place it ends, then
+// pulled as far right as possible so the matched characters sit close together
+fn find_positions(text: &[char], query: &[char], from: usize) -> Option<Vec<usize>> {
+  let mut next = 0;
+  let mut end = None;
+  for (index, c) in text.iter().enumerate().skip(from) {
+    if *c == query[next] {
+      next += 1;
+      if next == query.len() {
+        end = Some(index);
+        break;
+      }
+    }
+  }
+  let end = end?;
+  let mut positions = vec![0; query.len()];
+  let mut remaining = query.len();
+  for index in (from..=end).rev() {
+    if text[index] == query[remaining - 1] {
+      remaining -= 1;
+      positions[remaining] = index;
+      if remaining == 0 {
+        break;
+      }
+    }
+  }
+  Some(positions)
+}
+
+fn score_positions(file: &IndexedFile, positions: &[usize]) -> i64 {
+  let chars = &file.relative;
+  let mut score = 0;
+  let mut previous: Option<usize> = None;
+  for &position in positions {
+    score += SCORE_MATCH;
+    let boundary = match position.checked_sub(1).map(|i| chars[i]) {
+      None => true,
+      Some(before) => {
+        matches!(before, '/' | '-' | '_' | ' ' | '.')
+          || (before.is_lowercase() && chars[position].is_uppercase())
+      }
+    };
+    if position == file.name_start {
+      score += BONUS_NAME_START;
+    } else if boundary {
+      score += BONUS_BOUNDARY;
+    }
+    if let Some(previous) = previous {
+      let gap = position - previous - 1;
+      if gap == 0 {
+        score += BONUS_CONSECUTIVE;
+      } else {
+        score -= PENALTY_GAP_START + gap.min(MAX_GAP_PENALIZED) as i64 * PENALTY_GAP;
+      }
+    }
+    previous = Some(position);
+  }
+  score
+}
+
+// Best match of a lowercased query in a file: within its name if possible, else anywhere
+// in its path
+fn match_file(file: &IndexedFile, query: &[char]) -> Option<(i64, Vec<usize>)> {
+  let in_name = find_positions(&file.lower, query, file.name_start).map(|positions| {
+    (
+      score_positions(file, &positions) + BONUS_FILE_NAME,
+      positions,
+    )
+  });
+  let in_path = find_positions(&file.lower, query, 0)
+    .map(|positions| (score_positions(file, &positions), positions));
+  match (in_name, in_path) {
+    (Some(name), Some(path)) if path.0 > name.0 => Some(path),
+    (Some(name), _) => Some(name),
+    (None, path) => path,
+  }
+}
+
+// Recents are compared like recent_paths_equal does
+fn recent_key(path: &str) -> String {
+  if cfg!(any(target_os = "macos", target_os = "windows")) {
+    path.to_lowercase()
+  } else {
+    path.to_string()
+  }
+}
+
+fn search(index: &FileIndex, query: &str, limit: usize, recents: &[String]) -> Vec<FileMatch> {
+  let to_match = |file: &IndexedFile, score: i64, match_indices: Vec<usize>| FileMatch {
+    path: file.path.to_string_lossy().to_string(),
+    relative_path: file.relative.iter().collect(),
+    score,
+    match_indices,
+  };
+
+  let query: Vec<char> = query
+    .chars()
+    .filter(|c| !c.is_whitespace())
+    .flat_map(char::to_lowercase)
+    .collect();
+  if query.is_empty() {
+    let mut files: Vec<&IndexedFile> = index.files.iter().collect();
+    files.sort_by(|a, b| {
+      b.modified
+        .cmp(&a.modified)
+        .then_with(|| a.path.cmp(&b.path))
+    });
+    return files
+      .into_iter()
+      .take(limit)
+      .map(|file| to_match(file, 0, Vec::new()))
+      .collect();
+  }
+
+  let recent_ranks: HashMap<String, i64> = recents
+    .iter()
+    .enumerate()
+    .map(|(rank, path)| (recent_key(path), rank as i64))
+    .collect();
+  let mut scored: Vec<(i64, &IndexedFile, Vec<usize>)> = index
+    .files
+    .iter()
+    .filter_map(|file| {
+      let (mut score, positions) = match_file(file, &query)?;
+      if !recent_ranks.is_empty() {
+        if let Some(rank) = recent_ranks.get(&recent_key(&file.path.to_string_lossy())) {
+          score += (BONUS_RECENT - rank * BONUS_RECENT_STEP).max(0);
+        }
+      }
+      Some((score, file, positions))
+    })
+    .collect();
+  // Best first; among equals the shorter path, then alphabetical
+  scored.sort_by(|a, b| {
+    b.0
+      .cmp(&a.0)
+      .then_with(|| a.1.relative.len().cmp(&b.1.relative.len()))
+      .then_with(|| a.1.path.cmp(&b.1.path))
+  });
+  scored
+    .into_iter()
+    .take(limit)
+    .map(|(score, file, positions)| to_match(file, score, positions))
+    .collect()
+}
+
+// The workspace a quick open anchored at `anchor` searches, within the access scope
+fn search_root(scope: &AccessScope, anchor: &Path) -> CommandResult<Option<PathBuf>> {
+  scope.check(anchor)?;
+  Ok(
+    anchor
+      .parent()
+      .map(|dir| wiki::scoped_workspace_root(scope, dir)),
+  )
+}
+
+// Quick open: rank the notes in the workspace of `current_file` (or of the most recent file)
+// against `query`. An empty query lists the most recently modified files.
+#[tauri::command]
+pub async fn fuzzy_find_files(
+  state: tauri::State<'_, FileIndexState>,
+  recent_files: tauri::State<'_, RecentFilesState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  query: String,
+  limit: Option<usize>,
+  current_file: Option<String>,
+) -> CommandResult<Vec<FileMatch>> {
+  let recents = recent_files.0.lock().unwrap().clone();
+  let root = {
+    let scope = scope.0.lock().unwrap();
+    match current_file {
+      Some(file) => search_root(&scope, Path::new(&file))?,
+      // The most recent file may be outside the scope by now; then there's nothing to list
+      None => recents
+        .first()
+        .and_then(|recent| search_root(&scope, Path::new(recent)).ok().flatten()),
+    }
+  };
+  let Some(root) = root else {
+    return Ok(Vec::new());
+  };
+
+  let mut indexes = state.0.lock().unwrap();
+  Ok(search(
+    fresh_index(&mut indexes, &root),
+    &query,
+    limit.unwrap_or(DEFAULT_LIMIT),
+    &recents,
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::time::{Duration, SystemTime};
+
+  fn index(paths: &[&str]) -> FileIndex {
+    let root = Path::new("/ws");
+    let notes = paths
+      .iter()
+      .enumerate()
+      .map(|(i, path)| NoteFile {
+        path: root.join(path),
+        modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(i as u64)),
+      })
+      .collect();
+    FileIndex::new(root, notes)
+  }
+
+  fn relative_paths(matches: &[FileMatch]) -> Vec<&str> {
+    matches.iter().map(|m| m.relative_path.as_str()).collect()
+  }
+
+  #[test]
+  fn test_file_name_matches_rank_first() {
+    let index = index(&[
+      "projects/readme/notes.md",
+      "archive/old-readme.md",
+      "readme.md",
+      "docs/api.md",
+    ]);
+    let matches = search(&index, "readme", 10, &[]);
+    assert_eq!(
+      relative_paths(&matches),
+      vec![
+        "readme.md",
+        "archive/old-readme.md",
+        "projects/readme/notes.md"
+      ]
+    );
+  }
+
+  #[test]
+  fn test_match_indices_point_at_matched_characters() {
+    let index = index(&["Daily Notes/2026-10-15.md", "docs/DailyNotes.md"]);
+    let matches = search(&index, "dn", 10, &[]);
+    assert_eq!(matches[0].relative_path, "docs/DailyNotes.md");
+    assert_eq!(matches[0].match_indices, vec![5, 10]);
+    let matches = search(&index, "notes 15", 10, &[]);
+    assert_eq!(relative_paths(&matches), vec!["Daily Notes/2026-10-15.md"]);
+  }
+
+  #[test]
+  fn test_recent_files_get_a_boost() {
+    let index = index(&["a/todo.md", "b/todo.md"]);
+    assert_eq!(search(&index, "todo", 1, &[])[0].relative_path, "a/todo.md");
+    let recents = vec!["/ws/b/todo.md".to_string()];
+    assert_eq!(
+      search(&index, "todo", 1, &recents)[0].relative_path,
+      "b/todo.md"
+    );
+  }
+
+  #[test]
+  fn test_empty_query_lists_recently_modified() {
+    let index = index(&["old.md", "newer.md", "newest.md"]);
+    assert_eq!(
+      relative_paths(&search(&index, "  ", 2, &[])),
+      vec!["newest.md", "newer.md"]
+    );
+  }
+
+  #[test]
+  fn test_anchors_outside_the_scope_are_denied() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    std::fs::create_dir_all(dir.path().join(".git")).unwrap();
+    std::fs::create_dir(&notes).unwrap();
+    let anchor = notes.join("today.md");
+
+    let error = search_root(&AccessScope::default(), &anchor).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    // The workspace marker above the approved folder doesn't widen the search
+    let scope = AccessScope::with_folders(&[&notes]);
+    assert_eq!(search_root(&scope, &anchor).unwrap(), Some(notes.clone()));
+    let scope = AccessScope::with_folders(&[dir.path()]);
+    assert_eq!(
+      search_root(&scope, &anchor).unwrap(),
+      Some(dir.path().to_path_buf())
+    );
+  }
+
+  // Timings depend on the machine, so this only reports them:
+  // `cargo test --release -- --ignored --nocapture test_large_workspace_timings`
+  #[test]
+  #[ignore = "reports timings; run explicitly"]
+  fn test_large_workspace_timings() {
+    let paths: Vec<String> = (0..10_000)
+      .map(|i| format!("area-{}/project {}/notes/meeting-{}.md", i % 17, i % 211, i))
+      .collect();
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+
+    let started = Instant::now();
+    let index = index(&paths);
+    let indexing = started.elapsed();
+    let started = Instant::now();
+    let matches = search(&index, "proj12meet9", 20, &[]);
+    let querying = started.elapsed();
+
+    assert!(!matches.is_empty());
+    eprintln!(
+      "indexing 10000 paths took {:?}, querying took {:?}",
+      indexing, querying
+    );
+  }
+}
@@ -0,0 +1,196 @@
+use crate::settings::FilenameSeparator;
+
+// Name used when a document has no heading to derive one from
+pub const DEFAULT_FILE_STEM: &str = "Untitled";
+
+// Longest stem we suggest, in characters (well under every platform's limit)
+const MAX_STEM_CHARS: usize = 60;
+
+// Characters Windows doesn't allow in file names (and `/` everywhere)
+const INVALID_FILENAME_CHARS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+// Device names Windows reserves regardless of extension
+const RESERVED_WINDOWS_NAMES: &[&str] = &[
+  "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+  "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+// Text of the first ATX or setext heading, skipping frontmatter and code blocks
+pub fn first_heading(content: &str) -> Option<String> {
+  let mut lines = content.lines().peekable();
+  if lines.peek().map(|l| l.trim_end()) == Some("---") {
+    lines.next();
+    for line in lines.by_ref() {
+      if matches!(line.trim_end(), "---" | "...") {
+        break;
+      }
+    }
+  }
+
+  let mut in_fence = false;
+  let mut previous: Option<&str> = None;
+  for line in lines {
+    let trimmed = line.trim();
+    if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+      in_fence = !in_fence;
+      previous = None;
+      continue;
+    }
+    if in_fence {
+      continue;
+    }
+
+    let hashes = trimmed.chars().take_while(|c| *c == '#').count();
+    if (1..=6).contains(&hashes) {
+      let rest = &trimmed[hashes..];
+      if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        let text = rest.trim().trim_end_matches('#').trim();
+        if !text.is_empty() {
+          return Some(text.to_string());
+        }
+      }
+    }
+
+    let is_underline = !trimmed.is_empty()
+      && (trimmed.chars().all(|c| c == '=') || trimmed.chars().all(|c| c == '-'));
+    if let (true, Some(text)) = (is_underline, previous) {
+      return Some(text.to_string());
+    }
+    previous = (!trimmed.is_empty()).then_some(trimmed);
+  }
+  None
+}
+
+// Reduce inline markdown to its text: `[Link](url)` -> `Link`, `**bold**` -> `bold`,
+// `<b>x</b>` -> `x`
+pub fn strip_inline_markdown(text: &str) -> String {
+  let mut plain = String::with_capacity(text.len());
+  let mut chars = text.chars();
+  let mut previous = None;
+  while let Some(c) = chars.next() {
+    match c {
+      '*' | '_' | '~' | '`' | '[' | ']' | '!' => {}
+      // Drop the URL part of a link or image
+      '(' if previous == Some(']') => {
+        for c in chars.by_ref() {
+          if c == ')' {
+            break;
+          }
+        }
+      }
+      '<' => {
+        for c in chars.by_ref() {
+          if c == '>' {
+            break;
+          }
+        }
+      }
+      '\\' => {
+        if let Some(escaped) = chars.next() {
+          plain.push(escaped);
+        }
+      }
+      _ => plain.push(c),
+    }
+    previous = Some(c);
+  }
+  plain
+}
+
+// Turn arbitrary text into a file name stem that is valid on every platform
+pub fn sanitize_file_stem(text: &str, separator: FilenameSeparator) -> Option<String> {
+  let cleaned: String = text
+    .chars()
+    .map(|c| {
+      if c.is_control() || INVALID_FILENAME_CHARS.contains(&c) {
+        ' '
+      } else {
+        c
+      }
+    })
+    .collect();
+
+  let (joiner, words) = match separator {
+    FilenameSeparator::Dash => ("-", cleaned.to_lowercase()),
+    FilenameSeparator::Space => (" ", cleaned),
+  };
+  let joined = words.split_whitespace().collect::<Vec<_>>().join(joiner);
+
+  let mut stem: String = joined.chars().take(MAX_STEM_CHARS).collect();
+  // Windows drops trailing dots and spaces; dangling dashes just look odd
+  stem = stem
+    .trim_end_matches(['.', ' ', '-'])
+    .trim_start_matches('.')
+    .to_string();
+  if stem.is_empty() {
+    return None;
+  }
+  if RESERVED_WINDOWS_NAMES.contains(&stem.to_lowercase().as_str()) {
+    stem.push('_');
+  }
+  Some(stem)
+}
+
+// Suggested `.md` file name for a document, from its first heading
+pub fn suggest_file_name(content: &str, separator: FilenameSeparator) -> String {
+  let stem = first_heading(content)
+    .and_then(|heading| sanitize_file_stem(&strip_inline_markdown(&heading), separator))
+    .unwrap_or_else(|| DEFAULT_FILE_STEM.to_string());
+  format!("{}.md", stem)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_first_heading() {
+    assert_eq!(
+      first_heading("intro\n\n## Meeting notes ##\n# Later"),
+      Some("Meeting notes".to_string())
+    );
+    assert_eq!(
+      first_heading("---\ntitle: x\n---\n```\n# not a heading\n```\nSetext title\n===\n"),
+      Some("Setext title".to_string())
+    );
+    assert_eq!(first_heading("#hashtag\nplain text"), None);
+  }
+
+  #[test]
+  fn test_suggest_file_name_strips_markdown_and_invalid_chars() {
+    let content = "# **Q3 plan:** [roadmap](https://x.y/z) \"draft\"? *v2*\n";
+    assert_eq!(
+      suggest_file_name(content, FilenameSeparator::Dash),
+      "q3-plan-roadmap-draft-v2.md"
+    );
+    assert_eq!(
+      suggest_file_name(content, FilenameSeparator::Space),
+      "Q3 plan roadmap draft v2.md"
+    );
+  }
+
+  #[test]
+  fn test_suggest_file_name_fallbacks() {
+    assert_eq!(
+      suggest_file_name("no heading here", FilenameSeparator::Dash),
+      "Untitled.md"
+    );
+    assert_eq!(
+      suggest_file_name("# ???\n", FilenameSeparator::Dash),
+      "Untitled.md"
+    );
+    assert_eq!(
+      suggest_file_name("# CON\n", FilenameSeparator::Space),
+      "CON_.md"
+    );
+  }
+
+  #[test]
+  fn test_long_headings_are_truncated() {
+    let content = format!("# {}\n", "word ".repeat(40));
+    let name = suggest_file_name(&content, FilenameSeparator::Dash);
+    let stem = name.trim_end_matches(".md");
+    assert!(stem.chars().count() <= MAX_STEM_CHARS);
+    assert!(!stem.ends_with('-'));
+  }
+}
@@ -0,0 +1,54 @@
+use std::collections::BTreeSet;
+
+use crate::error::{CommandError, CommandResult};
+
+// Families among `faces` (family names and whether the face is fixed-pitch) that have a
+// monospaced face, sorted and without duplicates
+fn monospace_families<'a>(faces: impl IntoIterator<Item = (&'a str, bool)>) -> Vec<String> {
+  faces
+    .into_iter()
+    .filter(|(family, monospaced)| *monospaced && !family.trim().is_empty())
+    .map(|(family, _)| family.trim().to_string())
+    .collect::<BTreeSet<_>>()
+    .into_iter()
+    .collect()
+}
+
+// Installed font families with a monospaced face, for the editor font picker. Scanning the
+// system fonts takes a while on machines with many installed, so it runs off the main
+// thread.
+#[tauri::command]
+pub async fn list_system_fonts() -> CommandResult<Vec<String>> {
+  tauri::async_runtime::spawn_blocking(|| {
+    let mut database = fontdb::Database::new();
+    database.load_system_fonts();
+    monospace_families(database.faces().flat_map(|face| {
+      face
+        .families
+        .iter()
+        .map(move |(family, _)| (family.as_str(), face.monospaced))
+    }))
+  })
+  .await
+  .map_err(|e| CommandError::io("Failed to list fonts", e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_monospace_families_are_sorted_and_unique() {
+    let faces = [
+      ("JetBrains Mono", true),
+      ("Helvetica", false),
+      ("Fira Code", true),
+      ("JetBrains Mono", true),
+      (" ", true),
+    ];
+    assert_eq!(
+      monospace_families(faces),
+      vec!["Fira Code", "JetBrains Mono"]
+    );
+  }
+}
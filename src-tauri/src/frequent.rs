@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::app_store;
+use crate::error::CommandResult;
+use crate::{normalize_recent_path, now_millis, recent_paths_equal, RecentFilesState};
+
+// Store key holding how often each file was opened, highest score first
+pub const FREQUENT_FILES_KEY: &str = "frequent_files";
+
+// Files we keep counting; the lowest scoring ones are dropped beyond this
+const MAX_FREQUENT_ENTRIES: usize = 200;
+
+// An open counts half as much after this long, so last year's project fades behind this
+// week's even if it was opened far more often
+const SCORE_HALF_LIFE_MILLIS: f64 = 30.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+// How many frequent files get_frequent_files returns when no limit is given
+const DEFAULT_FREQUENT_LIMIT: usize = 5;
+
+// How often a file was opened. `score` is as of `last_opened`; it decays from there.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrequencyEntry {
+  // Normalized like recents
+  pub path: String,
+  pub open_count: u32,
+  pub score: f64,
+  // Milliseconds since the epoch
+  pub last_opened: u64,
+}
+
+// Frequent file as returned to the frontend; like RecentFileEntry, plus the counts
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FrequentFileEntry {
+  pub path: String,
+  pub display_path: String,
+  pub exists: bool,
+  pub open_count: u32,
+  // Decayed to now
+  pub score: f64,
+}
+
+// The entry's score at `now`, halved for every half-life since it was last opened
+fn decayed_score(entry: &FrequencyEntry, now: u64) -> f64 {
+  let elapsed = now.saturating_sub(entry.last_opened) as f64;
+  entry.score * 0.5_f64.powf(elapsed / SCORE_HALF_LIFE_MILLIS)
+}
+
+// Count an open of `path` at `now`, evicting the lowest scores beyond the cap
+fn record(entries: &mut Vec<FrequencyEntry>, path: &str, now: u64) {
+  match entries
+    .iter_mut()
+    .find(|entry| recent_paths_equal(&entry.path, path))
+  {
+    Some(entry) => {
+      entry.score = decayed_score(entry, now) + 1.0;
+      entry.open_count = entry.open_count.saturating_add(1);
+      entry.last_opened = now;
+    }
+    // At the front, so it beats older entries with the same score to a place under the cap
+    None => entries.insert(
+      0,
+      FrequencyEntry {
+        path: path.to_string(),
+        open_count: 1,
+        score: 1.0,
+        last_opened: now,
+      },
+    ),
+  }
+  entries.sort_by(|a, b| decayed_score(b, now).total_cmp(&decayed_score(a, now)));
+  entries.truncate(MAX_FREQUENT_ENTRIES);
+}
+
+// The highest scoring entries that aren't in `recents` (already listed there), best first
+fn top_entries<'a>(
+  entries: &'a [FrequencyEntry],
+  recents: &[String],
+  limit: usize,
+  now: u64,
+) -> Vec<(&'a FrequencyEntry, f64)> {
+  let mut top: Vec<(&FrequencyEntry, f64)> = entries
+    .iter()
+    .filter(|entry| !recents.iter().any(|p| recent_paths_equal(p, &entry.path)))
+    .map(|entry| (entry, decayed_score(entry, now)))
+    .collect();
+  top.sort_by(|a, b| b.1.total_cmp(&a.1));
+  top.truncate(limit);
+  top
+}
+
+fn load_entries(app: &AppHandle) -> CommandResult<Vec<FrequencyEntry>> {
+  app_store::read_list(app, FREQUENT_FILES_KEY)
+}
+
+fn save_entries(app: &AppHandle, entries: &[FrequencyEntry]) -> CommandResult<()> {
+  app_store::write_list(app, FREQUENT_FILES_KEY, "open counts", entries)
+}
+
+// Count an open of `path` (errors are logged; the file still opens)
+pub fn record_open(app: &AppHandle, path: &str) {
+  let result = load_entries(app).and_then(|mut entries| {
+    record(&mut entries, &normalize_recent_path(path), now_millis());
+    save_entries(app, &entries)
+  });
+  if let Err(e) = result {
+    log::error!("Failed to record file open: {}", e);
+  }
+}
+
+// The most frequently opened files that aren't already in the recents list
+#[tauri::command]
+pub async fn get_frequent_files(
+  app: AppHandle,
+  state: tauri::State<'_, RecentFilesState>,
+  limit: Option<usize>,
+) -> CommandResult<Vec<FrequentFileEntry>> {
+  let recents = state.0.lock().unwrap().clone();
+  let entries = load_entries(&app)?;
+  let top = top_entries(
+    &entries,
+    &recents,
+    limit.unwrap_or(DEFAULT_FREQUENT_LIMIT),
+    now_millis(),
+  );
+  let paths: Vec<String> = top.iter().map(|(entry, _)| entry.path.clone()).collect();
+  let exists = crate::check_paths_exist(&paths, crate::RECENT_EXISTS_TIMEOUT);
+  let home_dir = app.path().home_dir().ok();
+  Ok(
+    top
+      .into_iter()
+      .zip(exists)
+      .map(|((entry, score), exists)| FrequentFileEntry {
+        path: entry.path.clone(),
+        display_path: crate::display_recent_path(&entry.path, home_dir.as_deref()),
+        exists,
+        open_count: entry.open_count,
+        score,
+      })
+      .collect(),
+  )
+}
+
+// Forget every open count (clearing recents leaves them, so this is asked separately)
+#[tauri::command]
+pub async fn clear_frequent_files(app: AppHandle) -> CommandResult<()> {
+  save_entries(&app, &[])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DAY: u64 = 24 * 60 * 60 * 1000;
+
+  fn paths(top: &[(&FrequencyEntry, f64)]) -> Vec<String> {
+    top.iter().map(|(entry, _)| entry.path.clone()).collect()
+  }
+
+  #[test]
+  fn test_record_counts_opens_and_decays_the_score() {
+    let mut entries = Vec::new();
+    record(&mut entries, "/notes/a.md", 0);
+    record(&mut entries, "/notes/a.md", 30 * DAY);
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].open_count, 2);
+    // The first open is worth half after one half-life
+    assert!((entries[0].score - 1.5).abs() < 1e-9);
+    assert!((decayed_score(&entries[0], 90 * DAY) - 0.375).abs() < 1e-9);
+  }
+
+  #[test]
+  fn test_recent_opens_outrank_old_habits() {
+    let mut entries = Vec::new();
+    for day in 0..50 {
+      record(&mut entries, "/old/project.md", day * DAY);
+    }
+    let now = 365 * DAY;
+    for _ in 0..3 {
+      record(&mut entries, "/new/project.md", now - DAY);
+    }
+    let top = top_entries(&entries, &[], 5, now);
+    assert_eq!(paths(&top), vec!["/new/project.md", "/old/project.md"]);
+    assert_eq!(top[1].0.open_count, 50);
+  }
+
+  #[test]
+  fn test_top_skips_recents_and_respects_the_limit() {
+    let mut entries = Vec::new();
+    for (path, opens) in [("/a.md", 3), ("/b.md", 2), ("/c.md", 1)] {
+      for _ in 0..opens {
+        record(&mut entries, path, 0);
+      }
+    }
+    let top = top_entries(&entries, &["/a.md".to_string()], 1, 0);
+    assert_eq!(paths(&top), vec!["/b.md"]);
+  }
+
+  #[test]
+  fn test_record_evicts_the_lowest_scores() {
+    let mut entries = Vec::new();
+    record(&mut entries, "/kept.md", 0);
+    record(&mut entries, "/kept.md", 0);
+    for index in 0..MAX_FREQUENT_ENTRIES {
+      record(&mut entries, &format!("/{}.md", index), 0);
+    }
+    assert_eq!(entries.len(), MAX_FREQUENT_ENTRIES);
+    assert_eq!(entries[0].path, "/kept.md");
+    assert_eq!(entries[1].path, format!("/{}.md", MAX_FREQUENT_ENTRIES - 1));
+  }
+}
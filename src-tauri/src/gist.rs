@@ -0,0 +1,336 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::error::{CommandError, CommandResult};
+use crate::{http, now_millis, secrets};
+
+const GITHUB_API: &str = "https://api.github.com";
+const GITHUB: &str = "GitHub";
+
+// Secret holding a GitHub token with the `gist` scope (see secrets.rs)
+pub const GITHUB_TOKEN_KEY: &str = "github_token";
+
+// Frontmatter key a published document keeps its gist id under, so publishing it again
+// updates the same gist
+const GIST_ID_KEY: &str = "gist_id";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize)]
+struct GistFile<'a> {
+  content: &'a str,
+}
+
+#[derive(Serialize)]
+struct GistRequest<'a> {
+  description: &'a str,
+  // Only on create; GitHub doesn't let an existing gist change visibility
+  #[serde(skip_serializing_if = "Option::is_none")]
+  public: Option<bool>,
+  files: BTreeMap<&'a str, GistFile<'a>>,
+}
+
+#[derive(Deserialize)]
+struct GistResponse {
+  id: String,
+  html_url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PublishedGist {
+  pub id: String,
+  pub url: String,
+  // False when a new gist was created
+  pub updated: bool,
+}
+
+// Gist ids are hex; anything else in the frontmatter isn't ours to put in a URL
+fn valid_gist_id(id: &str) -> bool {
+  !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+// The `gist_id` a previously published document recorded in its frontmatter
+fn frontmatter_gist_id(content: &str) -> Option<String> {
+  let mut lines = content.lines();
+  if lines.next().map(str::trim_end) != Some("---") {
+    return None;
+  }
+  for line in lines {
+    let line = line.trim();
+    if line == "---" || line == "..." {
+      break;
+    }
+    let Some((key, value)) = line.split_once(':') else {
+      continue;
+    };
+    if key.trim() == GIST_ID_KEY {
+      let id = value.trim().trim_matches(['"', '\'']);
+      return valid_gist_id(id).then(|| id.to_string());
+    }
+  }
+  None
+}
+
+// What a failed GitHub response means. `remaining` and `reset` are the x-ratelimit headers
+// (reset in seconds since the epoch), `retry_after` the Retry-After header in seconds.
+fn status_error(
+  status: u16,
+  remaining: Option<u64>,
+  reset: Option<u64>,
+  retry_after: Option<u64>,
+  message: &str,
+) -> CommandError {
+  let rate_limited = matches!(status, 403 | 429) && (remaining == Some(0) || retry_after.is_some());
+  if rate_limited {
+    let retry_at = match retry_after {
+      Some(seconds) => Some(now_millis() + seconds * 1000),
+      None => reset.map(|seconds| seconds * 1000),
+    };
+    return CommandError::RateLimited {
+      service: GITHUB.to_string(),
+      retry_at,
+    };
+  }
+  match status {
+    401 => CommandError::AuthFailed {
+      service: GITHUB.to_string(),
+      reason: "the token is invalid or expired".to_string(),
+    },
+    // A valid token that isn't allowed to touch gists
+    403 => CommandError::AuthFailed {
+      service: GITHUB.to_string(),
+      reason: "the token is missing the gist scope".to_string(),
+    },
+    422 => CommandError::invalid_data(format!("GitHub rejected the gist: {}", message)),
+    _ => CommandError::Network {
+      message: format!("GitHub request failed ({}): {}", status, message),
+    },
+  }
+}
+
+async fn check_response(response: reqwest::Response) -> CommandResult<reqwest::Response> {
+  let status = response.status();
+  if status.is_success() {
+    return Ok(response);
+  }
+  let header = |name: &str| {
+    response
+      .headers()
+      .get(name)
+      .and_then(|value| value.to_str().ok())
+      .and_then(|value| value.trim().parse::<u64>().ok())
+  };
+  let remaining = header("x-ratelimit-remaining");
+  let reset = header("x-ratelimit-reset");
+  let retry_after = header("retry-after");
+  #[derive(Deserialize)]
+  struct ErrorBody {
+    message: String,
+  }
+  let message = response
+    .json::<ErrorBody>()
+    .await
+    .map(|body| body.message)
+    .unwrap_or_else(|_| status.canonical_reason().unwrap_or("").to_string());
+  Err(status_error(
+    status.as_u16(),
+    remaining,
+    reset,
+    retry_after,
+    &message,
+  ))
+}
+
+struct GitHub {
+  client: reqwest::Client,
+  token: String,
+}
+
+impl GitHub {
+  fn new(app: &AppHandle) -> CommandResult<Self> {
+    let token =
+      secrets::read_secret(app, GITHUB_TOKEN_KEY)?.ok_or_else(|| CommandError::AuthFailed {
+        service: GITHUB.to_string(),
+        reason: "no token has been saved".to_string(),
+      })?;
+    Ok(GitHub {
+      client: http::client(REQUEST_TIMEOUT)?,
+      token,
+    })
+  }
+
+  async fn send(
+    &self,
+    method: reqwest::Method,
+    path: &str,
+    body: Option<&GistRequest<'_>>,
+  ) -> CommandResult<reqwest::Response> {
+    let mut request = self
+      .client
+      .request(method, format!("{}{}", GITHUB_API, path))
+      .bearer_auth(&self.token)
+      .header("Accept", "application/vnd.github+json")
+      .header("X-GitHub-Api-Version", "2022-11-28");
+    if let Some(body) = body {
+      request = request.json(body);
+    }
+    request
+      .send()
+      .await
+      .map_err(|e| http::request_error("GitHub request failed", e))
+  }
+
+  // Update gist `id`, or create a new gist when there's none or it was deleted on GitHub
+  async fn publish(
+    &self,
+    id: Option<&str>,
+    public: bool,
+    mut body: GistRequest<'_>,
+  ) -> CommandResult<GistResponse> {
+    let mut response = None;
+    if let Some(id) = id {
+      let updated = self
+        .send(
+          reqwest::Method::PATCH,
+          &format!("/gists/{}", id),
+          Some(&body),
+        )
+        .await?;
+      if updated.status() != reqwest::StatusCode::NOT_FOUND {
+        response = Some(updated);
+      }
+    }
+    let response = match response {
+      Some(response) => response,
+      None => {
+        body.public = Some(public);
+        self
+          .send(reqwest::Method::POST, "/gists", Some(&body))
+          .await?
+      }
+    };
+    check_response(response)
+      .await?
+      .json::<GistResponse>()
+      .await
+      .map_err(|e| CommandError::invalid_data(format!("Unexpected response from GitHub: {}", e)))
+  }
+}
+
+// Publish `content` as a gist with a single file. A document that was published before
+// (it has a `gist_id` in its frontmatter) updates that gist. The frontend copies the URL
+// and records the id back into the frontmatter.
+#[tauri::command]
+pub async fn publish_gist(
+  app: AppHandle,
+  content: String,
+  filename: String,
+  description: String,
+  public: bool,
+) -> CommandResult<PublishedGist> {
+  let filename = filename.trim();
+  if filename.is_empty() || filename.contains(['/', '\\']) {
+    return Err(CommandError::invalid_data(format!(
+      "Invalid gist file name: {:?}",
+      filename
+    )));
+  }
+  let existing = frontmatter_gist_id(&content);
+  let github = GitHub::new(&app)?;
+  let body = GistRequest {
+    description: &description,
+    public: None,
+    files: BTreeMap::from([(filename, GistFile { content: &content })]),
+  };
+  let gist = github.publish(existing.as_deref(), public, body).await?;
+  Ok(PublishedGist {
+    updated: existing.as_deref() == Some(gist.id.as_str()),
+    id: gist.id,
+    url: gist.html_url,
+  })
+}
+
+// Delete a published gist. One that's already gone counts as deleted.
+#[tauri::command]
+pub async fn delete_gist(app: AppHandle, id: String) -> CommandResult<()> {
+  if !valid_gist_id(&id) {
+    return Err(CommandError::invalid_data(format!(
+      "Invalid gist id: {:?}",
+      id
+    )));
+  }
+  let github = GitHub::new(&app)?;
+  let response = github
+    .send(reqwest::Method::DELETE, &format!("/gists/{}", id), None)
+    .await?;
+  if response.status() == reqwest::StatusCode::NOT_FOUND {
+    return Ok(());
+  }
+  check_response(response).await?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_frontmatter_gist_id() {
+    let doc = "---\ntitle: Notes\ngist_id: \"aa5a315d61ae9438b18d\"\n---\n\n# Notes\n";
+    assert_eq!(
+      frontmatter_gist_id(doc).as_deref(),
+      Some("aa5a315d61ae9438b18d")
+    );
+    assert_eq!(frontmatter_gist_id("# Notes\ngist_id: abc\n"), None);
+    assert_eq!(
+      frontmatter_gist_id("---\ntitle: x\n---\ngist_id: abc\n"),
+      None
+    );
+    // Not something to splice into an API path
+    assert_eq!(frontmatter_gist_id("---\ngist_id: ../user\n---\n"), None);
+  }
+
+  #[test]
+  fn test_status_errors_have_distinct_codes() {
+    assert_eq!(
+      status_error(401, None, None, None, "").code(),
+      "auth_failed"
+    );
+    assert_eq!(
+      status_error(403, Some(12), None, None, "").code(),
+      "auth_failed"
+    );
+    assert_eq!(
+      status_error(403, Some(0), Some(1_700_000_000), None, ""),
+      CommandError::RateLimited {
+        service: "GitHub".to_string(),
+        retry_at: Some(1_700_000_000_000),
+      }
+    );
+    // Secondary rate limits only send Retry-After
+    assert_eq!(
+      status_error(429, None, None, Some(60), "").code(),
+      "rate_limited"
+    );
+    assert_eq!(
+      status_error(422, None, None, None, "").code(),
+      "invalid_data"
+    );
+    assert_eq!(status_error(502, None, None, None, "").code(), "network");
+  }
+
+  #[test]
+  fn test_update_request_leaves_visibility_alone() {
+    let body = GistRequest {
+      description: "Notes",
+      public: None,
+      files: BTreeMap::from([("notes.md", GistFile { content: "# Hi" })]),
+    };
+    assert_eq!(
+      serde_json::to_value(&body).unwrap(),
+      serde_json::json!({ "description": "Notes", "files": { "notes.md": { "content": "# Hi" } } })
+    );
+  }
+}
@@ -0,0 +1,106 @@
+use serde::Serialize;
+
+use crate::error::CommandResult;
+use crate::includes::{atx_heading, is_fence};
+use crate::typography::frontmatter_end;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HeadingShift {
+  pub text: String,
+  // Some heading would have gone past H1 or H6 and was stopped there
+  pub clamped: bool,
+}
+
+// Move every ATX heading in `markdown` `delta` levels down (positive) or up (negative),
+// stopping at H1 and H6. Lines in frontmatter, fenced code and HTML comments are left alone.
+pub fn shift(markdown: &str, delta: i32) -> HeadingShift {
+  let (frontmatter, body) = markdown.split_at(frontmatter_end(markdown).unwrap_or(0));
+  let mut text = String::with_capacity(markdown.len() + 16);
+  text.push_str(frontmatter);
+  let mut clamped = false;
+  let mut fence: Option<&str> = None;
+  let mut in_comment = false;
+
+  for line in body.split_inclusive('\n') {
+    if let Some(marker) = fence {
+      if line.trim_start().starts_with(marker) {
+        fence = None;
+      }
+      text.push_str(line);
+      continue;
+    }
+    if in_comment {
+      in_comment = !line.contains("-->");
+      text.push_str(line);
+      continue;
+    }
+    if is_fence(line) {
+      fence = Some(&line.trim_start()[..3]);
+      text.push_str(line);
+      continue;
+    }
+    if let Some(start) = line.rfind("<!--") {
+      in_comment = !line[start..].contains("-->");
+    }
+
+    match atx_heading(line) {
+      Some((level, after)) => {
+        let target = level as i32 + delta;
+        clamped |= !(1..=6).contains(&target);
+        let indent = &line[..line.len() - after.len() - level];
+        text.push_str(indent);
+        text.push_str(&"#".repeat(target.clamp(1, 6) as usize));
+        text.push_str(after);
+      }
+      None => text.push_str(line),
+    }
+  }
+  HeadingShift { text, clamped }
+}
+
+#[tauri::command]
+pub async fn shift_headings(markdown: String, delta: i32) -> CommandResult<HeadingShift> {
+  Ok(shift(&markdown, delta))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DOCUMENT: &str = "\
+---
+# not a heading
+---
+# Title
+Text with a # sign
+## Section ##
+```sh
+# a comment
+```
+<!--
+# commented out
+-->
+   ###### Deep
+";
+
+  #[test]
+  fn test_shifts_headings_outside_code_and_comments() {
+    let down = shift(DOCUMENT, 1);
+    assert_eq!(
+      down.text,
+      "---\n# not a heading\n---\n## Title\nText with a # sign\n### Section ##\n```sh\n\
+       # a comment\n```\n<!--\n# commented out\n-->\n   ###### Deep\n"
+    );
+    assert!(down.clamped);
+
+    let up = shift(DOCUMENT, -1);
+    assert!(up.text.contains("\n# Title\n"));
+    assert!(up.text.contains("\n# Section ##\n"));
+    assert!(up.text.contains("\n   ##### Deep\n"));
+    assert!(up.clamped);
+
+    let within = shift("## A\n### B", -1);
+    assert_eq!(within.text, "# A\n## B");
+    assert!(!within.clamped);
+  }
+}
@@ -0,0 +1,107 @@
+use serde::Serialize;
+use tauri::AppHandle;
+use tauri_plugin_opener::OpenerExt;
+
+use crate::error::CommandResult;
+
+const DOCUMENTATION_URL: &str = "https://github.com/Avidity-Studio/markdowner#readme";
+const NEW_ISSUE_URL: &str = "https://github.com/Avidity-Studio/markdowner/issues/new";
+
+// Versions and platform, shown in the shortcuts view and put into bug reports
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AppInfo {
+  pub version: String,
+  pub tauri_version: String,
+  pub os: String,
+  pub arch: String,
+}
+
+// The pages the Help menu opens. Only these are ever handed to the opener, so nothing the
+// webview sends can make us open an arbitrary URL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HelpLink {
+  Documentation,
+  ReportIssue,
+}
+
+impl HelpLink {
+  pub fn url(self, info: &AppInfo) -> String {
+    match self {
+      HelpLink::Documentation => DOCUMENTATION_URL.to_string(),
+      HelpLink::ReportIssue => format!(
+        "{}?body={}",
+        NEW_ISSUE_URL,
+        percent_encode(&issue_template(info))
+      ),
+    }
+  }
+}
+
+pub fn app_info(app: &AppHandle) -> AppInfo {
+  AppInfo {
+    version: app.package_info().version.to_string(),
+    tauri_version: tauri::VERSION.to_string(),
+    os: std::env::consts::OS.to_string(),
+    arch: std::env::consts::ARCH.to_string(),
+  }
+}
+
+fn issue_template(info: &AppInfo) -> String {
+  format!(
+    "**What happened?**\n\n\n**Steps to reproduce**\n\n\n---\nMarkdowner {} (Tauri {}) on {} {}\n",
+    info.version, info.tauri_version, info.os, info.arch
+  )
+}
+
+// Encode everything but RFC 3986 unreserved characters, for use in a query string
+fn percent_encode(text: &str) -> String {
+  let mut encoded = String::with_capacity(text.len());
+  for byte in text.bytes() {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+        encoded.push(byte as char)
+      }
+      _ => encoded.push_str(&format!("%{:02X}", byte)),
+    }
+  }
+  encoded
+}
+
+// Open a Help page in the default browser. Called from the menu, so failures are logged.
+pub fn open_help_link(app: &AppHandle, link: HelpLink) {
+  let url = link.url(&app_info(app));
+  if let Err(e) = app.opener().open_url(url, None::<&str>) {
+    log::error!("Failed to open {:?}: {}", link, e);
+  }
+}
+
+#[tauri::command]
+pub async fn get_app_info(app: AppHandle) -> CommandResult<AppInfo> {
+  Ok(app_info(&app))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_report_issue_url_includes_app_info() {
+    let info = AppInfo {
+      version: "1.2.0".to_string(),
+      tauri_version: "2.5.1".to_string(),
+      os: "macos".to_string(),
+      arch: "aarch64".to_string(),
+    };
+    let url = HelpLink::ReportIssue.url(&info);
+    assert!(
+      url.starts_with("https://github.com/Avidity-Studio/markdowner/issues/new?body=%2A%2AWhat")
+    );
+    assert!(url.contains("Markdowner%201.2.0%20%28Tauri%202.5.1%29%20on%20macos%20aarch64%0A"));
+    assert_eq!(HelpLink::Documentation.url(&info), DOCUMENTATION_URL);
+  }
+
+  #[test]
+  fn test_percent_encode() {
+    assert_eq!(percent_encode("a b&c=d/é~"), "a%20b%26c%3Dd%2F%C3%A9~");
+  }
+}
@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use crate::error::{CommandError, CommandResult};
+
+// Requests the app makes on its own behalf (publishing, link checks) identify themselves;
+// GitHub's API rejects requests without a User-Agent
+pub const USER_AGENT: &str = concat!("Markdowner/", env!("CARGO_PKG_VERSION"));
+
+// Requests from clients built from this give up after `timeout`, so a flaky network fails
+// the command instead of leaving it hanging
+pub fn builder(timeout: Duration) -> reqwest::ClientBuilder {
+  reqwest::Client::builder()
+    .user_agent(USER_AGENT)
+    .timeout(timeout)
+}
+
+pub fn client(timeout: Duration) -> CommandResult<reqwest::Client> {
+  builder(timeout)
+    .build()
+    .map_err(|e| CommandError::io("Failed to set up HTTP client", e))
+}
+
+// A request that didn't get a response at all
+pub fn request_error(context: &str, error: reqwest::Error) -> CommandError {
+  let message = if error.is_timeout() {
+    format!("{}: the request timed out", context)
+  } else if error.is_connect() {
+    format!("{}: could not connect", context)
+  } else {
+    format!("{}: {}", context, error)
+  };
+  CommandError::Network { message }
+}
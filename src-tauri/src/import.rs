@@ -0,0 +1,1490 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+use tauri_plugin_dialog::DialogExt;
+
+use crate::access_scope::AccessScopeState;
+use crate::error::{CommandError, CommandResult};
+
+// Offered by the import dialog
+pub const IMPORT_EXTENSIONS: &[&str] = &["org", "adoc", "asciidoc", "textile"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+  Org,
+  AsciiDoc,
+  Textile,
+}
+
+impl ImportFormat {
+  pub fn from_path(path: &Path) -> Option<Self> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+      "org" => Some(ImportFormat::Org),
+      "adoc" | "asciidoc" => Some(ImportFormat::AsciiDoc),
+      "textile" => Some(ImportFormat::Textile),
+      _ => None,
+    }
+  }
+
+  // Info string of the fenced blocks that keep what couldn't be converted
+  fn language(self) -> &'static str {
+    match self {
+      ImportFormat::Org => "org",
+      ImportFormat::AsciiDoc => "asciidoc",
+      ImportFormat::Textile => "textile",
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportWarning {
+  // 1-based line in the original document
+  pub line: usize,
+  pub message: String,
+}
+
+// A converted document, opened as unsaved so the original is never written to
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportedDocument {
+  pub content: String,
+  // The original's file name without extension, used as the untitled document's title
+  pub title: String,
+  pub format: ImportFormat,
+  pub warnings: Vec<ImportWarning>,
+}
+
+// The markdown being written, with what got lost on the way
+struct Output {
+  language: &'static str,
+  lines: Vec<String>,
+  warnings: Vec<ImportWarning>,
+}
+
+impl Output {
+  fn new(language: &'static str) -> Self {
+    Output {
+      language,
+      lines: Vec::new(),
+      warnings: Vec::new(),
+    }
+  }
+
+  fn push(&mut self, line: impl Into<String>) {
+    self.lines.push(line.into());
+  }
+
+  // `index` is the 0-based line in the original
+  fn warn(&mut self, index: usize, message: impl Into<String>) {
+    self.warnings.push(ImportWarning {
+      line: index + 1,
+      message: message.into(),
+    });
+  }
+
+  fn fence(&mut self, info: &str, body: &[&str]) {
+    let fence = fence_for(body);
+    self.push(format!("{}{}", fence, info));
+    for line in body {
+      self.push(*line);
+    }
+    self.push(fence);
+  }
+
+  // Keep lines markdown has no equivalent for as a fenced block of the original syntax
+  fn preserve(&mut self, index: usize, lines: &[&str], what: &str) {
+    self.warn(index, format!("{} kept as {} source", what, self.language));
+    self.fence(self.language, lines);
+  }
+
+  // Add what `inner` converted as a blockquote
+  fn quote(&mut self, inner: Output) {
+    for line in inner.lines {
+      self.push(if line.is_empty() {
+        ">".to_string()
+      } else {
+        format!("> {}", line)
+      });
+    }
+    self.warnings.extend(inner.warnings);
+  }
+}
+
+// A backtick fence longer than any run of backticks in the body
+fn fence_for(body: &[&str]) -> String {
+  let longest = body
+    .iter()
+    .filter_map(|line| {
+      let line = line.trim_start();
+      line
+        .starts_with("```")
+        .then(|| line.chars().take_while(|c| *c == '`').count())
+    })
+    .max()
+    .unwrap_or(0);
+  "`".repeat(longest.max(2) + 1)
+}
+
+fn code_span(code: &str) -> String {
+  if code.contains('`') {
+    format!("`` {} ``", code)
+  } else {
+    format!("`{}`", code)
+  }
+}
+
+// Markdown link destinations can't contain spaces unless wrapped in <>
+fn link_destination(target: &str) -> String {
+  if target.contains(' ') {
+    format!("<{}>", target)
+  } else {
+    target.to_string()
+  }
+}
+
+fn blockquote_label(style: &str) -> Option<&'static str> {
+  match style.to_ascii_uppercase().as_str() {
+    "NOTE" => Some("Note"),
+    "TIP" => Some("Tip"),
+    "IMPORTANT" => Some("Important"),
+    "WARNING" => Some("Warning"),
+    "CAUTION" => Some("Caution"),
+    _ => None,
+  }
+}
+
+// What an inline markup character turns into
+#[derive(Clone, Copy)]
+enum Inline {
+  Code,
+  Wrap(&'static str, &'static str),
+}
+
+// Markdown for what starts at an index, and the index after it
+type Special = fn(&[char], usize) -> Option<(String, usize)>;
+
+// Rules for one format's inline markup
+struct InlineSyntax {
+  marker: fn(char) -> Option<Inline>,
+  // Links, images and the like
+  special: Special,
+  // Doubled markers (`**bold**`) work inside words
+  unconstrained: bool,
+}
+
+fn opens_emphasis(chars: &[char], index: usize) -> bool {
+  index == 0 || chars[index - 1].is_whitespace() || "-({[\"'".contains(chars[index - 1])
+}
+
+fn closes_emphasis(chars: &[char], index: usize) -> bool {
+  chars
+    .get(index + 1)
+    .is_none_or(|next| next.is_whitespace() || "-.,;:!?'\")}]".contains(*next))
+}
+
+// Index of the marker closing emphasis that opens at `index`: outside of words, with content
+// that doesn't start or end with whitespace (so `2*3*4` and `a * b` stay as they are)
+fn constrained_end(chars: &[char], index: usize) -> Option<usize> {
+  let marker = chars[index];
+  let first = *chars.get(index + 1)?;
+  if !opens_emphasis(chars, index) || first.is_whitespace() || first == marker {
+    return None;
+  }
+  (index + 2..chars.len()).find(|&end| {
+    chars[end] == marker && !chars[end - 1].is_whitespace() && closes_emphasis(chars, end)
+  })
+}
+
+// Index of the doubled marker closing `**...**` at `index`
+fn unconstrained_end(chars: &[char], index: usize) -> Option<usize> {
+  let marker = chars[index];
+  if chars.get(index + 1) != Some(&marker) || chars.get(index + 2).is_none_or(|c| *c == marker) {
+    return None;
+  }
+  (index + 3..chars.len().saturating_sub(1))
+    .find(|&end| chars[end] == marker && chars[end + 1] == marker)
+}
+
+fn convert_inline(text: &str, syntax: &InlineSyntax) -> String {
+  let chars: Vec<char> = text.chars().collect();
+  let mut out = String::new();
+  let mut index = 0;
+  while index < chars.len() {
+    if let Some((markdown, next)) = (syntax.special)(&chars, index) {
+      out.push_str(&markdown);
+      index = next;
+      continue;
+    }
+    if let Some(inline) = (syntax.marker)(chars[index]) {
+      let span = syntax
+        .unconstrained
+        .then(|| unconstrained_end(&chars, index).map(|end| (index + 2, end, end + 2)))
+        .flatten()
+        .or_else(|| constrained_end(&chars, index).map(|end| (index + 1, end, end + 1)));
+      if let Some((start, end, next)) = span {
+        let inner: String = chars[start..end].iter().collect();
+        match inline {
+          Inline::Code => out.push_str(&code_span(&inner)),
+          Inline::Wrap(open, close) => {
+            out.push_str(open);
+            out.push_str(&convert_inline(&inner, syntax));
+            out.push_str(close);
+          }
+        }
+        index = next;
+        continue;
+      }
+    }
+    out.push(chars[index]);
+    index += 1;
+  }
+  out
+}
+
+fn find_from(chars: &[char], from: usize, pattern: &str) -> Option<usize> {
+  let pattern: Vec<char> = pattern.chars().collect();
+  (from..chars.len()).find(|&index| chars[index..].starts_with(&pattern))
+}
+
+fn collect(chars: &[char]) -> String {
+  chars.iter().collect()
+}
+
+// Rows of cells as a markdown table; the first row is the header
+fn markdown_table(rows: &[Vec<String>]) -> Vec<String> {
+  let columns = rows.iter().map(Vec::len).max().unwrap_or(0).max(1);
+  let row_line = |cells: &[String]| {
+    let mut line = String::from("|");
+    for column in 0..columns {
+      line.push(' ');
+      line.push_str(cells.get(column).map(String::as_str).unwrap_or(""));
+      line.push_str(" |");
+    }
+    line
+  };
+  let mut lines = Vec::new();
+  for (index, row) in rows.iter().enumerate() {
+    lines.push(row_line(row));
+    if index == 0 {
+      lines.push(format!("|{}", " --- |".repeat(columns)));
+    }
+  }
+  lines
+}
+
+fn list_indent(depth: usize, ordered: bool) -> String {
+  " ".repeat(depth.saturating_sub(1) * if ordered { 3 } else { 2 })
+}
+
+// A run of one repeated marker followed by a space, e.g. `** item` -> (2, "item")
+fn nested_marker(line: &str, marker: char) -> Option<(usize, &str)> {
+  let depth = line.chars().take_while(|c| *c == marker).count();
+  let text = line[depth..].strip_prefix(' ')?;
+  (depth > 0).then_some((depth, text))
+}
+
+// Org mode
+
+const ORG_OPEN_KEYWORDS: &[&str] = &["TODO", "NEXT", "WAITING"];
+const ORG_DONE_KEYWORDS: &[&str] = &["DONE", "CANCELED", "CANCELLED"];
+// Keywords that have a front matter field
+const ORG_FRONT_MATTER: &[&str] = &["TITLE", "AUTHOR", "DATE"];
+
+fn org_marker(c: char) -> Option<Inline> {
+  match c {
+    '*' => Some(Inline::Wrap("**", "**")),
+    '/' => Some(Inline::Wrap("*", "*")),
+    '_' => Some(Inline::Wrap("<u>", "</u>")),
+    '+' => Some(Inline::Wrap("~~", "~~")),
+    '=' | '~' => Some(Inline::Code),
+    _ => None,
+  }
+}
+
+// `[[target][description]]` and `[[target]]`
+fn org_link(chars: &[char], index: usize) -> Option<(String, usize)> {
+  if !chars[index..].starts_with(&['[', '[']) {
+    return None;
+  }
+  let end = find_from(chars, index + 2, "]]")?;
+  let inner = collect(&chars[index + 2..end]);
+  let (target, description) = match inner.split_once("][") {
+    Some((target, description)) => (target, Some(description)),
+    None => (inner.as_str(), None),
+  };
+  let target = target.strip_prefix("file:").unwrap_or(target);
+  let markdown = match description {
+    Some(description) => format!(
+      "[{}]({})",
+      convert_inline(description, &ORG_INLINE),
+      link_destination(target)
+    ),
+    None if target.contains("://") => format!("<{}>", target),
+    None => format!("[{}]({})", target, link_destination(target)),
+  };
+  Some((markdown, end + 2))
+}
+
+const ORG_INLINE: InlineSyntax = InlineSyntax {
+  marker: org_marker,
+  special: org_link,
+  unconstrained: false,
+};
+
+fn org_inline(text: &str) -> String {
+  convert_inline(text, &ORG_INLINE)
+}
+
+// `#+KEY: value` (also `#+BEGIN_SRC rust`, which has no colon)
+fn org_keyword(line: &str) -> Option<(String, &str)> {
+  let rest = line.trim_start().strip_prefix("#+")?;
+  let end = rest
+    .find(|c: char| c == ':' || c.is_whitespace())
+    .unwrap_or(rest.len());
+  let value = rest[end..].strip_prefix(':').unwrap_or(&rest[end..]);
+  Some((rest[..end].to_ascii_uppercase(), value.trim()))
+}
+
+// A `#+KEY:` line other than a block or a front matter field
+fn is_org_setting(line: &str) -> bool {
+  org_keyword(line).is_some_and(|(keyword, _)| {
+    !keyword.starts_with("BEGIN_") && !ORG_FRONT_MATTER.contains(&keyword.as_str())
+  })
+}
+
+fn org_heading(line: &str) -> Option<(usize, &str)> {
+  let level = line.chars().take_while(|c| *c == '*').count();
+  let rest = &line[level..];
+  (level > 0 && (rest.is_empty() || rest.starts_with(' '))).then_some((level, rest.trim()))
+}
+
+// A heading's TODO keyword, as whether the task is done, and the rest of its title
+fn org_task(title: &str) -> (Option<bool>, &str) {
+  let (keyword, rest) = title.split_once(' ').unwrap_or((title, ""));
+  let done = if ORG_OPEN_KEYWORDS.contains(&keyword) {
+    Some(false)
+  } else if ORG_DONE_KEYWORDS.contains(&keyword) {
+    Some(true)
+  } else {
+    None
+  };
+  match done {
+    Some(_) => (done, rest.trim_start()),
+    None => (None, title),
+  }
+}
+
+fn org_priority(title: &str) -> &str {
+  match title.strip_prefix("[#") {
+    Some(rest) if rest.get(1..2) == Some("]") => rest[2..].trim_start(),
+    _ => title,
+  }
+}
+
+// Indentation, whether it's numbered, and the text after the marker
+fn org_list_item(line: &str) -> Option<(&str, bool, &str)> {
+  let indent = &line[..line.len() - line.trim_start().len()];
+  let rest = &line[indent.len()..];
+  // A `*` in the first column starts a heading
+  let bullets: &[&str] = if indent.is_empty() {
+    &["- ", "+ "]
+  } else {
+    &["- ", "+ ", "* "]
+  };
+  if let Some(text) = bullets.iter().find_map(|bullet| rest.strip_prefix(bullet)) {
+    return Some((indent, false, text));
+  }
+  let digits = rest.chars().take_while(char::is_ascii_digit).count();
+  let after = &rest[digits..];
+  let text = after
+    .strip_prefix(". ")
+    .or_else(|| after.strip_prefix(") "))?;
+  (digits > 0).then_some((indent, true, text))
+}
+
+fn org_checkbox(text: &str) -> (&str, &str) {
+  for (box_, markdown) in [
+    ("[ ] ", "[ ] "),
+    ("[-] ", "[ ] "),
+    ("[X] ", "[x] "),
+    ("[x] ", "[x] "),
+  ] {
+    if let Some(rest) = text.strip_prefix(box_) {
+      return (markdown, rest);
+    }
+  }
+  ("", text)
+}
+
+fn is_org_drawer(line: &str) -> bool {
+  let line = line.trim();
+  line.len() > 2
+    && line.starts_with(':')
+    && line.ends_with(':')
+    && line[1..line.len() - 1]
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn is_org_planning(line: &str) -> bool {
+  let line = line.trim_start();
+  ["SCHEDULED:", "DEADLINE:", "CLOSED:"]
+    .iter()
+    .any(|keyword| line.starts_with(keyword))
+}
+
+fn org_table_row(line: &str) -> Vec<String> {
+  let line = line.trim();
+  let line = line.strip_prefix('|').unwrap_or(line);
+  let line = line.strip_suffix('|').unwrap_or(line);
+  line
+    .split('|')
+    .map(|cell| org_inline(cell.trim()))
+    .collect()
+}
+
+fn convert_org(source: &str, out: &mut Output) -> Vec<(String, String)> {
+  let lines: Vec<&str> = source.lines().collect();
+  let mut front_matter = Vec::new();
+  let mut index = 0;
+  while index < lines.len() {
+    let line = lines[index];
+    let trimmed = line.trim_start();
+    let rest_after = |from: usize, is_end: &dyn Fn(&str) -> bool| {
+      lines[from..]
+        .iter()
+        .position(|line| is_end(line))
+        .map(|offset| from + offset)
+    };
+
+    if let Some((keyword, value)) = org_keyword(trimmed) {
+      if let Some(name) = keyword.strip_prefix("BEGIN_") {
+        let end_keyword = format!("END_{}", name);
+        let end = rest_after(index + 1, &|line| {
+          org_keyword(line).is_some_and(|(keyword, _)| keyword == end_keyword)
+        });
+        let Some(end) = end else {
+          out.preserve(index, &[line], &format!("Unclosed #+BEGIN_{} block", name));
+          index += 1;
+          continue;
+        };
+        let body = &lines[index + 1..end];
+        match name {
+          "SRC" => {
+            let language = value.split_whitespace().next().unwrap_or("");
+            // Org escapes lines starting with `*` or `#+` in source blocks with a comma
+            let body: Vec<&str> = body
+              .iter()
+              .map(|line| match line.trim_start().strip_prefix(',') {
+                Some(rest) if rest.starts_with('*') || rest.starts_with("#+") => rest,
+                _ => line,
+              })
+              .collect();
+            out.fence(language, &body);
+          }
+          "EXAMPLE" => out.fence("", body),
+          "QUOTE" => {
+            let mut inner = Output::new(out.language);
+            for line in body {
+              inner.push(org_inline(line.trim()));
+            }
+            out.quote(inner);
+          }
+          _ => out.preserve(
+            index,
+            &lines[index..=end],
+            &format!("#+BEGIN_{} block", name),
+          ),
+        }
+        index = end + 1;
+        continue;
+      }
+      if !is_org_setting(line) {
+        front_matter.push((keyword.to_ascii_lowercase(), value.to_string()));
+        index += 1;
+        continue;
+      }
+      let end = rest_after(index, &|line| !is_org_setting(line)).unwrap_or(lines.len());
+      out.preserve(index, &lines[index..end], "Org settings");
+      index = end;
+      continue;
+    }
+
+    if is_org_drawer(trimmed) && !trimmed.eq_ignore_ascii_case(":END:") {
+      let end = rest_after(index + 1, &|line| line.trim().eq_ignore_ascii_case(":END:"));
+      let end = end.unwrap_or(index);
+      let name = trimmed.trim_matches(':');
+      out.preserve(index, &lines[index..=end], &format!("{} drawer", name));
+      index = end + 1;
+      continue;
+    }
+
+    if is_org_planning(trimmed) {
+      out.preserve(index, &[line], "Scheduling");
+      index += 1;
+      continue;
+    }
+
+    if let Some((level, title)) = org_heading(line) {
+      let (done, title) = org_task(title);
+      let title = org_inline(org_priority(title));
+      match done {
+        Some(done) => out.push(format!("- [{}] {}", if done { "x" } else { " " }, title)),
+        None => {
+          if level > 6 {
+            out.warn(
+              index,
+              "Heading deeper than 6 levels shown as a level 6 heading",
+            );
+          }
+          out.push(format!("{} {}", "#".repeat(level.min(6)), title));
+        }
+      }
+      index += 1;
+      continue;
+    }
+
+    if trimmed.starts_with('|') {
+      let end =
+        rest_after(index, &|line| !line.trim_start().starts_with('|')).unwrap_or(lines.len());
+      let rows: Vec<Vec<String>> = lines[index..end]
+        .iter()
+        .filter(|line| !line.trim_start().starts_with("|-"))
+        .map(|line| org_table_row(line))
+        .collect();
+      out.lines.extend(markdown_table(&rows));
+      index = end;
+      continue;
+    }
+
+    if trimmed == ":" || trimmed.starts_with(": ") {
+      let end = rest_after(index, &|line| {
+        let line = line.trim_start();
+        line != ":" && !line.starts_with(": ")
+      })
+      .unwrap_or(lines.len());
+      let body: Vec<&str> = lines[index..end]
+        .iter()
+        .map(|line| {
+          let line = line.trim_start();
+          line.strip_prefix(": ").unwrap_or(&line[1..])
+        })
+        .collect();
+      out.fence("", &body);
+      index = end;
+      continue;
+    }
+
+    if let Some((indent, ordered, text)) = org_list_item(line) {
+      let (checkbox, text) = org_checkbox(text);
+      let text = match text.split_once(" :: ") {
+        Some((term, description)) => {
+          format!("**{}**: {}", org_inline(term), org_inline(description))
+        }
+        None => org_inline(text),
+      };
+      let marker = if ordered { "1." } else { "-" };
+      out.push(format!("{}{} {}{}", indent, marker, checkbox, text));
+    } else if trimmed == "#" || trimmed.starts_with("# ") {
+      out.push(format!("<!-- {} -->", trimmed[1..].trim()));
+    } else if trimmed.len() >= 5 && trimmed.chars().all(|c| c == '-') {
+      out.push("---");
+    } else {
+      let indent = &line[..line.len() - trimmed.len()];
+      out.push(format!("{}{}", indent, org_inline(trimmed)));
+    }
+    index += 1;
+  }
+  front_matter
+}
+
+// AsciiDoc
+
+fn asciidoc_marker(c: char) -> Option<Inline> {
+  match c {
+    '*' => Some(Inline::Wrap("**", "**")),
+    '_' => Some(Inline::Wrap("*", "*")),
+    '`' => Some(Inline::Code),
+    '#' => Some(Inline::Wrap("<mark>", "</mark>")),
+    _ => None,
+  }
+}
+
+// The text in `[...]` starting at `index`, and the index after it
+fn bracketed(chars: &[char], index: usize) -> Option<(String, usize)> {
+  if chars.get(index) != Some(&'[') {
+    return None;
+  }
+  let end = (index + 1..chars.len()).find(|&end| chars[end] == ']')?;
+  Some((collect(&chars[index + 1..end]), end + 1))
+}
+
+// `https://url[text]`, `link:url[text]`, `image:path[alt]` and `<<id,text>>`
+fn asciidoc_link(chars: &[char], index: usize) -> Option<(String, usize)> {
+  if chars[index..].starts_with(&['<', '<']) {
+    let end = find_from(chars, index + 2, ">>")?;
+    let inner = collect(&chars[index + 2..end]);
+    let (id, text) = inner.split_once(',').unwrap_or((&inner, &inner));
+    return Some((format!("[{}](#{})", text.trim(), id.trim()), end + 2));
+  }
+  if !matches!(chars[index], 'h' | 'm' | 'l' | 'i') || !opens_emphasis(chars, index) {
+    return None;
+  }
+  let rest = collect(&chars[index..chars.len().min(index + 8)]);
+  let (prefix, is_image) = ["https://", "http://", "mailto:", "link:", "image:"]
+    .into_iter()
+    .find(|prefix| rest.starts_with(prefix))
+    .map(|prefix| (prefix, prefix == "image:"))?;
+  let target_start = if matches!(prefix, "link:" | "image:") {
+    index + prefix.len()
+  } else {
+    index
+  };
+  let target_end = (target_start..chars.len())
+    .find(|&end| chars[end].is_whitespace() || chars[end] == '[')
+    .unwrap_or(chars.len());
+  let (text, next) = bracketed(chars, target_end)?;
+  let target = collect(&chars[target_start..target_end]);
+  if target.is_empty() {
+    return None;
+  }
+  let text = text
+    .split(',')
+    .next()
+    .unwrap_or("")
+    .trim()
+    .trim_matches('"');
+  let markdown = if is_image {
+    format!("![{}]({})", text, link_destination(&target))
+  } else if text.is_empty() {
+    format!("<{}>", target)
+  } else {
+    format!(
+      "[{}]({})",
+      convert_inline(text, &ASCIIDOC_INLINE),
+      link_destination(&target)
+    )
+  };
+  Some((markdown, next))
+}
+
+const ASCIIDOC_INLINE: InlineSyntax = InlineSyntax {
+  marker: asciidoc_marker,
+  special: asciidoc_link,
+  unconstrained: true,
+};
+
+fn asciidoc_inline(text: &str) -> String {
+  // A trailing ` +` forces a line break
+  match text.strip_suffix(" +") {
+    Some(text) => format!("{}\\", convert_inline(text, &ASCIIDOC_INLINE)),
+    None => convert_inline(text, &ASCIIDOC_INLINE),
+  }
+}
+
+// `----`, `====`, `|===` etc. on a line of their own
+fn asciidoc_delimiter(line: &str) -> Option<&str> {
+  let line = line.trim_end();
+  if line == "--" || line == "|===" {
+    return Some(line);
+  }
+  let first = line.chars().next()?;
+  ("-.=_*+/".contains(first) && line.len() >= 4 && line.chars().all(|c| c == first)).then_some(line)
+}
+
+// `[source,rust]` and the like, as their comma-separated values
+fn asciidoc_attributes(line: &str) -> Option<Vec<&str>> {
+  let inner = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+  if inner.starts_with('[') {
+    return None;
+  }
+  Some(inner.split(',').map(str::trim).collect())
+}
+
+// `name::target[attributes]` on a line of its own
+fn asciidoc_block_macro(line: &str) -> Option<(&str, &str, &str)> {
+  let (name, rest) = line.trim().split_once("::")?;
+  let (target, attributes) = rest.strip_suffix(']')?.split_once('[')?;
+  let is_name = !name.is_empty()
+    && name
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+  is_name.then_some((name, target, attributes))
+}
+
+fn asciidoc_list_item(line: &str) -> Option<(String, &str)> {
+  let line = line.trim_start();
+  if let Some((depth, text)) = nested_marker(line, '*') {
+    return Some((format!("{}- ", list_indent(depth, false)), text));
+  }
+  if let Some((depth, text)) = nested_marker(line, '.') {
+    return Some((format!("{}1. ", list_indent(depth, true)), text));
+  }
+  if let Some(text) = line.strip_prefix("- ") {
+    return Some(("- ".to_string(), text));
+  }
+  let digits = line.chars().take_while(char::is_ascii_digit).count();
+  let text = line[digits..].strip_prefix(". ")?;
+  (digits > 0).then(|| (format!("{}. ", &line[..digits]), text))
+}
+
+fn is_asciidoc_attribute_entry(line: &str) -> bool {
+  line.strip_prefix(':').is_some_and(|rest| {
+    rest
+      .split_once(':')
+      .is_some_and(|(name, _)| !name.is_empty() && !name.contains(char::is_whitespace))
+  })
+}
+
+// The lines of the paragraph starting at `index`
+fn paragraph_end(lines: &[&str], index: usize) -> usize {
+  lines[index..]
+    .iter()
+    .position(|line| line.trim().is_empty())
+    .map_or(lines.len(), |offset| index + offset)
+}
+
+// `raw` is the whole block as written, attribute line included, for keeping it as it is
+fn convert_asciidoc_block(
+  delimiter: &str,
+  attributes: &[&str],
+  body: &[&str],
+  first_line: usize,
+  raw: &[&str],
+  raw_line: usize,
+  out: &mut Output,
+) {
+  let style = attributes.first().copied().unwrap_or("");
+  if let Some(label) = blockquote_label(style) {
+    let mut inner = Output::new(out.language);
+    inner.push(format!("**{}**", label));
+    inner.push("");
+    convert_asciidoc(body, first_line, &mut inner);
+    out.quote(inner);
+    return;
+  }
+  match delimiter {
+    "----" => {
+      let language = if style.is_empty() || style == "source" {
+        attributes.get(1).copied().unwrap_or("")
+      } else {
+        ""
+      };
+      out.fence(language, body);
+    }
+    "...." => out.fence("", body),
+    "____" => {
+      let mut inner = Output::new(out.language);
+      convert_asciidoc(body, first_line, &mut inner);
+      let attribution: Vec<&str> = attributes
+        .iter()
+        .skip(1)
+        .copied()
+        .filter(|part| !part.is_empty())
+        .collect();
+      if !attribution.is_empty() {
+        inner.push("");
+        inner.push(format!("— {}", attribution.join(", ")));
+      }
+      out.quote(inner);
+    }
+    "////" => {
+      out.push("<!--");
+      for line in body {
+        out.push(*line);
+      }
+      out.push("-->");
+    }
+    // Passthrough, usually HTML
+    "++++" => {
+      for line in body {
+        out.push(*line);
+      }
+    }
+    "--" => convert_asciidoc(body, first_line, out),
+    _ => {
+      let what = match delimiter {
+        "|===" => "Table",
+        "****" => "Sidebar",
+        "====" => "Example block",
+        _ => "Block",
+      };
+      out.preserve(raw_line, raw, what);
+    }
+  }
+}
+
+fn convert_asciidoc(lines: &[&str], first_line: usize, out: &mut Output) {
+  // The attribute line before a block, and where it is
+  let mut pending: Option<(usize, Vec<&str>)> = None;
+  let mut index = 0;
+  while index < lines.len() {
+    let line = lines[index];
+    let trimmed = line.trim();
+    let attributes = pending.take();
+
+    if let Some(delimiter) = asciidoc_delimiter(line) {
+      let end = lines[index + 1..]
+        .iter()
+        .position(|line| line.trim_end() == delimiter)
+        .map_or(lines.len(), |offset| index + 1 + offset);
+      let raw_start = attributes.as_ref().map_or(index, |(at, _)| *at);
+      let (_, attributes) = attributes.unwrap_or_default();
+      convert_asciidoc_block(
+        delimiter,
+        &attributes,
+        &lines[index + 1..end],
+        first_line + index + 1,
+        &lines[raw_start..(end + 1).min(lines.len())],
+        first_line + raw_start,
+        out,
+      );
+      index = end + 1;
+      continue;
+    }
+
+    if let Some(id) = trimmed
+      .strip_prefix("[[")
+      .and_then(|rest| rest.strip_suffix("]]"))
+    {
+      out.push(format!("<a id=\"{}\"></a>", id));
+      index += 1;
+      continue;
+    }
+
+    if let Some(values) = asciidoc_attributes(trimmed) {
+      pending = Some((index, values));
+      index += 1;
+      continue;
+    }
+
+    if let Some((at, values)) = attributes {
+      // A paragraph styled like a block: `[source]`, `[quote]` or an admonition
+      let style = values.first().copied().unwrap_or("");
+      let delimiter = match style {
+        "source" | "listing" => Some("----"),
+        "quote" | "verse" => Some("____"),
+        _ => blockquote_label(style).map(|_| "===="),
+      };
+      if let (Some(delimiter), false) = (delimiter, trimmed.is_empty()) {
+        let end = paragraph_end(lines, index);
+        convert_asciidoc_block(
+          delimiter,
+          &values,
+          &lines[index..end],
+          first_line + index,
+          &lines[at..end],
+          first_line + at,
+          out,
+        );
+        index = end;
+        continue;
+      }
+      out.warn(
+        first_line + at,
+        format!("Block attributes [{}] dropped", values.join(",")),
+      );
+    }
+
+    if is_asciidoc_attribute_entry(trimmed) {
+      let end = lines[index..]
+        .iter()
+        .position(|line| !is_asciidoc_attribute_entry(line.trim()))
+        .map_or(lines.len(), |offset| index + offset);
+      out.preserve(
+        first_line + index,
+        &lines[index..end],
+        "Document attributes",
+      );
+      index = end;
+      continue;
+    }
+
+    if let Some((depth, title)) = nested_marker(trimmed, '=') {
+      if depth <= 6 {
+        out.push(format!("{} {}", "#".repeat(depth), asciidoc_inline(title)));
+        index += 1;
+        continue;
+      }
+    }
+
+    if let Some((name, target, attributes)) = asciidoc_block_macro(trimmed) {
+      if name == "image" {
+        let alt = attributes.split(',').next().unwrap_or("").trim();
+        out.push(format!("![{}]({})", alt, link_destination(target)));
+      } else {
+        out.preserve(
+          first_line + index,
+          &[line],
+          &format!("{}:: directive", name),
+        );
+      }
+      index += 1;
+      continue;
+    }
+
+    if let Some((label, text)) = trimmed
+      .split_once(": ")
+      .and_then(|(style, text)| Some((blockquote_label(style)?, text)))
+      .filter(|_| trimmed.starts_with(|c: char| c.is_ascii_uppercase()))
+      .filter(|_| {
+        let style = trimmed.split(':').next().unwrap_or("");
+        style == style.to_ascii_uppercase()
+      })
+    {
+      let end = paragraph_end(lines, index);
+      let mut inner = Output::new(out.language);
+      inner.push(format!("**{}:** {}", label, asciidoc_inline(text)));
+      for line in &lines[index + 1..end] {
+        inner.push(asciidoc_inline(line.trim()));
+      }
+      out.quote(inner);
+      index = end;
+      continue;
+    }
+
+    if trimmed.starts_with('.')
+      && trimmed
+        .chars()
+        .nth(1)
+        .is_some_and(|c| c != '.' && !c.is_whitespace())
+    {
+      out.push(format!("**{}**", asciidoc_inline(&trimmed[1..])));
+    } else if let Some((marker, text)) = asciidoc_list_item(line) {
+      out.push(format!("{}{}", marker, asciidoc_inline(text)));
+    } else if let Some((term, description)) =
+      trimmed.split_once("::").filter(|(term, description)| {
+        !term.is_empty() && (description.is_empty() || description.starts_with(' '))
+      })
+    {
+      out.push(format!(
+        "- **{}**: {}",
+        asciidoc_inline(term),
+        asciidoc_inline(description.trim())
+      ));
+    } else if let Some(comment) = trimmed.strip_prefix("//") {
+      out.push(format!("<!-- {} -->", comment.trim()));
+    } else if trimmed == "'''" || trimmed == "<<<" {
+      out.push("---");
+    } else {
+      out.push(asciidoc_inline(trimmed));
+    }
+    index += 1;
+  }
+  if let Some((at, values)) = pending {
+    out.warn(
+      first_line + at,
+      format!("Block attributes [{}] dropped", values.join(",")),
+    );
+  }
+}
+
+// Textile
+
+fn textile_marker(c: char) -> Option<Inline> {
+  match c {
+    '*' => Some(Inline::Wrap("**", "**")),
+    '_' => Some(Inline::Wrap("*", "*")),
+    '@' => Some(Inline::Code),
+    '-' => Some(Inline::Wrap("~~", "~~")),
+    '+' => Some(Inline::Wrap("<ins>", "</ins>")),
+    '^' => Some(Inline::Wrap("<sup>", "</sup>")),
+    '~' => Some(Inline::Wrap("<sub>", "</sub>")),
+    _ => None,
+  }
+}
+
+// `"text":url`, `!image(alt)!` and footnote references like `word[1]`
+fn textile_link(chars: &[char], index: usize) -> Option<(String, usize)> {
+  match chars[index] {
+    '"' => {
+      let close = (index + 1..chars.len()).find(|&end| chars[end] == '"')?;
+      if chars.get(close + 1) != Some(&':') {
+        return None;
+      }
+      let mut end = (close + 2..chars.len())
+        .find(|&end| chars[end].is_whitespace())
+        .unwrap_or(chars.len());
+      while end > close + 2 && ".,;:!?".contains(chars[end - 1]) {
+        end -= 1;
+      }
+      if end == close + 2 {
+        return None;
+      }
+      let text = collect(&chars[index + 1..close]);
+      // Drop a link title: "text(title)":url
+      let text = match text
+        .strip_suffix(')')
+        .and_then(|text| text.rsplit_once('('))
+      {
+        Some((text, _)) => text.trim_end().to_string(),
+        None => text,
+      };
+      let markdown = format!(
+        "[{}]({})",
+        convert_inline(&text, &TEXTILE_INLINE),
+        collect(&chars[close + 2..end])
+      );
+      Some((markdown, end))
+    }
+    '!' if opens_emphasis(chars, index) => {
+      let close = (index + 1..chars.len()).find(|&end| chars[end] == '!')?;
+      let inner = collect(&chars[index + 1..close]);
+      let (source, alt) = match inner
+        .strip_suffix(')')
+        .and_then(|inner| inner.split_once('('))
+      {
+        Some((source, alt)) => (source, alt),
+        None => (inner.as_str(), ""),
+      };
+      if source.is_empty() || source.contains(char::is_whitespace) {
+        return None;
+      }
+      Some((format!("![{}]({})", alt, source), close + 1))
+    }
+    '[' if index > 0 && !chars[index - 1].is_whitespace() => {
+      let close = (index + 1..chars.len()).find(|&end| chars[end] == ']')?;
+      let label = collect(&chars[index + 1..close]);
+      (!label.is_empty() && label.chars().all(|c| c.is_ascii_digit()))
+        .then(|| (format!("[^{}]", label), close + 1))
+    }
+    _ => None,
+  }
+}
+
+const TEXTILE_INLINE: InlineSyntax = InlineSyntax {
+  marker: textile_marker,
+  special: textile_link,
+  unconstrained: true,
+};
+
+fn textile_inline(text: &str) -> String {
+  convert_inline(text, &TEXTILE_INLINE)
+}
+
+// A block signature like `h2.`, `bq..` or `p(intro).`
+struct Signature<'a> {
+  tag: &'a str,
+  // Classes, ids, styles and alignment, which markdown can't express
+  attributes: &'a str,
+  // `..`: the block runs until the next signature rather than the next blank line
+  extended: bool,
+  text: &'a str,
+}
+
+fn textile_signature(line: &str) -> Option<Signature<'_>> {
+  let tag_end = line
+    .find(|c: char| !c.is_ascii_alphanumeric() && c != '#')
+    .unwrap_or(line.len());
+  let tag = &line[..tag_end];
+  let is_tag = matches!(tag, "p" | "bq" | "bc" | "pre" | "notextile" | "###")
+    || matches!(tag.as_bytes(), [b'h', b'1'..=b'6'])
+    || tag
+      .strip_prefix("fn")
+      .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+  if !is_tag {
+    return None;
+  }
+  let rest = &line[tag_end..];
+  let dot = rest.find('.')?;
+  let attributes = &rest[..dot];
+  if !attributes.is_empty() && !attributes.starts_with(['(', '{', '[', '<', '>', '=']) {
+    return None;
+  }
+  let after = &rest[dot + 1..];
+  let (extended, after) = match after.strip_prefix('.') {
+    Some(after) => (true, after),
+    None => (false, after),
+  };
+  let text = if after.is_empty() {
+    after
+  } else {
+    after.strip_prefix(' ')?
+  };
+  Some(Signature {
+    tag,
+    attributes,
+    extended,
+    text,
+  })
+}
+
+fn textile_table_row(line: &str, index: usize, out: &mut Output) -> Vec<String> {
+  let line = line.trim();
+  let start = line.find('|').unwrap_or(0);
+  if start > 0 {
+    out.warn(
+      index,
+      format!("Row styling {} dropped", line[..start].trim()),
+    );
+  }
+  let line = &line[start + 1..];
+  let line = line.strip_suffix('|').unwrap_or(line);
+  line
+    .split('|')
+    .map(|cell| {
+      // Cell modifiers end with `. `: `_. ` marks a header, others are spans and alignment
+      let modifier = cell.find(". ").filter(|&end| {
+        end > 0
+          && cell[..end]
+            .chars()
+            .all(|c| "_\\/<>=^~{}();:#-".contains(c) || c.is_ascii_alphanumeric())
+      });
+      let cell = match modifier {
+        Some(end) => {
+          if &cell[..end] != "_" {
+            out.warn(index, format!("Cell modifier {}. dropped", &cell[..end]));
+          }
+          &cell[end + 2..]
+        }
+        None => cell,
+      };
+      textile_inline(cell.trim())
+    })
+    .collect()
+}
+
+fn convert_textile(source: &str, out: &mut Output) {
+  let lines: Vec<&str> = source.lines().collect();
+  let mut index = 0;
+  while index < lines.len() {
+    let line = lines[index];
+    if let Some(signature) = textile_signature(line) {
+      let end = if signature.extended {
+        lines[index + 1..]
+          .iter()
+          .position(|line| textile_signature(line).is_some())
+          .map_or(lines.len(), |offset| index + 1 + offset)
+      } else {
+        paragraph_end(lines.as_slice(), index)
+      };
+      let mut body: Vec<&str> = Vec::new();
+      if !signature.text.is_empty() {
+        body.push(signature.text);
+      }
+      body.extend(&lines[index + 1..end]);
+      while body.last().is_some_and(|line| line.trim().is_empty()) {
+        body.pop();
+      }
+      if !signature.attributes.is_empty() {
+        out.warn(index, format!("Styling {} dropped", signature.attributes));
+      }
+      match signature.tag {
+        "p" => {
+          for line in body {
+            out.push(textile_inline(line));
+          }
+        }
+        "bq" => {
+          let mut inner = Output::new(out.language);
+          for line in body {
+            inner.push(textile_inline(line));
+          }
+          out.quote(inner);
+        }
+        "bc" | "pre" => out.fence("", &body),
+        "notextile" => {
+          for line in body {
+            out.push(line);
+          }
+        }
+        "###" => {
+          out.push("<!--");
+          for line in body {
+            out.push(line);
+          }
+          out.push("-->");
+        }
+        tag => {
+          if let Some(number) = tag.strip_prefix("fn") {
+            let mut lines = body.into_iter();
+            let first = lines.next().unwrap_or("");
+            out.push(format!("[^{}]: {}", number, textile_inline(first)));
+            for line in lines {
+              out.push(format!("    {}", textile_inline(line)));
+            }
+          } else {
+            let level: usize = tag[1..].parse().unwrap_or(1);
+            let text: Vec<String> = body
+              .iter()
+              .map(|line| textile_inline(line.trim()))
+              .collect();
+            out.push(format!("{} {}", "#".repeat(level), text.join(" ")));
+          }
+        }
+      }
+      index = end;
+      continue;
+    }
+
+    let trimmed = line.trim_start();
+    if trimmed.starts_with('|') || (trimmed.starts_with('(') && trimmed.contains(". |")) {
+      let end = lines[index..]
+        .iter()
+        .position(|line| !line.trim_end().ends_with('|'))
+        .map_or(lines.len(), |offset| index + offset);
+      let rows: Vec<Vec<String>> = (index..end)
+        .map(|row| textile_table_row(lines[row], row, out))
+        .collect();
+      out.lines.extend(markdown_table(&rows));
+      index = end.max(index + 1);
+      continue;
+    }
+
+    if let Some((depth, text)) = nested_marker(trimmed, '*') {
+      out.push(format!(
+        "{}- {}",
+        list_indent(depth, false),
+        textile_inline(text)
+      ));
+    } else if let Some((depth, text)) = nested_marker(trimmed, '#') {
+      out.push(format!(
+        "{}1. {}",
+        list_indent(depth, true),
+        textile_inline(text)
+      ));
+    } else {
+      out.push(textile_inline(line));
+    }
+    index += 1;
+  }
+}
+
+fn front_matter_block(fields: &[(String, String)]) -> Vec<String> {
+  if fields.is_empty() {
+    return Vec::new();
+  }
+  let mut lines = vec!["---".to_string()];
+  for (key, value) in fields {
+    // A JSON string is a valid YAML scalar, whatever the value contains
+    let value = serde_json::to_string(value).unwrap_or_default();
+    lines.push(format!("{}: {}", key, value));
+  }
+  lines.push("---".to_string());
+  lines.push(String::new());
+  lines
+}
+
+pub fn import(source: &str, format: ImportFormat, title: &str) -> ImportedDocument {
+  let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+  let mut out = Output::new(format.language());
+  let front_matter = match format {
+    ImportFormat::Org => convert_org(source, &mut out),
+    ImportFormat::AsciiDoc => {
+      let lines: Vec<&str> = source.lines().collect();
+      convert_asciidoc(&lines, 0, &mut out);
+      Vec::new()
+    }
+    ImportFormat::Textile => {
+      convert_textile(source, &mut out);
+      Vec::new()
+    }
+  };
+  let mut lines = front_matter_block(&front_matter);
+  lines.extend(out.lines);
+  while lines.last().is_some_and(|line| line.trim().is_empty()) {
+    lines.pop();
+  }
+  let mut content = lines.join("\n");
+  content.push('\n');
+  out.warnings.sort_by_key(|warning| warning.line);
+  ImportedDocument {
+    content,
+    title: title.to_string(),
+    format,
+    warnings: out.warnings,
+  }
+}
+
+// Convert an org, AsciiDoc or Textile file to markdown. Without a path the user picks the
+// file; returns None if they cancel. A given path has to be inside the access scope.
+#[tauri::command]
+pub async fn import_document(
+  app: AppHandle,
+  scope: tauri::State<'_, AccessScopeState>,
+  path: Option<String>,
+) -> CommandResult<Option<ImportedDocument>> {
+  let path = match path {
+    Some(path) => {
+      let path = PathBuf::from(path);
+      scope.check(&path)?;
+      path
+    }
+    None => {
+      let picked = app
+        .dialog()
+        .file()
+        .add_filter("Importable documents", IMPORT_EXTENSIONS)
+        .blocking_pick_file();
+      match picked.map(crate::dialog_result_to_path).transpose()? {
+        Some(path) => path,
+        None => return Ok(None),
+      }
+    }
+  };
+  let format = ImportFormat::from_path(&path).ok_or_else(|| {
+    CommandError::invalid_data(format!(
+      "{} is not an org, AsciiDoc or Textile document",
+      path.display()
+    ))
+  })?;
+  let source = crate::read_text_file(&path)?;
+  let title = path
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_default();
+  Ok(Some(import(&source, format, &title)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn convert(source: &str, format: ImportFormat) -> ImportedDocument {
+    import(source, format, "notes")
+  }
+
+  #[test]
+  fn test_format_from_extension() {
+    assert_eq!(
+      ImportFormat::from_path(Path::new("notes.ORG")),
+      Some(ImportFormat::Org)
+    );
+    assert_eq!(
+      ImportFormat::from_path(Path::new("guide.asciidoc")),
+      Some(ImportFormat::AsciiDoc)
+    );
+    assert_eq!(
+      ImportFormat::from_path(Path::new("a.textile")),
+      Some(ImportFormat::Textile)
+    );
+    assert_eq!(ImportFormat::from_path(Path::new("readme.md")), None);
+  }
+
+  #[test]
+  fn test_org_headings_tasks_and_markup() {
+    let source = "#+TITLE: My notes\n\
+      * Projects\n\
+      ** TODO [#A] Write *the* report :work:\n\
+      ** DONE Call Sam\n\
+      Some /italic/, =verbatim= and ~code~, but not 2*3*4.\n\
+      See [[https://orgmode.org][the manual]] or [[file:other.org][other notes]].\n\
+      - [X] first\n\
+      - [ ] second\n\
+      \x20 1. nested\n\
+      - term :: meaning\n";
+    let imported = convert(source, ImportFormat::Org);
+    assert_eq!(
+      imported.content,
+      "---\ntitle: \"My notes\"\n---\n\n\
+       # Projects\n\
+       - [ ] Write **the** report :work:\n\
+       - [x] Call Sam\n\
+       Some *italic*, `verbatim` and `code`, but not 2*3*4.\n\
+       See [the manual](https://orgmode.org) or [other notes](other.org).\n\
+       - [x] first\n\
+       - [ ] second\n\
+      \x20 1. nested\n\
+       - **term**: meaning\n"
+    );
+    assert!(imported.warnings.is_empty());
+  }
+
+  #[test]
+  fn test_org_blocks_and_tables() {
+    let source = "#+BEGIN_SRC rust :results silent\nfn main() {}\n,* not a heading\n#+END_SRC\n\
+      #+begin_quote\nWise /words/.\n#+end_quote\n\
+      | Name | Age |\n|------+-----|\n| Ann  | 3   |\n";
+    let imported = convert(source, ImportFormat::Org);
+    assert_eq!(
+      imported.content,
+      "```rust\nfn main() {}\n* not a heading\n```\n> Wise *words*.\n\
+       | Name | Age |\n| --- | --- |\n| Ann | 3 |\n"
+    );
+  }
+
+  #[test]
+  fn test_org_without_equivalent_is_kept_with_warnings() {
+    let source = "* Task\n:PROPERTIES:\n:ID: 42\n:END:\n#+STARTUP: overview\n\
+      #+BEGIN_VERSE\nRoses\n#+END_VERSE\n";
+    let imported = convert(source, ImportFormat::Org);
+    assert_eq!(
+      imported.content,
+      "# Task\n```org\n:PROPERTIES:\n:ID: 42\n:END:\n```\n```org\n#+STARTUP: overview\n```\n\
+       ```org\n#+BEGIN_VERSE\nRoses\n#+END_VERSE\n```\n"
+    );
+    let lines: Vec<usize> = imported.warnings.iter().map(|w| w.line).collect();
+    assert_eq!(lines, vec![2, 5, 6]);
+    assert_eq!(
+      imported.warnings[0].message,
+      "PROPERTIES drawer kept as org source"
+    );
+  }
+
+  #[test]
+  fn test_asciidoc_headings_admonitions_and_source() {
+    let source = "= Guide\n\n== Setup\n\nNOTE: Read *this* _first_.\n\n\
+      [source,rust]\n----\nlet x = 1;\n----\n\n\
+      [WARNING]\n====\nHot.\n====\n\n\
+      * one\n** nested\n. first\n\
+      See https://asciidoc.org[the docs] and <<setup,Setup>>.\n\
+      image::diagram.png[Diagram]\n";
+    let imported = convert(source, ImportFormat::AsciiDoc);
+    assert_eq!(
+      imported.content,
+      "# Guide\n\n## Setup\n\n> **Note:** Read **this** *first*.\n\n\
+       ```rust\nlet x = 1;\n```\n\n\
+       > **Warning**\n>\n> Hot.\n\n\
+       - one\n  - nested\n1. first\n\
+       See [the docs](https://asciidoc.org) and [Setup](#setup).\n\
+       ![Diagram](diagram.png)\n"
+    );
+    assert!(imported.warnings.is_empty());
+  }
+
+  #[test]
+  fn test_asciidoc_tables_and_directives_are_kept() {
+    let source = ":toc: left\n\n[cols=\"1,1\"]\n|===\n|a |b\n|===\ninclude::other.adoc[]\n";
+    let imported = convert(source, ImportFormat::AsciiDoc);
+    assert_eq!(
+      imported.content,
+      "```asciidoc\n:toc: left\n```\n\n```asciidoc\n[cols=\"1,1\"]\n|===\n|a |b\n|===\n```\n\
+       ```asciidoc\ninclude::other.adoc[]\n```\n"
+    );
+    let messages: Vec<&str> = imported
+      .warnings
+      .iter()
+      .map(|w| w.message.as_str())
+      .collect();
+    assert_eq!(
+      messages,
+      vec![
+        "Document attributes kept as asciidoc source",
+        "Table kept as asciidoc source",
+        "include:: directive kept as asciidoc source"
+      ]
+    );
+    assert_eq!(imported.warnings[1].line, 3);
+  }
+
+  #[test]
+  fn test_textile_blocks_and_inline() {
+    let source = "h1. Title\n\np(intro). *Bold* and _italic_ with @code@ and a note[1].\n\n\
+      bq. Quoted\n\nbc. let x = 1;\n\n\
+      * one\n** two\n# first\n\n\
+      \"Textile\":https://textile-lang.com. !logo.png(Logo)!\n\n\
+      |_. Name|_. Age|\n|Ann|3|\n\nfn1. The note.\n";
+    let imported = convert(source, ImportFormat::Textile);
+    assert_eq!(
+      imported.content,
+      "# Title\n\n**Bold** and *italic* with `code` and a note[^1].\n\n\
+       > Quoted\n\n```\nlet x = 1;\n```\n\n\
+       - one\n  - two\n1. first\n\n\
+       [Textile](https://textile-lang.com). ![Logo](logo.png)\n\n\
+       | Name | Age |\n| --- | --- |\n| Ann | 3 |\n\n[^1]: The note.\n"
+    );
+    assert_eq!(imported.warnings.len(), 1);
+    assert_eq!(imported.warnings[0].line, 3);
+  }
+}
@@ -0,0 +1,470 @@
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+use crate::access_scope::AccessScopeState;
+use crate::error::{CommandError, CommandResult, FieldError};
+use crate::settings::SettingsState;
+use crate::write_queue::WriteCoordinator;
+use crate::{file_mtime_millis, launch};
+
+// When Quick Capture starts a new inbox file
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InboxRotation {
+  // One file that keeps growing
+  #[default]
+  Never,
+  // A file per month: `inbox-2024-06.md`
+  Monthly,
+  // The next numbered file (`inbox-2.md`, `inbox-3.md`...) once a capture would take the
+  // current one past `max_size_kb`
+  Size,
+}
+
+const DEFAULT_MAX_SIZE_KB: u64 = 512;
+
+// Where captured notes go
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InboxOptions {
+  // The inbox file; rotated files are named after it, in its folder. None is `inbox.md` in a
+  // Markdowner folder in Documents.
+  pub path: Option<String>,
+  pub rotation: InboxRotation,
+  pub max_size_kb: u64,
+}
+
+impl Default for InboxOptions {
+  fn default() -> Self {
+    InboxOptions {
+      path: None,
+      rotation: InboxRotation::default(),
+      max_size_kb: DEFAULT_MAX_SIZE_KB,
+    }
+  }
+}
+
+impl InboxOptions {
+  // What's wrong with each field, named `inbox.<field>`
+  pub fn validate(&self) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let mut reject = |field: &str, message: &str| {
+      errors.push(FieldError {
+        field: format!("inbox.{}", field),
+        message: message.to_string(),
+      })
+    };
+    if let Some(path) = &self.path {
+      let path = Path::new(path);
+      if !path.is_absolute() || path.file_stem().is_none() {
+        reject("path", "Inbox must be an absolute file path");
+      }
+    }
+    if self.max_size_kb == 0 {
+      reject("max_size_kb", "Inbox size limit must be at least 1 KB");
+    }
+    errors
+  }
+}
+
+// Which file of a rotated inbox a file is. Newer files compare greater; monthly files sort
+// after numbered ones, for an inbox whose rotation was changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Period {
+  // `inbox.md` is part 1, then `inbox-2.md`...
+  Part(u32),
+  // Year and month
+  Month(i32, u32),
+}
+
+// The configured inbox's name, split the way rotated files are named
+#[derive(Debug, Clone, PartialEq)]
+struct InboxName {
+  folder: PathBuf,
+  stem: String,
+  // With its dot, or empty
+  extension: String,
+}
+
+impl InboxName {
+  fn new(base: &Path) -> InboxName {
+    InboxName {
+      folder: base.parent().map(Path::to_path_buf).unwrap_or_default(),
+      stem: base
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default(),
+      extension: base
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default(),
+    }
+  }
+
+  fn file_name(&self, period: Period) -> String {
+    match period {
+      Period::Part(1) => format!("{}{}", self.stem, self.extension),
+      Period::Part(part) => format!("{}-{}{}", self.stem, part, self.extension),
+      Period::Month(year, month) => {
+        format!("{}-{:04}-{:02}{}", self.stem, year, month, self.extension)
+      }
+    }
+  }
+
+  fn path(&self, period: Period) -> PathBuf {
+    self.folder.join(self.file_name(period))
+  }
+
+  // The period of a file in the inbox's folder, None if it isn't one of the inbox's files
+  fn period(&self, file_name: &str) -> Option<Period> {
+    let rest = file_name
+      .strip_suffix(&self.extension)?
+      .strip_prefix(&self.stem)?;
+    if rest.is_empty() {
+      return Some(Period::Part(1));
+    }
+    let rest = rest.strip_prefix('-')?;
+    let digits =
+      |text: &str, len: usize| text.len() == len && text.chars().all(|c| c.is_ascii_digit());
+    if let Some((year, month)) = rest.split_once('-') {
+      if !digits(year, 4) || !digits(month, 2) {
+        return None;
+      }
+      let year: i32 = year.parse().ok()?;
+      let month: u32 = month.parse().ok()?;
+      return (1..=12)
+        .contains(&month)
+        .then_some(Period::Month(year, month));
+    }
+    let part: u32 = rest.parse().ok()?;
+    (part >= 2 && !rest.starts_with('0')).then_some(Period::Part(part))
+  }
+}
+
+// Where a capture goes, and the file to link at the top when that starts a new one
+#[derive(Debug, Clone, PartialEq)]
+struct AppendPlan {
+  target: Period,
+  previous: Option<Period>,
+}
+
+// `existing` is the inbox's files with their sizes; `incoming` the bytes about to be added
+fn plan_append(
+  options: &InboxOptions,
+  today: (i32, u32),
+  existing: &[(Period, u64)],
+  incoming: u64,
+) -> AppendPlan {
+  let newest = existing.iter().map(|(period, _)| *period).max();
+  let exists = |period: Period| existing.iter().any(|(p, _)| *p == period);
+  match options.rotation {
+    InboxRotation::Never => AppendPlan {
+      target: Period::Part(1),
+      previous: None,
+    },
+    InboxRotation::Monthly => {
+      let target = Period::Month(today.0, today.1);
+      AppendPlan {
+        target,
+        previous: newest.filter(|_| !exists(target)),
+      }
+    }
+    InboxRotation::Size => {
+      let (part, size) = existing
+        .iter()
+        .filter_map(|(period, size)| match period {
+          Period::Part(part) => Some((*part, *size)),
+          Period::Month(..) => None,
+        })
+        .max()
+        .unwrap_or((1, 0));
+      let current = Period::Part(part);
+      if size > 0 && size + incoming > options.max_size_kb * 1024 {
+        AppendPlan {
+          target: Period::Part(part + 1),
+          previous: Some(current),
+        }
+      } else {
+        AppendPlan {
+          target: current,
+          previous: None,
+        }
+      }
+    }
+  }
+}
+
+// `content` with `text` added as a new paragraph, or a new file's content: a link back to
+// the file it follows, then `text`
+fn appended(content: &str, text: &str, previous: Option<&str>) -> String {
+  let mut updated = content.to_string();
+  if let Some(previous) = previous.filter(|_| content.is_empty()) {
+    updated.push_str(&format!(
+      "Previous: [{}]({})\n\n",
+      previous,
+      previous.replace(' ', "%20")
+    ));
+  }
+  if !updated.is_empty() {
+    while !updated.ends_with("\n\n") {
+      updated.push('\n');
+    }
+  }
+  updated.push_str(text.trim_end());
+  updated.push('\n');
+  updated
+}
+
+// The configured inbox file, before rotation
+fn inbox_base(app: &AppHandle, options: &InboxOptions) -> CommandResult<PathBuf> {
+  if let Some(path) = &options.path {
+    return Ok(PathBuf::from(path));
+  }
+  let documents = app
+    .path()
+    .document_dir()
+    .or_else(|_| app.path().home_dir())
+    .map_err(|e| CommandError::io("Failed to find the Documents folder", e))?;
+  Ok(documents.join("Markdowner").join("inbox.md"))
+}
+
+// The inbox's files in its folder, with their sizes
+fn inbox_files(name: &InboxName) -> Vec<(Period, PathBuf, std::fs::Metadata)> {
+  let Ok(entries) = std::fs::read_dir(&name.folder) else {
+    return Vec::new();
+  };
+  entries
+    .flatten()
+    .filter_map(|entry| {
+      let period = name.period(&entry.file_name().to_string_lossy())?;
+      let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+      Some((period, entry.path(), metadata))
+    })
+    .collect()
+}
+
+fn today() -> (i32, u32) {
+  let now = chrono::Local::now();
+  (now.year(), now.month())
+}
+
+// The file captures go to right now (it may not exist yet)
+fn active_path(name: &InboxName, options: &InboxOptions) -> PathBuf {
+  let existing: Vec<(Period, u64)> = inbox_files(name)
+    .iter()
+    .map(|(period, _, metadata)| (*period, metadata.len()))
+    .collect();
+  name.path(plan_append(options, today(), &existing, 0).target)
+}
+
+// Append `text` to the inbox, starting the next file first when the rotation setting says
+// so. Captures are queued on the configured inbox path, so two captures can't both decide
+// to rotate. Returns the file written.
+#[tauri::command]
+pub async fn append_to_inbox(
+  app: AppHandle,
+  coordinator: tauri::State<'_, WriteCoordinator>,
+  settings: tauri::State<'_, SettingsState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  text: String,
+) -> CommandResult<String> {
+  if text.trim().is_empty() {
+    return Err(CommandError::invalid_data("Nothing to capture"));
+  }
+  let (options, durable) = {
+    let settings = settings.0.lock().unwrap();
+    (settings.inbox.clone(), settings.durable_saves)
+  };
+  let base = inbox_base(&app, &options)?;
+  let name = InboxName::new(&base);
+  scope.check(&base)?;
+  let (result, _) = coordinator
+    .submit(&base, move || {
+      let existing: Vec<(Period, u64)> = inbox_files(&name)
+        .iter()
+        .map(|(period, _, metadata)| (*period, metadata.len()))
+        .collect();
+      let plan = plan_append(&options, today(), &existing, text.len() as u64);
+      let target = name.path(plan.target);
+      let content = if target.exists() {
+        crate::read_text_file(&target)?
+      } else {
+        std::fs::create_dir_all(&name.folder)
+          .map_err(|e| CommandError::from_io(&e, &name.folder, "Failed to create folder"))?;
+        String::new()
+      };
+      let previous = plan.previous.map(|period| name.file_name(period));
+      let updated = appended(&content, &text, previous.as_deref());
+      crate::write_text_file(&target, &updated, None, durable)?;
+      Ok(target.to_string_lossy().to_string())
+    })
+    .await?;
+  result
+}
+
+// An inbox file, for the capture history
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InboxFile {
+  pub path: String,
+  pub name: String,
+  pub size: u64,
+  // Milliseconds since the epoch
+  pub modified: Option<u64>,
+  // The file captures go to now
+  pub active: bool,
+}
+
+// The inbox's files, newest first
+#[tauri::command]
+pub async fn list_inbox_files(
+  app: AppHandle,
+  settings: tauri::State<'_, SettingsState>,
+  scope: tauri::State<'_, AccessScopeState>,
+) -> CommandResult<Vec<InboxFile>> {
+  let options = settings.0.lock().unwrap().inbox.clone();
+  let base = inbox_base(&app, &options)?;
+  scope.check(&base)?;
+  let name = InboxName::new(&base);
+  let active = active_path(&name, &options);
+  let mut files = inbox_files(&name);
+  files.sort_by(|a, b| b.0.cmp(&a.0));
+  Ok(
+    files
+      .into_iter()
+      .map(|(_, path, metadata)| InboxFile {
+        name: path
+          .file_name()
+          .map(|name| name.to_string_lossy().to_string())
+          .unwrap_or_default(),
+        size: metadata.len(),
+        modified: file_mtime_millis(&metadata),
+        active: path == active,
+        path: path.to_string_lossy().to_string(),
+      })
+      .collect(),
+  )
+}
+
+// The file captures go to now, whether or not it exists yet
+#[tauri::command]
+pub async fn get_inbox_path(
+  app: AppHandle,
+  settings: tauri::State<'_, SettingsState>,
+) -> CommandResult<String> {
+  let options = settings.0.lock().unwrap().inbox.clone();
+  let base = inbox_base(&app, &options)?;
+  Ok(
+    active_path(&InboxName::new(&base), &options)
+      .to_string_lossy()
+      .to_string(),
+  )
+}
+
+// File > Open Inbox: open the active inbox file, creating it empty if this period has no
+// captures yet
+pub fn open_inbox(app: &AppHandle) -> CommandResult<()> {
+  let options = app.state::<SettingsState>().0.lock().unwrap().inbox.clone();
+  let base = inbox_base(app, &options)?;
+  let path = active_path(&InboxName::new(&base), &options);
+  if !path.exists() {
+    if let Some(folder) = path.parent() {
+      std::fs::create_dir_all(folder)
+        .map_err(|e| CommandError::from_io(&e, folder, "Failed to create folder"))?;
+    }
+    crate::write_text_file(&path, "", None, false)?;
+  }
+  launch::open_files(app, &[path.to_string_lossy().to_string().into()]);
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn name() -> InboxName {
+    InboxName::new(Path::new("/notes/inbox.md"))
+  }
+
+  fn options(rotation: InboxRotation) -> InboxOptions {
+    InboxOptions {
+      rotation,
+      max_size_kb: 1,
+      ..InboxOptions::default()
+    }
+  }
+
+  #[test]
+  fn test_rotated_file_names_round_trip() {
+    let name = name();
+    for period in [Period::Part(1), Period::Part(12), Period::Month(2024, 6)] {
+      assert_eq!(name.period(&name.file_name(period)), Some(period));
+    }
+    assert_eq!(name.file_name(Period::Month(2024, 6)), "inbox-2024-06.md");
+    assert_eq!(
+      name.path(Period::Part(2)),
+      PathBuf::from("/notes/inbox-2.md")
+    );
+    for other in [
+      "inbox.txt",
+      "inbox-1.md",
+      "inbox-02.md",
+      "inbox-2024-13.md",
+      "inbox-notes.md",
+      "outbox.md",
+    ] {
+      assert_eq!(name.period(other), None, "{}", other);
+    }
+  }
+
+  #[test]
+  fn test_captures_rotate_by_month_and_size() {
+    let plan = plan_append(&options(InboxRotation::Never), (2024, 6), &[], 10);
+    assert_eq!(plan.target, Period::Part(1));
+
+    // A new month links the newest file before it, but only when starting the file
+    let existing = [(Period::Month(2024, 4), 10), (Period::Month(2024, 5), 10)];
+    let monthly = options(InboxRotation::Monthly);
+    assert_eq!(
+      plan_append(&monthly, (2024, 6), &existing, 10),
+      AppendPlan {
+        target: Period::Month(2024, 6),
+        previous: Some(Period::Month(2024, 5)),
+      }
+    );
+    assert_eq!(
+      plan_append(&monthly, (2024, 5), &existing, 10).previous,
+      None
+    );
+
+    let size = options(InboxRotation::Size);
+    let existing = [(Period::Part(1), 1000), (Period::Part(2), 900)];
+    assert_eq!(
+      plan_append(&size, (2024, 6), &existing, 100).target,
+      Period::Part(2)
+    );
+    assert_eq!(
+      plan_append(&size, (2024, 6), &existing, 200),
+      AppendPlan {
+        target: Period::Part(3),
+        previous: Some(Period::Part(2)),
+      }
+    );
+    // An oversized capture still goes into an empty inbox
+    assert_eq!(
+      plan_append(&size, (2024, 6), &[], 5000).target,
+      Period::Part(1)
+    );
+  }
+
+  #[test]
+  fn test_new_files_start_with_a_link_to_the_previous_one() {
+    assert_eq!(
+      appended("", "Call Sam\n", Some("inbox 2024-05.md")),
+      "Previous: [inbox 2024-05.md](inbox%202024-05.md)\n\nCall Sam\n"
+    );
+    assert_eq!(appended("First\n", "Second", None), "First\n\nSecond\n");
+    assert_eq!(appended("", "Only", None), "Only\n");
+  }
+}
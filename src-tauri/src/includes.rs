@@ -0,0 +1,349 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::error::{CommandError, CommandResult};
+
+// Nesting allowed when the caller doesn't say
+const DEFAULT_MAX_DEPTH: usize = 8;
+
+// Upper bound on what the caller may ask for; deeper than this is almost certainly a mistake
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+// Extension assumed for `![[part]]`, the way wiki links leave it off
+const DEFAULT_EXTENSION: &str = "md";
+
+// A document with its includes stitched in
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResolvedIncludes {
+  pub markdown: String,
+  // Every included file (canonical paths, each once), for the caller to watch for changes
+  pub dependencies: Vec<String>,
+}
+
+struct Resolver<'a> {
+  // Includes are only read from inside it, whatever `..` or absolute paths they use
+  scope: &'a AccessScope,
+  max_depth: usize,
+  demote_headings: bool,
+  dependencies: Vec<PathBuf>,
+  // Files currently being resolved, outermost first
+  chain: Vec<PathBuf>,
+}
+
+// The target of an include on its own line: `![[parts/intro.md]]` or
+// `<!-- include: parts/intro.md -->`
+fn include_target(line: &str) -> Option<&str> {
+  let line = line.trim();
+  let target = if let Some(inner) = line
+    .strip_prefix("![[")
+    .and_then(|rest| rest.strip_suffix("]]"))
+  {
+    // `![[part|alias]]`: the alias means nothing for an include
+    inner.split('|').next().unwrap_or(inner)
+  } else {
+    line
+      .strip_prefix("<!--")
+      .and_then(|rest| rest.strip_suffix("-->"))
+      .and_then(|rest| rest.trim().strip_prefix("include:"))?
+  };
+  let target = target.trim();
+  (!target.is_empty()).then_some(target)
+}
+
+// Level of an ATX heading (`## Title`) and the text after its hashes
+pub(crate) fn atx_heading(line: &str) -> Option<(usize, &str)> {
+  let indent = line.len() - line.trim_start_matches(' ').len();
+  if indent > 3 {
+    return None;
+  }
+  let rest = &line[indent..];
+  let level = rest.chars().take_while(|c| *c == '#').count();
+  let after = &rest[level..];
+  let is_heading =
+    (1..=6).contains(&level) && (after.is_empty() || after.starts_with([' ', '\t', '\r', '\n']));
+  is_heading.then_some((level, after))
+}
+
+pub(crate) fn is_fence(line: &str) -> bool {
+  let trimmed = line.trim_start();
+  trimmed.starts_with("```") || trimmed.starts_with("~~~")
+}
+
+// An included part's frontmatter describes the part, not the merged document
+fn strip_frontmatter(content: &str) -> &str {
+  let mut lines = content.split_inclusive('\n');
+  if lines.next().map(str::trim_end) != Some("---") {
+    return content;
+  }
+  let mut offset = content.find('\n').map_or(content.len(), |i| i + 1);
+  for line in lines {
+    offset += line.len();
+    if matches!(line.trim_end(), "---" | "...") {
+      return &content[offset..];
+    }
+  }
+  content
+}
+
+fn display_chain(chain: &[PathBuf], last: &Path) -> Vec<String> {
+  chain
+    .iter()
+    .map(|path| path.as_path())
+    .chain(std::iter::once(last))
+    .map(|path| path.to_string_lossy().to_string())
+    .collect()
+}
+
+impl Resolver<'_> {
+  // Append `path` to `out`, with its headings shifted down `offset` levels (when demoting)
+  // and its includes resolved
+  fn resolve_file(&mut self, path: &Path, offset: usize, out: &mut String) -> CommandResult<()> {
+    let canonical = std::fs::canonicalize(path)
+      .map_err(|e| CommandError::from_io(&e, path, "Failed to resolve include"))?;
+    self.scope.check(&canonical)?;
+    if self.chain.contains(&canonical) {
+      return Err(CommandError::IncludeCycle {
+        chain: display_chain(&self.chain, &canonical),
+      });
+    }
+    // The document itself is depth 0
+    if self.chain.len() > self.max_depth {
+      return Err(CommandError::invalid_data(format!(
+        "Includes are nested more than {} levels deep: {}",
+        self.max_depth,
+        display_chain(&self.chain, &canonical).join(" -> ")
+      )));
+    }
+    let content = crate::read_text_file(&canonical)?;
+    let content = if self.chain.is_empty() {
+      content.as_str()
+    } else {
+      if !self.dependencies.contains(&canonical) {
+        self.dependencies.push(canonical.clone());
+      }
+      strip_frontmatter(&content)
+    };
+    let dir = canonical
+      .parent()
+      .map(Path::to_path_buf)
+      .unwrap_or_default();
+    self.chain.push(canonical);
+
+    let mut in_fence = false;
+    // Level of the last heading written from this file, which includes below it nest under
+    let mut site_level = offset;
+    for line in content.split_inclusive('\n') {
+      if is_fence(line) {
+        in_fence = !in_fence;
+      }
+      if in_fence {
+        out.push_str(line);
+        continue;
+      }
+      if let Some(target) = include_target(line) {
+        let mut target_path = dir.join(target);
+        if target_path.extension().is_none() {
+          target_path.set_extension(DEFAULT_EXTENSION);
+        }
+        let nested_offset = if self.demote_headings { site_level } else { 0 };
+        self.resolve_file(&target_path, nested_offset, out)?;
+        if !out.is_empty() && !out.ends_with('\n') {
+          out.push('\n');
+        }
+        continue;
+      }
+      match atx_heading(line) {
+        Some((level, text)) => {
+          let level = (level + offset).min(6);
+          site_level = level;
+          out.push_str(&"#".repeat(level));
+          out.push_str(text);
+        }
+        None => out.push_str(line),
+      }
+    }
+
+    self.chain.pop();
+    Ok(())
+  }
+}
+
+fn resolve_includes_in(
+  scope: &AccessScope,
+  path: &Path,
+  max_depth: usize,
+  demote_headings: bool,
+) -> CommandResult<ResolvedIncludes> {
+  scope.check(path)?;
+  let mut resolver = Resolver {
+    scope,
+    max_depth: max_depth.min(MAX_INCLUDE_DEPTH),
+    demote_headings,
+    dependencies: Vec::new(),
+    chain: Vec::new(),
+  };
+  let mut markdown = String::new();
+  resolver.resolve_file(path, 0, &mut markdown)?;
+  Ok(ResolvedIncludes {
+    markdown,
+    dependencies: resolver
+      .dependencies
+      .iter()
+      .map(|path| path.to_string_lossy().to_string())
+      .collect(),
+  })
+}
+
+// Stitch a document split into parts back together: each `![[part.md]]` or
+// `<!-- include: part.md -->` line is replaced by that file (relative to the file including
+// it), recursively. With `demote_headings`, an included part's headings nest under the
+// heading the include appears below. Parts outside the access scope are refused.
+#[tauri::command]
+pub async fn resolve_includes(
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+  max_depth: Option<usize>,
+  demote_headings: Option<bool>,
+) -> CommandResult<ResolvedIncludes> {
+  resolve_includes_in(
+    &scope.0.lock().unwrap(),
+    Path::new(&path),
+    max_depth.unwrap_or(DEFAULT_MAX_DEPTH),
+    demote_headings.unwrap_or(false),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_resolves_nested_includes() {
+    let dir = TempDir::new().unwrap();
+    let scope = AccessScope::with_folders(&[dir.path()]);
+    fs::create_dir(dir.path().join("parts")).unwrap();
+    fs::write(
+      dir.path().join("book.md"),
+      "# Book\n\n![[parts/one.md]]\n\n```\n![[not-an-include.md]]\n```\n\
+       <!-- include: parts/two -->\n",
+    )
+    .unwrap();
+    fs::write(
+      dir.path().join("parts/one.md"),
+      "---\ntitle: One\n---\n# One\n![[../parts/two.md|again]]\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("parts/two.md"), "Two.").unwrap();
+
+    let resolved = resolve_includes_in(&scope, &dir.path().join("book.md"), 8, false).unwrap();
+    assert_eq!(
+      resolved.markdown,
+      "# Book\n\n# One\nTwo.\n\n```\n![[not-an-include.md]]\n```\nTwo.\n"
+    );
+    let canonical = |p: &str| {
+      fs::canonicalize(dir.path().join(p))
+        .unwrap()
+        .to_string_lossy()
+        .to_string()
+    };
+    assert_eq!(
+      resolved.dependencies,
+      vec![canonical("parts/one.md"), canonical("parts/two.md")]
+    );
+  }
+
+  #[test]
+  fn test_demotes_included_headings_under_the_include_site() {
+    let dir = TempDir::new().unwrap();
+    let scope = AccessScope::with_folders(&[dir.path()]);
+    fs::write(
+      dir.path().join("main.md"),
+      "# Title\n## Chapter\n![[part]]\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("part.md"), "# Part\n## Section\n").unwrap();
+
+    let resolved = resolve_includes_in(&scope, &dir.path().join("main.md"), 8, true).unwrap();
+    assert_eq!(
+      resolved.markdown,
+      "# Title\n## Chapter\n### Part\n#### Section\n"
+    );
+  }
+
+  #[test]
+  fn test_cycles_and_depth_are_reported() {
+    let dir = TempDir::new().unwrap();
+    let scope = AccessScope::with_folders(&[dir.path()]);
+    fs::write(dir.path().join("a.md"), "![[b.md]]\n").unwrap();
+    fs::write(dir.path().join("b.md"), "![[a.md]]\n").unwrap();
+
+    let error = resolve_includes_in(&scope, &dir.path().join("a.md"), 8, false).unwrap_err();
+    match error {
+      CommandError::IncludeCycle { chain } => {
+        assert_eq!(chain.len(), 3);
+        assert!(chain[0].ends_with("a.md") && chain[1].ends_with("b.md"));
+        assert_eq!(chain[0], chain[2]);
+      }
+      other => panic!("expected include_cycle, got {:?}", other),
+    }
+
+    fs::write(dir.path().join("b.md"), "![[c.md]]\n").unwrap();
+    fs::write(dir.path().join("c.md"), "C\n").unwrap();
+    let error = resolve_includes_in(&scope, &dir.path().join("a.md"), 1, false).unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+    assert_eq!(
+      resolve_includes_in(&scope, &dir.path().join("a.md"), 2, false)
+        .unwrap()
+        .markdown,
+      "C\n"
+    );
+  }
+
+  #[test]
+  fn test_documents_outside_the_scope_are_denied() {
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("book.md"), "# Book\n").unwrap();
+    let error = resolve_includes_in(
+      &AccessScope::default(),
+      &dir.path().join("book.md"),
+      8,
+      false,
+    )
+    .unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+  }
+
+  #[test]
+  fn test_includes_cannot_leave_the_scope() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    fs::create_dir_all(notes.join("parts")).unwrap();
+    fs::create_dir(dir.path().join("private")).unwrap();
+    let secret = dir.path().join("private/secret.md");
+    fs::write(&secret, "Secret.\n").unwrap();
+    fs::write(notes.join("parts/one.md"), "One.\n").unwrap();
+    let scope = AccessScope::with_folders(&[&notes]);
+    let book = notes.join("book.md");
+
+    for include in [
+      "![[../private/secret]]".to_string(),
+      "![[parts/../../private/secret.md]]".to_string(),
+      format!("<!-- include: {} -->", secret.display()),
+    ] {
+      fs::write(&book, format!("# Book\n{}\n", include)).unwrap();
+      let error = resolve_includes_in(&scope, &book, 8, false).unwrap_err();
+      assert_eq!(error.code(), "scope_denied", "{}", include);
+    }
+
+    fs::write(&book, "![[parts/../parts/one]]\n").unwrap();
+    assert_eq!(
+      resolve_includes_in(&scope, &book, 8, false)
+        .unwrap()
+        .markdown,
+      "One.\n"
+    );
+  }
+}
@@ -0,0 +1,815 @@
+use pulldown_cmark::{
+  Alignment, CodeBlockKind, Event, HeadingLevel, LinkType, Options, Parser, Tag, TagEnd,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::assets::resolve_reference;
+use crate::error::{CommandError, CommandResult};
+use crate::export::{
+  copy_assets, requested_output_path, scoped_assets, DocumentAsset, ExportResult, EXPORT_ASSETS_DIR,
+};
+use crate::staging::write_staged;
+
+// Packages the converted body relies on, loaded by standalone documents and listed at the
+// top of fragments for the including document to load
+const PACKAGES: &[&str] = &[
+  "[T1]{fontenc}",
+  "{amsmath,amssymb}",
+  "{graphicx}",
+  "{booktabs}",
+  "{listings}",
+  "[normalem]{ulem}",
+  "{hyperref}",
+];
+
+// Classes whose top-level division is the chapter, so H1 maps to \chapter
+const CHAPTER_CLASSES: &[&str] = &["book", "report", "memoir", "scrbook", "scrreprt"];
+
+// Code fence languages listings knows, by the names markdown uses for them. Other
+// languages get a plain listing; listings stops with an error on a language it doesn't know.
+const LISTINGS_LANGUAGES: &[(&str, &str)] = &[
+  ("bash", "bash"),
+  ("c", "C"),
+  ("c++", "C++"),
+  ("cpp", "C++"),
+  ("cs", "[Sharp]C"),
+  ("csharp", "[Sharp]C"),
+  ("fortran", "Fortran"),
+  ("haskell", "Haskell"),
+  ("html", "HTML"),
+  ("java", "Java"),
+  ("latex", "TeX"),
+  ("lisp", "Lisp"),
+  ("lua", "Lua"),
+  ("matlab", "Matlab"),
+  ("pascal", "Pascal"),
+  ("perl", "Perl"),
+  ("php", "PHP"),
+  ("py", "Python"),
+  ("python", "Python"),
+  ("r", "R"),
+  ("rb", "Ruby"),
+  ("ruby", "Ruby"),
+  ("scala", "Scala"),
+  ("sh", "sh"),
+  ("shell", "bash"),
+  ("sql", "SQL"),
+  ("tex", "TeX"),
+  ("xml", "XML"),
+];
+
+// Stand-ins for footnote references until every footnote's text is known. Private-use
+// characters, so prose never contains them.
+const FOOTNOTE_OPEN: char = '\u{E000}';
+const FOOTNOTE_CLOSE: char = '\u{E001}';
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct LatexOptions {
+  // \documentclass of a standalone document. book and report-like classes start H1
+  // headings as chapters.
+  pub document_class: String,
+  // A complete document, or just the body for \input into another one
+  pub standalone: bool,
+}
+
+impl Default for LatexOptions {
+  fn default() -> Self {
+    LatexOptions {
+      document_class: "article".to_string(),
+      standalone: true,
+    }
+  }
+}
+
+// Escape the characters LaTeX treats specially in running text
+fn escape_latex(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '\\' => escaped.push_str("\\textbackslash{}"),
+      '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+        escaped.push('\\');
+        escaped.push(c);
+      }
+      '~' => escaped.push_str("\\textasciitilde{}"),
+      '^' => escaped.push_str("\\textasciicircum{}"),
+      _ => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+// A URL as an \href or \includegraphics argument, where only % and # still need escaping
+fn escape_url(url: &str) -> String {
+  url.replace('%', "\\%").replace('#', "\\#")
+}
+
+fn listings_language(language: &str) -> Option<&'static str> {
+  let language = language.to_lowercase();
+  LISTINGS_LANGUAGES
+    .iter()
+    .find(|(name, _)| *name == language)
+    .map(|(_, listings)| *listings)
+}
+
+fn sectioning_command(level: HeadingLevel, chapters: bool) -> &'static str {
+  const COMMANDS: [&str; 6] = [
+    "chapter",
+    "section",
+    "subsection",
+    "subsubsection",
+    "paragraph",
+    "subparagraph",
+  ];
+  let index = level as usize - 1 + usize::from(!chapters);
+  COMMANDS[index.min(COMMANDS.len() - 1)]
+}
+
+// An image being read: where it points and its alt text
+struct ImageState {
+  path: String,
+  remote: bool,
+  alt: String,
+  // Alone in its paragraph, so it becomes a figure with the alt text as caption
+  figure: bool,
+}
+
+// Turns markdown events into LaTeX
+struct LatexWriter<'a> {
+  out: String,
+  // H1 is \chapter rather than \section
+  chapters: bool,
+  // Image reference as written -> path to use in \includegraphics
+  images: &'a HashMap<String, String>,
+  // Open lists, true for numbered ones
+  lists: Vec<bool>,
+  // Open links, true when written as \href (false for wiki links and in-document anchors)
+  links: Vec<bool>,
+  // Language and text of the code block being read
+  code: Option<(Option<String>, String)>,
+  image: Option<ImageState>,
+  // Cells written so far in the table row being read
+  cells: usize,
+  // Footnote being read, with the output it interrupted
+  footnote: Option<(String, String)>,
+  footnotes: HashMap<String, String>,
+  in_metadata: bool,
+}
+
+impl LatexWriter<'_> {
+  fn text(&mut self, text: &str) {
+    if self.in_metadata {
+      return;
+    }
+    if let Some((_, code)) = self.code.as_mut() {
+      code.push_str(text);
+    } else if let Some(image) = self.image.as_mut() {
+      image.alt.push_str(&escape_latex(text));
+    } else {
+      self.out.push_str(&escape_latex(text));
+    }
+  }
+
+  // Start the next thing on a line of its own
+  fn line(&mut self) {
+    if !self.out.is_empty() && !self.out.ends_with('\n') {
+      self.out.push('\n');
+    }
+  }
+
+  fn start(&mut self, tag: Tag, figure: bool) {
+    match tag {
+      Tag::Paragraph | Tag::HtmlBlock | Tag::DefinitionListDefinition => {}
+      Tag::Heading { level, .. } => {
+        self.line();
+        self.out.push('\\');
+        self.out.push_str(sectioning_command(level, self.chapters));
+        self.out.push('{');
+      }
+      Tag::BlockQuote(_) => {
+        self.line();
+        self.out.push_str("\\begin{quote}\n");
+      }
+      Tag::CodeBlock(kind) => {
+        let language = match kind {
+          CodeBlockKind::Fenced(info) => info.split_whitespace().next().map(str::to_string),
+          CodeBlockKind::Indented => None,
+        };
+        self.code = Some((language, String::new()));
+      }
+      Tag::List(start) => {
+        self.line();
+        match start {
+          Some(start) => {
+            self.out.push_str("\\begin{enumerate}\n");
+            let depth = self.lists.iter().filter(|numbered| **numbered).count();
+            let counter = ["enumi", "enumii", "enumiii", "enumiv"].get(depth);
+            if let (Some(counter), true) = (counter, start != 1) {
+              self.out.push_str(&format!(
+                "\\setcounter{{{}}}{{{}}}\n",
+                counter,
+                start.saturating_sub(1)
+              ));
+            }
+          }
+          None => self.out.push_str("\\begin{itemize}\n"),
+        }
+        self.lists.push(start.is_some());
+      }
+      Tag::Item => {
+        self.line();
+        self.out.push_str("\\item ");
+      }
+      Tag::DefinitionList => {
+        self.line();
+        self.out.push_str("\\begin{description}\n");
+      }
+      Tag::DefinitionListTitle => {
+        self.line();
+        self.out.push_str("\\item[{");
+      }
+      Tag::FootnoteDefinition(label) => {
+        let interrupted = std::mem::take(&mut self.out);
+        self.footnote = Some((label.to_string(), interrupted));
+      }
+      Tag::Table(alignments) => {
+        let columns: String = alignments
+          .iter()
+          .map(|alignment| match alignment {
+            Alignment::Center => 'c',
+            Alignment::Right => 'r',
+            Alignment::None | Alignment::Left => 'l',
+          })
+          .collect();
+        self.line();
+        self.out.push_str(&format!(
+          "\\begin{{center}}\n\\begin{{tabular}}{{{}}}\n\\toprule\n",
+          columns
+        ));
+      }
+      Tag::TableHead | Tag::TableRow => self.cells = 0,
+      Tag::TableCell => {
+        if self.cells > 0 {
+          self.out.push_str(" & ");
+        }
+        self.cells += 1;
+      }
+      Tag::Emphasis => self.out.push_str("\\emph{"),
+      Tag::Strong => self.out.push_str("\\textbf{"),
+      Tag::Strikethrough => self.out.push_str("\\sout{"),
+      Tag::Superscript => self.out.push_str("\\textsuperscript{"),
+      Tag::Subscript => self.out.push_str("\\textsubscript{"),
+      Tag::Link {
+        link_type,
+        dest_url,
+        ..
+      } => {
+        let href = link_type != LinkType::WikiLink && !dest_url.starts_with('#');
+        if href {
+          let url = if link_type == LinkType::Email {
+            format!("mailto:{}", dest_url)
+          } else {
+            dest_url.to_string()
+          };
+          self
+            .out
+            .push_str(&format!("\\href{{{}}}{{", escape_url(&url)));
+        }
+        self.links.push(href);
+      }
+      Tag::Image { dest_url, .. } => {
+        let remote = dest_url.contains("://");
+        let path = self
+          .images
+          .get(&*dest_url)
+          .cloned()
+          .unwrap_or_else(|| dest_url.to_string());
+        self.image = Some(ImageState {
+          path,
+          remote,
+          alt: String::new(),
+          figure,
+        });
+      }
+      Tag::MetadataBlock(_) => self.in_metadata = true,
+    }
+  }
+
+  fn end(&mut self, tag: TagEnd) {
+    match tag {
+      TagEnd::Paragraph => self.out.push_str("\n\n"),
+      TagEnd::Heading(_) => self.out.push_str("}\n\n"),
+      TagEnd::BlockQuote(_) => {
+        self.line();
+        self.out.push_str("\\end{quote}\n\n");
+      }
+      TagEnd::CodeBlock => {
+        let Some((language, mut code)) = self.code.take() else {
+          return;
+        };
+        if !code.ends_with('\n') {
+          code.push('\n');
+        }
+        self.line();
+        let environment = match language.as_deref() {
+          None => "\\begin{verbatim}\n".to_string(),
+          Some(language) => match listings_language(language) {
+            Some(listings) => format!("\\begin{{lstlisting}}[language={}]\n", listings),
+            None => "\\begin{lstlisting}\n".to_string(),
+          },
+        };
+        let end = if language.is_some() {
+          "\\end{lstlisting}\n\n"
+        } else {
+          "\\end{verbatim}\n\n"
+        };
+        self.out.push_str(&environment);
+        self.out.push_str(&code);
+        self.out.push_str(end);
+      }
+      TagEnd::List(numbered) => {
+        self.lists.pop();
+        self.line();
+        self.out.push_str(if numbered {
+          "\\end{enumerate}\n\n"
+        } else {
+          "\\end{itemize}\n\n"
+        });
+      }
+      TagEnd::Item | TagEnd::DefinitionListDefinition => self.line(),
+      TagEnd::DefinitionList => {
+        self.line();
+        self.out.push_str("\\end{description}\n\n");
+      }
+      TagEnd::DefinitionListTitle => self.out.push_str("}] "),
+      TagEnd::FootnoteDefinition => {
+        if let Some((label, interrupted)) = self.footnote.take() {
+          let text = std::mem::replace(&mut self.out, interrupted);
+          self.footnotes.insert(label, text.trim().to_string());
+        }
+      }
+      TagEnd::Table => self
+        .out
+        .push_str("\\bottomrule\n\\end{tabular}\n\\end{center}\n\n"),
+      TagEnd::TableHead => self.out.push_str(" \\\\\n\\midrule\n"),
+      TagEnd::TableRow => self.out.push_str(" \\\\\n"),
+      TagEnd::Emphasis
+      | TagEnd::Strong
+      | TagEnd::Strikethrough
+      | TagEnd::Superscript
+      | TagEnd::Subscript => self.out.push('}'),
+      TagEnd::Link => {
+        if self.links.pop() == Some(true) {
+          self.out.push('}');
+        }
+      }
+      TagEnd::Image => {
+        let Some(image) = self.image.take() else {
+          return;
+        };
+        // LaTeX can't include a remote image; link to it instead
+        if image.remote {
+          let text = if image.alt.is_empty() {
+            escape_latex(&image.path)
+          } else {
+            image.alt
+          };
+          self.out.push_str(&format!(
+            "\\href{{{}}}{{{}}}",
+            escape_url(&image.path),
+            text
+          ));
+        } else if image.figure {
+          self.out.push_str(&format!(
+            "\\begin{{figure}}[htbp]\n\\centering\n\\includegraphics[width=\\linewidth]{{{}}}\n",
+            escape_url(&image.path)
+          ));
+          if !image.alt.is_empty() {
+            self.out.push_str(&format!("\\caption{{{}}}\n", image.alt));
+          }
+          self.out.push_str("\\end{figure}\n\n");
+        } else {
+          // In running text, e.g. an icon or a badge: sized to the line
+          self.out.push_str(&format!(
+            "\\includegraphics[height=1em]{{{}}}",
+            escape_url(&image.path)
+          ));
+        }
+      }
+      TagEnd::MetadataBlock(_) => self.in_metadata = false,
+      TagEnd::TableCell | TagEnd::HtmlBlock => {}
+    }
+  }
+
+  fn event(&mut self, event: Event, figure: bool) {
+    match event {
+      Event::Start(tag) => self.start(tag, figure),
+      Event::End(tag) => self.end(tag),
+      Event::Text(text) => self.text(&text),
+      Event::Code(code) => {
+        self.out.push_str("\\texttt{");
+        self.out.push_str(&escape_latex(&code));
+        self.out.push('}');
+      }
+      // Math is already LaTeX
+      Event::InlineMath(math) => {
+        self.out.push('$');
+        self.out.push_str(&math);
+        self.out.push('$');
+      }
+      Event::DisplayMath(math) => {
+        self.out.push_str("\\[");
+        self.out.push_str(&math);
+        self.out.push_str("\\]");
+      }
+      Event::FootnoteReference(label) => {
+        self.out.push(FOOTNOTE_OPEN);
+        self.out.push_str(&label);
+        self.out.push(FOOTNOTE_CLOSE);
+      }
+      Event::SoftBreak => self.out.push('\n'),
+      Event::HardBreak => self.out.push_str("\\\\\n"),
+      Event::Rule => {
+        self.line();
+        self
+          .out
+          .push_str("\\noindent\\rule{\\linewidth}{0.4pt}\n\n");
+      }
+      Event::TaskListMarker(checked) => {
+        if self.out.ends_with("\\item ") {
+          self.out.truncate(self.out.len() - "\\item ".len());
+          self.out.push_str(if checked {
+            "\\item[$\\boxtimes$] "
+          } else {
+            "\\item[$\\square$] "
+          });
+        }
+      }
+      // LaTeX has no use for HTML
+      Event::Html(_) | Event::InlineHtml(_) => {}
+    }
+  }
+
+  // The output with footnote references replaced by their text
+  fn finish(self) -> String {
+    let mut finished = String::with_capacity(self.out.len());
+    let mut rest = self.out.as_str();
+    while let Some(open) = rest.find(FOOTNOTE_OPEN) {
+      finished.push_str(&rest[..open]);
+      rest = &rest[open + FOOTNOTE_OPEN.len_utf8()..];
+      let close = rest.find(FOOTNOTE_CLOSE).unwrap_or(rest.len());
+      if let Some(text) = self.footnotes.get(&rest[..close]) {
+        finished.push_str(&format!("\\footnote{{{}}}", text));
+      }
+      rest = rest.get(close + FOOTNOTE_CLOSE.len_utf8()..).unwrap_or("");
+    }
+    finished.push_str(rest);
+    format!("{}\n", finished.trim_end())
+  }
+}
+
+// Markdown to the body of a LaTeX document. `images` maps image references as written to
+// the path \includegraphics should use; others are used as written. Raw HTML is dropped and
+// frontmatter is left out.
+fn markdown_to_latex(markdown: &str, images: &HashMap<String, String>, chapters: bool) -> String {
+  let events: Vec<Event> = Parser::new_ext(markdown, Options::all()).collect();
+  let mut writer = LatexWriter {
+    out: String::new(),
+    chapters,
+    images,
+    lists: Vec::new(),
+    links: Vec::new(),
+    code: None,
+    image: None,
+    cells: 0,
+    footnote: None,
+    footnotes: HashMap::new(),
+    in_metadata: false,
+  };
+  // Set from a paragraph holding nothing but an image to that image's end
+  let mut figure_until: Option<usize> = None;
+  for (index, event) in events.iter().enumerate() {
+    if let (Event::Start(Tag::Paragraph), Some(Event::Start(Tag::Image { .. }))) =
+      (event, events.get(index + 1))
+    {
+      let image_end = events[index + 1..]
+        .iter()
+        .position(|event| matches!(event, Event::End(TagEnd::Image)))
+        .map(|offset| index + 1 + offset);
+      if let Some(image_end) =
+        image_end.filter(|end| matches!(events.get(end + 1), Some(Event::End(TagEnd::Paragraph))))
+      {
+        figure_until = Some(image_end + 1);
+        continue;
+      }
+    }
+    match figure_until {
+      // The lone image's paragraph end; the figure is a block of its own
+      Some(end) if end == index => figure_until = None,
+      Some(_) => writer.event(event.clone(), true),
+      None => writer.event(event.clone(), false),
+    }
+  }
+  writer.finish()
+}
+
+// A complete document around `body`, or `body` with a note of the packages it needs
+fn latex_document(body: &str, options: &LatexOptions) -> String {
+  let packages: Vec<String> = PACKAGES
+    .iter()
+    .map(|package| format!("\\usepackage{}", package))
+    .collect();
+  if !options.standalone {
+    return format!(
+      "% Needs these packages in the including document:\n% {}\n\n{}",
+      packages.join("\n% "),
+      body
+    );
+  }
+  format!(
+    "\\documentclass{{{}}}\n{}\n\\lstset{{basicstyle=\\ttfamily\\small,breaklines=true}}\n\n\\begin{{document}}\n\n{}\n\\end{{document}}\n",
+    options.document_class,
+    packages.join("\n"),
+    body
+  )
+}
+
+fn export_latex_to(
+  scope: &AccessScope,
+  document: Option<&Path>,
+  markdown: &str,
+  output_path: &Path,
+  options: &LatexOptions,
+) -> CommandResult<ExportResult> {
+  if !output_path.is_absolute() {
+    return Err(CommandError::invalid_path(
+      output_path,
+      "File path must be absolute",
+    ));
+  }
+  let class = options.document_class.trim();
+  if class.is_empty() || !class.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+    return Err(CommandError::invalid_data(format!(
+      "Not a document class: {}",
+      options.document_class
+    )));
+  }
+
+  let root = output_path.parent().ok_or_else(|| {
+    CommandError::invalid_path(output_path, "Export path has no parent directory")
+  })?;
+  let file_name = output_path.file_name().unwrap_or_default();
+  // The .tex file and its images are moved into place together once all are written
+  write_staged(root, output_path, |staging| {
+    // Local images are copied into assets/ next to the export, like HTML exports do
+    let mut images = HashMap::new();
+    let mut copied_assets = Vec::new();
+    let mut warnings = Vec::new();
+    if let Some(document) = document {
+      let mut assets: Vec<DocumentAsset> = Vec::new();
+      for event in Parser::new_ext(markdown, Options::all()) {
+        let Event::Start(Tag::Image { dest_url, .. }) = event else {
+          continue;
+        };
+        let Some(path) = resolve_reference(document, &dest_url) else {
+          continue;
+        };
+        if !path.is_file() {
+          warnings.push(format!("Image {} was not found", dest_url));
+        } else if !assets.iter().any(|asset| asset.reference == &*dest_url) {
+          assets.push(DocumentAsset {
+            reference: dest_url.to_string(),
+            path,
+          });
+        }
+      }
+      let assets = scoped_assets(scope, assets, &mut warnings);
+      let exported = copy_assets(&assets, &staging.join(EXPORT_ASSETS_DIR))?;
+      for asset in &assets {
+        if let Some(name) = exported.get(&asset.path) {
+          images.insert(
+            asset.reference.clone(),
+            format!("{}/{}", EXPORT_ASSETS_DIR, name),
+          );
+        }
+      }
+      let assets_dir = root.join(EXPORT_ASSETS_DIR);
+      copied_assets = exported
+        .into_values()
+        .map(|name| assets_dir.join(name).to_string_lossy().to_string())
+        .collect();
+      copied_assets.sort();
+      copied_assets.dedup();
+    }
+
+    let chapters = CHAPTER_CLASSES.contains(&class);
+    let options = LatexOptions {
+      document_class: class.to_string(),
+      ..options.clone()
+    };
+    let body = markdown_to_latex(markdown, &images, chapters);
+    let staged_path = staging.join(file_name);
+    std::fs::write(&staged_path, latex_document(&body, &options))
+      .map_err(|e| CommandError::from_io(&e, &staged_path, "Failed to write file"))?;
+
+    Ok(ExportResult {
+      output_path: output_path.to_string_lossy().to_string(),
+      copied_assets,
+      warnings,
+    })
+  })
+}
+
+// Write the document as a .tex file, copying its local images next to it. Prompts for a
+// destination when no path is given; returns None if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_latex(
+  app: AppHandle,
+  document_path: Option<String>,
+  markdown_content: String,
+  output_path: Option<String>,
+  options: Option<LatexOptions>,
+) -> CommandResult<Option<ExportResult>> {
+  let document = document_path.map(PathBuf::from);
+  // Its images are copied next to the export
+  if let Some(document) = &document {
+    app.state::<AccessScopeState>().check(document)?;
+  }
+  let output_path = match output_path {
+    Some(path) => requested_output_path(&app.state::<AccessScopeState>(), path)?,
+    None => {
+      let file_name = document
+        .as_ref()
+        .and_then(|d| d.file_stem())
+        .map(|stem| format!("{}.tex", stem.to_string_lossy()))
+        .unwrap_or_else(|| "Untitled.tex".to_string());
+      let picked = app
+        .dialog()
+        .file()
+        .add_filter("LaTeX", &["tex"])
+        .set_file_name(file_name)
+        .blocking_save_file();
+      match picked.map(crate::dialog_result_to_path).transpose()? {
+        Some(path) => path,
+        None => return Ok(None),
+      }
+    }
+  };
+  export_latex_to(
+    &app.state::<AccessScopeState>().0.lock().unwrap(),
+    document.as_deref(),
+    &markdown_content,
+    &output_path,
+    &options.unwrap_or_default(),
+  )
+  .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  const FIXTURE: &str = r#"---
+title: Results & Discussion
+---
+# Results & Discussion
+
+We saw a 50% drop in cost_per_unit (see #42), ~3x faster and 2^10 *smaller*
+**runs** with `x_1 & y_2`, and a \backslash is kept.
+
+Energy is $E = mc^2$.
+
+$$\sum_{i=1}^{n} i = \frac{n(n+1)}{2}$$
+
+## Method
+
+```python
+def cost_per_unit(total, n):
+    return total / n  # 100% of {cases} & more_
+```
+
+    raw_indented & 50% {braces}
+
+| Name | Score |
+|:-----|------:|
+| a_b  | 10%   |
+
+- [x] done
+- [ ] open
+
+3. third
+4. fourth
+
+![Fig_1 & more](figure.png)
+
+Read [the docs](https://example.com/a_b#c%20d) and note.[^1]
+
+[^1]: A note with 100% _emphasis_.
+"#;
+
+  fn convert(markdown: &str) -> String {
+    markdown_to_latex(markdown, &HashMap::new(), false)
+  }
+
+  #[test]
+  fn test_escapes_prose_but_not_code_or_math() {
+    let latex = convert(FIXTURE);
+    assert!(latex.starts_with("\\section{Results \\& Discussion}\n"));
+    assert!(!latex.contains("title:"));
+    assert!(latex.contains("50\\% drop in cost\\_per\\_unit (see \\#42), \\textasciitilde{}3x"));
+    assert!(latex.contains("2\\textasciicircum{}10 \\emph{smaller}"));
+    assert!(latex.contains("\\textbf{runs} with \\texttt{x\\_1 \\& y\\_2}"));
+    assert!(latex.contains("a \\textbackslash{}backslash"));
+    // Math and code are passed through as written
+    assert!(latex.contains("$E = mc^2$"));
+    assert!(latex.contains("\\[\\sum_{i=1}^{n} i = \\frac{n(n+1)}{2}\\]"));
+    assert!(latex.contains(
+      "\\begin{lstlisting}[language=Python]\ndef cost_per_unit(total, n):\n    return total / n  # 100% of {cases} & more_\n\\end{lstlisting}"
+    ));
+    assert!(latex.contains("\\begin{verbatim}\nraw_indented & 50% {braces}\n\\end{verbatim}"));
+  }
+
+  #[test]
+  fn test_converts_structure() {
+    let latex = convert(FIXTURE);
+    assert!(latex.contains("\\subsection{Method}"));
+    assert!(latex.contains(
+      "\\begin{tabular}{lr}\n\\toprule\nName & Score \\\\\n\\midrule\na\\_b & 10\\% \\\\\n\\bottomrule\n\\end{tabular}"
+    ));
+    assert!(latex.contains("\\item[$\\boxtimes$] done\n\\item[$\\square$] open"));
+    assert!(latex.contains("\\begin{enumerate}\n\\setcounter{enumi}{2}\n\\item third"));
+    assert!(latex.contains(
+      "\\begin{figure}[htbp]\n\\centering\n\\includegraphics[width=\\linewidth]{figure.png}\n\\caption{Fig\\_1 \\& more}\n\\end{figure}"
+    ));
+    assert!(latex.contains("\\href{https://example.com/a_b\\#c\\%20d}{the docs}"));
+    assert!(latex.contains("and note.\\footnote{A note with 100\\% \\emph{emphasis}.}"));
+    assert!(!latex.contains(FOOTNOTE_OPEN));
+  }
+
+  #[test]
+  fn test_chapter_classes_start_with_chapters() {
+    let latex = markdown_to_latex("# One\n\n## Two\n", &HashMap::new(), true);
+    assert_eq!(latex, "\\chapter{One}\n\n\\section{Two}\n");
+    assert_eq!(convert("###### Six\n"), "\\subparagraph{Six}\n");
+  }
+
+  #[test]
+  fn test_exports_standalone_document_with_copied_images() {
+    let dir = TempDir::new().unwrap();
+    let document = dir.path().join("notes").join("paper.md");
+    fs::create_dir_all(dir.path().join("notes")).unwrap();
+    fs::write(dir.path().join("notes").join("figure.png"), b"png").unwrap();
+    fs::write(dir.path().join("secret.png"), b"key").unwrap();
+    let output = dir.path().join("out").join("paper.tex");
+    fs::create_dir_all(output.parent().unwrap()).unwrap();
+    let scope = AccessScope::with_folders(&[dir.path().join("notes"), dir.path().join("out")]);
+
+    let markdown = "![Plot](figure.png)\n\n![Gone](missing.png)\n\n![Key](../secret.png)\n";
+    let result = export_latex_to(
+      &scope,
+      Some(&document),
+      markdown,
+      &output,
+      &LatexOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(result.copied_assets.len(), 1);
+    assert!(dir.path().join("out/assets/figure.png").is_file());
+    assert!(!dir.path().join("out/assets/secret.png").exists());
+    assert_eq!(
+      result.warnings,
+      vec![
+        "Image missing.png was not found",
+        "../secret.png was left out: access to its folder was not approved"
+      ]
+    );
+
+    let tex = fs::read_to_string(&output).unwrap();
+    assert!(tex.starts_with("\\documentclass{article}\n\\usepackage[T1]{fontenc}\n"));
+    assert!(tex.contains("\\includegraphics[width=\\linewidth]{assets/figure.png}"));
+    assert!(tex.ends_with("\\end{document}\n"));
+
+    let options = LatexOptions {
+      standalone: false,
+      ..LatexOptions::default()
+    };
+    export_latex_to(&scope, None, "Hi", &output, &options).unwrap();
+    let tex = fs::read_to_string(&output).unwrap();
+    assert!(tex.starts_with("% Needs these packages"));
+    assert!(!tex.contains("\\begin{document}"));
+
+    let options = LatexOptions {
+      document_class: "article}\\evil".to_string(),
+      ..LatexOptions::default()
+    };
+    assert!(export_latex_to(&scope, None, "Hi", &output, &options).is_err());
+  }
+}
@@ -0,0 +1,694 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, WebviewWindowBuilder, Window, WindowEvent};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+
+use crate::error::CommandResult;
+use crate::{PendingFileState, DOCK_OPEN_FILE_EVENT};
+
+// Opens a new window in the running instance instead of reusing its window
+pub const NEW_WINDOW_FLAG: &str = "--new-window";
+
+// Starts with a blank document, without offering to recover unsaved drafts
+pub const NEW_DOCUMENT_FLAG: &str = "--new";
+
+// Starts without the saved settings, recent files and tab session, and without watching
+// workspaces, for when something in them keeps the app from starting
+pub const SAFE_MODE_FLAG: &str = "--safe-mode";
+
+// In place of a file: read a document from stdin (`curl ... | markdowner -`)
+pub const STDIN_ARG: &str = "-";
+
+pub const USAGE: &str = "\
+Usage: markdowner [OPTIONS] [FILE...]
+
+Open markdown files in Markdowner. Relative paths are resolved against the
+current directory; if Markdowner is already running, the files open there.
+Use `-` as FILE to open a document piped to standard input. A FILE ending in
+`:LINE`, `:LINE:COLUMN` or `#HEADING` opens there.
+
+Options:
+  --new         Start with a blank document
+  --new-window  Open in a new window of the running instance
+  --safe-mode   Start without saved settings, recent files and tabs (also:
+                hold Shift while launching, on macOS)
+  -h, --help    Print this help and exit
+";
+
+// Label of the window created from tauri.conf.json; extra windows get `main-2`, `main-3`...
+pub const MAIN_WINDOW_LABEL: &str = "main";
+
+// Where files opened from outside the app (file associations, the dock, a second launch)
+// go while it's running
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OpenFilesIn {
+  // The focused window, or the last one focused while the app is in the background
+  #[default]
+  CurrentWindow,
+  NewWindow,
+  // Ask in the current window whether to open there or in a new window
+  Ask,
+}
+
+// Files waiting for each window to pick them up with get_pending_file
+#[derive(Debug, Default)]
+pub struct PendingFiles {
+  // By window label
+  pub queues: HashMap<String, VecDeque<PendingOpen>>,
+  // The window focused last, which may have lost focus to the app a file was opened from
+  pub last_focused: Option<String>,
+}
+
+// Where open_files sends files
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OpenTarget {
+  Window(String),
+  NewWindow,
+  Ask(String),
+}
+
+// A file waiting to be opened, and where to put the cursor in it: `notes.md:120:5` gives a
+// 1-based line and column, `notes.md#setup` a heading (its text or anchor)
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "PendingOpenPayload")]
+pub struct PendingOpen {
+  pub path: String,
+  pub line: Option<u32>,
+  pub column: Option<u32>,
+  pub heading: Option<String>,
+}
+
+impl From<String> for PendingOpen {
+  fn from(path: String) -> Self {
+    PendingOpen {
+      path,
+      ..PendingOpen::default()
+    }
+  }
+}
+
+// What set_pending_file accepts: a PendingOpen, or just a path as it used to
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PendingOpenPayload {
+  Path(String),
+  Open {
+    path: String,
+    #[serde(default)]
+    line: Option<u32>,
+    #[serde(default)]
+    column: Option<u32>,
+    #[serde(default)]
+    heading: Option<String>,
+  },
+}
+
+impl From<PendingOpenPayload> for PendingOpen {
+  fn from(payload: PendingOpenPayload) -> Self {
+    match payload {
+      PendingOpenPayload::Path(path) => PendingOpen::from(path),
+      PendingOpenPayload::Open {
+        path,
+        line,
+        column,
+        heading,
+      } => PendingOpen {
+        path,
+        line,
+        column,
+        heading,
+      },
+    }
+  }
+}
+
+// A positive number written only with digits (`+1` and `0` aren't line numbers)
+fn position_number(text: &str) -> Option<u32> {
+  if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+    return None;
+  }
+  text.parse().ok().filter(|n| *n > 0)
+}
+
+// Split a trailing `#heading`, `:line` or `:line:column` off `path`. Only done when what's
+// left is an existing file and the whole isn't, so a file really named `a#b.md` or
+// `notes:2` opens as itself.
+pub fn split_location(path: &str) -> PendingOpen {
+  let is_file = |path: &str| Path::new(path).is_file();
+  let plain = PendingOpen::from(path.to_string());
+  if is_file(path) {
+    return plain;
+  }
+  if let Some((base, fragment)) = path.rsplit_once('#') {
+    if !fragment.is_empty() && is_file(base) {
+      return PendingOpen {
+        heading: Some(fragment.to_string()),
+        ..PendingOpen::from(base.to_string())
+      };
+    }
+  }
+  let Some((rest, last)) = path.rsplit_once(':') else {
+    return plain;
+  };
+  let Some(last) = position_number(last) else {
+    return plain;
+  };
+  if let Some((base, line)) = rest.rsplit_once(':') {
+    if let (Some(line), true) = (position_number(line), is_file(base)) {
+      return PendingOpen {
+        line: Some(line),
+        column: Some(last),
+        ..PendingOpen::from(base.to_string())
+      };
+    }
+  }
+  if is_file(rest) {
+    return PendingOpen {
+      line: Some(last),
+      ..PendingOpen::from(rest.to_string())
+    };
+  }
+  plain
+}
+
+// The file a file:// URL points at. A `#fragment` is a heading: a `#` in the file name
+// would have been percent-encoded.
+pub fn pending_open_from_url(url: &str) -> Option<PendingOpen> {
+  let (url, fragment) = match url.split_once('#') {
+    Some((url, fragment)) => (url, Some(fragment)),
+    None => (url, None),
+  };
+  let path = crate::file_url_to_path(url)?;
+  // Paths reach the frontend as JSON strings, which can't hold a name that isn't UTF-8
+  let Some(path) = path.to_str() else {
+    log::warn!(
+      "Can't open {}: the file name isn't valid UTF-8",
+      path.to_string_lossy()
+    );
+    return None;
+  };
+  let mut open = split_location(path);
+  if let Some(fragment) = fragment.filter(|fragment| !fragment.is_empty()) {
+    let heading = urlencoding::decode(fragment)
+      .map(|heading| heading.into_owned())
+      .unwrap_or_else(|_| fragment.to_string());
+    open.heading = Some(heading);
+  }
+  Some(open)
+}
+
+// What a launch asked for
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LaunchArgs {
+  // Absolute paths, not yet checked to exist
+  pub files: Vec<PendingOpen>,
+  pub new_window: bool,
+  pub new_document: bool,
+  pub help: bool,
+  // `-` was given: open what's piped to stdin as an unsaved document
+  pub stdin: bool,
+  pub safe_mode: bool,
+}
+
+// Options the frontend asks for once it has loaded
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct LaunchOptions {
+  pub new_document: bool,
+}
+
+pub struct LaunchOptionsState(pub LaunchOptions);
+
+// Parse a launch's argv (including the program name). Arguments starting with `-` are
+// flags; anything else is a file path or file:// URL, resolved against `cwd` (the working
+// directory of the process that was launched, which may not be ours).
+pub fn parse_launch_args(args: &[String], cwd: &Path) -> LaunchArgs {
+  let mut launch = LaunchArgs::default();
+  for arg in args.iter().skip(1) {
+    if arg == NEW_WINDOW_FLAG {
+      launch.new_window = true;
+    } else if arg == NEW_DOCUMENT_FLAG {
+      launch.new_document = true;
+    } else if arg == "--help" || arg == "-h" {
+      launch.help = true;
+    } else if arg == SAFE_MODE_FLAG {
+      launch.safe_mode = true;
+    } else if arg == STDIN_ARG {
+      launch.stdin = true;
+    } else if arg.starts_with('-') {
+      // Unknown flag, or one added by the OS (e.g. macOS `-psn_...`)
+      continue;
+    } else if let Some(path) = resolve_file_arg(arg, cwd) {
+      launch.files.push(path);
+    }
+  }
+  launch
+}
+
+// Only file:// URLs are percent-decoded: `100%25.md` given as a path is a file name
+fn resolve_file_arg(arg: &str, cwd: &Path) -> Option<PendingOpen> {
+  // Some launchers pass a path with spaces still wrapped in its quotes
+  let arg = arg
+    .strip_prefix('"')
+    .and_then(|arg| arg.strip_suffix('"'))
+    .unwrap_or(arg);
+  if arg.starts_with("file://") {
+    return pending_open_from_url(arg);
+  }
+  let path = PathBuf::from(arg);
+  let path = if path.is_absolute() {
+    path
+  } else {
+    cwd.join(path)
+  };
+  Some(split_location(&path.to_string_lossy()))
+}
+
+// The files that exist and are readable; the rest are logged and dropped
+pub fn openable_files(files: &[PendingOpen]) -> Vec<PendingOpen> {
+  files
+    .iter()
+    .filter(
+      |file| match crate::validate_file_path(Path::new(&file.path)) {
+        Ok(metadata) if metadata.is_file && metadata.is_readable => true,
+        Ok(_) => {
+          log::warn!(
+            "Ignoring launch argument, not a readable file: {}",
+            file.path
+          );
+          false
+        }
+        Err(e) => {
+          log::warn!("Ignoring launch argument {}: {}", file.path, e);
+          false
+        }
+      },
+    )
+    .cloned()
+    .collect()
+}
+
+// Queue files for the get_pending_file of window `label`. With `notify`, also tell the
+// window to open the first one, in case it has loaded already.
+pub fn queue_pending_files(app: &AppHandle, label: &str, files: &[PendingOpen], notify: bool) {
+  if let Some(pending_state) = app.try_state::<PendingFileState>() {
+    pending_state
+      .0
+      .lock()
+      .unwrap()
+      .queues
+      .entry(label.to_string())
+      .or_default()
+      .extend(files.iter().cloned());
+  }
+  if let (true, Some(first)) = (notify, files.first()) {
+    let _ = app.emit_to(label, DOCK_OPEN_FILE_EVENT, first.clone());
+  }
+}
+
+// The window files go to: the focused one, else the one focused last, else the main one.
+// `windows` are the open windows' labels and whether each has focus.
+fn open_target(
+  setting: OpenFilesIn,
+  windows: &[(String, bool)],
+  last_focused: Option<&str>,
+) -> OpenTarget {
+  let is_open = |label: &str| windows.iter().any(|(open, _)| open == label);
+  let current = windows
+    .iter()
+    .find(|(_, focused)| *focused)
+    .map(|(label, _)| label.as_str())
+    .or(last_focused.filter(|label| is_open(label)))
+    .or(Some(MAIN_WINDOW_LABEL).filter(|label| is_open(label)))
+    .or(windows.iter().map(|(label, _)| label.as_str()).min());
+  match (setting, current) {
+    (OpenFilesIn::NewWindow, _) | (_, None) => OpenTarget::NewWindow,
+    (OpenFilesIn::Ask, Some(label)) => OpenTarget::Ask(label.to_string()),
+    (OpenFilesIn::CurrentWindow, Some(label)) => OpenTarget::Window(label.to_string()),
+  }
+}
+
+// Open files from outside the app where the open_files_in setting says
+pub fn open_files(app: &AppHandle, files: &[PendingOpen]) {
+  if files.is_empty() {
+    return;
+  }
+  let documents: Vec<&Path> = files.iter().map(|open| Path::new(&open.path)).collect();
+  crate::access_scope::allow_documents(app, &documents);
+  let setting = app
+    .try_state::<crate::settings::SettingsState>()
+    .map(|settings| settings.0.lock().unwrap().open_files_in)
+    .unwrap_or_default();
+  let windows: Vec<(String, bool)> = app
+    .webview_windows()
+    .into_iter()
+    .map(|(label, window)| {
+      let focused = window.is_focused().unwrap_or(false);
+      (label, focused)
+    })
+    .collect();
+  let last_focused = app
+    .try_state::<PendingFileState>()
+    .and_then(|pending| pending.0.lock().unwrap().last_focused.clone());
+  match open_target(setting, &windows, last_focused.as_deref()) {
+    OpenTarget::Window(label) => {
+      queue_pending_files(app, &label, files, true);
+      crate::window_menu::focus_window(app, &label);
+    }
+    OpenTarget::NewWindow => {
+      if let Err(e) = open_new_window_with(app, files) {
+        log::error!("Failed to open a new window: {}", e);
+      }
+    }
+    OpenTarget::Ask(label) => ask_where_to_open(app, label, files.to_vec()),
+  }
+}
+
+// The dialog doesn't block, so the event loop that called open_files keeps running
+fn ask_where_to_open(app: &AppHandle, label: String, files: Vec<PendingOpen>) {
+  let Some(window) = app.get_webview_window(&label) else {
+    return;
+  };
+  crate::window_menu::focus_window(app, &label);
+  let name = Path::new(&files[0].path)
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_else(|| files[0].path.clone());
+  let app_handle = app.clone();
+  app
+    .dialog()
+    .message(format!("Open \"{}\" in this window or in a new one?", name))
+    .title("Open File")
+    .parent(&window)
+    .buttons(MessageDialogButtons::OkCancelCustom(
+      "This Window".to_string(),
+      "New Window".to_string(),
+    ))
+    .show(move |this_window| {
+      if this_window {
+        queue_pending_files(&app_handle, &label, &files, true);
+      } else if let Err(e) = open_new_window_with(&app_handle, &files) {
+        log::error!("Failed to open a new window: {}", e);
+      }
+    });
+}
+
+// Track the window focused last, and drop the files a closed window never picked up
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+  let Some(pending_state) = window.try_state::<PendingFileState>() else {
+    return;
+  };
+  let mut pending = pending_state.0.lock().unwrap();
+  match event {
+    WindowEvent::Focused(true) => pending.last_focused = Some(window.label().to_string()),
+    WindowEvent::Destroyed => {
+      pending.queues.remove(window.label());
+      if pending.last_focused.as_deref() == Some(window.label()) {
+        pending.last_focused = None;
+      }
+    }
+    _ => {}
+  }
+}
+
+// Called in the primary instance when the app is launched again (the single-instance
+// plugin makes the second process exit after handing over its argv)
+pub fn handle_secondary_launch(app: &AppHandle, argv: Vec<String>, cwd: String) {
+  log::debug!("Secondary launch with args: {:?}", argv);
+  let launch = parse_launch_args(&argv, Path::new(&cwd));
+  let files = openable_files(&launch.files);
+
+  if launch.new_window {
+    let documents: Vec<&Path> = files.iter().map(|open| Path::new(&open.path)).collect();
+    crate::access_scope::allow_documents(app, &documents);
+    if let Err(e) = open_new_window_with(app, &files) {
+      log::error!("Failed to open a new window: {}", e);
+    }
+    return;
+  }
+  if files.is_empty() {
+    focus_main_window(app);
+    return;
+  }
+  open_files(app, &files);
+}
+
+// Launch arguments of this process: argv as given, relative paths resolved against our
+// working directory
+pub fn current_launch_args() -> LaunchArgs {
+  let args: Vec<String> = std::env::args_os()
+    .map(|arg| arg.to_string_lossy().to_string())
+    .collect();
+  let cwd = std::env::current_dir().unwrap_or_default();
+  parse_launch_args(&args, &cwd)
+}
+
+#[tauri::command]
+pub async fn get_launch_options(
+  state: tauri::State<'_, LaunchOptionsState>,
+) -> CommandResult<LaunchOptions> {
+  Ok(state.0.clone())
+}
+
+pub fn open_new_window(app: &AppHandle) -> tauri::Result<()> {
+  open_new_window_with(app, &[])
+}
+
+// Open a window that picks `files` up through get_pending_file once it has loaded
+pub fn open_new_window_with(app: &AppHandle, files: &[PendingOpen]) -> tauri::Result<()> {
+  let mut config = main_window_config(app);
+  // With every window closed (the app kept running in the tray), the main window comes back
+  if !app.webview_windows().is_empty() {
+    let mut n = 2;
+    while app
+      .get_webview_window(&format!("{}-{}", MAIN_WINDOW_LABEL, n))
+      .is_some()
+    {
+      n += 1;
+    }
+    config.label = format!("{}-{}", MAIN_WINDOW_LABEL, n);
+  }
+  queue_pending_files(app, &config.label, files, false);
+  WebviewWindowBuilder::from_config(app, &config)?.build()?;
+  if let Err(e) = crate::window_menu::update_menu(app, None) {
+    log::error!("Failed to update the Window menu: {}", e);
+  }
+  Ok(())
+}
+
+fn main_window_config(app: &AppHandle) -> tauri::utils::config::WindowConfig {
+  app
+    .config()
+    .app
+    .windows
+    .first()
+    .cloned()
+    .unwrap_or_default()
+}
+
+// Focus the main window, creating it again if every window was closed while the app kept
+// running (in the tray)
+pub fn show_main_window(app: &AppHandle) {
+  if app.webview_windows().is_empty() {
+    let config = main_window_config(app);
+    if let Err(e) = WebviewWindowBuilder::from_config(app, &config).and_then(|w| w.build()) {
+      log::error!("Failed to open the main window: {}", e);
+    }
+    return;
+  }
+  focus_main_window(app);
+}
+
+pub fn focus_main_window(app: &AppHandle) {
+  let window = app
+    .get_webview_window(MAIN_WINDOW_LABEL)
+    .or_else(|| app.webview_windows().into_values().next());
+  if let Some(window) = window {
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_open_target_prefers_the_current_window() {
+    let windows = |focused: Option<&str>| -> Vec<(String, bool)> {
+      ["main", "main-2", "main-3"]
+        .iter()
+        .map(|label| (label.to_string(), Some(*label) == focused))
+        .collect()
+    };
+    let current = OpenFilesIn::CurrentWindow;
+    assert_eq!(
+      open_target(current, &windows(Some("main-2")), Some("main-3")),
+      OpenTarget::Window("main-2".to_string())
+    );
+    // In the background (opened from Finder), the window focused last
+    assert_eq!(
+      open_target(current, &windows(None), Some("main-3")),
+      OpenTarget::Window("main-3".to_string())
+    );
+    // ...unless it has closed since
+    assert_eq!(
+      open_target(current, &windows(None), Some("main-4")),
+      OpenTarget::Window("main".to_string())
+    );
+    let others = vec![("main-3".to_string(), false), ("main-2".to_string(), false)];
+    assert_eq!(
+      open_target(current, &others, None),
+      OpenTarget::Window("main-2".to_string())
+    );
+    assert_eq!(
+      open_target(OpenFilesIn::Ask, &windows(Some("main-3")), None),
+      OpenTarget::Ask("main-3".to_string())
+    );
+    assert_eq!(
+      open_target(OpenFilesIn::NewWindow, &windows(Some("main")), None),
+      OpenTarget::NewWindow
+    );
+    // Every window closed while running in the tray
+    assert_eq!(
+      open_target(current, &[], Some("main")),
+      OpenTarget::NewWindow
+    );
+  }
+
+  fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+  }
+
+  fn paths(files: &[PendingOpen]) -> Vec<String> {
+    files.iter().map(|file| file.path.clone()).collect()
+  }
+
+  #[test]
+  fn test_parse_launch_args() {
+    let cwd = Path::new("/home/user/notes");
+    let launch = parse_launch_args(
+      &args(&[
+        "markdowner",
+        "todo.md",
+        "--new-window",
+        "-psn_0_12345",
+        "/tmp/My Notes.md",
+        "file:///tmp/a%20b.md",
+      ]),
+      cwd,
+    );
+    assert!(launch.new_window);
+    assert_eq!(
+      paths(&launch.files),
+      vec![
+        cwd.join("todo.md").to_string_lossy().to_string(),
+        "/tmp/My Notes.md".to_string(),
+        "/tmp/a b.md".to_string(),
+      ]
+    );
+    assert_eq!(
+      parse_launch_args(&args(&["markdowner"]), cwd),
+      LaunchArgs::default()
+    );
+  }
+
+  #[test]
+  fn test_parse_cli_flags_and_quoted_paths() {
+    let cwd = Path::new("/home/user");
+    let launch = parse_launch_args(
+      &args(&[
+        "markdowner",
+        "--new",
+        "\"My Notes/a b.md\"",
+        "100%25 done.md",
+      ]),
+      cwd,
+    );
+    assert!(launch.new_document);
+    assert!(!launch.help);
+    assert_eq!(
+      paths(&launch.files),
+      vec![
+        cwd.join("My Notes/a b.md").to_string_lossy().to_string(),
+        cwd.join("100%25 done.md").to_string_lossy().to_string(),
+      ]
+    );
+    assert!(parse_launch_args(&args(&["markdowner", "-h"]), cwd).help);
+    assert!(parse_launch_args(&args(&["markdowner", "--safe-mode"]), cwd).safe_mode);
+    let launch = parse_launch_args(&args(&["markdowner", "-"]), cwd);
+    assert!(launch.stdin);
+    assert!(launch.files.is_empty());
+  }
+
+  #[test]
+  fn test_openable_files_drops_missing_and_directories() {
+    let dir = TempDir::new().unwrap();
+    let file = dir.path().join("notes.md");
+    fs::write(&file, "# Notes").unwrap();
+    let files: Vec<PendingOpen> = [
+      file.clone(),
+      dir.path().join("missing.md"),
+      dir.path().to_path_buf(),
+    ]
+    .iter()
+    .map(|path| PendingOpen::from(path.to_string_lossy().to_string()))
+    .collect();
+
+    assert_eq!(
+      paths(&openable_files(&files)),
+      vec![file.to_string_lossy().to_string()]
+    );
+  }
+
+  #[test]
+  fn test_split_location_prefers_existing_files() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes.md");
+    let hashed = dir.path().join("a#b.md");
+    fs::write(&notes, "# Notes").unwrap();
+    fs::write(&hashed, "# Hash").unwrap();
+    let at = |suffix: &str| split_location(&format!("{}{}", notes.display(), suffix));
+    let notes_path = notes.to_string_lossy().to_string();
+
+    assert_eq!(at(""), PendingOpen::from(notes_path.clone()));
+    assert_eq!(at(":120").line, Some(120));
+    assert_eq!(at(":120").path, notes_path);
+    let open = at(":12:5");
+    assert_eq!((open.line, open.column), (Some(12), Some(5)));
+    assert_eq!(at("#setup").heading.as_deref(), Some("setup"));
+    assert_eq!(at("#setup").path, notes_path);
+    // Not locations: left for openable_files to report
+    assert_eq!(at(":0").path, format!("{}:0", notes_path));
+    assert_eq!(at(":+3").path, format!("{}:+3", notes_path));
+    // A file whose name has a `#` in it is opened as it is
+    let hashed_path = hashed.to_string_lossy().to_string();
+    assert_eq!(
+      split_location(&hashed_path),
+      PendingOpen::from(hashed_path.clone())
+    );
+  }
+
+  #[test]
+  fn test_locations_in_file_urls_and_payloads() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("my notes.md");
+    fs::write(&notes, "# Notes").unwrap();
+    let url = format!(
+      "file://{}#Getting%20started",
+      urlencoding::encode(&notes.to_string_lossy()).replace("%2F", "/")
+    );
+    let open = pending_open_from_url(&url).unwrap();
+    assert_eq!(open.path, notes.to_string_lossy());
+    assert_eq!(open.heading.as_deref(), Some("Getting started"));
+
+    let open: PendingOpen = serde_json::from_str("\"/notes/a.md\"").unwrap();
+    assert_eq!(open, PendingOpen::from("/notes/a.md".to_string()));
+    let open: PendingOpen = serde_json::from_str("{\"path\": \"/a.md\", \"line\": 3}").unwrap();
+    assert_eq!((open.path.as_str(), open.line), ("/a.md", Some(3)));
+  }
+}
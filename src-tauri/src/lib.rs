@@ -1,33 +1,204 @@
-use std::path::{Path, PathBuf};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::sync::mpsc;
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tauri::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
 use tauri::{AppHandle, Emitter, Manager};
 use tauri_plugin_deep_link::DeepLinkExt;
-use tauri_plugin_dialog::DialogExt;
-use tauri_plugin_store::StoreExt;
-use urlencoding::decode;
+use tauri_plugin_dialog::{DialogExt, FilePath};
+
+mod access_scope;
+mod app_data;
+mod app_store;
+mod assets;
+mod atomic_write;
+mod batch_rename;
+mod binary;
+mod bookmarks;
+mod bundle;
+mod case;
+mod clipboard;
+mod close_guard;
+mod diff;
+mod document_reference;
+mod document_window;
+mod drafts;
+mod duplicates;
+mod dynamic_menu;
+mod email;
+mod error;
+mod export;
+mod export_defaults;
+mod file_finder;
+mod filename;
+mod fonts;
+mod frequent;
+mod gist;
+mod headings;
+mod help;
+mod http;
+mod import;
+mod inbox;
+mod includes;
+mod latex;
+mod launch;
+mod link_check;
+mod link_title;
+mod logging;
+#[cfg(target_os = "macos")]
+mod macos;
+mod menu_toggles;
+mod open_with;
+mod plain_text;
+mod print_layout;
+mod recent_workspaces;
+mod recently_closed;
+mod references;
+mod relative_links;
+mod relocate;
+mod safe_mode;
+mod secrets;
+mod sections;
+#[cfg(target_os = "macos")]
+mod services;
+mod session;
+mod settings;
+mod shortcuts;
+mod slides;
+mod speech;
+mod split;
+mod staging;
+mod stdin;
+mod styles;
+mod tags;
+mod task_registry;
+mod tasks;
+mod terminal;
+mod transform;
+#[cfg(desktop)]
+mod tray;
+mod typography;
+mod updates;
+mod urls;
+mod view_state;
+mod wiki;
+mod window_menu;
+mod workspace;
+mod workspace_import;
+mod workspace_stats;
+mod write_queue;
+mod writing_history;
+
+use app_store::{StoreRecovery, StoreRecoveryState, STORE_RECOVERED_EVENT};
+use atomic_write::AtomicWriteError;
+use error::{CommandError, CommandResult};
+use safe_mode::Startup;
+use settings::SettingsState;
+use write_queue::{WriteCoordinator, WriteResult};
+
+// Decode `%XX` escapes into the bytes they stand for. A `%` not followed by two hex digits
+// is kept as it is, and `+` stays a plus: it only means a space in form data, not in paths.
+fn percent_decode(text: &str) -> Vec<u8> {
+  let bytes = text.as_bytes();
+  let hex = |i: usize| bytes.get(i).and_then(|b| (*b as char).to_digit(16));
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    match (bytes[i], hex(i + 1), hex(i + 2)) {
+      (b'%', Some(high), Some(low)) => {
+        decoded.push((high * 16 + low) as u8);
+        i += 3;
+      }
+      (byte, _, _) => {
+        decoded.push(byte);
+        i += 1;
+      }
+    }
+  }
+  decoded
+}
 
 /// Convert a file:// URL to a local file path
 /// Handles percent-encoding and platform-specific path formats
-fn file_url_to_path(url: &str) -> Option<String> {
-  if !url.starts_with("file://") {
-    return None;
+fn file_url_to_path(url: &str) -> Option<PathBuf> {
+  let path_part = url.strip_prefix("file://")?;
+  // `file://localhost/...` is the same file as `file:///...`
+  let path_part = path_part
+    .strip_prefix("localhost")
+    .filter(|rest| rest.starts_with('/'))
+    .unwrap_or(path_part);
+  let bytes = percent_decode(path_part);
+
+  // A Unix path is bytes, so names that aren't UTF-8 (Latin-1 ones from older Linux file
+  // managers) still lead to the file
+  #[cfg(unix)]
+  {
+    use std::os::unix::ffi::OsStringExt;
+    Some(PathBuf::from(std::ffi::OsString::from_vec(bytes)))
   }
+  // Windows paths are UTF-16, which only UTF-8 escapes map to; a drive path comes as
+  // `/C:/Users/...`
+  #[cfg(not(unix))]
+  {
+    let path = String::from_utf8_lossy(&bytes);
+    let path = match path.strip_prefix('/') {
+      Some(rest) if rest.as_bytes().get(1) == Some(&b':') => rest,
+      _ => &path,
+    };
+    Some(PathBuf::from(path))
+  }
+}
 
-  // Remove the file:// prefix
-  let path_part = &url[7..];
-
-  // Decode percent-encoded characters (e.g., %20 -> space)
-  match decode(path_part) {
-    Ok(decoded) => {
-      let path_str = decoded.into_owned();
-      // On macOS, file URLs often have an extra leading slash that needs to be removed
-      // e.g., file:///Users/name/file.md -> /Users/name/file.md
-      Some(path_str)
+// Percent-encode everything in a URL path but unreserved characters, `/` and `:`. Parens
+// are encoded too, so the URL can go in a markdown link as it is.
+pub(crate) fn percent_encode_path(bytes: &[u8]) -> String {
+  let mut encoded = String::with_capacity(bytes.len());
+  for &byte in bytes {
+    match byte {
+      b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+        encoded.push(byte as char)
+      }
+      _ => encoded.push_str(&format!("%{:02X}", byte)),
     }
-    Err(e) => {
-      eprintln!("Failed to decode URL: {}", e);
-      None
+  }
+  encoded
+}
+
+// The file:// URL of an absolute path; file_url_to_path turns it back into the path
+pub(crate) fn path_to_file_url(path: &Path) -> String {
+  #[cfg(unix)]
+  {
+    use std::os::unix::ffi::OsStrExt;
+    format!(
+      "file://{}",
+      percent_encode_path(path.as_os_str().as_bytes())
+    )
+  }
+  // `C:\Users\...` -> `file:///C:/Users/...`
+  #[cfg(not(unix))]
+  {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let path = if path.starts_with('/') {
+      path
+    } else {
+      format!("/{}", path)
+    };
+    format!("file://{}", percent_encode_path(path.as_bytes()))
+  }
+}
+
+// The local path of a file picked in a dialog. Some dialogs hand back URLs instead of paths
+// (content URIs on mobile, some portals on Linux): `file://` ones are converted, anything
+// else fails with UnsupportedLocation since it can't be read as a file.
+pub(crate) fn dialog_result_to_path(picked: FilePath) -> CommandResult<PathBuf> {
+  match picked {
+    FilePath::Path(path) => Ok(path),
+    FilePath::Url(url) => {
+      file_url_to_path(url.as_str()).ok_or_else(|| CommandError::UnsupportedLocation {
+        uri: url.to_string(),
+      })
     }
   }
 }
@@ -35,60 +206,312 @@ fn file_url_to_path(url: &str) -> Option<String> {
 // Maximum number of recent files to keep
 const MAX_RECENT_FILES: usize = 10;
 
+// Time budget for checking whether recent files still exist. Paths on an unreachable
+// network share can block stat() for a long time, so anything slower is reported missing.
+const RECENT_EXISTS_TIMEOUT: Duration = Duration::from_millis(500);
+
 // Store key for recent files
 const RECENT_FILES_KEY: &str = "recent_files";
-const STORE_FILE: &str = "app_data.bin";
+// Store key for the directory the last Save As dialog saved into
+const LAST_SAVE_DIRECTORY_KEY: &str = "last_save_directory";
 
 // State to store recent files (in-memory cache)
 pub struct RecentFilesState(pub Mutex<Vec<String>>);
 
-// State to store files opened via dock drag-drop (when app is not running)
-pub struct PendingFileState(pub Mutex<Option<String>>);
+// Set once the recent files have been read from the store. That happens after setup, so
+// it doesn't hold up the window; commands that need the saved list wait for it.
+pub struct RecentFilesLoaded(tokio::sync::watch::Sender<bool>);
+
+// Wait until the saved recent files are in RecentFilesState
+pub(crate) async fn recent_files_loaded(app: &AppHandle) {
+  let Some(loaded) = app.try_state::<RecentFilesLoaded>() else {
+    return;
+  };
+  let mut receiver = loaded.0.subscribe();
+  let _ = receiver.wait_for(|loaded| *loaded).await;
+}
+
+// Recent file entry as returned to the frontend. `path` is the canonical form used for
+// opening the file; `display_path` abbreviates the home directory as `~`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecentFileEntry {
+  pub path: String,
+  pub display_path: String,
+  pub exists: bool,
+}
+
+// Files waiting to be opened by each window's frontend: opened via dock drag-drop or file
+// association before the webview was ready, or forwarded by a second launch
+pub struct PendingFileState(pub Mutex<launch::PendingFiles>);
+
+// Event emitted with the recent file paths whenever the list changes
+const RECENTS_CHANGED_EVENT: &str = "recents-changed";
 
 // Event name for file open from dock
 const DOCK_OPEN_FILE_EVENT: &str = "dock-open-file";
 
+// Event emitted with a FileAccess when a file is opened or made writable, so the title bar
+// can show a lock on files that can't be saved
+const FILE_ACCESS_EVENT: &str = "file-access";
+
 // Event names for menu actions
 const MENU_NEW_FILE_EVENT: &str = "menu-new-file";
+const MENU_NEW_FROM_CLIPBOARD_EVENT: &str = "menu-new-from-clipboard";
 const MENU_OPEN_FILE_EVENT: &str = "menu-open-file";
 const MENU_SAVE_FILE_EVENT: &str = "menu-save-file";
 const MENU_SAVE_AS_FILE_EVENT: &str = "menu-save-as-file";
-
-// Create the application menu
-fn create_app_menu(app_handle: &AppHandle) -> Result<Menu<tauri::Wry>, tauri::Error> {
+const MENU_EXPORT_HTML_EVENT: &str = "menu-export-html";
+const MENU_EXPORT_SLIDES_EVENT: &str = "menu-export-slides";
+const MENU_EXPORT_PRINT_HTML_EVENT: &str = "menu-export-print-html";
+const MENU_EXPORT_LATEX_EVENT: &str = "menu-export-latex";
+const MENU_EXPORT_PLAIN_TEXT_EVENT: &str = "menu-export-plain-text";
+const MENU_COPY_PLAIN_TEXT_EVENT: &str = "menu-copy-plain-text";
+const MENU_COPY_FOR_EMAIL_EVENT: &str = "menu-copy-for-email";
+// Payload is the document_reference::ReferenceKind to copy (path, filename, file-url or
+// markdown-link)
+const MENU_COPY_DOCUMENT_REFERENCE_EVENT: &str = "menu-copy-document-reference";
+const MENU_EXPORT_APP_DATA_EVENT: &str = "menu-export-app-data";
+const MENU_IMPORT_APP_DATA_EVENT: &str = "menu-import-app-data";
+const MENU_SHOW_SHORTCUTS_EVENT: &str = "menu-show-shortcuts";
+const MENU_SHOW_LOGS_EVENT: &str = "menu-show-logs";
+const MENU_REOPEN_CLOSED_EVENT: &str = "menu-reopen-closed";
+const MENU_PUBLISH_GIST_EVENT: &str = "menu-publish-gist";
+const MENU_SMARTEN_TYPOGRAPHY_EVENT: &str = "menu-smarten-typography";
+const MENU_STRAIGHTEN_TYPOGRAPHY_EVENT: &str = "menu-straighten-typography";
+const MENU_TIDY_REFERENCES_EVENT: &str = "menu-tidy-references";
+// Payload is the case operation (upper, lower, title or sentence)
+const MENU_TRANSFORM_CASE_EVENT: &str = "menu-transform-case";
+// Payload is how many levels to move the headings down (negative for up)
+const MENU_SHIFT_HEADINGS_EVENT: &str = "menu-shift-headings";
+// Payload is the application id to open the document with, or null for the default one
+const MENU_OPEN_WITH_EVENT: &str = "menu-open-with";
+const MENU_OPEN_TERMINAL_EVENT: &str = "menu-open-terminal";
+const MENU_START_SPEAKING_EVENT: &str = "menu-start-speaking";
+const MENU_IMPORT_DOCUMENT_EVENT: &str = "menu-import-document";
+// Payload is the heading level (1-3) to split the document at
+const MENU_SPLIT_BY_HEADING_EVENT: &str = "menu-split-by-heading";
+// Payload is the transform::LineOperation to apply to the selection
+const MENU_TRANSFORM_LINES_EVENT: &str = "menu-transform-lines";
+
+// Create the application menu, with the user's shortcuts
+fn create_app_menu(
+  app_handle: &AppHandle,
+  shortcuts: &BTreeMap<String, String>,
+) -> Result<Menu<tauri::Wry>, tauri::Error> {
   let menu = Menu::new(app_handle)?;
+  let accelerator = |id: &str| shortcuts::accelerator(shortcuts, id);
 
   // App menu (required on macOS as the first menu)
   let about_item = PredefinedMenuItem::about(app_handle, Some("About Markdowner"), None)?;
+  let check_updates_item = MenuItem::with_id(
+    app_handle,
+    "check_for_updates",
+    "Check for Updates...",
+    true,
+    accelerator("check_for_updates"),
+  )?;
   let separator_app = PredefinedMenuItem::separator(app_handle)?;
-  let quit_item = PredefinedMenuItem::quit(app_handle, Some("Quit Markdowner"))?;
+  let export_data_item = MenuItem::with_id(
+    app_handle,
+    "export_app_data",
+    "Export App Data...",
+    true,
+    accelerator("export_app_data"),
+  )?;
+  let import_data_item = MenuItem::with_id(
+    app_handle,
+    "import_app_data",
+    "Import App Data...",
+    true,
+    accelerator("import_app_data"),
+  )?;
+  let separator_app2 = PredefinedMenuItem::separator(app_handle)?;
+  // Not the predefined Quit, which exits without asking about unsaved changes
+  let quit_item = MenuItem::with_id(
+    app_handle,
+    "quit",
+    "Quit Markdowner",
+    true,
+    Some("CmdOrCtrl+Q"),
+  )?;
 
   let app_submenu = Submenu::with_items(
     app_handle,
     "Markdowner",
     true,
-    &[&about_item, &separator_app, &quit_item],
+    &[
+      &about_item,
+      &check_updates_item,
+      &separator_app,
+      &export_data_item,
+      &import_data_item,
+      &separator_app2,
+      &quit_item,
+    ],
   )?;
 
   // File menu items
-  let new_item = MenuItem::with_id(app_handle, "new_file", "New", true, Some("CmdOrCtrl+N"))?;
+  let new_item = MenuItem::with_id(app_handle, "new_file", "New", true, accelerator("new_file"))?;
+  let new_from_clipboard_item = MenuItem::with_id(
+    app_handle,
+    "new_from_clipboard",
+    "New from Clipboard",
+    true,
+    accelerator("new_from_clipboard"),
+  )?;
   let open_item = MenuItem::with_id(
     app_handle,
     "open_file",
     "Open...",
     true,
-    Some("CmdOrCtrl+O"),
+    accelerator("open_file"),
+  )?;
+  let reopen_closed_item = MenuItem::with_id(
+    app_handle,
+    "reopen_closed",
+    "Reopen Closed",
+    true,
+    accelerator("reopen_closed"),
+  )?;
+  let open_inbox_item = MenuItem::with_id(
+    app_handle,
+    "open_inbox",
+    "Open Inbox",
+    true,
+    accelerator("open_inbox"),
+  )?;
+  // Filled in from the recent workspaces by recent_workspaces::update_menu
+  let recent_workspaces_submenu = Submenu::with_id(
+    app_handle,
+    recent_workspaces::MENU_ID,
+    "Open Recent Workspace",
+    false,
+  )?;
+  let import_document_item = MenuItem::with_id(
+    app_handle,
+    "import_document",
+    "Import Document...",
+    true,
+    accelerator("import_document"),
+  )?;
+  // Filled in for the open document by open_with::update_menu
+  let open_with_submenu = Submenu::with_id(app_handle, open_with::MENU_ID, "Open With", false)?;
+  let copy_path_submenu = Submenu::new(app_handle, "Copy Path", true)?;
+  for (id, label) in document_reference::ITEMS {
+    copy_path_submenu.append(&MenuItem::with_id(
+      app_handle,
+      *id,
+      *label,
+      true,
+      accelerator(id),
+    )?)?;
+  }
+  let open_terminal_item = MenuItem::with_id(
+    app_handle,
+    "open_terminal",
+    "Open in Terminal",
+    true,
+    accelerator("open_terminal"),
+  )?;
+  let save_item = MenuItem::with_id(
+    app_handle,
+    "save_file",
+    "Save",
+    true,
+    accelerator("save_file"),
   )?;
-  let save_item = MenuItem::with_id(app_handle, "save_file", "Save", true, Some("CmdOrCtrl+S"))?;
   let save_as_item = MenuItem::with_id(
     app_handle,
     "save_as_file",
     "Save As...",
     true,
-    Some("CmdOrCtrl+Shift+S"),
+    accelerator("save_as_file"),
+  )?;
+  let autosave_item = menu_toggles::menu_item(app_handle, &menu_toggles::AUTOSAVE)?;
+  let export_html_item = MenuItem::with_id(
+    app_handle,
+    "export_html",
+    "HTML...",
+    true,
+    accelerator("export_html"),
+  )?;
+  let export_print_html_item = MenuItem::with_id(
+    app_handle,
+    "export_print_html",
+    "Print Layout as HTML...",
+    true,
+    accelerator("export_print_html"),
+  )?;
+  let export_slides_item = MenuItem::with_id(
+    app_handle,
+    "export_slides",
+    "Slides (reveal.js)...",
+    true,
+    accelerator("export_slides"),
+  )?;
+  let export_latex_item = MenuItem::with_id(
+    app_handle,
+    "export_latex",
+    "LaTeX...",
+    true,
+    accelerator("export_latex"),
+  )?;
+  let export_plain_text_item = MenuItem::with_id(
+    app_handle,
+    "export_plain_text",
+    "Plain Text...",
+    true,
+    accelerator("export_plain_text"),
+  )?;
+  let export_submenu = Submenu::with_items(
+    app_handle,
+    "Export",
+    true,
+    &[
+      &export_html_item,
+      &export_print_html_item,
+      &export_slides_item,
+      &export_latex_item,
+      &export_plain_text_item,
+    ],
+  )?;
+  let split_h1_item = MenuItem::with_id(
+    app_handle,
+    "split_h1",
+    "Heading 1",
+    true,
+    accelerator("split_h1"),
+  )?;
+  let split_h2_item = MenuItem::with_id(
+    app_handle,
+    "split_h2",
+    "Heading 2",
+    true,
+    accelerator("split_h2"),
+  )?;
+  let split_h3_item = MenuItem::with_id(
+    app_handle,
+    "split_h3",
+    "Heading 3",
+    true,
+    accelerator("split_h3"),
+  )?;
+  let split_submenu = Submenu::with_items(
+    app_handle,
+    "Split by Heading",
+    true,
+    &[&split_h1_item, &split_h2_item, &split_h3_item],
+  )?;
+  let publish_gist_item = MenuItem::with_id(
+    app_handle,
+    "publish_gist",
+    "Publish as Gist",
+    true,
+    accelerator("publish_gist"),
   )?;
   let separator1 = PredefinedMenuItem::separator(app_handle)?;
   let separator2 = PredefinedMenuItem::separator(app_handle)?;
+  let separator_export = PredefinedMenuItem::separator(app_handle)?;
   let close_item = PredefinedMenuItem::close_window(app_handle, Some("Close Window"))?;
 
   let file_submenu = Submenu::with_items(
@@ -97,10 +520,23 @@ fn create_app_menu(app_handle: &AppHandle) -> Result<Menu<tauri::Wry>, tauri::Er
     true,
     &[
       &new_item,
+      &new_from_clipboard_item,
       &open_item,
+      &reopen_closed_item,
+      &open_inbox_item,
+      &recent_workspaces_submenu,
+      &import_document_item,
+      &open_with_submenu,
+      &open_terminal_item,
+      &copy_path_submenu,
       &separator1,
       &save_item,
       &save_as_item,
+      &autosave_item,
+      &separator_export,
+      &export_submenu,
+      &split_submenu,
+      &publish_gist_item,
       &separator2,
       &close_item,
     ],
@@ -112,8 +548,83 @@ fn create_app_menu(app_handle: &AppHandle) -> Result<Menu<tauri::Wry>, tauri::Er
   let separator3 = PredefinedMenuItem::separator(app_handle)?;
   let cut_item = PredefinedMenuItem::cut(app_handle, None)?;
   let copy_item = PredefinedMenuItem::copy(app_handle, None)?;
+  let copy_plain_text_item = MenuItem::with_id(
+    app_handle,
+    "copy_plain_text",
+    "Copy as Plain Text",
+    true,
+    accelerator("copy_plain_text"),
+  )?;
+  let copy_for_email_item = MenuItem::with_id(
+    app_handle,
+    "copy_for_email",
+    "Copy for Email",
+    true,
+    accelerator("copy_for_email"),
+  )?;
   let paste_item = PredefinedMenuItem::paste(app_handle, None)?;
   let select_all_item = PredefinedMenuItem::select_all(app_handle, None)?;
+  let separator_lines = PredefinedMenuItem::separator(app_handle)?;
+  let sort_ascending_item = MenuItem::with_id(
+    app_handle,
+    "lines_sort_ascending",
+    "Sort Ascending",
+    true,
+    accelerator("lines_sort_ascending"),
+  )?;
+  let sort_descending_item = MenuItem::with_id(
+    app_handle,
+    "lines_sort_descending",
+    "Sort Descending",
+    true,
+    accelerator("lines_sort_descending"),
+  )?;
+  let dedupe_item = MenuItem::with_id(
+    app_handle,
+    "lines_dedupe",
+    "Remove Duplicate Lines",
+    true,
+    accelerator("lines_dedupe"),
+  )?;
+  let reverse_item = MenuItem::with_id(
+    app_handle,
+    "lines_reverse",
+    "Reverse Lines",
+    true,
+    accelerator("lines_reverse"),
+  )?;
+  let lines_submenu = Submenu::with_items(
+    app_handle,
+    "Lines",
+    true,
+    &[
+      &sort_ascending_item,
+      &sort_descending_item,
+      &dedupe_item,
+      &reverse_item,
+    ],
+  )?;
+
+  let start_speaking_item = MenuItem::with_id(
+    app_handle,
+    "start_speaking",
+    "Start Speaking",
+    true,
+    accelerator("start_speaking"),
+  )?;
+  let stop_speaking_item = MenuItem::with_id(
+    app_handle,
+    "stop_speaking",
+    "Stop Speaking",
+    true,
+    accelerator("stop_speaking"),
+  )?;
+  let speech_submenu = Submenu::with_items(
+    app_handle,
+    "Speech",
+    true,
+    &[&start_speaking_item, &stop_speaking_item],
+  )?;
 
   let edit_submenu = Submenu::with_items(
     app_handle,
@@ -125,26 +636,162 @@ fn create_app_menu(app_handle: &AppHandle) -> Result<Menu<tauri::Wry>, tauri::Er
       &separator3,
       &cut_item,
       &copy_item,
+      &copy_plain_text_item,
+      &copy_for_email_item,
       &paste_item,
       &select_all_item,
+      &separator_lines,
+      &lines_submenu,
+      &speech_submenu,
     ],
   )?;
 
-  // Window menu
-  let minimize_item = PredefinedMenuItem::minimize(app_handle, Some("Minimize"))?;
-  let close_item_win = PredefinedMenuItem::close_window(app_handle, Some("Close Window"))?;
+  // Format menu
+  let smarten_item = MenuItem::with_id(
+    app_handle,
+    "smarten_typography",
+    "Smart Punctuation",
+    true,
+    accelerator("smarten_typography"),
+  )?;
+  let straighten_item = MenuItem::with_id(
+    app_handle,
+    "straighten_typography",
+    "Straight Punctuation",
+    true,
+    accelerator("straighten_typography"),
+  )?;
+  let tidy_references_item = MenuItem::with_id(
+    app_handle,
+    "tidy_references",
+    "Tidy Footnotes and Links",
+    true,
+    accelerator("tidy_references"),
+  )?;
 
-  let window_submenu = Submenu::with_items(
+  let upper_case_item = MenuItem::with_id(
     app_handle,
-    "Window",
+    "case_upper",
+    "UPPERCASE",
     true,
-    &[&minimize_item, &close_item_win],
+    accelerator("case_upper"),
+  )?;
+  let lower_case_item = MenuItem::with_id(
+    app_handle,
+    "case_lower",
+    "lowercase",
+    true,
+    accelerator("case_lower"),
+  )?;
+  let title_case_item = MenuItem::with_id(
+    app_handle,
+    "case_title",
+    "Title Case",
+    true,
+    accelerator("case_title"),
+  )?;
+  let sentence_case_item = MenuItem::with_id(
+    app_handle,
+    "case_sentence",
+    "Sentence case",
+    true,
+    accelerator("case_sentence"),
+  )?;
+  let case_submenu = Submenu::with_items(
+    app_handle,
+    "Change Case",
+    true,
+    &[
+      &upper_case_item,
+      &lower_case_item,
+      &title_case_item,
+      &sentence_case_item,
+    ],
+  )?;
+  let promote_headings_item = MenuItem::with_id(
+    app_handle,
+    "headings_promote",
+    "Promote Headings",
+    true,
+    accelerator("headings_promote"),
+  )?;
+  let demote_headings_item = MenuItem::with_id(
+    app_handle,
+    "headings_demote",
+    "Demote Headings",
+    true,
+    accelerator("headings_demote"),
+  )?;
+
+  let format_submenu = Submenu::with_items(
+    app_handle,
+    "Format",
+    true,
+    &[
+      &smarten_item,
+      &straighten_item,
+      &case_submenu,
+      &PredefinedMenuItem::separator(app_handle)?,
+      &promote_headings_item,
+      &demote_headings_item,
+      &PredefinedMenuItem::separator(app_handle)?,
+      &tidy_references_item,
+    ],
+  )?;
+
+  // Window menu, filled in with the open windows by window_menu::update_menu
+  let window_submenu = Submenu::with_id(app_handle, window_menu::MENU_ID, "Window", true)?;
+
+  // Help menu
+  let shortcuts_item = MenuItem::with_id(
+    app_handle,
+    "show_shortcuts",
+    "Keyboard Shortcuts",
+    true,
+    accelerator("show_shortcuts"),
+  )?;
+  let documentation_item = MenuItem::with_id(
+    app_handle,
+    "open_documentation",
+    "Documentation",
+    true,
+    accelerator("open_documentation"),
+  )?;
+  let show_logs_item = MenuItem::with_id(
+    app_handle,
+    "show_logs",
+    "Show Logs",
+    true,
+    accelerator("show_logs"),
+  )?;
+  let report_issue_item = MenuItem::with_id(
+    app_handle,
+    "report_issue",
+    "Report an Issue...",
+    true,
+    accelerator("report_issue"),
+  )?;
+  let separator_help = PredefinedMenuItem::separator(app_handle)?;
+
+  let help_submenu = Submenu::with_items(
+    app_handle,
+    "Help",
+    true,
+    &[
+      &shortcuts_item,
+      &separator_help,
+      &documentation_item,
+      &show_logs_item,
+      &report_issue_item,
+    ],
   )?;
 
   menu.append(&app_submenu)?;
   menu.append(&file_submenu)?;
   menu.append(&edit_submenu)?;
+  menu.append(&format_submenu)?;
   menu.append(&window_submenu)?;
+  menu.append(&help_submenu)?;
 
   Ok(menu)
 }
@@ -155,165 +802,569 @@ fn handle_menu_event(app_handle: &AppHandle, id: &str) {
     "new_file" => {
       let _ = app_handle.emit(MENU_NEW_FILE_EVENT, ());
     }
+    "new_from_clipboard" => {
+      let _ = app_handle.emit(MENU_NEW_FROM_CLIPBOARD_EVENT, ());
+    }
     "open_file" => {
       let _ = app_handle.emit(MENU_OPEN_FILE_EVENT, ());
     }
+    "reopen_closed" => {
+      let _ = app_handle.emit(MENU_REOPEN_CLOSED_EVENT, ());
+    }
     "save_file" => {
       let _ = app_handle.emit(MENU_SAVE_FILE_EVENT, ());
     }
     "save_as_file" => {
       let _ = app_handle.emit(MENU_SAVE_AS_FILE_EVENT, ());
     }
-    _ => {}
+    "export_html" => {
+      let _ = app_handle.emit(MENU_EXPORT_HTML_EVENT, ());
+    }
+    "export_print_html" => {
+      let _ = app_handle.emit(MENU_EXPORT_PRINT_HTML_EVENT, ());
+    }
+    "export_slides" => {
+      let _ = app_handle.emit(MENU_EXPORT_SLIDES_EVENT, ());
+    }
+    "export_latex" => {
+      let _ = app_handle.emit(MENU_EXPORT_LATEX_EVENT, ());
+    }
+    "export_plain_text" => {
+      let _ = app_handle.emit(MENU_EXPORT_PLAIN_TEXT_EVENT, ());
+    }
+    "copy_plain_text" => {
+      let _ = app_handle.emit(MENU_COPY_PLAIN_TEXT_EVENT, ());
+    }
+    "copy_for_email" => {
+      let _ = app_handle.emit(MENU_COPY_FOR_EMAIL_EVENT, ());
+    }
+    "publish_gist" => {
+      let _ = app_handle.emit(MENU_PUBLISH_GIST_EVENT, ());
+    }
+    "export_app_data" => {
+      let _ = app_handle.emit(MENU_EXPORT_APP_DATA_EVENT, ());
+    }
+    "import_app_data" => {
+      let _ = app_handle.emit(MENU_IMPORT_APP_DATA_EVENT, ());
+    }
+    "check_for_updates" => {
+      let app_handle = app_handle.clone();
+      tauri::async_runtime::spawn(async move {
+        updates::check_and_report(&app_handle, true).await;
+      });
+    }
+    "show_shortcuts" => {
+      let _ = app_handle.emit(MENU_SHOW_SHORTCUTS_EVENT, ());
+    }
+    "show_logs" => {
+      let _ = app_handle.emit(MENU_SHOW_LOGS_EVENT, ());
+    }
+    "lines_sort_ascending" | "lines_sort_descending" | "lines_dedupe" | "lines_reverse" => {
+      let operation = id.trim_start_matches("lines_");
+      let _ = app_handle.emit(MENU_TRANSFORM_LINES_EVENT, operation);
+    }
+    "smarten_typography" => {
+      let _ = app_handle.emit(MENU_SMARTEN_TYPOGRAPHY_EVENT, ());
+    }
+    "straighten_typography" => {
+      let _ = app_handle.emit(MENU_STRAIGHTEN_TYPOGRAPHY_EVENT, ());
+    }
+    "tidy_references" => {
+      let _ = app_handle.emit(MENU_TIDY_REFERENCES_EVENT, ());
+    }
+    "case_upper" | "case_lower" | "case_title" | "case_sentence" => {
+      let operation = id.trim_start_matches("case_");
+      let _ = app_handle.emit(MENU_TRANSFORM_CASE_EVENT, operation);
+    }
+    "headings_promote" => {
+      let _ = app_handle.emit(MENU_SHIFT_HEADINGS_EVENT, -1);
+    }
+    "headings_demote" => {
+      let _ = app_handle.emit(MENU_SHIFT_HEADINGS_EVENT, 1);
+    }
+    "import_document" => {
+      let _ = app_handle.emit(MENU_IMPORT_DOCUMENT_EVENT, ());
+    }
+    "open_inbox" => {
+      if let Err(e) = inbox::open_inbox(app_handle) {
+        log::error!("Failed to open the inbox: {}", e);
+      }
+    }
+    "split_h1" | "split_h2" | "split_h3" => {
+      let level: u8 = id.trim_start_matches("split_h").parse().unwrap_or(1);
+      let _ = app_handle.emit(MENU_SPLIT_BY_HEADING_EVENT, level);
+    }
+    "open_terminal" => {
+      let _ = app_handle.emit(MENU_OPEN_TERMINAL_EVENT, ());
+    }
+    recent_workspaces::CLEAR_ITEM_ID => recent_workspaces::clear(app_handle),
+    open_with::DEFAULT_ITEM_ID => {
+      let _ = app_handle.emit(MENU_OPEN_WITH_EVENT, None::<String>);
+    }
+    "start_speaking" => {
+      let _ = app_handle.emit(MENU_START_SPEAKING_EVENT, ());
+    }
+    // Stopped right here rather than through the window, so it's immediate
+    "stop_speaking" => {
+      if let Err(e) = app_handle.state::<speech::SpeechState>().stop() {
+        log::error!("Failed to stop speaking: {}", e);
+      }
+    }
+    "quit" => close_guard::request_quit(app_handle),
+    "open_documentation" => help::open_help_link(app_handle, help::HelpLink::Documentation),
+    "report_issue" => help::open_help_link(app_handle, help::HelpLink::ReportIssue),
+    _ => {
+      if let Some(application) = id.strip_prefix(open_with::ITEM_ID_PREFIX) {
+        let _ = app_handle.emit(MENU_OPEN_WITH_EVENT, Some(application));
+      } else if let Some(kind) = id.strip_prefix(document_reference::ITEM_ID_PREFIX) {
+        let _ = app_handle.emit(MENU_COPY_DOCUMENT_REFERENCE_EVENT, kind);
+      } else if let Some(root) = id.strip_prefix(recent_workspaces::ITEM_ID_PREFIX) {
+        recent_workspaces::open_from_menu(app_handle, root);
+      } else if let Some(label) = id.strip_prefix(window_menu::ITEM_ID_PREFIX) {
+        window_menu::focus_window(app_handle, label);
+      } else {
+        menu_toggles::handle_menu_event(app_handle, id);
+      }
+    }
   }
 }
 
 // File metadata for validation
 #[derive(Debug)]
 struct FileMetadata {
+  // The path with symlinks resolved as far as it exists and `.`/`..` removed
+  normalized_path: PathBuf,
   exists: bool,
   is_file: bool,
   is_readable: bool,
+  // Whether we may create files in the nearest existing ancestor directory, i.e. whether a
+  // new file could be saved here (once any missing folders are created)
+  parent_writable: bool,
+  // Whether saving to this path is allowed: the file (if it exists) isn't read-only and
+  // the folder accepts the replacement file
+  writable: bool,
+}
+
+// Whether the user can save to a file, as sent in FILE_ACCESS_EVENT
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct FileAccess {
+  path: String,
+  writable: bool,
+  // Modification time on disk in milliseconds, sent back as `expected_mtime` on save
+  mtime: Option<u64>,
 }
 
-// Validate and get file metadata
-fn validate_file_path(path: &Path) -> Result<FileMetadata, String> {
+// Validate and get file metadata. Works for paths that don't exist yet, so it can check a
+// Save As target as well as a file being opened.
+fn validate_file_path(path: &Path) -> CommandResult<FileMetadata> {
   // Check if path is absolute
   if !path.is_absolute() {
-    return Err("File path must be absolute".to_string());
-  }
-
-  // Check for path traversal attempts
-  let canonical_path = path
-    .canonicalize()
-    .map_err(|e| format!("Invalid path: {}", e))?;
-  if !canonical_path.starts_with(std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"))) {
-    // Allow paths outside current dir but log warning - they're still valid absolute paths
-    println!(
-      "Warning: Opening file outside current directory: {:?}",
-      canonical_path
-    );
+    return Err(CommandError::invalid_path(
+      path,
+      "File path must be absolute",
+    ));
   }
 
-  let exists = canonical_path.exists();
-  let is_file = canonical_path.is_file();
+  let normalized_path = normalize_partially_existing(path);
+  let exists = normalized_path.exists();
+  let is_file = normalized_path.is_file();
 
   // Check if file is readable (for existing files)
   let is_readable = if exists && is_file {
-    std::fs::File::open(&canonical_path).is_ok()
+    std::fs::File::open(&normalized_path).is_ok()
   } else {
     false
   };
 
+  let parent_writable = normalized_path
+    .parent()
+    .and_then(|parent| parent.ancestors().find(|dir| dir.exists()))
+    .is_some_and(is_writable);
+  let writable = parent_writable && (!exists || is_file_writable(&normalized_path));
+
   Ok(FileMetadata {
+    normalized_path,
     exists,
     is_file,
     is_readable,
+    parent_writable,
+    writable,
+  })
+}
+
+// Canonicalize the deepest ancestor of `path` that exists and append the rest lexically
+fn normalize_partially_existing(path: &Path) -> PathBuf {
+  for ancestor in path.ancestors() {
+    if let Ok(canonical) = ancestor.canonicalize() {
+      let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+      return lexical_normalize(&canonical.join(rest));
+    }
+  }
+  lexical_normalize(path)
+}
+
+// Whether the current user may write to `path` (a file, or a directory to create files in)
+#[cfg(unix)]
+fn is_writable(path: &Path) -> bool {
+  use std::os::unix::ffi::OsStrExt;
+  match std::ffi::CString::new(path.as_os_str().as_bytes()) {
+    // access() accounts for ownership, groups and root, which the mode bits alone don't
+    Ok(path) => unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 },
+    Err(_) => false,
+  }
+}
+
+#[cfg(not(unix))]
+fn is_writable(path: &Path) -> bool {
+  std::fs::metadata(path).is_ok_and(|metadata| !metadata.permissions().readonly())
+}
+
+// Whether an existing file may be overwritten. A file without write bits counts as
+// read-only even for root, who could write it anyway: somebody marked it read-only.
+fn is_file_writable(path: &Path) -> bool {
+  is_writable(path) && std::fs::metadata(path).is_ok_and(|m| !m.permissions().readonly())
+}
+
+// Give the user write permission on a file they own (clear the read-only attribute on
+// Windows)
+#[cfg(unix)]
+fn clear_read_only(path: &Path) -> CommandResult<()> {
+  use std::os::unix::fs::{MetadataExt, PermissionsExt};
+  let metadata = std::fs::metadata(path)
+    .map_err(|e| CommandError::from_io(&e, path, "Failed to read file metadata"))?;
+  let euid = unsafe { libc::geteuid() };
+  if euid != 0 && metadata.uid() != euid {
+    return Err(CommandError::NotOwner {
+      path: path.to_string_lossy().to_string(),
+    });
+  }
+  let mut permissions = metadata.permissions();
+  permissions.set_mode(permissions.mode() | 0o200);
+  std::fs::set_permissions(path, permissions)
+    .map_err(|e| CommandError::from_io(&e, path, "Failed to change permissions"))
+}
+
+#[cfg(not(unix))]
+fn clear_read_only(path: &Path) -> CommandResult<()> {
+  let metadata = std::fs::metadata(path)
+    .map_err(|e| CommandError::from_io(&e, path, "Failed to read file metadata"))?;
+  let mut permissions = metadata.permissions();
+  permissions.set_readonly(false);
+  std::fs::set_permissions(path, permissions).map_err(|e| match e.kind() {
+    std::io::ErrorKind::PermissionDenied => CommandError::NotOwner {
+      path: path.to_string_lossy().to_string(),
+    },
+    _ => CommandError::from_io(&e, path, "Failed to change permissions"),
   })
 }
 
+fn file_access(path: &Path) -> CommandResult<FileAccess> {
+  Ok(FileAccess {
+    path: path.to_string_lossy().to_string(),
+    writable: validate_file_path(path)?.writable,
+    mtime: disk_mtime(path),
+  })
+}
+
+fn emit_file_access(app: &AppHandle, path: &Path) -> CommandResult<FileAccess> {
+  let access = file_access(path)?;
+  let _ = app.emit(FILE_ACCESS_EVENT, access.clone());
+  Ok(access)
+}
+
 // Load recent files from persistent store
 fn load_recent_files_from_store(app: &AppHandle) -> Vec<String> {
-  match app.store(STORE_FILE) {
+  match app_store::open_store(app) {
     Ok(store) => {
       if let Some(files) = store.get(RECENT_FILES_KEY) {
         if let Ok(files_vec) = serde_json::from_value::<Vec<String>>(files.clone()) {
-          // Filter out files that no longer exist
-          let valid_files: Vec<String> = files_vec
-            .into_iter()
-            .filter(|path| PathBuf::from(path).exists())
-            .take(MAX_RECENT_FILES)
-            .collect();
-          return valid_files;
+          // Keep missing files too (e.g. an unmounted drive); get_recent_files flags them
+          return files_vec.into_iter().take(MAX_RECENT_FILES).collect();
         }
       }
     }
-    Err(e) => eprintln!("Failed to load store: {}", e),
+    Err(e) => log::error!("Failed to load store: {}", e),
   }
   Vec::new()
 }
 
-// Save recent files to persistent store
+// Save recent files to persistent store and update the dock menu, which lists them too
 fn save_recent_files_to_store(app: &AppHandle, files: &[String]) {
-  match app.store(STORE_FILE) {
-    Ok(store) => {
-      if let Ok(value) = serde_json::to_value(files) {
-        store.set(RECENT_FILES_KEY, value);
-        if let Err(e) = store.save() {
-          eprintln!("Failed to save store: {}", e);
-        }
-      }
-    }
-    Err(e) => eprintln!("Failed to save store: {}", e),
+  #[cfg(target_os = "macos")]
+  macos::refresh_dock_menu(app, files);
+  // Safe mode started without the saved list; keep it for the next normal launch
+  if !safe_mode::startup(app).store {
+    return;
+  }
+  if let Ok(value) = serde_json::to_value(files) {
+    // Failures are logged and reported to the frontend by write_key
+    let _ = app_store::write_key(app, "save recent files", RECENT_FILES_KEY, value);
+    let _ = app.emit(RECENTS_CHANGED_EVENT, files);
+  }
+}
+
+// Move a corrupted store file aside before anything opens the store
+fn recover_corrupted_store(app: &AppHandle) -> Option<StoreRecovery> {
+  let path = app_store::store_path(app).ok()?;
+  let recovery = app_store::recover_store_file(&path)?;
+  log::warn!(
+    "Store file was corrupted; moved to {} (salvaged keys: {:?})",
+    recovery.backup_path,
+    recovery.salvaged_keys
+  );
+  Some(recovery)
+}
+
+// Read file content. Whether the file can be saved is sent separately as FILE_ACCESS_EVENT.
+#[tauri::command]
+async fn read_file(
+  app: AppHandle,
+  scope: tauri::State<'_, access_scope::AccessScopeState>,
+  path: String,
+) -> CommandResult<String> {
+  let path = PathBuf::from(&path);
+  scope.check(&path)?;
+  let content = read_text_file(&path)?;
+  emit_file_access(&app, &path)?;
+  Ok(content)
+}
+
+// A file opened in the editor, with where the user left off in it last time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct OpenedDocument {
+  content: String,
+  view_state: Option<view_state::FileViewState>,
+  // Modification time of what was read, for the save's `expected_mtime`
+  mtime: Option<u64>,
+}
+
+// Read a file for the editor, together with its saved view state, so the editor can restore
+// the cursor and scroll position without another round trip
+#[tauri::command]
+async fn open_document(
+  app: AppHandle,
+  scope: tauri::State<'_, access_scope::AccessScopeState>,
+  path: String,
+) -> CommandResult<OpenedDocument> {
+  scope.check(Path::new(&path))?;
+  let content = read_text_file(Path::new(&path))?;
+  let access = emit_file_access(&app, Path::new(&path))?;
+  Ok(OpenedDocument {
+    content,
+    view_state: view_state::load_view_state(&app, &path),
+    mtime: access.mtime,
+  })
+}
+
+// Make a read-only file writable. Only called when the user explicitly asks (e.g. clicks
+// the lock in the title bar); saves never do this on their own.
+#[tauri::command]
+async fn make_writable(
+  app: AppHandle,
+  scope: tauri::State<'_, access_scope::AccessScopeState>,
+  path: String,
+) -> CommandResult<FileAccess> {
+  let path = PathBuf::from(&path);
+  scope.check(&path)?;
+  let metadata = validate_file_path(&path)?;
+  let path_str = path.to_string_lossy().to_string();
+  if !metadata.exists {
+    return Err(CommandError::NotFound { path: path_str });
+  }
+  if !metadata.is_file {
+    return Err(CommandError::NotAFile { path: path_str });
   }
+  clear_read_only(&metadata.normalized_path)?;
+  emit_file_access(&app, &path)
 }
 
-// Read file content
-#[tauri::command]
-async fn read_file(_app: AppHandle, path: String) -> Result<String, String> {
-  let path = PathBuf::from(&path);
+// Largest file we open or save (bigger files make the editor unusable)
+const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024; // 10MB limit
 
+// Validate and read a text file from disk (shared by read_file and other commands)
+fn read_text_file(path: &Path) -> CommandResult<String> {
   // Validate the file path
-  let metadata = validate_file_path(&path).map_err(|e| format!("Path validation failed: {}", e))?;
+  let metadata = validate_file_path(path)?;
+  let path_str = path.to_string_lossy().to_string();
 
   if !metadata.exists {
-    return Err("File does not exist".to_string());
+    return Err(CommandError::NotFound { path: path_str });
   }
 
   if !metadata.is_file {
-    return Err("Path is not a file".to_string());
+    return Err(CommandError::NotAFile { path: path_str });
   }
 
   if !metadata.is_readable {
-    return Err("File is not readable".to_string());
+    return Err(CommandError::PermissionDenied { path: path_str });
   }
 
   // Check file size (prevent loading extremely large files)
-  let metadata_std =
-    std::fs::metadata(&path).map_err(|e| format!("Failed to read file metadata: {}", e))?;
-  const MAX_FILE_SIZE: u64 = 10 * 1024 * 1024; // 10MB limit
+  let metadata_std = std::fs::metadata(path)
+    .map_err(|e| CommandError::from_io(&e, path, "Failed to read file metadata"))?;
   if metadata_std.len() > MAX_FILE_SIZE {
-    return Err("File is too large (max 10MB)".to_string());
+    return Err(CommandError::TooLarge {
+      limit: MAX_FILE_SIZE,
+      actual: metadata_std.len(),
+    });
   }
 
-  match std::fs::read_to_string(&path) {
-    Ok(content) => Ok(content),
-    Err(e) => Err(format!("Failed to read file: {}", e)),
+  let bytes =
+    std::fs::read(path).map_err(|e| CommandError::from_io(&e, path, "Failed to read file"))?;
+  // Check before decoding so e.g. a renamed image gets a useful error instead of a UTF-8 one
+  if let Some(mime) = binary::sniff_binary(&bytes) {
+    return Err(CommandError::BinaryFile {
+      path: path_str,
+      mime: mime.to_string(),
+    });
   }
+  String::from_utf8(bytes).map_err(|e| {
+    CommandError::invalid_data(format!(
+      "Failed to read file: file is not valid UTF-8 ({})",
+      e
+    ))
+  })
 }
 
-// Write file content
+// Modification time in milliseconds since the epoch
+fn file_mtime_millis(metadata: &std::fs::Metadata) -> Option<u64> {
+  let modified = metadata.modified().ok()?;
+  let millis = modified
+    .duration_since(std::time::UNIX_EPOCH)
+    .ok()?
+    .as_millis();
+  u64::try_from(millis).ok()
+}
+
+// Current time in milliseconds since the epoch
+pub(crate) fn now_millis() -> u64 {
+  std::time::SystemTime::now()
+    .duration_since(std::time::UNIX_EPOCH)
+    .map(|d| d.as_millis() as u64)
+    .unwrap_or(0)
+}
+
+// Modification time of the file at `path`, if it can be read
+fn disk_mtime(path: &Path) -> Option<u64> {
+  file_mtime_millis(&std::fs::metadata(path).ok()?)
+}
+
+// Write file content. Writes to the same file are queued so they land in the order they
+// were made. When `expected_mtime` is given and the file on disk was modified after it,
+// the write is refused with a conflict error instead of clobbering the change. `durable`
+// overrides the durable_saves setting for this write.
 #[tauri::command]
-async fn write_file(_app: AppHandle, path: String, content: String) -> Result<(), String> {
+async fn write_file(
+  app: AppHandle,
+  coordinator: tauri::State<'_, WriteCoordinator>,
+  settings: tauri::State<'_, SettingsState>,
+  wiki_index: tauri::State<'_, wiki::WikiIndexState>,
+  file_index: tauri::State<'_, file_finder::FileIndexState>,
+  recently_closed: tauri::State<'_, recently_closed::RecentlyClosedState>,
+  external_edits: tauri::State<'_, open_with::ExternalEditState>,
+  scope: tauri::State<'_, access_scope::AccessScopeState>,
+  path: String,
+  content: String,
+  expected_mtime: Option<u64>,
+  durable: Option<bool>,
+) -> CommandResult<WriteResult> {
+  let (durable, smart_typography) = {
+    let settings = settings.0.lock().unwrap();
+    (
+      durable.unwrap_or(settings.durable_saves),
+      settings.smart_typography_on_save,
+    )
+  };
   let path = PathBuf::from(&path);
+  scope.check(&path)?;
+  let target = path.clone();
+  let smartened = smart_typography
+    .then(|| typography::smarten(&content, &typography::TypographyOptions::default()))
+    .filter(|smartened| *smartened != content);
+  let written = smartened.clone().unwrap_or(content);
+  let words = writing_history::count_words(&written);
+  let (result, queue_depth) = coordinator
+    .submit(&path, move || {
+      write_text_file(&target, &written, expected_mtime, durable)
+    })
+    .await?;
+  if result.is_ok() {
+    // The note's title may have changed, which changes what wiki links resolve to
+    wiki_index.invalidate(&path);
+    file_index.invalidate(&path);
+    recently_closed.saved(&path.to_string_lossy());
+    external_edits.saved(&path);
+    writing_history::record_save(&app, &path, words);
+  }
+  result.map(|mtime| WriteResult {
+    queue_depth,
+    content: smartened,
+    mtime,
+  })
+}
 
-  // Validate the path is absolute
-  if !path.is_absolute() {
-    return Err("File path must be absolute".to_string());
+// Validate and atomically write a text file (blocking). Returns the file's new modification
+// time, to be passed as `expected_mtime` on the next write.
+fn write_text_file(
+  path: &Path,
+  content: &str,
+  expected_mtime: Option<u64>,
+  durable: bool,
+) -> CommandResult<Option<u64>> {
+  let metadata = validate_file_path(path)?;
+  let path = metadata.normalized_path.as_path();
+
+  if metadata.exists && !metadata.is_file {
+    return Err(CommandError::NotAFile {
+      path: path.to_string_lossy().to_string(),
+    });
   }
 
-  // If file exists, validate it's a file and writable
-  if path.exists() && !path.is_file() {
-    return Err("Path is not a file".to_string());
+  // Checked up front so no temp file is left behind for a save that can't succeed
+  if metadata.exists && !is_file_writable(path) {
+    return Err(CommandError::ReadOnly {
+      path: path.to_string_lossy().to_string(),
+    });
   }
 
-  // Validate parent directory exists
+  // Validate parent directory exists and we can create the new file in it
   if let Some(parent) = path.parent() {
-    if !parent.exists() {
-      return Err("Parent directory does not exist".to_string());
+    if !parent.is_dir() {
+      return Err(CommandError::NotFound {
+        path: parent.to_string_lossy().to_string(),
+      });
+    }
+    if !metadata.parent_writable {
+      return Err(CommandError::PermissionDenied {
+        path: parent.to_string_lossy().to_string(),
+      });
     }
   }
 
   // Check content size
-  const MAX_CONTENT_SIZE: usize = 10 * 1024 * 1024; // 10MB limit
-  if content.len() > MAX_CONTENT_SIZE {
-    return Err("Content is too large (max 10MB)".to_string());
+  if content.len() as u64 > MAX_FILE_SIZE {
+    return Err(CommandError::TooLarge {
+      limit: MAX_FILE_SIZE,
+      actual: content.len() as u64,
+    });
   }
 
-  match std::fs::write(&path, content) {
-    Ok(_) => Ok(()),
-    Err(e) => Err(format!("Failed to write file: {}", e)),
+  if let (Some(expected), Ok(metadata)) = (expected_mtime, std::fs::metadata(path)) {
+    if let Some(disk_mtime) = file_mtime_millis(&metadata).filter(|mtime| *mtime > expected) {
+      return Err(CommandError::Conflict { disk_mtime });
+    }
   }
+
+  atomic_write::write_atomically_durable(path, content.as_bytes(), durable).map_err(
+    |e| match e {
+      AtomicWriteError::Write(e) => CommandError::from_io(&e, path, "Failed to write file"),
+      AtomicWriteError::Sync(e) => CommandError::SyncFailed {
+        path: path.to_string_lossy().to_string(),
+        reason: e.to_string(),
+      },
+    },
+  )?;
+  Ok(disk_mtime(path))
 }
 
 // Open file dialog
@@ -321,81 +1372,306 @@ async fn write_file(_app: AppHandle, path: String, content: String) -> Result<()
 async fn open_file_dialog(
   app: AppHandle,
   state: tauri::State<'_, RecentFilesState>,
-) -> Result<Option<String>, String> {
+  settings: tauri::State<'_, SettingsState>,
+) -> CommandResult<Option<String>> {
+  let extensions = settings.0.lock().unwrap().open_dialog_extensions();
+  let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
   let file_path = app
     .dialog()
     .file()
-    .add_filter("Markdown", &["md", "markdown", "txt"])
+    .add_filter("Markdown", &extensions)
     .blocking_pick_file();
 
-  match file_path {
-    Some(path) => {
-      if let Some(p) = path.as_path() {
-        let path_str = p.to_string_lossy().to_string();
-        // Add to recents
-        add_to_recents_internal(&app, &state, path_str.clone());
-        Ok(Some(path_str))
-      } else {
-        Ok(None)
-      }
-    }
-    None => Ok(None),
+  let Some(path) = file_path.map(dialog_result_to_path).transpose()? else {
+    return Ok(None);
+  };
+  let path_str = path.to_string_lossy().to_string();
+  access_scope::allow_documents(&app, &[path.as_path()]);
+  // Add to recents
+  add_to_recents_internal(&app, &state, path_str.clone());
+  Ok(Some(path_str))
+}
+
+// Open dialog allowing several files; every picked file is added to recents. Returns an
+// empty list if the dialog was cancelled.
+#[tauri::command]
+async fn open_files_dialog(
+  app: AppHandle,
+  state: tauri::State<'_, RecentFilesState>,
+  settings: tauri::State<'_, SettingsState>,
+) -> CommandResult<Vec<String>> {
+  let extensions = settings.0.lock().unwrap().open_dialog_extensions();
+  let extensions: Vec<&str> = extensions.iter().map(String::as_str).collect();
+  let picked = app
+    .dialog()
+    .file()
+    .add_filter("Markdown", &extensions)
+    .blocking_pick_files()
+    .unwrap_or_default();
+
+  let paths: Vec<String> = picked
+    .into_iter()
+    .map(|path| dialog_result_to_path(path).map(|p| p.to_string_lossy().to_string()))
+    .collect::<CommandResult<_>>()?;
+  let documents: Vec<&Path> = paths.iter().map(Path::new).collect();
+  access_scope::allow_documents(&app, &documents);
+  // Add in reverse so the first picked file ends up on top of the recents
+  for path in paths.iter().rev() {
+    add_to_recents_internal(&app, &state, path.clone());
   }
+  Ok(paths)
 }
 
-// Save file dialog
+// Save file dialog. The file name is `suggested_name` if given, otherwise derived from the
+// first heading of `content`; the dialog opens in the directory of the last save.
 #[tauri::command]
 async fn save_file_dialog(
   app: AppHandle,
   state: tauri::State<'_, RecentFilesState>,
-) -> Result<Option<String>, String> {
-  let file_path = app
+  settings: tauri::State<'_, SettingsState>,
+  suggested_name: Option<String>,
+  content: Option<String>,
+) -> CommandResult<Option<String>> {
+  let separator = settings.0.lock().unwrap().filename_separator;
+  let file_name = suggested_name
+    .and_then(|name| {
+      let stem = name.strip_suffix(".md").unwrap_or(&name).to_string();
+      filename::sanitize_file_stem(&stem, separator)
+    })
+    .map(|stem| format!("{}.md", stem))
+    .unwrap_or_else(|| filename::suggest_file_name(content.as_deref().unwrap_or(""), separator));
+
+  let mut dialog = app
     .dialog()
     .file()
     .add_filter("Markdown", &["md", "markdown"])
-    .blocking_save_file();
-
-  match file_path {
-    Some(path) => {
-      if let Some(p) = path.as_path() {
-        let path_str = p.to_string_lossy().to_string();
-        // Add to recents
-        add_to_recents_internal(&app, &state, path_str.clone());
-        Ok(Some(path_str))
-      } else {
-        Ok(None)
+    .set_file_name(file_name);
+  if let Some(directory) = load_last_save_directory(&app) {
+    dialog = dialog.set_directory(directory);
+  }
+  let file_path = dialog.blocking_save_file();
+
+  let Some(path) = file_path.map(dialog_result_to_path).transpose()? else {
+    return Ok(None);
+  };
+  let path_str = path.to_string_lossy().to_string();
+  if let Some(parent) = path.parent() {
+    save_last_save_directory(&app, parent);
+  }
+  access_scope::allow_documents(&app, &[path.as_path()]);
+  // Add to recents
+  add_to_recents_internal(&app, &state, path_str.clone());
+  Ok(Some(path_str))
+}
+
+// Directory of the last Save As, if it still exists
+fn load_last_save_directory(app: &AppHandle) -> Option<PathBuf> {
+  let store = app_store::open_store(app).ok()?;
+  let directory = store
+    .get(LAST_SAVE_DIRECTORY_KEY)
+    .and_then(|v| v.as_str().map(PathBuf::from))?;
+  directory.is_dir().then_some(directory)
+}
+
+fn save_last_save_directory(app: &AppHandle, directory: &Path) {
+  let directory = directory.to_string_lossy().to_string();
+  let _ = app_store::write_key(
+    app,
+    "save the last save folder",
+    LAST_SAVE_DIRECTORY_KEY,
+    directory.into(),
+  );
+}
+
+// Resolve `.` and `..` components and drop trailing separators without touching the disk
+fn lexical_normalize(path: &Path) -> PathBuf {
+  let mut normalized = PathBuf::new();
+  for component in path.components() {
+    match component {
+      Component::CurDir => {}
+      Component::ParentDir => {
+        normalized.pop();
       }
+      other => normalized.push(other.as_os_str()),
     }
-    None => Ok(None),
   }
+  normalized
 }
 
-// Internal function to add a file to recents (updates both memory and persistent store)
-fn add_to_recents_internal(
-  app: &AppHandle,
-  state: &tauri::State<'_, RecentFilesState>,
-  path: String,
-) {
-  let mut recents = state.0.lock().unwrap();
+// Normalize a path before storing it in recents so the same file always maps to one entry.
+// Canonicalization resolves symlinks; if it fails (e.g. the file is on an unmounted drive)
+// we fall back to lexical normalization.
+fn normalize_recent_path(path: &str) -> String {
+  let path = Path::new(path);
+  let normalized = path
+    .canonicalize()
+    .unwrap_or_else(|_| lexical_normalize(path));
+  normalized.to_string_lossy().to_string()
+}
+
+// Compare two normalized recents paths. macOS and Windows filesystems are case-insensitive
+// by default, so `Todo.md` and `todo.md` are the same file there.
+fn recent_paths_equal(a: &str, b: &str) -> bool {
+  if cfg!(any(target_os = "macos", target_os = "windows")) {
+    a.to_lowercase() == b.to_lowercase()
+  } else {
+    a == b
+  }
+}
+
+// Abbreviate the home directory prefix as `~` for display
+fn display_recent_path(path: &str, home_dir: Option<&Path>) -> String {
+  if let Some(home) = home_dir {
+    if let Ok(rest) = Path::new(path).strip_prefix(home) {
+      if rest.as_os_str().is_empty() {
+        return "~".to_string();
+      }
+      return format!("~{}{}", std::path::MAIN_SEPARATOR, rest.to_string_lossy());
+    }
+  }
+  path.to_string()
+}
+
+// Move a path to the top of the recents list, deduplicating and trimming to the max
+fn insert_recent(recents: &mut Vec<String>, path: &str) {
+  let path = normalize_recent_path(path);
   // Remove if already exists (to move to top)
-  recents.retain(|p| p != &path);
+  recents.retain(|p| !recent_paths_equal(p, &path));
   // Add to front
   recents.insert(0, path);
   // Trim to max
   if recents.len() > MAX_RECENT_FILES {
     recents.truncate(MAX_RECENT_FILES);
   }
+}
+
+// Internal function to add a file to recents (updates both memory and persistent store)
+fn add_to_recents_internal(
+  app: &AppHandle,
+  state: &tauri::State<'_, RecentFilesState>,
+  path: String,
+) {
+  let mut recents = state.0.lock().unwrap();
+  insert_recent(&mut recents, &path);
   // Save to persistent store
   save_recent_files_to_store(app, &recents);
+  frequent::record_open(app, &path);
 }
 
-// Get recent files
+// Check which paths exist, giving up on any check that doesn't finish before the timeout.
+// Each check runs on its own thread so one hung mount doesn't delay the others.
+fn check_paths_exist(paths: &[String], timeout: Duration) -> Vec<bool> {
+  let (tx, rx) = mpsc::channel();
+  for (index, path) in paths.iter().enumerate() {
+    let tx = tx.clone();
+    let path = PathBuf::from(path);
+    std::thread::spawn(move || {
+      let _ = tx.send((index, path.exists()));
+    });
+  }
+  drop(tx);
+
+  let mut results = vec![false; paths.len()];
+  let deadline = Instant::now() + timeout;
+  let mut remaining = paths.len();
+  while remaining > 0 {
+    let wait = deadline.saturating_duration_since(Instant::now());
+    match rx.recv_timeout(wait) {
+      Ok((index, exists)) => {
+        results[index] = exists;
+        remaining -= 1;
+      }
+      // Timed out (or all senders finished): whatever is left counts as missing
+      Err(_) => break,
+    }
+  }
+  results
+}
+
+// Get recent files, each flagged with whether it currently exists. At startup this waits
+// for them to be loaded.
 #[tauri::command]
 async fn get_recent_files(
+  app: AppHandle,
+  state: tauri::State<'_, RecentFilesState>,
+) -> CommandResult<Vec<RecentFileEntry>> {
+  recent_files_loaded(&app).await;
+  let recents = state.0.lock().unwrap().clone();
+  let exists = check_paths_exist(&recents, RECENT_EXISTS_TIMEOUT);
+  let home_dir = app.path().home_dir().ok();
+  Ok(
+    recents
+      .into_iter()
+      .zip(exists)
+      .map(|(path, exists)| RecentFileEntry {
+        display_path: display_recent_path(&path, home_dir.as_deref()),
+        path,
+        exists,
+      })
+      .collect(),
+  )
+}
+
+// Remove a single file from recents
+#[tauri::command]
+async fn remove_from_recents(
+  app: AppHandle,
+  state: tauri::State<'_, RecentFilesState>,
+  path: String,
+) -> CommandResult<()> {
+  let path = normalize_recent_path(&path);
+  recent_files_loaded(&app).await;
+  let mut recents = state.0.lock().unwrap();
+  recents.retain(|p| !recent_paths_equal(p, &path));
+  save_recent_files_to_store(&app, &recents);
+  view_state::forget_view_state(&app, Some(&path));
+  Ok(())
+}
+
+// Point the recent entry `old` at `new` in place, e.g. after relocate_recent found where the
+// file went. If `new` was listed too, only its higher entry stays.
+fn replace_recent(recents: &mut Vec<String>, old: &str, new: &str) -> bool {
+  let old = normalize_recent_path(old);
+  let new = normalize_recent_path(new);
+  let Some(index) = recents.iter().position(|p| recent_paths_equal(p, &old)) else {
+    return false;
+  };
+  recents[index] = new.clone();
+  let mut seen = false;
+  recents.retain(|p| {
+    let duplicate = recent_paths_equal(p, &new);
+    let keep = !duplicate || !seen;
+    seen |= duplicate;
+    keep
+  });
+  true
+}
+
+// Update a moved file's recent entry (only ever on the user's say-so)
+#[tauri::command]
+async fn update_recent_path(
+  app: AppHandle,
   state: tauri::State<'_, RecentFilesState>,
-) -> Result<Vec<String>, String> {
-  let recents = state.0.lock().unwrap();
-  Ok(recents.clone())
+  old: String,
+  new: String,
+) -> CommandResult<()> {
+  recent_files_loaded(&app).await;
+  let metadata = validate_file_path(Path::new(&new))?;
+  if !metadata.exists {
+    return Err(CommandError::NotFound { path: new });
+  }
+  if !metadata.is_file {
+    return Err(CommandError::NotAFile { path: new });
+  }
+  let mut recents = state.0.lock().unwrap();
+  if !replace_recent(&mut recents, &old, &new) {
+    return Err(CommandError::invalid_data(format!(
+      "{} is not a recent file",
+      old
+    )));
+  }
+  save_recent_files_to_store(&app, &recents);
+  Ok(())
 }
 
 // Add file to recents (called when opening a file directly)
@@ -404,7 +1680,7 @@ async fn add_to_recents(
   app: AppHandle,
   state: tauri::State<'_, RecentFilesState>,
   path: String,
-) -> Result<(), String> {
+) -> CommandResult<()> {
   add_to_recents_internal(&app, &state, path);
   Ok(())
 }
@@ -414,130 +1690,311 @@ async fn add_to_recents(
 async fn clear_recent_files(
   app: AppHandle,
   state: tauri::State<'_, RecentFilesState>,
-) -> Result<(), String> {
+) -> CommandResult<()> {
+  recent_files_loaded(&app).await;
   let mut recents = state.0.lock().unwrap();
   recents.clear();
   // Also clear from persistent store
   save_recent_files_to_store(&app, &[]);
+  view_state::forget_view_state(&app, None);
   Ok(())
 }
 
-// Command to get pending file (for when app is opened with file)
+// Command to get the store recovery notice (returned once, then cleared)
+#[tauri::command]
+async fn take_store_recovery(
+  state: tauri::State<'_, StoreRecoveryState>,
+) -> CommandResult<Option<StoreRecovery>> {
+  let mut recovery = state.0.lock().unwrap();
+  Ok(recovery.take())
+}
+
+// Command to get the calling window's pending file (for when app is opened with file)
 #[tauri::command]
 async fn get_pending_file(
+  window: tauri::Window,
   state: tauri::State<'_, PendingFileState>,
-) -> Result<Option<String>, String> {
+) -> CommandResult<Option<launch::PendingOpen>> {
   let mut pending = state.0.lock().unwrap();
-  let result = pending.take();
-  println!("get_pending_file called, returning: {:?}", result);
+  let result = pending
+    .queues
+    .get_mut(window.label())
+    .and_then(|queue| queue.pop_front());
+  log::debug!(
+    "get_pending_file called by {}, returning: {:?}",
+    window.label(),
+    result
+  );
   Ok(result)
 }
 
-// Command to set pending file (used when receiving file-open events)
+// Command to set a pending file for the calling window (used when receiving file-open
+// events). `path` is a PendingOpen or a plain path.
 #[tauri::command]
 async fn set_pending_file(
   app: AppHandle,
-  state: tauri::State<'_, PendingFileState>,
-  path: String,
-) -> Result<(), String> {
-  println!("set_pending_file called with: {}", path);
-  let mut pending = state.0.lock().unwrap();
-  *pending = Some(path);
+  window: tauri::Window,
+  path: launch::PendingOpen,
+) -> CommandResult<()> {
+  log::debug!("set_pending_file called with: {:?}", path);
+  launch::queue_pending_files(&app, window.label(), &[path], true);
+  Ok(())
+}
+
+fn handle_run_event(app: &AppHandle, event: &tauri::RunEvent) {
+  match event {
+    tauri::RunEvent::ExitRequested { api, .. } if close_guard::intercept_exit(app) => {
+      api.prevent_exit()
+    }
+    // Closing the last window asks to exit without a code; app.exit() (e.g. Quit) has one
+    #[cfg(desktop)]
+    tauri::RunEvent::ExitRequested {
+      code: None, api, ..
+    } if tray::keeps_running(app) => api.prevent_exit(),
+    tauri::RunEvent::Exit => {
+      session::flush_tab_session(app);
+      app_store::flush_unsaved(app);
+    }
+    _ => {}
+  }
+}
+
+// Move an older version's store file over, recover from a corrupted store file, then load
+// the recent files from it. Safe mode starts with no recents and leaves the store file alone.
+fn init_store(app: &AppHandle, startup: &Startup) {
+  let recovery = if startup.store {
+    app_store::migrate_legacy_store(app);
+    recover_corrupted_store(app)
+  } else {
+    None
+  };
+  if let Some(recovery) = &recovery {
+    let _ = app.emit(STORE_RECOVERED_EVENT, recovery.clone());
+  }
+  app.manage(StoreRecoveryState(Mutex::new(recovery)));
+  #[cfg(target_os = "macos")]
+  macos::init_dock_menu(app, &[]);
+  app.manage(RecentFilesState(Mutex::new(Vec::new())));
+  // Safe mode starts without the saved list, so there's nothing to wait for
+  let (loaded, _) = tokio::sync::watch::channel(!startup.store);
+  app.manage(RecentFilesLoaded(loaded));
+  if startup.store {
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || load_recent_files(&app));
+  }
+}
+
+// Read the saved recent files into RecentFilesState, below any opened since startup, and
+// tell the menus and the frontend
+fn load_recent_files(app: &AppHandle) {
+  let started = Instant::now();
+  let saved = load_recent_files_from_store(app);
+  let state = app.state::<RecentFilesState>();
+  let mut recents = state.0.lock().unwrap();
+  let opened = std::mem::replace(&mut *recents, saved);
+  for path in opened.iter().rev() {
+    insert_recent(&mut recents, path);
+  }
+  app.state::<RecentFilesLoaded>().0.send_replace(true);
+  if opened.is_empty() {
+    #[cfg(target_os = "macos")]
+    macos::refresh_dock_menu(app, &recents);
+    let _ = app.emit(RECENTS_CHANGED_EVENT, &*recents);
+  } else {
+    // Saving the files opened meanwhile replaced the saved list
+    save_recent_files_to_store(app, &recents);
+  }
+  log::debug!(
+    "Loaded {} recent files in {:?}",
+    recents.len(),
+    started.elapsed()
+  );
+}
 
-  // Also emit event for frontend
-  let _ = app.emit(DOCK_OPEN_FILE_EVENT, pending.clone().unwrap());
+// Load the settings (the defaults in safe mode) and set the menu, with their shortcuts
+fn init_settings_and_menu(app: &AppHandle, startup: &Startup) -> tauri::Result<()> {
+  let settings = if startup.settings {
+    settings::load_settings(app)
+  } else {
+    settings::Settings::default()
+  };
+  app.manage(menu_toggles::MenuTogglesState::default());
+  let menu = create_app_menu(app, &settings.shortcuts)?;
+  app.set_menu(menu)?;
+  menu_toggles::sync(app, &settings);
+  if let Err(e) = window_menu::update_menu(app, None) {
+    log::error!("Failed to update the Window menu: {}", e);
+  }
+  app.manage(SettingsState(Mutex::new(settings)));
   Ok(())
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-  tauri::Builder::default()
+  let launch = launch::current_launch_args();
+  if launch.help {
+    print!("{}", launch::USAGE);
+    return;
+  }
+  let safe_mode = safe_mode::requested(launch.safe_mode);
+
+  let builder = tauri::Builder::default();
+  // Must be registered first: a second launch hands its argv to this instance and exits
+  #[cfg(desktop)]
+  let builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+    launch::handle_secondary_launch(app, argv, cwd);
+  }));
+  // The updater needs the release signing key from `plugins.updater` and refuses to start
+  // without it, so builds that don't configure one just go without updates
+  let context = tauri::generate_context!();
+  let updates_enabled = cfg!(desktop) && context.config().plugins.0.contains_key("updater");
+  #[cfg(desktop)]
+  let builder = if updates_enabled {
+    builder.plugin(tauri_plugin_updater::Builder::new().build())
+  } else {
+    builder
+  };
+  builder
+    .plugin(logging::plugin())
+    .plugin(tauri_plugin_opener::init())
     .plugin(tauri_plugin_fs::init())
     .plugin(tauri_plugin_dialog::init())
     .plugin(tauri_plugin_store::Builder::default().build())
     .plugin(tauri_plugin_deep_link::init())
-    .setup(|app| {
-      // Create and set the menu
-      let menu = create_app_menu(app.handle())?;
-      app.set_menu(menu)?;
-      // Load recent files from persistent store
-      let recent_files = load_recent_files_from_store(app.handle());
-      app.manage(RecentFilesState(Mutex::new(recent_files)));
-      app.manage(PendingFileState(Mutex::new(None)));
+    .register_uri_scheme_protocol(assets::ASSET_SCHEME, assets::handle_asset_request)
+    .setup(move |app| {
+      logging::init();
+      let setup_started = Instant::now();
+      log::info!(
+        "Starting Markdowner {} on {} {}",
+        app.package_info().version,
+        std::env::consts::OS,
+        std::env::consts::ARCH
+      );
+      let startup = Startup::new(safe_mode);
+      if startup.safe_mode() {
+        log::warn!("Starting in safe mode");
+      }
+      app.manage(safe_mode::StartupState(startup));
+      app.manage(app_store::PersistenceState::default());
+      init_store(app.handle(), &startup);
+      // Files given on the command line open first, through the main window's
+      // get_pending_file
+      let launch_files = launch::openable_files(&launch.files);
+      let launch_paths: Vec<&Path> = launch_files
+        .iter()
+        .map(|open| Path::new(&open.path))
+        .collect();
+      access_scope::init(app.handle(), &launch_paths);
+      app.manage(PendingFileState(
+        Mutex::new(launch::PendingFiles::default()),
+      ));
+      launch::queue_pending_files(
+        app.handle(),
+        launch::MAIN_WINDOW_LABEL,
+        &launch_files,
+        false,
+      );
+      app.manage(launch::LaunchOptionsState(launch::LaunchOptions {
+        new_document: launch.new_document,
+      }));
+      // Bounded by the stdin timeout, so a pipe that never closes only delays the window
+      let piped = launch
+        .stdin
+        .then(|| stdin::read_piped_input(std::io::stdin(), stdin::STDIN_TIMEOUT));
+      app.manage(stdin::PendingContentState(Mutex::new(piped)));
+      #[cfg(target_os = "macos")]
+      services::init(app.handle());
+      app.manage(WriteCoordinator::default());
+      app.manage(close_guard::CloseGuardState::default());
+      app.manage(recently_closed::RecentlyClosedState::default());
+      app.manage(wiki::WikiIndexState::default());
+      app.manage(workspace::WorkspaceWatcherState::default());
+      app.manage(tags::TagIndexState::default());
+      app.manage(file_finder::FileIndexState::default());
+      app.manage(workspace_stats::WorkspaceStatsState::default());
+      app.manage(open_with::ExternalEditState::default());
+      app.manage(speech::SpeechState::default());
+      app.manage(styles::StyleCacheState::default());
+      app.manage(task_registry::TaskRegistry::default());
+      app.manage(session::TabSessionState::default());
+      init_settings_and_menu(app.handle(), &startup)?;
+      recent_workspaces::init(app.handle(), &startup);
+      app.manage(assets::AssetScopeState(Mutex::new(
+        assets::AssetScope::default(),
+      )));
+      app.manage(updates::UpdaterState::new(updates_enabled));
+      updates::spawn_automatic_check(app.handle());
+      #[cfg(desktop)]
+      tray::init(app.handle());
 
       // Handle files opened via file association (clicking on .md files)
       // This uses the deep-link plugin which is more reliable than tauri://file-open
       {
         let app_handle = app.handle().clone();
 
-        println!("Setting up deep-link handler for file associations");
-
-        // Get any pending files (when app was opened with a file)
-        if let Ok(Some(pending_urls)) = app.deep_link().get_current() {
-          if !pending_urls.is_empty() {
-            for url in &pending_urls {
-              let url_str = url.to_string();
-              println!("App was opened with deep link/URL: {}", url_str);
-
-              // Parse file:// URL to get the path
-              if url_str.starts_with("file://") {
-                let path = file_url_to_path(&url_str).unwrap_or_else(|| {
-                  println!("Failed to parse file URL: {}", url_str);
-                  String::new()
-                });
-                if path.is_empty() {
-                  continue;
-                }
-                println!("Extracted path from deep link: {}", path);
-
-                // Store in pending state
-                if let Some(pending_state) = app_handle.try_state::<PendingFileState>() {
-                  let mut pending = pending_state.0.lock().unwrap();
-                  *pending = Some(path.clone());
-                  println!("Stored in pending state from deep link: {}", path);
+        log::debug!("Setting up deep-link handler for file associations");
+
+        // Get any pending files (when app was opened with a file). Done after setup so it
+        // doesn't hold up the window; the file is queued for get_pending_file and sent
+        // with dock-open-file in case the window asked already.
+        let deferred_app = app_handle.clone();
+        tauri::async_runtime::spawn_blocking(move || {
+          if let Ok(Some(pending_urls)) = deferred_app.deep_link().get_current() {
+            if !pending_urls.is_empty() {
+              for url in &pending_urls {
+                let url_str = url.to_string();
+                log::debug!("App was opened with deep link/URL: {}", url_str);
+
+                // Parse file:// URL to get the path
+                if url_str.starts_with("file://") {
+                  let Some(open) = launch::pending_open_from_url(&url_str) else {
+                    log::warn!("Failed to parse file URL: {}", url_str);
+                    continue;
+                  };
+                  log::debug!("Extracted path from deep link: {:?}", open);
+
+                  // Store in the main window's pending state, and tell it in case it has
+                  // loaded already
+                  access_scope::allow_documents(&deferred_app, &[Path::new(&open.path)]);
+                  launch::queue_pending_files(
+                    &deferred_app,
+                    launch::MAIN_WINDOW_LABEL,
+                    std::slice::from_ref(&open),
+                    true,
+                  );
+                  log::debug!("Stored in pending state from deep link: {}", open.path);
+                  // Only process the first file for now
+                  break;
                 }
-
-                // Also emit event for when app is already running
-                let _ = app_handle.emit(DOCK_OPEN_FILE_EVENT, path);
-                // Only process the first file for now
-                break;
               }
+            } else {
+              log::debug!("No deep link/URL available at startup");
             }
           } else {
-            println!("No deep link/URL available at startup");
+            log::debug!("No deep link/URL available at startup");
           }
-        } else {
-          println!("No deep link/URL available at startup");
-        }
+        });
 
         // Listen for deep link events (when app is already running and user clicks a file)
         let _ = app.deep_link().on_open_url(move |event| {
           let urls = event.urls();
-          println!("Received deep link event with {} URLs", urls.len());
+          log::debug!("Received deep link event with {} URLs", urls.len());
 
           for url in urls {
             let url_str = url.to_string();
-            println!("Processing URL: {}", url_str);
+            log::debug!("Processing URL: {}", url_str);
 
             if url_str.starts_with("file://") {
-              let path = file_url_to_path(&url_str).unwrap_or_else(|| {
-                println!("Failed to parse file URL: {}", url_str);
-                String::new()
-              });
-              if path.is_empty() {
+              let Some(open) = launch::pending_open_from_url(&url_str) else {
+                log::warn!("Failed to parse file URL: {}", url_str);
                 continue;
-              }
-              println!("Extracted path from URL: {}", path);
-
-              // Store in pending state
-              if let Some(pending_state) = app_handle.try_state::<PendingFileState>() {
-                let mut pending = pending_state.0.lock().unwrap();
-                *pending = Some(path.clone());
-                println!("Stored in pending state: {}", path);
-              }
+              };
+              log::debug!("Extracted path from URL: {:?}", open);
 
-              // Emit event to frontend
-              let _ = app_handle.emit(DOCK_OPEN_FILE_EVENT, path);
+              // Into the pending state of the window the open_files_in setting picks
+              log::debug!("Routing to a window: {}", open.path);
+              launch::open_files(&app_handle, &[open]);
               // Only process the first file for now
               break;
             }
@@ -545,24 +2002,155 @@ pub fn run() {
         });
       }
 
+      log::debug!("Setup finished in {:?}", setup_started.elapsed());
       Ok(())
     })
+    .on_window_event(|window, event| {
+      access_scope::handle_window_event(window, event);
+      close_guard::handle_window_event(window, event);
+      window_menu::handle_window_event(window, event);
+      launch::handle_window_event(window, event);
+    })
     .on_menu_event(|app_handle, event| {
       handle_menu_event(app_handle, &event.id().0);
     })
     .invoke_handler(tauri::generate_handler![
       read_file,
+      open_document,
+      make_writable,
       write_file,
       open_file_dialog,
+      open_files_dialog,
       save_file_dialog,
+      relative_links::rewrite_links_for_save_as,
+      access_scope::request_scope,
       get_recent_files,
       add_to_recents,
+      remove_from_recents,
+      update_recent_path,
+      relocate::relocate_recent,
       clear_recent_files,
+      frequent::get_frequent_files,
+      frequent::clear_frequent_files,
       get_pending_file,
-      set_pending_file
+      stdin::get_pending_content,
+      set_pending_file,
+      take_store_recovery,
+      app_store::persistence_status,
+      launch::get_launch_options,
+      help::get_app_info,
+      logging::get_recent_logs,
+      logging::set_log_level,
+      logging::open_log_folder,
+      updates::check_for_updates,
+      updates::install_update,
+      view_state::save_file_view_state,
+      view_state::get_file_view_state,
+      secrets::set_secret,
+      secrets::get_secret,
+      secrets::delete_secret,
+      gist::publish_gist,
+      gist::delete_gist,
+      link_check::check_external_links,
+      link_title::fetch_link_title,
+      urls::normalize_url,
+      urls::is_probable_url,
+      tasks::task_stats,
+      tasks::toggle_task,
+      tasks::archive_completed_tasks,
+      task_registry::list_tasks,
+      task_registry::cancel_task,
+      file_finder::fuzzy_find_files,
+      transform::edit_transform,
+      typography::smarten_typography,
+      typography::straighten_typography,
+      case::transform_text,
+      headings::shift_headings,
+      sections::extract_section,
+      sections::replace_section,
+      references::tidy_references,
+      open_with::list_applications_for,
+      open_with::open_with_default,
+      open_with::open_with,
+      terminal::open_terminal_at,
+      import::import_document,
+      inbox::append_to_inbox,
+      inbox::list_inbox_files,
+      inbox::get_inbox_path,
+      speech::speak_text,
+      speech::stop_speaking,
+      speech::list_voices,
+      settings::get_settings,
+      settings::update_settings,
+      fonts::list_system_fonts,
+      close_guard::set_dirty,
+      close_guard::confirm_close_response,
+      clipboard::new_from_clipboard,
+      document_window::set_window_document,
+      diff::diff_text,
+      diff::diff_files,
+      document_reference::copy_document_reference,
+      drafts::save_draft,
+      drafts::list_drafts,
+      drafts::read_draft,
+      drafts::discard_draft,
+      session::save_tab_session,
+      session::get_tab_session,
+      session::clear_session,
+      bookmarks::add_bookmark,
+      bookmarks::list_bookmarks,
+      bookmarks::remove_bookmark,
+      bookmarks::list_all_bookmarks,
+      recently_closed::record_closed_document,
+      recently_closed::get_recently_closed,
+      recently_closed::reopen_closed,
+      export::export_html,
+      export::export_print_html,
+      export::print_section,
+      export_defaults::get_export_defaults,
+      styles::get_effective_styles,
+      slides::export_slides,
+      latex::export_latex,
+      plain_text::export_plain_text,
+      plain_text::export_plain_text_to_file,
+      plain_text::copy_as_plain_text,
+      email::copy_for_email,
+      split::split_by_heading,
+      duplicates::hash_file,
+      duplicates::find_duplicate_files,
+      workspace_stats::workspace_stats,
+      batch_rename::batch_rename,
+      includes::resolve_includes,
+      bundle::export_bundle,
+      app_data::export_app_data,
+      app_data::import_app_data,
+      assets::allow_document_assets,
+      assets::revoke_document_assets,
+      assets::resolve_asset_url,
+      wiki::resolve_wiki_link,
+      wiki::create_note_for_link,
+      workspace::watch_workspace,
+      workspace::close_workspace,
+      recent_workspaces::get_recent_workspaces,
+      recent_workspaces::remove_recent_workspace,
+      recent_workspaces::get_startup_workspace,
+      workspace_import::import_file_into_workspace,
+      writing_history::get_writing_history,
+      writing_history::get_writing_history_all,
+      writing_history::clear_writing_history,
+      shortcuts::get_shortcuts,
+      shortcuts::set_shortcut,
+      shortcuts::reset_shortcuts,
+      safe_mode::safe_mode_active,
+      safe_mode::reset_settings,
+      safe_mode::reset_session,
+      tags::index_tags,
+      tags::list_tags,
+      tags::files_with_tag
     ])
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(context)
+    .expect("error while building tauri application")
+    .run(|app_handle, event| handle_run_event(app_handle, &event));
 }
 
 #[cfg(test)]
@@ -598,17 +2186,48 @@ mod tests {
     let path = PathBuf::from("test.md");
     let result = validate_file_path(&path);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("absolute"));
+    assert!(result.unwrap_err().to_string().contains("absolute"));
   }
 
   #[test]
   fn test_validate_file_path_nonexistent() {
-    // Note: canonicalize() fails for non-existent files
-    // This is expected behavior - the path validation will fail
-    let path = PathBuf::from("/tmp/nonexistent_file_12345.md");
-    let result = validate_file_path(&path);
-    // canonicalize will fail for non-existent paths
-    assert!(result.is_err());
+    // A Save As target that doesn't exist yet, in a folder that doesn't either
+    let dir = TempDir::new().unwrap();
+    let path = dir
+      .path()
+      .join("new")
+      .join("..")
+      .join("drafts")
+      .join("note.md");
+    let metadata = validate_file_path(&path).unwrap();
+
+    assert!(!metadata.exists);
+    assert!(!metadata.is_file);
+    assert!(metadata.parent_writable);
+    assert_eq!(
+      metadata.normalized_path,
+      dir
+        .path()
+        .canonicalize()
+        .unwrap()
+        .join("drafts")
+        .join("note.md")
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_validate_file_path_resolves_symlinked_folder() {
+    let dir = TempDir::new().unwrap();
+    let real = dir.path().join("real");
+    fs::create_dir(&real).unwrap();
+    std::os::unix::fs::symlink(&real, dir.path().join("link")).unwrap();
+
+    let metadata = validate_file_path(&dir.path().join("link").join("note.md")).unwrap();
+    assert_eq!(
+      metadata.normalized_path,
+      real.canonicalize().unwrap().join("note.md")
+    );
   }
 
   #[test]
@@ -653,14 +2272,104 @@ mod tests {
     let path = PathBuf::from("test.md");
     let result = validate_file_path(&path);
     assert!(result.is_err());
-    assert!(result.unwrap_err().contains("absolute"));
+    assert!(result.unwrap_err().to_string().contains("absolute"));
   }
 
   #[test]
   fn test_write_file_parent_validation() {
-    let path = PathBuf::from("/nonexistent/directory/test.md");
-    if let Some(parent) = path.parent() {
-      assert!(!parent.exists());
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("nonexistent").join("test.md");
+    let result = write_text_file(&path, "content", None, false);
+    assert_eq!(result.unwrap_err().code(), "not_found");
+  }
+
+  #[test]
+  fn test_durable_write_replaces_file() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("notes.md");
+    fs::write(&path, "old").unwrap();
+
+    write_text_file(&path, "# Saved", None, true).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "# Saved");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+  }
+
+  #[test]
+  fn test_write_returns_the_mtime_for_the_next_save() {
+    let dir = TempDir::new().unwrap();
+    let path = create_test_file(dir.path(), "notes.md", "old");
+    let opened = file_access(&path).unwrap().mtime;
+    assert!(opened.is_some());
+
+    let saved = write_text_file(&path, "first", opened, false).unwrap();
+    assert_eq!(saved, disk_mtime(&path));
+    write_text_file(&path, "second", saved, false).unwrap();
+
+    let error = write_text_file(&path, "stale", Some(0), false).unwrap_err();
+    assert_eq!(error.code(), "conflict");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_read_only_file_is_refused_until_made_writable() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::new().unwrap();
+    let path = create_test_file(dir.path(), "locked.md", "old");
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o444)).unwrap();
+    assert!(!validate_file_path(&path).unwrap().writable);
+
+    let error = write_text_file(&path, "new", None, false).unwrap_err();
+    assert_eq!(error.code(), "read_only");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    clear_read_only(&path).unwrap();
+    assert!(validate_file_path(&path).unwrap().writable);
+    write_text_file(&path, "new", None, false).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+  }
+
+  #[test]
+  fn test_read_missing_file_is_not_found() {
+    let dir = TempDir::new().unwrap();
+    let result = read_text_file(&dir.path().join("missing.md"));
+    assert_eq!(result.unwrap_err().code(), "not_found");
+  }
+
+  #[test]
+  fn test_read_binary_file_reports_mime() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("image.md");
+    fs::write(&path, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+
+    let error = read_text_file(&path).unwrap_err();
+    assert_eq!(error.code(), "binary_file");
+    assert!(error.to_string().starts_with("This looks like a PNG image"));
+  }
+
+  #[test]
+  fn test_read_directory_is_not_a_file() {
+    let dir = TempDir::new().unwrap();
+    let result = read_text_file(dir.path());
+    assert_eq!(result.unwrap_err().code(), "not_a_file");
+  }
+
+  #[test]
+  fn test_read_oversized_file_is_too_large() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("huge.md");
+    fs::File::create(&path)
+      .unwrap()
+      .set_len(MAX_FILE_SIZE + 1)
+      .unwrap();
+
+    match read_text_file(&path).unwrap_err() {
+      CommandError::TooLarge { limit, actual } => {
+        assert_eq!(limit, MAX_FILE_SIZE);
+        assert_eq!(actual, MAX_FILE_SIZE + 1);
+      }
+      other => panic!("expected too_large, got {:?}", other),
     }
   }
 
@@ -746,6 +2455,110 @@ mod tests {
     assert_eq!(recents[1], file2);
   }
 
+  #[test]
+  fn test_replace_recent_keeps_the_entry_in_place() {
+    let dir = TempDir::new().unwrap();
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+    let mut recents = vec![path("a.md"), path("old.md"), path("b.md"), path("new.md")];
+    assert!(replace_recent(
+      &mut recents,
+      &path("old.md"),
+      &path("new.md")
+    ));
+    assert_eq!(recents, vec![path("a.md"), path("new.md"), path("b.md")]);
+    assert!(!replace_recent(
+      &mut recents,
+      &path("old.md"),
+      &path("c.md")
+    ));
+  }
+
+  #[test]
+  fn test_check_paths_exist_flags_missing_files() {
+    let dir = TempDir::new().unwrap();
+    let existing = create_test_file(dir.path(), "exists.md", "content");
+    let paths = vec![
+      existing.to_string_lossy().to_string(),
+      dir.path().join("missing.md").to_string_lossy().to_string(),
+    ];
+
+    let result = check_paths_exist(&paths, Duration::from_secs(5));
+    assert_eq!(result, vec![true, false]);
+  }
+
+  #[test]
+  fn test_check_paths_exist_empty() {
+    assert!(check_paths_exist(&[], Duration::from_millis(10)).is_empty());
+  }
+
+  #[test]
+  fn test_insert_recent_collapses_trailing_slash_and_dot_segments() {
+    let dir = TempDir::new().unwrap();
+    let file = create_test_file(dir.path(), "note.md", "content");
+    let base = file.to_string_lossy().to_string();
+
+    let mut recents = Vec::new();
+    insert_recent(&mut recents, &base);
+    insert_recent(&mut recents, &format!("{}/", base));
+    let dotted = dir.path().join(".").join("sub").join("..").join("note.md");
+    insert_recent(&mut recents, &dotted.to_string_lossy());
+
+    assert_eq!(recents.len(), 1);
+  }
+
+  #[test]
+  fn test_insert_recent_missing_file_falls_back_to_lexical() {
+    let mut recents = Vec::new();
+    insert_recent(&mut recents, "/unmounted/share/./notes/../notes/todo.md");
+    insert_recent(&mut recents, "/unmounted/share/notes/todo.md/");
+
+    assert_eq!(
+      recents,
+      vec![PathBuf::from("/unmounted/share/notes/todo.md")
+        .to_string_lossy()
+        .to_string()]
+    );
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_insert_recent_resolves_symlinks() {
+    let dir = TempDir::new().unwrap();
+    let file = create_test_file(dir.path(), "real.md", "content");
+    let link = dir.path().join("link.md");
+    std::os::unix::fs::symlink(&file, &link).unwrap();
+
+    let mut recents = Vec::new();
+    insert_recent(&mut recents, &file.to_string_lossy());
+    insert_recent(&mut recents, &link.to_string_lossy());
+
+    assert_eq!(recents.len(), 1);
+    assert!(recents[0].ends_with("real.md"));
+  }
+
+  #[test]
+  fn test_recent_paths_equal_case_handling() {
+    assert!(recent_paths_equal("/Users/me/Todo.md", "/Users/me/Todo.md"));
+    let case_insensitive = cfg!(any(target_os = "macos", target_os = "windows"));
+    assert_eq!(
+      recent_paths_equal("/Users/me/Notes/Todo.md", "/users/me/notes/todo.md"),
+      case_insensitive
+    );
+  }
+
+  #[test]
+  fn test_display_recent_path_abbreviates_home() {
+    let home = PathBuf::from("/home/me");
+    let expected = format!("~{}notes/todo.md", std::path::MAIN_SEPARATOR);
+    assert_eq!(
+      display_recent_path("/home/me/notes/todo.md", Some(&home)),
+      expected
+    );
+    assert_eq!(display_recent_path("/home/me", Some(&home)), "~");
+    assert_eq!(display_recent_path("/tmp/a.md", Some(&home)), "/tmp/a.md");
+    assert_eq!(display_recent_path("/tmp/a.md", None), "/tmp/a.md");
+  }
+
   #[test]
   fn test_recent_files_deduplication() {
     let state = RecentFilesState(Mutex::new(Vec::new()));
@@ -770,4 +2583,89 @@ mod tests {
     let recents = state.0.lock().unwrap();
     assert_eq!(recents.len(), 1);
   }
+
+  #[test]
+  fn test_file_url_to_path_decoding() {
+    let cases: &[(&str, Option<&str>)] = &[
+      ("file:///tmp/a%20b.md", Some("/tmp/a b.md")),
+      ("file:///tmp/My%2BNotes.md", Some("/tmp/My+Notes.md")),
+      ("file:///tmp/My+Notes.md", Some("/tmp/My+Notes.md")),
+      ("file:///tmp/caf%C3%A9.md", Some("/tmp/café.md")),
+      ("file:///tmp/100%.md", Some("/tmp/100%.md")),
+      ("file:///tmp/50%zz%2.md", Some("/tmp/50%zz%2.md")),
+      ("file://localhost/tmp/notes.md", Some("/tmp/notes.md")),
+      ("https://example.com/notes.md", None),
+      ("/tmp/notes.md", None),
+    ];
+    for (url, expected) in cases {
+      assert_eq!(
+        file_url_to_path(url),
+        expected.map(PathBuf::from),
+        "decoding {}",
+        url
+      );
+    }
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_file_url_to_path_keeps_non_utf8_names() {
+    use std::os::unix::ffi::OsStrExt;
+    // `café.md` percent-encoded as Latin-1
+    let path = file_url_to_path("file:///tmp/caf%E9.md").unwrap();
+    assert_eq!(path.as_os_str().as_bytes(), b"/tmp/caf\xe9.md");
+    assert_eq!(launch::pending_open_from_url("file:///tmp/caf%E9.md"), None);
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_file_urls_round_trip() {
+    use std::os::unix::ffi::OsStrExt;
+    let paths: &[&[u8]] = &[
+      b"/tmp/notes.md",
+      b"/tmp/My Notes (draft) #2.md",
+      b"/tmp/100%+50%?.md",
+      "/tmp/café/日記.md".as_bytes(),
+      b"/tmp/caf\xe9.md",
+    ];
+    for bytes in paths {
+      let path = Path::new(std::ffi::OsStr::from_bytes(bytes));
+      let url = path_to_file_url(path);
+      assert!(
+        url.is_ascii() && !url.contains([' ', '(', '#', '?']),
+        "{}",
+        url
+      );
+      assert_eq!(file_url_to_path(&url).as_deref(), Some(path), "{}", url);
+    }
+    assert_eq!(
+      path_to_file_url(Path::new("/tmp/a b.md")),
+      "file:///tmp/a%20b.md"
+    );
+  }
+
+  #[test]
+  fn test_dialog_results_become_paths() {
+    let picked = FilePath::Path(PathBuf::from("/notes/a.md"));
+    assert_eq!(
+      dialog_result_to_path(picked).unwrap(),
+      PathBuf::from("/notes/a.md")
+    );
+
+    let picked = FilePath::Url(url::Url::parse("file:///notes/my%20note.md").unwrap());
+    assert_eq!(
+      dialog_result_to_path(picked).unwrap(),
+      PathBuf::from("/notes/my note.md")
+    );
+
+    let uri = "content://com.android.providers.downloads/document/42";
+    let error = dialog_result_to_path(FilePath::Url(url::Url::parse(uri).unwrap())).unwrap_err();
+    assert_eq!(error.code(), "unsupported_location");
+    assert_eq!(
+      error,
+      CommandError::UnsupportedLocation {
+        uri: uri.to_string()
+      }
+    );
+  }
 }
@@ -0,0 +1,351 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::{Mutex, Semaphore};
+
+use crate::error::{CommandError, CommandResult};
+use crate::http;
+use crate::task_registry;
+
+// Sent to the checking window for every URL as its result comes in
+pub const LINK_CHECK_PROGRESS_EVENT: &str = "link-check-progress";
+
+// Upper bound on `concurrency`, whatever the caller asks for
+const MAX_CONCURRENCY: usize = 32;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LinkCheckOptions {
+  // Requests in flight at once, across all hosts
+  pub concurrency: usize,
+  pub timeout_ms: u64,
+  pub follow_redirects: bool,
+  pub max_redirects: usize,
+  // Minimum time between two requests to the same host
+  pub per_host_delay_ms: u64,
+}
+
+impl Default for LinkCheckOptions {
+  fn default() -> Self {
+    LinkCheckOptions {
+      concurrency: 8,
+      timeout_ms: 10_000,
+      follow_redirects: true,
+      max_redirects: 5,
+      per_host_delay_ms: 250,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LinkCheckResult {
+  pub url: String,
+  // None when there was no response at all (see `error`)
+  pub status: Option<u16>,
+  // Where redirects ended up; the same as `url` without any
+  pub final_url: Option<String>,
+  pub latency_ms: u64,
+  // Answered with a success status (or a redirect, when they're not followed)
+  pub ok: bool,
+  pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkCheckProgress {
+  pub result: LinkCheckResult,
+  pub checked: usize,
+  pub total: usize,
+}
+
+// Characters that end a bare URL in running text
+fn ends_bare_url(c: char) -> bool {
+  c.is_whitespace() || matches!(c, '<' | '>' | '"' | '`')
+}
+
+// http(s) URLs written out in text, without trailing punctuation that belongs to the
+// sentence (or a closing parenthesis the URL didn't open)
+fn bare_urls(text: &str) -> Vec<String> {
+  let mut urls = Vec::new();
+  let mut rest = text;
+  while let Some(start) = rest.find("http") {
+    let candidate = &rest[start..];
+    let preceded_by_word = rest[..start]
+      .chars()
+      .next_back()
+      .is_some_and(|c| c.is_alphanumeric());
+    let end = candidate.find(ends_bare_url).unwrap_or(candidate.len());
+    let mut url = &candidate[..end];
+    loop {
+      let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'']);
+      let trimmed = match trimmed.strip_suffix(')') {
+        Some(inner) if trimmed.matches(')').count() > trimmed.matches('(').count() => inner,
+        _ => trimmed,
+      };
+      if trimmed.len() == url.len() {
+        break;
+      }
+      url = trimmed;
+    }
+    if !preceded_by_word && is_checkable(url) {
+      urls.push(url.to_string());
+    }
+    rest = &candidate[end..];
+  }
+  urls
+}
+
+// Only web links get checked; mailto:, file:, anchors and relative links are local matters
+fn is_checkable(url: &str) -> bool {
+  let lower = url.to_ascii_lowercase();
+  (lower.starts_with("http://") || lower.starts_with("https://"))
+    && reqwest::Url::parse(url).is_ok_and(|parsed| parsed.host_str().is_some())
+}
+
+// Every http(s) URL in a document in order of appearance, each once: link and image
+// destinations, autolinks and bare URLs in text. Code is skipped.
+fn extract_urls(content: &str) -> Vec<String> {
+  let mut urls: Vec<String> = Vec::new();
+  let mut in_code_block = false;
+  for event in Parser::new_ext(content, Options::all()) {
+    let found = match event {
+      Event::Start(Tag::Link { dest_url, .. }) | Event::Start(Tag::Image { dest_url, .. }) => {
+        vec![dest_url.to_string()]
+      }
+      Event::Start(Tag::CodeBlock(_)) => {
+        in_code_block = true;
+        continue;
+      }
+      Event::End(TagEnd::CodeBlock) => {
+        in_code_block = false;
+        continue;
+      }
+      Event::Text(text) if !in_code_block => bare_urls(&text),
+      _ => continue,
+    };
+    for url in found {
+      if is_checkable(&url) && !urls.contains(&url) {
+        urls.push(url);
+      }
+    }
+  }
+  urls
+}
+
+// Spaces out requests to each host so a document full of links to one site doesn't send
+// them all at once
+#[derive(Default)]
+struct HostPacer {
+  last_request: std::sync::Mutex<HashMap<String, Arc<Mutex<Option<Instant>>>>>,
+}
+
+impl HostPacer {
+  async fn wait_turn(&self, host: &str, delay: Duration) {
+    let slot = {
+      let mut hosts = self.last_request.lock().unwrap();
+      Arc::clone(hosts.entry(host.to_string()).or_default())
+    };
+    // Waiters queue up on the host's lock in order
+    let mut last = slot.lock().await;
+    if let Some(last) = *last {
+      let elapsed = last.elapsed();
+      if elapsed < delay {
+        tokio::time::sleep(delay - elapsed).await;
+      }
+    }
+    *last = Some(Instant::now());
+  }
+}
+
+// Servers that reject HEAD (or answer it wrongly) get a GET for the first byte instead
+fn retry_with_get(status: reqwest::StatusCode) -> bool {
+  // A rate-limited host wouldn't answer a GET either
+  status != reqwest::StatusCode::TOO_MANY_REQUESTS
+    && (status.is_client_error() || status.is_server_error())
+}
+
+async fn check_url(client: &reqwest::Client, url: &str, follow_redirects: bool) -> LinkCheckResult {
+  let started = Instant::now();
+  let mut response = client.head(url).send().await;
+  if let Ok(head) = &response {
+    if retry_with_get(head.status()) {
+      response = client
+        .get(url)
+        .header(reqwest::header::RANGE, "bytes=0-0")
+        .send()
+        .await;
+    }
+  }
+  let latency_ms = started.elapsed().as_millis() as u64;
+  match response {
+    Ok(response) => {
+      let status = response.status();
+      LinkCheckResult {
+        url: url.to_string(),
+        status: Some(status.as_u16()),
+        final_url: Some(response.url().to_string()),
+        latency_ms,
+        ok: status.is_success() || (!follow_redirects && status.is_redirection()),
+        error: None,
+      }
+    }
+    Err(e) => LinkCheckResult {
+      url: url.to_string(),
+      status: None,
+      final_url: None,
+      latency_ms,
+      ok: false,
+      error: Some(http::request_error("Request failed", e).to_string()),
+    },
+  }
+}
+
+// Check every web link in `content`. Results are sent to the calling window as they arrive
+// (LINK_CHECK_PROGRESS_EVENT) and returned together, in document order, at the end. Runs as
+// a registered task (see task_registry); once cancelled, links not yet requested are
+// skipped and the command fails with Cancelled.
+#[tauri::command]
+pub async fn check_external_links(
+  app: AppHandle,
+  window: tauri::Window,
+  content: String,
+  options: Option<LinkCheckOptions>,
+  task_id: Option<String>,
+) -> CommandResult<Vec<LinkCheckResult>> {
+  let guard = task_registry::start_task(&app, task_id, "check_external_links", "Checking links")?;
+  let task = guard.task();
+  let result = check_links(app, window, content, options.unwrap_or_default(), task).await;
+  guard.finish(result)
+}
+
+async fn check_links(
+  app: AppHandle,
+  window: tauri::Window,
+  content: String,
+  options: LinkCheckOptions,
+  task: task_registry::Task,
+) -> CommandResult<Vec<LinkCheckResult>> {
+  let redirect = if options.follow_redirects {
+    reqwest::redirect::Policy::limited(options.max_redirects)
+  } else {
+    reqwest::redirect::Policy::none()
+  };
+  let client = http::builder(Duration::from_millis(options.timeout_ms.max(1)))
+    .redirect(redirect)
+    .build()
+    .map_err(|e| CommandError::io("Failed to set up HTTP client", e))?;
+
+  let urls = extract_urls(&content);
+  let total = urls.len();
+  let permits = Arc::new(Semaphore::new(
+    options.concurrency.clamp(1, MAX_CONCURRENCY),
+  ));
+  let pacer = Arc::new(HostPacer::default());
+  let checked = Arc::new(AtomicUsize::new(0));
+  let delay = Duration::from_millis(options.per_host_delay_ms);
+
+  let handles: Vec<_> = urls
+    .into_iter()
+    .map(|url| {
+      let (client, permits, pacer, checked, task) = (
+        client.clone(),
+        Arc::clone(&permits),
+        Arc::clone(&pacer),
+        Arc::clone(&checked),
+        task.clone(),
+      );
+      let (app, label) = (app.clone(), window.label().to_string());
+      let follow_redirects = options.follow_redirects;
+      tauri::async_runtime::spawn(async move {
+        let host = reqwest::Url::parse(&url)
+          .ok()
+          .and_then(|parsed| parsed.host_str().map(str::to_string))
+          .unwrap_or_default();
+        pacer.wait_turn(&host, delay).await;
+        let result = {
+          let _permit = permits.acquire().await;
+          if task.is_cancelled() {
+            return None;
+          }
+          check_url(&client, &url, follow_redirects).await
+        };
+        let checked = checked.fetch_add(1, Ordering::SeqCst) + 1;
+        let progress = LinkCheckProgress {
+          result: result.clone(),
+          checked,
+          total,
+        };
+        let _ = app.emit_to(label.as_str(), LINK_CHECK_PROGRESS_EVENT, progress);
+        task.progress(checked, total);
+        Some(result)
+      })
+    })
+    .collect();
+
+  let mut results = Vec::with_capacity(total);
+  for handle in handles {
+    match handle.await {
+      Ok(Some(result)) => results.push(result),
+      Ok(None) => {}
+      Err(e) => log::error!("Link check task failed: {}", e),
+    }
+  }
+  task.check()?;
+  Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_extract_urls_skips_local_links_and_code() {
+    let content = "\
+[site](https://example.com/a) and [again](https://example.com/a)
+![img](http://example.com/i.png) [mail](mailto:me@example.com) [anchor](#top)
+[file](file:///etc/hosts) [doc](notes/other.md) <https://auto.example.com>
+
+See https://bare.example.com/path. Or (https://paren.example.com/x).
+
+```
+https://in-code.example.com
+```
+";
+    assert_eq!(
+      extract_urls(content),
+      vec![
+        "https://example.com/a",
+        "http://example.com/i.png",
+        "https://auto.example.com",
+        "https://bare.example.com/path",
+        "https://paren.example.com/x",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_bare_urls_keep_balanced_parentheses() {
+    assert_eq!(
+      bare_urls("see https://en.wikipedia.org/wiki/Rust_(language), ok"),
+      vec!["https://en.wikipedia.org/wiki/Rust_(language)"]
+    );
+    assert!(bare_urls("nothttps://example.com").is_empty());
+  }
+
+  #[test]
+  fn test_pacer_spaces_requests_to_one_host() {
+    let pacer = HostPacer::default();
+    let delay = Duration::from_millis(100);
+    tauri::async_runtime::block_on(async {
+      let started = Instant::now();
+      pacer.wait_turn("example.com", delay).await;
+      pacer.wait_turn("other.example.com", delay).await;
+      assert!(started.elapsed() < delay);
+      pacer.wait_turn("example.com", delay).await;
+      assert!(started.elapsed() >= delay);
+    });
+  }
+}
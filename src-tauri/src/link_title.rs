@@ -0,0 +1,209 @@
+use std::time::Duration;
+
+use crate::error::{CommandError, CommandResult};
+use crate::http;
+
+// Only the start of a page is read; <title> and og:title live in <head>
+const MAX_TITLE_BYTES: usize = 64 * 1024;
+const TITLE_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_TITLE_REDIRECTS: usize = 3;
+
+// Decode the entities that show up in titles: the XML five, a few typographic ones, and
+// numeric references. Anything else is left as written.
+fn decode_entities(text: &str) -> String {
+  let mut decoded = String::with_capacity(text.len());
+  let mut rest = text;
+  while let Some(start) = rest.find('&') {
+    decoded.push_str(&rest[..start]);
+    rest = &rest[start..];
+    // Entities are short; a `;` further on belongs to the text
+    let Some(end) = rest.find(';').filter(|end| *end <= 12) else {
+      decoded.push('&');
+      rest = &rest[1..];
+      continue;
+    };
+    let entity = &rest[1..end];
+    let character = match entity {
+      "amp" => Some('&'),
+      "lt" => Some('<'),
+      "gt" => Some('>'),
+      "quot" => Some('"'),
+      "apos" => Some('\''),
+      "nbsp" => Some(' '),
+      "ndash" => Some('–'),
+      "mdash" => Some('—'),
+      "hellip" => Some('…'),
+      "lsquo" => Some('‘'),
+      "rsquo" => Some('’'),
+      "ldquo" => Some('“'),
+      "rdquo" => Some('”'),
+      _ => entity
+        .strip_prefix("#x")
+        .or_else(|| entity.strip_prefix("#X"))
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+        .and_then(char::from_u32),
+    };
+    match character {
+      Some(c) => {
+        decoded.push(c);
+        rest = &rest[end + 1..];
+      }
+      None => {
+        decoded.push('&');
+        rest = &rest[1..];
+      }
+    }
+  }
+  decoded.push_str(rest);
+  decoded
+}
+
+// Entity-decoded, with runs of whitespace (including newlines) collapsed to one space
+fn clean_title(raw: &str) -> Option<String> {
+  let title = decode_entities(raw)
+    .split_whitespace()
+    .collect::<Vec<_>>()
+    .join(" ");
+  (!title.is_empty()).then_some(title)
+}
+
+// Value of `name="..."` (or single-quoted) in a tag's text. `tag` is lowercased.
+fn attribute<'a>(tag: &'a str, lower: &str, name: &str) -> Option<&'a str> {
+  let pattern = format!("{}=", name);
+  for (index, _) in lower.match_indices(&pattern) {
+    if !lower[..index].ends_with(|c: char| c.is_ascii_whitespace()) {
+      continue;
+    }
+    let start = index + pattern.len();
+    let quote = tag[start..]
+      .chars()
+      .next()
+      .filter(|c| *c == '"' || *c == '\'')?;
+    let length = tag[start + 1..].find(quote)?;
+    return Some(&tag[start + 1..start + 1 + length]);
+  }
+  None
+}
+
+// The page's og:title, falling back to <title>
+fn page_title(html: &str) -> Option<String> {
+  // ASCII lowercasing keeps byte offsets, so positions in `lower` index `html` too
+  let lower = html.to_ascii_lowercase();
+  for (index, _) in lower.match_indices("<meta") {
+    let Some(length) = lower[index..].find('>') else {
+      break;
+    };
+    let (tag, tag_lower) = (&html[index..index + length], &lower[index..index + length]);
+    let is_og_title = attribute(tag, tag_lower, "property")
+      .or_else(|| attribute(tag, tag_lower, "name"))
+      .is_some_and(|value| value.eq_ignore_ascii_case("og:title"));
+    if is_og_title {
+      if let Some(title) = attribute(tag, tag_lower, "content").and_then(clean_title) {
+        return Some(title);
+      }
+    }
+  }
+
+  let open = lower.find("<title")?;
+  let start = open + lower[open..].find('>')? + 1;
+  let end = start + lower[start..].find("</title")?;
+  clean_title(&html[start..end])
+}
+
+// Title of the web page at `url`, for turning a pasted URL into `[Title](url)`. Only the
+// first 64KB are read, and the client keeps no cookie jar so none are ever sent.
+#[tauri::command]
+pub async fn fetch_link_title(url: String) -> CommandResult<String> {
+  let parsed = reqwest::Url::parse(&url)
+    .ok()
+    .filter(|parsed| matches!(parsed.scheme(), "http" | "https"))
+    .ok_or_else(|| CommandError::invalid_data(format!("Not a web link: {}", url)))?;
+  let client = http::builder(TITLE_TIMEOUT)
+    .redirect(reqwest::redirect::Policy::limited(MAX_TITLE_REDIRECTS))
+    .build()
+    .map_err(|e| CommandError::io("Failed to set up HTTP client", e))?;
+  let mut response = client
+    .get(parsed)
+    .header(reqwest::header::ACCEPT, "text/html,application/xhtml+xml")
+    .send()
+    .await
+    .map_err(|e| http::request_error("Failed to fetch page", e))?;
+
+  let status = response.status();
+  if !status.is_success() {
+    let message = match status.as_u16() {
+      401 | 403 | 429 => format!("The site refused the request ({})", status.as_u16()),
+      code => format!("The site answered with an error ({})", code),
+    };
+    return Err(CommandError::Network { message });
+  }
+  let content_type = response
+    .headers()
+    .get(reqwest::header::CONTENT_TYPE)
+    .and_then(|value| value.to_str().ok())
+    .unwrap_or("")
+    .to_ascii_lowercase();
+  if !content_type.contains("html") {
+    return Err(CommandError::invalid_data(format!(
+      "Not an HTML page ({})",
+      if content_type.is_empty() {
+        "no content type"
+      } else {
+        content_type.as_str()
+      }
+    )));
+  }
+
+  let mut body = Vec::new();
+  while body.len() < MAX_TITLE_BYTES {
+    match response
+      .chunk()
+      .await
+      .map_err(|e| http::request_error("Failed to read page", e))?
+    {
+      Some(chunk) => body.extend_from_slice(&chunk),
+      None => break,
+    }
+  }
+  body.truncate(MAX_TITLE_BYTES);
+  page_title(&String::from_utf8_lossy(&body))
+    .ok_or_else(|| CommandError::invalid_data("The page has no title"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_page_title_prefers_og_title() {
+    let html = r#"<html><head>
+      <title>
+        Rust &amp; WebAssembly &mdash; Docs
+      </title>
+      <meta property="og:title" content="Rust &#x26; Wasm">
+    </head>"#;
+    assert_eq!(page_title(html).as_deref(), Some("Rust & Wasm"));
+    let html = html.replace("og:title", "og:description");
+    assert_eq!(
+      page_title(&html).as_deref(),
+      Some("Rust & WebAssembly — Docs")
+    );
+  }
+
+  #[test]
+  fn test_page_title_missing_or_empty() {
+    assert_eq!(page_title("<html><body>Hi</body></html>"), None);
+    assert_eq!(page_title("<title>  </title>"), None);
+    // Cut off by the size cap before the title closed
+    assert_eq!(page_title("<title>Half a ti"), None);
+  }
+
+  #[test]
+  fn test_decode_entities_leaves_unknown_ones() {
+    assert_eq!(
+      decode_entities("Tom &amp; Jerry&#39;s &copy; &"),
+      "Tom & Jerry's &copy; &"
+    );
+  }
+}
@@ -0,0 +1,256 @@
+use log::{Level, LevelFilter};
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::plugin::TauriPlugin;
+use tauri::{AppHandle, Manager, Runtime};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::error::{CommandError, CommandResult};
+
+// Log file in the app log folder; the plugin adds the `.log` extension
+const LOG_FILE_NAME: &str = "markdowner";
+
+// Past this size the log starts over, so it never grows without bound
+const MAX_LOG_FILE_BYTES: u128 = 1024 * 1024;
+
+// Entries get_recent_logs returns when no count is given
+const DEFAULT_LOG_LINES: usize = 500;
+
+// Every line starts with this, then the level, the module and the message
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3f";
+
+// Our own messages at this level and above are logged until set_log_level changes it.
+// Dependencies only ever get to log warnings and errors; they'd drown ours out otherwise.
+fn default_level() -> LevelFilter {
+  if cfg!(debug_assertions) {
+    LevelFilter::Debug
+  } else {
+    LevelFilter::Info
+  }
+}
+
+// A log entry as returned to the frontend
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LogEntry {
+  pub timestamp: String,
+  // ERROR, WARN, INFO, DEBUG or TRACE
+  pub level: String,
+  // Module that logged it
+  pub target: String,
+  // May span several lines
+  pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecentLogs {
+  // Oldest first
+  pub entries: Vec<LogEntry>,
+  // The level currently logged, lowercase
+  pub level: String,
+  // Where the log file is, for bug reports
+  pub path: String,
+}
+
+// Log to a file in the app log folder, and to the terminal in debug builds. The level
+// filter is left wide open here; `log::set_max_level` decides at runtime.
+pub fn plugin<R: Runtime>() -> TauriPlugin<R> {
+  let mut targets = vec![Target::new(TargetKind::LogDir {
+    file_name: Some(LOG_FILE_NAME.to_string()),
+  })];
+  if cfg!(debug_assertions) {
+    targets.push(Target::new(TargetKind::Stdout));
+  }
+  tauri_plugin_log::Builder::new()
+    .clear_targets()
+    .targets(targets)
+    .max_file_size(MAX_LOG_FILE_BYTES)
+    .rotation_strategy(RotationStrategy::KeepOne)
+    .level(LevelFilter::Warn)
+    .level_for(env!("CARGO_CRATE_NAME"), LevelFilter::Trace)
+    .format(|out, message, record| {
+      out.finish(format_args!(
+        "{} {} {}: {}",
+        chrono::Local::now().format(TIMESTAMP_FORMAT),
+        record.level(),
+        record.target(),
+        message
+      ))
+    })
+    .build()
+}
+
+// Apply the default level once the plugin has installed the logger
+pub fn init() {
+  log::set_max_level(default_level());
+}
+
+fn log_file_path<R: Runtime>(app: &AppHandle<R>) -> CommandResult<PathBuf> {
+  app
+    .path()
+    .app_log_dir()
+    .map(|dir| dir.join(format!("{}.log", LOG_FILE_NAME)))
+    .map_err(|e| CommandError::io("Failed to resolve app log dir", e))
+}
+
+fn parse_level_filter(level: &str) -> CommandResult<LevelFilter> {
+  level
+    .parse()
+    .map_err(|_| CommandError::invalid_data(format!("Unknown log level: {}", level)))
+}
+
+// An entry from a line as written by `plugin`, or None for a line that doesn't start one
+fn parse_line(line: &str) -> Option<LogEntry> {
+  let (timestamp, rest) = line.split_once(' ')?;
+  chrono::NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT).ok()?;
+  let (level, rest) = rest.split_once(' ')?;
+  let level: Level = level.parse().ok()?;
+  let (target, message) = rest.split_once(": ")?;
+  Some(LogEntry {
+    timestamp: timestamp.to_string(),
+    level: level.to_string(),
+    target: target.to_string(),
+    message: message.to_string(),
+  })
+}
+
+// Entries in a log file, oldest first. Lines that don't start an entry continue the one
+// before (multi-line messages); any before the first entry are dropped.
+fn parse_entries(text: &str) -> Vec<LogEntry> {
+  let mut entries: Vec<LogEntry> = Vec::new();
+  for line in text.lines() {
+    match parse_line(line) {
+      Some(entry) => entries.push(entry),
+      None => {
+        if let Some(last) = entries.last_mut() {
+          last.message.push('\n');
+          last.message.push_str(line);
+        }
+      }
+    }
+  }
+  entries
+}
+
+// The last `count` entries at `filter` or more severe
+fn recent_entries(text: &str, count: usize, filter: LevelFilter) -> Vec<LogEntry> {
+  let mut entries: Vec<LogEntry> = parse_entries(text)
+    .into_iter()
+    .filter(|entry| {
+      entry
+        .level
+        .parse::<Level>()
+        .is_ok_and(|level| level <= filter)
+    })
+    .collect();
+  let skip = entries.len().saturating_sub(count);
+  entries.drain(..skip);
+  entries
+}
+
+// The last `lines` log entries (default 500), optionally only those at `level_filter`
+// ("error", "warn", "info", "debug" or "trace") or more severe
+#[tauri::command]
+pub async fn get_recent_logs(
+  app: AppHandle,
+  lines: Option<usize>,
+  level_filter: Option<String>,
+) -> CommandResult<RecentLogs> {
+  let filter = match level_filter {
+    Some(level) => parse_level_filter(&level)?,
+    None => LevelFilter::Trace,
+  };
+  let path = log_file_path(&app)?;
+  let entries = match std::fs::read(&path) {
+    Ok(bytes) => recent_entries(
+      &String::from_utf8_lossy(&bytes),
+      lines.unwrap_or(DEFAULT_LOG_LINES),
+      filter,
+    ),
+    // Nothing logged yet
+    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+    Err(e) => return Err(CommandError::from_io(&e, &path, "Failed to read the log")),
+  };
+  Ok(RecentLogs {
+    entries,
+    level: log::max_level().to_string().to_lowercase(),
+    path: path.to_string_lossy().to_string(),
+  })
+}
+
+// Log messages at `level` and above from now on. Not persisted: the next launch starts
+// at the default again, so a "trace" left on by accident doesn't fill the disk.
+#[tauri::command]
+pub async fn set_log_level(level: String) -> CommandResult<()> {
+  let filter = parse_level_filter(&level)?;
+  log::set_max_level(filter);
+  log::info!("Log level set to {}", filter);
+  Ok(())
+}
+
+// Show the folder holding the log file, to attach it to a bug report
+#[tauri::command]
+pub async fn open_log_folder(app: AppHandle) -> CommandResult<()> {
+  let dir = app
+    .path()
+    .app_log_dir()
+    .map_err(|e| CommandError::io("Failed to resolve app log dir", e))?;
+  std::fs::create_dir_all(&dir)
+    .map_err(|e| CommandError::from_io(&e, &dir, "Failed to create the log folder"))?;
+  app
+    .opener()
+    .open_path(dir.to_string_lossy(), None::<&str>)
+    .map_err(|e| CommandError::io("Failed to open the log folder", e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const LOG: &str = "\
+2026-03-01T09:15:02.118 INFO markdown_editor_lib::launch: Opening 1 file
+2026-03-01T09:15:02.420 DEBUG markdown_editor_lib: Stored in pending state: /notes/a.md
+2026-03-01T09:15:07.003 ERROR markdown_editor_lib::tray: Failed to update tray menu: gone
+  caused by: the menu was removed
+2026-03-01T09:16:00.000 WARN markdown_editor_lib::settings: Failed to load settings: x: y
+";
+
+  #[test]
+  fn test_parses_entries_and_joins_continuation_lines() {
+    let entries = parse_entries(&format!("stray line\n{}", LOG));
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0].timestamp, "2026-03-01T09:15:02.118");
+    assert_eq!(entries[0].level, "INFO");
+    assert_eq!(entries[0].target, "markdown_editor_lib::launch");
+    assert_eq!(entries[0].message, "Opening 1 file");
+    assert_eq!(
+      entries[2].message,
+      "Failed to update tray menu: gone\n  caused by: the menu was removed"
+    );
+    // Only the first ": " separates the module from the message
+    assert_eq!(entries[3].message, "Failed to load settings: x: y");
+  }
+
+  #[test]
+  fn test_recent_entries_filters_by_level_and_keeps_the_newest() {
+    let levels = |entries: Vec<LogEntry>| -> Vec<String> {
+      entries.into_iter().map(|entry| entry.level).collect()
+    };
+    assert_eq!(
+      levels(recent_entries(LOG, 10, LevelFilter::Warn)),
+      vec!["ERROR", "WARN"]
+    );
+    assert_eq!(
+      levels(recent_entries(LOG, 2, LevelFilter::Trace)),
+      vec!["ERROR", "WARN"]
+    );
+    assert!(recent_entries(LOG, 10, LevelFilter::Off).is_empty());
+  }
+
+  #[test]
+  fn test_parse_level_filter() {
+    assert_eq!(parse_level_filter("debug").unwrap(), LevelFilter::Debug);
+    assert_eq!(parse_level_filter("WARN").unwrap(), LevelFilter::Warn);
+    assert!(parse_level_filter("loud").is_err());
+  }
+}
@@ -0,0 +1,241 @@
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use objc2::rc::Retained;
+use objc2::runtime::{AnyClass, AnyObject, Imp, Sel};
+use objc2::{define_class, msg_send, sel, MainThreadMarker, MainThreadOnly};
+use objc2_app_kit::{NSApplication, NSMenu, NSMenuItem, NSWindow};
+use objc2_foundation::{ns_string, NSObject, NSString};
+use tauri::{AppHandle, Emitter, WebviewWindow};
+
+use crate::{launch, MENU_NEW_FILE_EVENT};
+
+// What a dock menu item does. Items are tagged with their index in DockMenu::actions.
+#[derive(Debug, Clone, PartialEq)]
+enum DockAction {
+  NewDocument,
+  OpenRecent(String),
+}
+
+impl DockAction {
+  fn title(&self) -> String {
+    match self {
+      DockAction::NewDocument => "New Document".to_string(),
+      DockAction::OpenRecent(path) => Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.clone()),
+    }
+  }
+}
+
+fn dock_actions(recents: &[String]) -> Vec<DockAction> {
+  std::iter::once(DockAction::NewDocument)
+    .chain(recents.iter().cloned().map(DockAction::OpenRecent))
+    .collect()
+}
+
+struct DockMenu {
+  menu: Retained<NSMenu>,
+  // Receives the items' clicks; kept alive here since menu items don't retain their target
+  _target: Retained<DockTarget>,
+  actions: Vec<DockAction>,
+}
+
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+thread_local! {
+  // AppKit objects may only be touched on the main thread, which is the only one that sets
+  // or reads this
+  static DOCK_MENU: RefCell<Option<DockMenu>> = const { RefCell::new(None) };
+}
+
+define_class!(
+  #[unsafe(super(NSObject))]
+  #[thread_kind = MainThreadOnly]
+  #[name = "MarkdownerDockTarget"]
+  struct DockTarget;
+
+  impl DockTarget {
+    #[unsafe(method(dockItemSelected:))]
+    fn dock_item_selected(&self, sender: &NSMenuItem) {
+      run_dock_action(sender.tag());
+    }
+  }
+);
+
+impl DockTarget {
+  fn new(mtm: MainThreadMarker) -> Retained<Self> {
+    let this = Self::alloc(mtm).set_ivars(());
+    unsafe { msg_send![super(this), init] }
+  }
+}
+
+// Route a dock item through the same events as the menu bar and file associations
+fn run_dock_action(tag: isize) {
+  let action = DOCK_MENU.with(|dock| {
+    let dock = dock.borrow();
+    let index = usize::try_from(tag).ok()?;
+    dock.as_ref()?.actions.get(index).cloned()
+  });
+  let (Some(app), Some(action)) = (APP_HANDLE.get(), action) else {
+    return;
+  };
+  match action {
+    DockAction::NewDocument => {
+      launch::focus_main_window(app);
+      let _ = app.emit(MENU_NEW_FILE_EVENT, ());
+    }
+    DockAction::OpenRecent(path) => launch::open_files(app, &[path.into()]),
+  }
+}
+
+fn build_dock_menu(mtm: MainThreadMarker, recents: &[String]) -> DockMenu {
+  let target = DockTarget::new(mtm);
+  let target_object: &AnyObject = &target;
+  let menu = NSMenu::new(mtm);
+  let actions = dock_actions(recents);
+  for (tag, action) in actions.iter().enumerate() {
+    // Between New Document and the recent files
+    if tag == 1 {
+      menu.addItem(&NSMenuItem::separatorItem(mtm));
+    }
+    let title = NSString::from_str(&action.title());
+    let item = unsafe {
+      NSMenuItem::initWithTitle_action_keyEquivalent(
+        NSMenuItem::alloc(mtm),
+        &title,
+        Some(sel!(dockItemSelected:)),
+        ns_string!(""),
+      )
+    };
+    unsafe { item.setTarget(Some(target_object)) };
+    item.setTag(tag as isize);
+    menu.addItem(&item);
+  }
+  DockMenu {
+    menu,
+    _target: target,
+    actions,
+  }
+}
+
+// NSApplicationDelegate's applicationDockMenu:, added to tao's delegate class. AppKit asks
+// for the menu every time the dock icon is right-clicked.
+unsafe extern "C-unwind" fn application_dock_menu(
+  _this: &AnyObject,
+  _cmd: Sel,
+  _sender: &AnyObject,
+) -> *mut NSMenu {
+  DOCK_MENU.with(|dock| match dock.borrow().as_ref() {
+    Some(dock) => Retained::as_ptr(&dock.menu) as *mut NSMenu,
+    None => std::ptr::null_mut(),
+  })
+}
+
+// Give the dock icon a menu with New Document and `recents`. Must run on the main thread,
+// after tao has installed its application delegate (i.e. from setup).
+pub fn init_dock_menu(app: &AppHandle, recents: &[String]) {
+  let Some(mtm) = MainThreadMarker::new() else {
+    log::warn!("Dock menu must be set up on the main thread");
+    return;
+  };
+  let _ = APP_HANDLE.set(app.clone());
+  DOCK_MENU.with(|dock| *dock.borrow_mut() = Some(build_dock_menu(mtm, recents)));
+
+  let application = NSApplication::sharedApplication(mtm);
+  let Some(delegate) = application.delegate() else {
+    log::warn!("No application delegate to attach the dock menu to");
+    return;
+  };
+  let delegate: &AnyObject = (*delegate).as_ref();
+  let class: &AnyClass = delegate.class();
+  let added = unsafe {
+    let imp: Imp = std::mem::transmute(
+      application_dock_menu
+        as unsafe extern "C-unwind" fn(&AnyObject, Sel, &AnyObject) -> *mut NSMenu,
+    );
+    objc2::ffi::class_addMethod(
+      class as *const AnyClass as *mut AnyClass,
+      sel!(applicationDockMenu:),
+      imp,
+      c"@@:@".as_ptr(),
+    )
+  };
+  if !added.as_bool() {
+    log::warn!("Application delegate already has a dock menu");
+  }
+}
+
+// Rebuild the dock menu after the recent files changed
+pub fn refresh_dock_menu(app: &AppHandle, recents: &[String]) {
+  let recents = recents.to_vec();
+  let result = app.run_on_main_thread(move || {
+    if let Some(mtm) = MainThreadMarker::new() {
+      let menu = build_dock_menu(mtm, &recents);
+      DOCK_MENU.with(|dock| *dock.borrow_mut() = Some(menu));
+    }
+  });
+  if let Err(e) = result {
+    log::error!("Failed to update the dock menu: {}", e);
+  }
+}
+
+// Show the unsaved-changes dot in the window's close button
+pub fn set_document_edited(window: &WebviewWindow, edited: bool) {
+  let Ok(ns_window) = window.ns_window() else {
+    return;
+  };
+  // Raw pointers aren't Send; the window outlives this call since it's looked up by label
+  let ns_window = ns_window as usize;
+  let _ = window.run_on_main_thread(move || {
+    let ns_window = unsafe { &*(ns_window as *const NSWindow) };
+    ns_window.setDocumentEdited(edited);
+  });
+}
+
+// Set the file the title bar's proxy icon stands for (Cmd-click shows its folders, and it
+// can be dragged like the file). None clears it for an unsaved document.
+pub fn set_represented_file(window: &WebviewWindow, path: Option<&Path>) {
+  let Ok(ns_window) = window.ns_window() else {
+    return;
+  };
+  let ns_window = ns_window as usize;
+  let filename = path
+    .map(|path| path.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let _ = window.run_on_main_thread(move || {
+    let ns_window = unsafe { &*(ns_window as *const NSWindow) };
+    ns_window.setRepresentedFilename(&NSString::from_str(&filename));
+  });
+}
+
+// NSEventModifierFlagShift
+const SHIFT_MODIFIER_FLAG: usize = 1 << 17;
+
+// Whether Shift is held down right now (NSEvent reads the keyboard state, so this works
+// before the app has a window or an event loop)
+pub fn shift_held() -> bool {
+  let flags: usize = unsafe { msg_send![objc2::class!(NSEvent), modifierFlags] };
+  flags & SHIFT_MODIFIER_FLAG != 0
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_dock_actions_start_with_new_document() {
+    let actions = dock_actions(&["/Users/me/notes/todo.md".to_string()]);
+    assert_eq!(
+      actions,
+      vec![
+        DockAction::NewDocument,
+        DockAction::OpenRecent("/Users/me/notes/todo.md".to_string()),
+      ]
+    );
+    assert_eq!(actions[0].title(), "New Document");
+    assert_eq!(actions[1].title(), "todo.md");
+  }
+}
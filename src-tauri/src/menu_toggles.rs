@@ -0,0 +1,89 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tauri::menu::CheckMenuItem;
+use tauri::{AppHandle, Manager, Wry};
+
+use crate::settings::{self, Settings, SettingsState};
+
+// A check menu item that mirrors a boolean setting: picking it flips the setting, and
+// changing the setting anywhere else moves the checkmark
+pub struct Toggle {
+  pub id: &'static str,
+  label: &'static str,
+  get: fn(&Settings) -> bool,
+  set: fn(&mut Settings, bool),
+}
+
+// File > Autosave
+pub const AUTOSAVE: Toggle = Toggle {
+  id: "toggle_autosave",
+  label: "Autosave",
+  get: |settings| settings.editor.autosave_enabled,
+  set: |settings, on| settings.editor.autosave_enabled = on,
+};
+
+const TOGGLES: &[Toggle] = &[AUTOSAVE];
+
+// The toggles' menu items by id, kept to update their checkmarks
+#[derive(Default)]
+pub struct MenuTogglesState(Mutex<HashMap<&'static str, CheckMenuItem<Wry>>>);
+
+// The menu item for `toggle`, for building the menu. It starts unchecked; `sync` checks it
+// once the settings are loaded.
+pub fn menu_item(app: &AppHandle, toggle: &Toggle) -> tauri::Result<CheckMenuItem<Wry>> {
+  let item = CheckMenuItem::with_id(app, toggle.id, toggle.label, true, false, None::<&str>)?;
+  if let Some(state) = app.try_state::<MenuTogglesState>() {
+    state.0.lock().unwrap().insert(toggle.id, item.clone());
+  }
+  Ok(item)
+}
+
+// Check the toggles' menu items that `settings` turn on. Called whenever the settings
+// change.
+pub fn sync(app: &AppHandle, settings: &Settings) {
+  let Some(state) = app.try_state::<MenuTogglesState>() else {
+    return;
+  };
+  let items = state.0.lock().unwrap();
+  for toggle in TOGGLES {
+    let Some(item) = items.get(toggle.id) else {
+      continue;
+    };
+    if let Err(e) = item.set_checked((toggle.get)(settings)) {
+      log::error!("Failed to update the {} menu item: {}", toggle.label, e);
+    }
+  }
+}
+
+// Flip the setting of the toggle picked in the menu. Returns false if `id` isn't a toggle.
+pub fn handle_menu_event(app: &AppHandle, id: &str) -> bool {
+  let Some(toggle) = TOGGLES.iter().find(|toggle| toggle.id == id) else {
+    return false;
+  };
+  let state = app.state::<SettingsState>();
+  let mut changed = state.0.lock().unwrap().clone();
+  (toggle.set)(&mut changed, !(toggle.get)(&changed));
+  // Applying the settings syncs the checkmark and sends settings-changed
+  if let Err(e) = settings::apply_settings(app, &state, changed) {
+    log::error!("Failed to change the {} setting: {}", toggle.label, e);
+    // The menu may have moved the checkmark by itself
+    sync(app, &state.0.lock().unwrap());
+  }
+  true
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_toggles_flip_their_setting() {
+    let mut settings = Settings::default();
+    for toggle in TOGGLES {
+      let on = (toggle.get)(&settings);
+      (toggle.set)(&mut settings, !on);
+      assert_eq!((toggle.get)(&settings), !on, "{}", toggle.id);
+    }
+    assert!(!settings.editor.autosave_enabled);
+  }
+}
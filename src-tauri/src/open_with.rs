@@ -0,0 +1,382 @@
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::menu::{MenuItem, PredefinedMenuItem};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_opener::OpenerExt;
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::dynamic_menu;
+use crate::error::{CommandError, CommandResult};
+
+// Sent to a window when a file it handed to another app changes on disk
+pub const FILE_CHANGED_EXTERNALLY_EVENT: &str = "file-changed-externally";
+
+// How often handed-off files are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// File > Open With. Items are `open_with:<application id>`, plus the default application.
+pub const MENU_ID: &str = "open_with";
+pub const DEFAULT_ITEM_ID: &str = "open_with_default";
+pub const ITEM_ID_PREFIX: &str = "open_with:";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Application {
+  // What open_with takes: the app bundle's path on macOS, the program elsewhere
+  pub id: String,
+  pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExternalChange {
+  pub path: String,
+  pub mtime: u64,
+}
+
+// Editors offered when they're on the PATH: program and display name
+#[cfg(windows)]
+const CANDIDATES: &[(&str, &str)] = &[
+  ("code", "Visual Studio Code"),
+  ("typora", "Typora"),
+  ("notepad++", "Notepad++"),
+  ("notepad", "Notepad"),
+];
+#[cfg(all(unix, not(target_os = "macos")))]
+const CANDIDATES: &[(&str, &str)] = &[
+  ("code", "Visual Studio Code"),
+  ("codium", "VSCodium"),
+  ("typora", "Typora"),
+  ("subl", "Sublime Text"),
+  ("zed", "Zed"),
+  ("gnome-text-editor", "Text Editor"),
+  ("gedit", "gedit"),
+  ("kate", "Kate"),
+  ("mousepad", "Mousepad"),
+];
+
+// Whether `program` is in one of the directories of a PATH-style list
+#[cfg(not(target_os = "macos"))]
+pub(crate) fn find_program(program: &str, path_var: &std::ffi::OsStr) -> bool {
+  let extensions: &[&str] = if cfg!(windows) {
+    &["exe", "cmd", "bat"]
+  } else {
+    &[""]
+  };
+  std::env::split_paths(path_var).any(|dir| {
+    extensions
+      .iter()
+      .any(|extension| dir.join(program).with_extension(extension).is_file())
+  })
+}
+
+// The apps LaunchServices has registered for the file's type, default first, without us
+#[cfg(target_os = "macos")]
+fn applications_for(path: &Path) -> Vec<Application> {
+  use objc2_app_kit::NSWorkspace;
+  use objc2_foundation::{NSString, NSURL};
+
+  let url = NSURL::fileURLWithPath(&NSString::from_str(&path.to_string_lossy()));
+  let urls = NSWorkspace::sharedWorkspace().URLsForApplicationsToOpenURL(&url);
+  let current_exe = std::env::current_exe().unwrap_or_default();
+  urls
+    .iter()
+    .filter_map(|url| Some(PathBuf::from(url.path()?.to_string())))
+    .filter(|bundle| !current_exe.starts_with(bundle))
+    .map(|bundle| Application {
+      name: bundle
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| bundle.to_string_lossy().to_string()),
+      id: bundle.to_string_lossy().to_string(),
+    })
+    .collect()
+}
+
+#[cfg(all(not(target_os = "macos"), any(windows, unix)))]
+fn applications_for(_path: &Path) -> Vec<Application> {
+  let Some(path_var) = std::env::var_os("PATH") else {
+    return Vec::new();
+  };
+  CANDIDATES
+    .iter()
+    .filter(|(program, _)| find_program(program, &path_var))
+    .map(|(program, name)| Application {
+      id: program.to_string(),
+      name: name.to_string(),
+    })
+    .collect()
+}
+
+#[cfg(not(any(windows, unix)))]
+fn applications_for(_path: &Path) -> Vec<Application> {
+  Vec::new()
+}
+
+fn existing_file(scope: &AccessScope, path: &str) -> CommandResult<PathBuf> {
+  let path = PathBuf::from(path);
+  scope.check(&path)?;
+  if !path.exists() {
+    return Err(CommandError::NotFound {
+      path: path.to_string_lossy().to_string(),
+    });
+  }
+  if !path.is_file() {
+    return Err(CommandError::NotAFile {
+      path: path.to_string_lossy().to_string(),
+    });
+  }
+  Ok(path)
+}
+
+fn mtime_of(path: &Path) -> Option<u64> {
+  crate::file_mtime_millis(&std::fs::metadata(path).ok()?)
+}
+
+// The same file reached through different paths is watched once
+fn watch_key(path: &Path) -> PathBuf {
+  std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+struct Watched {
+  // The path as the windows know it, which is what the change event carries
+  path: String,
+  windows: BTreeSet<String>,
+  mtime: Option<u64>,
+}
+
+// Files handed to another app, polled for changes until no window showing them is left.
+// There is no general file watcher, so only these files are checked.
+#[derive(Default)]
+pub struct ExternalEditState(Mutex<HashMap<PathBuf, Watched>>);
+
+impl ExternalEditState {
+  // Take in a save of our own, so it isn't reported as a change from outside
+  pub fn saved(&self, path: &Path) {
+    if let Some(watched) = self.0.lock().unwrap().get_mut(&watch_key(path)) {
+      watched.mtime = mtime_of(path);
+    }
+  }
+
+  // Returns whether the file wasn't watched yet, i.e. needs a poller
+  fn watch(&self, path: &Path, window_label: &str) -> bool {
+    let mut watched = self.0.lock().unwrap();
+    let key = watch_key(path);
+    if let Some(watched) = watched.get_mut(&key) {
+      watched.windows.insert(window_label.to_string());
+      return false;
+    }
+    watched.insert(
+      key,
+      Watched {
+        path: path.to_string_lossy().to_string(),
+        windows: BTreeSet::from([window_label.to_string()]),
+        mtime: mtime_of(path),
+      },
+    );
+    true
+  }
+
+  // The windows to tell about a change since the last poll, or None once nobody's left to
+  // tell and the file is no longer watched
+  fn poll(
+    &self,
+    key: &Path,
+    mtime: Option<u64>,
+    is_open: impl Fn(&str) -> bool,
+  ) -> Option<(Option<ExternalChange>, Vec<String>)> {
+    let mut all = self.0.lock().unwrap();
+    let watched = all.get_mut(key)?;
+    watched.windows.retain(|label| is_open(label));
+    if watched.windows.is_empty() {
+      all.remove(key);
+      return None;
+    }
+    let change = match mtime {
+      Some(mtime) if watched.mtime != Some(mtime) => {
+        watched.mtime = Some(mtime);
+        Some(ExternalChange {
+          path: watched.path.clone(),
+          mtime,
+        })
+      }
+      _ => None,
+    };
+    Some((change, watched.windows.iter().cloned().collect()))
+  }
+}
+
+fn watch(app: &AppHandle, path: &Path, window_label: &str) {
+  if !app.state::<ExternalEditState>().watch(path, window_label) {
+    return;
+  }
+  let app = app.clone();
+  let path = path.to_path_buf();
+  tauri::async_runtime::spawn(async move {
+    let key = watch_key(&path);
+    loop {
+      tokio::time::sleep(POLL_INTERVAL).await;
+      let state = app.state::<ExternalEditState>();
+      let polled = state.poll(&key, mtime_of(&path), |label| {
+        app.get_webview_window(label).is_some()
+      });
+      let Some((change, windows)) = polled else {
+        break;
+      };
+      if let Some(change) = change {
+        for label in windows {
+          let _ = app.emit_to(
+            label.as_str(),
+            FILE_CHANGED_EXTERNALLY_EVENT,
+            change.clone(),
+          );
+        }
+      }
+    }
+  });
+}
+
+fn open(app: &AppHandle, path: &Path, with: Option<&str>) -> CommandResult<()> {
+  app
+    .opener()
+    .open_path(path.to_string_lossy(), with)
+    .map_err(|e| CommandError::io("Failed to open the file in another app", e))
+}
+
+// Fill File > Open With for the focused document, or leave it empty without one
+pub fn update_menu(app: &AppHandle, path: Option<&Path>) -> tauri::Result<()> {
+  dynamic_menu::rebuild_submenu(app, MENU_ID, |submenu| {
+    let Some(path) = path.filter(|path| path.is_file()) else {
+      return submenu.set_enabled(false);
+    };
+    submenu.append(&MenuItem::with_id(
+      app,
+      DEFAULT_ITEM_ID,
+      "Default Application",
+      true,
+      None::<&str>,
+    )?)?;
+    let applications = applications_for(path);
+    if !applications.is_empty() {
+      submenu.append(&PredefinedMenuItem::separator(app)?)?;
+    }
+    for application in applications {
+      submenu.append(&MenuItem::with_id(
+        app,
+        format!("{}{}", ITEM_ID_PREFIX, application.id),
+        application.name,
+        true,
+        None::<&str>,
+      )?)?;
+    }
+    submenu.set_enabled(true)
+  })
+}
+
+#[tauri::command]
+pub async fn list_applications_for(
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+) -> CommandResult<Vec<Application>> {
+  let path = existing_file(&scope.0.lock().unwrap(), &path)?;
+  Ok(applications_for(&path))
+}
+
+// Open the file in the app the system uses for its type
+#[tauri::command]
+pub async fn open_with_default(
+  app: AppHandle,
+  window: tauri::Window,
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+) -> CommandResult<()> {
+  let path = existing_file(&scope.0.lock().unwrap(), &path)?;
+  open(&app, &path, None)?;
+  watch(&app, &path, window.label());
+  Ok(())
+}
+
+// Open the file in one of the apps list_applications_for returned for it
+#[tauri::command]
+pub async fn open_with(
+  app: AppHandle,
+  window: tauri::Window,
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+  app_identifier: String,
+) -> CommandResult<()> {
+  let path = existing_file(&scope.0.lock().unwrap(), &path)?;
+  // Only ever launch what was offered, not any program the caller names
+  if !applications_for(&path)
+    .iter()
+    .any(|application| application.id == app_identifier)
+  {
+    return Err(CommandError::invalid_data(format!(
+      "{} can't open this file",
+      app_identifier
+    )));
+  }
+  open(&app, &path, Some(&app_identifier))?;
+  watch(&app, &path, window.label());
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::TempDir;
+
+  #[cfg(not(target_os = "macos"))]
+  #[test]
+  fn test_find_program_searches_each_directory() {
+    let empty = TempDir::new().unwrap();
+    let bin = TempDir::new().unwrap();
+    let program = if cfg!(windows) { "code.cmd" } else { "code" };
+    std::fs::write(bin.path().join(program), "").unwrap();
+    let path_var = std::env::join_paths([empty.path(), bin.path()]).unwrap();
+    assert!(find_program("code", &path_var));
+    assert!(!find_program("typora", &path_var));
+  }
+
+  #[test]
+  fn test_files_outside_the_scope_are_denied() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("note.md");
+    std::fs::write(&path, "# Note").unwrap();
+    let path = path.to_string_lossy();
+
+    let error = existing_file(&AccessScope::default(), &path).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    let scope = AccessScope::with_folders(&[dir.path()]);
+    assert!(existing_file(&scope, &path).is_ok());
+    let missing = dir.path().join("gone.md");
+    let error = existing_file(&scope, &missing.to_string_lossy()).unwrap_err();
+    assert_eq!(error.code(), "not_found");
+  }
+
+  #[test]
+  fn test_reports_each_change_once_while_a_window_is_open() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("note.md");
+    std::fs::write(&path, "# Note").unwrap();
+    let state = ExternalEditState::default();
+    assert!(state.watch(&path, "main"));
+    assert!(!state.watch(&path, "main-2"));
+
+    let key = watch_key(&path);
+    let before = mtime_of(&path);
+    let (change, _) = state.poll(&key, before, |_| true).unwrap();
+    assert_eq!(change, None);
+
+    let later = before.map(|mtime| mtime + 1000);
+    let (change, windows) = state.poll(&key, later, |label| label == "main-2").unwrap();
+    assert_eq!(change.map(|change| change.mtime), later);
+    assert_eq!(windows, vec!["main-2".to_string()]);
+    assert_eq!(state.poll(&key, later, |_| true).unwrap().0, None);
+
+    // Once no window is left the file is no longer watched
+    assert!(state.poll(&key, later, |_| false).is_none());
+    assert!(state.watch(&path, "main"));
+  }
+}
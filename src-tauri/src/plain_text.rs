@@ -0,0 +1,498 @@
+use arboard::Clipboard;
+use pulldown_cmark::{Alignment, Event, LinkType, Options, Parser, Tag, TagEnd};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::access_scope::AccessScopeState;
+use crate::atomic_write::write_atomically;
+use crate::error::{CommandError, CommandResult};
+use crate::export::{requested_output_path, ExportResult};
+
+// Stand-ins for footnote references until every footnote's text is known (see latex.rs)
+const FOOTNOTE_OPEN: char = '\u{E000}';
+const FOOTNOTE_CLOSE: char = '\u{E001}';
+
+// Block quotes and definitions are set off by this much
+const INDENT: &str = "  ";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PlainTextOptions {
+  // Write links as `text (url)`; otherwise just their text
+  pub link_urls: bool,
+}
+
+impl Default for PlainTextOptions {
+  fn default() -> Self {
+    PlainTextOptions { link_urls: true }
+  }
+}
+
+// A table being read
+struct Table {
+  alignments: Vec<Alignment>,
+  // Header row first
+  rows: Vec<Vec<String>>,
+}
+
+// `text` padded to `width` characters as `alignment` asks
+fn align(text: &str, width: usize, alignment: Alignment) -> String {
+  let padding = width.saturating_sub(text.chars().count());
+  let (left, right) = match alignment {
+    Alignment::Right => (padding, 0),
+    Alignment::Center => (padding / 2, padding - padding / 2),
+    Alignment::None | Alignment::Left => (0, padding),
+  };
+  format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+}
+
+// A table as columns lined up with spaces, with a dashed line under the header
+fn table_text(table: &Table) -> String {
+  let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+  let widths: Vec<usize> = (0..columns)
+    .map(|column| {
+      table
+        .rows
+        .iter()
+        .filter_map(|row| row.get(column))
+        .map(|cell| cell.chars().count())
+        .max()
+        .unwrap_or(0)
+    })
+    .collect();
+  let line = |cells: Vec<String>| format!("{}\n", cells.join("  ").trim_end());
+
+  let mut text = String::new();
+  for (index, row) in table.rows.iter().enumerate() {
+    let cells = widths
+      .iter()
+      .enumerate()
+      .map(|(column, width)| {
+        let cell = row.get(column).map(String::as_str).unwrap_or("");
+        let alignment = table
+          .alignments
+          .get(column)
+          .copied()
+          .unwrap_or(Alignment::None);
+        align(cell, *width, alignment)
+      })
+      .collect();
+    text.push_str(&line(cells));
+    if index == 0 {
+      text.push_str(&line(
+        widths.iter().map(|width| "-".repeat(*width)).collect(),
+      ));
+    }
+  }
+  text
+}
+
+fn indent(text: &str) -> String {
+  text
+    .lines()
+    .map(|line| {
+      if line.is_empty() {
+        String::new()
+      } else {
+        format!("{}{}", INDENT, line)
+      }
+    })
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+// Turns markdown events into plain text
+struct PlainTextWriter {
+  out: String,
+  link_urls: bool,
+  // Open lists, with the next number for numbered ones
+  lists: Vec<Option<u64>>,
+  // Open links, with the URL to write after the link text if any
+  links: Vec<Option<String>>,
+  in_metadata: bool,
+  table: Option<Table>,
+  // Output interrupted by a block quote, footnote or table cell, restored when it ends
+  interrupted: Vec<String>,
+  footnote_label: Option<String>,
+  footnotes: HashMap<String, String>,
+}
+
+impl PlainTextWriter {
+  fn line(&mut self) {
+    if !self.out.is_empty() && !self.out.ends_with('\n') {
+      self.out.push('\n');
+    }
+  }
+
+  fn blank_line(&mut self) {
+    self.line();
+    if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+      self.out.push('\n');
+    }
+  }
+
+  fn interrupt(&mut self) {
+    let out = std::mem::take(&mut self.out);
+    self.interrupted.push(out);
+  }
+
+  // What was written since `interrupt`, with the earlier output back in place
+  fn resume(&mut self) -> String {
+    let earlier = self.interrupted.pop().unwrap_or_default();
+    std::mem::replace(&mut self.out, earlier)
+  }
+
+  fn start(&mut self, tag: Tag) {
+    match tag {
+      Tag::Paragraph | Tag::Heading { .. } | Tag::DefinitionListTitle => self.line(),
+      Tag::BlockQuote(_) | Tag::DefinitionListDefinition => {
+        self.line();
+        self.interrupt();
+      }
+      // Code blocks' text is written as it is
+      Tag::CodeBlock(_) => self.blank_line(),
+      Tag::List(start) => {
+        self.line();
+        self.lists.push(start);
+      }
+      Tag::Item => {
+        self.line();
+        let depth = self.lists.len().saturating_sub(1);
+        self.out.push_str(&INDENT.repeat(depth));
+        match self.lists.last_mut() {
+          Some(Some(number)) => {
+            self.out.push_str(&format!("{}. ", number));
+            *number += 1;
+          }
+          _ => self.out.push_str("- "),
+        }
+      }
+      Tag::FootnoteDefinition(label) => {
+        self.footnote_label = Some(label.to_string());
+        self.interrupt();
+      }
+      Tag::Table(alignments) => {
+        self.blank_line();
+        self.table = Some(Table {
+          alignments,
+          rows: Vec::new(),
+        });
+      }
+      Tag::TableHead | Tag::TableRow => {
+        if let Some(table) = self.table.as_mut() {
+          table.rows.push(Vec::new());
+        }
+      }
+      Tag::TableCell => self.interrupt(),
+      Tag::Link {
+        link_type,
+        dest_url,
+        ..
+      } => {
+        // Autolinks already show their URL; wiki links and anchors have none worth showing
+        let url = (self.link_urls
+          && !matches!(
+            link_type,
+            LinkType::Autolink | LinkType::Email | LinkType::WikiLink
+          )
+          && !dest_url.starts_with('#'))
+        .then(|| dest_url.to_string());
+        self.links.push(url);
+      }
+      Tag::MetadataBlock(_) => self.in_metadata = true,
+      // Only their text is kept; an image's is its alt text
+      Tag::HtmlBlock
+      | Tag::DefinitionList
+      | Tag::Emphasis
+      | Tag::Strong
+      | Tag::Strikethrough
+      | Tag::Superscript
+      | Tag::Subscript
+      | Tag::Image { .. } => {}
+    }
+  }
+
+  fn end(&mut self, tag: TagEnd) {
+    match tag {
+      TagEnd::Paragraph | TagEnd::Heading(_) => self.blank_line(),
+      TagEnd::DefinitionListTitle => self.line(),
+      TagEnd::BlockQuote(_) | TagEnd::DefinitionListDefinition => {
+        let quoted = self.resume();
+        self.line();
+        self.out.push_str(&indent(quoted.trim_end()));
+        if matches!(tag, TagEnd::BlockQuote(_)) {
+          self.blank_line();
+        } else {
+          self.line();
+        }
+      }
+      TagEnd::CodeBlock => self.blank_line(),
+      TagEnd::List(_) => {
+        self.lists.pop();
+        if self.lists.is_empty() {
+          self.blank_line();
+        } else {
+          self.line();
+        }
+      }
+      TagEnd::Item => self.line(),
+      TagEnd::DefinitionList => self.blank_line(),
+      TagEnd::FootnoteDefinition => {
+        let text = self.resume();
+        if let Some(label) = self.footnote_label.take() {
+          let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+          self.footnotes.insert(label, text);
+        }
+      }
+      TagEnd::TableCell => {
+        let cell = self.resume();
+        let cell = cell.split_whitespace().collect::<Vec<_>>().join(" ");
+        if let Some(row) = self.table.as_mut().and_then(|table| table.rows.last_mut()) {
+          row.push(cell);
+        }
+      }
+      TagEnd::Table => {
+        if let Some(table) = self.table.take() {
+          self.out.push_str(&table_text(&table));
+          self.blank_line();
+        }
+      }
+      TagEnd::Link => {
+        if let Some(Some(url)) = self.links.pop() {
+          self.out.push_str(&format!(" ({})", url));
+        }
+      }
+      TagEnd::MetadataBlock(_) => self.in_metadata = false,
+      TagEnd::TableHead
+      | TagEnd::TableRow
+      | TagEnd::HtmlBlock
+      | TagEnd::Emphasis
+      | TagEnd::Strong
+      | TagEnd::Strikethrough
+      | TagEnd::Superscript
+      | TagEnd::Subscript
+      | TagEnd::Image => {}
+    }
+  }
+
+  fn event(&mut self, event: Event) {
+    match event {
+      Event::Start(tag) => self.start(tag),
+      Event::End(tag) => self.end(tag),
+      Event::Text(_) if self.in_metadata => {}
+      Event::Text(text) | Event::Code(text) | Event::InlineMath(text) => self.out.push_str(&text),
+      Event::DisplayMath(math) => {
+        self.line();
+        self.out.push_str(math.trim());
+        self.out.push('\n');
+      }
+      Event::FootnoteReference(label) => {
+        self.out.push(FOOTNOTE_OPEN);
+        self.out.push_str(&label);
+        self.out.push(FOOTNOTE_CLOSE);
+      }
+      Event::SoftBreak | Event::HardBreak => self.out.push('\n'),
+      Event::Rule => self.blank_line(),
+      Event::TaskListMarker(checked) => self.out.push_str(if checked { "[x] " } else { "[ ] " }),
+      Event::Html(_) | Event::InlineHtml(_) => {}
+    }
+  }
+
+  // The output with footnote references replaced by their text in brackets
+  fn finish(self) -> String {
+    let mut finished = String::with_capacity(self.out.len());
+    let mut rest = self.out.as_str();
+    while let Some(open) = rest.find(FOOTNOTE_OPEN) {
+      finished.push_str(&rest[..open]);
+      rest = &rest[open + FOOTNOTE_OPEN.len_utf8()..];
+      let close = rest.find(FOOTNOTE_CLOSE).unwrap_or(rest.len());
+      if let Some(text) = self.footnotes.get(&rest[..close]) {
+        finished.push_str(&format!(" [{}]", text));
+      }
+      rest = rest.get(close + FOOTNOTE_CLOSE.len_utf8()..).unwrap_or("");
+    }
+    finished.push_str(rest);
+    format!("{}\n", finished.trim())
+  }
+}
+
+// Markdown as readable plain text: no markup, frontmatter or HTML. Code blocks are kept
+// as written, set off by blank lines.
+fn markdown_to_plain_text(markdown: &str, options: &PlainTextOptions) -> String {
+  let mut writer = PlainTextWriter {
+    out: String::new(),
+    link_urls: options.link_urls,
+    lists: Vec::new(),
+    links: Vec::new(),
+    in_metadata: false,
+    table: None,
+    interrupted: Vec::new(),
+    footnote_label: None,
+    footnotes: HashMap::new(),
+  };
+  for event in Parser::new_ext(markdown, Options::all()) {
+    writer.event(event);
+  }
+  writer.finish()
+}
+
+// The document as plain text, with the markdown syntax stripped
+#[tauri::command]
+pub async fn export_plain_text(
+  markdown_content: String,
+  options: Option<PlainTextOptions>,
+) -> CommandResult<String> {
+  Ok(markdown_to_plain_text(
+    &markdown_content,
+    &options.unwrap_or_default(),
+  ))
+}
+
+// Write the document as a .txt file. Prompts for a destination when no path is given;
+// returns None if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_plain_text_to_file(
+  app: AppHandle,
+  document_path: Option<String>,
+  markdown_content: String,
+  output_path: Option<String>,
+  options: Option<PlainTextOptions>,
+) -> CommandResult<Option<ExportResult>> {
+  let output_path = match output_path {
+    Some(path) => requested_output_path(&app.state::<AccessScopeState>(), path)?,
+    None => {
+      let file_name = document_path
+        .as_deref()
+        .and_then(|d| std::path::Path::new(d).file_stem())
+        .map(|stem| format!("{}.txt", stem.to_string_lossy()))
+        .unwrap_or_else(|| "Untitled.txt".to_string());
+      let picked = app
+        .dialog()
+        .file()
+        .add_filter("Plain Text", &["txt"])
+        .set_file_name(file_name)
+        .blocking_save_file();
+      match picked.map(crate::dialog_result_to_path).transpose()? {
+        Some(path) => path,
+        None => return Ok(None),
+      }
+    }
+  };
+  if !output_path.is_absolute() {
+    return Err(CommandError::invalid_path(
+      &output_path,
+      "File path must be absolute",
+    ));
+  }
+  let text = markdown_to_plain_text(&markdown_content, &options.unwrap_or_default());
+  write_atomically(&output_path, text.as_bytes())
+    .map_err(|e| CommandError::from_io(&e, &output_path, "Failed to write file"))?;
+  Ok(Some(ExportResult {
+    output_path: output_path.to_string_lossy().to_string(),
+    copied_assets: Vec::new(),
+    warnings: Vec::new(),
+  }))
+}
+
+// Put the document on the clipboard as plain text, for pasting where markdown would show
+// as clutter
+#[tauri::command]
+pub async fn copy_as_plain_text(
+  markdown_content: String,
+  options: Option<PlainTextOptions>,
+) -> CommandResult<()> {
+  let text = markdown_to_plain_text(&markdown_content, &options.unwrap_or_default());
+  Clipboard::new()
+    .and_then(|mut clipboard| clipboard.set_text(text))
+    .map_err(|e| CommandError::io("Failed to copy to the clipboard", e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const FIXTURE: &str = r#"---
+title: Release notes
+---
+# Release *notes*
+
+Version **2.0** is out, with `inline code` and ~~old~~ new _emphasis_.
+See [the guide](https://example.com/guide) or <https://example.com>.[^1]
+
+## Changes
+
+* First
+* Second
+  1. Nested one
+  2. Nested two
+* [x] Done
+
+![A diagram of the flow](flow.png)
+
+> Quoted *text*
+> over two lines
+
+| Name | Count |
+|------|------:|
+| alpha | 1 |
+| b | 200 |
+
+```rust
+// # not a heading, *not* emphasis
+let x = `y`;
+```
+
+***
+
+Final line.
+
+[^1]: The guide covers **everything**.
+"#;
+
+  fn convert(markdown: &str) -> String {
+    markdown_to_plain_text(markdown, &PlainTextOptions::default())
+  }
+
+  #[test]
+  fn test_no_markdown_syntax_survives_in_prose() {
+    let text = convert(FIXTURE);
+    let code_start = text.find("// # not a heading").unwrap();
+    let code_end = text.find("let x = `y`;\n").unwrap() + "let x = `y`;\n".len();
+    for prose in [&text[..code_start], &text[code_end..]] {
+      assert!(
+        !prose.contains(['*', '#', '`']),
+        "syntax left in prose: {}",
+        prose
+      );
+    }
+    assert!(!text.contains("title:"));
+    assert!(text.starts_with("Release notes\n\nVersion 2.0 is out, with inline code and old new"));
+  }
+
+  #[test]
+  fn test_converts_structure() {
+    let text = convert(FIXTURE);
+    assert!(text.contains(
+      "See the guide (https://example.com/guide) or https://example.com. [The guide covers everything.]"
+    ));
+    assert!(text.contains("- First\n- Second\n  1. Nested one\n  2. Nested two\n- [x] Done\n"));
+    assert!(text.contains("\n\nA diagram of the flow\n\n"));
+    assert!(text.contains("\n\n  Quoted text\n  over two lines\n\n"));
+    assert!(text.contains("Name   Count\n-----  -----\nalpha      1\nb        200\n"));
+    // Code is kept as written, with a blank line before and after
+    assert!(text.contains("\n\n// # not a heading, *not* emphasis\nlet x = `y`;\n\nFinal line.\n"));
+    assert!(text.ends_with("Final line.\n"));
+  }
+
+  #[test]
+  fn test_links_can_leave_out_urls() {
+    let options = PlainTextOptions { link_urls: false };
+    assert_eq!(
+      markdown_to_plain_text("See [the guide](https://example.com).", &options),
+      "See the guide.\n"
+    );
+  }
+}
@@ -0,0 +1,281 @@
+use crate::settings::{Palette, PrintOptions};
+
+// Deepest heading level listed in a generated table of contents
+const TOC_MAX_LEVEL: u8 = 3;
+
+// Marks the document's first heading, which never gets a page break before it
+const FIRST_HEADING_ATTR: &str = "data-first-heading";
+
+// Styles of the generated table of contents. The page numbers rely on `target-counter`,
+// which only paged-media renderers (e.g. PDF export) support; elsewhere the links still work.
+const TOC_STYLE: &str = "\
+.toc { break-after: page; page-break-after: always; }
+.toc ol { list-style: none; padding-left: 0; }
+.toc li a { display: flex; color: inherit; text-decoration: none; }
+.toc li a::after { content: target-counter(attr(href), page); }
+.toc .toc-leader { flex: 1; border-bottom: 1px dotted; margin: 0 0.25em 0.3em; }
+.toc .toc-level-2 { padding-left: 1.5em; }
+.toc .toc-level-3 { padding-left: 3em; }
+";
+
+// Colors behind the theme style: background, text, code blocks, table headers, borders, links
+fn palette_variables(palette: Palette) -> &'static str {
+  match palette {
+    Palette::Light => {
+      "color-scheme: light; --export-background: #ffffff; --export-text: #1f2328; \
+       --export-code-background: #f6f8fa; --export-table-header: #f6f8fa; \
+       --export-border: #d0d7de; --export-link: #0969da;"
+    }
+    Palette::Dark => {
+      "color-scheme: dark; --export-background: #0d1117; --export-text: #e6edf3; \
+       --export-code-background: #161b22; --export-table-header: #21262d; \
+       --export-border: #30363d; --export-link: #4493f8;"
+    }
+  }
+}
+
+const THEME_RULES: &str = "\
+body { background: var(--export-background); color: var(--export-text); }
+pre, code { background: var(--export-code-background); }
+th { background: var(--export-table-header); }
+th, td { border-color: var(--export-border); }
+a { color: var(--export-link); }
+";
+
+// Colors of an exported document: `screen` in a browser, `paper` when printed
+pub fn theme_style(screen: Palette, paper: Palette) -> String {
+  let mut style = format!(":root {{ {} }}\n{}", palette_variables(screen), THEME_RULES);
+  if paper != screen {
+    style.push_str(&format!(
+      "@media print {{\n:root {{ {} }}\n}}\n",
+      palette_variables(paper)
+    ));
+  }
+  style
+}
+
+// An `<h1>`...`<h6>` element in rendered HTML
+#[derive(Debug, Clone, PartialEq)]
+struct HtmlHeading {
+  level: u8,
+  // Position of the `>` closing the opening tag, where attributes can be added
+  tag_end: usize,
+  id: Option<String>,
+  // Inner HTML with tags removed (entities stay escaped)
+  text: String,
+}
+
+// Byte offset of `needle` in `haystack` at or after `from`, ignoring ASCII case
+pub(crate) fn find_ignore_case(haystack: &str, needle: &str, from: usize) -> Option<usize> {
+  haystack
+    .get(from..)?
+    .to_ascii_lowercase()
+    .find(&needle.to_ascii_lowercase())
+    .map(|i| i + from)
+}
+
+// Value of `name="..."` (or single-quoted) in a tag's attributes, `lower` being them
+// lowercased. The name must follow whitespace, so `id` doesn't match `data-id=`.
+fn attribute<'a>(attributes: &'a str, lower: &str, name: &str) -> Option<&'a str> {
+  let pattern = format!("{}=", name);
+  let (index, _) = lower
+    .match_indices(&pattern)
+    .find(|(index, _)| lower[..*index].ends_with(|c: char| c.is_ascii_whitespace()))?;
+  let rest = &attributes[index + pattern.len()..];
+  let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+  let rest = &rest[1..];
+  rest.find(quote).map(|end| &rest[..end])
+}
+
+fn strip_tags(html: &str) -> String {
+  let mut text = String::with_capacity(html.len());
+  let mut in_tag = false;
+  for c in html.chars() {
+    match c {
+      '<' => in_tag = true,
+      '>' => in_tag = false,
+      _ if !in_tag => text.push(c),
+      _ => {}
+    }
+  }
+  text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn html_headings(html: &str) -> Vec<HtmlHeading> {
+  // ASCII lowercasing keeps byte offsets, so positions in `lower` index `html` too
+  let lower = html.to_ascii_lowercase();
+  let bytes = html.as_bytes();
+  let mut headings = Vec::new();
+  let mut from = 0;
+  while let Some(start) = lower[from..].find("<h").map(|i| i + from) {
+    from = start + 2;
+    let level = match bytes.get(start + 2) {
+      Some(digit @ b'1'..=b'6') => digit - b'0',
+      _ => continue,
+    };
+    // `<h1>` or `<h1 id="...">`, not `<header>`
+    if !matches!(bytes.get(start + 3), Some(b'>' | b' ' | b'\t' | b'\n')) {
+      continue;
+    }
+    let Some(tag_end) = html[start..].find('>').map(|i| i + start) else {
+      break;
+    };
+    let close = format!("</h{}", level);
+    let inner_end = lower[tag_end..]
+      .find(&close)
+      .map_or(html.len(), |i| i + tag_end);
+    let attributes = start + 3..tag_end;
+    headings.push(HtmlHeading {
+      level,
+      tag_end,
+      id: attribute(&html[attributes.clone()], &lower[attributes], "id").map(str::to_string),
+      text: strip_tags(&html[tag_end + 1..inner_end]),
+    });
+    from = inner_end;
+  }
+  headings
+}
+
+// Print styles for `options`, empty when they don't change anything
+pub fn print_style(options: &PrintOptions) -> String {
+  let mut broken: Vec<&str> = Vec::new();
+  if options.page_break_on_h1 {
+    broken.push("h1");
+  }
+  if options.page_break_on_h2 {
+    broken.push("h2");
+  }
+  let mut style = String::new();
+  if !broken.is_empty() {
+    style.push_str(&format!(
+      "@media print {{\n{} {{ break-before: page; page-break-before: always; }}\n\
+       [{}] {{ break-before: auto; page-break-before: auto; }}\n}}\n",
+      broken.join(", "),
+      FIRST_HEADING_ATTR
+    ));
+  }
+  if options.table_of_contents {
+    style.push_str(TOC_STYLE);
+  }
+  if let Some(size) = options.page_size.css() {
+    style.push_str(&format!("@page {{ size: {}; }}\n", size));
+  }
+  style
+}
+
+// The rendered document `body` laid out for `options`: the first heading marked so it
+// doesn't break, and a table of contents in front (headings without an id get one to link to)
+pub fn print_body(body: &str, options: &PrintOptions) -> String {
+  let breaks_pages = options.page_break_on_h1 || options.page_break_on_h2;
+  if !breaks_pages && !options.table_of_contents {
+    return body.to_string();
+  }
+  let headings = html_headings(body);
+
+  let mut insertions: Vec<(usize, String)> = Vec::new();
+  let mut toc_entries = Vec::new();
+  for (index, heading) in headings.iter().enumerate() {
+    let mut attributes = String::new();
+    if index == 0 && breaks_pages {
+      attributes.push_str(&format!(" {}", FIRST_HEADING_ATTR));
+    }
+    if options.table_of_contents && heading.level <= TOC_MAX_LEVEL {
+      let id = match &heading.id {
+        Some(id) => id.clone(),
+        None => {
+          let id = format!("section-{}", index + 1);
+          attributes.push_str(&format!(" id=\"{}\"", id));
+          id
+        }
+      };
+      toc_entries.push(format!(
+        "<li class=\"toc-level-{}\"><a href=\"#{}\"><span class=\"toc-title\">{}</span>\
+         <span class=\"toc-leader\"></span></a></li>",
+        heading.level, id, heading.text
+      ));
+    }
+    if !attributes.is_empty() {
+      insertions.push((heading.tag_end, attributes));
+    }
+  }
+
+  let mut laid_out = String::with_capacity(body.len());
+  if !toc_entries.is_empty() {
+    laid_out.push_str(&format!(
+      "<nav class=\"toc\">\n<h1 class=\"toc-heading\">Contents</h1>\n<ol>\n{}\n</ol>\n</nav>\n",
+      toc_entries.join("\n")
+    ));
+  }
+  let mut last = 0;
+  for (at, attributes) in insertions {
+    laid_out.push_str(&body[last..at]);
+    laid_out.push_str(&attributes);
+    last = at;
+  }
+  laid_out.push_str(&body[last..]);
+  laid_out
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const BODY: &str = "<h1>Intro</h1>\n<p>x</p>\n<h2 id=\"setup\">Set <em>up</em></h2>\n\
+    <header>not a heading</header>\n<H1>Usage</H1>\n<h4>Deep</h4>\n";
+
+  #[test]
+  fn test_page_breaks_skip_the_first_heading() {
+    let options = PrintOptions {
+      page_break_on_h1: true,
+      ..PrintOptions::default()
+    };
+    let style = print_style(&options);
+    assert!(style.contains("h1 { break-before: page;"));
+    assert!(!style.contains("h2"));
+    assert_eq!(
+      print_body(BODY, &options),
+      BODY.replacen("<h1>", "<h1 data-first-heading>", 1)
+    );
+    assert_eq!(print_style(&PrintOptions::default()), "");
+  }
+
+  #[test]
+  fn test_table_of_contents_links_every_heading() {
+    let options = PrintOptions {
+      page_break_on_h2: true,
+      table_of_contents: true,
+      ..PrintOptions::default()
+    };
+    let laid_out = print_body(BODY, &options);
+    assert!(laid_out.starts_with("<nav class=\"toc\">"));
+    assert!(laid_out.contains(
+      "<li class=\"toc-level-1\"><a href=\"#section-1\"><span class=\"toc-title\">Intro</span>"
+    ));
+    // Existing ids are reused, inline markup is dropped
+    assert!(laid_out.contains("<a href=\"#setup\"><span class=\"toc-title\">Set up</span>"));
+    assert!(laid_out.contains("<H1 id=\"section-3\">Usage</H1>"));
+    assert!(laid_out.contains("<h1 data-first-heading id=\"section-1\">Intro</h1>"));
+    assert!(!laid_out.contains("Deep</span>"));
+    assert!(print_style(&options).contains("target-counter"));
+    assert_eq!(
+      print_body("<p>No headings</p>", &options),
+      "<p>No headings</p>"
+    );
+  }
+
+  #[test]
+  fn test_heading_ids_need_a_whole_attribute_name() {
+    let headings = html_headings("<h2 data-id=\"x\">A</h2><H3 class=\"a\" ID='b'>B</H3>");
+    assert_eq!(headings[0].id, None);
+    assert_eq!(headings[1].id.as_deref(), Some("b"));
+    assert_eq!(headings[1].text, "B");
+  }
+
+  #[test]
+  fn test_theme_style_prints_its_own_palette() {
+    let style = theme_style(Palette::Dark, Palette::Light);
+    assert!(style.starts_with(":root { color-scheme: dark; --export-background: #0d1117;"));
+    assert!(style.contains("@media print {\n:root { color-scheme: light;"));
+    assert!(!theme_style(Palette::Light, Palette::Light).contains("@media print"));
+  }
+}
@@ -0,0 +1,262 @@
+use std::path::Path;
+use std::sync::Mutex;
+use tauri::menu::{MenuItem, PredefinedMenuItem};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::CommandResult;
+use crate::safe_mode::Startup;
+use crate::settings::SettingsState;
+use crate::{
+  app_store, check_paths_exist, display_recent_path, dynamic_menu, normalize_recent_path,
+  recent_paths_equal, safe_mode, workspace, RecentFileEntry, RECENT_EXISTS_TIMEOUT,
+};
+
+// Store key for the recently opened workspace roots, most recent first
+pub const RECENT_WORKSPACES_KEY: &str = "recent_workspaces";
+
+const MAX_RECENT_WORKSPACES: usize = 10;
+
+// File > Open Recent Workspace. Lists the workspaces as `open_recent_workspace:<path>` items.
+pub const MENU_ID: &str = "recent_workspaces_menu";
+pub const ITEM_ID_PREFIX: &str = "open_recent_workspace:";
+pub const CLEAR_ITEM_ID: &str = "clear_recent_workspaces";
+
+// Sent to every window with the root of a workspace opened from the menu, for the frontend
+// to show its tree
+const WORKSPACE_OPENED_EVENT: &str = "workspace-opened";
+
+pub struct RecentWorkspacesState {
+  recents: Mutex<Vec<String>>,
+  // The workspace reopened at startup, for the main window to show once it has loaded
+  startup: Mutex<Option<String>>,
+}
+
+// Move `root` to the top of the list, deduplicating and trimming to the max
+fn insert(recents: &mut Vec<String>, root: &str) {
+  let root = normalize_recent_path(root);
+  recents.retain(|p| !recent_paths_equal(p, &root));
+  recents.insert(0, root);
+  recents.truncate(MAX_RECENT_WORKSPACES);
+}
+
+fn load(app: &AppHandle) -> Vec<String> {
+  match app_store::open_store(app) {
+    Ok(store) => store
+      .get(RECENT_WORKSPACES_KEY)
+      .and_then(|value| serde_json::from_value::<Vec<String>>(value).ok())
+      // Missing folders stay listed (e.g. an unmounted drive); get_recent_workspaces flags them
+      .map(|roots| roots.into_iter().take(MAX_RECENT_WORKSPACES).collect())
+      .unwrap_or_default(),
+    Err(e) => {
+      log::error!("Failed to load store: {}", e);
+      Vec::new()
+    }
+  }
+}
+
+// Save the list and rebuild the menu from it. Safe mode started without the saved list and
+// keeps it for the next normal launch.
+fn save(app: &AppHandle, recents: &[String]) {
+  if let Err(e) = update_menu(app, recents) {
+    log::error!("Failed to update the recent workspaces menu: {}", e);
+  }
+  if !safe_mode::startup(app).store {
+    return;
+  }
+  if let Ok(value) = serde_json::to_value(recents) {
+    // Failures are logged and reported to the frontend by write_key
+    let _ = app_store::write_key(app, "save recent workspaces", RECENT_WORKSPACES_KEY, value);
+  }
+}
+
+fn entries(app: &AppHandle, recents: Vec<String>) -> Vec<RecentFileEntry> {
+  let exists = check_paths_exist(&recents, RECENT_EXISTS_TIMEOUT);
+  let home_dir = app.path().home_dir().ok();
+  recents
+    .into_iter()
+    .zip(exists)
+    .map(|(path, exists)| RecentFileEntry {
+      display_path: display_recent_path(&path, home_dir.as_deref()),
+      path,
+      exists,
+    })
+    .collect()
+}
+
+// Rebuild File > Open Recent Workspace from `recents`. Folders that are gone are listed but
+// disabled.
+fn update_menu(app: &AppHandle, recents: &[String]) -> tauri::Result<()> {
+  let entries = entries(app, recents.to_vec());
+  dynamic_menu::rebuild_submenu(app, MENU_ID, |submenu| {
+    if entries.is_empty() {
+      return submenu.set_enabled(false);
+    }
+    for entry in entries {
+      submenu.append(&MenuItem::with_id(
+        app,
+        format!("{}{}", ITEM_ID_PREFIX, entry.path),
+        entry.display_path,
+        entry.exists,
+        None::<&str>,
+      )?)?;
+    }
+    submenu.append(&PredefinedMenuItem::separator(app)?)?;
+    submenu.append(&MenuItem::with_id(
+      app,
+      CLEAR_ITEM_ID,
+      "Clear Menu",
+      true,
+      None::<&str>,
+    )?)?;
+    submenu.set_enabled(true)
+  })
+}
+
+// Called by workspace::open with the normalized root
+pub fn record(app: &AppHandle, root: &Path) {
+  let Some(state) = app.try_state::<RecentWorkspacesState>() else {
+    return;
+  };
+  let mut recents = state.recents.lock().unwrap();
+  insert(&mut recents, &root.to_string_lossy());
+  save(app, &recents);
+}
+
+fn contains_root(recents: &[String], root: &Path) -> bool {
+  let root = normalize_recent_path(&root.to_string_lossy());
+  recents.iter().any(|p| recent_paths_equal(p, &root))
+}
+
+// Whether `root` was opened as a workspace before, and so already approved by the user
+pub fn contains(app: &AppHandle, root: &Path) -> bool {
+  app
+    .try_state::<RecentWorkspacesState>()
+    .is_some_and(|state| contains_root(&state.recents.lock().unwrap(), root))
+}
+
+// Load the list and fill the menu, then reopen the last workspace if the settings ask for
+// it. Runs after the settings and menu are set up.
+pub fn init(app: &AppHandle, startup: &Startup) {
+  let recents = if startup.store { load(app) } else { Vec::new() };
+  let last = recents.first().cloned();
+  app.manage(RecentWorkspacesState {
+    recents: Mutex::new(recents),
+    startup: Mutex::new(None),
+  });
+  // Checking which folders still exist can stall on network drives, so the menu is filled
+  // after setup
+  let handle = app.clone();
+  tauri::async_runtime::spawn_blocking(move || {
+    let recents = handle
+      .state::<RecentWorkspacesState>()
+      .recents
+      .lock()
+      .unwrap()
+      .clone();
+    if let Err(e) = update_menu(&handle, &recents) {
+      log::error!("Failed to update the recent workspaces menu: {}", e);
+    }
+  });
+
+  let reopen = app
+    .state::<SettingsState>()
+    .0
+    .lock()
+    .unwrap()
+    .open_last_workspace_on_start;
+  let Some(last) = last.filter(|_| reopen) else {
+    return;
+  };
+  match workspace::open(app, Path::new(&last)) {
+    Ok(()) => *app.state::<RecentWorkspacesState>().startup.lock().unwrap() = Some(last),
+    Err(e) => log::warn!("Failed to reopen the last workspace {}: {}", last, e),
+  }
+}
+
+// Open a workspace picked in File > Open Recent Workspace
+pub fn open_from_menu(app: &AppHandle, root: &str) {
+  match workspace::open(app, Path::new(root)) {
+    Ok(()) => {
+      let _ = app.emit(WORKSPACE_OPENED_EVENT, root);
+    }
+    Err(e) => log::error!("Failed to open the workspace {}: {}", root, e),
+  }
+}
+
+pub fn clear(app: &AppHandle) {
+  let state = app.state::<RecentWorkspacesState>();
+  let mut recents = state.recents.lock().unwrap();
+  recents.clear();
+  save(app, &recents);
+}
+
+// Recent workspace roots, each flagged with whether the folder currently exists
+#[tauri::command]
+pub async fn get_recent_workspaces(
+  app: AppHandle,
+  state: tauri::State<'_, RecentWorkspacesState>,
+) -> CommandResult<Vec<RecentFileEntry>> {
+  let recents = state.recents.lock().unwrap().clone();
+  Ok(entries(&app, recents))
+}
+
+// Remove a workspace from the list (e.g. a missing one the user gave up on)
+#[tauri::command]
+pub async fn remove_recent_workspace(
+  app: AppHandle,
+  state: tauri::State<'_, RecentWorkspacesState>,
+  path: String,
+) -> CommandResult<()> {
+  let path = normalize_recent_path(&path);
+  let mut recents = state.recents.lock().unwrap();
+  recents.retain(|p| !recent_paths_equal(p, &path));
+  save(&app, &recents);
+  Ok(())
+}
+
+// The workspace reopened at startup (see the open_last_workspace_on_start setting), if any
+#[tauri::command]
+pub async fn get_startup_workspace(
+  state: tauri::State<'_, RecentWorkspacesState>,
+) -> CommandResult<Option<String>> {
+  Ok(state.startup.lock().unwrap().clone())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_insert_moves_to_top_and_trims() {
+    let mut recents = Vec::new();
+    for i in 0..12 {
+      insert(&mut recents, &format!("/notes/{}", i));
+    }
+    assert_eq!(recents.len(), MAX_RECENT_WORKSPACES);
+    assert_eq!(recents[0], normalize_recent_path("/notes/11"));
+
+    insert(&mut recents, "/notes/5");
+    assert_eq!(recents[0], normalize_recent_path("/notes/5"));
+    assert_eq!(recents.len(), MAX_RECENT_WORKSPACES);
+    assert_eq!(
+      recents
+        .iter()
+        .filter(|p| recent_paths_equal(p, &normalize_recent_path("/notes/5")))
+        .count(),
+      1
+    );
+  }
+
+  #[test]
+  fn test_contains_root_matches_normalized_paths() {
+    let mut recents = Vec::new();
+    insert(&mut recents, "/notes/work");
+    assert!(contains_root(&recents, Path::new("/notes/work")));
+    assert!(contains_root(
+      &recents,
+      Path::new("/notes/personal/../work/")
+    ));
+    assert!(!contains_root(&recents, Path::new("/notes")));
+    assert!(!contains_root(&recents, Path::new("/notes/work/sub")));
+  }
+}
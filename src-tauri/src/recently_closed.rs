@@ -0,0 +1,218 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use crate::error::CommandResult;
+use crate::{normalize_recent_path, now_millis, recent_paths_equal};
+
+// How many closed documents "Reopen Closed" can go back through
+const MAX_RECENTLY_CLOSED: usize = 10;
+
+// Total size of the content snapshots we hold in memory. Past it the oldest snapshots are
+// dropped; a closed file can still be reopened from disk.
+const MAX_SNAPSHOT_BYTES: usize = 4 * 1024 * 1024;
+
+// A document that was closed (replaced in its window, or its window closed)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClosedDocument {
+  // None for a document that was never saved
+  pub path: Option<String>,
+  pub title: String,
+  // Milliseconds since the epoch
+  pub closed_at: u64,
+  // What the editor held, for documents closed unsaved or with unsaved changes
+  pub content: Option<String>,
+}
+
+// A closed document as listed for the frontend, without its content
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClosedDocumentInfo {
+  pub path: Option<String>,
+  pub title: String,
+  pub closed_at: u64,
+  pub has_snapshot: bool,
+}
+
+// Most recently closed first
+#[derive(Debug, Default)]
+struct RecentlyClosed(VecDeque<ClosedDocument>);
+
+impl RecentlyClosed {
+  fn push(&mut self, document: ClosedDocument) {
+    if let Some(path) = &document.path {
+      self.0.retain(|closed| {
+        !closed
+          .path
+          .as_ref()
+          .is_some_and(|p| recent_paths_equal(p, path))
+      });
+    }
+    self.0.push_front(document);
+    self.0.truncate(MAX_RECENTLY_CLOSED);
+    self.enforce_snapshot_budget();
+  }
+
+  // Drop the oldest snapshots until the rest fit. An unsaved document without its snapshot
+  // has nothing left to reopen, so it goes entirely.
+  fn enforce_snapshot_budget(&mut self) {
+    let mut total: usize = self
+      .0
+      .iter()
+      .filter_map(|closed| closed.content.as_ref().map(String::len))
+      .sum();
+    while total > MAX_SNAPSHOT_BYTES {
+      let Some(index) = self.0.iter().rposition(|closed| closed.content.is_some()) else {
+        break;
+      };
+      total -= self.0[index]
+        .content
+        .take()
+        .map_or(0, |content| content.len());
+      if self.0[index].path.is_none() {
+        self.0.remove(index);
+      }
+    }
+  }
+
+  // The file was saved since it was closed, so its snapshot would bring back stale edits
+  fn drop_snapshots(&mut self, path: &str) {
+    for closed in self.0.iter_mut() {
+      if closed
+        .path
+        .as_ref()
+        .is_some_and(|p| recent_paths_equal(p, path))
+      {
+        closed.content = None;
+      }
+    }
+  }
+
+  fn list(&self) -> Vec<ClosedDocumentInfo> {
+    self
+      .0
+      .iter()
+      .map(|closed| ClosedDocumentInfo {
+        path: closed.path.clone(),
+        title: closed.title.clone(),
+        closed_at: closed.closed_at,
+        has_snapshot: closed.content.is_some(),
+      })
+      .collect()
+  }
+}
+
+#[derive(Default)]
+pub struct RecentlyClosedState(Mutex<RecentlyClosed>);
+
+impl RecentlyClosedState {
+  // Called after a successful save of `path`
+  pub fn saved(&self, path: &str) {
+    self
+      .0
+      .lock()
+      .unwrap()
+      .drop_snapshots(&normalize_recent_path(path));
+  }
+}
+
+// Record a document leaving its window. `content` is only passed for documents that were
+// unsaved or had unsaved changes.
+#[tauri::command]
+pub async fn record_closed_document(
+  state: tauri::State<'_, RecentlyClosedState>,
+  path: Option<String>,
+  title: String,
+  content: Option<String>,
+) -> CommandResult<()> {
+  state.0.lock().unwrap().push(ClosedDocument {
+    path: path.as_deref().map(normalize_recent_path),
+    title,
+    closed_at: now_millis(),
+    content,
+  });
+  Ok(())
+}
+
+#[tauri::command]
+pub async fn get_recently_closed(
+  state: tauri::State<'_, RecentlyClosedState>,
+) -> CommandResult<Vec<ClosedDocumentInfo>> {
+  Ok(state.0.lock().unwrap().list())
+}
+
+// Take a closed document off the list to open it again (0 is the most recently closed).
+// None if there's nothing at `index`.
+#[tauri::command]
+pub async fn reopen_closed(
+  state: tauri::State<'_, RecentlyClosedState>,
+  index: usize,
+) -> CommandResult<Option<ClosedDocument>> {
+  Ok(state.0.lock().unwrap().0.remove(index))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn closed(path: Option<&str>, content: Option<&str>) -> ClosedDocument {
+    ClosedDocument {
+      path: path.map(str::to_string),
+      title: "Notes".to_string(),
+      closed_at: 0,
+      content: content.map(str::to_string),
+    }
+  }
+
+  #[test]
+  fn test_push_dedupes_paths_and_keeps_the_newest() {
+    let mut recently_closed = RecentlyClosed::default();
+    recently_closed.push(closed(Some("/notes/a.md"), None));
+    for _ in 0..MAX_RECENTLY_CLOSED {
+      recently_closed.push(closed(None, Some("draft")));
+    }
+    assert_eq!(recently_closed.0.len(), MAX_RECENTLY_CLOSED);
+    assert!(recently_closed.0.iter().all(|c| c.path.is_none()));
+
+    recently_closed.push(closed(Some("/notes/b.md"), Some("edited")));
+    recently_closed.push(closed(Some("/notes/b.md"), None));
+    let with_path: Vec<_> = recently_closed
+      .list()
+      .into_iter()
+      .filter(|c| c.path.is_some())
+      .collect();
+    assert_eq!(with_path.len(), 1);
+    assert!(!with_path[0].has_snapshot);
+  }
+
+  #[test]
+  fn test_snapshot_budget_drops_oldest_content() {
+    let big = "x".repeat(MAX_SNAPSHOT_BYTES / 2 + 1);
+    let mut recently_closed = RecentlyClosed::default();
+    recently_closed.push(closed(None, Some(&big)));
+    recently_closed.push(closed(Some("/notes/a.md"), Some(&big)));
+    recently_closed.push(closed(Some("/notes/b.md"), Some(&big)));
+
+    // The unsaved one had nothing but its snapshot; a.md can still be reopened from disk
+    let paths: Vec<_> = recently_closed
+      .list()
+      .into_iter()
+      .map(|c| (c.path, c.has_snapshot))
+      .collect();
+    assert_eq!(
+      paths,
+      vec![
+        (Some("/notes/b.md".to_string()), true),
+        (Some("/notes/a.md".to_string()), false),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_saving_drops_stale_snapshots() {
+    let mut recently_closed = RecentlyClosed::default();
+    recently_closed.push(closed(Some("/notes/a.md"), Some("old edits")));
+    recently_closed.drop_snapshots("/notes/a.md");
+    assert_eq!(recently_closed.0[0].content, None);
+    assert_eq!(recently_closed.0[0].path.as_deref(), Some("/notes/a.md"));
+  }
+}
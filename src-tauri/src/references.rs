@@ -0,0 +1,774 @@
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+
+use crate::error::CommandResult;
+use crate::includes::{atx_heading, is_fence};
+use crate::typography::protected_ranges;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReferenceKind {
+  // `[^label]`
+  Footnote,
+  // `[text][label]`, `[label][]` and `[label]`
+  Link,
+}
+
+// Where the definitions end up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DefinitionPlacement {
+  // All at the end of the document
+  #[default]
+  End,
+  // At the end of the section (heading to heading) that first uses them
+  Section,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TidyOptions {
+  pub placement: DefinitionPlacement,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Relabel {
+  pub kind: ReferenceKind,
+  pub from: String,
+  pub to: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LabelAt {
+  pub kind: ReferenceKind,
+  pub label: String,
+  // 1-based line in the original document
+  pub line: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReferenceReport {
+  pub relabeled: Vec<Relabel>,
+  // Definitions nothing refers to
+  pub removed_orphans: Vec<LabelAt>,
+  // Later definitions of a label that was already defined (the first one wins)
+  pub removed_duplicates: Vec<LabelAt>,
+  // References without a definition, left as they are
+  pub undefined: Vec<LabelAt>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TidiedReferences {
+  pub content: String,
+  pub report: ReferenceReport,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Form {
+  Footnote,
+  Full,
+  Collapsed,
+  Shortcut,
+}
+
+#[derive(Debug)]
+struct Reference {
+  kind: ReferenceKind,
+  key: String,
+  form: Form,
+  // Byte range of the label in the document
+  label: Range<usize>,
+  line: usize,
+}
+
+#[derive(Debug)]
+struct Definition {
+  kind: ReferenceKind,
+  key: String,
+  label: Range<usize>,
+  // Line indices of the definition and its continuation lines
+  lines: Range<usize>,
+  line: usize,
+}
+
+// Labels match case-insensitively with runs of whitespace collapsed
+pub(crate) fn normalize(label: &str) -> String {
+  label
+    .split_whitespace()
+    .collect::<Vec<_>>()
+    .join(" ")
+    .to_lowercase()
+}
+
+fn is_number(key: &str) -> bool {
+  !key.is_empty() && key.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_blank(line: &str) -> bool {
+  line.trim().is_empty()
+}
+
+fn is_indented(line: &str) -> bool {
+  line.starts_with("    ") || line.starts_with('\t')
+}
+
+fn is_protected(protected: &[Range<usize>], pos: usize) -> bool {
+  let index = protected.partition_point(|range| range.start <= pos);
+  index > 0 && protected[index - 1].contains(&pos)
+}
+
+// `[^label]: ` or `[label]: destination` starting a line, with the label's byte range
+fn definition_label(line: &str) -> Option<(ReferenceKind, Range<usize>)> {
+  let indent = line.len() - line.trim_start_matches(' ').len();
+  if indent > 3 {
+    return None;
+  }
+  let close = indent + 1 + line[indent..].strip_prefix('[')?.find(']')?;
+  if !line[close + 1..].starts_with(':') {
+    return None;
+  }
+  let (kind, label) = match line[indent + 1..].strip_prefix('^') {
+    Some(_) => (ReferenceKind::Footnote, indent + 2..close),
+    None => (ReferenceKind::Link, indent + 1..close),
+  };
+  let text = &line[label.clone()];
+  let valid = match kind {
+    ReferenceKind::Footnote => !text.is_empty() && !text.contains(char::is_whitespace),
+    ReferenceKind::Link => !is_blank(text) && !is_blank(&line[close + 2..]),
+  };
+  (valid && !text.contains('[')).then_some((kind, label))
+}
+
+// The footnote and link reference definitions outside code: kind, normalized label and the
+// byte range of the definition with its continuation lines
+pub(crate) fn definition_spans(content: &str) -> Vec<(ReferenceKind, String, Range<usize>)> {
+  let document = Document::new(content);
+  document
+    .definitions()
+    .into_iter()
+    .map(|(definition, _)| {
+      let lines = definition.lines;
+      let range = document.lines[lines.start].start..document.lines[lines.end - 1].end;
+      (definition.kind, definition.key, range)
+    })
+    .collect()
+}
+
+// References in one line (without its newline) from byte `from`. `start` is the line's
+// offset in the document.
+fn scan_line(
+  line: &str,
+  from: usize,
+  start: usize,
+  line_number: usize,
+  protected: &[Range<usize>],
+  references: &mut Vec<Reference>,
+) {
+  let bytes = line.as_bytes();
+  let mut push = |kind, form, label: Range<usize>| {
+    references.push(Reference {
+      kind,
+      key: normalize(&line[label.clone()]),
+      form,
+      label: start + label.start..start + label.end,
+      line: line_number,
+    })
+  };
+  let mut i = from;
+  while i < bytes.len() {
+    if bytes[i] != b'[' || is_protected(protected, start + i) || (i > 0 && bytes[i - 1] == b'\\') {
+      i += 1;
+      continue;
+    }
+    let Some(close) = line[i + 1..].find(['[', ']']).map(|close| i + 1 + close) else {
+      break;
+    };
+    // Nested brackets: look at the inner ones instead
+    if bytes[close] == b'[' {
+      i = close;
+      continue;
+    }
+    let inner = i + 1..close;
+    i = close + 1;
+    if let Some(label) = line[inner.clone()].strip_prefix('^') {
+      if !label.is_empty() && !label.contains(char::is_whitespace) {
+        push(
+          ReferenceKind::Footnote,
+          Form::Footnote,
+          inner.start + 1..close,
+        );
+      }
+      continue;
+    }
+    // Task boxes and empty brackets
+    if is_blank(&line[inner.clone()]) {
+      continue;
+    }
+    let after = &line[close + 1..];
+    // Inline links have their destination right there
+    if after.starts_with('(') {
+      continue;
+    }
+    if let Some(second) = after.strip_prefix('[') {
+      if let Some(end) = second.find(']').map(|end| close + 2 + end) {
+        let label = close + 2..end;
+        if !line[label.clone()].contains('[') {
+          i = end + 1;
+          if label.is_empty() {
+            push(ReferenceKind::Link, Form::Collapsed, inner);
+          } else {
+            push(ReferenceKind::Link, Form::Full, label);
+          }
+          continue;
+        }
+      }
+    }
+    push(ReferenceKind::Link, Form::Shortcut, inner);
+  }
+}
+
+struct Document<'a> {
+  content: &'a str,
+  // Byte ranges of the lines, newlines included
+  lines: Vec<Range<usize>>,
+  protected: Vec<Range<usize>>,
+}
+
+impl<'a> Document<'a> {
+  fn new(content: &'a str) -> Self {
+    let mut offset = 0;
+    let lines = content
+      .split_inclusive('\n')
+      .map(|line| {
+        offset += line.len();
+        offset - line.len()..offset
+      })
+      .collect();
+    let mut protected = protected_ranges(content);
+    protected.sort_by_key(|range| range.start);
+    Document {
+      content,
+      lines,
+      protected,
+    }
+  }
+
+  fn text(&self, index: usize) -> &'a str {
+    self.content[self.lines[index].clone()].trim_end_matches(['\n', '\r'])
+  }
+
+  fn starts_protected(&self, index: usize) -> bool {
+    is_protected(&self.protected, self.lines[index].start)
+  }
+
+  // End of a footnote definition starting at `index`: indented continuation paragraphs
+  // and lazy continuation lines
+  fn footnote_end(&self, index: usize) -> usize {
+    let mut end = index + 1;
+    while end < self.lines.len() {
+      let text = self.text(end);
+      if is_blank(text) {
+        match (end..self.lines.len()).find(|next| !is_blank(self.text(*next))) {
+          Some(next) if is_indented(self.text(next)) => end = next + 1,
+          _ => break,
+        }
+      } else if is_indented(text)
+        || (!is_blank(self.text(end - 1))
+          && definition_label(text).is_none()
+          && atx_heading(text).is_none()
+          && !is_fence(text))
+      {
+        end += 1;
+      } else {
+        break;
+      }
+    }
+    end
+  }
+
+  // Definitions, each with the lines it's removed with (its blank lines after it, unless
+  // that would join the paragraphs around it)
+  fn definitions(&self) -> Vec<(Definition, Range<usize>)> {
+    let mut definitions = Vec::new();
+    // Whether the previous line is paragraph text, which link definitions can't interrupt
+    let mut after_text = false;
+    let mut index = 0;
+    while index < self.lines.len() {
+      let text = self.text(index);
+      let definition = (!self.starts_protected(index))
+        .then(|| definition_label(text))
+        .flatten()
+        .filter(|(kind, _)| !(after_text && *kind == ReferenceKind::Link));
+      if let Some((kind, label)) = definition {
+        let end = match kind {
+          ReferenceKind::Footnote => self.footnote_end(index),
+          ReferenceKind::Link => index + 1,
+        };
+        let mut removed_end = end;
+        if !after_text {
+          while removed_end < self.lines.len() && is_blank(self.text(removed_end)) {
+            removed_end += 1;
+          }
+        }
+        let start = self.lines[index].start;
+        definitions.push((
+          Definition {
+            kind,
+            key: normalize(&text[label.clone()]),
+            label: start + label.start..start + label.end,
+            lines: index..end,
+            line: index + 1,
+          },
+          index..removed_end,
+        ));
+        index = removed_end;
+        after_text = false;
+        continue;
+      }
+      after_text = !is_blank(text) && (self.starts_protected(index) || atx_heading(text).is_none());
+      index += 1;
+    }
+    definitions
+  }
+
+  fn references(&self, definitions: &[(Definition, Range<usize>)]) -> Vec<Reference> {
+    // Where scanning starts on definition lines: after `]:` for footnotes, which can
+    // refer to others, and nowhere for link definitions
+    let starts: HashMap<usize, usize> = definitions
+      .iter()
+      .map(|(definition, _)| {
+        let line = definition.lines.start;
+        let from = match definition.kind {
+          ReferenceKind::Footnote => definition.label.end + 2 - self.lines[line].start,
+          ReferenceKind::Link => self.text(line).len(),
+        };
+        (line, from)
+      })
+      .collect();
+    let mut references = Vec::new();
+    for index in 0..self.lines.len() {
+      let from = starts.get(&index).copied().unwrap_or(0);
+      let start = self.lines[index].start;
+      scan_line(
+        self.text(index),
+        from,
+        start,
+        index + 1,
+        &self.protected,
+        &mut references,
+      );
+    }
+    references
+  }
+
+  // Copy a byte range of the document with the labels in it replaced
+  fn render(&self, range: Range<usize>, replacements: &[(Range<usize>, String)]) -> String {
+    let mut rendered = String::new();
+    let mut offset = range.start;
+    let first = replacements.partition_point(|(label, _)| label.start < range.start);
+    for (label, replacement) in &replacements[first..] {
+      if label.end > range.end {
+        break;
+      }
+      rendered.push_str(&self.content[offset..label.start]);
+      rendered.push_str(replacement);
+      offset = label.end;
+    }
+    rendered.push_str(&self.content[offset..range.end]);
+    rendered
+  }
+}
+
+// Text up to the end of its last non-blank line
+fn without_trailing_blank_lines(text: &str) -> &str {
+  let mut end = text.len();
+  for line in text.split_inclusive('\n').rev() {
+    if !is_blank(line) {
+      break;
+    }
+    end -= line.len();
+  }
+  &text[..end]
+}
+
+// Renumber footnotes in order of first use, gather footnote and link definitions at the
+// end of the document or of their section, and drop the ones nothing refers to. Nothing
+// but labels and definitions changes, and tidying a tidied document changes nothing.
+pub fn tidy(content: &str, options: &TidyOptions) -> TidiedReferences {
+  let newline = if content.contains("\r\n") {
+    "\r\n"
+  } else {
+    "\n"
+  };
+  let document = Document::new(content);
+  let definitions = document.definitions();
+  let references = document.references(&definitions);
+  let mut report = ReferenceReport::default();
+
+  // The first definition of a label wins
+  let mut defined: HashMap<(ReferenceKind, &str), usize> = HashMap::new();
+  for (index, (definition, _)) in definitions.iter().enumerate() {
+    let id = (definition.kind, definition.key.as_str());
+    match defined.entry(id) {
+      Entry::Occupied(_) => report.removed_duplicates.push(LabelAt {
+        kind: definition.kind,
+        label: document.content[definition.label.clone()].to_string(),
+        line: definition.line,
+      }),
+      Entry::Vacant(entry) => {
+        entry.insert(index);
+      }
+    }
+  }
+
+  // Definitions in order of first use, with the line of that use
+  let mut used: Vec<(usize, usize)> = Vec::new();
+  let mut seen = HashSet::new();
+  // Link labels that are also the link text can't be renamed
+  let mut fixed = HashSet::new();
+  let mut undefined = HashSet::new();
+  for reference in &references {
+    let id = (reference.kind, reference.key.as_str());
+    match defined.get(&id) {
+      Some(&definition) => {
+        if seen.insert(definition) {
+          used.push((definition, reference.line));
+        }
+        if matches!(reference.form, Form::Collapsed | Form::Shortcut) {
+          fixed.insert(definition);
+        }
+      }
+      // Brackets that aren't a defined label are just text
+      None if reference.form == Form::Shortcut => {}
+      None => {
+        if undefined.insert(id) {
+          report.undefined.push(LabelAt {
+            kind: reference.kind,
+            label: document.content[reference.label.clone()].to_string(),
+            line: reference.line,
+          });
+        }
+      }
+    }
+  }
+  for (index, (definition, _)) in definitions.iter().enumerate() {
+    if defined.get(&(definition.kind, definition.key.as_str())) == Some(&index)
+      && !seen.contains(&index)
+    {
+      report.removed_orphans.push(LabelAt {
+        kind: definition.kind,
+        label: document.content[definition.label.clone()].to_string(),
+        line: definition.line,
+      });
+    }
+  }
+
+  // Numbered labels become 1, 2, 3... skipping numbers that stay as they are
+  let mut labels: HashMap<usize, String> = HashMap::new();
+  for kind in [ReferenceKind::Footnote, ReferenceKind::Link] {
+    let kept: HashSet<u64> = undefined
+      .iter()
+      .filter(|(undefined_kind, _)| *undefined_kind == kind)
+      .map(|(_, key)| *key)
+      .chain(
+        fixed
+          .iter()
+          .map(|index| definitions[*index].0.key.as_str())
+          .filter(|_| kind == ReferenceKind::Link),
+      )
+      .filter(|key| is_number(key))
+      .filter_map(|key| key.parse().ok())
+      .collect();
+    let mut next = 0;
+    for (index, _) in &used {
+      let definition = &definitions[*index].0;
+      if definition.kind != kind || !is_number(&definition.key) || fixed.contains(index) {
+        continue;
+      }
+      next += 1;
+      while kept.contains(&next) {
+        next += 1;
+      }
+      let from = &document.content[definition.label.clone()];
+      let to = next.to_string();
+      if from != to {
+        report.relabeled.push(Relabel {
+          kind,
+          from: from.to_string(),
+          to: to.clone(),
+        });
+        labels.insert(*index, to);
+      }
+    }
+  }
+  let mut replacements: Vec<(Range<usize>, String)> = Vec::new();
+  for (index, label) in &labels {
+    replacements.push((definitions[*index].0.label.clone(), label.clone()));
+  }
+  for reference in &references {
+    let id = (reference.kind, reference.key.as_str());
+    if let Some(label) = defined.get(&id).and_then(|index| labels.get(index)) {
+      replacements.push((reference.label.clone(), label.clone()));
+    }
+  }
+  replacements.sort_by_key(|(label, _)| label.start);
+
+  // Split what's left of the document into sections, then put each definition back at
+  // the end of its section
+  let mut removed = vec![false; document.lines.len()];
+  for (_, lines) in &definitions {
+    removed[lines.clone()].fill(true);
+  }
+  let mut sections = vec![String::new()];
+  let mut section_of_line = Vec::with_capacity(document.lines.len());
+  for (index, line) in document.lines.iter().enumerate() {
+    if !removed[index]
+      && !document.starts_protected(index)
+      && atx_heading(document.text(index)).is_some()
+    {
+      sections.push(String::new());
+    }
+    section_of_line.push(sections.len() - 1);
+    if !removed[index] {
+      let section = sections.last_mut().unwrap();
+      section.push_str(&document.render(line.clone(), &replacements));
+    }
+  }
+  let mut moved: Vec<[Vec<String>; 2]> = vec![Default::default(); sections.len()];
+  for (index, first_use) in &used {
+    let definition = &definitions[*index].0;
+    let section = match options.placement {
+      DefinitionPlacement::End => sections.len() - 1,
+      DefinitionPlacement::Section => section_of_line[first_use - 1],
+    };
+    let lines =
+      document.lines[definition.lines.start].start..document.lines[definition.lines.end - 1].end;
+    let text = document.render(lines, &replacements);
+    let slot = match definition.kind {
+      ReferenceKind::Link => 0,
+      ReferenceKind::Footnote => 1,
+    };
+    moved[section][slot].push(text.trim_end_matches(['\n', '\r']).to_string());
+  }
+
+  let mut tidied = String::with_capacity(content.len());
+  let last = sections.len() - 1;
+  for (index, (section, [links, footnotes])) in sections.iter().zip(&moved).enumerate() {
+    if links.is_empty() && footnotes.is_empty() {
+      tidied.push_str(section);
+      continue;
+    }
+    let text = without_trailing_blank_lines(section);
+    tidied.push_str(text);
+    if !text.is_empty() {
+      if !text.ends_with('\n') {
+        tidied.push_str(newline);
+      }
+      tidied.push_str(newline);
+    }
+    for (group, entries) in [links, footnotes].into_iter().enumerate() {
+      if entries.is_empty() {
+        continue;
+      }
+      if group == 1 && !links.is_empty() {
+        tidied.push_str(newline);
+      }
+      for (position, definition) in entries.iter().enumerate() {
+        // A blank line keeps multi-paragraph footnotes apart
+        if position > 0 && (definition.contains('\n') || entries[position - 1].contains('\n')) {
+          tidied.push_str(newline);
+        }
+        tidied.push_str(definition);
+        tidied.push_str(newline);
+      }
+    }
+    if index < last {
+      tidied.push_str(newline);
+    }
+  }
+  TidiedReferences {
+    content: tidied,
+    report,
+  }
+}
+
+#[tauri::command]
+pub async fn tidy_references(
+  content: String,
+  options: Option<TidyOptions>,
+) -> CommandResult<TidiedReferences> {
+  Ok(tidy(&content, &options.unwrap_or_default()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn tidy_end(content: &str) -> TidiedReferences {
+    tidy(content, &TidyOptions::default())
+  }
+
+  fn assert_idempotent(content: &str, options: &TidyOptions) {
+    let once = tidy(content, options).content;
+    assert_eq!(tidy(&once, options).content, once);
+  }
+
+  #[test]
+  fn test_renumbers_footnotes_in_order_of_first_use() {
+    let content = "Intro.[^3]\n\n[^3]: Third.\n\nMore[^1] and[^7], again[^3].\n\n[^7]: Seventh.\n[^1]: First.\n";
+    let tidied = tidy_end(content);
+    assert_eq!(
+      tidied.content,
+      "Intro.[^1]\n\nMore[^2] and[^3], again[^1].\n\n[^1]: Third.\n[^2]: First.\n[^3]: Seventh.\n"
+    );
+    assert_eq!(
+      tidied.report.relabeled,
+      vec![
+        Relabel {
+          kind: ReferenceKind::Footnote,
+          from: "3".to_string(),
+          to: "1".to_string(),
+        },
+        Relabel {
+          kind: ReferenceKind::Footnote,
+          from: "1".to_string(),
+          to: "2".to_string(),
+        },
+        Relabel {
+          kind: ReferenceKind::Footnote,
+          from: "7".to_string(),
+          to: "3".to_string(),
+        },
+      ]
+    );
+    assert_idempotent(content, &TidyOptions::default());
+  }
+
+  #[test]
+  fn test_named_footnotes_keep_their_label() {
+    let content = "A[^note] and B[^2].\n\n[^2]: Two.\n[^note]: Named.\n";
+    assert_eq!(
+      tidy_end(content).content,
+      "A[^note] and B[^1].\n\n[^note]: Named.\n[^1]: Two.\n"
+    );
+  }
+
+  #[test]
+  fn test_reports_orphans_duplicates_and_undefined() {
+    let content = "Used[^1], missing[^9] and [a link][gone].\n\n[^1]: One.\n[^1]: Again.\n[^2]: Nobody.\n\n[unused]: https://example.com\n";
+    let tidied = tidy_end(content);
+    assert_eq!(
+      tidied.content,
+      "Used[^1], missing[^9] and [a link][gone].\n\n[^1]: One.\n"
+    );
+    let labels = |found: &[LabelAt]| {
+      found
+        .iter()
+        .map(|at| (at.label.clone(), at.line))
+        .collect::<Vec<_>>()
+    };
+    assert_eq!(
+      labels(&tidied.report.removed_duplicates),
+      vec![("1".to_string(), 4)]
+    );
+    assert_eq!(
+      labels(&tidied.report.removed_orphans),
+      vec![("2".to_string(), 5), ("unused".to_string(), 7)]
+    );
+    assert_eq!(
+      labels(&tidied.report.undefined),
+      vec![("9".to_string(), 1), ("gone".to_string(), 1)]
+    );
+  }
+
+  #[test]
+  fn test_undefined_numbers_are_not_reused() {
+    let content = "A[^5] B[^2]\n\n[^5]: Five.\n";
+    assert_eq!(tidy_end(content).content, "A[^1] B[^2]\n\n[^1]: Five.\n");
+    let content = "A[^2] B[^1]\n\n[^2]: Two.\n";
+    assert_eq!(tidy_end(content).content, "A[^2] B[^1]\n\n[^2]: Two.\n");
+  }
+
+  #[test]
+  fn test_link_definitions_follow_first_use() {
+    let content = "[b]: https://b.example\n\nSee [the docs][2], [Home][] and [site][1].\n\n[Home]: https://home.example\n[1]: https://one.example \"One\"\n[2]: https://two.example\n\nEnd [b].\n";
+    let tidied = tidy_end(content);
+    assert_eq!(
+      tidied.content,
+      "See [the docs][1], [Home][] and [site][2].\n\nEnd [b].\n\n[1]: https://two.example\n[Home]: https://home.example\n[2]: https://one.example \"One\"\n[b]: https://b.example\n"
+    );
+    assert_idempotent(content, &TidyOptions::default());
+  }
+
+  #[test]
+  fn test_link_labels_used_as_text_are_not_renamed() {
+    let content = "[2] and [x][1].\n\n[1]: https://one.example\n[2]: https://two.example\n";
+    assert_eq!(
+      tidy_end(content).content,
+      "[2] and [x][1].\n\n[2]: https://two.example\n[1]: https://one.example\n"
+    );
+  }
+
+  #[test]
+  fn test_links_then_footnotes_at_the_end() {
+    let content = "# Title\n\nText[^a] with [a link][x].\n\n[^a]: Note.\n\n[x]: https://x.example\n\n## Next\n\nMore.\n";
+    assert_eq!(
+      tidy_end(content).content,
+      "# Title\n\nText[^a] with [a link][x].\n\n## Next\n\nMore.\n\n[x]: https://x.example\n\n[^a]: Note.\n"
+    );
+  }
+
+  #[test]
+  fn test_section_placement() {
+    let options = TidyOptions {
+      placement: DefinitionPlacement::Section,
+    };
+    let content = "# One\n\nFirst[^2].\n\n# Two\n\nSecond[^1].\n\n[^1]: One.\n[^2]: Two.\n";
+    assert_eq!(
+      tidy(content, &options).content,
+      "# One\n\nFirst[^1].\n\n[^1]: Two.\n\n# Two\n\nSecond[^2].\n\n[^2]: One.\n"
+    );
+    assert_idempotent(content, &options);
+  }
+
+  #[test]
+  fn test_multi_paragraph_footnotes_move_whole() {
+    let content = "A[^long] B[^1]\n\n[^long]: First paragraph\ncontinued lazily.\n\n    Second paragraph.\n\n[^1]: Short.\n\nAfter.\n";
+    let tidied = tidy_end(content).content;
+    assert_eq!(
+      tidied,
+      "A[^long] B[^1]\n\nAfter.\n\n[^long]: First paragraph\ncontinued lazily.\n\n    Second paragraph.\n\n[^1]: Short.\n"
+    );
+    assert_idempotent(content, &TidyOptions::default());
+  }
+
+  #[test]
+  fn test_ignores_code_and_leaves_other_bytes_alone() {
+    let content = "---\nnote: \"[^3]\"\n---\nText[^3] and `[^9]`.  \n\n```\n[^1]: not a definition\n```\n\n[^3]: Real.\n";
+    assert_eq!(
+      tidy_end(content).content,
+      "---\nnote: \"[^3]\"\n---\nText[^1] and `[^9]`.  \n\n```\n[^1]: not a definition\n```\n\n[^1]: Real.\n"
+    );
+    // Nothing to tidy means nothing changes, even odd spacing
+    let untouched = "# Notes\n\n\n- [ ] task\n- [x] done  \r\nplain [brackets] here\n\n\n";
+    assert_eq!(tidy_end(untouched).content, untouched);
+    assert_eq!(tidy_end(untouched).report, ReferenceReport::default());
+  }
+
+  #[test]
+  fn test_link_definition_cannot_interrupt_a_paragraph() {
+    let content = "Some text\n[x]: https://x.example\n";
+    assert_eq!(tidy_end(content).content, content);
+  }
+
+  #[test]
+  fn test_keeps_crlf() {
+    let content = "B[^2] A[^1]\r\n\r\n[^1]: One.\r\n[^2]: Two.\r\n";
+    assert_eq!(
+      tidy_end(content).content,
+      "B[^1] A[^2]\r\n\r\n[^1]: Two.\r\n[^2]: One.\r\n"
+    );
+  }
+}
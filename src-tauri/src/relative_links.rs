@@ -0,0 +1,246 @@
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use urlencoding::{decode, encode};
+
+use crate::access_scope::AccessScopeState;
+use crate::batch_rename::{rewrite_destinations, ENCODED_NAME_CHARS};
+use crate::error::{CommandError, CommandResult};
+use crate::settings::SettingsState;
+
+// What becomes of a link destination when its document moves to another folder
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Relocation {
+  // Absolute, a URL or an anchor, or the relative path comes out the same
+  Kept,
+  Rewritten(String),
+  // No relative path from the new folder reaches the target without leaving the workspace
+  Unpreserved,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RelocatedLinks {
+  pub content: String,
+  // Destinations left as written because they can't be kept working
+  pub unpreserved: Vec<String>,
+}
+
+// Path from the folder `from` to `to`, with `..` where they part ways. Both must be
+// absolute and normalized. None if they don't share a root (another Windows drive).
+pub(crate) fn relative_path(from: &Path, to: &Path) -> Option<PathBuf> {
+  let from: Vec<Component> = from.components().collect();
+  let to: Vec<Component> = to.components().collect();
+  let shared = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+  if shared == 0 {
+    return None;
+  }
+  let mut relative: PathBuf = from[shared..]
+    .iter()
+    .map(|_| Component::ParentDir)
+    .collect();
+  relative.extend(&to[shared..]);
+  if relative.as_os_str().is_empty() {
+    relative.push(Component::CurDir);
+  }
+  Some(relative)
+}
+
+// Whether a destination's path part is relative: not empty (an anchor), not rooted, and
+// without a scheme (`https:`, `mailto:`, `data:`, or a Windows drive `C:`)
+fn is_relative(path: &str) -> bool {
+  if path.is_empty() || path.starts_with(['/', '\\']) {
+    return false;
+  }
+  !path.split_once(':').is_some_and(|(scheme, _)| {
+    !scheme.is_empty()
+      && scheme
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+  })
+}
+
+// `relative` as a link destination with `/` separators. Names are percent-encoded when
+// the old destination was, or when they'd otherwise break the link.
+fn to_destination(relative: &Path, angle_brackets: bool, was_encoded: bool) -> String {
+  relative
+    .components()
+    .map(|component| {
+      let name = component.as_os_str().to_string_lossy();
+      if !angle_brackets && (was_encoded || name.contains(ENCODED_NAME_CHARS)) {
+        encode(&name).into_owned()
+      } else {
+        name.into_owned()
+      }
+    })
+    .collect::<Vec<_>>()
+    .join("/")
+}
+
+// Where a destination written in a document in `old_dir` has to point once the document
+// is in `new_dir`, to reach the same file. When `new_dir` is in `workspace`, targets
+// outside it are Unpreserved rather than reached with `../..` out of the workspace.
+pub(crate) fn relocate_destination(
+  destination: &str,
+  angle_brackets: bool,
+  old_dir: &Path,
+  new_dir: &Path,
+  workspace: Option<&Path>,
+) -> Relocation {
+  let (path, suffix) =
+    destination.split_at(destination.find(['?', '#']).unwrap_or(destination.len()));
+  if !is_relative(path) {
+    return Relocation::Kept;
+  }
+  let decoded = decode(path).map_or_else(|_| path.to_string(), |p| p.into_owned());
+  let target = crate::lexical_normalize(&old_dir.join(decoded));
+  let escapes =
+    workspace.is_some_and(|root| new_dir.starts_with(root) && !target.starts_with(root));
+  let Some(relative) = relative_path(new_dir, &target).filter(|_| !escapes) else {
+    return Relocation::Unpreserved;
+  };
+  let relocated = to_destination(&relative, angle_brackets, path.contains('%'));
+  if relocated == path {
+    Relocation::Kept
+  } else {
+    Relocation::Rewritten(format!("{}{}", relocated, suffix))
+  }
+}
+
+// `content`, written in `old_dir`, with its relative links and images recomputed to reach
+// the same files from `new_dir`. Absolute paths, URLs, anchors and code are left alone.
+// Also returns the destinations that can't be kept working (see relocate_destination),
+// which stay as written.
+pub fn rewrite_relative_links(
+  content: &str,
+  old_dir: &Path,
+  new_dir: &Path,
+  workspace: Option<&Path>,
+) -> (String, Vec<String>) {
+  let mut unpreserved: Vec<String> = Vec::new();
+  if old_dir == new_dir {
+    return (content.to_string(), unpreserved);
+  }
+  let (rewritten, _) = rewrite_destinations(content, |destination, angle_brackets| {
+    let relocation = relocate_destination(destination, angle_brackets, old_dir, new_dir, workspace);
+    match relocation {
+      Relocation::Kept => None,
+      Relocation::Rewritten(relocated) => Some(relocated),
+      Relocation::Unpreserved => {
+        if !unpreserved.iter().any(|d| d == destination) {
+          unpreserved.push(destination.to_string());
+        }
+        None
+      }
+    }
+  });
+  (rewritten, unpreserved)
+}
+
+// Save As: the editor's content with its relative links following the document from
+// `old_path` to `new_path`, unless the rewrite_links_on_save_as setting is off
+#[tauri::command]
+pub async fn rewrite_links_for_save_as(
+  settings: tauri::State<'_, SettingsState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  content: String,
+  old_path: String,
+  new_path: String,
+) -> CommandResult<RelocatedLinks> {
+  if !settings.0.lock().unwrap().editor.rewrite_links_on_save_as {
+    return Ok(RelocatedLinks {
+      content,
+      unpreserved: Vec::new(),
+    });
+  }
+  let mut dirs = Vec::new();
+  for path in [PathBuf::from(old_path), PathBuf::from(new_path)] {
+    let dir = path
+      .parent()
+      .filter(|_| path.is_absolute())
+      .ok_or_else(|| CommandError::invalid_path(&path, "File path must be absolute"))?;
+    dirs.push(crate::lexical_normalize(dir));
+  }
+  let workspace = scope.0.lock().unwrap().workspace().map(Path::to_path_buf);
+  let (content, unpreserved) =
+    rewrite_relative_links(&content, &dirs[0], &dirs[1], workspace.as_deref());
+  Ok(RelocatedLinks {
+    content,
+    unpreserved,
+  })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use super::*;
+
+  const NOTE: &str = "\
+# Trip
+![map](img/map.png) and [plan](../shared/plan%20v2.md#day-1 \"Plan\")
+![photo](<img/my photo.jpg>) [site](https://example.com/a.md) [top](#trip)
+[mail](mailto:me@example.com) ![abs](/srv/logo.png) [here](./notes.md)
+<img src=\"img/map.png\">
+
+[ref]: ../shared/ref.md
+
+```
+![code](img/map.png)
+```
+";
+
+  #[test]
+  fn test_relative_path() {
+    let path = |from: &str, to: &str| relative_path(Path::new(from), Path::new(to));
+    assert_eq!(path("/a/b", "/a/c/d.md"), Some(PathBuf::from("../c/d.md")));
+    assert_eq!(path("/a", "/a/b/c.md"), Some(PathBuf::from("b/c.md")));
+    assert_eq!(
+      path("/a/b/c", "/x.md"),
+      Some(PathBuf::from("../../../x.md"))
+    );
+    assert_eq!(path("/a/b", "/a/b"), Some(PathBuf::from(".")));
+  }
+
+  #[test]
+  fn test_links_follow_the_document() {
+    let (rewritten, unpreserved) = rewrite_relative_links(
+      NOTE,
+      Path::new("/home/me/drafts"),
+      Path::new("/home/me/notes/projects"),
+      None,
+    );
+    assert_eq!(
+      rewritten,
+      "\
+# Trip
+![map](../../drafts/img/map.png) and [plan](../../shared/plan%20v2.md#day-1 \"Plan\")
+![photo](<../../drafts/img/my photo.jpg>) [site](https://example.com/a.md) [top](#trip)
+[mail](mailto:me@example.com) ![abs](/srv/logo.png) [here](../../drafts/notes.md)
+<img src=\"../../drafts/img/map.png\">
+
+[ref]: ../../shared/ref.md
+
+```
+![code](img/map.png)
+```
+"
+    );
+    assert!(unpreserved.is_empty());
+
+    // Same folder: nothing to do
+    let same = Path::new("/home/me/drafts");
+    assert_eq!(rewrite_relative_links(NOTE, same, same, None).0, NOTE);
+  }
+
+  #[test]
+  fn test_links_out_of_the_workspace_are_reported() {
+    let (rewritten, unpreserved) = rewrite_relative_links(
+      "![map](img/map.png) [plan](../shared/plan.md) [again](img/map.png)",
+      Path::new("/home/me/notes"),
+      Path::new("/home/me/notes/projects"),
+      Some(Path::new("/home/me/notes")),
+    );
+    assert_eq!(
+      rewritten,
+      "![map](../img/map.png) [plan](../shared/plan.md) [again](../img/map.png)"
+    );
+    assert_eq!(unpreserved, vec!["../shared/plan.md"]);
+  }
+}
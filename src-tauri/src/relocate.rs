@@ -0,0 +1,281 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager};
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::error::{CommandError, CommandResult};
+use crate::{display_recent_path, recent_paths_equal, wiki};
+
+// How far below each searched folder a moved file is looked for
+const MAX_SEARCH_DEPTH: usize = 6;
+
+// Directory entries looked at over the whole search, so a search starting in the home
+// folder can't end up crawling the disk
+const MAX_SCANNED_ENTRIES: usize = 50_000;
+
+// Candidates returned, best first
+const MAX_CANDIDATES: usize = 5;
+
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target"];
+
+// A file that may be where a missing recent file went
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RelocationCandidate {
+  pub path: String,
+  pub display_path: String,
+  pub size: u64,
+  // Milliseconds since the epoch
+  pub modified: Option<u64>,
+}
+
+// Folders to search for a file that was at `old_path`: the old folder's parent (so its
+// sibling folders), the workspace the old folder belonged to, and the frontend's
+// workspace. Folders that no longer exist are replaced by their nearest existing ancestor;
+// filesystem roots are never searched.
+fn search_roots(old_path: &Path, workspace: Option<&Path>) -> Vec<PathBuf> {
+  let existing = |path: &Path| {
+    path
+      .ancestors()
+      .find(|dir| dir.is_dir())
+      .map(Path::to_path_buf)
+  };
+  let old_dir = old_path.parent().and_then(existing);
+  let mut roots: Vec<PathBuf> = Vec::new();
+  let candidates = [
+    old_dir
+      .as_deref()
+      .and_then(Path::parent)
+      .map(Path::to_path_buf),
+    old_dir.as_deref().map(wiki::workspace_root),
+    workspace.and_then(existing),
+  ];
+  for root in candidates.into_iter().flatten() {
+    if root.parent().is_none() {
+      continue;
+    }
+    // A folder inside one already searched adds nothing; one containing it replaces it
+    if roots.iter().any(|searched| root.starts_with(searched)) {
+      continue;
+    }
+    roots.retain(|searched| !searched.starts_with(&root));
+    roots.push(root);
+  }
+  roots
+}
+
+// search_roots inside the access scope. The old path and the workspace must be in it.
+fn scoped_search_roots(
+  scope: &AccessScope,
+  old_path: &Path,
+  workspace: Option<&Path>,
+) -> CommandResult<Vec<PathBuf>> {
+  scope.check(old_path)?;
+  if let Some(workspace) = workspace {
+    scope.check(workspace)?;
+  }
+  Ok(
+    search_roots(old_path, workspace)
+      .into_iter()
+      .filter(|root| scope.is_allowed(root))
+      .collect(),
+  )
+}
+
+// Files named like `old_path` under `roots`, skipping hidden and dependency folders.
+// Stops after MAX_SCANNED_ENTRIES entries.
+fn find_same_name(old_path: &Path, roots: &[PathBuf]) -> Vec<PathBuf> {
+  let Some(name) = old_path
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+  else {
+    return Vec::new();
+  };
+  let mut found = Vec::new();
+  let mut scanned = 0;
+  let mut pending: Vec<(PathBuf, usize)> = roots.iter().map(|root| (root.clone(), 0)).collect();
+  while let Some((dir, depth)) = pending.pop() {
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+      continue;
+    };
+    for entry in entries.flatten() {
+      scanned += 1;
+      if scanned > MAX_SCANNED_ENTRIES {
+        log::debug!("Stopped looking for {} after {} entries", name, scanned - 1);
+        return found;
+      }
+      let entry_name = entry.file_name().to_string_lossy().to_string();
+      let Ok(file_type) = entry.file_type() else {
+        continue;
+      };
+      if file_type.is_dir() {
+        let skipped = entry_name.starts_with('.') || SKIPPED_DIRS.contains(&entry_name.as_str());
+        if !skipped && depth < MAX_SEARCH_DEPTH {
+          pending.push((entry.path(), depth + 1));
+        }
+      } else if file_type.is_file()
+        && recent_paths_equal(&entry_name, &name)
+        && entry.path() != old_path
+      {
+        found.push(entry.path());
+      }
+    }
+  }
+  found
+}
+
+// Number of leading path components `a` and `b` share
+fn shared_components(a: &Path, b: &Path) -> usize {
+  a.components()
+    .zip(b.components())
+    .take_while(|(a, b)| a == b)
+    .count()
+}
+
+// Where the file at `old_path` may have moved under `roots`, closest to its old folder first,
+// then most recently modified. Never changes anything: the user picks one for
+// update_recent_path.
+fn relocation_candidates(
+  old_path: &Path,
+  roots: &[PathBuf],
+  home_dir: Option<&Path>,
+) -> Vec<RelocationCandidate> {
+  let mut found: Vec<(usize, Option<u64>, RelocationCandidate)> = find_same_name(old_path, roots)
+    .into_iter()
+    .filter_map(|path| {
+      let metadata = std::fs::metadata(&path).ok()?;
+      let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_millis() as u64);
+      let path_str = path.to_string_lossy().to_string();
+      Some((
+        shared_components(&path, old_path),
+        modified,
+        RelocationCandidate {
+          display_path: display_recent_path(&path_str, home_dir),
+          path: path_str,
+          size: metadata.len(),
+          modified,
+        },
+      ))
+    })
+    .collect();
+  found.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+  found
+    .into_iter()
+    .take(MAX_CANDIDATES)
+    .map(|(_, _, candidate)| candidate)
+    .collect()
+}
+
+// Look for where a missing recent file went, for the frontend to offer "This file seems to
+// have moved to ..., update the entry?". `workspace` is the folder open in the sidebar, if
+// any. Only folders inside the access scope are searched. Empty if the file still exists or
+// nothing was found.
+#[tauri::command]
+pub async fn relocate_recent(
+  app: AppHandle,
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+  workspace: Option<String>,
+) -> CommandResult<Vec<RelocationCandidate>> {
+  let old_path = PathBuf::from(&path);
+  if !old_path.is_absolute() {
+    return Err(CommandError::invalid_path(
+      &old_path,
+      "File path must be absolute",
+    ));
+  }
+  if old_path.exists() {
+    return Ok(Vec::new());
+  }
+  let home_dir = app.path().home_dir().ok();
+  let workspace = workspace.map(PathBuf::from);
+  let roots = scoped_search_roots(&scope.0.lock().unwrap(), &old_path, workspace.as_deref())?;
+  tauri::async_runtime::spawn_blocking(move || {
+    relocation_candidates(&old_path, &roots, home_dir.as_deref())
+  })
+  .await
+  .map_err(|e| CommandError::io("Failed to search for the moved file", e))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_candidates_are_suggested_closest_first() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    for folder in ["archive/2024", "other", ".git/objects", "node_modules/pkg"] {
+      fs::create_dir_all(notes.join(folder)).unwrap();
+    }
+    fs::write(notes.join("archive/2024/todo.md"), "a").unwrap();
+    fs::write(notes.join("other/todo.md"), "bb").unwrap();
+    fs::write(notes.join("other/done.md"), "c").unwrap();
+    fs::write(notes.join(".git/objects/todo.md"), "d").unwrap();
+    fs::write(notes.join("node_modules/pkg/todo.md"), "e").unwrap();
+
+    // The file used to be in notes/archive, which still exists
+    let old = notes.join("archive/todo.md");
+    let candidates = relocation_candidates(&old, &search_roots(&old, None), None);
+    let paths: Vec<&str> = candidates.iter().map(|c| c.path.as_str()).collect();
+    let expected = [
+      notes.join("archive/2024/todo.md"),
+      notes.join("other/todo.md"),
+    ];
+    let expected: Vec<String> = expected
+      .iter()
+      .map(|path| path.to_string_lossy().to_string())
+      .collect();
+    assert_eq!(paths, expected);
+    assert_eq!(candidates[1].size, 2);
+
+    // A removed folder is searched from its nearest existing ancestor
+    let gone = notes.join("old/deeper/todo.md");
+    assert_eq!(
+      relocation_candidates(&gone, &search_roots(&gone, None), None).len(),
+      2
+    );
+  }
+
+  #[test]
+  fn test_search_roots_skip_nested_folders_and_filesystem_roots() {
+    let dir = TempDir::new().unwrap();
+    let project = dir.path().join("project");
+    fs::create_dir_all(project.join("docs")).unwrap();
+    let roots = search_roots(&project.join("docs/a.md"), Some(dir.path()));
+    assert_eq!(roots, vec![dir.path().to_path_buf()]);
+
+    let roots = search_roots(&project.join("docs/a.md"), None);
+    assert_eq!(roots, vec![project.clone()]);
+
+    #[cfg(unix)]
+    assert!(search_roots(Path::new("/gone/a.md"), None).is_empty());
+  }
+
+  #[test]
+  fn test_search_stays_inside_the_scope() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    fs::create_dir_all(notes.join("archive")).unwrap();
+    let old = notes.join("archive/todo.md");
+
+    let error = scoped_search_roots(&AccessScope::default(), &old, None).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    let scope = AccessScope::with_folders(&[&notes]);
+    let error = scoped_search_roots(&scope, &old, Some(dir.path())).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    assert_eq!(
+      scoped_search_roots(&scope, &old, None).unwrap(),
+      vec![notes.clone()]
+    );
+    // A file directly in notes would be looked for in its parent, which isn't approved
+    let top = notes.join("todo.md");
+    assert!(scoped_search_roots(&scope, &top, None).unwrap().is_empty());
+  }
+}
@@ -0,0 +1,116 @@
+use tauri::{AppHandle, Manager};
+
+use crate::app_store;
+use crate::error::CommandResult;
+use crate::session::{self, TabSessionState};
+use crate::settings::{self, Settings, SettingsState, SETTINGS_KEY};
+
+// Which parts of startup run. Each one reads persisted state that could be what keeps the
+// app from starting, so safe mode skips them all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Startup {
+  // Recover the store file and load the recent files from it
+  pub store: bool,
+  // Load the saved settings (and the menu shortcuts they set) instead of the defaults
+  pub settings: bool,
+  // Restore and save the tab session
+  pub session: bool,
+  // Watch workspaces for changes
+  pub watchers: bool,
+}
+
+impl Startup {
+  pub fn new(safe_mode: bool) -> Startup {
+    Startup {
+      store: !safe_mode,
+      settings: !safe_mode,
+      session: !safe_mode,
+      watchers: !safe_mode,
+    }
+  }
+
+  pub fn safe_mode(&self) -> bool {
+    *self == Startup::new(true)
+  }
+}
+
+pub struct StartupState(pub Startup);
+
+// Whether this launch runs in safe mode: asked for with `--safe-mode`, or by holding Shift
+// while launching where that can be checked (macOS)
+pub fn requested(flag: bool) -> bool {
+  #[cfg(target_os = "macos")]
+  if crate::macos::shift_held() {
+    return true;
+  }
+  flag
+}
+
+// What this launch started, for the parts that start on demand (a normal startup until
+// setup has decided)
+pub fn startup(app: &AppHandle) -> Startup {
+  app
+    .try_state::<StartupState>()
+    .map(|state| state.0)
+    .unwrap_or(Startup::new(false))
+}
+
+// Copy the store file aside before resetting part of it
+fn archive_store(app: &AppHandle, label: &str) -> CommandResult<Option<String>> {
+  let archive = app_store::archive_store_file(app, label)?;
+  if let Some(archive) = &archive {
+    log::info!("Store archived to {} before a reset", archive.display());
+  }
+  Ok(archive.map(|archive| archive.to_string_lossy().to_string()))
+}
+
+// For the frontend's safe mode banner
+#[tauri::command]
+pub async fn safe_mode_active(state: tauri::State<'_, StartupState>) -> CommandResult<bool> {
+  Ok(state.0.safe_mode())
+}
+
+// Go back to the default settings. The store file is archived first; returns the archive's
+// path (None if nothing was saved yet).
+#[tauri::command]
+pub async fn reset_settings(
+  app: AppHandle,
+  state: tauri::State<'_, SettingsState>,
+) -> CommandResult<Option<String>> {
+  let archive = archive_store(&app, "settings-reset")?;
+  app_store::delete_key(&app, "reset settings", SETTINGS_KEY)?;
+  settings::apply_settings(&app, &state, Settings::default())?;
+  Ok(archive)
+}
+
+// Forget the saved tab session, archiving the store file first like reset_settings. Drafts
+// stay, as with clear_session.
+#[tauri::command]
+pub async fn reset_session(
+  app: AppHandle,
+  state: tauri::State<'_, TabSessionState>,
+) -> CommandResult<Option<String>> {
+  let archive = archive_store(&app, "session-reset")?;
+  session::forget_session(&app, &state)?;
+  Ok(archive)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_safe_mode_skips_every_subsystem() {
+    let safe = Startup::new(true);
+    assert!(safe.safe_mode());
+    assert!(!safe.store && !safe.settings && !safe.session && !safe.watchers);
+    let normal = Startup::new(false);
+    assert!(!normal.safe_mode());
+    // Skipping only some of it isn't safe mode
+    let partial = Startup {
+      watchers: false,
+      ..normal
+    };
+    assert!(!partial.safe_mode());
+  }
+}
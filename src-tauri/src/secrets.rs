@@ -0,0 +1,165 @@
+use tauri::AppHandle;
+
+use crate::error::{CommandError, CommandResult};
+
+// Secrets (e.g. API tokens for publishing) live in the OS keychain: Keychain on macOS,
+// Credential Manager on Windows, Secret Service on Linux. Entries are filed under the app
+// identifier as the service, with the key as the account name. Values never go to the store
+// or into logs and error messages.
+
+// Longest key we accept; keychains limit account names and keys are short ids anyway
+const MAX_KEY_LEN: usize = 128;
+
+// Keys are ids like `github_token`, not user text
+fn validate_key(key: &str) -> CommandResult<()> {
+  let valid = !key.is_empty()
+    && key.len() <= MAX_KEY_LEN
+    && key
+      .chars()
+      .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+  if valid {
+    Ok(())
+  } else {
+    Err(CommandError::invalid_data(format!(
+      "Invalid secret key: {:?}",
+      key
+    )))
+  }
+}
+
+fn service(app: &AppHandle) -> String {
+  app.config().identifier.clone()
+}
+
+#[cfg(any(target_os = "macos", windows, target_os = "linux"))]
+mod keychain {
+  use crate::error::{CommandError, CommandResult};
+  use keyring::{Entry, Error};
+
+  fn keychain_error(error: Error) -> CommandError {
+    match error {
+      // No keychain to talk to, e.g. no Secret Service daemon running on Linux, or a
+      // locked keychain the user declined to unlock
+      Error::PlatformFailure(e) | Error::NoStorageAccess(e) => CommandError::KeyringUnavailable {
+        reason: e.to_string(),
+      },
+      // Don't echo anything that may contain the value (BadEncoding carries its bytes)
+      Error::BadEncoding(_) => CommandError::invalid_data("Stored secret is not valid UTF-8"),
+      other => CommandError::io("Keychain error", other),
+    }
+  }
+
+  fn entry(service: &str, key: &str) -> CommandResult<Entry> {
+    Entry::new(service, key).map_err(keychain_error)
+  }
+
+  pub fn set(service: &str, key: &str, value: &str) -> CommandResult<()> {
+    entry(service, key)?
+      .set_password(value)
+      .map_err(keychain_error)
+  }
+
+  pub fn get(service: &str, key: &str) -> CommandResult<Option<String>> {
+    match entry(service, key)?.get_password() {
+      Ok(value) => Ok(Some(value)),
+      Err(Error::NoEntry) => Ok(None),
+      Err(e) => Err(keychain_error(e)),
+    }
+  }
+
+  pub fn delete(service: &str, key: &str) -> CommandResult<()> {
+    match entry(service, key)?.delete_credential() {
+      Ok(()) | Err(Error::NoEntry) => Ok(()),
+      Err(e) => Err(keychain_error(e)),
+    }
+  }
+}
+
+#[cfg(not(any(target_os = "macos", windows, target_os = "linux")))]
+mod keychain {
+  use crate::error::{CommandError, CommandResult};
+
+  fn unavailable() -> CommandError {
+    CommandError::KeyringUnavailable {
+      reason: "not supported on this platform".to_string(),
+    }
+  }
+
+  pub fn set(_service: &str, _key: &str, _value: &str) -> CommandResult<()> {
+    Err(unavailable())
+  }
+
+  pub fn get(_service: &str, _key: &str) -> CommandResult<Option<String>> {
+    Err(unavailable())
+  }
+
+  pub fn delete(_service: &str, _key: &str) -> CommandResult<()> {
+    Err(unavailable())
+  }
+}
+
+// Read a secret for use in the backend (e.g. a token for an API request)
+pub fn read_secret(app: &AppHandle, key: &str) -> CommandResult<Option<String>> {
+  validate_key(key)?;
+  keychain::get(&service(app), key)
+}
+
+#[tauri::command]
+pub async fn set_secret(app: AppHandle, key: String, value: String) -> CommandResult<()> {
+  validate_key(&key)?;
+  keychain::set(&service(&app), &key, &value)
+}
+
+#[tauri::command]
+pub async fn get_secret(app: AppHandle, key: String) -> CommandResult<Option<String>> {
+  read_secret(&app, &key)
+}
+
+// Deleting a secret that isn't there succeeds
+#[tauri::command]
+pub async fn delete_secret(app: AppHandle, key: String) -> CommandResult<()> {
+  validate_key(&key)?;
+  keychain::delete(&service(&app), &key)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_validate_key() {
+    assert!(validate_key("github_token").is_ok());
+    assert!(validate_key("publish.gist-token").is_ok());
+    assert_eq!(validate_key("").unwrap_err().code(), "invalid_data");
+    assert!(validate_key("a b").is_err());
+    assert!(validate_key("../token").is_err());
+    assert!(validate_key(&"k".repeat(MAX_KEY_LEN + 1)).is_err());
+  }
+
+  // Talks to the real keychain, which CI machines often don't have unlocked (or at all on
+  // Linux), so it only runs with `cargo test --features keychain-tests`
+  #[cfg(feature = "keychain-tests")]
+  #[test]
+  fn test_keychain_round_trip() {
+    let service = "com.daudabas.markdown-editor.tests";
+    let key = "round_trip_token";
+    keychain::delete(service, key).unwrap();
+    assert_eq!(keychain::get(service, key).unwrap(), None);
+
+    keychain::set(service, key, "s3cr3t").unwrap();
+    assert_eq!(
+      keychain::get(service, key).unwrap().as_deref(),
+      Some("s3cr3t")
+    );
+    keychain::set(service, key, "rotated").unwrap();
+    assert_eq!(
+      keychain::get(service, key).unwrap().as_deref(),
+      Some("rotated")
+    );
+
+    keychain::delete(service, key).unwrap();
+    assert_eq!(keychain::get(service, key).unwrap(), None);
+    // Deleting again is fine
+    keychain::delete(service, key).unwrap();
+  }
+}
@@ -0,0 +1,345 @@
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::access_scope::AccessScopeState;
+use crate::error::{CommandError, CommandResult};
+use crate::includes::{atx_heading, is_fence};
+use crate::references::{definition_spans, normalize, ReferenceKind};
+use crate::settings::SettingsState;
+use crate::split::{heading_anchor, heading_text, HeadingAnchors};
+use crate::typography::frontmatter_end;
+use crate::write_queue::WriteCoordinator;
+
+// Which heading's section: the 1-based line of the heading, or its anchor (`setup`, `setup-1`
+// for the second one) or text as in a `notes.md#setup` link
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum SectionTarget {
+  Line(usize),
+  Heading(String),
+}
+
+// Where a heading's section is in a document
+#[derive(Debug, Clone, PartialEq)]
+struct SectionSpan {
+  // The heading line, with its line break
+  heading: Range<usize>,
+  // From the heading to just before the next heading of the same or higher level, or to the
+  // end of the document
+  section: Range<usize>,
+}
+
+impl SectionSpan {
+  fn range(&self, include_heading: bool) -> Range<usize> {
+    if include_heading {
+      self.section.clone()
+    } else {
+      self.heading.end..self.section.end
+    }
+  }
+}
+
+// Find the section of `target` among the ATX headings outside frontmatter and code blocks
+fn find_section(content: &str, target: &SectionTarget) -> Option<SectionSpan> {
+  let body_start = frontmatter_end(content).unwrap_or(0);
+  let wanted = match target {
+    SectionTarget::Line(_) => None,
+    SectionTarget::Heading(heading) => Some(heading_anchor(heading.trim_start_matches('#'))),
+  };
+  let mut anchors = HeadingAnchors::default();
+  let mut line_number = content[..body_start].matches('\n').count();
+  let mut offset = body_start;
+  let mut in_fence = false;
+  // Level and line of the heading once found
+  let mut found: Option<(usize, Range<usize>)> = None;
+
+  for line in content[body_start..].split_inclusive('\n') {
+    let start = offset;
+    offset += line.len();
+    line_number += 1;
+    if is_fence(line) {
+      in_fence = !in_fence;
+      continue;
+    }
+    let Some((level, after)) = atx_heading(line).filter(|_| !in_fence) else {
+      continue;
+    };
+    let anchor = anchors.next(heading_text(after));
+    match &found {
+      Some((found_level, heading)) if level <= *found_level => {
+        return Some(SectionSpan {
+          heading: heading.clone(),
+          section: heading.start..start,
+        });
+      }
+      Some(_) => {}
+      None => {
+        let matches = match target {
+          SectionTarget::Line(line) => *line == line_number,
+          SectionTarget::Heading(heading) => {
+            anchor == *heading || wanted.as_deref() == Some(anchor.as_str())
+          }
+        };
+        if matches {
+          found = Some((level, start..offset));
+        }
+      }
+    }
+  }
+  found.map(|(_, heading)| SectionSpan {
+    section: heading.start..content.len(),
+    heading,
+  })
+}
+
+// The text of `target`'s section, with or without its heading line. None if the document
+// has no such heading.
+pub fn section_text<'a>(
+  content: &'a str,
+  target: &SectionTarget,
+  include_heading: bool,
+) -> Option<&'a str> {
+  find_section(content, target).map(|span| &content[span.range(include_heading)])
+}
+
+// `target`'s section on its own, for printing: the document's `title` on top (unless the
+// section is the one it heads), then the section, then the footnote and link reference
+// definitions the section uses that are elsewhere in the document. None if the document
+// has no such heading.
+pub fn printable_section(content: &str, target: &SectionTarget, title: &str) -> Option<String> {
+  let span = find_section(content, target)?;
+  let section = &content[span.section.clone()];
+  let heading =
+    atx_heading(&content[span.heading.clone()]).map_or("", |(_, after)| heading_text(after));
+
+  let mut printable = String::new();
+  if !title.trim().is_empty() && heading_anchor(title) != heading_anchor(heading) {
+    printable.push_str(&format!("# {}\n\n", title.trim()));
+  }
+  printable.push_str(section.trim_end());
+  printable.push('\n');
+
+  // `[text][label]`, `[label]` and `[^note]` all put the label in brackets
+  let used: HashSet<(ReferenceKind, String)> = section
+    .split('[')
+    .skip(1)
+    .filter_map(|part| part.split_once(']').map(|(label, _)| label))
+    .map(|label| match label.strip_prefix('^') {
+      Some(note) => (ReferenceKind::Footnote, normalize(note)),
+      None => (ReferenceKind::Link, normalize(label)),
+    })
+    .collect();
+  for (kind, label, range) in definition_spans(content) {
+    if span.section.contains(&range.start) || !used.contains(&(kind, label)) {
+      continue;
+    }
+    printable.push('\n');
+    printable.push_str(content[range].trim_end());
+    printable.push('\n');
+  }
+  Some(printable)
+}
+
+// `content` with `target`'s section (or only what's under its heading) swapped for
+// `new_text`. A line break is added when the next heading would otherwise join its last line.
+fn replace_in(
+  content: &str,
+  target: &SectionTarget,
+  new_text: &str,
+  include_heading: bool,
+) -> CommandResult<String> {
+  let span = find_section(content, target).ok_or_else(|| {
+    CommandError::invalid_data(match target {
+      SectionTarget::Line(line) => format!("Line {} is not a heading", line),
+      SectionTarget::Heading(heading) => format!("No heading matches \"{}\"", heading),
+    })
+  })?;
+  let range = span.range(include_heading);
+  let mut updated = String::with_capacity(content.len() + new_text.len());
+  updated.push_str(&content[..range.start]);
+  updated.push_str(new_text);
+  if range.end < content.len() && !new_text.is_empty() && !new_text.ends_with('\n') {
+    updated.push('\n');
+  }
+  updated.push_str(&content[range.end..]);
+  Ok(updated)
+}
+
+// For Copy Section, section-only export and previews of `file.md#heading` links
+#[tauri::command]
+pub async fn extract_section(
+  content: String,
+  heading: SectionTarget,
+  include_heading: bool,
+) -> CommandResult<Option<String>> {
+  Ok(section_text(&content, &heading, include_heading).map(str::to_string))
+}
+
+// Replace a heading's section of a file and save it atomically, returning the file's new
+// modification time. Fails with a conflict if the file changed on disk after `expected_mtime`.
+#[tauri::command]
+pub async fn replace_section(
+  coordinator: tauri::State<'_, WriteCoordinator>,
+  settings: tauri::State<'_, SettingsState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+  heading: SectionTarget,
+  new_text: String,
+  include_heading: bool,
+  expected_mtime: Option<u64>,
+) -> CommandResult<Option<u64>> {
+  let durable = settings.0.lock().unwrap().durable_saves;
+  let path = PathBuf::from(&path);
+  scope.check(&path)?;
+  let target = path.clone();
+  let (result, _) = coordinator
+    .submit(&path, move || {
+      let content = crate::read_text_file(&target)?;
+      let updated = replace_in(&content, &heading, &new_text, include_heading)?;
+      crate::write_text_file(&target, &updated, expected_mtime, durable)
+    })
+    .await?;
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const DOCUMENT: &str = "\
+---
+title: Notes
+---
+# Notes
+
+Intro
+
+## Setup
+
+Install.
+
+### Details
+
+```sh
+# not a heading
+```
+
+## Setup
+
+Again.
+
+## Usage ##
+
+Run it.
+";
+
+  fn heading(text: &str) -> SectionTarget {
+    SectionTarget::Heading(text.to_string())
+  }
+
+  #[test]
+  fn test_sections_end_at_the_next_heading_of_their_level() {
+    assert_eq!(
+      section_text(DOCUMENT, &heading("setup"), true),
+      Some("## Setup\n\nInstall.\n\n### Details\n\n```sh\n# not a heading\n```\n\n")
+    );
+    // Repeated headings are told apart by their anchor, and found by line too
+    assert_eq!(
+      section_text(DOCUMENT, &heading("setup-1"), false),
+      Some("\nAgain.\n\n")
+    );
+    assert_eq!(
+      section_text(DOCUMENT, &SectionTarget::Line(18), false),
+      Some("\nAgain.\n\n")
+    );
+    // The last section runs to the end; heading text works as well as the anchor
+    assert_eq!(
+      section_text(DOCUMENT, &heading("Usage"), true),
+      Some("## Usage ##\n\nRun it.\n")
+    );
+    assert!(section_text(DOCUMENT, &heading("notes"), true)
+      .unwrap()
+      .ends_with("Run it.\n"));
+    assert_eq!(section_text(DOCUMENT, &SectionTarget::Line(1), true), None);
+    assert_eq!(
+      section_text(DOCUMENT, &heading("not-a-heading"), true),
+      None
+    );
+  }
+
+  #[test]
+  fn test_replace_keeps_the_next_heading_on_its_own_line() {
+    let updated = replace_in(DOCUMENT, &heading("setup-1"), "\nDone.", false).unwrap();
+    assert!(updated.contains("## Setup\n\nDone.\n## Usage ##\n"));
+
+    let updated = replace_in(DOCUMENT, &heading("usage"), "## Use\n", true).unwrap();
+    assert!(updated.ends_with("Again.\n\n## Use\n"));
+
+    let error = replace_in(DOCUMENT, &heading("missing"), "", true).unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+  }
+
+  #[test]
+  fn test_printable_section_brings_its_definitions() {
+    let recipes = "\
+# Recipes
+
+## Pancakes
+
+Mix[^flour] and fry, see [the video][video] or [Tips].
+
+## Bread
+
+Knead[^knead].
+
+```
+[^flour]: not a definition
+```
+
+[^flour]: Any white flour.
+    Sifted is best.
+
+[^knead]: Ten minutes.
+[Video]: https://example.com/pancakes
+[tips]: tips.md
+[unused]: unused.md
+";
+    assert_eq!(
+      printable_section(recipes, &heading("pancakes"), "Recipes").as_deref(),
+      Some(
+        "# Recipes\n\n## Pancakes\n\nMix[^flour] and fry, see [the video][video] or [Tips].\n\n\
+         [^flour]: Any white flour.\n    Sifted is best.\n\n\
+         [Video]: https://example.com/pancakes\n\n\
+         [tips]: tips.md\n"
+      )
+    );
+    // The title isn't repeated over its own section, and definitions in it stay put
+    let whole = printable_section(recipes, &heading("recipes"), "Recipes").unwrap();
+    assert!(whole.starts_with("# Recipes\n\n## Pancakes"));
+    assert!(whole.ends_with("[unused]: unused.md\n"));
+    assert_eq!(
+      printable_section(recipes, &heading("soup"), "Recipes"),
+      None
+    );
+  }
+
+  #[test]
+  fn test_printable_section_skips_what_isnt_a_definition() {
+    let notes = "\
+## Links
+
+See [empty] and [^ note].
+
+## Elsewhere
+
+[empty]:
+[^ note]: Not a footnote.
+";
+    assert_eq!(
+      printable_section(notes, &heading("links"), "").as_deref(),
+      Some("## Links\n\nSee [empty] and [^ note].\n")
+    );
+  }
+}
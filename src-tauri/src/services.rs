@@ -0,0 +1,149 @@
+use std::cell::RefCell;
+use std::sync::OnceLock;
+
+use objc2::rc::Retained;
+use objc2::runtime::AnyObject;
+use objc2::{define_class, msg_send, MainThreadMarker, MainThreadOnly};
+use objc2_app_kit::NSApplication;
+use objc2_foundation::{ns_string, NSObject, NSString};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::clipboard::markdown_from_flavors;
+use crate::launch::{self, PendingOpen};
+use crate::stdin::{PendingContent, PendingContentState, PENDING_CONTENT_EVENT};
+
+// Title the frontend shows for a document created from a service
+const SHARED_TEXT_TITLE: &str = "Shared text";
+
+// What a service request asks to open
+#[derive(Debug, Clone, PartialEq)]
+enum SharedItem {
+  File(PendingOpen),
+  Text(PendingContent),
+}
+
+// A file URL wins (the service was used on a file in Finder); otherwise the selection
+// becomes a new document, converted from HTML when the sender provided it
+fn shared_item(
+  file_url: Option<&str>,
+  html: Option<&str>,
+  text: Option<&str>,
+) -> Option<SharedItem> {
+  if let Some(open) = file_url.and_then(launch::pending_open_from_url) {
+    return Some(SharedItem::File(open));
+  }
+  let (markdown, _) = markdown_from_flavors(html, text)?;
+  Some(SharedItem::Text(PendingContent {
+    content: markdown,
+    title: SHARED_TEXT_TITLE.to_string(),
+    partial: false,
+  }))
+}
+
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
+
+thread_local! {
+  // NSApplication doesn't retain its services provider
+  static PROVIDER: RefCell<Option<Retained<ServiceProvider>>> = const { RefCell::new(None) };
+}
+
+define_class!(
+  #[unsafe(super(NSObject))]
+  #[thread_kind = MainThreadOnly]
+  #[name = "MarkdownerServiceProvider"]
+  struct ServiceProvider;
+
+  impl ServiceProvider {
+    // The NSMessage of the NSServices entry in Info.plist
+    #[unsafe(method(newDocumentFromSelection:userData:error:))]
+    fn new_document_from_selection(
+      &self,
+      pasteboard: &AnyObject,
+      _user_data: Option<&NSString>,
+      _error: *mut *mut NSString,
+    ) {
+      let read = |kind: &NSString| -> Option<String> {
+        let value: Option<Retained<NSString>> =
+          unsafe { msg_send![pasteboard, stringForType: kind] };
+        value.map(|value| value.to_string())
+      };
+      let item = shared_item(
+        read(ns_string!("public.file-url")).as_deref(),
+        read(ns_string!("public.html")).as_deref(),
+        read(ns_string!("public.utf8-plain-text")).as_deref(),
+      );
+      match (APP_HANDLE.get(), item) {
+        (Some(app), Some(item)) => open_shared(app, item),
+        (_, None) => log::warn!("Service request had nothing to open"),
+        (None, _) => {}
+      }
+    }
+  }
+);
+
+impl ServiceProvider {
+  fn new(mtm: MainThreadMarker) -> Retained<Self> {
+    let this = Self::alloc(mtm).set_ivars(());
+    unsafe { msg_send![super(this), init] }
+  }
+}
+
+// Route shared items like launches: files to a window's pending file queue, text through
+// the pending content get_pending_content hands out
+fn open_shared(app: &AppHandle, item: SharedItem) {
+  match item {
+    SharedItem::File(open) => launch::open_files(app, &[open]),
+    SharedItem::Text(content) => {
+      launch::focus_main_window(app);
+      if let Some(state) = app.try_state::<PendingContentState>() {
+        *state.0.lock().unwrap() = Some(Ok(content));
+      }
+      let _ = app.emit(PENDING_CONTENT_EVENT, ());
+    }
+  }
+}
+
+// Register for the Services menu. Must run on the main thread, from setup.
+pub fn init(app: &AppHandle) {
+  let Some(mtm) = MainThreadMarker::new() else {
+    log::warn!("Services must be set up on the main thread");
+    return;
+  };
+  let _ = APP_HANDLE.set(app.clone());
+  let provider = ServiceProvider::new(mtm);
+  let application = NSApplication::sharedApplication(mtm);
+  let provider_object: &AnyObject = &provider;
+  let _: () = unsafe { msg_send![&*application, setServicesProvider: provider_object] };
+  PROVIDER.with(|current| *current.borrow_mut() = Some(provider));
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_shared_item_prefers_files_then_html() {
+    let file = shared_item(
+      Some("file:///Users/me/My%20Notes.md"),
+      None,
+      Some("/Users/me/My Notes.md"),
+    );
+    let Some(SharedItem::File(open)) = file else {
+      panic!("expected a file, got {:?}", file);
+    };
+    assert_eq!(open.path, "/Users/me/My Notes.md");
+
+    let html = shared_item(
+      None,
+      Some("<p>Hello <strong>world</strong></p>"),
+      Some("Hello world"),
+    );
+    let Some(SharedItem::Text(content)) = html else {
+      panic!("expected text, got {:?}", html);
+    };
+    assert_eq!(content.content, "Hello **world**");
+    assert_eq!(content.title, SHARED_TEXT_TITLE);
+
+    assert_eq!(shared_item(None, None, Some("  \n")), None);
+  }
+}
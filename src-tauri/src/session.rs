@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+use crate::app_store;
+use crate::drafts;
+use crate::error::{CommandError, CommandResult};
+use crate::safe_mode;
+use crate::view_state::FileViewState;
+
+// Store key holding the documents that were open, as of the last save_tab_session
+pub const TAB_SESSION_KEY: &str = "tab_session";
+
+// Sessions saved within this long of each other are written to the store once
+const WRITE_DELAY: Duration = Duration::from_millis(1000);
+
+// An open document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionTab {
+  // None for a document that was never saved
+  #[serde(default)]
+  pub path: Option<String>,
+  // Id of the draft (see drafts) holding the tab's unsaved changes, if it has any
+  #[serde(default)]
+  pub draft_id: Option<String>,
+  #[serde(default)]
+  pub view_state: Option<FileViewState>,
+  // Unsaved content sent with save_tab_session. It's kept as the tab's draft, so the drafts
+  // size cap applies to it, and never stored in the session itself.
+  #[serde(default, skip_serializing)]
+  pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TabSession {
+  pub tabs: Vec<SessionTab>,
+  pub active_index: usize,
+}
+
+// A tab as returned by get_tab_session, with what's gone since the session was saved
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RestoredTab {
+  #[serde(flatten)]
+  pub tab: SessionTab,
+  // The tab's file no longer exists
+  pub file_missing: bool,
+  // The tab's draft was evicted or discarded, so its unsaved changes are lost
+  pub draft_missing: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RestoredSession {
+  pub tabs: Vec<RestoredTab>,
+  pub active_index: usize,
+}
+
+// A draft to write with the next session write
+#[derive(Debug)]
+struct PendingDraft {
+  content: String,
+  original_path: Option<String>,
+}
+
+// Saved sessions and drafts not written yet. Only the latest of each is kept.
+#[derive(Default)]
+struct PendingSession {
+  session: Option<TabSession>,
+  drafts: HashMap<String, PendingDraft>,
+  write_scheduled: bool,
+}
+
+#[derive(Default)]
+pub struct TabSessionState(Mutex<PendingSession>);
+
+// Take the unsaved content out of the tabs, returning it by draft id. The active index is
+// clamped to the tabs there are.
+fn split_drafts(
+  mut tabs: Vec<SessionTab>,
+  active_index: usize,
+) -> CommandResult<(TabSession, Vec<(String, PendingDraft)>)> {
+  let mut drafts = Vec::new();
+  for tab in tabs.iter_mut() {
+    let Some(content) = tab.content.take() else {
+      continue;
+    };
+    let Some(draft_id) = tab.draft_id.clone() else {
+      return Err(CommandError::invalid_data(
+        "A tab with unsaved content needs a draft id",
+      ));
+    };
+    let original_path = tab.path.clone();
+    drafts.push((
+      draft_id,
+      PendingDraft {
+        content,
+        original_path,
+      },
+    ));
+  }
+  let active_index = active_index.min(tabs.len().saturating_sub(1));
+  Ok((TabSession { tabs, active_index }, drafts))
+}
+
+// Flag the tabs whose file or draft has disappeared. They're kept so the frontend can tell
+// the user rather than silently losing a tab.
+fn restore(session: TabSession, has_draft: impl Fn(&str) -> bool) -> RestoredSession {
+  let tabs = session
+    .tabs
+    .into_iter()
+    .map(|tab| RestoredTab {
+      file_missing: tab
+        .path
+        .as_ref()
+        .is_some_and(|path| !Path::new(path).is_file()),
+      draft_missing: tab.draft_id.as_deref().is_some_and(|id| !has_draft(id)),
+      tab,
+    })
+    .collect();
+  RestoredSession {
+    tabs,
+    active_index: session.active_index,
+  }
+}
+
+fn load_session(app: &AppHandle) -> CommandResult<Option<TabSession>> {
+  let store = app_store::open_store(app)?;
+  Ok(
+    store
+      .get(TAB_SESSION_KEY)
+      .and_then(|value| serde_json::from_value(value).ok()),
+  )
+}
+
+fn save_session(app: &AppHandle, session: &TabSession) -> CommandResult<()> {
+  let value = serde_json::to_value(session)
+    .map_err(|e| CommandError::io("Failed to serialize tab session", e))?;
+  app_store::write_key(app, "save the tab session", TAB_SESSION_KEY, value)
+}
+
+// Write whatever save_tab_session has queued. Runs after WRITE_DELAY, and on exit so a
+// quit right after a change still keeps it (errors are logged).
+pub fn flush_tab_session(app: &AppHandle) {
+  let (session, drafts) = {
+    let mut pending = app.state::<TabSessionState>().0.lock().unwrap();
+    pending.write_scheduled = false;
+    (pending.session.take(), std::mem::take(&mut pending.drafts))
+  };
+  for (draft_id, draft) in drafts {
+    if let Err(e) = drafts::store_draft(app, &draft_id, &draft.content, draft.original_path) {
+      log::error!("Failed to save draft of session tab: {}", e);
+    }
+  }
+  if let Some(session) = session {
+    if let Err(e) = save_session(app, &session) {
+      log::error!("Failed to save tab session: {}", e);
+    }
+  }
+}
+
+// Remember the open documents. Called on every change to the tabs; the store is written
+// once things settle for WRITE_DELAY.
+#[tauri::command]
+pub async fn save_tab_session(
+  app: AppHandle,
+  state: tauri::State<'_, TabSessionState>,
+  tabs: Vec<SessionTab>,
+  active_index: usize,
+) -> CommandResult<()> {
+  // Safe mode leaves the saved session as it was, for the next normal launch
+  if !safe_mode::startup(&app).session {
+    return Ok(());
+  }
+  let (session, drafts) = split_drafts(tabs, active_index)?;
+  let mut pending = state.0.lock().unwrap();
+  pending.session = Some(session);
+  pending.drafts.extend(drafts);
+  if !pending.write_scheduled {
+    pending.write_scheduled = true;
+    tauri::async_runtime::spawn(async move {
+      tokio::time::sleep(WRITE_DELAY).await;
+      flush_tab_session(&app);
+    });
+  }
+  Ok(())
+}
+
+// The documents open when the session was last saved, or None if it was cleared (or never
+// saved, or this is safe mode). Unsaved content is read separately with read_draft.
+#[tauri::command]
+pub async fn get_tab_session(
+  app: AppHandle,
+  state: tauri::State<'_, TabSessionState>,
+) -> CommandResult<Option<RestoredSession>> {
+  if !safe_mode::startup(&app).session {
+    return Ok(None);
+  }
+  let pending = state.0.lock().unwrap().session.clone();
+  let session = match pending {
+    Some(session) => Some(session),
+    None => load_session(&app)?,
+  };
+  Ok(session.map(|session| restore(session, |id| drafts::has_draft(&app, id))))
+}
+
+// Forget the session, for starting without restoring it. Drafts stay, so unsaved changes
+// can still be recovered.
+#[tauri::command]
+pub async fn clear_session(
+  app: AppHandle,
+  state: tauri::State<'_, TabSessionState>,
+) -> CommandResult<()> {
+  forget_session(&app, &state)
+}
+
+pub(crate) fn forget_session(app: &AppHandle, state: &TabSessionState) -> CommandResult<()> {
+  state.0.lock().unwrap().session = None;
+  app_store::delete_key(app, "clear the tab session", TAB_SESSION_KEY)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  fn tab(path: Option<&str>, draft_id: Option<&str>, content: Option<&str>) -> SessionTab {
+    SessionTab {
+      path: path.map(str::to_string),
+      draft_id: draft_id.map(str::to_string),
+      view_state: None,
+      content: content.map(str::to_string),
+    }
+  }
+
+  #[test]
+  fn test_unsaved_content_goes_to_drafts() {
+    let tabs = vec![
+      tab(Some("/notes/a.md"), None, None),
+      tab(None, Some("untitled-1"), Some("# Draft")),
+    ];
+    let (session, drafts) = split_drafts(tabs, 5).unwrap();
+    assert_eq!(session.active_index, 1);
+    assert!(session.tabs.iter().all(|tab| tab.content.is_none()));
+    assert_eq!(drafts.len(), 1);
+    assert_eq!(drafts[0].0, "untitled-1");
+    assert_eq!(drafts[0].1.content, "# Draft");
+
+    // The stored session never carries content
+    let value = serde_json::to_value(&session).unwrap();
+    assert!(value["tabs"][1].get("content").is_none());
+    assert_eq!(value["tabs"][1]["draft_id"], "untitled-1");
+
+    let error = split_drafts(vec![tab(None, None, Some("text"))], 0).unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+    assert_eq!(split_drafts(Vec::new(), 3).unwrap().0.active_index, 0);
+  }
+
+  #[test]
+  fn test_restore_flags_missing_files_and_drafts() {
+    let dir = TempDir::new().unwrap();
+    let present = dir.path().join("present.md");
+    fs::write(&present, "# Here").unwrap();
+    let gone = dir.path().join("gone.md");
+    let session = TabSession {
+      tabs: vec![
+        tab(Some(&present.to_string_lossy()), None, None),
+        tab(Some(&gone.to_string_lossy()), None, None),
+        tab(None, Some("untitled-1"), None),
+        tab(None, Some("untitled-2"), None),
+      ],
+      active_index: 2,
+    };
+
+    let restored = restore(session, |id| id == "untitled-1");
+    let flags: Vec<(bool, bool)> = restored
+      .tabs
+      .iter()
+      .map(|tab| (tab.file_missing, tab.draft_missing))
+      .collect();
+    assert_eq!(
+      flags,
+      vec![(false, false), (true, false), (false, false), (false, true)]
+    );
+    assert_eq!(restored.active_index, 2);
+  }
+}
@@ -0,0 +1,492 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+use crate::app_store;
+use crate::error::{CommandError, CommandResult, FieldError};
+use crate::export_defaults::ExportSettings;
+use crate::inbox::InboxOptions;
+use crate::launch::OpenFilesIn;
+use crate::slides::SlideSplit;
+
+// Store key holding the user's settings
+pub const SETTINGS_KEY: &str = "settings";
+
+// Event emitted with the new settings whenever they change
+pub const SETTINGS_CHANGED_EVENT: &str = "settings-changed";
+
+// How words are separated in file names suggested from a document's heading
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilenameSeparator {
+  // `meeting-notes.md`
+  #[default]
+  Dash,
+  // `Meeting notes.md`
+  Space,
+}
+
+// Light or dark colors, as the app shows them or an export uses them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Palette {
+  #[default]
+  Light,
+  Dark,
+}
+
+// Colors of exported documents and slides
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportTheme {
+  #[default]
+  Light,
+  Dark,
+  // Whichever the app is showing when the export is made
+  MatchApp,
+}
+
+impl ExportTheme {
+  // `app` is what the frontend's stored theme preference currently resolves to
+  pub fn resolve(self, app: Palette) -> Palette {
+    match self {
+      ExportTheme::Light => Palette::Light,
+      ExportTheme::Dark => Palette::Dark,
+      ExportTheme::MatchApp => app,
+    }
+  }
+}
+
+// Paper size of printed and exported documents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+  // Whatever the printer or PDF writer picks
+  #[default]
+  Auto,
+  Letter,
+  Legal,
+  A4,
+  A5,
+}
+
+impl PageSize {
+  // The CSS `@page` size, None for Auto
+  pub fn css(self) -> Option<&'static str> {
+    match self {
+      PageSize::Auto => None,
+      PageSize::Letter => Some("letter"),
+      PageSize::Legal => Some("legal"),
+      PageSize::A4 => Some("A4"),
+      PageSize::A5 => Some("A5"),
+    }
+  }
+}
+
+// Page layout of printed and exported documents
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrintOptions {
+  // Start every H1 (or H2) on a new page, except the document's first heading
+  pub page_break_on_h1: bool,
+  pub page_break_on_h2: bool,
+  // Put a generated table of contents on its own page at the front
+  pub table_of_contents: bool,
+  // Colors on screen
+  pub theme: ExportTheme,
+  // Paper always gets the light colors unless this is set, in which case a dark theme is
+  // printed as it is shown
+  pub dark_on_paper: bool,
+  pub page_size: PageSize,
+}
+
+impl PrintOptions {
+  // Colors on screen and on paper
+  pub fn palettes(&self, app: Palette) -> (Palette, Palette) {
+    let screen = self.theme.resolve(app);
+    let paper = if self.dark_on_paper {
+      screen
+    } else {
+      Palette::Light
+    };
+    (screen, paper)
+  }
+}
+
+// Limits checked by EditorSettings::validate
+const FONT_SIZE_RANGE: std::ops::RangeInclusive<u32> = 8..=72;
+const LINE_HEIGHT_RANGE: std::ops::RangeInclusive<f64> = 1.0..=3.0;
+const TAB_SIZE_RANGE: std::ops::RangeInclusive<u32> = 1..=8;
+const MIN_AUTOSAVE_INTERVAL_SECS: u32 = 5;
+
+// How the editor looks and behaves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EditorSettings {
+  // A family from list_system_fonts; None uses the built-in monospace stack
+  pub font_family: Option<String>,
+  // In CSS pixels
+  pub font_size: u32,
+  // Multiple of the font size
+  pub line_height: f64,
+  // Columns a tab takes, and how many spaces Tab inserts when `insert_spaces` is on
+  pub tab_size: u32,
+  pub insert_spaces: bool,
+  pub word_wrap: bool,
+  pub show_line_numbers: bool,
+  // Snapshot unsaved changes as recoverable drafts (see drafts.rs) at most every
+  // `autosave_interval_secs`; while off they're snapshotted at a fixed default interval
+  pub autosave_enabled: bool,
+  pub autosave_interval_secs: u32,
+  // Run the document formatter before saving
+  pub format_on_save: bool,
+  // Save As points the document's relative links and images at the same files from its
+  // new folder (see relative_links.rs)
+  pub rewrite_links_on_save_as: bool,
+}
+
+impl Default for EditorSettings {
+  fn default() -> Self {
+    EditorSettings {
+      font_family: None,
+      font_size: 14,
+      line_height: 1.6,
+      tab_size: 2,
+      insert_spaces: true,
+      word_wrap: false,
+      show_line_numbers: false,
+      autosave_enabled: true,
+      autosave_interval_secs: MIN_AUTOSAVE_INTERVAL_SECS,
+      format_on_save: false,
+      rewrite_links_on_save_as: true,
+    }
+  }
+}
+
+impl EditorSettings {
+  // What's wrong with each out-of-range field, named `editor.<field>`
+  pub fn validate(&self) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let mut reject = |field: &str, message: String| {
+      errors.push(FieldError {
+        field: format!("editor.{}", field),
+        message,
+      })
+    };
+    if self
+      .font_family
+      .as_ref()
+      .is_some_and(|family| family.trim().is_empty())
+    {
+      reject("font_family", "Font family can't be blank".to_string());
+    }
+    if !FONT_SIZE_RANGE.contains(&self.font_size) {
+      reject(
+        "font_size",
+        format!(
+          "Font size must be between {} and {}",
+          FONT_SIZE_RANGE.start(),
+          FONT_SIZE_RANGE.end()
+        ),
+      );
+    }
+    if !LINE_HEIGHT_RANGE.contains(&self.line_height) {
+      reject(
+        "line_height",
+        format!(
+          "Line height must be between {} and {}",
+          LINE_HEIGHT_RANGE.start(),
+          LINE_HEIGHT_RANGE.end()
+        ),
+      );
+    }
+    if !TAB_SIZE_RANGE.contains(&self.tab_size) {
+      reject(
+        "tab_size",
+        format!(
+          "Tab size must be between {} and {}",
+          TAB_SIZE_RANGE.start(),
+          TAB_SIZE_RANGE.end()
+        ),
+      );
+    }
+    if self.autosave_interval_secs < MIN_AUTOSAVE_INTERVAL_SECS {
+      reject(
+        "autosave_interval_secs",
+        format!(
+          "Autosave interval must be at least {} seconds",
+          MIN_AUTOSAVE_INTERVAL_SECS
+        ),
+      );
+    }
+    errors
+  }
+}
+
+// Extensions the open dialog shows when the user hasn't configured any
+const DEFAULT_OPEN_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+// Editor and OS clutter the workspace watcher leaves out unless configured otherwise
+const DEFAULT_WORKSPACE_IGNORE: &[&str] = &[".DS_Store", "*.swp", "*~"];
+
+// User settings. Missing fields take their defaults so older stores keep loading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+  pub filename_separator: FilenameSeparator,
+  // File extensions listed by the open dialog's Markdown filter, e.g. `mdx`, `qmd`
+  pub open_extensions: Vec<String>,
+  // Which window opens files from Finder, the dock or a second launch while the app runs
+  pub open_files_in: OpenFilesIn,
+  // Fsync every save before reporting success (see write_atomically_durable). Off by
+  // default since it makes saves noticeably slower on removable and network drives.
+  pub durable_saves: bool,
+  pub print: PrintOptions,
+  // Author and file names of exports, unless a document's frontmatter says otherwise
+  pub export: ExportSettings,
+  // Look for a new version at startup (the menu can always check)
+  pub check_updates_automatically: bool,
+  // Show an icon with quick actions in the system tray (menu bar on macOS)
+  pub show_tray_icon: bool,
+  // Closing the last window leaves the app running in the tray instead of quitting. Only
+  // applies while the tray icon is shown.
+  pub keep_running_in_tray: bool,
+  // Curl quotes and convert dashes and ellipses when saving (see typography.rs)
+  pub smart_typography_on_save: bool,
+  // Terminal for Open in Terminal: an app name on macOS (`iTerm`), a program elsewhere.
+  // None picks the platform's usual one.
+  pub terminal_app: Option<String>,
+  // Where Export > Slides starts a new slide
+  pub slide_split: SlideSplit,
+  // File and folder names the workspace watcher ignores, with `*` and `?` wildcards.
+  // `.git` and `node_modules` are always ignored.
+  pub workspace_ignore: Vec<String>,
+  // Open the most recent workspace again when the app starts
+  pub open_last_workspace_on_start: bool,
+  // Stylesheet added to the preview, print and exports of every document. Must be a .css
+  // file in the app's config folder.
+  pub custom_css_path: Option<String>,
+  // Drop utm_* and other tracking parameters from URLs pasted as links
+  pub strip_tracking_params: bool,
+  pub editor: EditorSettings,
+  // Where Quick Capture appends, and when it starts a new file
+  pub inbox: InboxOptions,
+  // Accelerators of menu items by item id, replacing the defaults (see shortcuts.rs). An
+  // empty one removes the item's shortcut.
+  pub shortcuts: BTreeMap<String, String>,
+}
+
+impl Default for Settings {
+  fn default() -> Self {
+    Settings {
+      filename_separator: FilenameSeparator::default(),
+      open_extensions: DEFAULT_OPEN_EXTENSIONS
+        .iter()
+        .map(|e| e.to_string())
+        .collect(),
+      open_files_in: OpenFilesIn::default(),
+      durable_saves: false,
+      print: PrintOptions::default(),
+      export: ExportSettings::default(),
+      check_updates_automatically: true,
+      show_tray_icon: false,
+      keep_running_in_tray: false,
+      smart_typography_on_save: false,
+      terminal_app: None,
+      slide_split: SlideSplit::default(),
+      workspace_ignore: DEFAULT_WORKSPACE_IGNORE
+        .iter()
+        .map(|pattern| pattern.to_string())
+        .collect(),
+      open_last_workspace_on_start: false,
+      custom_css_path: None,
+      strip_tracking_params: true,
+      editor: EditorSettings::default(),
+      inbox: InboxOptions::default(),
+      shortcuts: BTreeMap::new(),
+    }
+  }
+}
+
+impl Settings {
+  // `open_extensions` cleaned up for the dialog filter: `.MDX` -> `mdx`, no blanks or
+  // duplicates, and the defaults if nothing usable is left
+  pub fn open_dialog_extensions(&self) -> Vec<String> {
+    let mut extensions: Vec<String> = Vec::new();
+    for extension in &self.open_extensions {
+      let extension = extension.trim().trim_start_matches('.').to_lowercase();
+      if !extension.is_empty() && !extensions.contains(&extension) {
+        extensions.push(extension);
+      }
+    }
+    if extensions.is_empty() {
+      return Settings::default().open_extensions;
+    }
+    extensions
+  }
+
+  // Every field that can't be saved as it is
+  pub fn validate(&self) -> CommandResult<()> {
+    let mut errors = self.editor.validate();
+    errors.extend(self.inbox.validate());
+    errors.extend(self.export.validate());
+    errors.extend(crate::shortcuts::validate(&self.shortcuts));
+    if errors.is_empty() {
+      Ok(())
+    } else {
+      Err(CommandError::InvalidSettings { errors })
+    }
+  }
+}
+
+pub struct SettingsState(pub Mutex<Settings>);
+
+// Read settings from the store, falling back to defaults
+pub fn load_settings(app: &AppHandle) -> Settings {
+  match app_store::open_store(app) {
+    Ok(store) => store
+      .get(SETTINGS_KEY)
+      .and_then(|value| serde_json::from_value(value).ok())
+      .unwrap_or_default(),
+    Err(e) => {
+      log::warn!("Failed to load settings: {}", e);
+      Settings::default()
+    }
+  }
+}
+
+fn save_settings(app: &AppHandle, settings: &Settings) -> CommandResult<()> {
+  let value = serde_json::to_value(settings)
+    .map_err(|e| CommandError::io("Failed to serialize settings", e))?;
+  app_store::write_key(app, "save settings", SETTINGS_KEY, value)
+}
+
+#[tauri::command]
+pub async fn get_settings(state: tauri::State<'_, SettingsState>) -> CommandResult<Settings> {
+  Ok(state.0.lock().unwrap().clone())
+}
+
+// Validate, persist and apply new settings, and notify the frontend
+pub(crate) fn apply_settings(
+  app: &AppHandle,
+  state: &SettingsState,
+  settings: Settings,
+) -> CommandResult<Settings> {
+  settings.validate()?;
+  // Safe mode keeps changes to this launch, leaving the saved settings for the next one
+  if crate::safe_mode::startup(app).settings {
+    save_settings(app, &settings)?;
+  }
+  *state.0.lock().unwrap() = settings.clone();
+  #[cfg(desktop)]
+  crate::tray::apply_settings(app, &settings);
+  if let Err(e) = crate::shortcuts::update_menu(app, &settings.shortcuts) {
+    log::error!("Failed to update menu shortcuts: {}", e);
+  }
+  crate::menu_toggles::sync(app, &settings);
+  let _ = app.emit(SETTINGS_CHANGED_EVENT, settings.clone());
+  Ok(settings)
+}
+
+// Replace the settings, persist them and notify the frontend. Nothing is changed if any
+// field is invalid; the error lists them all.
+#[tauri::command]
+pub async fn update_settings(
+  app: AppHandle,
+  state: tauri::State<'_, SettingsState>,
+  settings: Settings,
+) -> CommandResult<Settings> {
+  apply_settings(&app, &state, settings)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use serde_json::json;
+
+  #[test]
+  fn test_missing_fields_use_defaults() {
+    let settings: Settings = serde_json::from_value(json!({})).unwrap();
+    assert_eq!(settings, Settings::default());
+    assert_eq!(settings.filename_separator, FilenameSeparator::Dash);
+
+    let settings: Settings =
+      serde_json::from_value(json!({"filename_separator": "space", "unknown": 1})).unwrap();
+    assert_eq!(settings.filename_separator, FilenameSeparator::Space);
+
+    let settings: Settings =
+      serde_json::from_value(json!({"print": {"page_break_on_h1": true}})).unwrap();
+    assert!(settings.print.page_break_on_h1);
+    assert!(!settings.print.table_of_contents);
+  }
+
+  #[test]
+  fn test_paper_stays_light_unless_dark_is_forced() {
+    let mut print: PrintOptions = serde_json::from_value(json!({"theme": "match-app"})).unwrap();
+    assert_eq!(
+      print.palettes(Palette::Dark),
+      (Palette::Dark, Palette::Light)
+    );
+    assert_eq!(
+      print.palettes(Palette::Light),
+      (Palette::Light, Palette::Light)
+    );
+    print.dark_on_paper = true;
+    assert_eq!(
+      print.palettes(Palette::Dark),
+      (Palette::Dark, Palette::Dark)
+    );
+    assert_eq!(
+      PrintOptions::default().palettes(Palette::Dark),
+      (Palette::Light, Palette::Light)
+    );
+  }
+
+  #[test]
+  fn test_open_dialog_extensions_are_normalized() {
+    let settings = Settings {
+      open_extensions: vec![".MDX".into(), "md".into(), " qmd ".into(), "mdx".into()],
+      ..Settings::default()
+    };
+    assert_eq!(settings.open_dialog_extensions(), vec!["mdx", "md", "qmd"]);
+
+    let settings = Settings {
+      open_extensions: vec!["".into(), ".".into()],
+      ..Settings::default()
+    };
+    assert_eq!(
+      settings.open_dialog_extensions(),
+      vec!["md", "markdown", "txt"]
+    );
+  }
+
+  #[test]
+  fn test_editor_settings_report_every_invalid_field() {
+    assert!(Settings::default().validate().is_ok());
+
+    let settings: Settings = serde_json::from_value(json!({
+      "editor": {"font_size": 7, "tab_size": 4, "autosave_interval_secs": 2}
+    }))
+    .unwrap();
+    assert_eq!(settings.editor.tab_size, 4);
+    assert!(settings.editor.insert_spaces);
+    let Err(CommandError::InvalidSettings { errors }) = settings.validate() else {
+      panic!("expected invalid settings");
+    };
+    let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+    assert_eq!(
+      fields,
+      vec!["editor.font_size", "editor.autosave_interval_secs"]
+    );
+
+    let editor = EditorSettings {
+      font_size: 72,
+      line_height: 3.5,
+      font_family: Some(" ".into()),
+      ..EditorSettings::default()
+    };
+    let fields: Vec<String> = editor.validate().into_iter().map(|e| e.field).collect();
+    assert_eq!(fields, vec!["editor.font_family", "editor.line_height"]);
+  }
+}
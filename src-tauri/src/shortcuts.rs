@@ -0,0 +1,393 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tauri::AppHandle;
+
+use crate::dynamic_menu;
+use crate::error::{CommandResult, FieldError};
+use crate::settings::{self, SettingsState};
+
+// Menu items whose accelerator can be changed: id, name for messages, default accelerator
+const ACTIONS: &[(&str, &str, Option<&str>)] = &[
+  ("check_for_updates", "Check for Updates", None),
+  ("export_app_data", "Export App Data", None),
+  ("import_app_data", "Import App Data", None),
+  ("new_file", "New", Some("CmdOrCtrl+N")),
+  (
+    "new_from_clipboard",
+    "New from Clipboard",
+    Some("CmdOrCtrl+Shift+N"),
+  ),
+  ("open_file", "Open", Some("CmdOrCtrl+O")),
+  ("reopen_closed", "Reopen Closed", Some("CmdOrCtrl+Shift+T")),
+  ("open_inbox", "Open Inbox", None),
+  ("import_document", "Import Document", None),
+  ("open_terminal", "Open in Terminal", None),
+  ("save_file", "Save", Some("CmdOrCtrl+S")),
+  ("save_as_file", "Save As", Some("CmdOrCtrl+Shift+S")),
+  ("export_html", "Export HTML", None),
+  ("export_print_html", "Export Print Layout as HTML", None),
+  ("export_slides", "Export Slides", None),
+  ("export_latex", "Export LaTeX", None),
+  ("export_plain_text", "Export Plain Text", None),
+  ("split_h1", "Split by Heading 1", None),
+  ("split_h2", "Split by Heading 2", None),
+  ("split_h3", "Split by Heading 3", None),
+  ("publish_gist", "Publish as Gist", None),
+  ("copy_plain_text", "Copy as Plain Text", None),
+  ("copy_for_email", "Copy for Email", None),
+  ("lines_sort_ascending", "Sort Ascending", None),
+  ("lines_sort_descending", "Sort Descending", None),
+  ("lines_dedupe", "Remove Duplicate Lines", None),
+  ("lines_reverse", "Reverse Lines", None),
+  ("start_speaking", "Start Speaking", None),
+  ("stop_speaking", "Stop Speaking", None),
+  ("smarten_typography", "Smart Punctuation", None),
+  ("straighten_typography", "Straight Punctuation", None),
+  ("tidy_references", "Tidy Footnotes and Links", None),
+  ("case_upper", "UPPERCASE", None),
+  ("case_lower", "lowercase", None),
+  ("case_title", "Title Case", None),
+  ("case_sentence", "Sentence case", None),
+  ("headings_promote", "Promote Headings", None),
+  ("headings_demote", "Demote Headings", None),
+  ("show_shortcuts", "Keyboard Shortcuts", Some("CmdOrCtrl+/")),
+  ("open_documentation", "Documentation", None),
+  ("show_logs", "Show Logs", None),
+  ("report_issue", "Report an Issue", None),
+];
+
+// Accelerators of the menu items that can't be changed: Quit and the predefined edit and
+// window items
+const FIXED: &[(&str, &str)] = &[
+  ("Quit Markdowner", "CmdOrCtrl+Q"),
+  ("Undo", "CmdOrCtrl+Z"),
+  ("Redo", "CmdOrCtrl+Shift+Z"),
+  ("Cut", "CmdOrCtrl+X"),
+  ("Copy", "CmdOrCtrl+C"),
+  ("Paste", "CmdOrCtrl+V"),
+  ("Select All", "CmdOrCtrl+A"),
+  ("Close Window", "CmdOrCtrl+W"),
+  ("Minimize", "CmdOrCtrl+M"),
+];
+
+// Shortcuts the OS handles before the app sees them
+#[cfg(target_os = "macos")]
+const RESERVED: &[&str] = &[
+  "Cmd+Q",
+  "Cmd+H",
+  "Cmd+Alt+H",
+  "Cmd+Tab",
+  "Cmd+Space",
+  "Cmd+`",
+  "Cmd+Alt+Escape",
+  "Ctrl+Cmd+Q",
+];
+#[cfg(not(target_os = "macos"))]
+const RESERVED: &[&str] = &["Alt+F4", "Alt+Tab", "Ctrl+Alt+Delete", "Super+L", "Super+D"];
+
+// Keys with names, as accepted in accelerators (aliases first, then the canonical name)
+const NAMED_KEYS: &[(&str, &str)] = &[
+  ("return", "Enter"),
+  ("enter", "Enter"),
+  ("esc", "Escape"),
+  ("escape", "Escape"),
+  ("space", "Space"),
+  ("tab", "Tab"),
+  ("backspace", "Backspace"),
+  ("delete", "Delete"),
+  ("insert", "Insert"),
+  ("home", "Home"),
+  ("end", "End"),
+  ("pageup", "PageUp"),
+  ("pagedown", "PageDown"),
+  ("up", "Up"),
+  ("down", "Down"),
+  ("left", "Left"),
+  ("right", "Right"),
+  ("plus", "Plus"),
+];
+
+const PUNCTUATION_KEYS: &str = "/.,;'[]\\-=`";
+
+// A key combination as the platform sees it: CmdOrCtrl is Cmd on macOS and Ctrl elsewhere
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Chord {
+  ctrl: bool,
+  alt: bool,
+  shift: bool,
+  // Cmd on macOS, the Windows key elsewhere
+  meta: bool,
+  key: String,
+}
+
+fn parse_key(key: &str) -> Option<String> {
+  let lower = key.to_ascii_lowercase();
+  if let Some((_, name)) = NAMED_KEYS.iter().find(|(alias, _)| *alias == lower) {
+    return Some(name.to_string());
+  }
+  let mut chars = key.chars();
+  match (chars.next(), chars.next()) {
+    (Some(c), None) if c.is_ascii_alphanumeric() => Some(c.to_ascii_uppercase().to_string()),
+    (Some(c), None) if PUNCTUATION_KEYS.contains(c) => Some(c.to_string()),
+    _ => {
+      let number: u32 = lower.strip_prefix('f')?.parse().ok()?;
+      (1..=24).contains(&number).then(|| format!("F{}", number))
+    }
+  }
+}
+
+// Parse an accelerator like `CmdOrCtrl+Shift+K`, in the syntax menu items take
+fn parse_accelerator(accelerator: &str) -> Result<Chord, String> {
+  let parts: Vec<&str> = accelerator.split('+').map(str::trim).collect();
+  let (key, modifiers) = match parts.split_last() {
+    // `CmdOrCtrl++` is Plus
+    Some((&"", [rest @ .., ""])) => ("Plus", rest),
+    Some((key, modifiers)) => (*key, modifiers),
+    None => return Err("Shortcut is empty".to_string()),
+  };
+  let mut chord = Chord {
+    ctrl: false,
+    alt: false,
+    shift: false,
+    meta: false,
+    key: parse_key(key).ok_or_else(|| format!("Unknown key \"{}\"", key))?,
+  };
+  for modifier in modifiers {
+    let flag = match modifier.to_ascii_lowercase().as_str() {
+      "cmdorctrl" | "commandorcontrol" if cfg!(target_os = "macos") => &mut chord.meta,
+      "cmdorctrl" | "commandorcontrol" => &mut chord.ctrl,
+      "cmd" | "command" | "super" | "meta" => &mut chord.meta,
+      "ctrl" | "control" => &mut chord.ctrl,
+      "alt" | "option" => &mut chord.alt,
+      "shift" => &mut chord.shift,
+      _ => return Err(format!("Unknown modifier \"{}\"", modifier)),
+    };
+    if *flag {
+      return Err(format!("\"{}\" is repeated", modifier));
+    }
+    *flag = true;
+  }
+  // A bare key, or one with only Shift, would stop that key from typing
+  let function_key = chord.key.len() > 1 && chord.key.starts_with('F');
+  if !(function_key || chord.ctrl || chord.alt || chord.meta) {
+    return Err("Shortcut needs Cmd, Ctrl or Alt".to_string());
+  }
+  Ok(chord)
+}
+
+fn find_action(id: &str) -> Option<&'static (&'static str, &'static str, Option<&'static str>)> {
+  ACTIONS.iter().find(|(action, _, _)| *action == id)
+}
+
+// The accelerator of a menu item with the user's overrides: an empty override removes the
+// default. Items that can't be changed keep what they're given.
+pub fn accelerator(overrides: &BTreeMap<String, String>, id: &str) -> Option<String> {
+  match overrides.get(id) {
+    Some(accelerator) => Some(accelerator.trim().to_string()).filter(|a| !a.is_empty()),
+    None => find_action(id).and_then(|(_, _, default)| default.map(str::to_string)),
+  }
+}
+
+// What's wrong with each override, named `shortcuts.<action>`: unknown actions, accelerators
+// that don't parse or that the OS reserves, and ones already used by another menu item
+pub fn validate(overrides: &BTreeMap<String, String>) -> Vec<FieldError> {
+  let mut errors = Vec::new();
+  let taken: Vec<(&str, Chord)> = FIXED
+    .iter()
+    .filter_map(|(name, accelerator)| Some((*name, parse_accelerator(accelerator).ok()?)))
+    .chain(ACTIONS.iter().filter_map(|(id, name, _)| {
+      let chord = parse_accelerator(&accelerator(overrides, id)?).ok()?;
+      Some((*name, chord))
+    }))
+    .collect();
+  for (id, value) in overrides {
+    let mut reject = |message: String| {
+      errors.push(FieldError {
+        field: format!("shortcuts.{}", id),
+        message,
+      })
+    };
+    let Some((_, name, _)) = find_action(id) else {
+      reject("Unknown menu item".to_string());
+      continue;
+    };
+    if value.trim().is_empty() {
+      continue;
+    }
+    let chord = match parse_accelerator(value) {
+      Ok(chord) => chord,
+      Err(message) => {
+        reject(message);
+        continue;
+      }
+    };
+    if RESERVED
+      .iter()
+      .any(|reserved| parse_accelerator(reserved).as_ref() == Ok(&chord))
+    {
+      reject("Reserved by the system".to_string());
+    } else if let Some((other, _)) = taken
+      .iter()
+      .find(|(other, taken)| other != name && *taken == chord)
+    {
+      reject(format!("Already used by {}", other));
+    }
+  }
+  errors
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Shortcut {
+  pub action: String,
+  pub name: String,
+  pub accelerator: Option<String>,
+  pub default_accelerator: Option<String>,
+}
+
+fn list(overrides: &BTreeMap<String, String>) -> Vec<Shortcut> {
+  ACTIONS
+    .iter()
+    .map(|(id, name, default)| Shortcut {
+      action: id.to_string(),
+      name: name.to_string(),
+      accelerator: accelerator(overrides, id),
+      default_accelerator: default.map(str::to_string),
+    })
+    .collect()
+}
+
+// Give the menu items the accelerators the overrides call for
+pub fn update_menu(app: &AppHandle, overrides: &BTreeMap<String, String>) -> tauri::Result<()> {
+  for item in dynamic_menu::menu_items(app)? {
+    let id = item.id().0.clone();
+    if find_action(&id).is_some() {
+      item.set_accelerator(accelerator(overrides, &id))?;
+    }
+  }
+  Ok(())
+}
+
+#[tauri::command]
+pub async fn get_shortcuts(state: tauri::State<'_, SettingsState>) -> CommandResult<Vec<Shortcut>> {
+  Ok(list(&state.0.lock().unwrap().shortcuts))
+}
+
+// Change one menu item's accelerator; None removes it. Setting it back to the default
+// drops the override.
+#[tauri::command]
+pub async fn set_shortcut(
+  app: AppHandle,
+  state: tauri::State<'_, SettingsState>,
+  action: String,
+  accelerator: Option<String>,
+) -> CommandResult<Vec<Shortcut>> {
+  let mut settings = state.0.lock().unwrap().clone();
+  let default = find_action(&action).and_then(|(_, _, default)| *default);
+  let accelerator = accelerator
+    .map(|a| a.trim().to_string())
+    .filter(|a| !a.is_empty());
+  if accelerator.as_deref() == default {
+    settings.shortcuts.remove(&action);
+  } else {
+    settings
+      .shortcuts
+      .insert(action, accelerator.unwrap_or_default());
+  }
+  let settings = settings::apply_settings(&app, &state, settings)?;
+  Ok(list(&settings.shortcuts))
+}
+
+// Put every menu item back to its default accelerator
+#[tauri::command]
+pub async fn reset_shortcuts(
+  app: AppHandle,
+  state: tauri::State<'_, SettingsState>,
+) -> CommandResult<Vec<Shortcut>> {
+  let mut settings = state.0.lock().unwrap().clone();
+  settings.shortcuts.clear();
+  let settings = settings::apply_settings(&app, &state, settings)?;
+  Ok(list(&settings.shortcuts))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn overrides(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs
+      .iter()
+      .map(|(id, accelerator)| (id.to_string(), accelerator.to_string()))
+      .collect()
+  }
+
+  fn messages(overrides: &BTreeMap<String, String>) -> Vec<(String, String)> {
+    validate(overrides)
+      .into_iter()
+      .map(|error| (error.field, error.message))
+      .collect()
+  }
+
+  #[test]
+  fn test_parse_accelerator() {
+    let chord = parse_accelerator("cmdorctrl + shift + k").unwrap();
+    assert_eq!(chord.key, "K");
+    assert!(chord.shift);
+    assert_eq!(
+      chord,
+      parse_accelerator("Shift+CommandOrControl+K").unwrap()
+    );
+    assert_eq!(parse_accelerator("Ctrl++").unwrap().key, "Plus");
+    assert_eq!(parse_accelerator("F5").unwrap().key, "F5");
+    assert_eq!(parse_accelerator("Alt+Return").unwrap().key, "Enter");
+
+    assert!(parse_accelerator("Shift+K").is_err());
+    assert!(parse_accelerator("Ctrl+Ctrl+K").is_err());
+    assert!(parse_accelerator("Hyper+K").is_err());
+    assert!(parse_accelerator("Ctrl+F25").is_err());
+    assert!(parse_accelerator("Ctrl+").is_err());
+  }
+
+  #[test]
+  fn test_overrides_apply_and_validate() {
+    let custom = overrides(&[("export_html", "CmdOrCtrl+E"), ("save_as_file", "")]);
+    assert!(validate(&custom).is_empty());
+    assert_eq!(
+      accelerator(&custom, "export_html").as_deref(),
+      Some("CmdOrCtrl+E")
+    );
+    assert_eq!(accelerator(&custom, "save_as_file"), None);
+    assert_eq!(
+      accelerator(&custom, "save_file").as_deref(),
+      Some("CmdOrCtrl+S")
+    );
+
+    // Taking Save As's old shortcut is fine now that it has none
+    let custom = overrides(&[("save_as_file", ""), ("export_html", "CmdOrCtrl+Shift+S")]);
+    assert!(validate(&custom).is_empty());
+
+    let invalid = overrides(&[
+      ("export_latex", "CmdOrCtrl+S"),
+      ("export_slides", "CmdOrCtrl+C"),
+      ("export_html", "CmdOrCtrl+Q"),
+      ("nonexistent", "CmdOrCtrl+J"),
+      ("publish_gist", "CmdOrCtrl+Hyper"),
+    ]);
+    let quit = if cfg!(target_os = "macos") {
+      "Reserved by the system"
+    } else {
+      "Already used by Quit Markdowner"
+    };
+    let expected = [
+      ("shortcuts.export_html", quit),
+      ("shortcuts.export_latex", "Already used by Save"),
+      ("shortcuts.export_slides", "Already used by Copy"),
+      ("shortcuts.nonexistent", "Unknown menu item"),
+      ("shortcuts.publish_gist", "Unknown key \"Hyper\""),
+    ];
+    let expected: Vec<(String, String)> = expected
+      .iter()
+      .map(|(field, message)| (field.to_string(), message.to_string()))
+      .collect();
+    assert_eq!(messages(&invalid), expected);
+  }
+}
@@ -0,0 +1,518 @@
+use pulldown_cmark::{html, CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::SyntaxSet;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_dialog::DialogExt;
+
+use crate::access_scope::AccessScopeState;
+use crate::atomic_write::write_atomically;
+use crate::error::{CommandError, CommandResult};
+use crate::export::requested_output_path;
+use crate::settings::{Palette, SettingsState};
+
+// Code block colors, to go with reveal's white and black themes
+fn highlight_theme(palette: Palette) -> &'static str {
+  match palette {
+    Palette::Light => "InspiredGitHub",
+    Palette::Dark => "base16-ocean.dark",
+  }
+}
+
+// Where one slide ends and the next begins
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SlideSplit {
+  // `---` horizontal rules
+  #[default]
+  Rule,
+  // Every H2 heading starts a slide
+  Heading,
+}
+
+// reveal.js itself, bundled with the frontend and passed in so the deck works offline
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SlideRuntime {
+  pub script: String,
+  pub notes_plugin: String,
+  pub stylesheet: String,
+  pub theme: String,
+}
+
+// Math and mermaid rendered by the preview's renderers, keyed by their (trimmed) source
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Prerendered {
+  pub math: HashMap<String, String>,
+  pub display_math: HashMap<String, String>,
+  pub mermaid: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SlideOptions {
+  // None uses the setting
+  pub split: Option<SlideSplit>,
+  pub runtime: SlideRuntime,
+  pub prerendered: Prerendered,
+  // The look the app has when exporting, for the `match-app` theme. The frontend passes
+  // the reveal theme and mermaid renderings for the resolved palette.
+  pub app_theme: Palette,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlideExportResult {
+  pub output_path: String,
+  pub slide_count: usize,
+}
+
+// One slide's markdown and its speaker notes
+#[derive(Debug, Clone, PartialEq)]
+struct Slide {
+  content: String,
+  notes: Option<String>,
+}
+
+// Where the document starts after its frontmatter, if it has one
+fn body_start(markdown: &str) -> usize {
+  if !markdown.starts_with("---") {
+    return 0;
+  }
+  let mut offset = 0;
+  for (index, line) in markdown.split_inclusive('\n').enumerate() {
+    offset += line.len();
+    if index > 0 && matches!(line.trim_end(), "---" | "...") {
+      return offset;
+    }
+  }
+  0
+}
+
+// Ranges of the top-level blocks `keep` picks, with events from pulldown-cmark
+fn top_level<'a>(
+  markdown: &'a str,
+  keep: impl Fn(&Event<'a>) -> bool + 'a,
+) -> impl Iterator<Item = Range<usize>> + 'a {
+  let mut depth = 0usize;
+  Parser::new_ext(markdown, Options::all())
+    .into_offset_iter()
+    .filter_map(move |(event, range)| {
+      let picked = depth == 0 && keep(&event);
+      match event {
+        Event::Start(_) => depth += 1,
+        Event::End(_) => depth -= 1,
+        _ => {}
+      }
+      picked.then_some(range)
+    })
+}
+
+// Cut the document into slides: at each top-level `---` rule, or before each H2. A `---`
+// in a code block or under a line of text (a setext heading) doesn't split.
+fn split_slides(markdown: &str, split: SlideSplit) -> Vec<&str> {
+  let markdown = &markdown[body_start(markdown)..];
+  let cuts: Vec<Range<usize>> = match split {
+    SlideSplit::Rule => top_level(markdown, |event| matches!(event, Event::Rule)).collect(),
+    SlideSplit::Heading => top_level(markdown, |event| {
+      matches!(
+        event,
+        Event::Start(Tag::Heading {
+          level: HeadingLevel::H2,
+          ..
+        })
+      )
+    })
+    .map(|range| range.start..range.start)
+    .collect(),
+  };
+  let mut slides = Vec::new();
+  let mut start = 0;
+  for cut in cuts.into_iter().chain([markdown.len()..markdown.len()]) {
+    let slide = &markdown[start..cut.start];
+    if !slide.trim().is_empty() {
+      slides.push(slide);
+    }
+    start = cut.end;
+  }
+  slides
+}
+
+// Take blockquotes starting with `Note:` out of a slide as its speaker notes
+fn take_notes(slide: &str) -> Slide {
+  let mut content = String::new();
+  let mut notes: Vec<String> = Vec::new();
+  let mut last = 0;
+  for range in top_level(slide, |event| {
+    matches!(event, Event::Start(Tag::BlockQuote(_)))
+  }) {
+    let quoted: Vec<&str> = slide[range.clone()]
+      .lines()
+      .map(|line| {
+        let line = line.trim_start();
+        let line = line.strip_prefix('>').unwrap_or(line);
+        line.strip_prefix(' ').unwrap_or(line)
+      })
+      .collect();
+    let quoted = quoted.join("\n");
+    let Some(note) = quoted.trim_start().strip_prefix("Note:") else {
+      continue;
+    };
+    content.push_str(&slide[last..range.start]);
+    notes.push(note.trim().to_string());
+    last = range.end;
+  }
+  content.push_str(&slide[last..]);
+  Slide {
+    content,
+    notes: (!notes.is_empty()).then(|| notes.join("\n\n")),
+  }
+}
+
+fn escape_html(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+    .replace('"', "&quot;")
+}
+
+fn highlighter() -> &'static (SyntaxSet, ThemeSet) {
+  static HIGHLIGHTER: OnceLock<(SyntaxSet, ThemeSet)> = OnceLock::new();
+  HIGHLIGHTER.get_or_init(|| {
+    (
+      SyntaxSet::load_defaults_newlines(),
+      ThemeSet::load_defaults(),
+    )
+  })
+}
+
+// Code colored with inline styles, so the deck needs no highlighting script or stylesheet
+fn highlight_code(code: &str, language: &str, palette: Palette) -> String {
+  let (syntaxes, themes) = highlighter();
+  let syntax = syntaxes
+    .find_syntax_by_token(language)
+    .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+  themes
+    .themes
+    .get(highlight_theme(palette))
+    .and_then(|theme| {
+      syntect::html::highlighted_html_for_string(code, syntaxes, syntax, theme).ok()
+    })
+    .unwrap_or_else(|| format!("<pre><code>{}</code></pre>\n", escape_html(code)))
+}
+
+fn code_block_html(
+  code: &str,
+  language: &str,
+  prerendered: &Prerendered,
+  palette: Palette,
+) -> String {
+  if language == "mermaid" {
+    return match prerendered.mermaid.get(code.trim()) {
+      Some(svg) => format!("<div class=\"mermaid-container\">{}</div>\n", svg),
+      None => format!("<pre class=\"mermaid\">{}</pre>\n", escape_html(code)),
+    };
+  }
+  highlight_code(code, language, palette)
+}
+
+fn math_html(source: &str, display: bool, prerendered: &Prerendered) -> String {
+  let (rendered, delimiter, class) = if display {
+    (&prerendered.display_math, "$$", "math-display")
+  } else {
+    (&prerendered.math, "$", "math-inline")
+  };
+  match rendered.get(source.trim()) {
+    Some(html) => format!("<span class=\"{}\">{}</span>", class, html),
+    // Not rendered by the preview: show the source as written
+    None => format!(
+      "<span class=\"{}\">{}{}{}</span>",
+      class,
+      delimiter,
+      escape_html(source),
+      delimiter
+    ),
+  }
+}
+
+// Markdown to HTML, with code highlighted and math and diagrams swapped for their renderings
+fn render(markdown: &str, prerendered: &Prerendered, palette: Palette) -> String {
+  let mut events = Vec::new();
+  // Language and text of the code block being read
+  let mut code: Option<(String, String)> = None;
+  for event in Parser::new_ext(markdown, Options::all()) {
+    match event {
+      Event::Start(Tag::CodeBlock(kind)) => {
+        let language = match kind {
+          CodeBlockKind::Fenced(info) => info.split_whitespace().next().unwrap_or("").to_string(),
+          CodeBlockKind::Indented => String::new(),
+        };
+        code = Some((language, String::new()));
+      }
+      Event::Text(text) if code.is_some() => {
+        if let Some((_, body)) = code.as_mut() {
+          body.push_str(&text);
+        }
+      }
+      Event::End(TagEnd::CodeBlock) => {
+        if let Some((language, body)) = code.take() {
+          events.push(Event::Html(
+            code_block_html(&body, &language, prerendered, palette).into(),
+          ));
+        }
+      }
+      Event::InlineMath(source) => {
+        events.push(Event::InlineHtml(
+          math_html(&source, false, prerendered).into(),
+        ));
+      }
+      Event::DisplayMath(source) => {
+        events.push(Event::InlineHtml(
+          math_html(&source, true, prerendered).into(),
+        ));
+      }
+      event => events.push(event),
+    }
+  }
+  let mut rendered = String::new();
+  html::push_html(&mut rendered, events.into_iter());
+  rendered
+}
+
+// Runtime code goes inside <script> and <style>, which would end at a literal closing tag
+fn embeddable(code: &str, tag: &str) -> String {
+  code.replace(&format!("</{}", tag), &format!("<\\/{}", tag))
+}
+
+fn deck_html(title: &str, slides: &[Slide], options: &SlideOptions, palette: Palette) -> String {
+  let runtime = &options.runtime;
+  let mut sections = String::new();
+  for slide in slides {
+    sections.push_str("<section>\n");
+    sections.push_str(&render(&slide.content, &options.prerendered, palette));
+    if let Some(notes) = &slide.notes {
+      sections.push_str("<aside class=\"notes\">\n");
+      sections.push_str(&render(notes, &options.prerendered, palette));
+      sections.push_str("</aside>\n");
+    }
+    sections.push_str("</section>\n");
+  }
+  format!(
+    "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+     <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n\
+     <title>{}</title>\n<style>\n{}\n</style>\n<style>\n{}\n</style>\n\
+     <style>\n.reveal pre {{ box-shadow: none; }}\n.reveal .mermaid-container svg {{ max-height: 60vh; }}\n</style>\n\
+     </head>\n<body>\n<div class=\"reveal\">\n<div class=\"slides\">\n{}</div>\n</div>\n\
+     <script>\n{}\n</script>\n<script>\n{}\n</script>\n\
+     <script>\nReveal.initialize({{ hash: true, plugins: [RevealNotes] }});\n</script>\n\
+     </body>\n</html>\n",
+    escape_html(title),
+    embeddable(&runtime.stylesheet, "style"),
+    embeddable(&runtime.theme, "style"),
+    sections,
+    embeddable(&runtime.script, "script"),
+    embeddable(&runtime.notes_plugin, "script"),
+  )
+}
+
+fn export_slides_to(
+  title: &str,
+  markdown: &str,
+  output_path: &Path,
+  split: SlideSplit,
+  palette: Palette,
+  options: &SlideOptions,
+) -> CommandResult<SlideExportResult> {
+  if !output_path.is_absolute() {
+    return Err(CommandError::invalid_path(
+      output_path,
+      "File path must be absolute",
+    ));
+  }
+  if options.runtime.script.trim().is_empty() {
+    return Err(CommandError::invalid_data(
+      "The reveal.js runtime is missing",
+    ));
+  }
+  let slides: Vec<Slide> = split_slides(markdown, split)
+    .into_iter()
+    .map(take_notes)
+    .collect();
+  write_atomically(
+    output_path,
+    deck_html(title, &slides, options, palette).as_bytes(),
+  )
+  .map_err(|e| CommandError::from_io(&e, output_path, "Failed to write file"))?;
+  Ok(SlideExportResult {
+    output_path: output_path.to_string_lossy().to_string(),
+    slide_count: slides.len(),
+  })
+}
+
+// Write the document as a self-contained reveal.js deck. Prompts for a destination when no
+// path is given; returns None if the dialog was cancelled.
+#[tauri::command]
+pub async fn export_slides(
+  app: AppHandle,
+  settings: tauri::State<'_, SettingsState>,
+  document_path: Option<String>,
+  markdown_content: String,
+  output_path: Option<String>,
+  options: SlideOptions,
+) -> CommandResult<Option<SlideExportResult>> {
+  let title = document_path
+    .as_deref()
+    .and_then(|d| Path::new(d).file_stem())
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_else(|| "Untitled".to_string());
+  let output_path = match output_path {
+    Some(path) => requested_output_path(&app.state::<AccessScopeState>(), path)?,
+    None => {
+      let picked = app
+        .dialog()
+        .file()
+        .add_filter("HTML", &["html", "htm"])
+        .set_file_name(format!("{}-slides.html", title))
+        .blocking_save_file();
+      match picked.map(crate::dialog_result_to_path).transpose()? {
+        Some(path) => path,
+        None => return Ok(None),
+      }
+    }
+  };
+  let (split, theme) = {
+    let settings = settings.0.lock().unwrap();
+    (settings.slide_split, settings.print.theme)
+  };
+  let split = options.split.unwrap_or(split);
+  let palette = theme.resolve(options.app_theme);
+  export_slides_to(
+    &title,
+    &markdown_content,
+    &output_path,
+    split,
+    palette,
+    &options,
+  )
+  .map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_split_on_rules() {
+    let markdown =
+      "---\ntitle: Talk\n---\n# Intro\n\n---\n\nSetext\n---\n\n```\n---\n```\n\n---\n\n***\n";
+    let slides: Vec<&str> = split_slides(markdown, SlideSplit::Rule)
+      .into_iter()
+      .map(str::trim)
+      .collect();
+    assert_eq!(slides, vec!["# Intro", "Setext\n---\n\n```\n---\n```"]);
+  }
+
+  #[test]
+  fn test_split_on_h2_headings() {
+    let markdown = "# Talk\n\nHello\n\n## One\n\nText\n\n### Detail\n\n## Two\n";
+    let slides = split_slides(markdown, SlideSplit::Heading);
+    assert_eq!(
+      slides,
+      vec![
+        "# Talk\n\nHello\n\n",
+        "## One\n\nText\n\n### Detail\n\n",
+        "## Two\n"
+      ]
+    );
+  }
+
+  #[test]
+  fn test_note_blockquotes_become_speaker_notes() {
+    let slide = take_notes("## One\n\n> A quote\n\n> Note: say hi\n> and *wave*\n\nAfter\n");
+    assert!(slide.content.starts_with("## One\n\n> A quote\n"));
+    assert!(slide.content.trim_end().ends_with("After"));
+    assert!(!slide.content.contains("Note"));
+    assert_eq!(slide.notes.as_deref(), Some("say hi\nand *wave*"));
+    assert_eq!(take_notes("Just text\n").notes, None);
+  }
+
+  #[test]
+  fn test_math_and_mermaid_use_prerendered_output() {
+    let mut prerendered = Prerendered::default();
+    prerendered
+      .math
+      .insert("x^2".to_string(), "<math>x2</math>".to_string());
+    prerendered
+      .mermaid
+      .insert("graph TD\n  A --> B".to_string(), "<svg></svg>".to_string());
+
+    let html = render(
+      "Inline $x^2$ and $y$.\n\n```mermaid\ngraph TD\n  A --> B\n```\n",
+      &prerendered,
+      Palette::Light,
+    );
+    assert!(html.contains("<span class=\"math-inline\"><math>x2</math></span>"));
+    assert!(html.contains("<span class=\"math-inline\">$y$</span>"));
+    assert!(html.contains("<div class=\"mermaid-container\"><svg></svg></div>"));
+  }
+
+  #[test]
+  fn test_code_colors_follow_the_palette() {
+    let light = highlight_code("fn main() {}\n", "rust", Palette::Light);
+    let dark = highlight_code("fn main() {}\n", "rust", Palette::Dark);
+    assert!(light.starts_with("<pre style=\"background-color:#ffffff;\">"));
+    assert!(dark.starts_with("<pre style=\"background-color:#2b303b;\">"));
+  }
+
+  #[test]
+  fn test_export_writes_a_self_contained_deck() {
+    let dir = TempDir::new().unwrap();
+    let output = dir.path().join("talk-slides.html");
+    let options = SlideOptions {
+      runtime: SlideRuntime {
+        script: "var Reveal = { s: '</script>' };".to_string(),
+        notes_plugin: "var RevealNotes = {};".to_string(),
+        stylesheet: ".reveal {}".to_string(),
+        theme: ".theme {}".to_string(),
+      },
+      ..SlideOptions::default()
+    };
+    let markdown = "# Hi\n\n---\n\n```rust\nfn main() {}\n```\n\n> Note: explain\n";
+    let result = export_slides_to(
+      "Talk <1>",
+      markdown,
+      &output,
+      SlideSplit::Rule,
+      Palette::Light,
+      &options,
+    )
+    .unwrap();
+    assert_eq!(result.slide_count, 2);
+
+    let deck = std::fs::read_to_string(&output).unwrap();
+    assert!(deck.contains("<title>Talk &lt;1&gt;</title>"));
+    assert!(deck.contains("var Reveal = { s: '<\\/script>' };"));
+    assert_eq!(deck.matches("<section>").count(), 2);
+    assert!(deck.contains("<aside class=\"notes\">\n<p>explain</p>"));
+    assert!(deck.contains("main"));
+    assert!(!deck.contains("```"));
+
+    let missing = SlideOptions::default();
+    let error = export_slides_to(
+      "Talk",
+      markdown,
+      &output,
+      SlideSplit::Rule,
+      Palette::Light,
+      &missing,
+    )
+    .unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+  }
+}
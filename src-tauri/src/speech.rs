@@ -0,0 +1,596 @@
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+use crate::error::{CommandError, CommandResult};
+
+// Sent while reading aloud with the part of the text being spoken
+pub const SPEECH_PROGRESS_EVENT: &str = "speech-progress";
+// Sent once reading aloud ends, whether it finished or was stopped
+pub const SPEECH_ENDED_EVENT: &str = "speech-ended";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct SpeechOptions {
+  // 1.0 is the voice's normal rate, 0.5 the slowest and 2.0 the fastest the engine allows
+  pub rate: f32,
+  // An id from list_voices; None keeps the system's voice
+  pub voice: Option<String>,
+  pub skip_code: bool,
+}
+
+impl Default for SpeechOptions {
+  fn default() -> Self {
+    SpeechOptions {
+      rate: 1.0,
+      voice: None,
+      skip_code: true,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Voice {
+  pub id: String,
+  pub name: String,
+  pub language: String,
+}
+
+// The part of the text being spoken, in UTF-16 code units of the text given to speak_text
+// so the frontend can select it directly
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SpeechProgress {
+  pub start: usize,
+  pub end: usize,
+}
+
+// A sentence to speak and the byte range of the markdown it was read from
+#[derive(Debug, Clone, PartialEq)]
+struct Utterance {
+  text: String,
+  range: Range<usize>,
+}
+
+// Text of one block (paragraph, heading, list item...) with where each piece came from
+#[derive(Default)]
+struct Block {
+  text: String,
+  // Offset in `text` and the source range of each piece
+  pieces: Vec<(usize, Range<usize>)>,
+}
+
+impl Block {
+  fn push(&mut self, text: &str, source: Range<usize>) {
+    self.pieces.push((self.text.len(), source));
+    self.text.push_str(text);
+  }
+
+  // Source position of a byte offset in `text`. Pieces can be shorter or longer than their
+  // source (escapes, entities), so the position is kept inside the piece's range.
+  fn source_at(&self, markdown: &str, offset: usize, is_end: bool) -> usize {
+    let index = self
+      .pieces
+      .partition_point(|(start, _)| {
+        if is_end {
+          *start < offset
+        } else {
+          *start <= offset
+        }
+      })
+      .max(1);
+    let (start, source) = &self.pieces[index - 1];
+    let mut position = (source.start + (offset - start)).min(source.end);
+    while !markdown.is_char_boundary(position) {
+      position -= 1;
+    }
+    position
+  }
+
+  // Split into sentences, skipping ones with nothing to pronounce
+  fn utterances(&self, markdown: &str, utterances: &mut Vec<Utterance>) {
+    let mut start = 0;
+    let mut chars = self.text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+      let next = chars.peek().map(|(_, next)| *next);
+      let ends_sentence =
+        matches!(c, '.' | '!' | '?' | '…') && next.is_none_or(char::is_whitespace);
+      if ends_sentence || next.is_none() {
+        let end = index + c.len_utf8();
+        self.push_sentence(markdown, start..end, utterances);
+        start = end;
+      }
+    }
+  }
+
+  fn push_sentence(&self, markdown: &str, range: Range<usize>, utterances: &mut Vec<Utterance>) {
+    let sentence = &self.text[range.clone()];
+    let leading = sentence.len() - sentence.trim_start().len();
+    let text = sentence.trim();
+    if !text.chars().any(char::is_alphanumeric) {
+      return;
+    }
+    let start = range.start + leading;
+    let end = start + text.len();
+    utterances.push(Utterance {
+      text: text.to_string(),
+      range: self.source_at(markdown, start, false)..self.source_at(markdown, end, true),
+    });
+  }
+}
+
+// What reading `markdown` aloud says: its text without markup, sentence by sentence. Links
+// read as their text, images as their description; HTML, URLs and footnote markers are left
+// out, and code blocks too when `skip_code` is set.
+fn utterances(markdown: &str, skip_code: bool) -> Vec<Utterance> {
+  let mut utterances = Vec::new();
+  let mut block = Block::default();
+  let mut in_code_block = false;
+  for (event, range) in Parser::new_ext(markdown, Options::all()).into_offset_iter() {
+    match event {
+      Event::Start(Tag::CodeBlock(_)) => in_code_block = true,
+      Event::End(TagEnd::CodeBlock) => {
+        in_code_block = false;
+        if !skip_code {
+          block.utterances(markdown, &mut utterances);
+        }
+        block = Block::default();
+      }
+      Event::Text(text) if in_code_block => {
+        if !skip_code {
+          block.push(&text, range);
+        }
+      }
+      Event::Text(text) | Event::Code(text) | Event::InlineMath(text) => block.push(&text, range),
+      Event::SoftBreak | Event::HardBreak => block.push(" ", range),
+      Event::End(
+        TagEnd::Paragraph
+        | TagEnd::Heading(_)
+        | TagEnd::Item
+        | TagEnd::TableCell
+        | TagEnd::BlockQuote(_)
+        | TagEnd::FootnoteDefinition,
+      )
+      | Event::Start(Tag::Item | Tag::List(_)) => {
+        block.utterances(markdown, &mut utterances);
+        block = Block::default();
+      }
+      _ => {}
+    }
+  }
+  block.utterances(markdown, &mut utterances);
+  utterances
+}
+
+// Byte offsets to UTF-16 offsets, for offsets that mostly come in increasing order
+struct Utf16Cursor<'a> {
+  text: &'a str,
+  byte: usize,
+  unit: usize,
+}
+
+impl Utf16Cursor<'_> {
+  fn at(&mut self, byte: usize) -> usize {
+    if byte < self.byte {
+      self.byte = 0;
+      self.unit = 0;
+    }
+    self.unit += self.text[self.byte..byte].encode_utf16().count();
+    self.byte = byte;
+    self.unit
+  }
+}
+
+// The sentences to speak with the progress to report for each
+fn speech_queue(markdown: &str, skip_code: bool) -> Vec<(String, SpeechProgress)> {
+  let mut cursor = Utf16Cursor {
+    text: markdown,
+    byte: 0,
+    unit: 0,
+  };
+  utterances(markdown, skip_code)
+    .into_iter()
+    .map(|utterance| {
+      let progress = SpeechProgress {
+        start: cursor.at(utterance.range.start),
+        end: cursor.at(utterance.range.end),
+      };
+      (utterance.text, progress)
+    })
+    .collect()
+}
+
+// Map a rate relative to normal (0.5 to 2.0) onto an engine's own scale, which differs
+// between platforms (e.g. -10 to 10 with SAPI)
+#[cfg_attr(
+  not(any(target_os = "macos", windows, target_os = "linux")),
+  allow(dead_code)
+)]
+fn engine_rate(rate: f32, min: f32, normal: f32, max: f32) -> f32 {
+  let rate = rate.clamp(0.5, 2.0);
+  if rate < 1.0 {
+    normal - (1.0 - rate) * 2.0 * (normal - min)
+  } else {
+    normal + (rate - 1.0) * (max - normal)
+  }
+}
+
+// The platform's speech engine (AVFoundation or AppKit on macOS, SAPI or WinRT on Windows,
+// speech-dispatcher on Linux), owned by a thread of its own that takes requests over a
+// channel. Sentences are spoken one at a time so progress can follow along.
+#[cfg(any(target_os = "macos", windows, target_os = "linux"))]
+mod engine {
+  use std::collections::VecDeque;
+  use std::sync::mpsc::{self, Sender};
+  use tauri::{AppHandle, Emitter};
+  use tts::{Tts, UtteranceId};
+
+  use super::{
+    engine_rate, SpeechOptions, SpeechProgress, Voice, SPEECH_ENDED_EVENT, SPEECH_PROGRESS_EVENT,
+  };
+  use crate::error::{CommandError, CommandResult};
+
+  enum Request {
+    Speak {
+      queue: Vec<(String, SpeechProgress)>,
+      options: SpeechOptions,
+      reply: Sender<CommandResult<()>>,
+    },
+    Stop,
+    Voices(Sender<CommandResult<Vec<Voice>>>),
+    // The engine finished an utterance
+    Finished(UtteranceId),
+  }
+
+  fn unavailable(error: impl std::fmt::Display) -> CommandError {
+    CommandError::SpeechUnavailable {
+      reason: error.to_string(),
+    }
+  }
+
+  struct Reader {
+    app: AppHandle,
+    tts: Tts,
+    queue: VecDeque<(String, SpeechProgress)>,
+    // What's being spoken, when the engine hands out ids
+    current: Option<UtteranceId>,
+    speaking: bool,
+  }
+
+  impl Reader {
+    fn configure(&mut self, options: &SpeechOptions) -> CommandResult<()> {
+      let features = self.tts.supported_features();
+      if features.rate {
+        let rate = engine_rate(
+          options.rate,
+          self.tts.min_rate(),
+          self.tts.normal_rate(),
+          self.tts.max_rate(),
+        );
+        self.tts.set_rate(rate).map_err(unavailable)?;
+      }
+      if let (Some(id), true) = (&options.voice, features.voice) {
+        let voice = self
+          .tts
+          .voices()
+          .map_err(unavailable)?
+          .into_iter()
+          .find(|voice| voice.id() == *id)
+          .ok_or_else(|| CommandError::invalid_data(format!("No voice with id {}", id)))?;
+        self.tts.set_voice(&voice).map_err(unavailable)?;
+      }
+      Ok(())
+    }
+
+    fn start(
+      &mut self,
+      queue: Vec<(String, SpeechProgress)>,
+      options: &SpeechOptions,
+    ) -> CommandResult<()> {
+      self.stop();
+      self.configure(options)?;
+      self.queue = queue.into();
+      // Without end-of-utterance callbacks there's no telling when to go on, so it's all
+      // one utterance
+      if !self.tts.supported_features().utterance_callbacks {
+        let text: Vec<_> = self.queue.iter().map(|(text, _)| text.as_str()).collect();
+        let (first, last) = (self.queue.front(), self.queue.back());
+        if let (Some((_, first)), Some((_, last))) = (first, last) {
+          let progress = SpeechProgress {
+            start: first.start,
+            end: last.end,
+          };
+          self.queue = VecDeque::from([(text.join(" "), progress)]);
+        }
+      }
+      self.speaking = true;
+      self.speak_next();
+      Ok(())
+    }
+
+    fn speak_next(&mut self) {
+      let Some((text, progress)) = self.queue.pop_front() else {
+        self.finish();
+        return;
+      };
+      let _ = self.app.emit(SPEECH_PROGRESS_EVENT, progress);
+      match self.tts.speak(text, false) {
+        Ok(id) => self.current = id,
+        Err(e) => {
+          log::error!("Failed to speak: {}", e);
+          self.finish();
+        }
+      }
+    }
+
+    fn finished(&mut self, id: UtteranceId) {
+      if self.speaking && self.current.is_none_or(|current| current == id) {
+        self.speak_next();
+      }
+    }
+
+    fn stop(&mut self) {
+      self.queue.clear();
+      if self.speaking {
+        let _ = self.tts.stop();
+      }
+      self.finish();
+    }
+
+    fn finish(&mut self) {
+      self.queue.clear();
+      self.current = None;
+      if self.speaking {
+        self.speaking = false;
+        let _ = self.app.emit(SPEECH_ENDED_EVENT, ());
+      }
+    }
+
+    fn voices(&self) -> CommandResult<Vec<Voice>> {
+      let voices = self.tts.voices().map_err(unavailable)?;
+      Ok(
+        voices
+          .into_iter()
+          .map(|voice| Voice {
+            id: voice.id(),
+            name: voice.name(),
+            language: voice.language().to_string(),
+          })
+          .collect(),
+      )
+    }
+  }
+
+  pub struct Speaker(Sender<Request>);
+
+  impl Speaker {
+    pub fn start(app: AppHandle) -> CommandResult<Self> {
+      let (sender, receiver) = mpsc::channel();
+      let (ready, started) = mpsc::channel();
+      let finished = sender.clone();
+      std::thread::Builder::new()
+        .name("speech".to_string())
+        .spawn(move || {
+          let tts = match Tts::default() {
+            Ok(tts) => tts,
+            Err(e) => {
+              let _ = ready.send(Err(unavailable(e)));
+              return;
+            }
+          };
+          if tts.supported_features().utterance_callbacks {
+            let _ = tts.on_utterance_end(Some(Box::new(move |id| {
+              let _ = finished.send(Request::Finished(id));
+            })));
+          }
+          let _ = ready.send(Ok(()));
+          let mut reader = Reader {
+            app,
+            tts,
+            queue: VecDeque::new(),
+            current: None,
+            speaking: false,
+          };
+          for request in receiver {
+            match request {
+              Request::Speak {
+                queue,
+                options,
+                reply,
+              } => {
+                let _ = reply.send(reader.start(queue, &options));
+              }
+              Request::Stop => reader.stop(),
+              Request::Voices(reply) => {
+                let _ = reply.send(reader.voices());
+              }
+              Request::Finished(id) => reader.finished(id),
+            }
+          }
+        })
+        .map_err(unavailable)?;
+      started
+        .recv()
+        .map_err(|_| unavailable("the speech engine quit"))??;
+      Ok(Speaker(sender))
+    }
+
+    fn send<T>(&self, request: impl FnOnce(Sender<T>) -> Request) -> CommandResult<T> {
+      let (reply, response) = mpsc::channel();
+      self.0.send(request(reply)).map_err(unavailable)?;
+      response
+        .recv()
+        .map_err(|_| unavailable("the speech engine quit"))
+    }
+
+    pub fn speak(
+      &self,
+      queue: Vec<(String, SpeechProgress)>,
+      options: SpeechOptions,
+    ) -> CommandResult<()> {
+      self.send(|reply| Request::Speak {
+        queue,
+        options,
+        reply,
+      })?
+    }
+
+    pub fn stop(&self) -> CommandResult<()> {
+      self.0.send(Request::Stop).map_err(unavailable)
+    }
+
+    pub fn voices(&self) -> CommandResult<Vec<Voice>> {
+      self.send(Request::Voices)?
+    }
+  }
+}
+
+#[cfg(not(any(target_os = "macos", windows, target_os = "linux")))]
+mod engine {
+  use tauri::AppHandle;
+
+  use super::{SpeechOptions, SpeechProgress, Voice};
+  use crate::error::{CommandError, CommandResult};
+
+  pub enum Speaker {}
+
+  impl Speaker {
+    pub fn start(_app: AppHandle) -> CommandResult<Self> {
+      Err(CommandError::SpeechUnavailable {
+        reason: "not supported on this platform".to_string(),
+      })
+    }
+
+    pub fn speak(&self, _: Vec<(String, SpeechProgress)>, _: SpeechOptions) -> CommandResult<()> {
+      match *self {}
+    }
+
+    pub fn stop(&self) -> CommandResult<()> {
+      match *self {}
+    }
+
+    pub fn voices(&self) -> CommandResult<Vec<Voice>> {
+      match *self {}
+    }
+  }
+}
+
+// The speech engine, started on first use
+#[derive(Default)]
+pub struct SpeechState(Mutex<Option<engine::Speaker>>);
+
+impl SpeechState {
+  fn with<T>(
+    &self,
+    app: &AppHandle,
+    f: impl FnOnce(&engine::Speaker) -> CommandResult<T>,
+  ) -> CommandResult<T> {
+    let mut speaker = self.0.lock().unwrap();
+    if speaker.is_none() {
+      *speaker = Some(engine::Speaker::start(app.clone())?);
+    }
+    f(speaker.as_ref().unwrap())
+  }
+
+  // Stop reading, if the engine was ever started (e.g. from the menu)
+  pub fn stop(&self) -> CommandResult<()> {
+    match self.0.lock().unwrap().as_ref() {
+      Some(speaker) => speaker.stop(),
+      None => Ok(()),
+    }
+  }
+}
+
+// Read markdown aloud, without its syntax. Replaces whatever is being read.
+#[tauri::command]
+pub async fn speak_text(
+  app: AppHandle,
+  state: tauri::State<'_, SpeechState>,
+  text: String,
+  options: Option<SpeechOptions>,
+) -> CommandResult<()> {
+  let options = options.unwrap_or_default();
+  let queue = speech_queue(&text, options.skip_code);
+  if queue.is_empty() {
+    return Err(CommandError::invalid_data("There is no text to read aloud"));
+  }
+  state.with(&app, |speaker| speaker.speak(queue, options))
+}
+
+#[tauri::command]
+pub async fn stop_speaking(state: tauri::State<'_, SpeechState>) -> CommandResult<()> {
+  state.stop()
+}
+
+#[tauri::command]
+pub async fn list_voices(
+  app: AppHandle,
+  state: tauri::State<'_, SpeechState>,
+) -> CommandResult<Vec<Voice>> {
+  state.with(&app, |speaker| speaker.voices())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn spoken(markdown: &str, skip_code: bool) -> Vec<String> {
+    utterances(markdown, skip_code)
+      .into_iter()
+      .map(|utterance| utterance.text)
+      .collect()
+  }
+
+  #[test]
+  fn test_reads_text_without_markup() {
+    let markdown = "# Intro\n\nSome **bold** and _soft_ text. See [the docs](https://x.example)!\n\n- one\n- two `code`\n\n<div>html</div>\n";
+    assert_eq!(
+      spoken(markdown, true),
+      vec![
+        "Intro",
+        "Some bold and soft text.",
+        "See the docs!",
+        "one",
+        "two code",
+      ]
+    );
+  }
+
+  #[test]
+  fn test_code_blocks_are_optional() {
+    let markdown = "Before.\n\n```rust\nlet x = 1;\n```\n\nAfter.\n";
+    assert_eq!(spoken(markdown, true), vec!["Before.", "After."]);
+    assert_eq!(
+      spoken(markdown, false),
+      vec!["Before.", "let x = 1;", "After."]
+    );
+  }
+
+  #[test]
+  fn test_ranges_point_at_the_source() {
+    let markdown = "One *two*. Three\nfour.\n";
+    let ranges: Vec<&str> = utterances(markdown, true)
+      .into_iter()
+      .map(|utterance| &markdown[utterance.range])
+      .collect();
+    assert_eq!(ranges, vec!["One *two*.", "Three\nfour."]);
+  }
+
+  #[test]
+  fn test_progress_uses_utf16_offsets() {
+    let markdown = "😀 Hi. Bye.";
+    let queue = speech_queue(markdown, true);
+    // The emoji is two UTF-16 code units
+    assert_eq!(queue[0].1, SpeechProgress { start: 0, end: 6 });
+    assert_eq!(queue[1].1, SpeechProgress { start: 7, end: 11 });
+  }
+
+  #[test]
+  fn test_engine_rate_keeps_normal_at_one() {
+    assert_eq!(engine_rate(1.0, -10.0, 0.0, 10.0), 0.0);
+    assert_eq!(engine_rate(0.5, -10.0, 0.0, 10.0), -10.0);
+    assert_eq!(engine_rate(2.0, 0.1, 1.0, 10.0), 10.0);
+    assert_eq!(engine_rate(5.0, 0.1, 1.0, 10.0), 10.0);
+  }
+}
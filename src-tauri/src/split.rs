@@ -0,0 +1,544 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tauri::AppHandle;
+
+use crate::access_scope::AccessScopeState;
+use crate::error::{CommandError, CommandResult};
+use crate::filename::{sanitize_file_stem, strip_inline_markdown};
+use crate::includes::{atx_heading, is_fence};
+use crate::settings::FilenameSeparator;
+use crate::staging::write_staged;
+use crate::task_registry;
+
+// What goes before the first heading when there's no index
+const PREAMBLE_STEM: &str = "preamble";
+const INDEX_STEM: &str = "index";
+// For headings with no usable text, e.g. `## ???`
+const UNTITLED_STEM: &str = "section";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SplitOptions {
+  // Only plan: return the files without writing anything
+  pub dry_run: bool,
+  // Write an index.md (with the content before the first heading) linking to every part
+  pub index: bool,
+  // Replace files that already exist in the output folder
+  pub overwrite: bool,
+}
+
+impl Default for SplitOptions {
+  fn default() -> Self {
+    SplitOptions {
+      dry_run: false,
+      index: true,
+      overwrite: false,
+    }
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SplitFile {
+  pub path: String,
+  // The heading the part starts with; None for the index or preamble
+  pub title: Option<String>,
+  pub exists: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SplitResult {
+  pub output_dir: String,
+  pub files: Vec<SplitFile>,
+  // `#anchor` links pointed at the part that now has the heading
+  pub rewritten_links: usize,
+  // False for a dry run
+  pub written: bool,
+}
+
+// One file to write
+#[derive(Debug, Clone, PartialEq)]
+struct Part {
+  file_name: String,
+  title: Option<String>,
+  content: String,
+}
+
+// GitHub's anchor for a heading: lowercase, punctuation dropped, spaces as dashes
+pub(crate) fn heading_anchor(text: &str) -> String {
+  strip_inline_markdown(text)
+    .trim()
+    .to_lowercase()
+    .chars()
+    .filter_map(|c| match c {
+      ' ' => Some('-'),
+      c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+      _ => None,
+    })
+    .collect()
+}
+
+pub(crate) fn heading_text(after_hashes: &str) -> &str {
+  after_hashes.trim().trim_end_matches('#').trim()
+}
+
+// Anchors of a document's headings, fed in order: repeated headings get -1, -2... like on
+// GitHub
+#[derive(Debug, Default)]
+pub(crate) struct HeadingAnchors(HashMap<String, usize>);
+
+impl HeadingAnchors {
+  pub(crate) fn next(&mut self, text: &str) -> String {
+    let base = heading_anchor(text);
+    let seen = self.0.entry(base.clone()).or_insert(0);
+    let anchor = if *seen == 0 {
+      base
+    } else {
+      format!("{}-{}", base, seen)
+    };
+    *seen += 1;
+    anchor
+  }
+}
+
+// Where each `#anchor` now lives: the part index and whether it's that part's first heading
+type AnchorTargets = HashMap<String, (usize, bool)>;
+
+// Point `](#anchor)` links and `[label]: #anchor` definitions at the part holding the
+// heading; links within the same part are left alone. Returns the number of links changed.
+fn rewrite_anchor_links(
+  content: &str,
+  part: usize,
+  targets: &AnchorTargets,
+  file_names: &[String],
+) -> (String, usize) {
+  let mut rewritten = String::with_capacity(content.len());
+  let mut count = 0;
+  let mut in_fence = false;
+  for line in content.split_inclusive('\n') {
+    if is_fence(line) {
+      in_fence = !in_fence;
+    }
+    if in_fence || !line.contains('#') {
+      rewritten.push_str(line);
+      continue;
+    }
+    let mut last = 0;
+    let mut starts: Vec<usize> = line
+      .match_indices("](#")
+      .map(|(index, _)| index + 2)
+      .collect();
+    if line.trim_start().starts_with('[') {
+      if let Some(index) = line.find("]: #") {
+        starts.push(index + 3);
+      }
+    }
+    starts.sort_unstable();
+    for start in starts {
+      let anchor_end = line[start + 1..]
+        .find(|c: char| c == ')' || c.is_whitespace() || c == '"')
+        .map_or(line.len(), |offset| start + 1 + offset);
+      let anchor = &line[start + 1..anchor_end];
+      let Some(&(target, is_top)) = targets.get(anchor) else {
+        continue;
+      };
+      if target == part {
+        continue;
+      }
+      rewritten.push_str(&line[last..start]);
+      rewritten.push_str(&file_names[target]);
+      if !is_top {
+        rewritten.push('#');
+        rewritten.push_str(anchor);
+      }
+      last = anchor_end;
+      count += 1;
+    }
+    rewritten.push_str(&line[last..]);
+  }
+  (rewritten, count)
+}
+
+// A stem not used yet: `setup`, then `setup-2`, `setup-3`...
+fn unique_stem(stem: String, used: &mut HashSet<String>) -> String {
+  let mut candidate = stem.clone();
+  let mut number = 2;
+  while !used.insert(candidate.to_lowercase()) {
+    candidate = format!("{}-{}", stem, number);
+    number += 1;
+  }
+  candidate
+}
+
+// Cut `content` before every ATX heading of `level` (outside code blocks and frontmatter)
+fn plan_parts(
+  content: &str,
+  level: usize,
+  document_title: &str,
+  options: &SplitOptions,
+) -> (Vec<Part>, usize) {
+  // Preamble first, then one entry per section: its title and text
+  let mut sections: Vec<(Option<String>, String)> = vec![(None, String::new())];
+  let mut anchors: AnchorTargets = HashMap::new();
+  let mut heading_anchors = HeadingAnchors::default();
+  let mut in_fence = false;
+  let mut in_frontmatter = content.starts_with("---");
+  for (index, line) in content.split_inclusive('\n').enumerate() {
+    if in_frontmatter {
+      if index > 0 && matches!(line.trim_end(), "---" | "...") {
+        in_frontmatter = false;
+      }
+    } else if is_fence(line) {
+      in_fence = !in_fence;
+    } else if let Some((heading_level, after)) = atx_heading(line).filter(|_| !in_fence) {
+      let text = heading_text(after);
+      let starts_part = heading_level == level;
+      if starts_part {
+        sections.push((Some(text.to_string()), String::new()));
+      }
+      anchors.insert(
+        heading_anchors.next(text),
+        (sections.len() - 1, starts_part),
+      );
+    }
+    if let Some((_, text)) = sections.last_mut() {
+      text.push_str(line);
+    }
+  }
+
+  let mut used: HashSet<String> = [INDEX_STEM, PREAMBLE_STEM]
+    .iter()
+    .map(|stem| stem.to_string())
+    .collect();
+  let file_names: Vec<String> = sections
+    .iter()
+    .map(|(title, _)| match title {
+      None if options.index => format!("{}.md", INDEX_STEM),
+      None => format!("{}.md", PREAMBLE_STEM),
+      Some(title) => {
+        let stem = sanitize_file_stem(&strip_inline_markdown(title), FilenameSeparator::Dash)
+          .unwrap_or_else(|| UNTITLED_STEM.to_string());
+        format!("{}.md", unique_stem(stem, &mut used))
+      }
+    })
+    .collect();
+
+  let mut rewritten_links = 0;
+  let mut parts = Vec::new();
+  for (part, (title, text)) in sections.iter().enumerate() {
+    let (text, count) = rewrite_anchor_links(text, part, &anchors, &file_names);
+    rewritten_links += count;
+    let content = if title.is_some() {
+      text
+    } else if options.index {
+      let mut index = text.trim_end().to_string();
+      if index.trim().is_empty() {
+        index = format!("# {}", document_title);
+      }
+      index.push_str("\n\n");
+      for (file_name, (title, _)) in file_names.iter().zip(&sections).skip(1) {
+        let title = title.as_deref().unwrap_or_default();
+        index.push_str(&format!(
+          "- [{}]({})\n",
+          strip_inline_markdown(title),
+          file_name
+        ));
+      }
+      index
+    } else if text.trim().is_empty() {
+      continue;
+    } else {
+      text
+    };
+    parts.push(Part {
+      file_name: file_names[part].clone(),
+      title: title.clone(),
+      content,
+    });
+  }
+  (parts, rewritten_links)
+}
+
+// Split `path` into `output_dir`. The parts are written to a temp folder first and moved
+// into place once all are written, so a split that fails or is cancelled (checked with
+// `is_cancelled` before each part) leaves the output folder as it was. `on_written` gets
+// the number of parts written so far.
+fn split_document(
+  path: &Path,
+  level: usize,
+  output_dir: &Path,
+  options: &SplitOptions,
+  is_cancelled: impl Fn() -> bool,
+  on_written: impl Fn(usize, usize),
+) -> CommandResult<SplitResult> {
+  if !(1..=6).contains(&level) {
+    return Err(CommandError::invalid_data(format!(
+      "There is no heading level {}",
+      level
+    )));
+  }
+  if !output_dir.is_absolute() {
+    return Err(CommandError::invalid_path(
+      output_dir,
+      "Output folder must be absolute",
+    ));
+  }
+  let content = crate::read_text_file(path)?;
+  let document_title = path
+    .file_stem()
+    .map(|stem| stem.to_string_lossy().to_string())
+    .unwrap_or_default();
+  let (parts, rewritten_links) = plan_parts(&content, level, &document_title, options);
+
+  let files: Vec<SplitFile> = parts
+    .iter()
+    .map(|part| {
+      let path = output_dir.join(&part.file_name);
+      SplitFile {
+        exists: path.exists(),
+        path: path.to_string_lossy().to_string(),
+        title: part.title.clone(),
+      }
+    })
+    .collect();
+  let mut result = SplitResult {
+    output_dir: output_dir.to_string_lossy().to_string(),
+    files,
+    rewritten_links,
+    written: false,
+  };
+  if options.dry_run {
+    return Ok(result);
+  }
+  if let Some(existing) = result.files.iter().find(|f| f.exists && !options.overwrite) {
+    return Err(CommandError::AlreadyExists {
+      path: existing.path.clone(),
+    });
+  }
+
+  write_staged(output_dir, output_dir, |staging| {
+    for (done, part) in parts.iter().enumerate() {
+      if is_cancelled() {
+        return Err(CommandError::Cancelled);
+      }
+      let path = staging.join(&part.file_name);
+      std::fs::write(&path, &part.content)
+        .map_err(|e| CommandError::from_io(&e, &path, "Failed to write file"))?;
+      on_written(done + 1, parts.len());
+    }
+    Ok(())
+  })?;
+  result.written = true;
+  Ok(result)
+}
+
+// Cut a document into one file per heading of `level`, written to `output_dir` (a folder
+// named after the document, next to it, by default). Run with `dry_run` first to see the
+// files that would be written. Runs as a registered task (see task_registry); cancelling
+// it leaves the output folder untouched.
+#[tauri::command]
+pub async fn split_by_heading(
+  app: AppHandle,
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+  level: usize,
+  output_dir: Option<String>,
+  options: Option<SplitOptions>,
+  task_id: Option<String>,
+) -> CommandResult<SplitResult> {
+  let path = PathBuf::from(path);
+  let output_dir = match output_dir {
+    Some(dir) => PathBuf::from(dir),
+    None => {
+      let stem = path.file_stem().unwrap_or_default();
+      path
+        .parent()
+        .ok_or_else(|| CommandError::invalid_path(&path, "Document has no folder"))?
+        .join(stem)
+    }
+  };
+  scope.check(&path)?;
+  scope.check(&output_dir)?;
+  let guard = task_registry::start_task(&app, task_id, "split_by_heading", "Splitting document")?;
+  let task = guard.task();
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    split_document(
+      &path,
+      level,
+      &output_dir,
+      &options.unwrap_or_default(),
+      || task.is_cancelled(),
+      |done, total| task.progress(done, total),
+    )
+  })
+  .await
+  .map_err(|e| CommandError::io("Split failed", e))
+  .and_then(|result| result);
+  guard.finish(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  const NOTES: &str = "# Notes\n\nSee [setup](#setup) and [tools](#build-tools).\n\n\
+    ## Setup\n\nInstall.\n\n```\n## not a heading\n```\n\n\
+    ## Usage\n\nBack to [setup](#setup), down to [tools](#build-tools).\n\n\
+    ### Build tools\n\nMore [usage](#usage).\n\n## Setup\n\nAgain.\n";
+
+  fn split(
+    doc: &Path,
+    level: usize,
+    out: &Path,
+    options: &SplitOptions,
+  ) -> CommandResult<SplitResult> {
+    split_document(doc, level, out, options, || false, |_, _| {})
+  }
+
+  fn plan(content: &str, options: &SplitOptions) -> Vec<Part> {
+    plan_parts(content, 2, "notes", options).0
+  }
+
+  #[test]
+  fn test_cuts_at_headings_of_the_level() {
+    let parts = plan(NOTES, &SplitOptions::default());
+    let names: Vec<&str> = parts.iter().map(|p| p.file_name.as_str()).collect();
+    assert_eq!(
+      names,
+      vec!["index.md", "setup.md", "usage.md", "setup-2.md"]
+    );
+    assert!(parts[1].content.contains("## not a heading"));
+    assert!(parts[2].content.contains("### Build tools"));
+    assert_eq!(parts[3].content, "## Setup\n\nAgain.\n");
+  }
+
+  #[test]
+  fn test_index_links_every_part_after_the_preamble() {
+    let parts = plan(NOTES, &SplitOptions::default());
+    assert_eq!(
+      parts[0].content,
+      "# Notes\n\nSee [setup](setup.md) and [tools](usage.md#build-tools).\n\n\
+       - [Setup](setup.md)\n- [Usage](usage.md)\n- [Setup](setup-2.md)\n"
+    );
+    assert_eq!(parts[0].title, None);
+  }
+
+  #[test]
+  fn test_anchor_links_follow_their_heading() {
+    let (parts, rewritten) = plan_parts(NOTES, 2, "notes", &SplitOptions::default());
+    assert!(parts[2]
+      .content
+      .contains("Back to [setup](setup.md), down to [tools](#build-tools)."));
+    assert!(parts[2].content.contains("More [usage](#usage)."));
+    assert_eq!(rewritten, 3);
+
+    let (parts, _) = plan_parts(
+      "[a]: #second\n\n## First\n\n## Second\n",
+      2,
+      "x",
+      &SplitOptions::default(),
+    );
+    assert!(parts[0].content.starts_with("[a]: second.md\n"));
+  }
+
+  #[test]
+  fn test_preamble_without_index() {
+    let options = SplitOptions {
+      index: false,
+      ..SplitOptions::default()
+    };
+    let parts = plan("Intro\n\n## One\n", &options);
+    let names: Vec<&str> = parts.iter().map(|p| p.file_name.as_str()).collect();
+    assert_eq!(names, vec!["preamble.md", "one.md"]);
+    // Nothing before the first heading, no preamble
+    assert_eq!(plan("## One\n", &options).len(), 1);
+    assert_eq!(
+      plan("## One\n", &SplitOptions::default())[0].content,
+      "# notes\n\n- [One](one.md)\n"
+    );
+  }
+
+  #[test]
+  fn test_dry_run_first_and_never_overwrite_unasked() {
+    let dir = TempDir::new().unwrap();
+    let doc = dir.path().join("notes.md");
+    fs::write(&doc, NOTES).unwrap();
+    let out = dir.path().join("notes");
+
+    let dry_run = SplitOptions {
+      dry_run: true,
+      ..SplitOptions::default()
+    };
+    let planned = split(&doc, 2, &out, &dry_run).unwrap();
+    assert!(!planned.written);
+    assert_eq!(planned.files.len(), 4);
+    assert!(!out.exists());
+
+    let written = split(&doc, 2, &out, &SplitOptions::default()).unwrap();
+    assert!(written.written);
+    assert_eq!(
+      fs::read_to_string(out.join("setup-2.md")).unwrap(),
+      "## Setup\n\nAgain.\n"
+    );
+
+    let planned = split(&doc, 2, &out, &dry_run).unwrap();
+    assert!(planned.files.iter().all(|f| f.exists));
+    let error = split(&doc, 2, &out, &SplitOptions::default()).unwrap_err();
+    assert_eq!(error.code(), "already_exists");
+    let overwrite = SplitOptions {
+      overwrite: true,
+      ..SplitOptions::default()
+    };
+    assert!(split(&doc, 2, &out, &overwrite).unwrap().written);
+
+    let error = split(&doc, 7, &out, &dry_run).unwrap_err();
+    assert_eq!(error.code(), "invalid_data");
+  }
+
+  #[test]
+  fn test_cancelled_split_leaves_the_folder_as_it_was() {
+    let dir = TempDir::new().unwrap();
+    let doc = dir.path().join("notes.md");
+    fs::write(&doc, NOTES).unwrap();
+    let out = dir.path().join("notes");
+    fs::create_dir(&out).unwrap();
+    fs::write(out.join("setup.md"), "kept").unwrap();
+    let overwrite = SplitOptions {
+      overwrite: true,
+      ..SplitOptions::default()
+    };
+
+    // Cancelled halfway: after two of the four parts were written
+    let written = std::cell::Cell::new(0);
+    let error = split_document(
+      &doc,
+      2,
+      &out,
+      &overwrite,
+      || written.get() == 2,
+      |done, _| written.set(done),
+    )
+    .unwrap_err();
+    assert!(error.is_cancelled());
+    let CommandError::ExportAborted { cleaned_up, .. } = error else {
+      panic!("expected ExportAborted, got {:?}", error);
+    };
+    let staging = dir.path().join(".notes.partial");
+    assert_eq!(
+      cleaned_up,
+      vec![
+        staging.join("index.md").to_string_lossy().to_string(),
+        staging.join("setup.md").to_string_lossy().to_string(),
+      ]
+    );
+    let names: Vec<String> = fs::read_dir(&out)
+      .unwrap()
+      .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+      .collect();
+    assert_eq!(names, vec!["setup.md"]);
+    assert_eq!(fs::read_to_string(out.join("setup.md")).unwrap(), "kept");
+    assert!(!staging.exists());
+  }
+}
@@ -0,0 +1,175 @@
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::{CommandError, CommandResult};
+
+// `/out/report.html` -> `/out/.report.html.partial`, hidden so it doesn't flash up in file
+// browsers
+fn staging_dir(destination: &Path) -> CommandResult<PathBuf> {
+  let name = destination
+    .file_name()
+    .ok_or_else(|| CommandError::invalid_path(destination, "Export path has no file name"))?;
+  let mut staging_name = OsString::from(".");
+  staging_name.push(name);
+  staging_name.push(".partial");
+  Ok(destination.with_file_name(staging_name))
+}
+
+// Files under `dir`, sorted
+fn list_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+  let mut files = Vec::new();
+  let mut pending = vec![dir.to_path_buf()];
+  while let Some(dir) = pending.pop() {
+    for entry in fs::read_dir(&dir)? {
+      let entry = entry?;
+      if entry.file_type()?.is_dir() {
+        pending.push(entry.path());
+      } else {
+        files.push(entry.path());
+      }
+    }
+  }
+  files.sort();
+  Ok(files)
+}
+
+// Move each file under `staging` to the same place under `root`. The file for `destination`
+// goes last, so it never refers to assets that aren't there yet.
+fn move_into_place(staging: &Path, root: &Path, destination: &Path) -> CommandResult<()> {
+  let mut files =
+    list_files(staging).map_err(|e| CommandError::from_io(&e, staging, "Failed to list export"))?;
+  let main = destination
+    .strip_prefix(root)
+    .ok()
+    .map(|relative| staging.join(relative));
+  files.sort_by_key(|file| Some(file) == main.as_ref());
+  for file in files {
+    let Ok(relative) = file.strip_prefix(staging) else {
+      continue;
+    };
+    let target = root.join(relative);
+    if let Some(parent) = target.parent() {
+      fs::create_dir_all(parent)
+        .map_err(|e| CommandError::from_io(&e, parent, "Failed to create folder"))?;
+    }
+    fs::rename(&file, &target)
+      .map_err(|e| CommandError::from_io(&e, &target, "Failed to write file"))?;
+  }
+  Ok(())
+}
+
+// Remove `staging` and return the files that were still in it
+fn remove_staging(staging: &Path) -> Vec<String> {
+  let files = list_files(staging).unwrap_or_default();
+  if let Err(e) = fs::remove_dir_all(staging) {
+    if e.kind() != io::ErrorKind::NotFound {
+      log::warn!("Failed to remove {}: {}", staging.display(), e);
+    }
+  }
+  files
+    .into_iter()
+    .map(|file| file.to_string_lossy().to_string())
+    .collect()
+}
+
+// Run an export that writes one or more files. `write` gets a temp folder next to
+// `destination` (the exported file, or the folder exported into) to lay its files out in
+// as they should end up under `root`. They're moved into place only once `write` succeeds.
+// If it fails or is cancelled, the temp folder is removed and the files written so far are
+// listed in an ExportAborted error.
+pub fn write_staged<T>(
+  root: &Path,
+  destination: &Path,
+  write: impl FnOnce(&Path) -> CommandResult<T>,
+) -> CommandResult<T> {
+  let staging = staging_dir(destination)?;
+  // Left over from an export that was killed before it could clean up
+  remove_staging(&staging);
+  fs::create_dir_all(&staging)
+    .map_err(|e| CommandError::from_io(&e, &staging, "Failed to create temp folder"))?;
+
+  let result = write(&staging).and_then(|value| {
+    move_into_place(&staging, root, destination)?;
+    Ok(value)
+  });
+  let cleaned_up = remove_staging(&staging);
+  match result {
+    Ok(value) => Ok(value),
+    Err(cause) if cleaned_up.is_empty() => Err(cause),
+    Err(cause) => {
+      log::warn!(
+        "Export to {} stopped ({}); removed {} partial files",
+        destination.display(),
+        cause,
+        cleaned_up.len()
+      );
+      Err(CommandError::ExportAborted {
+        cause: Box::new(cause),
+        cleaned_up,
+      })
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_files_move_into_place_on_success() {
+    let dir = TempDir::new().unwrap();
+    let destination = dir.path().join("report.html");
+    let staged = write_staged(dir.path(), &destination, |staging| {
+      fs::create_dir(staging.join("assets")).unwrap();
+      fs::write(staging.join("assets").join("a.png"), "png").unwrap();
+      fs::write(staging.join("report.html"), "html").unwrap();
+      Ok(staging.to_path_buf())
+    })
+    .unwrap();
+    assert_eq!(staged, dir.path().join(".report.html.partial"));
+    assert!(!staged.exists());
+    assert_eq!(fs::read_to_string(&destination).unwrap(), "html");
+    assert_eq!(
+      fs::read_to_string(dir.path().join("assets").join("a.png")).unwrap(),
+      "png"
+    );
+  }
+
+  #[test]
+  fn test_failed_export_leaves_nothing_behind() {
+    let dir = TempDir::new().unwrap();
+    let destination = dir.path().join("report.html");
+    fs::write(&destination, "old").unwrap();
+    let error = write_staged(dir.path(), &destination, |staging| {
+      fs::write(staging.join("report.html"), "half").unwrap();
+      Err::<(), _>(CommandError::Cancelled)
+    })
+    .unwrap_err();
+    assert!(error.is_cancelled());
+    assert_eq!(error.code(), "cancelled");
+    let CommandError::ExportAborted { cleaned_up, .. } = error else {
+      panic!("expected ExportAborted, got {:?}", error);
+    };
+    assert_eq!(
+      cleaned_up,
+      vec![dir
+        .path()
+        .join(".report.html.partial")
+        .join("report.html")
+        .to_string_lossy()
+        .to_string()]
+    );
+    assert_eq!(fs::read_to_string(&destination).unwrap(), "old");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    // Nothing written, nothing to report
+    let error = write_staged(dir.path(), &destination, |_| {
+      Err::<(), _>(CommandError::Cancelled)
+    })
+    .unwrap_err();
+    assert_eq!(error, CommandError::Cancelled);
+  }
+}
@@ -0,0 +1,174 @@
+use serde::Serialize;
+use std::io::{self, Read};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::binary;
+use crate::error::{CommandError, CommandResult};
+
+// Title the frontend shows for a document read from stdin
+const PIPED_INPUT_TITLE: &str = "Piped input";
+
+// How long startup waits for the pipe to close. Whatever arrived by then is opened (marked
+// partial) so a producer that never exits can't keep the window from appearing.
+pub const STDIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Stands in for a path in errors about piped input
+const STDIN_NAME: &str = "<stdin>";
+
+// A document that didn't come from a file, opened as unsaved
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PendingContent {
+  pub content: String,
+  pub title: String,
+  // The input was cut short: it was still arriving at the timeout, or exceeded the size cap
+  pub partial: bool,
+}
+
+// Tells running windows to fetch new pending content with get_pending_content (e.g. text
+// sent through the macOS Services menu)
+pub const PENDING_CONTENT_EVENT: &str = "pending-content";
+
+// Piped input read during startup, kept until the frontend asks for it
+pub struct PendingContentState(pub Mutex<Option<CommandResult<PendingContent>>>);
+
+// Read `reader` to EOF, giving up after `timeout` or MAX_FILE_SIZE bytes, and decode it the
+// way read_file decodes files
+pub fn read_piped_input<R: Read + Send + 'static>(
+  mut reader: R,
+  timeout: Duration,
+) -> CommandResult<PendingContent> {
+  let (tx, rx) = mpsc::channel::<io::Result<Vec<u8>>>();
+  // Left running if the timeout hits; it ends with the process
+  std::thread::spawn(move || {
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+      match reader.read(&mut buffer) {
+        // Dropping the sender tells the receiver we reached EOF
+        Ok(0) => break,
+        Ok(n) => {
+          if tx.send(Ok(buffer[..n].to_vec())).is_err() {
+            break;
+          }
+        }
+        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+        Err(e) => {
+          let _ = tx.send(Err(e));
+          break;
+        }
+      }
+    }
+  });
+
+  let limit = crate::MAX_FILE_SIZE as usize;
+  let deadline = Instant::now() + timeout;
+  let mut bytes = Vec::new();
+  let mut partial = false;
+  loop {
+    match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+      Ok(Ok(chunk)) => {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > limit {
+          bytes.truncate(limit);
+          partial = true;
+          break;
+        }
+      }
+      Ok(Err(e)) => return Err(CommandError::io("Failed to read piped input", e)),
+      Err(RecvTimeoutError::Timeout) => {
+        partial = true;
+        break;
+      }
+      Err(RecvTimeoutError::Disconnected) => break,
+    }
+  }
+
+  if let Some(mime) = binary::sniff_binary(&bytes) {
+    return Err(CommandError::BinaryFile {
+      path: STDIN_NAME.to_string(),
+      mime: mime.to_string(),
+    });
+  }
+  let content = match String::from_utf8(bytes) {
+    Ok(content) => content,
+    // A cut-off read can end in the middle of a character; drop the incomplete tail
+    Err(e) if partial && e.utf8_error().error_len().is_none() => {
+      let valid = e.utf8_error().valid_up_to();
+      let mut bytes = e.into_bytes();
+      bytes.truncate(valid);
+      String::from_utf8(bytes).unwrap_or_default()
+    }
+    Err(e) => {
+      return Err(CommandError::invalid_data(format!(
+        "Failed to read piped input: not valid UTF-8 ({})",
+        e.utf8_error()
+      )))
+    }
+  };
+  Ok(PendingContent {
+    content,
+    title: PIPED_INPUT_TITLE.to_string(),
+    partial,
+  })
+}
+
+// Content to open as a new unsaved document (e.g. from `markdowner -`), returned once
+#[tauri::command]
+pub async fn get_pending_content(
+  state: tauri::State<'_, PendingContentState>,
+) -> CommandResult<Option<PendingContent>> {
+  state.0.lock().unwrap().take().transpose()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Yields its data, then blocks until the test drops the sender, like a pipe whose
+  // writer never exits
+  struct StalledPipe {
+    data: Option<Vec<u8>>,
+    block: mpsc::Receiver<()>,
+  }
+
+  impl Read for StalledPipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      if let Some(data) = self.data.take() {
+        buf[..data.len()].copy_from_slice(&data);
+        return Ok(data.len());
+      }
+      let _ = self.block.recv();
+      Ok(0)
+    }
+  }
+
+  #[test]
+  fn test_reads_piped_input_to_eof() {
+    let input = io::Cursor::new("# From curl\n".as_bytes().to_vec());
+    let pending = read_piped_input(input, STDIN_TIMEOUT).unwrap();
+    assert_eq!(pending.content, "# From curl\n");
+    assert_eq!(pending.title, "Piped input");
+    assert!(!pending.partial);
+  }
+
+  #[test]
+  fn test_stalled_pipe_returns_partial_content() {
+    let (_keep_open, block) = mpsc::channel();
+    // Ends in the middle of "é"
+    let pipe = StalledPipe {
+      data: Some(b"caf\xc3".to_vec()),
+      block,
+    };
+    let pending = read_piped_input(pipe, Duration::from_millis(100)).unwrap();
+    assert!(pending.partial);
+    assert_eq!(pending.content, "caf");
+  }
+
+  #[test]
+  fn test_binary_piped_input_is_refused() {
+    let input = io::Cursor::new(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec());
+    let error = read_piped_input(input, STDIN_TIMEOUT).unwrap_err();
+    assert_eq!(error.code(), "binary_file");
+  }
+}
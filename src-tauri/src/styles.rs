@@ -0,0 +1,326 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+use crate::assets::resolve_reference;
+use crate::error::CommandResult;
+use crate::settings::SettingsState;
+use crate::wiki;
+
+// Frontmatter key naming a document's stylesheet, relative to the document
+const STYLESHEET_KEY: &str = "stylesheet";
+
+// Stylesheets bigger than this are skipped; nobody hand-writes this much CSS for a note
+const MAX_STYLESHEET_BYTES: u64 = 512 * 1024;
+
+// User CSS to add after the built-in styles, with what couldn't be used
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct EffectiveStyles {
+  // The global stylesheet, then the document's, so the document's rules win
+  pub css: String,
+  // Stylesheets that went into `css`
+  pub sources: Vec<String>,
+  // Why a configured stylesheet was left out (missing, too big, outside its folder...)
+  pub warnings: Vec<String>,
+}
+
+struct CachedStylesheet {
+  modified: SystemTime,
+  css: String,
+}
+
+// Stylesheets by path, reread only when their mtime changes
+#[derive(Default)]
+pub struct StyleCacheState(Mutex<HashMap<PathBuf, CachedStylesheet>>);
+
+// The `stylesheet:` value in a document's frontmatter
+pub(crate) fn frontmatter_stylesheet(content: &str) -> Option<String> {
+  let mut lines = content.lines();
+  if lines.next().map(str::trim_end) != Some("---") {
+    return None;
+  }
+  for line in lines {
+    let line = line.trim();
+    if line == "---" || line == "..." {
+      break;
+    }
+    let Some((key, value)) = line.split_once(':') else {
+      continue;
+    };
+    if key.trim() == STYLESHEET_KEY {
+      let value = value.trim().trim_matches(['"', '\'']);
+      return (!value.is_empty()).then(|| value.to_string());
+    }
+  }
+  None
+}
+
+// Read the stylesheet at `path` if it's a `.css` file under `root` (after following
+// symlinks) and not too big. The error is a warning for the user.
+fn read_stylesheet(
+  cache: &StyleCacheState,
+  path: &Path,
+  root: &Path,
+) -> Result<(PathBuf, String), String> {
+  let display = path.display();
+  let is_css = path
+    .extension()
+    .is_some_and(|e| e.to_string_lossy().eq_ignore_ascii_case("css"));
+  if !is_css {
+    return Err(format!("Stylesheet {} is not a .css file", display));
+  }
+  let path = path
+    .canonicalize()
+    .map_err(|e| format!("Stylesheet {} can't be read: {}", display, e))?;
+  let inside = root.canonicalize().is_ok_and(|root| path.starts_with(root));
+  if !inside {
+    return Err(format!(
+      "Stylesheet {} is outside {}",
+      display,
+      root.display()
+    ));
+  }
+  let metadata =
+    std::fs::metadata(&path).map_err(|e| format!("Stylesheet {} can't be read: {}", display, e))?;
+  if metadata.len() > MAX_STYLESHEET_BYTES {
+    return Err(format!(
+      "Stylesheet {} is larger than {} KB",
+      display,
+      MAX_STYLESHEET_BYTES / 1024
+    ));
+  }
+  let modified = metadata.modified().ok();
+
+  let mut cached = cache.0.lock().unwrap();
+  if let (Some(entry), Some(modified)) = (cached.get(&path), modified) {
+    if entry.modified == modified {
+      return Ok((path, entry.css.clone()));
+    }
+  }
+  let css = std::fs::read_to_string(&path)
+    .map_err(|e| format!("Stylesheet {} can't be read: {}", display, e))?;
+  if let Some(modified) = modified {
+    cached.insert(
+      path.clone(),
+      CachedStylesheet {
+        modified,
+        css: css.clone(),
+      },
+    );
+  }
+  Ok((path, css))
+}
+
+// Combine the global stylesheet (which must live in `config_dir`) and the document's
+// stylesheet `reference` (which must live in the document's workspace)
+fn collect_styles(
+  cache: &StyleCacheState,
+  global: Option<(&Path, &Path)>,
+  document: Option<(&Path, &str)>,
+) -> EffectiveStyles {
+  let mut styles = EffectiveStyles::default();
+  let mut wanted: Vec<(PathBuf, PathBuf)> = Vec::new();
+  if let Some((path, config_dir)) = global {
+    wanted.push((path.to_path_buf(), config_dir.to_path_buf()));
+  }
+  if let Some((document, reference)) = document {
+    let root = document
+      .parent()
+      .map(wiki::workspace_root)
+      .unwrap_or_default();
+    match resolve_reference(document, reference) {
+      Some(path) => wanted.push((path, root)),
+      None => styles
+        .warnings
+        .push(format!("Stylesheet {} must be a local file", reference)),
+    }
+  }
+
+  for (path, root) in wanted {
+    match read_stylesheet(cache, &path, &root) {
+      Ok((path, css)) => {
+        if !styles.css.is_empty() {
+          styles.css.push('\n');
+        }
+        styles.css.push_str(&css);
+        styles.sources.push(path.to_string_lossy().to_string());
+      }
+      Err(warning) => styles.warnings.push(warning),
+    }
+  }
+  styles
+}
+
+// The custom CSS for `document` (None for an untitled document). `content` is the
+// document's markdown when the caller has it, so unsaved frontmatter edits count;
+// otherwise it's read from disk.
+pub fn effective_styles(
+  app: &AppHandle,
+  document: Option<&Path>,
+  content: Option<&str>,
+) -> EffectiveStyles {
+  let read_content = match (document, content) {
+    (Some(document), None) => crate::read_text_file(document).ok(),
+    _ => None,
+  };
+  let reference = content
+    .or(read_content.as_deref())
+    .and_then(frontmatter_stylesheet);
+  styles_with(app, document, reference.as_deref())
+}
+
+// Like effective_styles, with the document's stylesheet given instead of read from its
+// frontmatter (an export's `css` option)
+pub fn styles_with(
+  app: &AppHandle,
+  document: Option<&Path>,
+  stylesheet: Option<&str>,
+) -> EffectiveStyles {
+  let global = app
+    .state::<SettingsState>()
+    .0
+    .lock()
+    .unwrap()
+    .custom_css_path
+    .clone()
+    .filter(|path| !path.trim().is_empty())
+    .map(PathBuf::from);
+  let config_dir = app.path().app_config_dir().ok();
+  let mut styles = EffectiveStyles::default();
+  let global = match (&global, &config_dir) {
+    (Some(path), Some(config_dir)) => Some((path.as_path(), config_dir.as_path())),
+    (Some(path), None) => {
+      styles.warnings.push(format!(
+        "Stylesheet {} can't be checked: no config folder",
+        path.display()
+      ));
+      None
+    }
+    _ => None,
+  };
+  let document = document.zip(stylesheet);
+
+  let collected = collect_styles(&app.state::<StyleCacheState>(), global, document);
+  styles.css = collected.css;
+  styles.sources = collected.sources;
+  styles.warnings.extend(collected.warnings);
+  styles
+}
+
+// The user CSS the preview, print and exports add after their own styles: the global
+// `custom_css_path` setting, then the document's `stylesheet:` frontmatter key
+#[tauri::command]
+pub async fn get_effective_styles(
+  app: AppHandle,
+  document_path: Option<String>,
+) -> CommandResult<EffectiveStyles> {
+  let document = document_path.map(PathBuf::from);
+  Ok(effective_styles(&app, document.as_deref(), None))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_frontmatter_stylesheet() {
+    assert_eq!(
+      frontmatter_stylesheet("---\ntitle: A\nstylesheet: \"styles/print.css\"\n---\n# A"),
+      Some("styles/print.css".to_string())
+    );
+    assert_eq!(frontmatter_stylesheet("# A\nstylesheet: a.css\n"), None);
+    assert_eq!(frontmatter_stylesheet("---\nstylesheet:\n---\n"), None);
+  }
+
+  #[test]
+  fn test_collects_global_then_document_styles() {
+    let config = TempDir::new().unwrap();
+    let workspace = TempDir::new().unwrap();
+    fs::create_dir(workspace.path().join(".git")).unwrap();
+    fs::create_dir(workspace.path().join("notes")).unwrap();
+    let global = config.path().join("custom.css");
+    fs::write(&global, "body { color: navy; }").unwrap();
+    fs::write(workspace.path().join("doc.css"), "h1 { color: red; }").unwrap();
+    let document = workspace.path().join("notes").join("a.md");
+
+    let cache = StyleCacheState::default();
+    let styles = collect_styles(
+      &cache,
+      Some((&global, config.path())),
+      Some((&document, "../doc.css")),
+    );
+    assert_eq!(styles.css, "body { color: navy; }\nh1 { color: red; }");
+    assert_eq!(styles.sources.len(), 2);
+    assert!(styles.warnings.is_empty());
+  }
+
+  #[test]
+  fn test_bad_stylesheets_become_warnings() {
+    let workspace = TempDir::new().unwrap();
+    let outside = TempDir::new().unwrap();
+    fs::create_dir(workspace.path().join(".git")).unwrap();
+    fs::write(outside.path().join("x.css"), "body {}").unwrap();
+    fs::write(workspace.path().join("notes.txt"), "body {}").unwrap();
+    fs::write(
+      workspace.path().join("huge.css"),
+      vec![b' '; MAX_STYLESHEET_BYTES as usize + 1],
+    )
+    .unwrap();
+    let document = workspace.path().join("a.md");
+    let cache = StyleCacheState::default();
+    let warnings = |reference: &str| {
+      let styles = collect_styles(&cache, None, Some((&document, reference)));
+      assert!(styles.css.is_empty());
+      styles.warnings
+    };
+
+    let outside_reference = outside.path().join("x.css").to_string_lossy().to_string();
+    assert!(warnings(&outside_reference)[0].contains("is outside"));
+    assert!(warnings("notes.txt")[0].contains("not a .css file"));
+    assert!(warnings("huge.css")[0].contains("larger than"));
+    assert!(warnings("missing.css")[0].contains("can't be read"));
+    assert!(warnings("https://example.com/a.css")[0].contains("must be a local file"));
+  }
+
+  #[test]
+  fn test_cached_until_modified() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("a.css");
+    fs::write(&path, "a {}").unwrap();
+    let cache = StyleCacheState::default();
+    assert_eq!(
+      read_stylesheet(&cache, &path, dir.path()).unwrap().1,
+      "a {}"
+    );
+
+    // Same mtime: the cached CSS is used
+    let modified = fs::metadata(&path).unwrap().modified().unwrap();
+    fs::write(&path, "b {}").unwrap();
+    fs::File::options()
+      .write(true)
+      .open(&path)
+      .unwrap()
+      .set_modified(modified)
+      .unwrap();
+    assert_eq!(
+      read_stylesheet(&cache, &path, dir.path()).unwrap().1,
+      "a {}"
+    );
+
+    fs::File::options()
+      .write(true)
+      .open(&path)
+      .unwrap()
+      .set_modified(modified + std::time::Duration::from_secs(5))
+      .unwrap();
+    assert_eq!(
+      read_stylesheet(&cache, &path, dir.path()).unwrap().1,
+      "b {}"
+    );
+  }
+}
@@ -0,0 +1,374 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::access_scope::AccessScopeState;
+use crate::error::{CommandError, CommandResult};
+use crate::typography::{frontmatter_end, protected_ranges};
+use crate::wiki::{is_note, walk_notes};
+use crate::workspace::{self, WorkspaceChanges};
+
+// Frontmatter keys holding a note's tags
+const FRONTMATTER_KEYS: &[&str] = &["tags", "tag"];
+
+// A tag and how many notes carry it or one of its subtags
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TagCount {
+  pub tag: String,
+  pub count: usize,
+}
+
+// Lowercase, without the `#` and empty `/` segments, or None if it isn't a usable tag:
+// empty, or only digits like `#123`
+fn normalize_tag(tag: &str) -> Option<String> {
+  let tag = tag.trim().trim_start_matches('#');
+  let segments: Vec<&str> = tag.split('/').filter(|s| !s.is_empty()).collect();
+  let tag = segments.join("/").to_lowercase();
+  tag
+    .chars()
+    .any(|c| !c.is_ascii_digit() && c != '/')
+    .then_some(tag)
+}
+
+fn is_tag_char(c: char) -> bool {
+  c.is_alphanumeric() || matches!(c, '_' | '-' | '/')
+}
+
+// What may come right before the `#` of an inline tag, so `(#tag)` and `"#tag"` count but
+// `issue#12`, `&#123;` and heading markers don't
+fn can_precede_tag(c: char) -> bool {
+  c.is_whitespace()
+    || matches!(
+      c,
+      '(' | '[' | '{' | '"' | '\'' | '*' | '_' | '~' | '>' | ','
+    )
+}
+
+// Tags listed under `tags:` in the frontmatter, inline (`tags: [a, b]`, `tags: a, b`) or
+// as a block list
+fn frontmatter_tags(frontmatter: &str, tags: &mut Vec<String>) {
+  let mut in_list = false;
+  for line in frontmatter.lines().skip(1) {
+    let trimmed = line.trim();
+    if in_list {
+      if let Some(item) = trimmed.strip_prefix("- ") {
+        tags.extend(normalize_tag(item.trim_matches(['"', '\''])));
+        continue;
+      }
+      in_list = false;
+    }
+    let Some((key, value)) = trimmed.split_once(':') else {
+      continue;
+    };
+    if line.starts_with(char::is_whitespace) || !FRONTMATTER_KEYS.contains(&key.trim()) {
+      continue;
+    }
+    let value = value.trim().trim_start_matches('[').trim_end_matches(']');
+    in_list = value.is_empty();
+    for item in value.split([',', ' ']) {
+      tags.extend(normalize_tag(item.trim_matches(['"', '\''])));
+    }
+  }
+}
+
+// `#tags` in the text, outside code, math, URLs, link destinations and HTML
+fn inline_tags(content: &str, tags: &mut Vec<String>) {
+  let protected = protected_ranges(content);
+  for (index, _) in content.match_indices('#') {
+    if protected.iter().any(|range| range.contains(&index)) {
+      continue;
+    }
+    if content[..index]
+      .chars()
+      .next_back()
+      .is_some_and(|c| !can_precede_tag(c))
+    {
+      continue;
+    }
+    let rest = &content[index + 1..];
+    let end = rest.find(|c: char| !is_tag_char(c)).unwrap_or(rest.len());
+    let tag = rest[..end].trim_end_matches(['/', '-']);
+    tags.extend(normalize_tag(tag));
+  }
+}
+
+// The distinct tags of a note, from its frontmatter and its text
+pub fn note_tags(content: &str) -> Vec<String> {
+  let mut tags = Vec::new();
+  if let Some(end) = frontmatter_end(content) {
+    frontmatter_tags(&content[..end], &mut tags);
+  }
+  inline_tags(content, &mut tags);
+  tags.sort();
+  tags.dedup();
+  tags
+}
+
+// Notes per tag, counting each tag's parents too (`project/alpha` also counts for
+// `project`), sorted. `files` holds the tags of each note.
+pub fn count_tags<'a>(files: impl Iterator<Item = &'a Vec<String>>) -> Vec<TagCount> {
+  let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+  for tags in files {
+    let mut seen: Vec<&str> = tags
+      .iter()
+      .flat_map(|tag| {
+        tag
+          .match_indices('/')
+          .map(|(i, _)| &tag[..i])
+          .chain([tag.as_str()])
+      })
+      .collect();
+    seen.sort();
+    seen.dedup();
+    for tag in seen {
+      *counts.entry(tag).or_default() += 1;
+    }
+  }
+  counts
+    .into_iter()
+    .map(|(tag, count)| TagCount {
+      tag: tag.to_string(),
+      count,
+    })
+    .collect()
+}
+
+// Tags of the notes of one workspace
+struct TagIndex {
+  root: PathBuf,
+  files: HashMap<PathBuf, Vec<String>>,
+}
+
+impl TagIndex {
+  fn build(root: &Path) -> TagIndex {
+    let mut index = TagIndex {
+      root: root.to_path_buf(),
+      files: HashMap::new(),
+    };
+    for note in walk_notes(root) {
+      index.read_note(note.path);
+    }
+    index
+  }
+
+  fn read_note(&mut self, path: PathBuf) {
+    match std::fs::read_to_string(&path) {
+      Ok(content) => {
+        let tags = note_tags(&content);
+        if tags.is_empty() {
+          self.files.remove(&path);
+        } else {
+          self.files.insert(path, tags);
+        }
+      }
+      Err(_) => {
+        self.files.remove(&path);
+      }
+    }
+  }
+
+  // Reread a changed note, or the notes of a created folder
+  fn read_path(&mut self, path: &Path) {
+    if path.is_dir() {
+      for note in walk_notes(path) {
+        self.read_note(note.path);
+      }
+    } else if is_note(path) {
+      self.read_note(path.to_path_buf());
+    }
+  }
+
+  fn forget_path(&mut self, path: &Path) {
+    self.files.retain(|file, _| !file.starts_with(path));
+  }
+
+  // Bring the index up to date with a batch of changes from the workspace watcher
+  fn apply(&mut self, changes: &WorkspaceChanges) {
+    for path in &changes.removed {
+      self.forget_path(Path::new(path));
+    }
+    for renamed in &changes.renamed {
+      self.forget_path(Path::new(&renamed.from));
+    }
+    let changed = changes
+      .created
+      .iter()
+      .chain(&changes.modified)
+      .chain(changes.renamed.iter().map(|renamed| &renamed.to));
+    for path in changed {
+      self.read_path(Path::new(path));
+    }
+  }
+
+  fn counts(&self) -> Vec<TagCount> {
+    count_tags(self.files.values())
+  }
+
+  // Notes tagged `tag` or one of its subtags, sorted
+  fn files_with(&self, tag: &str) -> Vec<String> {
+    let Some(tag) = normalize_tag(tag) else {
+      return Vec::new();
+    };
+    let subtag_prefix = format!("{}/", tag);
+    let mut files: Vec<String> = self
+      .files
+      .iter()
+      .filter(|(_, tags)| {
+        tags
+          .iter()
+          .any(|t| *t == tag || t.starts_with(&subtag_prefix))
+      })
+      .map(|(path, _)| path.to_string_lossy().to_string())
+      .collect();
+    files.sort();
+    files
+  }
+}
+
+// The tag index of the open workspace, kept current by the workspace watcher
+#[derive(Default)]
+pub struct TagIndexState(Mutex<Option<TagIndex>>);
+
+impl TagIndexState {
+  // Apply a batch of watcher changes under `root`, if that's the indexed workspace
+  pub fn apply(&self, root: &Path, changes: &WorkspaceChanges) {
+    let mut index = self.0.lock().unwrap();
+    if let Some(index) = index.as_mut().filter(|index| index.root == root) {
+      index.apply(changes);
+    }
+  }
+}
+
+// Index the tags of every note under `root`, replacing the previous workspace's index
+#[tauri::command]
+pub async fn index_tags(
+  state: tauri::State<'_, TagIndexState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  root: String,
+) -> CommandResult<Vec<TagCount>> {
+  let root = workspace::checked_root(&scope.0.lock().unwrap(), root)?;
+  let root = crate::lexical_normalize(&root);
+  let index = tauri::async_runtime::spawn_blocking(move || TagIndex::build(&root))
+    .await
+    .map_err(|e| CommandError::io("Failed to index tags", e))?;
+  let counts = index.counts();
+  *state.0.lock().unwrap() = Some(index);
+  Ok(counts)
+}
+
+// Tags of the indexed workspace with their note counts (empty until index_tags)
+#[tauri::command]
+pub async fn list_tags(state: tauri::State<'_, TagIndexState>) -> CommandResult<Vec<TagCount>> {
+  let index = state.0.lock().unwrap();
+  Ok(index.as_ref().map(TagIndex::counts).unwrap_or_default())
+}
+
+#[tauri::command]
+pub async fn files_with_tag(
+  state: tauri::State<'_, TagIndexState>,
+  tag: String,
+) -> CommandResult<Vec<String>> {
+  let index = state.0.lock().unwrap();
+  Ok(
+    index
+      .as_ref()
+      .map(|index| index.files_with(&tag))
+      .unwrap_or_default(),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::workspace::RenamedPath;
+  use std::fs;
+  use tempfile::TempDir;
+
+  const NOTE: &str = "\
+---
+title: Plan
+tags: [Project/Alpha, \"#draft\"]
+aliases:
+  - '#not-a-tag'
+---
+# Heading is not a tag
+## Notes #meeting
+Issue #123 and 2024/#05 stay numbers, but #v2 is a tag.
+Ends with punctuation: #todo, (#later) and \"#quoted\". Also #trailing/ and #dash-.
+Not tags: issue#12, &#169; `#code` [link](#anchor) https://example.com/page#section
+<span id=\"#html\"></span>
+
+```sh
+# comment #in-code
+```
+";
+
+  #[test]
+  fn test_note_tags() {
+    assert_eq!(
+      note_tags(NOTE),
+      vec![
+        "dash",
+        "draft",
+        "later",
+        "meeting",
+        "project/alpha",
+        "quoted",
+        "todo",
+        "trailing",
+        "v2"
+      ]
+    );
+    let block_list = "---\ntags:\n  - one\n  - two/three\ntitle: x\n---\nbody";
+    assert_eq!(note_tags(block_list), vec!["one", "two/three"]);
+    assert_eq!(
+      note_tags("tags: [not, frontmatter]\n#Mixed_Case"),
+      vec!["mixed_case"]
+    );
+  }
+
+  #[test]
+  fn test_index_counts_and_updates() {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    fs::write(root.join("a.md"), "#project/alpha #home").unwrap();
+    fs::write(root.join("b.md"), "---\ntags: project/beta\n---\n").unwrap();
+    fs::write(root.join("c.txt"), "#ignored").unwrap();
+    let mut index = TagIndex::build(root);
+
+    let counts: Vec<(String, usize)> = index
+      .counts()
+      .into_iter()
+      .map(|count| (count.tag, count.count))
+      .collect();
+    let expected = [
+      ("home", 1),
+      ("project", 2),
+      ("project/alpha", 1),
+      ("project/beta", 1),
+    ];
+    let expected: Vec<(String, usize)> = expected
+      .iter()
+      .map(|(tag, count)| (tag.to_string(), *count))
+      .collect();
+    assert_eq!(counts, expected);
+    assert_eq!(index.files_with("#Project").len(), 2);
+    assert_eq!(index.files_with("project/al"), Vec::<String>::new());
+
+    let display = |name: &str| root.join(name).to_string_lossy().to_string();
+    fs::write(root.join("a.md"), "#home only").unwrap();
+    fs::rename(root.join("b.md"), root.join("moved.md")).unwrap();
+    index.apply(&WorkspaceChanges {
+      root: display(""),
+      modified: vec![display("a.md")],
+      renamed: vec![RenamedPath {
+        from: display("b.md"),
+        to: display("moved.md"),
+      }],
+      ..WorkspaceChanges::default()
+    });
+    assert_eq!(index.files_with("project"), vec![display("moved.md")]);
+    assert_eq!(index.files_with("home"), vec![display("a.md")]);
+  }
+}
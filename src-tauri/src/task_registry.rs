@@ -0,0 +1,304 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::{CommandError, CommandResult};
+use crate::now_millis;
+
+// Sent to every window as long-running commands start, make progress and end, so one
+// progress UI can follow all of them
+pub const TASK_STARTED_EVENT: &str = "task-started";
+pub const TASK_PROGRESS_EVENT: &str = "task-progress";
+pub const TASK_FINISHED_EVENT: &str = "task-finished";
+
+// A running task, as listed by list_tasks and sent with TASK_STARTED_EVENT and
+// TASK_PROGRESS_EVENT
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskInfo {
+  pub id: String,
+  // The command running it, e.g. "find_duplicate_files"
+  pub kind: String,
+  // What it's doing, for showing to the user
+  pub label: String,
+  // Milliseconds since the epoch
+  pub started_at: u64,
+  // None until the task first reports progress
+  pub done: Option<usize>,
+  pub total: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskStatus {
+  Completed,
+  Cancelled,
+  Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskFinished {
+  pub id: String,
+  pub status: TaskStatus,
+}
+
+// Set by cancel_task; the task checks it between units of work
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+  pub fn cancel(&self) {
+    self.0.store(true, Ordering::SeqCst);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.0.load(Ordering::SeqCst)
+  }
+
+  // Err(Cancelled) once the task has been cancelled, so loops can stop with `?`
+  pub fn check(&self) -> CommandResult<()> {
+    if self.is_cancelled() {
+      Err(CommandError::Cancelled)
+    } else {
+      Ok(())
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct TaskRegistry {
+  tasks: Mutex<HashMap<String, (TaskInfo, CancelToken)>>,
+  next_id: AtomicU64,
+}
+
+impl TaskRegistry {
+  // Add a task under `id`, or a fresh id when the caller didn't pick one. A caller's id
+  // must not be in use, or cancelling it would be ambiguous.
+  fn register(
+    &self,
+    id: Option<String>,
+    kind: &str,
+    label: &str,
+    now: u64,
+  ) -> CommandResult<(TaskInfo, CancelToken)> {
+    let mut tasks = self.tasks.lock().unwrap();
+    let id = match id {
+      Some(id) if tasks.contains_key(&id) => {
+        return Err(CommandError::invalid_data(format!(
+          "A task with id {} is already running",
+          id
+        )));
+      }
+      Some(id) => id,
+      None => loop {
+        let id = format!("task-{}", self.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+        if !tasks.contains_key(&id) {
+          break id;
+        }
+      },
+    };
+    let info = TaskInfo {
+      id: id.clone(),
+      kind: kind.to_string(),
+      label: label.to_string(),
+      started_at: now,
+      done: None,
+      total: None,
+    };
+    let token = CancelToken::default();
+    tasks.insert(id, (info.clone(), token.clone()));
+    Ok((info, token))
+  }
+
+  fn update(&self, id: &str, done: usize, total: usize) -> Option<TaskInfo> {
+    let mut tasks = self.tasks.lock().unwrap();
+    let (info, _) = tasks.get_mut(id)?;
+    info.done = Some(done);
+    info.total = Some(total);
+    Some(info.clone())
+  }
+
+  fn remove(&self, id: &str) {
+    self.tasks.lock().unwrap().remove(id);
+  }
+
+  // Oldest first
+  fn list(&self) -> Vec<TaskInfo> {
+    let mut infos: Vec<TaskInfo> = self
+      .tasks
+      .lock()
+      .unwrap()
+      .values()
+      .map(|(info, _)| info.clone())
+      .collect();
+    infos.sort_by(|a, b| {
+      a.started_at
+        .cmp(&b.started_at)
+        .then_with(|| a.id.cmp(&b.id))
+    });
+    infos
+  }
+
+  // False when there's no such task, e.g. because it finished in the meantime
+  fn cancel(&self, id: &str) -> bool {
+    match self.tasks.lock().unwrap().get(id) {
+      Some((_, token)) => {
+        token.cancel();
+        true
+      }
+      None => false,
+    }
+  }
+}
+
+// What a task's work needs: its cancel token and a way to report progress. Cheap to clone
+// into worker threads.
+#[derive(Clone)]
+pub struct Task {
+  app: AppHandle,
+  id: String,
+  token: CancelToken,
+}
+
+impl Task {
+  pub fn is_cancelled(&self) -> bool {
+    self.token.is_cancelled()
+  }
+
+  pub fn check(&self) -> CommandResult<()> {
+    self.token.check()
+  }
+
+  pub fn progress(&self, done: usize, total: usize) {
+    if let Some(info) = self
+      .app
+      .state::<TaskRegistry>()
+      .update(&self.id, done, total)
+    {
+      let _ = self.app.emit(TASK_PROGRESS_EVENT, info);
+    }
+  }
+}
+
+// Registration of a running task. Dropping it takes the task off the registry and sends
+// TASK_FINISHED_EVENT; a guard dropped without `finish` (an early `?`) reports a failure.
+pub struct TaskGuard {
+  task: Task,
+  status: TaskStatus,
+}
+
+impl TaskGuard {
+  pub fn task(&self) -> Task {
+    self.task.clone()
+  }
+
+  // Record how the task ended and pass its result through
+  pub fn finish<T>(mut self, result: CommandResult<T>) -> CommandResult<T> {
+    self.status = match &result {
+      Ok(_) => TaskStatus::Completed,
+      Err(e) if e.is_cancelled() => TaskStatus::Cancelled,
+      Err(_) => TaskStatus::Failed,
+    };
+    result
+  }
+}
+
+impl Drop for TaskGuard {
+  fn drop(&mut self) {
+    let app = &self.task.app;
+    app.state::<TaskRegistry>().remove(&self.task.id);
+    let finished = TaskFinished {
+      id: self.task.id.clone(),
+      status: self.status,
+    };
+    let _ = app.emit(TASK_FINISHED_EVENT, finished);
+  }
+}
+
+// Register a long-running command's work. `task_id` is the id the caller asked for, so it
+// can cancel the task before the command returns.
+pub fn start_task(
+  app: &AppHandle,
+  task_id: Option<String>,
+  kind: &str,
+  label: &str,
+) -> CommandResult<TaskGuard> {
+  let (info, token) = app
+    .state::<TaskRegistry>()
+    .register(task_id, kind, label, now_millis())?;
+  let _ = app.emit(TASK_STARTED_EVENT, &info);
+  Ok(TaskGuard {
+    task: Task {
+      app: app.clone(),
+      id: info.id,
+      token,
+    },
+    status: TaskStatus::Failed,
+  })
+}
+
+#[tauri::command]
+pub async fn list_tasks(registry: tauri::State<'_, TaskRegistry>) -> CommandResult<Vec<TaskInfo>> {
+  Ok(registry.list())
+}
+
+// Ask a running task to stop. It ends with the `cancelled` error code once it gets to its
+// next check; returns false if there's no task with this id.
+#[tauri::command]
+pub async fn cancel_task(
+  registry: tauri::State<'_, TaskRegistry>,
+  id: String,
+) -> CommandResult<bool> {
+  Ok(registry.cancel(&id))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_register_picks_unused_ids() {
+    let registry = TaskRegistry::default();
+    let (first, _) = registry.register(None, "search", "Searching", 1).unwrap();
+    let (second, _) = registry.register(None, "search", "Searching", 2).unwrap();
+    assert_eq!(first.id, "task-1");
+    assert_eq!(second.id, "task-2");
+
+    // The caller's own id is used as given, and only once at a time
+    registry
+      .register(Some("task-3".into()), "export", "Exporting", 3)
+      .unwrap();
+    assert!(registry
+      .register(Some("task-3".into()), "export", "Exporting", 4)
+      .is_err());
+    let (fourth, _) = registry.register(None, "search", "Searching", 5).unwrap();
+    assert_eq!(fourth.id, "task-4");
+
+    registry.remove("task-3");
+    assert!(registry
+      .register(Some("task-3".into()), "export", "Exporting", 6)
+      .is_ok());
+  }
+
+  #[test]
+  fn test_cancel_and_progress() {
+    let registry = TaskRegistry::default();
+    let (_, token) = registry.register(None, "search", "Searching", 2).unwrap();
+    registry.register(None, "export", "Exporting", 1).unwrap();
+    assert!(token.check().is_ok());
+
+    let info = registry.update("task-1", 3, 10).unwrap();
+    assert_eq!((info.done, info.total), (Some(3), Some(10)));
+    let listed: Vec<String> = registry.list().into_iter().map(|info| info.id).collect();
+    assert_eq!(listed, vec!["task-2", "task-1"]);
+
+    assert!(registry.cancel("task-1"));
+    assert!(token.is_cancelled());
+    assert_eq!(token.check().unwrap_err().code(), "cancelled");
+
+    registry.remove("task-1");
+    assert!(!registry.cancel("task-1"));
+    assert!(registry.update("task-1", 4, 10).is_none());
+  }
+}
@@ -0,0 +1,575 @@
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::access_scope::AccessScopeState;
+use crate::error::{CommandError, CommandResult};
+use crate::includes::{atx_heading, is_fence};
+use crate::settings::SettingsState;
+use crate::write_queue::WriteCoordinator;
+
+// Where a GFM task item's box is on its line
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TaskMarker {
+  // Byte offset of the character between the brackets
+  state_offset: usize,
+  checked: bool,
+}
+
+fn indent_of(line: &str) -> usize {
+  line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+// A list item line (`-`, `*`, `+`, `1.` or `1)` marker followed by whitespace): its
+// indentation and the byte offset where its text starts
+fn list_item(line: &str) -> Option<(usize, usize)> {
+  let indent = indent_of(line);
+  let rest = &line[indent..];
+  let marker_len = if rest.starts_with(['-', '*', '+']) {
+    1
+  } else {
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    if !(1..=9).contains(&digits) || !rest[digits..].starts_with(['.', ')']) {
+      return None;
+    }
+    digits + 1
+  };
+  let after_marker = &rest[marker_len..];
+  let spaces = indent_of(after_marker);
+  (spaces > 0).then_some((indent, indent + marker_len + spaces))
+}
+
+// Parse a task list item: any indentation, a list marker, then `[ ]` or `[x]` followed by
+// the end of the line or whitespace
+fn task_marker(line: &str) -> Option<TaskMarker> {
+  let (_, text_offset) = list_item(line)?;
+  let task_box = line[text_offset..].as_bytes();
+  if task_box.len() < 3 || task_box[0] != b'[' || task_box[2] != b']' {
+    return None;
+  }
+  let checked = match task_box[1] {
+    b' ' => false,
+    b'x' | b'X' => true,
+    _ => return None,
+  };
+  if task_box.len() > 3 && !matches!(task_box[3], b' ' | b'\t' | b'\r' | b'\n') {
+    return None;
+  }
+  Some(TaskMarker {
+    state_offset: text_offset + 1,
+    checked,
+  })
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SectionTasks {
+  // None for tasks before the first heading
+  pub heading: Option<String>,
+  pub level: usize,
+  // 1-based line of the heading (0 before the first heading)
+  pub line: usize,
+  pub done: usize,
+  pub open: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TaskStats {
+  pub done: usize,
+  pub open: usize,
+  // Sections that have tasks, in document order
+  pub sections: Vec<SectionTasks>,
+}
+
+fn count_tasks(content: &str) -> TaskStats {
+  let mut stats = TaskStats::default();
+  let mut section = SectionTasks::default();
+  let mut in_fence = false;
+  for (index, line) in content.lines().enumerate() {
+    if is_fence(line) {
+      in_fence = !in_fence;
+      continue;
+    }
+    if in_fence {
+      continue;
+    }
+    if let Some((level, text)) = atx_heading(line) {
+      let next = SectionTasks {
+        heading: Some(text.trim().trim_end_matches('#').trim().to_string()),
+        level,
+        line: index + 1,
+        ..SectionTasks::default()
+      };
+      let finished = std::mem::replace(&mut section, next);
+      if finished.done + finished.open > 0 {
+        stats.sections.push(finished);
+      }
+    } else if let Some(task) = task_marker(line) {
+      if task.checked {
+        section.done += 1;
+        stats.done += 1;
+      } else {
+        section.open += 1;
+        stats.open += 1;
+      }
+    }
+  }
+  if section.done + section.open > 0 {
+    stats.sections.push(section);
+  }
+  stats
+}
+
+// Flip the task on 1-based `line_number`, returning the new content and the new line text
+fn toggle_line(content: &str, line_number: usize) -> CommandResult<(String, String)> {
+  let lines: Vec<&str> = content.split_inclusive('\n').collect();
+  if line_number == 0 || line_number > lines.len() {
+    return Err(CommandError::invalid_data(format!(
+      "Line {} is outside the file ({} lines)",
+      line_number,
+      lines.len()
+    )));
+  }
+  let in_fence = lines[..line_number - 1]
+    .iter()
+    .filter(|line| is_fence(line))
+    .count()
+    % 2
+    == 1;
+  let line = lines[line_number - 1];
+  let task = task_marker(line).filter(|_| !in_fence).ok_or_else(|| {
+    CommandError::invalid_data(format!("Line {} is not a task item", line_number))
+  })?;
+
+  let mut toggled = line.to_string();
+  let state = if task.checked { " " } else { "x" };
+  toggled.replace_range(task.state_offset..task.state_offset + 1, state);
+  let start: usize = lines[..line_number - 1].iter().map(|line| line.len()).sum();
+  let mut updated = String::with_capacity(content.len());
+  updated.push_str(&content[..start]);
+  updated.push_str(&toggled);
+  updated.push_str(&content[start + line.len()..]);
+  Ok((updated, toggled.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+// Done/open task counts for the whole document and per heading section
+#[tauri::command]
+pub async fn task_stats(content: String) -> CommandResult<TaskStats> {
+  Ok(count_tasks(&content))
+}
+
+// A task item toggled on disk by toggle_task
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToggledTask {
+  // The line's new text
+  pub line: String,
+  // Modification time of the saved file, for the next save's `expected_mtime`
+  pub mtime: Option<u64>,
+}
+
+// Tick or untick the task item on 1-based `line_number` of a file and save it, returning the
+// line's new text. Fails with a conflict if the file changed on disk after `expected_mtime`.
+#[tauri::command]
+pub async fn toggle_task(
+  coordinator: tauri::State<'_, WriteCoordinator>,
+  settings: tauri::State<'_, SettingsState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+  line_number: usize,
+  expected_mtime: Option<u64>,
+) -> CommandResult<ToggledTask> {
+  let durable = settings.0.lock().unwrap().durable_saves;
+  let path = PathBuf::from(&path);
+  scope.check(&path)?;
+  let target = path.clone();
+  let (result, _) = coordinator
+    .submit(&path, move || {
+      let content = crate::read_text_file(&target)?;
+      let (updated, line) = toggle_line(&content, line_number)?;
+      let mtime = crate::write_text_file(&target, &updated, expected_mtime, durable)?;
+      Ok(ToggledTask { line, mtime })
+    })
+    .await?;
+  result
+}
+
+// Where archive_completed_tasks moves completed tasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveMode {
+  // An `## Archive` section at the bottom of the same file, under a dated subheading
+  Section,
+  // A sibling `archive.md`, under a dated heading
+  File,
+}
+
+const ARCHIVE_HEADING: &str = "Archive";
+const ARCHIVE_FILE_NAME: &str = "archive.md";
+
+// Line index of the `## Archive` heading, if the document has one
+fn archive_heading_line(lines: &[&str]) -> Option<usize> {
+  let mut in_fence = false;
+  lines.iter().position(|line| {
+    if is_fence(line) {
+      in_fence = !in_fence;
+    }
+    !in_fence
+      && atx_heading(line).is_some_and(|(level, text)| level == 2 && text.trim() == ARCHIVE_HEADING)
+  })
+}
+
+// Line ranges of top-level task items that are checked along with every task nested under
+// them. An unchecked item stays with all its children, checked or not.
+fn completed_task_blocks(lines: &[&str]) -> Vec<Range<usize>> {
+  struct Block {
+    start: usize,
+    // Past the item's last non-blank line
+    end: usize,
+    indent: usize,
+    complete: bool,
+  }
+
+  let mut blocks = Vec::new();
+  let mut current: Option<Block> = None;
+  let mut in_fence = false;
+  for (index, line) in lines.iter().enumerate() {
+    let fence = is_fence(line);
+    let blank = line.trim().is_empty();
+    if let Some(block) = current.as_mut() {
+      if in_fence || blank || indent_of(line) > block.indent {
+        if !blank {
+          block.end = index + 1;
+        }
+        if !in_fence && !fence {
+          if let Some(task) = task_marker(line) {
+            block.complete &= task.checked;
+          }
+        }
+        if fence {
+          in_fence = !in_fence;
+        }
+        continue;
+      }
+      if let Some(block) = current.take().filter(|block| block.complete) {
+        blocks.push(block.start..block.end);
+      }
+    }
+    if fence {
+      in_fence = !in_fence;
+    } else if !in_fence {
+      // Other list items open a block too, so tasks nested under them aren't top-level
+      current = list_item(line).map(|(indent, _)| Block {
+        start: index,
+        end: index + 1,
+        indent,
+        complete: task_marker(line).is_some_and(|task| task.checked),
+      });
+    }
+  }
+  if let Some(block) = current.filter(|block| block.complete) {
+    blocks.push(block.start..block.end);
+  }
+  blocks
+}
+
+// Text of the last ATX heading in `content`
+fn last_heading(content: &str) -> Option<&str> {
+  content
+    .lines()
+    .rev()
+    .find(|line| atx_heading(line).is_some())
+}
+
+// Append `items` under `heading`, reusing the heading when it's already the last one (a
+// second archive on the same day)
+fn append_under_heading(content: &str, heading: &str, items: &str, newline: &str) -> String {
+  let mut updated = content.trim_end_matches(['\r', '\n']).to_string();
+  if last_heading(content).map(str::trim_end) == Some(heading) {
+    updated.push_str(newline);
+  } else {
+    if !updated.is_empty() {
+      updated.push_str(newline);
+      updated.push_str(newline);
+    }
+    updated.push_str(heading);
+    updated.push_str(newline);
+    updated.push_str(newline);
+  }
+  updated.push_str(items);
+  updated
+}
+
+// Split completed tasks out of `content`: returns the remaining content, the moved lines
+// (verbatim) and how many top-level items moved
+fn take_completed(content: &str, lines: &[&str]) -> (String, String, usize) {
+  let blocks = completed_task_blocks(lines);
+  let mut remaining = String::with_capacity(content.len());
+  let mut moved = String::new();
+  let mut next = 0;
+  for block in &blocks {
+    remaining.extend(lines[next..block.start].iter().copied());
+    moved.extend(lines[block.clone()].iter().copied());
+    next = block.end;
+  }
+  remaining.extend(lines[next..].iter().copied());
+  (remaining, moved, blocks.len())
+}
+
+fn archive_into_section(content: &str, date: &str) -> (String, usize) {
+  let lines: Vec<&str> = content.split_inclusive('\n').collect();
+  let newline = if content.contains("\r\n") {
+    "\r\n"
+  } else {
+    "\n"
+  };
+  // Tasks already in the archive stay where they are
+  let archive_start = archive_heading_line(&lines);
+  let (active, archived) = lines.split_at(archive_start.unwrap_or(lines.len()));
+  let (remaining, mut moved, count) = take_completed(content, active);
+  if count == 0 {
+    return (content.to_string(), 0);
+  }
+  if !moved.ends_with('\n') {
+    moved.push_str(newline);
+  }
+
+  let mut updated = remaining;
+  if archived.is_empty() {
+    updated = updated.trim_end_matches(['\r', '\n']).to_string();
+    updated.push_str(newline);
+    updated.push_str(newline);
+    updated.push_str(&format!("## {}", ARCHIVE_HEADING));
+  } else {
+    updated.extend(archived.iter().copied());
+  }
+  let heading = format!("### {}", date);
+  (
+    append_under_heading(&updated, &heading, &moved, newline),
+    count,
+  )
+}
+
+fn archive_into_file(content: &str, archive: Option<&str>, date: &str) -> (String, String, usize) {
+  let lines: Vec<&str> = content.split_inclusive('\n').collect();
+  let newline = if content.contains("\r\n") {
+    "\r\n"
+  } else {
+    "\n"
+  };
+  let (remaining, mut moved, count) = take_completed(content, &lines);
+  if !moved.ends_with('\n') {
+    moved.push_str(newline);
+  }
+  let archive = archive.map_or_else(
+    || format!("# {}{}", ARCHIVE_HEADING, newline),
+    str::to_string,
+  );
+  let heading = format!("## {}", date);
+  let archive = append_under_heading(&archive, &heading, &moved, newline);
+  (remaining, archive, count)
+}
+
+// Move checked top-level tasks, with everything nested under them, out of a document into
+// its archive (see ArchiveMode). Items move verbatim. Returns how many moved.
+#[tauri::command]
+pub async fn archive_completed_tasks(
+  coordinator: tauri::State<'_, WriteCoordinator>,
+  settings: tauri::State<'_, SettingsState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  path: String,
+  mode: ArchiveMode,
+) -> CommandResult<usize> {
+  let durable = settings.0.lock().unwrap().durable_saves;
+  let path = PathBuf::from(&path);
+  scope.check(&path)?;
+  let target = path.clone();
+  let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+  let (result, _) = coordinator
+    .submit(&path, move || {
+      let content = crate::read_text_file(&target)?;
+      match mode {
+        ArchiveMode::Section => {
+          let (updated, count) = archive_into_section(&content, &date);
+          if count > 0 {
+            crate::write_text_file(&target, &updated, None, durable)?;
+          }
+          Ok(count)
+        }
+        ArchiveMode::File => {
+          let archive_path = target.with_file_name(ARCHIVE_FILE_NAME);
+          if archive_path == target {
+            return Err(CommandError::invalid_data(
+              "The archive can't archive into itself",
+            ));
+          }
+          let archive = archive_path
+            .exists()
+            .then(|| crate::read_text_file(&archive_path))
+            .transpose()?;
+          let (remaining, archive, count) = archive_into_file(&content, archive.as_deref(), &date);
+          if count > 0 {
+            // Archive first: if the second write fails, items are duplicated, not lost
+            crate::write_text_file(&archive_path, &archive, None, durable)?;
+            crate::write_text_file(&target, &remaining, None, durable)?;
+          }
+          Ok(count)
+        }
+      }
+    })
+    .await?;
+  result
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_task_marker() {
+    assert_eq!(
+      task_marker("- [ ] write docs"),
+      Some(TaskMarker {
+        state_offset: 3,
+        checked: false,
+      })
+    );
+    assert!(task_marker("    * [X] nested").unwrap().checked);
+    assert_eq!(task_marker("12) [x]").unwrap().state_offset, 5);
+    assert_eq!(task_marker("+\t[ ] tab").unwrap().state_offset, 3);
+    assert_eq!(task_marker("- [] no"), None);
+    assert_eq!(task_marker("-[ ] no space"), None);
+    assert_eq!(task_marker("- [x]no space after"), None);
+    assert_eq!(task_marker("[ ] no marker"), None);
+  }
+
+  #[test]
+  fn test_count_tasks_by_section() {
+    let content = "\
+- [x] before any heading
+
+# Project
+
+- [ ] one
+  - [x] two
+
+```
+- [ ] in code
+```
+
+## Later ##
+
+1. [X] three
+- not a task
+";
+    let stats = count_tasks(content);
+    assert_eq!((stats.done, stats.open), (3, 1));
+    let sections: Vec<(Option<&str>, usize, usize, usize)> = stats
+      .sections
+      .iter()
+      .map(|s| (s.heading.as_deref(), s.line, s.done, s.open))
+      .collect();
+    assert_eq!(
+      sections,
+      vec![
+        (None, 0, 1, 0),
+        (Some("Project"), 3, 1, 1),
+        (Some("Later"), 12, 1, 0),
+      ]
+    );
+  }
+
+  #[test]
+  fn test_toggle_line() {
+    let content = "# Todo\r\n- [ ] a\r\n  - [x] b\r\n";
+    let (updated, line) = toggle_line(content, 2).unwrap();
+    assert_eq!(line, "- [x] a");
+    assert_eq!(updated, "# Todo\r\n- [x] a\r\n  - [x] b\r\n");
+    let (updated, line) = toggle_line(&updated, 3).unwrap();
+    assert_eq!(line, "  - [ ] b");
+    assert_eq!(updated, "# Todo\r\n- [x] a\r\n  - [ ] b\r\n");
+
+    assert_eq!(toggle_line(content, 1).unwrap_err().code(), "invalid_data");
+    assert!(toggle_line(content, 0).is_err());
+    assert!(toggle_line(content, 4).is_err());
+    assert!(toggle_line("```\n- [ ] code\n```\n", 2).is_err());
+  }
+
+  #[test]
+  fn test_toggle_task_refuses_stale_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("todo.md");
+    std::fs::write(&path, "- [ ] a\n").unwrap();
+    let (updated, _) = toggle_line(&crate::read_text_file(&path).unwrap(), 1).unwrap();
+    let err = crate::write_text_file(&path, &updated, Some(0), false).unwrap_err();
+    assert_eq!(err.code(), "conflict");
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "- [ ] a\n");
+  }
+
+  #[test]
+  fn test_archive_into_new_section() {
+    let content = "\
+# Todo
+
+- [x] ship it #release due:2026-10-01
+  - [x] changelog
+  notes about shipping
+- [ ] parent stays
+  - [x] finished child stays too
+- [x] blocked by child
+  - [ ] open child
+3. [X] numbered
+";
+    let (updated, count) = archive_into_section(content, "2026-10-15");
+    assert_eq!(count, 2);
+    assert_eq!(
+      updated,
+      "\
+# Todo
+
+- [ ] parent stays
+  - [x] finished child stays too
+- [x] blocked by child
+  - [ ] open child
+
+## Archive
+
+### 2026-10-15
+
+- [x] ship it #release due:2026-10-01
+  - [x] changelog
+  notes about shipping
+3. [X] numbered
+"
+    );
+    // Nothing left to move
+    assert_eq!(
+      archive_into_section(&updated, "2026-10-16"),
+      (updated.clone(), 0)
+    );
+  }
+
+  #[test]
+  fn test_archive_into_existing_section_same_day() {
+    let content = "- [x] b\r\n\r\n## Archive\r\n\r\n### 2026-10-15\r\n\r\n- [x] a\r\n";
+    let (updated, count) = archive_into_section(content, "2026-10-15");
+    assert_eq!(count, 1);
+    assert_eq!(
+      updated,
+      "\r\n## Archive\r\n\r\n### 2026-10-15\r\n\r\n- [x] a\r\n- [x] b\r\n"
+    );
+  }
+
+  #[test]
+  fn test_archive_into_file() {
+    let content = "- [ ] open\n- [x] done";
+    let (remaining, archive, count) = archive_into_file(content, None, "2026-10-15");
+    assert_eq!(count, 1);
+    assert_eq!(remaining, "- [ ] open\n");
+    assert_eq!(archive, "# Archive\n\n## 2026-10-15\n\n- [x] done\n");
+
+    let (_, archive, _) = archive_into_file("- [x] later\n", Some(&archive), "2026-10-16");
+    assert_eq!(
+      archive,
+      "# Archive\n\n## 2026-10-15\n\n- [x] done\n\n## 2026-10-16\n\n- [x] later\n"
+    );
+  }
+}
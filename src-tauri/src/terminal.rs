@@ -0,0 +1,240 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{CommandError, CommandResult};
+use crate::settings::SettingsState;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TerminalLaunch {
+  // The terminal that was started, e.g. `Terminal`, `wt` or `gnome-terminal`
+  pub launcher: String,
+}
+
+// How to start a terminal. The folder is passed as its own argument or as the working
+// directory, never through a shell, so spaces and other characters need no quoting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Launcher {
+  // Named in the result and in errors
+  name: String,
+  program: String,
+  args: Vec<String>,
+  // Wait for the program and treat a failed exit as an error (`open -a` exits right away
+  // and fails when the app doesn't exist)
+  wait: bool,
+}
+
+impl Launcher {
+  fn new(name: &str, program: &str, args: Vec<String>) -> Self {
+    Launcher {
+      name: name.to_string(),
+      program: program.to_string(),
+      args,
+      wait: false,
+    }
+  }
+
+  fn run(&self, dir: &Path) -> CommandResult<()> {
+    let mut command = Command::new(&self.program);
+    command.args(&self.args).current_dir(dir);
+    // cmd needs a console of its own, or it attaches to none and exits
+    #[cfg(windows)]
+    if self.program == "cmd" {
+      use std::os::windows::process::CommandExt;
+      const CREATE_NEW_CONSOLE: u32 = 0x0000_0010;
+      command.creation_flags(CREATE_NEW_CONSOLE);
+    }
+    let failed = |reason: String| CommandError::LaunchFailed {
+      program: self.name.clone(),
+      reason,
+    };
+    if self.wait {
+      let output = command.output().map_err(|e| failed(e.to_string()))?;
+      if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        return Err(failed(if stderr.is_empty() {
+          format!("exited with {}", output.status)
+        } else {
+          stderr
+        }));
+      }
+    } else {
+      let mut child = command.spawn().map_err(|e| failed(e.to_string()))?;
+      // Reap the terminal once it's closed
+      std::thread::spawn(move || child.wait());
+    }
+    Ok(())
+  }
+}
+
+// The folder to open: the document's, or the path itself for a folder (e.g. from the file
+// tree)
+fn terminal_dir(path: &Path) -> CommandResult<PathBuf> {
+  let dir = if path.is_dir() {
+    path
+  } else {
+    path.parent().unwrap_or(path)
+  };
+  if !dir.is_dir() {
+    return Err(CommandError::NotFound {
+      path: dir.to_string_lossy().to_string(),
+    });
+  }
+  Ok(dir.to_path_buf())
+}
+
+// `open -a` with Terminal, or the app chosen in the settings (e.g. iTerm)
+#[cfg(target_os = "macos")]
+fn launcher(dir: &Path, configured: Option<&str>) -> CommandResult<Launcher> {
+  let app = configured.unwrap_or("Terminal");
+  let args = vec![
+    "-a".to_string(),
+    app.to_string(),
+    dir.to_string_lossy().to_string(),
+  ];
+  Ok(Launcher {
+    wait: true,
+    ..Launcher::new(app, "open", args)
+  })
+}
+
+// The configured program, Windows Terminal, or a new cmd window
+#[cfg(windows)]
+fn launcher(dir: &Path, configured: Option<&str>) -> CommandResult<Launcher> {
+  if let Some(program) = configured {
+    return Ok(Launcher::new(program, program, Vec::new()));
+  }
+  let path_var = std::env::var_os("PATH").unwrap_or_default();
+  if crate::open_with::find_program("wt", &path_var) {
+    let args = vec!["-d".to_string(), dir.to_string_lossy().to_string()];
+    return Ok(Launcher::new("Windows Terminal", "wt", args));
+  }
+  Ok(Launcher::new("cmd", "cmd", vec!["/K".to_string()]))
+}
+
+// The configured program, then $TERMINAL, then the usual terminals in order
+#[cfg(all(unix, not(target_os = "macos")))]
+fn launcher(dir: &Path, configured: Option<&str>) -> CommandResult<Launcher> {
+  let path_var = std::env::var_os("PATH").unwrap_or_default();
+  let env_terminal = std::env::var("TERMINAL").ok();
+  linux_launcher(dir, configured, env_terminal.as_deref(), |program| {
+    crate::open_with::find_program(program, &path_var)
+  })
+}
+
+#[cfg(not(any(windows, unix)))]
+fn launcher(_dir: &Path, _configured: Option<&str>) -> CommandResult<Launcher> {
+  Err(CommandError::LaunchFailed {
+    program: "terminal".to_string(),
+    reason: "not supported on this platform".to_string(),
+  })
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+const LINUX_TERMINALS: &[&str] = &[
+  "x-terminal-emulator",
+  "gnome-terminal",
+  "konsole",
+  "xfce4-terminal",
+  "alacritty",
+  "kitty",
+  "xterm",
+];
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn linux_launcher(
+  dir: &Path,
+  configured: Option<&str>,
+  env_terminal: Option<&str>,
+  installed: impl Fn(&str) -> bool,
+) -> CommandResult<Launcher> {
+  // $TERMINAL may carry arguments, e.g. `kitty --single-instance`
+  let chosen = configured
+    .or(env_terminal)
+    .filter(|terminal| !terminal.trim().is_empty());
+  if let Some(terminal) = chosen {
+    let mut words = terminal.split_whitespace();
+    let program = words.next().unwrap_or(terminal);
+    return Ok(Launcher::new(
+      program,
+      program,
+      words.map(str::to_string).collect(),
+    ));
+  }
+  let program = LINUX_TERMINALS
+    .iter()
+    .copied()
+    .find(|program| installed(program))
+    .ok_or_else(|| CommandError::LaunchFailed {
+      program: "x-terminal-emulator".to_string(),
+      reason: "no terminal found, set $TERMINAL or choose one in the settings".to_string(),
+    })?;
+  // Most terminals start in the working directory; these two want it spelled out
+  let dir = dir.to_string_lossy();
+  let args = match program {
+    "gnome-terminal" => vec![format!("--working-directory={}", dir)],
+    "konsole" => vec!["--workdir".to_string(), dir.to_string()],
+    _ => Vec::new(),
+  };
+  Ok(Launcher::new(program, program, args))
+}
+
+// Open a terminal in the folder of `path` (or at `path` if it's a folder). Returns which
+// terminal was started.
+#[tauri::command]
+pub async fn open_terminal_at(
+  settings: tauri::State<'_, SettingsState>,
+  path: String,
+) -> CommandResult<TerminalLaunch> {
+  let dir = terminal_dir(Path::new(&path))?;
+  let configured = settings
+    .0
+    .lock()
+    .unwrap()
+    .terminal_app
+    .clone()
+    .filter(|app| !app.trim().is_empty());
+  let launcher = launcher(&dir, configured.as_deref())?;
+  launcher.run(&dir)?;
+  Ok(TerminalLaunch {
+    launcher: launcher.name,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_terminal_dir_is_the_documents_folder() {
+    let dir = TempDir::new().unwrap();
+    let folder = dir.path().join("my notes ü");
+    std::fs::create_dir(&folder).unwrap();
+    let file = folder.join("todo.md");
+    std::fs::write(&file, "").unwrap();
+
+    assert_eq!(terminal_dir(&file).unwrap(), folder);
+    assert_eq!(terminal_dir(&folder).unwrap(), folder);
+    let missing = dir.path().join("gone").join("todo.md");
+    assert_eq!(terminal_dir(&missing).unwrap_err().code(), "not_found");
+  }
+
+  #[cfg(all(unix, not(target_os = "macos")))]
+  #[test]
+  fn test_linux_launcher_order() {
+    let dir = Path::new("/home/me/my notes");
+    let launcher = linux_launcher(dir, None, Some("kitty --single-instance"), |_| true).unwrap();
+    assert_eq!(launcher.program, "kitty");
+    assert_eq!(launcher.args, vec!["--single-instance"]);
+
+    let launcher = linux_launcher(dir, Some("alacritty"), Some("kitty"), |_| true).unwrap();
+    assert_eq!(launcher.program, "alacritty");
+
+    let launcher = linux_launcher(dir, None, None, |program| program == "gnome-terminal").unwrap();
+    assert_eq!(launcher.args, vec!["--working-directory=/home/me/my notes"]);
+
+    let error = linux_launcher(dir, None, None, |_| false).unwrap_err();
+    assert_eq!(error.code(), "launch_failed");
+  }
+}
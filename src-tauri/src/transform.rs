@@ -0,0 +1,305 @@
+use serde::Deserialize;
+use std::cmp::Ordering;
+
+use crate::error::{CommandError, CommandResult};
+
+// Line-based transforms for the selection, exposed under Edit > Lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineOperation {
+  SortAscending,
+  SortDescending,
+  // Drop repeated lines, keeping the first of each
+  Dedupe,
+  Reverse,
+  // All lines into one, separated by `delimiter`
+  Join,
+  // Every line broken at each `delimiter`
+  Split,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TransformOptions {
+  // Compare runs of digits by value, so `item 9` sorts before `item 10`
+  pub natural: bool,
+  // Sort and dedupe ignoring case
+  pub case_insensitive: bool,
+  pub delimiter: String,
+}
+
+impl Default for TransformOptions {
+  fn default() -> Self {
+    TransformOptions {
+      natural: false,
+      case_insensitive: false,
+      delimiter: ", ".to_string(),
+    }
+  }
+}
+
+// Split a line into runs of digits and runs of everything else
+fn chunks(line: &str) -> impl Iterator<Item = &str> {
+  let mut rest = line;
+  std::iter::from_fn(move || {
+    let first = rest.chars().next()?;
+    let digits = first.is_ascii_digit();
+    let end = rest
+      .find(|c: char| c.is_ascii_digit() != digits)
+      .unwrap_or(rest.len());
+    let (chunk, remainder) = rest.split_at(end);
+    rest = remainder;
+    Some(chunk)
+  })
+}
+
+// Numbers compare by value (any length, leading zeros ignored), text by character
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+  let mut a_chunks = chunks(a);
+  let mut b_chunks = chunks(b);
+  loop {
+    let (a_chunk, b_chunk) = match (a_chunks.next(), b_chunks.next()) {
+      (None, None) => return Ordering::Equal,
+      (None, Some(_)) => return Ordering::Less,
+      (Some(_), None) => return Ordering::Greater,
+      (Some(a), Some(b)) => (a, b),
+    };
+    let both_numbers = a_chunk.starts_with(|c: char| c.is_ascii_digit())
+      && b_chunk.starts_with(|c: char| c.is_ascii_digit());
+    let ordering = if both_numbers {
+      let (a_value, b_value) = (
+        a_chunk.trim_start_matches('0'),
+        b_chunk.trim_start_matches('0'),
+      );
+      a_value
+        .len()
+        .cmp(&b_value.len())
+        .then_with(|| a_value.cmp(b_value))
+    } else {
+      a_chunk.cmp(b_chunk)
+    };
+    if ordering != Ordering::Equal {
+      return ordering;
+    }
+  }
+}
+
+fn compare_lines(a: &str, b: &str, options: &TransformOptions) -> Ordering {
+  let (a_key, b_key) = if options.case_insensitive {
+    (a.to_lowercase(), b.to_lowercase())
+  } else {
+    (a.to_string(), b.to_string())
+  };
+  let ordering = if options.natural {
+    natural_cmp(&a_key, &b_key)
+  } else {
+    a_key.cmp(&b_key)
+  };
+  // Lines that only differ in case or zero padding still get a fixed order
+  ordering.then_with(|| a.cmp(b))
+}
+
+fn transform_lines(
+  mut lines: Vec<&str>,
+  operation: LineOperation,
+  options: &TransformOptions,
+) -> CommandResult<Vec<String>> {
+  match operation {
+    LineOperation::SortAscending => lines.sort_by(|a, b| compare_lines(a, b, options)),
+    LineOperation::SortDescending => lines.sort_by(|a, b| compare_lines(b, a, options)),
+    LineOperation::Dedupe => {
+      let mut seen = std::collections::HashSet::new();
+      lines.retain(|line| {
+        seen.insert(if options.case_insensitive {
+          line.to_lowercase()
+        } else {
+          line.to_string()
+        })
+      });
+    }
+    LineOperation::Reverse => lines.reverse(),
+    LineOperation::Join => return Ok(vec![lines.join(&options.delimiter)]),
+    LineOperation::Split => {
+      if options.delimiter.is_empty() {
+        return Err(CommandError::invalid_data("Split needs a delimiter"));
+      }
+      return Ok(
+        lines
+          .iter()
+          .flat_map(|line| line.split(options.delimiter.as_str()))
+          .map(str::to_string)
+          .collect(),
+      );
+    }
+  }
+  Ok(lines.into_iter().map(str::to_string).collect())
+}
+
+// Apply a line operation to `text`. Output uses the input's line ending (CRLF if it has
+// any) and ends with a newline exactly when the input did.
+pub fn apply(
+  text: &str,
+  operation: LineOperation,
+  options: &TransformOptions,
+) -> CommandResult<String> {
+  let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+  let trailing = text.ends_with('\n');
+  let body = text
+    .strip_suffix('\n')
+    .map(|body| body.strip_suffix('\r').unwrap_or(body))
+    .unwrap_or(text);
+  if body.is_empty() && !matches!(operation, LineOperation::Join) {
+    return Ok(text.to_string());
+  }
+  let lines: Vec<&str> = body
+    .split('\n')
+    .map(|line| line.strip_suffix('\r').unwrap_or(line))
+    .collect();
+  let mut transformed = transform_lines(lines, operation, options)?.join(newline);
+  if trailing {
+    transformed.push_str(newline);
+  }
+  Ok(transformed)
+}
+
+#[tauri::command]
+pub async fn edit_transform(
+  text: String,
+  operation: LineOperation,
+  options: Option<TransformOptions>,
+) -> CommandResult<String> {
+  apply(&text, operation, &options.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn run(text: &str, operation: LineOperation) -> String {
+    apply(text, operation, &TransformOptions::default()).unwrap()
+  }
+
+  // Deterministic pseudo-random documents for the property tests
+  fn samples() -> Vec<String> {
+    let words = [
+      "apple",
+      "Apple",
+      "item 10",
+      "item 9",
+      "item 09",
+      "  indented",
+      "zebra",
+      "b",
+      "B",
+    ];
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    (0..200)
+      .map(|i| {
+        let count = i % 9;
+        let mut text: Vec<&str> = Vec::new();
+        for _ in 0..count {
+          seed ^= seed << 13;
+          seed ^= seed >> 7;
+          seed ^= seed << 17;
+          text.push(words[(seed % words.len() as u64) as usize]);
+        }
+        let newline = if i % 3 == 0 { "\r\n" } else { "\n" };
+        let mut text = text.join(newline);
+        if i % 2 == 0 {
+          text.push_str(newline);
+        }
+        text
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_sort_orders() {
+    let text = "item 10\nItem 9\nitem 2\n";
+    assert_eq!(
+      run(text, LineOperation::SortAscending),
+      "Item 9\nitem 10\nitem 2\n"
+    );
+    let options = TransformOptions {
+      natural: true,
+      case_insensitive: true,
+      ..TransformOptions::default()
+    };
+    assert_eq!(
+      apply(text, LineOperation::SortAscending, &options).unwrap(),
+      "item 2\nItem 9\nitem 10\n"
+    );
+    assert_eq!(
+      apply(text, LineOperation::SortDescending, &options).unwrap(),
+      "item 10\nItem 9\nitem 2\n"
+    );
+  }
+
+  #[test]
+  fn test_line_endings_and_trailing_newline_are_kept() {
+    assert_eq!(
+      run("b\r\na\r\n", LineOperation::SortAscending),
+      "a\r\nb\r\n"
+    );
+    assert_eq!(run("b\na", LineOperation::Reverse), "a\nb");
+    assert_eq!(run("", LineOperation::Reverse), "");
+    assert_eq!(run("a\nb\na\n", LineOperation::Dedupe), "a\nb\n");
+  }
+
+  #[test]
+  fn test_join_and_split() {
+    assert_eq!(
+      run("red\ngreen\nblue\n", LineOperation::Join),
+      "red, green, blue\n"
+    );
+    assert_eq!(
+      run("red, green, blue\n", LineOperation::Split),
+      "red\ngreen\nblue\n"
+    );
+    let options = TransformOptions {
+      delimiter: String::new(),
+      ..TransformOptions::default()
+    };
+    assert!(apply("a", LineOperation::Split, &options).is_err());
+  }
+
+  #[test]
+  fn test_sorting_twice_equals_sorting_once() {
+    for options in [
+      TransformOptions::default(),
+      TransformOptions {
+        natural: true,
+        case_insensitive: true,
+        ..TransformOptions::default()
+      },
+    ] {
+      for operation in [LineOperation::SortAscending, LineOperation::SortDescending] {
+        for text in samples() {
+          let once = apply(&text, operation, &options).unwrap();
+          assert_eq!(
+            apply(&once, operation, &options).unwrap(),
+            once,
+            "{:?}",
+            text
+          );
+          // Same lines, just reordered
+          let mut before: Vec<&str> = text.lines().collect();
+          let mut after: Vec<&str> = once.lines().collect();
+          before.sort();
+          after.sort();
+          assert_eq!(before, after);
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn test_dedupe_is_idempotent_and_reverse_is_an_involution() {
+    for text in samples() {
+      let deduped = run(&text, LineOperation::Dedupe);
+      assert_eq!(run(&deduped, LineOperation::Dedupe), deduped);
+      let reversed = run(&text, LineOperation::Reverse);
+      assert_eq!(run(&reversed, LineOperation::Reverse), text);
+    }
+  }
+}
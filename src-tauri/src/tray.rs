@@ -0,0 +1,188 @@
+use std::path::Path;
+use tauri::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Listener, Manager};
+
+use crate::settings::{Settings, SettingsState};
+use crate::{close_guard, launch, RecentFilesState, MENU_NEW_FILE_EVENT, RECENTS_CHANGED_EVENT};
+
+const TRAY_ID: &str = "main-tray";
+
+// How many recent files the tray menu lists
+const TRAY_RECENT_FILES: usize = 5;
+
+// Menu item ids. Recent files are `tray_recent:<path>`.
+const NEW_NOTE_ID: &str = "tray_new_note";
+const QUICK_CAPTURE_ID: &str = "tray_quick_capture";
+const RECENT_ID_PREFIX: &str = "tray_recent:";
+const QUIT_ID: &str = "tray_quit";
+
+fn recent_item_id(path: &str) -> String {
+  format!("{}{}", RECENT_ID_PREFIX, path)
+}
+
+fn recent_item_title(path: &str) -> String {
+  Path::new(path)
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_else(|| path.to_string())
+}
+
+fn create_tray_menu(app: &AppHandle, recents: &[String]) -> tauri::Result<Menu<tauri::Wry>> {
+  let menu = Menu::new(app)?;
+  menu.append(&MenuItem::with_id(
+    app,
+    NEW_NOTE_ID,
+    "New Note",
+    true,
+    None::<&str>,
+  )?)?;
+  menu.append(&MenuItem::with_id(
+    app,
+    QUICK_CAPTURE_ID,
+    "Quick Capture",
+    true,
+    None::<&str>,
+  )?)?;
+  if !recents.is_empty() {
+    menu.append(&PredefinedMenuItem::separator(app)?)?;
+    for path in recents.iter().take(TRAY_RECENT_FILES) {
+      menu.append(&MenuItem::with_id(
+        app,
+        recent_item_id(path),
+        recent_item_title(path),
+        true,
+        None::<&str>,
+      )?)?;
+    }
+  }
+  menu.append(&PredefinedMenuItem::separator(app)?)?;
+  menu.append(&MenuItem::with_id(
+    app,
+    QUIT_ID,
+    "Quit Markdowner",
+    true,
+    None::<&str>,
+  )?)?;
+  Ok(menu)
+}
+
+fn handle_tray_menu_event(app: &AppHandle, event: MenuEvent) {
+  let id = event.id().0.as_str();
+  match id {
+    NEW_NOTE_ID => {
+      launch::show_main_window(app);
+      let _ = app.emit(MENU_NEW_FILE_EVENT, ());
+    }
+    // A window of its own, so jotting something down doesn't replace the open document
+    QUICK_CAPTURE_ID => {
+      if let Err(e) = launch::open_new_window(app) {
+        log::error!("Failed to open a capture window: {}", e);
+      }
+    }
+    QUIT_ID => close_guard::request_quit(app),
+    _ => {
+      if let Some(path) = id.strip_prefix(RECENT_ID_PREFIX) {
+        launch::open_files(app, &[path.to_string().into()]);
+      }
+    }
+  }
+}
+
+fn create_tray(app: &AppHandle) -> tauri::Result<()> {
+  let recents = app.state::<RecentFilesState>().0.lock().unwrap().clone();
+  let menu = create_tray_menu(app, &recents)?;
+  let mut builder = TrayIconBuilder::with_id(TRAY_ID)
+    .tooltip("Markdowner")
+    .menu(&menu)
+    // Menu bar items open their menu on click on macOS; elsewhere a left click shows the
+    // window and the menu is on right click
+    .show_menu_on_left_click(cfg!(target_os = "macos"))
+    .on_menu_event(handle_tray_menu_event)
+    .on_tray_icon_event(|tray, event| {
+      if let TrayIconEvent::Click {
+        button: MouseButton::Left,
+        button_state: MouseButtonState::Up,
+        ..
+      } = event
+      {
+        launch::show_main_window(tray.app_handle());
+      }
+    });
+  if let Some(icon) = app.default_window_icon() {
+    builder = builder.icon(icon.clone());
+  }
+  builder.build(app)?;
+  Ok(())
+}
+
+// Add or remove the tray icon to match the settings
+pub fn apply_settings(app: &AppHandle, settings: &Settings) {
+  let exists = app.tray_by_id(TRAY_ID).is_some();
+  if settings.show_tray_icon && !exists {
+    if let Err(e) = create_tray(app) {
+      log::error!("Failed to create tray icon: {}", e);
+    }
+  } else if !settings.show_tray_icon && exists {
+    app.remove_tray_by_id(TRAY_ID);
+  }
+}
+
+// Show the tray if it's enabled and keep its recent files in step with the recents list
+pub fn init(app: &AppHandle) {
+  let settings = app.state::<SettingsState>().0.lock().unwrap().clone();
+  apply_settings(app, &settings);
+
+  let handle = app.clone();
+  app.listen_any(RECENTS_CHANGED_EVENT, move |event| {
+    let Some(tray) = handle.tray_by_id(TRAY_ID) else {
+      return;
+    };
+    let recents: Vec<String> = serde_json::from_str(event.payload()).unwrap_or_default();
+    match create_tray_menu(&handle, &recents) {
+      Ok(menu) => {
+        let _ = tray.set_menu(Some(menu));
+      }
+      Err(e) => log::error!("Failed to update tray menu: {}", e),
+    }
+  });
+}
+
+// Whether closing the last window should leave the app running in the tray
+pub fn keeps_running(app: &AppHandle) -> bool {
+  let settings = app.state::<SettingsState>().0.lock().unwrap().clone();
+  keeps_running_with(&settings) && app.tray_by_id(TRAY_ID).is_some()
+}
+
+fn keeps_running_with(settings: &Settings) -> bool {
+  settings.show_tray_icon && settings.keep_running_in_tray
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_recent_items_round_trip_their_path() {
+    let path = "/home/me/notes/todo.md";
+    assert_eq!(
+      recent_item_id(path).strip_prefix(RECENT_ID_PREFIX),
+      Some(path)
+    );
+    assert_eq!(recent_item_title(path), "todo.md");
+  }
+
+  #[test]
+  fn test_keep_running_needs_the_tray() {
+    let settings = Settings {
+      keep_running_in_tray: true,
+      ..Settings::default()
+    };
+    assert!(!keeps_running_with(&settings));
+    let settings = Settings {
+      show_tray_icon: true,
+      ..settings
+    };
+    assert!(keeps_running_with(&settings));
+  }
+}
@@ -0,0 +1,379 @@
+use serde::Deserialize;
+use std::ops::Range;
+
+use crate::error::CommandResult;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuoteStyle {
+  // “double” and ‘single’
+  #[default]
+  English,
+  // „double“ and ‚single‘
+  German,
+  // «double» and ‹single›
+  French,
+}
+
+impl QuoteStyle {
+  // (open double, close double, open single, close single)
+  fn marks(self) -> (char, char, char, char) {
+    match self {
+      QuoteStyle::English => ('“', '”', '‘', '’'),
+      QuoteStyle::German => ('„', '“', '‚', '‘'),
+      QuoteStyle::French => ('«', '»', '‹', '›'),
+    }
+  }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TypographyOptions {
+  pub quotes: bool,
+  // `--` to an en dash and `---` to an em dash
+  pub dashes: bool,
+  // `...` to an ellipsis
+  pub ellipses: bool,
+  pub quote_style: QuoteStyle,
+}
+
+impl Default for TypographyOptions {
+  fn default() -> Self {
+    TypographyOptions {
+      quotes: true,
+      dashes: true,
+      ellipses: true,
+      quote_style: QuoteStyle::default(),
+    }
+  }
+}
+
+// Thematic breaks, setext underlines and table delimiter rows are made of dashes that
+// must stay dashes
+fn is_rule_line(line: &str) -> bool {
+  let trimmed = line.trim();
+  trimmed.contains('-')
+    && trimmed
+      .chars()
+      .all(|c| matches!(c, '-' | '|' | ':' | ' ' | '\t' | '*' | '_' | '=' | '+'))
+}
+
+// Byte ranges inside a line (offset by `start`) that hold code spans, math, HTML tags,
+// autolinks, link destinations or bare URLs
+fn inline_protected(line: &str, start: usize, ranges: &mut Vec<Range<usize>>) {
+  let bytes = line.as_bytes();
+  let mut i = 0;
+  while i < bytes.len() {
+    let rest = &line[i..];
+    if bytes[i] == b'`' {
+      let ticks = rest.bytes().take_while(|b| *b == b'`').count();
+      match rest[ticks..].find(&rest[..ticks]) {
+        Some(close) => {
+          let end = i + ticks + close + ticks;
+          ranges.push(start + i..start + end);
+          i = end;
+        }
+        // An unmatched run of backticks is literal text
+        None => i += ticks,
+      }
+      continue;
+    }
+    let end = match bytes[i] {
+      b'$' => {
+        let dollars = if rest.starts_with("$$") { 2 } else { 1 };
+        let inner = &rest[dollars..];
+        let opens = inner.starts_with(|c: char| !c.is_whitespace());
+        inner
+          .find(&rest[..dollars])
+          .filter(|close| opens && *close > 0 && !inner[..*close].ends_with(char::is_whitespace))
+          .map(|close| i + dollars + close + dollars)
+      }
+      b'<' if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '/' || c == '!') => {
+        rest.find('>').map(|close| i + close + 1)
+      }
+      b']' if rest.starts_with("](") => rest.find(')').map(|close| i + close + 1),
+      b'h' | b'w'
+        if (rest.starts_with("http://")
+          || rest.starts_with("https://")
+          || rest.starts_with("www."))
+          && !line[..i].ends_with(|c: char| c.is_alphanumeric()) =>
+      {
+        Some(i + rest.find(char::is_whitespace).unwrap_or(rest.len()))
+      }
+      _ => None,
+    };
+    match end {
+      Some(end) => {
+        ranges.push(start + i..start + end);
+        i = end;
+      }
+      None => i += rest.chars().next().map_or(1, char::len_utf8),
+    }
+  }
+}
+
+// Length of the YAML frontmatter block, if the document starts with a closed one
+pub(crate) fn frontmatter_end(content: &str) -> Option<usize> {
+  let mut lines = content.split_inclusive('\n');
+  if lines.next()?.trim_end() != "---" {
+    return None;
+  }
+  let mut end = content.find('\n')? + 1;
+  for line in lines {
+    end += line.len();
+    if matches!(line.trim_end(), "---" | "...") {
+      return Some(end);
+    }
+  }
+  None
+}
+
+// Byte ranges smartening leaves alone: frontmatter, fenced code and math blocks, rule lines
+// and the inline constructs of inline_protected
+pub(crate) fn protected_ranges(content: &str) -> Vec<Range<usize>> {
+  let mut ranges = Vec::new();
+  let mut offset = 0;
+  if let Some(end) = frontmatter_end(content) {
+    ranges.push(0..end);
+    offset = end;
+  }
+  let lines = content[offset..].split_inclusive('\n');
+
+  // The fence or `$$` that opened the block we're in
+  let mut block: Option<(&str, usize)> = None;
+  for line in lines {
+    let trimmed = line.trim();
+    let line_range = offset..offset + line.len();
+    offset += line.len();
+    if let Some((marker, block_start)) = block {
+      if trimmed.starts_with(marker) {
+        ranges.push(block_start..line_range.end);
+        block = None;
+      }
+      continue;
+    }
+    let marker = ["```", "~~~", "$$"]
+      .into_iter()
+      .find(|marker| trimmed.starts_with(marker));
+    match marker {
+      // `$$ x $$` on one line is inline math
+      Some("$$") if trimmed.len() > 2 && trimmed.ends_with("$$") => {}
+      Some(marker) => {
+        block = Some((marker, line_range.start));
+        continue;
+      }
+      None => {}
+    }
+    if is_rule_line(line) {
+      ranges.push(line_range);
+    } else {
+      inline_protected(line, line_range.start, &mut ranges);
+    }
+  }
+  // An unclosed fence runs to the end
+  if let Some((_, block_start)) = block {
+    ranges.push(block_start..content.len());
+  }
+  ranges
+}
+
+// Characters looked past when deciding whether a quote opens or closes, so `*"quoted"*`
+// and `"*emphasis*"` get the right marks
+fn is_transparent(c: char) -> bool {
+  matches!(
+    c,
+    '*' | '_' | '~' | '"' | '\'' | '“' | '”' | '‘' | '’' | '„' | '‚' | '«' | '»' | '‹' | '›'
+  )
+}
+
+// Whether a quote preceded by `before` opens a quotation
+fn opens_quote(before: &str) -> bool {
+  match before.chars().rev().find(|c| !is_transparent(*c)) {
+    None => true,
+    Some(c) => c.is_whitespace() || matches!(c, '(' | '[' | '{' | '<' | '—' | '–' | '-' | '/'),
+  }
+}
+
+// Curly quotes, dashes and ellipses outside code, math, URLs, HTML and frontmatter
+pub fn smarten(content: &str, options: &TypographyOptions) -> String {
+  let protected = protected_ranges(content);
+  let (open_double, close_double, open_single, close_single) = options.quote_style.marks();
+  let mut result = String::with_capacity(content.len());
+  let mut next_range = 0;
+  let mut i = 0;
+  while i < content.len() {
+    while next_range < protected.len() && protected[next_range].end <= i {
+      next_range += 1;
+    }
+    if let Some(range) = protected.get(next_range).filter(|range| range.start <= i) {
+      result.push_str(&content[i..range.end]);
+      i = range.end;
+      continue;
+    }
+
+    let rest = &content[i..];
+    let c = rest.chars().next().unwrap_or_default();
+    let before = &content[..i];
+    let after = rest[c.len_utf8()..].chars().next();
+    let run = |mark: char| rest.chars().take_while(|c| *c == mark).count();
+    match c {
+      '"' if options.quotes => {
+        result.push(if opens_quote(before) {
+          open_double
+        } else {
+          close_double
+        });
+      }
+      '\'' if options.quotes => {
+        let in_word =
+          before.ends_with(char::is_alphanumeric) && after.is_some_and(char::is_alphanumeric);
+        let mark = if in_word {
+          // don't, rock'n'roll
+          '’'
+        } else if opens_quote(before) {
+          // '90s is an abbreviation, not a quotation
+          if after.is_some_and(|c| c.is_ascii_digit()) {
+            '’'
+          } else {
+            open_single
+          }
+        } else {
+          close_single
+        };
+        result.push(mark);
+      }
+      '-' if options.dashes => {
+        let dashes = run('-');
+        match dashes {
+          2 => result.push('–'),
+          3 => result.push('—'),
+          _ => result.push_str(&rest[..dashes]),
+        }
+        i += dashes;
+        continue;
+      }
+      '.' if options.ellipses => {
+        let dots = run('.');
+        if dots == 3 {
+          result.push('…');
+        } else {
+          result.push_str(&rest[..dots]);
+        }
+        i += dots;
+        continue;
+      }
+      _ => result.push(c),
+    }
+    i += c.len_utf8();
+  }
+  result
+}
+
+// Back to plain ASCII punctuation, for pasting into places that mangle anything else
+pub fn straighten(content: &str) -> String {
+  let mut result = String::with_capacity(content.len());
+  for c in content.chars() {
+    match c {
+      '“' | '”' | '„' | '«' | '»' => result.push('"'),
+      '‘' | '’' | '‚' | '‹' | '›' => result.push('\''),
+      '—' => result.push_str("---"),
+      '–' => result.push_str("--"),
+      '…' => result.push_str("..."),
+      _ => result.push(c),
+    }
+  }
+  result
+}
+
+#[tauri::command]
+pub async fn smarten_typography(
+  content: String,
+  options: Option<TypographyOptions>,
+) -> CommandResult<String> {
+  Ok(smarten(&content, &options.unwrap_or_default()))
+}
+
+#[tauri::command]
+pub async fn straighten_typography(content: String) -> CommandResult<String> {
+  Ok(straighten(&content))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn smart(content: &str) -> String {
+    smarten(content, &TypographyOptions::default())
+  }
+
+  #[test]
+  fn test_quotes_dashes_and_ellipses() {
+    assert_eq!(
+      smart("\"Hello,\" she said -- it's 'fine'... --- right?"),
+      "“Hello,” she said – it’s ‘fine’… — right?"
+    );
+    assert_eq!(smart("Wait.... what"), "Wait.... what");
+  }
+
+  #[test]
+  fn test_apostrophes_inside_words() {
+    assert_eq!(
+      smart("Don't stop rock'n'roll, the '90s dogs' bowls"),
+      "Don’t stop rock’n’roll, the ’90s dogs’ bowls"
+    );
+  }
+
+  #[test]
+  fn test_quotes_next_to_emphasis() {
+    assert_eq!(smart("He said *\"go\"* now"), "He said *“go”* now");
+    assert_eq!(
+      smart("\"*Really*\" and **'this'**"),
+      "“*Really*” and **‘this’**"
+    );
+    assert_eq!(smart("(\"_nested 'quote'_\")"), "(“_nested ‘quote’_”)");
+  }
+
+  #[test]
+  fn test_code_math_urls_and_frontmatter_are_untouched() {
+    let content = "---\ntitle: \"Don't\"\n---\n\nUse `\"raw\" -- x` and $a--b$ or \
+                   https://example.com/a--b...c \"ok\"\n\n```\nlet s = \"it's\";\n```\n\n\
+                   $$\nx -- y\n$$\n\n[link](https://example.com/'x') <span title=\"x--y\">hi</span>\n";
+    assert_eq!(
+      smart(content),
+      "---\ntitle: \"Don't\"\n---\n\nUse `\"raw\" -- x` and $a--b$ or \
+       https://example.com/a--b...c “ok”\n\n```\nlet s = \"it's\";\n```\n\n\
+       $$\nx -- y\n$$\n\n[link](https://example.com/'x') <span title=\"x--y\">hi</span>\n"
+    );
+  }
+
+  #[test]
+  fn test_rules_and_tables_keep_their_dashes() {
+    let content = "Title\n---\n\n| a | b |\n|---|:---:|\n\n- item -- note\n\n***\n";
+    assert_eq!(
+      smart(content),
+      "Title\n---\n\n| a | b |\n|---|:---:|\n\n- item – note\n\n***\n"
+    );
+  }
+
+  #[test]
+  fn test_quote_styles_and_options() {
+    let german = TypographyOptions {
+      quote_style: QuoteStyle::German,
+      ..TypographyOptions::default()
+    };
+    assert_eq!(smarten("\"Ja\" 'so'", &german), "„Ja“ ‚so‘");
+    let french = TypographyOptions {
+      quote_style: QuoteStyle::French,
+      dashes: false,
+      ..TypographyOptions::default()
+    };
+    assert_eq!(smarten("\"Oui\" -- non", &french), "«Oui» -- non");
+  }
+
+  #[test]
+  fn test_straighten_reverses_smarten() {
+    let text = "\"Hello,\" she said -- it's 'fine'... --- right?";
+    assert_eq!(straighten(&smart(text)), text);
+    assert_eq!(straighten("„Ja“ «oui» ‹non›"), "\"Ja\" \"oui\" 'non'");
+  }
+}
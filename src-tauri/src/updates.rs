@@ -0,0 +1,196 @@
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::{CommandError, CommandResult};
+use crate::settings::SettingsState;
+
+// Event emitted with an UpdateStatus after a check
+pub const UPDATE_STATUS_EVENT: &str = "update-status";
+
+// Event emitted with an UpdateProgress while an update downloads
+pub const UPDATE_PROGRESS_EVENT: &str = "update-progress";
+
+// Outcome of a check for updates
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum UpdateStatus {
+  UpToDate {
+    version: String,
+  },
+  Available {
+    version: String,
+    notes: Option<String>,
+  },
+  Error {
+    message: String,
+  },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpdateProgress {
+  pub downloaded: u64,
+  // None if the server didn't send a length
+  pub total: Option<u64>,
+}
+
+// The updater plugin is only registered when the build has `plugins.updater` configured (it
+// needs the release signing key), so everything here checks `enabled` first
+pub struct UpdaterState {
+  pub enabled: bool,
+  // The update found by the last check, installed by install_update
+  #[cfg(desktop)]
+  pending: Mutex<Option<tauri_plugin_updater::Update>>,
+  #[cfg(not(desktop))]
+  pending: Mutex<Option<()>>,
+}
+
+impl UpdaterState {
+  pub fn new(enabled: bool) -> Self {
+    UpdaterState {
+      enabled,
+      pending: Mutex::new(None),
+    }
+  }
+}
+
+// Automatic checks stay quiet unless there is something to install; a check the user asked
+// for always answers
+fn should_report(manual: bool, status: &UpdateStatus) -> bool {
+  manual || matches!(status, UpdateStatus::Available { .. })
+}
+
+#[cfg(desktop)]
+async fn check(app: &AppHandle) -> UpdateStatus {
+  use tauri_plugin_updater::UpdaterExt;
+
+  let state = app.state::<UpdaterState>();
+  if !state.enabled {
+    return UpdateStatus::Error {
+      message: "Updates aren't available for this build".to_string(),
+    };
+  }
+  let result = match app.updater() {
+    Ok(updater) => updater.check().await,
+    Err(e) => Err(e),
+  };
+  match result {
+    Ok(Some(update)) => {
+      let status = UpdateStatus::Available {
+        version: update.version.clone(),
+        notes: update.body.clone(),
+      };
+      *state.pending.lock().unwrap() = Some(update);
+      status
+    }
+    Ok(None) => UpdateStatus::UpToDate {
+      version: app.package_info().version.to_string(),
+    },
+    Err(e) => UpdateStatus::Error {
+      message: format!("Couldn't check for updates: {}", e),
+    },
+  }
+}
+
+#[cfg(not(desktop))]
+async fn check(_app: &AppHandle) -> UpdateStatus {
+  UpdateStatus::Error {
+    message: "Updates are installed through the app store".to_string(),
+  }
+}
+
+// Check for a new version, emitting UPDATE_STATUS_EVENT as should_report decides. Manual
+// checks come from the menu; automatic ones run at startup.
+pub async fn check_and_report(app: &AppHandle, manual: bool) -> UpdateStatus {
+  let status = check(app).await;
+  if should_report(manual, &status) {
+    let _ = app.emit(UPDATE_STATUS_EVENT, status.clone());
+  } else if let UpdateStatus::Error { message } = &status {
+    log::warn!("Automatic update check failed: {}", message);
+  }
+  status
+}
+
+// Startup check, unless the user turned automatic checks off
+pub fn spawn_automatic_check(app: &AppHandle) {
+  let enabled = app.state::<UpdaterState>().enabled
+    && app
+      .state::<SettingsState>()
+      .0
+      .lock()
+      .unwrap()
+      .check_updates_automatically;
+  if enabled {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+      check_and_report(&app, false).await;
+    });
+  }
+}
+
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle, manual: bool) -> CommandResult<UpdateStatus> {
+  Ok(check_and_report(&app, manual).await)
+}
+
+// Download and install the update found by the last check, then relaunch
+#[cfg(desktop)]
+#[tauri::command]
+pub async fn install_update(app: AppHandle) -> CommandResult<()> {
+  let update = app.state::<UpdaterState>().pending.lock().unwrap().take();
+  let Some(update) = update else {
+    return Err(CommandError::invalid_data(
+      "No update to install, check for updates first",
+    ));
+  };
+  let mut downloaded: u64 = 0;
+  let progress_app = app.clone();
+  update
+    .download_and_install(
+      move |chunk, total| {
+        downloaded += chunk as u64;
+        let _ = progress_app.emit(UPDATE_PROGRESS_EVENT, UpdateProgress { downloaded, total });
+      },
+      || {},
+    )
+    .await
+    .map_err(|e| CommandError::io("Failed to install update", e))?;
+  app.restart();
+}
+
+#[cfg(not(desktop))]
+#[tauri::command]
+pub async fn install_update(_app: AppHandle) -> CommandResult<()> {
+  Err(CommandError::invalid_data(
+    "Updates are installed through the app store",
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_automatic_checks_only_report_available_updates() {
+    let available = UpdateStatus::Available {
+      version: "0.2.0".to_string(),
+      notes: None,
+    };
+    let up_to_date = UpdateStatus::UpToDate {
+      version: "0.1.0".to_string(),
+    };
+    let error = UpdateStatus::Error {
+      message: "offline".to_string(),
+    };
+
+    assert!(should_report(false, &available));
+    assert!(!should_report(false, &up_to_date));
+    assert!(!should_report(false, &error));
+    assert!(should_report(true, &up_to_date));
+    assert!(should_report(true, &error));
+    assert_eq!(
+      serde_json::to_value(&available).unwrap(),
+      serde_json::json!({"status": "available", "version": "0.2.0", "notes": null})
+    );
+  }
+}
@@ -0,0 +1,279 @@
+use std::path::Path;
+use url::Url;
+
+use crate::access_scope::AccessScopeState;
+use crate::error::{CommandError, CommandResult};
+use crate::settings::SettingsState;
+
+// Schemes a link may use. `file` links must also point inside the access scope.
+const ALLOWED_SCHEMES: &[&str] = &["http", "https", "mailto", "file"];
+
+// Query parameters that only track where a click came from
+const TRACKING_PARAMS: &[&str] = &["fbclid", "gclid", "dclid", "msclkid", "mc_cid", "mc_eid"];
+const TRACKING_PREFIXES: &[&str] = &["utm_"];
+
+// Endings of file names that would otherwise pass for a domain (`notes.md` is a valid
+// Moldovan domain, but pasting it is much more likely to mean the file)
+const FILE_EXTENSIONS: &[&str] = &[
+  "md", "markdown", "txt", "rs", "js", "ts", "tsx", "py", "json", "yaml", "yml", "toml", "png",
+  "jpg", "jpeg", "gif", "svg", "pdf", "zip", "html", "css", "sh",
+];
+
+fn invalid_url(input: &str, reason: impl Into<String>) -> CommandError {
+  CommandError::InvalidUrl {
+    url: input.to_string(),
+    reason: reason.into(),
+  }
+}
+
+// The scheme `text` starts with, lowercased. `localhost:3000` is a host and port, not a
+// scheme.
+fn scheme(text: &str) -> Option<String> {
+  let (scheme, rest) = text.split_once(':')?;
+  let mut chars = scheme.chars();
+  let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+    && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+  let port = rest
+    .split(['/', '?', '#'])
+    .next()
+    .is_some_and(|port| !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()));
+  (valid && !port).then(|| scheme.to_ascii_lowercase())
+}
+
+// Whether `host` (without a port) looks like a host name someone would paste
+fn is_probable_host(host: &str) -> bool {
+  if host.eq_ignore_ascii_case("localhost") {
+    return true;
+  }
+  let labels: Vec<&str> = host.split('.').collect();
+  if labels.len() == 4 && labels.iter().all(|label| label.parse::<u8>().is_ok()) {
+    return true;
+  }
+  let Some(tld) = labels.last() else {
+    return false;
+  };
+  labels.len() >= 2
+    && labels.iter().all(|label| {
+      !label.is_empty()
+        && !label.starts_with('-')
+        && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+    })
+    && tld.chars().count() >= 2
+    && tld.chars().all(char::is_alphabetic)
+    && !FILE_EXTENSIONS.contains(&tld.to_lowercase().as_str())
+}
+
+// Whether pasted text is a single URL: one with a scheme, or a bare domain or localhost
+// with an optional port and path. For the editor's paste heuristic, so it errs on the side
+// of plain text.
+pub fn looks_like_url(text: &str) -> bool {
+  let text = text.trim();
+  if text.is_empty() || text.chars().any(char::is_whitespace) {
+    return false;
+  }
+  if let Some(scheme) = scheme(text) {
+    return match scheme.as_str() {
+      "mailto" => text.contains('@'),
+      "http" | "https" | "file" => text[scheme.len() + 1..].starts_with("//"),
+      _ => false,
+    };
+  }
+  if text.contains('@') {
+    return false;
+  }
+  let authority = text.split(['/', '?', '#']).next().unwrap_or_default();
+  let host = match authority.rsplit_once(':') {
+    Some((host, port)) if !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => host,
+    Some(_) => return false,
+    None => authority,
+  };
+  is_probable_host(host)
+}
+
+// `https://` for a URL typed without a scheme; `http://` for localhost and loopback
+// addresses, which rarely serve https
+fn with_scheme(text: &str) -> String {
+  if scheme(text).is_some() {
+    return text.to_string();
+  }
+  let text = text.trim_start_matches('/');
+  let host = text
+    .split(['/', '?', '#', ':'])
+    .next()
+    .unwrap_or_default()
+    .to_ascii_lowercase();
+  if host == "localhost" || host.starts_with("127.") {
+    format!("http://{}", text)
+  } else {
+    format!("https://{}", text)
+  }
+}
+
+fn is_tracking_param(name: &str) -> bool {
+  let name = name.to_ascii_lowercase();
+  TRACKING_PARAMS.contains(&name.as_str())
+    || TRACKING_PREFIXES
+      .iter()
+      .any(|prefix| name.starts_with(prefix))
+}
+
+// `query` without tracking parameters, keeping the others exactly as they were encoded.
+// None when nothing is left.
+fn strip_tracking(query: &str) -> Option<String> {
+  let kept: Vec<&str> = query
+    .split('&')
+    .filter(|pair| {
+      let name = pair.split('=').next().unwrap_or_default();
+      !pair.is_empty() && !is_tracking_param(name)
+    })
+    .collect();
+  (!kept.is_empty()).then(|| kept.join("&"))
+}
+
+// Clean up a URL for a link: trim it, add a scheme when it has none, punycode an
+// international host, percent-encode characters a URL can't contain and (with
+// `strip_tracking`) drop tracking parameters. Fails for schemes other than ALLOWED_SCHEMES
+// and for `file` URLs `file_allowed` refuses.
+fn normalize(
+  input: &str,
+  strip_tracking_params: bool,
+  file_allowed: impl Fn(&Path) -> bool,
+) -> CommandResult<String> {
+  let text = input.trim();
+  let text = text
+    .strip_prefix('<')
+    .and_then(|text| text.strip_suffix('>'))
+    .unwrap_or(text)
+    .trim();
+  if text.is_empty() {
+    return Err(invalid_url(input, "The URL is empty"));
+  }
+  let mut url = Url::parse(&with_scheme(text)).map_err(|e| invalid_url(input, e.to_string()))?;
+  if !ALLOWED_SCHEMES.contains(&url.scheme()) {
+    return Err(invalid_url(
+      input,
+      format!("Links to {}: URLs are not allowed", url.scheme()),
+    ));
+  }
+  if url.scheme() == "file" {
+    let allowed = url.to_file_path().is_ok_and(|path| file_allowed(&path));
+    if !allowed {
+      return Err(invalid_url(
+        input,
+        "File links must point inside an open folder",
+      ));
+    }
+  }
+  if strip_tracking_params {
+    if let Some(query) = url.query().map(str::to_string) {
+      url.set_query(strip_tracking(&query).as_deref());
+    }
+  }
+  Ok(url.to_string())
+}
+
+// For pasting a URL over selected text to make `[selection](url)`: the URL cleaned up as
+// by `normalize`, or an invalid_url error saying why it can't be linked
+#[tauri::command]
+pub async fn normalize_url(
+  settings: tauri::State<'_, SettingsState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  input: String,
+) -> CommandResult<String> {
+  let strip_tracking_params = settings.0.lock().unwrap().strip_tracking_params;
+  let scope = scope.0.lock().unwrap();
+  normalize(&input, strip_tracking_params, |path| scope.is_allowed(path))
+}
+
+// For the paste heuristic: whether pasting `text` over a selection should make a link
+#[tauri::command]
+pub async fn is_probable_url(text: String) -> CommandResult<bool> {
+  Ok(looks_like_url(&text))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn clean(input: &str) -> CommandResult<String> {
+    normalize(input, true, |path| path.starts_with("/notes"))
+  }
+
+  #[test]
+  fn test_bare_domains_get_https() {
+    assert_eq!(clean("  example.com ").unwrap(), "https://example.com/");
+    assert_eq!(
+      clean("www.example.com/a b?q=1").unwrap(),
+      "https://www.example.com/a%20b?q=1"
+    );
+    assert_eq!(
+      clean("<HTTP://Example.COM/Path>").unwrap(),
+      "http://example.com/Path"
+    );
+    assert!(looks_like_url("example.com/docs"));
+    assert!(looks_like_url("https://example.com"));
+    assert!(!looks_like_url("notes.md"));
+    assert!(!looks_like_url("see example.com"));
+    assert!(!looks_like_url("version 1.2"));
+    assert!(!looks_like_url("me@example.com"));
+  }
+
+  #[test]
+  fn test_localhost_keeps_its_port() {
+    assert_eq!(
+      clean("localhost:3000/api").unwrap(),
+      "http://localhost:3000/api"
+    );
+    assert_eq!(clean("127.0.0.1:8080").unwrap(), "http://127.0.0.1:8080/");
+    assert!(looks_like_url("localhost:3000"));
+    assert!(looks_like_url("192.168.1.10:8080/status"));
+    assert!(!looks_like_url("localhost:abc"));
+  }
+
+  #[test]
+  fn test_unicode_domains_are_punycoded() {
+    assert_eq!(
+      clean("bücher.example/straße").unwrap(),
+      "https://xn--bcher-kva.example/stra%C3%9Fe"
+    );
+    assert!(looks_like_url("bücher.de"));
+  }
+
+  #[test]
+  fn test_tracking_parameters_are_dropped() {
+    assert_eq!(
+      clean("https://example.com/a?utm_source=x&id=7&fbclid=abc#top").unwrap(),
+      "https://example.com/a?id=7#top"
+    );
+    assert_eq!(
+      clean("https://example.com/?UTM_medium=email").unwrap(),
+      "https://example.com/"
+    );
+    assert_eq!(
+      normalize("https://example.com/?utm_source=x", false, |_| true).unwrap(),
+      "https://example.com/?utm_source=x"
+    );
+    assert_eq!(strip_tracking("a=1&&b=2+3"), Some("a=1&b=2+3".to_string()));
+  }
+
+  #[test]
+  fn test_disallowed_schemes_are_rejected() {
+    for input in [
+      "data:text/html;base64,PHNjcmlwdD4=",
+      "javascript:alert(1)",
+      "ftp://example.com/file",
+      "file:///etc/passwd",
+      "",
+    ] {
+      let error = clean(input).unwrap_err();
+      assert_eq!(error.code(), "invalid_url", "{}", input);
+    }
+    assert!(!looks_like_url("data:text/plain,hi"));
+    assert_eq!(
+      clean("mailto:me@example.com").unwrap(),
+      "mailto:me@example.com"
+    );
+    #[cfg(unix)]
+    assert_eq!(clean("file:///notes/a.md").unwrap(), "file:///notes/a.md");
+  }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::app_store;
+use crate::error::CommandResult;
+use crate::{normalize_recent_path, recent_paths_equal};
+
+// Store key holding where the user left off in each file, most recently saved first
+pub const VIEW_STATES_KEY: &str = "file_view_states";
+
+// Files whose view state we remember; older ones are dropped
+const MAX_VIEW_STATES: usize = 200;
+
+// Where the user left off in a file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileViewState {
+  // Zero-based
+  pub cursor_line: u32,
+  pub cursor_col: u32,
+  // How far down the editor was scrolled, 0.0 to 1.0
+  pub scroll_percent: f64,
+  // Lines of the headings whose sections are folded
+  #[serde(default)]
+  pub folded_sections: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ViewStateEntry {
+  // Normalized like recents, so a renamed or moved file starts fresh
+  pub path: String,
+  pub state: FileViewState,
+}
+
+fn lookup(entries: &[ViewStateEntry], path: &str) -> Option<FileViewState> {
+  entries
+    .iter()
+    .find(|entry| recent_paths_equal(&entry.path, path))
+    .map(|entry| entry.state.clone())
+}
+
+// Put the state at the front, evicting the least recently saved beyond the cap
+fn remember(entries: &mut Vec<ViewStateEntry>, path: &str, state: FileViewState) {
+  entries.retain(|entry| !recent_paths_equal(&entry.path, path));
+  entries.insert(
+    0,
+    ViewStateEntry {
+      path: path.to_string(),
+      state,
+    },
+  );
+  entries.truncate(MAX_VIEW_STATES);
+}
+
+fn load_entries(app: &AppHandle) -> CommandResult<Vec<ViewStateEntry>> {
+  app_store::read_list(app, VIEW_STATES_KEY)
+}
+
+fn save_entries(app: &AppHandle, entries: &[ViewStateEntry]) -> CommandResult<()> {
+  app_store::write_list(app, VIEW_STATES_KEY, "view states", entries)
+}
+
+// The saved view state of a file, if any (errors are logged; the file opens at the top)
+pub fn load_view_state(app: &AppHandle, path: &str) -> Option<FileViewState> {
+  match load_entries(app) {
+    Ok(entries) => lookup(&entries, &normalize_recent_path(path)),
+    Err(e) => {
+      log::warn!("Failed to load view state: {}", e);
+      None
+    }
+  }
+}
+
+// Forget the view state of a file, or of every file when `path` is None
+pub fn forget_view_state(app: &AppHandle, path: Option<&str>) {
+  let result = load_entries(app).and_then(|mut entries| {
+    match path {
+      Some(path) => {
+        let path = normalize_recent_path(path);
+        entries.retain(|entry| !recent_paths_equal(&entry.path, &path));
+      }
+      None => entries.clear(),
+    }
+    save_entries(app, &entries)
+  });
+  if let Err(e) = result {
+    log::error!("Failed to forget view state: {}", e);
+  }
+}
+
+#[tauri::command]
+pub async fn save_file_view_state(
+  app: AppHandle,
+  path: String,
+  state: FileViewState,
+) -> CommandResult<()> {
+  let mut entries = load_entries(&app)?;
+  remember(&mut entries, &normalize_recent_path(&path), state);
+  save_entries(&app, &entries)
+}
+
+#[tauri::command]
+pub async fn get_file_view_state(
+  app: AppHandle,
+  path: String,
+) -> CommandResult<Option<FileViewState>> {
+  Ok(lookup(&load_entries(&app)?, &normalize_recent_path(&path)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn state(line: u32) -> FileViewState {
+    FileViewState {
+      cursor_line: line,
+      cursor_col: 0,
+      scroll_percent: 0.5,
+      folded_sections: Vec::new(),
+    }
+  }
+
+  #[test]
+  fn test_remember_replaces_and_evicts_least_recent() {
+    let mut entries = Vec::new();
+    for i in 0..MAX_VIEW_STATES as u32 {
+      remember(&mut entries, &format!("/notes/{}.md", i), state(i));
+    }
+    remember(&mut entries, "/notes/0.md", state(1000));
+    assert_eq!(entries.len(), MAX_VIEW_STATES);
+    assert_eq!(lookup(&entries, "/notes/0.md"), Some(state(1000)));
+
+    // /notes/1.md is now the least recently saved
+    remember(&mut entries, "/notes/new.md", state(1));
+    assert_eq!(entries.len(), MAX_VIEW_STATES);
+    assert_eq!(lookup(&entries, "/notes/1.md"), None);
+    assert_eq!(lookup(&entries, "/notes/2.md"), Some(state(2)));
+  }
+
+  #[test]
+  fn test_folded_sections_default_to_empty() {
+    let state: FileViewState = serde_json::from_value(
+      serde_json::json!({"cursor_line": 3, "cursor_col": 4, "scroll_percent": 0.25}),
+    )
+    .unwrap();
+    assert!(state.folded_sections.is_empty());
+  }
+}
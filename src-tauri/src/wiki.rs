@@ -0,0 +1,435 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::error::{CommandError, CommandResult};
+use crate::filename;
+use crate::settings::FilenameSeparator;
+
+// A folder containing one of these is the root of a workspace (a vault, a repository)
+const WORKSPACE_MARKERS: &[&str] = &[".markdowner", ".obsidian", ".git"];
+
+// Files that can be the target of a wiki link
+const NOTE_EXTENSIONS: &[&str] = &["md", "markdown", "mdx"];
+
+// Folders never worth indexing
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target"];
+
+// Stop indexing huge trees (e.g. a home directory that happens to contain `.git`)
+const MAX_INDEXED_FILES: usize = 20_000;
+
+// Only the start of a note is read when looking for its title
+const TITLE_SCAN_BYTES: u64 = 8 * 1024;
+
+// Only the watched workspace gets its index dropped on changes, so an index is also rebuilt
+// once it's this old to pick up notes created or renamed outside the app
+pub const INDEX_MAX_AGE: Duration = Duration::from_secs(30);
+
+// Notes of one workspace, keyed by normalized name (file stem) and title (first heading)
+struct WikiIndex {
+  built_at: Instant,
+  names: HashMap<String, Vec<PathBuf>>,
+  titles: HashMap<String, Vec<PathBuf>>,
+}
+
+// Indexes by workspace root, built on first use so lookups while typing are hash lookups
+#[derive(Default)]
+pub struct WikiIndexState(Mutex<HashMap<PathBuf, WikiIndex>>);
+
+impl WikiIndexState {
+  // Forget the index of the workspace containing `path`, e.g. after a note was written
+  pub fn invalidate(&self, path: &Path) {
+    let mut indexes = self.0.lock().unwrap();
+    indexes.retain(|root, _| !path.starts_with(root));
+  }
+}
+
+// What part of a note matched the link text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WikiLinkMatch {
+  Name,
+  Title,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WikiLinkCandidate {
+  pub path: String,
+  pub matched: WikiLinkMatch,
+  // Folder steps between the linking note and this one
+  pub distance: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum WikiLinkResolution {
+  Resolved { path: String },
+  // Several notes match equally well, best first
+  Ambiguous { candidates: Vec<WikiLinkCandidate> },
+  Missing,
+}
+
+// Case-insensitive key that treats `Café` typed with a combining accent and the
+// precomposed form the same, and ignores runs of whitespace
+pub(crate) fn normalize_key(text: &str) -> String {
+  let folded: String = text.nfkc().collect::<String>().to_lowercase();
+  folded.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// `Note#Heading|alias` -> `Note`
+pub(crate) fn link_target(link_text: &str) -> &str {
+  let target = link_text.split('|').next().unwrap_or(link_text);
+  target.split('#').next().unwrap_or(target).trim()
+}
+
+// Nearest ancestor of `dir` with a workspace marker, else `dir` itself
+pub fn workspace_root(dir: &Path) -> PathBuf {
+  dir
+    .ancestors()
+    .find(|ancestor| {
+      WORKSPACE_MARKERS
+        .iter()
+        .any(|marker| ancestor.join(marker).exists())
+    })
+    .unwrap_or(dir)
+    .to_path_buf()
+}
+
+// workspace_root, unless the marker is above the access scope: indexing from there would
+// read folders the user never approved, so `dir` is used instead
+pub fn scoped_workspace_root(scope: &AccessScope, dir: &Path) -> PathBuf {
+  Some(workspace_root(dir))
+    .filter(|root| scope.is_allowed(root))
+    .unwrap_or_else(|| dir.to_path_buf())
+}
+
+pub fn is_note(path: &Path) -> bool {
+  path
+    .extension()
+    .and_then(|e| e.to_str())
+    .is_some_and(|e| NOTE_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+fn note_title(path: &Path) -> Option<String> {
+  let mut head = String::new();
+  std::fs::File::open(path)
+    .ok()?
+    .take(TITLE_SCAN_BYTES)
+    .read_to_string(&mut head)
+    .ok()?;
+  filename::first_heading(&head).map(|heading| filename::strip_inline_markdown(&heading))
+}
+
+// A note found by walk_notes
+pub struct NoteFile {
+  pub path: PathBuf,
+  pub modified: Option<SystemTime>,
+}
+
+// Every note under `root`, skipping hidden and dependency folders, up to MAX_INDEXED_FILES
+pub fn walk_notes(root: &Path) -> Vec<NoteFile> {
+  walk_files(root, is_note)
+}
+
+// Every file under `root` that `keep` accepts, skipping hidden and dependency folders, up
+// to MAX_INDEXED_FILES
+pub fn walk_files(root: &Path, keep: impl Fn(&Path) -> bool) -> Vec<NoteFile> {
+  let mut files = Vec::new();
+  let mut pending = vec![root.to_path_buf()];
+  while let Some(dir) = pending.pop() {
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+      continue;
+    };
+    for entry in entries.flatten() {
+      let path = entry.path();
+      let name = entry.file_name().to_string_lossy().to_string();
+      let Ok(file_type) = entry.file_type() else {
+        continue;
+      };
+      if file_type.is_dir() {
+        if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name.as_str()) {
+          pending.push(path);
+        }
+        continue;
+      }
+      if !keep(&path) {
+        continue;
+      }
+      if files.len() >= MAX_INDEXED_FILES {
+        return files;
+      }
+      let modified = entry.metadata().and_then(|m| m.modified()).ok();
+      files.push(NoteFile { path, modified });
+    }
+  }
+  files
+}
+
+fn build_index(root: &Path) -> WikiIndex {
+  let mut index = WikiIndex {
+    built_at: Instant::now(),
+    names: HashMap::new(),
+    titles: HashMap::new(),
+  };
+  for NoteFile { path, .. } in walk_notes(root) {
+    if let Some(stem) = path.file_stem() {
+      let key = normalize_key(&stem.to_string_lossy());
+      index.names.entry(key).or_default().push(path.clone());
+    }
+    if let Some(title) = note_title(&path) {
+      index
+        .titles
+        .entry(normalize_key(&title))
+        .or_default()
+        .push(path);
+    }
+  }
+  index
+}
+
+// Number of folder steps (up, then down) from `from` to `to`
+fn folder_distance(from: &Path, to: &Path) -> usize {
+  let from: Vec<Component> = from.components().collect();
+  let to: Vec<Component> = to.components().collect();
+  let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+  (from.len() - common) + (to.len() - common)
+}
+
+fn resolve_in_index(index: &WikiIndex, target: &str, current_dir: &Path) -> WikiLinkResolution {
+  let key = normalize_key(target);
+  let mut candidates: Vec<WikiLinkCandidate> = Vec::new();
+  let matches = [
+    (WikiLinkMatch::Name, index.names.get(&key)),
+    (WikiLinkMatch::Title, index.titles.get(&key)),
+  ];
+  for (matched, paths) in matches {
+    for path in paths.into_iter().flatten() {
+      let path_str = path.to_string_lossy().to_string();
+      // A note whose name and title both match is listed once, as a name match
+      if candidates.iter().any(|c| c.path == path_str) {
+        continue;
+      }
+      candidates.push(WikiLinkCandidate {
+        distance: folder_distance(current_dir, path.parent().unwrap_or(path)),
+        path: path_str,
+        matched,
+      });
+    }
+  }
+  candidates
+    .sort_by(|a, b| (a.matched, a.distance, &a.path).cmp(&(b.matched, b.distance, &b.path)));
+
+  match candidates.as_slice() {
+    [] => WikiLinkResolution::Missing,
+    [best, second, ..] if (best.matched, best.distance) == (second.matched, second.distance) => {
+      WikiLinkResolution::Ambiguous { candidates }
+    }
+    [best, ..] => WikiLinkResolution::Resolved {
+      path: best.path.clone(),
+    },
+  }
+}
+
+fn resolve(
+  state: &WikiIndexState,
+  scope: &AccessScope,
+  link_text: &str,
+  current_file: &Path,
+) -> CommandResult<WikiLinkResolution> {
+  scope.check(current_file)?;
+  let target = link_target(link_text);
+  if target.is_empty() {
+    return Err(CommandError::invalid_data("Wiki link is empty"));
+  }
+  let current_dir = current_file
+    .parent()
+    .ok_or_else(|| CommandError::invalid_path(current_file, "File has no parent folder"))?;
+  let root = scoped_workspace_root(scope, current_dir);
+
+  let mut indexes = state.0.lock().unwrap();
+  let stale = indexes
+    .get(&root)
+    .is_none_or(|index| index.built_at.elapsed() > INDEX_MAX_AGE);
+  if stale {
+    indexes.insert(root.clone(), build_index(&root));
+  }
+  Ok(resolve_in_index(&indexes[&root], target, current_dir))
+}
+
+// Find the note a `[[link]]` in `current_file` points to: a note in the same workspace whose
+// file name or first heading matches the link text, preferring notes closer to
+// `current_file`
+#[tauri::command]
+pub async fn resolve_wiki_link(
+  state: tauri::State<'_, WikiIndexState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  link_text: String,
+  current_file: String,
+) -> CommandResult<WikiLinkResolution> {
+  let current_file = PathBuf::from(current_file);
+  if !current_file.is_absolute() {
+    return Err(CommandError::invalid_path(
+      &current_file,
+      "File path must be absolute",
+    ));
+  }
+  resolve(&state, &scope.0.lock().unwrap(), &link_text, &current_file)
+}
+
+fn create_note(scope: &AccessScope, directory: &Path, link_text: &str) -> CommandResult<PathBuf> {
+  scope.check(directory)?;
+  let target = link_target(link_text);
+  // Keep the name as typed so the link resolves by name, not only by title
+  let stem = filename::sanitize_file_stem(target, FilenameSeparator::Space)
+    .ok_or_else(|| CommandError::invalid_data("Wiki link has no usable note name"))?;
+  let path = directory.join(format!("{}.md", stem));
+  let mut file = std::fs::OpenOptions::new()
+    .write(true)
+    .create_new(true)
+    .open(&path)
+    .map_err(|e| match e.kind() {
+      std::io::ErrorKind::AlreadyExists => {
+        CommandError::invalid_path(&path, "A note with this name already exists")
+      }
+      _ => CommandError::from_io(&e, &path, "Failed to create note"),
+    })?;
+  std::io::Write::write_all(&mut file, format!("# {}\n", target).as_bytes())
+    .map_err(|e| CommandError::from_io(&e, &path, "Failed to create note"))?;
+  Ok(path)
+}
+
+// Create the missing target of a `[[link]]` in `directory`, titled with the link text
+#[tauri::command]
+pub async fn create_note_for_link(
+  state: tauri::State<'_, WikiIndexState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  link_text: String,
+  directory: String,
+) -> CommandResult<String> {
+  let directory = PathBuf::from(directory);
+  if !directory.is_dir() {
+    return Err(CommandError::NotFound {
+      path: directory.to_string_lossy().to_string(),
+    });
+  }
+  let path = create_note(&scope.0.lock().unwrap(), &directory, &link_text)?;
+  state.invalidate(&path);
+  Ok(path.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  fn note(dir: &Path, relative: &str, content: &str) -> PathBuf {
+    let path = dir.join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, content).unwrap();
+    path
+  }
+
+  #[test]
+  fn test_resolves_by_name_and_title() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join(".git")).unwrap();
+    let current = note(dir.path(), "daily/today.md", "See [[Café Plans]]");
+    let plans = note(dir.path(), "projects/Cafe\u{301} plans.md", "no heading");
+    let meeting = note(dir.path(), "notes/2024-01-02.md", "# Weekly *Meeting*\n");
+    let state = WikiIndexState::default();
+    let scope = AccessScope::with_folders(&[dir.path()]);
+
+    assert_eq!(
+      resolve(&state, &scope, "Café Plans", &current).unwrap(),
+      WikiLinkResolution::Resolved {
+        path: plans.to_string_lossy().to_string()
+      }
+    );
+    assert_eq!(
+      resolve(&state, &scope, "weekly meeting|the meeting", &current).unwrap(),
+      WikiLinkResolution::Resolved {
+        path: meeting.to_string_lossy().to_string()
+      }
+    );
+    assert_eq!(
+      resolve(&state, &scope, "Nowhere", &current).unwrap(),
+      WikiLinkResolution::Missing
+    );
+  }
+
+  #[test]
+  fn test_prefers_closer_notes_and_reports_ties() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join(".markdowner")).unwrap();
+    let current = note(dir.path(), "a/current.md", "");
+    let near = note(dir.path(), "a/ideas.md", "");
+    note(dir.path(), "b/c/ideas.md", "");
+    note(dir.path(), "x/todo.md", "");
+    note(dir.path(), "y/todo.md", "");
+    let state = WikiIndexState::default();
+    let scope = AccessScope::with_folders(&[dir.path()]);
+
+    assert_eq!(
+      resolve(&state, &scope, "ideas", &current).unwrap(),
+      WikiLinkResolution::Resolved {
+        path: near.to_string_lossy().to_string()
+      }
+    );
+    match resolve(&state, &scope, "todo", &current).unwrap() {
+      WikiLinkResolution::Ambiguous { candidates } => assert_eq!(candidates.len(), 2),
+      other => panic!("expected ambiguous, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn test_create_note_for_link() {
+    let dir = TempDir::new().unwrap();
+    let current = note(dir.path(), "current.md", "");
+    let state = WikiIndexState::default();
+    let scope = AccessScope::with_folders(&[dir.path()]);
+    assert_eq!(
+      resolve(&state, &scope, "New Idea", &current).unwrap(),
+      WikiLinkResolution::Missing
+    );
+
+    let path = create_note(&scope, dir.path(), "New Idea#Details").unwrap();
+    assert_eq!(path, dir.path().join("New Idea.md"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "# New Idea\n");
+    assert!(create_note(&scope, dir.path(), "New Idea").is_err());
+
+    state.invalidate(&path);
+    assert!(matches!(
+      resolve(&state, &scope, "new idea", &current).unwrap(),
+      WikiLinkResolution::Resolved { .. }
+    ));
+  }
+
+  #[test]
+  fn test_paths_outside_the_scope_are_denied() {
+    let dir = TempDir::new().unwrap();
+    let current = note(dir.path(), "notes/current.md", "");
+    note(dir.path(), "private/secret.md", "");
+    fs::create_dir(dir.path().join(".git")).unwrap();
+    let state = WikiIndexState::default();
+    let empty = AccessScope::default();
+
+    let error = resolve(&state, &empty, "secret", &current).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    let error = create_note(&empty, dir.path(), "New Idea").unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    assert!(!dir.path().join("New Idea.md").exists());
+
+    // The marker is above the approved folder, so only that folder is searched
+    let scope = AccessScope::with_folders(&[dir.path().join("notes")]);
+    assert_eq!(
+      resolve(&state, &scope, "secret", &current).unwrap(),
+      WikiLinkResolution::Missing
+    );
+  }
+}
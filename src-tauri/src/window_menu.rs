@@ -0,0 +1,140 @@
+use tauri::menu::{CheckMenuItem, PredefinedMenuItem};
+use tauri::{AppHandle, Manager, Window, WindowEvent};
+
+use crate::dynamic_menu;
+
+// The Window menu. Lists the document windows as `focus_window:<label>` items.
+pub const MENU_ID: &str = "window_menu";
+pub const ITEM_ID_PREFIX: &str = "focus_window:";
+
+const UNTITLED: &str = "Untitled";
+
+// A window as listed in the Window menu
+#[derive(Debug, Clone, PartialEq)]
+struct WindowEntry {
+  label: String,
+  title: String,
+  focused: bool,
+}
+
+// Windows in the order they were opened: `main`, then `main-2`, `main-3`...
+fn sort_windows(windows: &mut [WindowEntry]) {
+  windows.sort_by_key(|window| {
+    let number = window
+      .label
+      .rsplit_once('-')
+      .and_then(|(_, n)| n.parse::<u32>().ok())
+      .unwrap_or(1);
+    (number, window.label.clone())
+  });
+}
+
+fn menu_title(title: &str) -> &str {
+  match title.trim() {
+    "" => UNTITLED,
+    title => title,
+  }
+}
+
+// Rebuild the Window menu from the open windows, checking the focused one. `destroyed` is a
+// window that's going away but may still be listed by the app.
+pub fn update_menu(app: &AppHandle, destroyed: Option<&str>) -> tauri::Result<()> {
+  let mut windows: Vec<WindowEntry> = app
+    .webview_windows()
+    .into_iter()
+    .filter(|(label, _)| Some(label.as_str()) != destroyed)
+    .map(|(label, window)| WindowEntry {
+      title: window.title().unwrap_or_default(),
+      focused: window.is_focused().unwrap_or(false),
+      label,
+    })
+    .collect();
+  sort_windows(&mut windows);
+
+  dynamic_menu::rebuild_submenu(app, MENU_ID, |submenu| {
+    submenu.append(&PredefinedMenuItem::minimize(app, Some("Minimize"))?)?;
+    submenu.append(&PredefinedMenuItem::close_window(
+      app,
+      Some("Close Window"),
+    )?)?;
+    #[cfg(target_os = "macos")]
+    {
+      submenu.append(&PredefinedMenuItem::separator(app)?)?;
+      submenu.append(&PredefinedMenuItem::bring_all_to_front(
+        app,
+        Some("Bring All to Front"),
+      )?)?;
+      // Needs native window tabs, which document windows don't use yet
+      submenu.append(&tauri::menu::MenuItem::with_id(
+        app,
+        "merge_all_windows",
+        "Merge All Windows",
+        false,
+        None::<&str>,
+      )?)?;
+    }
+    if !windows.is_empty() {
+      submenu.append(&PredefinedMenuItem::separator(app)?)?;
+    }
+    for window in windows {
+      submenu.append(&CheckMenuItem::with_id(
+        app,
+        format!("{}{}", ITEM_ID_PREFIX, window.label),
+        menu_title(&window.title),
+        true,
+        window.focused,
+        None::<&str>,
+      )?)?;
+    }
+    Ok(())
+  })
+}
+
+// Bring a window picked in the Window menu to the front
+pub fn focus_window(app: &AppHandle, label: &str) {
+  if let Some(window) = app.get_webview_window(label) {
+    let _ = window.unminimize();
+    let _ = window.show();
+    let _ = window.set_focus();
+  }
+}
+
+// Keep the Window menu's list and checkmark current
+pub fn handle_window_event(window: &Window, event: &WindowEvent) {
+  let destroyed = match event {
+    WindowEvent::Focused(true) => None,
+    WindowEvent::Destroyed => Some(window.label()),
+    _ => return,
+  };
+  if let Err(e) = update_menu(window.app_handle(), destroyed) {
+    log::error!("Failed to update the Window menu: {}", e);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn entry(label: &str) -> WindowEntry {
+    WindowEntry {
+      label: label.to_string(),
+      title: String::new(),
+      focused: false,
+    }
+  }
+
+  #[test]
+  fn test_windows_listed_in_opening_order() {
+    let mut windows = vec![
+      entry("main-10"),
+      entry("main-2"),
+      entry("main"),
+      entry("main-3"),
+    ];
+    sort_windows(&mut windows);
+    let labels: Vec<&str> = windows.iter().map(|w| w.label.as_str()).collect();
+    assert_eq!(labels, vec!["main", "main-2", "main-3", "main-10"]);
+    assert_eq!(menu_title("  "), "Untitled");
+    assert_eq!(menu_title("todo.md — notes"), "todo.md — notes");
+  }
+}
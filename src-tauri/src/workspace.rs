@@ -0,0 +1,648 @@
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use regex::RegexSet;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::error::{CommandError, CommandResult};
+use crate::settings::SettingsState;
+use crate::{access_scope, batch_rename, file_finder, recent_workspaces, safe_mode, tags, wiki};
+
+// Sent to every window with a WorkspaceChanges batch
+pub const WORKSPACE_CHANGED_EVENT: &str = "workspace-changed";
+
+// A batch is sent once no change came in for this long...
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+// ...or after this long, so a change that never settles (a long build, a big checkout)
+// still shows up as it goes
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(2);
+
+// Folders whose changes never matter to the tree, whatever the settings say
+const ALWAYS_IGNORED: &[&str] = &[".git", "node_modules"];
+
+// Changes under a watched workspace since the last batch. A path is in at most one list.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WorkspaceChanges {
+  pub root: String,
+  pub created: Vec<String>,
+  pub modified: Vec<String>,
+  pub removed: Vec<String>,
+  pub renamed: Vec<RenamedPath>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RenamedPath {
+  pub from: String,
+  pub to: String,
+}
+
+// A change as reported by the watcher, before coalescing
+#[derive(Debug, Clone, PartialEq)]
+enum RawChange {
+  Created(PathBuf),
+  Modified(PathBuf),
+  Removed(PathBuf),
+  Renamed(PathBuf, PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NetChange {
+  Created,
+  Modified,
+  Removed,
+}
+
+// What identifies a file across a rename the watcher reported as a removal and a creation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct FileId {
+  inode: u64,
+  size: u64,
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &std::fs::Metadata) -> Option<FileId> {
+  use std::os::unix::fs::MetadataExt;
+  Some(FileId {
+    inode: metadata.ino(),
+    size: metadata.len(),
+  })
+}
+
+// Without inodes a size match alone is too weak; the platform watcher reports renames there
+#[cfg(not(unix))]
+fn file_id(_metadata: &std::fs::Metadata) -> Option<FileId> {
+  None
+}
+
+fn path_id(path: &Path) -> Option<FileId> {
+  std::fs::symlink_metadata(path)
+    .ok()
+    .and_then(|metadata| file_id(&metadata))
+}
+
+// Net effect of a burst of changes: a file created and removed again is no change, one
+// removed and created again was modified, and a chain of renames is one rename
+#[derive(Debug, Default)]
+struct Batch {
+  // In the order first seen
+  changes: Vec<(PathBuf, NetChange)>,
+  renames: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Batch {
+  fn net(&self, path: &Path) -> Option<NetChange> {
+    self
+      .changes
+      .iter()
+      .find(|(p, _)| p == path)
+      .map(|(_, change)| *change)
+  }
+
+  fn set(&mut self, path: &Path, change: Option<NetChange>) {
+    let index = self.changes.iter().position(|(p, _)| p == path);
+    match (index, change) {
+      (Some(index), Some(change)) => self.changes[index].1 = change,
+      (Some(index), None) => {
+        self.changes.remove(index);
+      }
+      (None, Some(change)) => self.changes.push((path.to_path_buf(), change)),
+      (None, None) => {}
+    }
+  }
+
+  fn add(&mut self, change: RawChange) {
+    match change {
+      RawChange::Created(path) => {
+        let net = match self.net(&path) {
+          Some(NetChange::Removed) | Some(NetChange::Modified) => NetChange::Modified,
+          _ => NetChange::Created,
+        };
+        self.set(&path, Some(net));
+      }
+      RawChange::Modified(path) => {
+        let net = match self.net(&path) {
+          Some(NetChange::Created) => NetChange::Created,
+          _ => NetChange::Modified,
+        };
+        self.set(&path, Some(net));
+      }
+      RawChange::Removed(path) => {
+        let net = match self.net(&path) {
+          Some(NetChange::Created) => None,
+          _ => Some(NetChange::Removed),
+        };
+        self.set(&path, net);
+      }
+      RawChange::Renamed(from, to) => {
+        let net = self.net(&from);
+        self.set(&from, None);
+        // Whatever was at `to` is replaced, so it isn't listed separately
+        self.set(&to, None);
+        if let Some(rename) = self.renames.iter_mut().find(|(_, t)| *t == from) {
+          rename.1 = to.clone();
+        } else if net != Some(NetChange::Created) {
+          self.renames.push((from, to.clone()));
+        }
+        match net {
+          Some(NetChange::Created) => self.set(&to, Some(NetChange::Created)),
+          Some(NetChange::Modified) => self.set(&to, Some(NetChange::Modified)),
+          _ => {}
+        }
+        // Renamed back to where it started
+        self.renames.retain(|(from, to)| from != to);
+      }
+    }
+  }
+
+  // Turn removals and creations of the same file (by `removed_id` before and `created_id`
+  // now) into renames, for watchers that don't report renames as such
+  fn pair_renames(
+    &mut self,
+    removed_id: impl Fn(&Path) -> Option<FileId>,
+    created_id: impl Fn(&Path) -> Option<FileId>,
+  ) {
+    let removed: Vec<PathBuf> = self
+      .changes
+      .iter()
+      .filter(|(_, change)| *change == NetChange::Removed)
+      .map(|(path, _)| path.clone())
+      .collect();
+    for from in removed {
+      let Some(id) = removed_id(&from) else {
+        continue;
+      };
+      let to = self
+        .changes
+        .iter()
+        .find(|(path, change)| *change == NetChange::Created && created_id(path) == Some(id))
+        .map(|(path, _)| path.clone());
+      if let Some(to) = to {
+        self.set(&from, None);
+        self.set(&to, None);
+        self.renames.push((from, to));
+      }
+    }
+  }
+
+  fn is_empty(&self) -> bool {
+    self.changes.is_empty() && self.renames.is_empty()
+  }
+
+  fn into_changes(self, root: &Path) -> WorkspaceChanges {
+    let display = |path: &Path| path.to_string_lossy().to_string();
+    let mut changes = WorkspaceChanges {
+      root: display(root),
+      ..WorkspaceChanges::default()
+    };
+    for (path, change) in &self.changes {
+      let list = match change {
+        NetChange::Created => &mut changes.created,
+        NetChange::Modified => &mut changes.modified,
+        NetChange::Removed => &mut changes.removed,
+      };
+      list.push(display(path));
+    }
+    changes.renamed = self
+      .renames
+      .iter()
+      .map(|(from, to)| RenamedPath {
+        from: display(from),
+        to: display(to),
+      })
+      .collect();
+    changes
+  }
+}
+
+// Paths the tree never shows: `.git`, `node_modules`, and names matching the workspace
+// ignore patterns from the settings (`*` and `?` wildcards), anywhere under the root
+struct IgnoreRules {
+  root: PathBuf,
+  patterns: RegexSet,
+}
+
+impl IgnoreRules {
+  fn new(root: &Path, patterns: &[String]) -> IgnoreRules {
+    let patterns = patterns
+      .iter()
+      .map(|pattern| pattern.trim())
+      .filter(|pattern| !pattern.is_empty())
+      .map(batch_rename::glob_to_regex);
+    IgnoreRules {
+      root: root.to_path_buf(),
+      // Globs are escaped into valid regexes, so this can't fail
+      patterns: RegexSet::new(patterns).unwrap_or_else(|_| RegexSet::empty()),
+    }
+  }
+
+  fn is_ignored(&self, path: &Path) -> bool {
+    let Ok(relative) = path.strip_prefix(&self.root) else {
+      return true;
+    };
+    relative.components().any(|component| {
+      let name = component.as_os_str().to_string_lossy();
+      ALWAYS_IGNORED.contains(&name.as_ref()) || self.patterns.is_match(&name)
+    })
+  }
+}
+
+// Turns watcher events into RawChanges, pairing the two halves of a rename
+#[derive(Default)]
+struct EventReader {
+  // Rename sources not yet matched with a destination, with the watcher's tracker id
+  pending_from: Vec<(Option<usize>, PathBuf)>,
+}
+
+impl EventReader {
+  fn read(&mut self, event: Event) -> Vec<RawChange> {
+    let tracker = event.attrs.tracker();
+    let mut paths = event.paths.into_iter();
+    match event.kind {
+      EventKind::Create(_) => paths.map(RawChange::Created).collect(),
+      EventKind::Remove(_) => paths.map(RawChange::Removed).collect(),
+      EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => match (paths.next(), paths.next()) {
+        (Some(from), Some(to)) => vec![RawChange::Renamed(from, to)],
+        _ => Vec::new(),
+      },
+      EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+        self.pending_from.extend(paths.map(|path| (tracker, path)));
+        Vec::new()
+      }
+      EventKind::Modify(ModifyKind::Name(RenameMode::To)) => paths
+        .map(|to| {
+          let index = match tracker {
+            Some(_) => self.pending_from.iter().position(|(t, _)| *t == tracker),
+            None => self.pending_from.len().checked_sub(1),
+          };
+          match index {
+            Some(index) => RawChange::Renamed(self.pending_from.remove(index).1, to),
+            // Moved in from outside the workspace
+            None => RawChange::Created(to),
+          }
+        })
+        .collect(),
+      // One side of a rename without saying which (FSEvents): whether it's still there
+      // tells, and pair_renames matches the two up
+      EventKind::Modify(ModifyKind::Name(_)) => paths
+        .map(|path| {
+          if path.exists() {
+            RawChange::Created(path)
+          } else {
+            RawChange::Removed(path)
+          }
+        })
+        .collect(),
+      EventKind::Modify(_) => paths.map(RawChange::Modified).collect(),
+      _ => Vec::new(),
+    }
+  }
+
+  // Sources whose destination never came were moved out of the workspace
+  fn finish(&mut self) -> Vec<RawChange> {
+    self
+      .pending_from
+      .drain(..)
+      .map(|(_, path)| RawChange::Removed(path))
+      .collect()
+  }
+}
+
+// Identities of the files under the root, to recognize them after a rename. Kept up to
+// date from the batches.
+struct KnownFiles(HashMap<PathBuf, FileId>);
+
+impl KnownFiles {
+  fn scan(root: &Path) -> KnownFiles {
+    KnownFiles(
+      wiki::walk_files(root, |_| true)
+        .into_iter()
+        .filter_map(|file| Some((file.path.clone(), path_id(&file.path)?)))
+        .collect(),
+    )
+  }
+
+  fn update(&mut self, batch: &Batch) {
+    for (from, to) in &batch.renames {
+      let moved: Vec<(PathBuf, FileId)> = self
+        .0
+        .iter()
+        .filter_map(|(path, id)| {
+          let rest = path.strip_prefix(from).ok()?;
+          Some((to.join(rest), *id))
+        })
+        .collect();
+      self.0.retain(|path, _| !path.starts_with(from));
+      self.0.extend(moved);
+    }
+    for (path, change) in &batch.changes {
+      match (change, path_id(path)) {
+        (NetChange::Removed, _) | (_, None) => {
+          self.0.remove(path);
+        }
+        (_, Some(id)) => {
+          self.0.insert(path.clone(), id);
+        }
+      }
+    }
+  }
+}
+
+// Collect watcher events into batches and send them until the watcher is dropped
+fn run_batches(app: AppHandle, root: PathBuf, ignore: IgnoreRules, events: Receiver<Event>) {
+  let mut known = KnownFiles::scan(&root);
+  let mut reader = EventReader::default();
+  // Wait for the first event of a batch, then for things to settle
+  while let Ok(event) = events.recv() {
+    let mut raw = reader.read(event);
+    let started = Instant::now();
+    loop {
+      let elapsed = started.elapsed();
+      if elapsed >= MAX_BATCH_DELAY {
+        break;
+      }
+      match events.recv_timeout(DEBOUNCE.min(MAX_BATCH_DELAY - elapsed)) {
+        Ok(event) => raw.extend(reader.read(event)),
+        Err(RecvTimeoutError::Timeout) => break,
+        // The workspace was closed or replaced; nobody wants this batch anymore
+        Err(RecvTimeoutError::Disconnected) => return,
+      }
+    }
+    raw.extend(reader.finish());
+
+    let mut batch = Batch::default();
+    for change in raw {
+      let change = match change {
+        RawChange::Created(ref path)
+        | RawChange::Modified(ref path)
+        | RawChange::Removed(ref path)
+          if ignore.is_ignored(path) =>
+        {
+          continue
+        }
+        // Moving a file into or out of an ignored folder shows or hides it
+        RawChange::Renamed(from, to) => match (ignore.is_ignored(&from), ignore.is_ignored(&to)) {
+          (true, true) => continue,
+          (true, false) => RawChange::Created(to),
+          (false, true) => RawChange::Removed(from),
+          (false, false) => RawChange::Renamed(from, to),
+        },
+        change => change,
+      };
+      batch.add(change);
+    }
+    batch.pair_renames(|path| known.0.get(path).copied(), path_id);
+    if batch.is_empty() {
+      continue;
+    }
+    known.update(&batch);
+
+    app.state::<wiki::WikiIndexState>().invalidate(&root);
+    app.state::<file_finder::FileIndexState>().invalidate(&root);
+    let changes = batch.into_changes(&root);
+    app.state::<tags::TagIndexState>().apply(&root, &changes);
+    let _ = app.emit(WORKSPACE_CHANGED_EVENT, changes);
+  }
+}
+
+fn validate_root(root: &Path) -> CommandResult<()> {
+  if !root.is_absolute() || !root.is_dir() {
+    return Err(CommandError::invalid_path(
+      root,
+      "Workspace must be an absolute folder path",
+    ));
+  }
+  Ok(())
+}
+
+// `root` as a folder to scan: absolute, existing and inside the access scope
+pub(crate) fn checked_root(scope: &AccessScope, root: String) -> CommandResult<PathBuf> {
+  let root = PathBuf::from(root);
+  validate_root(&root)?;
+  scope.check(&root)?;
+  Ok(root)
+}
+
+// The watched workspace. Dropping the watcher ends its batching thread.
+struct WorkspaceWatcher {
+  root: PathBuf,
+  _watcher: RecommendedWatcher,
+}
+
+#[derive(Default)]
+pub struct WorkspaceWatcherState(Mutex<Option<WorkspaceWatcher>>);
+
+// Open `root` as the workspace: widen the access scope to it, add it to the recent
+// workspaces and watch it recursively, sending WORKSPACE_CHANGED_EVENT batches. Replaces the
+// watcher of the previous workspace, if any.
+pub fn open(app: &AppHandle, root: &Path) -> CommandResult<()> {
+  validate_root(root)?;
+  access_scope::open_workspace(app, Some(root))?;
+  let root = crate::lexical_normalize(root);
+  recent_workspaces::record(app, &root);
+  if !safe_mode::startup(app).watchers {
+    log::info!("Safe mode: not watching {}", root.display());
+    return Ok(());
+  }
+  let state = app.state::<WorkspaceWatcherState>();
+  let mut current = state.0.lock().unwrap();
+  if current.as_ref().is_some_and(|watcher| watcher.root == root) {
+    return Ok(());
+  }
+  // Stop the old watcher first, so its last batch isn't mixed up with the new workspace's
+  *current = None;
+
+  let (sender, receiver) = mpsc::channel();
+  let mut watcher =
+    notify::recommended_watcher(move |result: notify::Result<Event>| match result {
+      Ok(event) => {
+        let _ = sender.send(event);
+      }
+      Err(e) => log::warn!("Workspace watcher error: {}", e),
+    })
+    .map_err(|e| CommandError::io("Failed to start watching the workspace", e))?;
+  watcher
+    .watch(&root, RecursiveMode::Recursive)
+    .map_err(|e| CommandError::io("Failed to watch the workspace", e))?;
+
+  let patterns = app
+    .state::<SettingsState>()
+    .0
+    .lock()
+    .unwrap()
+    .workspace_ignore
+    .clone();
+  let ignore = IgnoreRules::new(&root, &patterns);
+  let thread_root = root.clone();
+  let app = app.clone();
+  std::thread::spawn(move || run_batches(app, thread_root, ignore, receiver));
+  *current = Some(WorkspaceWatcher {
+    root,
+    _watcher: watcher,
+  });
+  Ok(())
+}
+
+// Open `root` as the workspace (see open). A folder the user hasn't approved yet, neither
+// inside the access scope nor a recent workspace, is only opened once they confirm it.
+#[tauri::command]
+pub async fn watch_workspace(
+  app: AppHandle,
+  scope: tauri::State<'_, AccessScopeState>,
+  root: String,
+) -> CommandResult<()> {
+  let root = PathBuf::from(root);
+  validate_root(&root)?;
+  let approved =
+    scope.0.lock().unwrap().is_allowed(&root) || recent_workspaces::contains(&app, &root);
+  if !approved && !access_scope::confirm_access(&app, &root) {
+    return Err(CommandError::ScopeDenied {
+      path: root.to_string_lossy().to_string(),
+    });
+  }
+  open(&app, &root)
+}
+
+// Stop watching the workspace
+#[tauri::command]
+pub async fn close_workspace(
+  app: AppHandle,
+  state: tauri::State<'_, WorkspaceWatcherState>,
+) -> CommandResult<()> {
+  access_scope::open_workspace(&app, None)?;
+  state.0.lock().unwrap().take();
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn batch(changes: Vec<RawChange>) -> Batch {
+    let mut batch = Batch::default();
+    for change in changes {
+      batch.add(change);
+    }
+    batch
+  }
+
+  fn p(path: &str) -> PathBuf {
+    PathBuf::from(path)
+  }
+
+  fn strings(paths: &[&str]) -> Vec<String> {
+    paths.iter().map(|path| path.to_string()).collect()
+  }
+
+  #[test]
+  fn test_batch_keeps_the_net_effect() {
+    use RawChange::*;
+    let changes = batch(vec![
+      Created(p("/w/tmp.md")),
+      Modified(p("/w/tmp.md")),
+      Removed(p("/w/tmp.md")),
+      Removed(p("/w/a.md")),
+      Created(p("/w/a.md")),
+      Created(p("/w/new.md")),
+      Modified(p("/w/new.md")),
+      Removed(p("/w/old.md")),
+    ])
+    .into_changes(Path::new("/w"));
+    assert_eq!(changes.created, strings(&["/w/new.md"]));
+    assert_eq!(changes.modified, strings(&["/w/a.md"]));
+    assert_eq!(changes.removed, strings(&["/w/old.md"]));
+    assert!(changes.renamed.is_empty());
+  }
+
+  #[test]
+  fn test_batch_collapses_rename_chains() {
+    use RawChange::*;
+    let changes = batch(vec![
+      Renamed(p("/w/a.md"), p("/w/b.md")),
+      Renamed(p("/w/b.md"), p("/w/c.md")),
+      // Created and renamed within the batch is just created
+      Created(p("/w/draft.md")),
+      Renamed(p("/w/draft.md"), p("/w/final.md")),
+      // Renamed back and forth is nothing
+      Renamed(p("/w/x.md"), p("/w/y.md")),
+      Renamed(p("/w/y.md"), p("/w/x.md")),
+    ])
+    .into_changes(Path::new("/w"));
+    assert_eq!(changes.created, strings(&["/w/final.md"]));
+    assert_eq!(
+      changes.renamed,
+      vec![RenamedPath {
+        from: "/w/a.md".into(),
+        to: "/w/c.md".into()
+      }]
+    );
+  }
+
+  #[test]
+  fn test_pair_renames_by_file_identity() {
+    use RawChange::*;
+    let mut pending = batch(vec![
+      Removed(p("/w/old.md")),
+      Created(p("/w/other.md")),
+      Created(p("/w/moved/old.md")),
+      Removed(p("/w/gone.md")),
+    ]);
+    let id = |inode| Some(FileId { inode, size: 10 });
+    pending.pair_renames(
+      |path| match path.to_str() {
+        Some("/w/old.md") => id(1),
+        Some("/w/gone.md") => id(3),
+        _ => None,
+      },
+      |path| match path.to_str() {
+        Some("/w/other.md") => id(2),
+        Some("/w/moved/old.md") => id(1),
+        _ => None,
+      },
+    );
+    let changes = pending.into_changes(Path::new("/w"));
+    assert_eq!(changes.created, strings(&["/w/other.md"]));
+    assert_eq!(changes.removed, strings(&["/w/gone.md"]));
+    assert_eq!(
+      changes.renamed,
+      vec![RenamedPath {
+        from: "/w/old.md".into(),
+        to: "/w/moved/old.md".into()
+      }]
+    );
+  }
+
+  #[test]
+  fn test_ignore_rules() {
+    let rules = IgnoreRules::new(Path::new("/w"), &["*.tmp".to_string(), " ".to_string()]);
+    assert!(rules.is_ignored(Path::new("/w/.git/index")));
+    assert!(rules.is_ignored(Path::new("/w/site/node_modules/x/readme.md")));
+    assert!(rules.is_ignored(Path::new("/w/notes/a.md.tmp")));
+    assert!(rules.is_ignored(Path::new("/elsewhere/a.md")));
+    assert!(!rules.is_ignored(Path::new("/w/notes/a.md")));
+    assert!(!rules.is_ignored(Path::new("/w/.github/readme.md")));
+  }
+
+  #[test]
+  fn test_checked_root_denies_folders_outside_the_scope() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    std::fs::create_dir(&notes).unwrap();
+    let scope = AccessScope::with_folders(&[&notes]);
+    let root = |path: &Path| path.to_string_lossy().to_string();
+
+    assert_eq!(checked_root(&scope, root(&notes)).unwrap(), notes);
+    let error = checked_root(&scope, root(dir.path())).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    let error = checked_root(&scope, root(&notes.join(".."))).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    let error = checked_root(&scope, "notes".to_string()).unwrap_err();
+    assert_eq!(error.code(), "invalid_path");
+  }
+}
@@ -0,0 +1,426 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::access_scope::{AccessScope, AccessScopeState};
+use crate::assets::resolve_reference;
+use crate::batch_rename::{rewrite_destinations, ENCODED_NAME_CHARS};
+use crate::error::{CommandError, CommandResult};
+use crate::export::{collect_assets, content_hash, suffixed_name, DocumentAsset};
+use crate::relative_links::{relocate_destination, Relocation};
+use crate::wiki::WikiIndexState;
+
+// Folder created next to the imported document to hold its assets
+const IMPORT_ASSETS_DIR: &str = "assets";
+
+// Give up on finding a free " copy N" name after this many
+const MAX_COPY_SUFFIX: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferMode {
+  // The source document stays where it was, untouched
+  #[default]
+  Copy,
+  // The source document is deleted once the new one is written. Its assets are copied
+  // either way, since other documents may use them too.
+  Move,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct WorkspaceImportOptions {
+  pub mode: TransferMode,
+}
+
+// An asset of the imported document, as it's now referenced
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImportedAsset {
+  pub source_path: String,
+  pub path: String,
+  // An identical file was already in the assets folder, so nothing was copied
+  pub reused: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkspaceImport {
+  pub document_path: String,
+  pub assets: Vec<ImportedAsset>,
+  // Relative references that didn't lead to a file at the source either, left as written
+  pub unresolved: Vec<String>,
+  // Relative references to files outside the workspace, left as written
+  pub unpreserved: Vec<String>,
+}
+
+// `notes.md` in `dir`, or `notes copy.md`, `notes copy 2.md`... if that's taken
+fn available_path(dir: &Path, name: &str) -> CommandResult<PathBuf> {
+  let path = dir.join(name);
+  if !path.exists() {
+    return Ok(path);
+  }
+  let name_path = Path::new(name);
+  let stem = name_path.file_stem().unwrap_or_default().to_string_lossy();
+  let extension = name_path
+    .extension()
+    .map(|e| format!(".{}", e.to_string_lossy()))
+    .unwrap_or_default();
+  (1..=MAX_COPY_SUFFIX)
+    .map(|n| match n {
+      1 => dir.join(format!("{} copy{}", stem, extension)),
+      n => dir.join(format!("{} copy {}{}", stem, n, extension)),
+    })
+    .find(|path| !path.exists())
+    .ok_or_else(|| CommandError::AlreadyExists {
+      path: path.to_string_lossy().to_string(),
+    })
+}
+
+// Copy an asset into `assets_dir` under its own name, unless a different file already has
+// it; then under a content-hash suffixed name. A file with the same content is reused.
+fn place_asset(asset: &Path, assets_dir: &Path) -> CommandResult<(PathBuf, bool)> {
+  let name = asset.file_name().unwrap_or_default().to_string_lossy();
+  let hash =
+    content_hash(asset).map_err(|e| CommandError::from_io(&e, asset, "Failed to read asset"))?;
+  for candidate in [name.to_string(), suffixed_name(&name, &hash)] {
+    let target = assets_dir.join(&candidate);
+    if !target.exists() {
+      std::fs::create_dir_all(assets_dir)
+        .map_err(|e| CommandError::from_io(&e, assets_dir, "Failed to create assets folder"))?;
+      std::fs::copy(asset, &target)
+        .map_err(|e| CommandError::from_io(&e, asset, "Failed to copy asset"))?;
+      return Ok((target, false));
+    }
+    if content_hash(&target).is_ok_and(|existing| existing == hash) {
+      return Ok((target, true));
+    }
+  }
+  Err(CommandError::AlreadyExists {
+    path: assets_dir
+      .join(suffixed_name(&name, &hash))
+      .to_string_lossy()
+      .to_string(),
+  })
+}
+
+// The assets whose references break when the document moves from `source` to `target`:
+// relative ones that don't lead to the same file from the new folder
+fn assets_to_copy(source: &Path, target: &Path, content: &str) -> Vec<DocumentAsset> {
+  collect_assets(source, content)
+    .into_iter()
+    .filter(|asset| {
+      let reference = asset.reference.split(['?', '#']).next().unwrap_or_default();
+      !Path::new(reference).is_absolute()
+        && resolve_reference(target, &asset.reference).as_ref() != Some(&asset.path)
+    })
+    .collect()
+}
+
+// What the imported document's references were rewritten to
+struct Rewritten {
+  content: String,
+  unresolved: Vec<String>,
+  unpreserved: Vec<String>,
+}
+
+// `content` with the references to copied assets pointing into the assets folder and the
+// other relative references recomputed from `target`'s folder, plus the relative
+// references that don't resolve to a file or that would have to leave `workspace`
+fn rewrite_for_target(
+  content: &str,
+  source: &Path,
+  target: &Path,
+  copied: &HashMap<PathBuf, String>,
+  workspace: Option<&Path>,
+) -> Rewritten {
+  let mut unresolved: Vec<String> = Vec::new();
+  let mut unpreserved: Vec<String> = Vec::new();
+  let (old_dir, new_dir) = (
+    source.parent().unwrap_or(source),
+    target.parent().unwrap_or(target),
+  );
+  let (rewritten, _) = rewrite_destinations(content, |destination, angle_brackets| {
+    let path = resolve_reference(source, destination)?;
+    let Some(name) = copied.get(&path) else {
+      if !path.exists() {
+        if !unresolved.iter().any(|r| r == destination) {
+          unresolved.push(destination.to_string());
+        }
+        return None;
+      }
+      return match relocate_destination(destination, angle_brackets, old_dir, new_dir, workspace) {
+        Relocation::Kept => None,
+        Relocation::Rewritten(relocated) => Some(relocated),
+        Relocation::Unpreserved => {
+          if !unpreserved.iter().any(|r| r == destination) {
+            unpreserved.push(destination.to_string());
+          }
+          None
+        }
+      };
+    };
+    let suffix = destination
+      .find(['?', '#'])
+      .map_or("", |index| &destination[index..]);
+    let name = if !angle_brackets && name.contains(ENCODED_NAME_CHARS) {
+      urlencoding::encode(name).into_owned()
+    } else {
+      name.clone()
+    };
+    Some(format!("{}/{}{}", IMPORT_ASSETS_DIR, name, suffix))
+  });
+  Rewritten {
+    content: rewritten,
+    unresolved,
+    unpreserved,
+  }
+}
+
+fn import_into(
+  scope: &AccessScope,
+  source: &Path,
+  dest_dir: &Path,
+  options: &WorkspaceImportOptions,
+) -> CommandResult<WorkspaceImport> {
+  scope.check(source)?;
+  scope.check(dest_dir)?;
+  let content = std::fs::read_to_string(source)
+    .map_err(|e| CommandError::from_io(&e, source, "Failed to read file"))?;
+  let name = source.file_name().unwrap_or_default().to_string_lossy();
+  let target = available_path(dest_dir, &name)?;
+  let assets_dir = dest_dir.join(IMPORT_ASSETS_DIR);
+
+  let mut copied: HashMap<PathBuf, String> = HashMap::new();
+  let mut assets = Vec::new();
+  for asset in assets_to_copy(source, &target, &content) {
+    let (path, reused) = place_asset(&asset.path, &assets_dir)?;
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    copied.insert(asset.path.clone(), file_name.to_string());
+    assets.push(ImportedAsset {
+      source_path: asset.path.to_string_lossy().to_string(),
+      path: path.to_string_lossy().to_string(),
+      reused,
+    });
+  }
+  let rewritten = rewrite_for_target(&content, source, &target, &copied, scope.workspace());
+
+  // create_new, so a file that appeared since available_path looked is never overwritten
+  let mut file = std::fs::OpenOptions::new()
+    .write(true)
+    .create_new(true)
+    .open(&target)
+    .map_err(|e| match e.kind() {
+      io::ErrorKind::AlreadyExists => CommandError::AlreadyExists {
+        path: target.to_string_lossy().to_string(),
+      },
+      _ => CommandError::from_io(&e, &target, "Failed to write file"),
+    })?;
+  file
+    .write_all(rewritten.content.as_bytes())
+    .map_err(|e| CommandError::from_io(&e, &target, "Failed to write file"))?;
+  if options.mode == TransferMode::Move {
+    std::fs::remove_file(source)
+      .map_err(|e| CommandError::from_io(&e, source, "Failed to remove the original"))?;
+  }
+
+  Ok(WorkspaceImport {
+    document_path: target.to_string_lossy().to_string(),
+    assets,
+    unresolved: rewritten.unresolved,
+    unpreserved: rewritten.unpreserved,
+  })
+}
+
+// Copy or move a markdown file into `dest_dir`, bringing along the images and files its
+// relative links point to so they keep working
+#[tauri::command]
+pub async fn import_file_into_workspace(
+  wiki_index: tauri::State<'_, WikiIndexState>,
+  scope: tauri::State<'_, AccessScopeState>,
+  source_path: String,
+  dest_dir: String,
+  options: Option<WorkspaceImportOptions>,
+) -> CommandResult<WorkspaceImport> {
+  let source = PathBuf::from(source_path);
+  let dest_dir = PathBuf::from(dest_dir);
+  for path in [&source, &dest_dir] {
+    if !path.is_absolute() {
+      return Err(CommandError::invalid_path(path, "Path must be absolute"));
+    }
+  }
+  if !source.is_file() {
+    return Err(CommandError::NotFound {
+      path: source.to_string_lossy().to_string(),
+    });
+  }
+  if !dest_dir.is_dir() {
+    return Err(CommandError::NotFound {
+      path: dest_dir.to_string_lossy().to_string(),
+    });
+  }
+  let source = crate::lexical_normalize(&source);
+  let dest_dir = crate::lexical_normalize(&dest_dir);
+  let imported = import_into(
+    &scope.0.lock().unwrap(),
+    &source,
+    &dest_dir,
+    &options.unwrap_or_default(),
+  )?;
+  wiki_index.invalidate(&dest_dir);
+  Ok(imported)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  fn write(dir: &Path, relative: &str, content: &str) -> PathBuf {
+    let path = dir.join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(&path, content).unwrap();
+    path
+  }
+
+  #[test]
+  fn test_import_copies_assets_and_rewrites_links() {
+    let dir = TempDir::new().unwrap();
+    let downloads = dir.path().join("downloads");
+    let notes = dir.path().join("notes");
+    write(&downloads, "img/chart.png", "chart");
+    write(&downloads, "img/my photo.jpg", "photo");
+    write(&notes, "assets/chart.png", "another chart");
+    let absolute = write(dir.path(), "elsewhere/logo.png", "logo");
+    let content = format!(
+      "# Report\n![chart](img/chart.png)\n![photo](<img/my photo.jpg>)\n\
+       [again](img/chart.png#top) ![gone](img/gone.png) ![logo]({})\n\
+       ```\n![code](img/chart.png)\n```\n",
+      absolute.display()
+    );
+    let source = write(&downloads, "report.md", &content);
+    write(&notes, "report.md", "already here");
+
+    let scope = AccessScope::with_folders(&[dir.path()]);
+    let imported =
+      import_into(&scope, &source, &notes, &WorkspaceImportOptions::default()).unwrap();
+    assert_eq!(
+      imported.document_path,
+      notes.join("report copy.md").to_string_lossy()
+    );
+    let written = fs::read_to_string(&imported.document_path).unwrap();
+    let chart = format!(
+      "assets/{}",
+      suffixed_name(
+        "chart.png",
+        &content_hash(&downloads.join("img/chart.png")).unwrap()
+      )
+    );
+    assert_eq!(
+      written,
+      format!(
+        "# Report\n![chart]({0})\n![photo](<assets/my photo.jpg>)\n\
+         [again]({0}#top) ![gone](img/gone.png) ![logo]({1})\n\
+         ```\n![code](img/chart.png)\n```\n",
+        chart,
+        absolute.display()
+      )
+    );
+    assert_eq!(imported.assets.len(), 2);
+    assert_eq!(imported.unresolved, vec!["img/gone.png"]);
+    assert_eq!(fs::read_to_string(&source).unwrap(), content);
+
+    // Moving it again reuses the identical assets and removes the source
+    let options = WorkspaceImportOptions {
+      mode: TransferMode::Move,
+    };
+    let moved = import_into(&scope, &source, &notes, &options).unwrap();
+    assert!(moved.document_path.ends_with("report copy 2.md"));
+    assert!(moved.assets.iter().all(|asset| asset.reused));
+    assert!(!source.exists());
+  }
+
+  #[test]
+  fn test_references_that_still_work_are_kept() {
+    let dir = TempDir::new().unwrap();
+    write(dir.path(), "shared/diagram.png", "diagram");
+    let source = write(
+      dir.path(),
+      "drafts/note.md",
+      "![d](../shared/diagram.png)\n",
+    );
+    let notes = dir.path().join("notes");
+    fs::create_dir(&notes).unwrap();
+
+    let scope = AccessScope::with_folders(&[dir.path()]);
+    let imported =
+      import_into(&scope, &source, &notes, &WorkspaceImportOptions::default()).unwrap();
+    assert!(imported.assets.is_empty());
+    assert_eq!(
+      fs::read_to_string(&imported.document_path).unwrap(),
+      "![d](../shared/diagram.png)\n"
+    );
+  }
+
+  #[test]
+  fn test_links_to_other_notes_follow_the_document() {
+    let dir = TempDir::new().unwrap();
+    let notes = dir.path().join("notes");
+    write(&notes, "projects/plan.md", "plan");
+    write(dir.path(), "drafts/ideas.md", "ideas");
+    let source = write(
+      dir.path(),
+      "drafts/trip.md",
+      "[plan](../notes/projects/plan.md#goals) [ideas](ideas.md) [gone](gone.md)\n",
+    );
+    let projects = notes.join("projects");
+
+    let mut scope = AccessScope::with_folders(&[dir.path()]);
+    scope.set_workspace(Some(notes.clone()));
+    let imported = import_into(
+      &scope,
+      &source,
+      &projects,
+      &WorkspaceImportOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(
+      fs::read_to_string(&imported.document_path).unwrap(),
+      "[plan](plan.md#goals) [ideas](ideas.md) [gone](gone.md)\n"
+    );
+    assert_eq!(imported.unresolved, vec!["gone.md"]);
+    assert_eq!(imported.unpreserved, vec!["ideas.md"]);
+
+    // Outside a workspace, a link out of the folder is fine
+    scope.set_workspace(None);
+    let imported = import_into(
+      &scope,
+      &source,
+      &projects,
+      &WorkspaceImportOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(
+      fs::read_to_string(&imported.document_path).unwrap(),
+      "[plan](plan.md#goals) [ideas](../../drafts/ideas.md) [gone](gone.md)\n"
+    );
+    assert!(imported.unpreserved.is_empty());
+  }
+
+  #[test]
+  fn test_paths_outside_the_scope_are_denied() {
+    let dir = TempDir::new().unwrap();
+    let source = write(dir.path(), "downloads/report.md", "# Report\n");
+    let notes = dir.path().join("notes");
+    fs::create_dir(&notes).unwrap();
+    let options = WorkspaceImportOptions::default();
+
+    let only_notes = AccessScope::with_folders(&[&notes]);
+    let error = import_into(&only_notes, &source, &notes, &options).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    let only_downloads = AccessScope::with_folders(&[&dir.path().join("downloads")]);
+    let error = import_into(&only_downloads, &source, &notes, &options).unwrap_err();
+    assert_eq!(error.code(), "scope_denied");
+    assert!(!notes.join("report.md").exists());
+  }
+}
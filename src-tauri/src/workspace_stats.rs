@@ -0,0 +1,428 @@
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tauri::{AppHandle, Manager};
+
+use crate::access_scope::AccessScopeState;
+use crate::assets::resolve_reference;
+use crate::error::{CommandError, CommandResult};
+use crate::file_finder::FileIndexState;
+use crate::filename::{first_heading, strip_inline_markdown};
+use crate::now_millis;
+use crate::tags::{count_tags, note_tags, TagCount};
+use crate::task_registry;
+use crate::wiki::{is_note, link_target, normalize_key};
+use crate::workspace;
+use crate::writing_history::count_words;
+
+// How many of the biggest files the stats list
+const LARGEST_FILES: usize = 10;
+
+// Progress is reported every this many files
+const PROGRESS_EVERY: usize = 50;
+
+const DAY_MILLIS: u64 = 24 * 60 * 60 * 1000;
+
+// What one note contributes to the stats, kept until the note's mtime or size changes
+#[derive(Debug, Clone, PartialEq)]
+struct NoteSummary {
+  modified: u64,
+  size: u64,
+  words: u64,
+  tags: Vec<String>,
+  // normalize_key of the first heading, which wiki links can name too
+  title_key: Option<String>,
+  // normalize_key of each `[[link]]` target
+  wiki_links: Vec<String>,
+  // Notes linked with markdown links, resolved against the note's folder
+  linked_paths: Vec<PathBuf>,
+}
+
+// Summaries by note path, across workspaces
+#[derive(Default)]
+pub struct WorkspaceStatsState(Mutex<HashMap<PathBuf, NoteSummary>>);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileSize {
+  pub path: String,
+  pub size: u64,
+  pub words: u64,
+}
+
+// Numbers for the "Vault statistics" panel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkspaceStats {
+  pub file_count: usize,
+  pub total_words: u64,
+  pub total_bytes: u64,
+  // Biggest first
+  pub largest_files: Vec<FileSize>,
+  pub modified_last_7_days: usize,
+  pub modified_last_30_days: usize,
+  // Notes no other note links to, sorted
+  pub orphans: Vec<String>,
+  pub tags: Vec<TagCount>,
+}
+
+// `[[target]]` and `![[target]]` link texts outside fenced code blocks
+fn wiki_link_texts(content: &str) -> Vec<&str> {
+  let mut links = Vec::new();
+  let mut fence: Option<&str> = None;
+  for line in content.lines() {
+    let trimmed = line.trim_start();
+    let marker = ["```", "~~~"]
+      .into_iter()
+      .find(|marker| trimmed.starts_with(marker));
+    match (fence, marker) {
+      (None, Some(marker)) => fence = Some(marker),
+      (Some(open), Some(marker)) if open == marker => fence = None,
+      _ => {}
+    }
+    if fence.is_some() || marker.is_some() {
+      continue;
+    }
+    let mut rest = line;
+    while let Some(start) = rest.find("[[") {
+      let after = &rest[start + 2..];
+      let Some(end) = after.find("]]") else {
+        break;
+      };
+      links.push(&after[..end]);
+      rest = &after[end + 2..];
+    }
+  }
+  links
+}
+
+fn summarize(path: &Path, content: &str, modified: u64, size: u64) -> NoteSummary {
+  let wiki_links = wiki_link_texts(content)
+    .into_iter()
+    .map(|text| normalize_key(link_target(text)))
+    .filter(|key| !key.is_empty())
+    .collect();
+  let linked_paths = Parser::new_ext(content, Options::all())
+    .filter_map(|event| match event {
+      Event::Start(Tag::Link { dest_url, .. }) => resolve_reference(path, &dest_url),
+      _ => None,
+    })
+    .filter(|target| is_note(target))
+    .collect();
+  NoteSummary {
+    modified,
+    size,
+    words: count_words(content),
+    tags: note_tags(content),
+    title_key: first_heading(content)
+      .map(|heading| normalize_key(&strip_inline_markdown(&heading))),
+    wiki_links,
+    linked_paths,
+  }
+}
+
+fn modified_millis(metadata: &std::fs::Metadata) -> u64 {
+  metadata
+    .modified()
+    .ok()
+    .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+    .map_or(0, |since| since.as_millis() as u64)
+}
+
+// The summary of `path`: the cached one while its mtime and size are unchanged, otherwise
+// read again. None for a note that can't be read.
+fn summary(cache: &WorkspaceStatsState, path: &Path) -> Option<NoteSummary> {
+  let metadata = std::fs::metadata(path).ok()?;
+  let (modified, size) = (modified_millis(&metadata), metadata.len());
+  let cached = cache.0.lock().unwrap().get(path).cloned();
+  if let Some(cached) = cached.filter(|c| c.modified == modified && c.size == size) {
+    return Some(cached);
+  }
+  let content = std::fs::read_to_string(path).ok()?;
+  let summary = summarize(path, &content, modified, size);
+  cache
+    .0
+    .lock()
+    .unwrap()
+    .insert(path.to_path_buf(), summary.clone());
+  Some(summary)
+}
+
+// Notes among `summaries` that no other note links to, by wiki link (to its file name, or
+// to its title when no file name matches) or markdown link
+fn orphans(summaries: &[(PathBuf, NoteSummary)]) -> Vec<String> {
+  let mut names: HashMap<String, Vec<&Path>> = HashMap::new();
+  let mut titles: HashMap<&str, Vec<&Path>> = HashMap::new();
+  for (path, summary) in summaries {
+    if let Some(stem) = path.file_stem() {
+      let key = normalize_key(&stem.to_string_lossy());
+      names.entry(key).or_default().push(path);
+    }
+    if let Some(title) = &summary.title_key {
+      titles.entry(title).or_default().push(path);
+    }
+  }
+
+  let mut linked: HashSet<&Path> = HashSet::new();
+  for (from, summary) in summaries {
+    for key in &summary.wiki_links {
+      let targets = names.get(key).or_else(|| titles.get(key.as_str()));
+      linked.extend(
+        targets
+          .into_iter()
+          .flatten()
+          .filter(|target| **target != from.as_path()),
+      );
+    }
+    linked.extend(
+      summary
+        .linked_paths
+        .iter()
+        .filter(|target| *target != from)
+        .map(PathBuf::as_path),
+    );
+  }
+
+  let mut orphans: Vec<String> = summaries
+    .iter()
+    .filter(|(path, _)| !linked.contains(path.as_path()))
+    .map(|(path, _)| path.to_string_lossy().to_string())
+    .collect();
+  orphans.sort();
+  orphans
+}
+
+// Stats of the notes at `paths`, summarized in parallel. `now` is in milliseconds since the
+// epoch. `on_summarized` gets the number of notes done so far; once `is_cancelled` says so
+// the rest are skipped and this fails with Cancelled.
+fn collect_stats(
+  cache: &WorkspaceStatsState,
+  paths: Vec<PathBuf>,
+  now: u64,
+  is_cancelled: impl Fn() -> bool + Sync,
+  on_summarized: impl Fn(usize) + Sync,
+) -> CommandResult<WorkspaceStats> {
+  let done = AtomicUsize::new(0);
+  let mut summaries: Vec<(PathBuf, NoteSummary)> = paths
+    .into_par_iter()
+    .filter_map(|path| {
+      if is_cancelled() {
+        return None;
+      }
+      let summary = summary(cache, &path);
+      on_summarized(done.fetch_add(1, Ordering::SeqCst) + 1);
+      Some((path, summary?))
+    })
+    .collect();
+  if is_cancelled() {
+    return Err(CommandError::Cancelled);
+  }
+  summaries.sort_by(|a, b| a.0.cmp(&b.0));
+
+  let modified_within = |days: u64| {
+    summaries
+      .iter()
+      .filter(|(_, summary)| summary.modified + days * DAY_MILLIS >= now)
+      .count()
+  };
+  let mut largest: Vec<&(PathBuf, NoteSummary)> = summaries.iter().collect();
+  largest.sort_by(|a, b| b.1.size.cmp(&a.1.size).then(a.0.cmp(&b.0)));
+  Ok(WorkspaceStats {
+    file_count: summaries.len(),
+    total_words: summaries.iter().map(|(_, summary)| summary.words).sum(),
+    total_bytes: summaries.iter().map(|(_, summary)| summary.size).sum(),
+    largest_files: largest
+      .into_iter()
+      .take(LARGEST_FILES)
+      .map(|(path, summary)| FileSize {
+        path: path.to_string_lossy().to_string(),
+        size: summary.size,
+        words: summary.words,
+      })
+      .collect(),
+    modified_last_7_days: modified_within(7),
+    modified_last_30_days: modified_within(30),
+    orphans: orphans(&summaries),
+    tags: count_tags(summaries.iter().map(|(_, summary)| &summary.tags)),
+  })
+}
+
+// Numbers about every note under `root` for the "Vault statistics" panel. Notes come from
+// the quick open file index and are read in parallel as a registered task (see
+// task_registry), so it reports progress and can be cancelled. Notes unchanged since the
+// last call aren't read again.
+#[tauri::command]
+pub async fn workspace_stats(
+  app: AppHandle,
+  scope: tauri::State<'_, AccessScopeState>,
+  root: String,
+  task_id: Option<String>,
+) -> CommandResult<WorkspaceStats> {
+  let root = workspace::checked_root(&scope.0.lock().unwrap(), root)?;
+  let root = crate::lexical_normalize(&root);
+  let guard = task_registry::start_task(
+    &app,
+    task_id,
+    "workspace_stats",
+    "Counting workspace statistics",
+  )?;
+  let task = guard.task();
+  let thread_app = app.clone();
+  let result = tauri::async_runtime::spawn_blocking(move || {
+    let paths = thread_app.state::<FileIndexState>().notes(&root);
+    let cache = thread_app.state::<WorkspaceStatsState>();
+    // Notes that are gone needn't stay cached
+    let current: HashSet<&PathBuf> = paths.iter().collect();
+    cache
+      .0
+      .lock()
+      .unwrap()
+      .retain(|path, _| !path.starts_with(&root) || current.contains(path));
+    let total = paths.len();
+    collect_stats(
+      &cache,
+      paths,
+      now_millis(),
+      || task.is_cancelled(),
+      |done| {
+        if total >= PROGRESS_EVERY && (done % PROGRESS_EVERY == 0 || done == total) {
+          task.progress(done, total);
+        }
+      },
+    )
+  })
+  .await
+  .map_err(|e| CommandError::io("Failed to count workspace statistics", e))
+  .and_then(|result| result);
+  guard.finish(result)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use tempfile::TempDir;
+
+  // A small workspace with known numbers:
+  //
+  // - index.md links to projects/alpha.md (markdown link) and [[Beta]] (wiki link by name)
+  // - projects/alpha.md links to [[The Gamma Plan]] (wiki link by title)
+  // - projects/beta.md links to itself, which doesn't count
+  // - gamma.md is only linked by its title
+  // - lonely.md and code.md are linked by nobody (code.md's link is inside a code block)
+  fn fixture() -> (TempDir, Vec<PathBuf>) {
+    let dir = TempDir::new().unwrap();
+    let files = [
+      (
+        "index.md",
+        "# Index\n\nSee [alpha](projects/alpha.md) and [[Beta]]. #home\n",
+      ),
+      (
+        "projects/alpha.md",
+        "---\ntags: [project/alpha]\n---\n# Alpha\n\nNext: [[The Gamma Plan|gamma]].\n",
+      ),
+      ("projects/beta.md", "# Beta\n\n[[beta]] #project\n"),
+      ("gamma.md", "# The Gamma Plan\n\none two three four five\n"),
+      ("lonely.md", "Nobody links here.\n"),
+      ("code.md", "```\n[[lonely]]\n```\n"),
+    ];
+    let paths = files
+      .iter()
+      .map(|(name, content)| {
+        let path = dir.path().join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, content).unwrap();
+        path
+      })
+      .collect();
+    (dir, paths)
+  }
+
+  #[test]
+  fn test_stats_of_a_known_workspace() {
+    let (dir, paths) = fixture();
+    let cache = WorkspaceStatsState::default();
+    let now = now_millis();
+    let progress = Mutex::new(Vec::new());
+    let stats = collect_stats(
+      &cache,
+      paths.clone(),
+      now,
+      || false,
+      |done| progress.lock().unwrap().push(done),
+    )
+    .unwrap();
+
+    let path = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+    assert_eq!(stats.file_count, 6);
+    assert_eq!(stats.total_words, 7 + 10 + 4 + 9 + 3 + 3);
+    let total_bytes: u64 = paths.iter().map(|p| fs::metadata(p).unwrap().len()).sum();
+    assert_eq!(stats.total_bytes, total_bytes);
+    assert_eq!(stats.largest_files[0].path, path("projects/alpha.md"));
+    assert_eq!(stats.largest_files.len(), 6);
+    assert_eq!(stats.modified_last_7_days, 6);
+    assert_eq!(stats.modified_last_30_days, 6);
+    assert_eq!(
+      stats.orphans,
+      vec![path("code.md"), path("index.md"), path("lonely.md")]
+    );
+    let tags: Vec<(&str, usize)> = stats
+      .tags
+      .iter()
+      .map(|count| (count.tag.as_str(), count.count))
+      .collect();
+    assert_eq!(
+      tags,
+      vec![("home", 1), ("project", 2), ("project/alpha", 1)]
+    );
+    let mut progress = progress.into_inner().unwrap();
+    progress.sort_unstable();
+    assert_eq!(progress, vec![1, 2, 3, 4, 5, 6]);
+
+    // Seen from 10 days later, nothing was modified in the last week
+    let later = collect_stats(&cache, paths, now + 10 * DAY_MILLIS, || false, |_| {}).unwrap();
+    assert_eq!(later.modified_last_7_days, 0);
+    assert_eq!(later.modified_last_30_days, 6);
+  }
+
+  #[test]
+  fn test_summaries_are_cached_until_the_note_changes() {
+    let (_dir, paths) = fixture();
+    let cache = WorkspaceStatsState::default();
+    let lonely = &paths[4];
+    let first = summary(&cache, lonely).unwrap();
+    assert_eq!(first.words, 3);
+
+    // Same size and mtime: the cached summary is used
+    let modified = fs::metadata(lonely).unwrap().modified().unwrap();
+    fs::write(lonely, "Everybody links here.\n").unwrap();
+    fs::File::options()
+      .write(true)
+      .open(lonely)
+      .unwrap()
+      .set_modified(modified)
+      .unwrap();
+    assert_eq!(summary(&cache, lonely).unwrap(), first);
+
+    fs::write(lonely, "Now it has five words.\n").unwrap();
+    assert_eq!(summary(&cache, lonely).unwrap().words, 5);
+  }
+
+  #[test]
+  fn test_cancelled_stats_fail() {
+    let (_dir, paths) = fixture();
+    let result = collect_stats(&WorkspaceStatsState::default(), paths, 0, || true, |_| {});
+    assert!(matches!(result, Err(CommandError::Cancelled)));
+  }
+
+  #[test]
+  fn test_wiki_links_skip_code_blocks() {
+    assert_eq!(
+      wiki_link_texts("[[a]] and ![[b|c]]\n~~~\n[[d]]\n~~~\n[[e#f]] [[unclosed"),
+      vec!["a", "b|c", "e#f"]
+    );
+  }
+}
@@ -0,0 +1,192 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::error::{CommandError, CommandResult};
+
+type WriteJob = Box<dyn FnOnce() + Send>;
+
+// Pending writes for one file, executed in order by a single worker task
+struct PathQueue {
+  sender: mpsc::UnboundedSender<WriteJob>,
+  // Writes submitted for this path that haven't finished yet
+  pending: usize,
+}
+
+// Orders writes per file: autosave and an explicit save of the same document run one after
+// the other in the order they were submitted, so older content can never win. Writes to
+// different files don't wait for each other. A path's queue goes away once it drains.
+#[derive(Default)]
+pub struct WriteCoordinator {
+  queues: Arc<Mutex<HashMap<PathBuf, PathQueue>>>,
+}
+
+// Result of a write, returned to the frontend
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WriteResult {
+  // Writes to the same file still waiting when this one finished. Non-zero means newer
+  // content is about to land, so e.g. a redundant autosave can be skipped.
+  pub queue_depth: usize,
+  // What was written when it differs from what was sent (smart typography on save), for
+  // the editor to pick up
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub content: Option<String>,
+  // Modification time of the written file, for the next save's `expected_mtime`
+  pub mtime: Option<u64>,
+}
+
+// Key writes by the file they end up in, so `a/../doc.md` and symlinks share a queue
+fn queue_key(path: &Path) -> PathBuf {
+  PathBuf::from(crate::normalize_recent_path(&path.to_string_lossy()))
+}
+
+impl WriteCoordinator {
+  // Queue `job` (which does blocking I/O) behind earlier writes to `path`. The job is queued
+  // immediately, not when the returned future is first polled, so submission order is
+  // execution order. Resolves to the job's output and the queue depth after it ran.
+  pub fn submit<T, F>(
+    &self,
+    path: &Path,
+    job: F,
+  ) -> impl Future<Output = CommandResult<(T, usize)>> + use<T, F>
+  where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+  {
+    let key = queue_key(path);
+    let (result_tx, result_rx) = oneshot::channel();
+    let queues = Arc::clone(&self.queues);
+
+    let wrapped: WriteJob = {
+      let key = key.clone();
+      Box::new(move || {
+        let output = job();
+        let remaining = {
+          let mut queues = queues.lock().unwrap();
+          let remaining = match queues.get_mut(&key) {
+            Some(queue) => {
+              queue.pending -= 1;
+              queue.pending
+            }
+            None => 0,
+          };
+          // Dropping the sender lets the worker exit once it has drained
+          if remaining == 0 {
+            queues.remove(&key);
+          }
+          remaining
+        };
+        let _ = result_tx.send((output, remaining));
+      })
+    };
+
+    {
+      let mut queues = self.queues.lock().unwrap();
+      let queue = queues.entry(key).or_insert_with(spawn_queue);
+      queue.pending += 1;
+      // The worker only stops after its sender is dropped, so this can't fail
+      let _ = queue.sender.send(wrapped);
+    }
+
+    async move {
+      result_rx
+        .await
+        .map_err(|_| CommandError::io("Write did not complete", "the write task stopped"))
+    }
+  }
+}
+
+fn spawn_queue() -> PathQueue {
+  let (sender, mut receiver) = mpsc::unbounded_channel::<WriteJob>();
+  tauri::async_runtime::spawn(async move {
+    while let Some(job) = receiver.recv().await {
+      let _ = tauri::async_runtime::spawn_blocking(job).await;
+    }
+  });
+  PathQueue { sender, pending: 0 }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::fs;
+  use std::sync::mpsc as std_mpsc;
+  use tempfile::TempDir;
+
+  #[test]
+  fn test_concurrent_writes_keep_submission_order() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("doc.md");
+    let coordinator = WriteCoordinator::default();
+
+    let writes: Vec<_> = (0..200)
+      .map(|i| {
+        let target = path.clone();
+        // Alternate spellings of the same file must share a queue
+        let submitted = if i % 2 == 0 {
+          path.clone()
+        } else {
+          dir.path().join(".").join("doc.md")
+        };
+        coordinator.submit(&submitted, move || {
+          fs::write(&target, format!("content {}", i)).unwrap();
+        })
+      })
+      .collect();
+
+    tauri::async_runtime::block_on(async {
+      for write in writes {
+        write.await.unwrap();
+      }
+    });
+    assert_eq!(fs::read_to_string(&path).unwrap(), "content 199");
+    assert!(coordinator.queues.lock().unwrap().is_empty());
+  }
+
+  #[test]
+  fn test_reports_queue_depth_for_waiting_writes() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("doc.md");
+    let coordinator = WriteCoordinator::default();
+    let (release_tx, release_rx) = std_mpsc::channel::<()>();
+
+    let first = coordinator.submit(&path, move || {
+      release_rx.recv().unwrap();
+    });
+    let second = coordinator.submit(&path, || ());
+    let third = coordinator.submit(&path, || ());
+    release_tx.send(()).unwrap();
+
+    let depths = tauri::async_runtime::block_on(async {
+      [
+        first.await.unwrap().1,
+        second.await.unwrap().1,
+        third.await.unwrap().1,
+      ]
+    });
+    assert_eq!(depths, [2, 1, 0]);
+  }
+
+  #[test]
+  fn test_writes_to_different_files_run_in_parallel() {
+    let dir = TempDir::new().unwrap();
+    let coordinator = WriteCoordinator::default();
+    let (release_tx, release_rx) = std_mpsc::channel::<()>();
+
+    // Blocks until the write to the other file has completed
+    let blocked = coordinator.submit(&dir.path().join("a.md"), move || {
+      release_rx.recv().unwrap();
+    });
+    let other = coordinator.submit(&dir.path().join("b.md"), move || {
+      release_tx.send(()).unwrap();
+    });
+
+    tauri::async_runtime::block_on(async {
+      other.await.unwrap();
+      blocked.await.unwrap();
+    });
+  }
+}
@@ -0,0 +1,309 @@
+use chrono::TimeZone;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+use crate::atomic_write::write_atomically;
+use crate::error::{CommandError, CommandResult};
+use crate::{normalize_recent_path, now_millis};
+
+// Folder (in the app data dir) holding a word count log per saved file
+const HISTORY_DIR: &str = "writing_history";
+
+// A file's log is trimmed to its newest records once it grows past this (about 3000 saves)
+const MAX_LOG_BYTES: u64 = 64 * 1024;
+
+// Serializes appends and trims: saves of several files can finish at once
+static HISTORY_LOCK: Mutex<()> = Mutex::new(());
+
+// The word count of a file as of one save
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Record {
+  // Milliseconds since the epoch
+  timestamp: u64,
+  words: u64,
+}
+
+// One day of writing in a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DailyWords {
+  // Local date, `2024-05-31`
+  pub date: String,
+  // Highest word count saved that day
+  pub word_count: u64,
+  // Last count of the day minus the last count before it (or the day's first count, for the
+  // first day on record). Negative after cutting.
+  pub words_added: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileWritingHistory {
+  pub path: String,
+  pub days: Vec<DailyWords>,
+}
+
+// Words as the status bar counts them: runs of non-whitespace
+pub fn count_words(content: &str) -> u64 {
+  content.split_whitespace().count() as u64
+}
+
+fn local_date(timestamp: u64) -> String {
+  chrono::Local
+    .timestamp_millis_opt(timestamp as i64)
+    .single()
+    .map(|time| time.format("%Y-%m-%d").to_string())
+    .unwrap_or_default()
+}
+
+// Logs are named after a hash of the normalized path. Paths that only differ in case are
+// the same file where the filesystem is case-insensitive (see recent_paths_equal).
+fn log_file_name(path: &str) -> String {
+  let key = if cfg!(any(target_os = "macos", target_os = "windows")) {
+    path.to_lowercase()
+  } else {
+    path.to_string()
+  };
+  let digest = Sha256::digest(key.as_bytes());
+  let hex: String = digest[..16].iter().map(|b| format!("{:02x}", b)).collect();
+  format!("{}.log", hex)
+}
+
+fn history_dir(app: &AppHandle) -> CommandResult<PathBuf> {
+  app
+    .path()
+    .app_data_dir()
+    .map(|dir| dir.join(HISTORY_DIR))
+    .map_err(|e| CommandError::io("Failed to resolve app data dir", e))
+}
+
+fn format_record(record: &Record) -> String {
+  format!("{} {}\n", record.timestamp, record.words)
+}
+
+// A log is the file's path on the first line, then `<timestamp> <words>` per save. Lines
+// that don't parse (a write cut short) are skipped.
+fn parse_log(text: &str) -> Option<(String, Vec<Record>)> {
+  let mut lines = text.lines();
+  let path = lines.next()?.to_string();
+  let records = lines
+    .filter_map(|line| {
+      let (timestamp, words) = line.split_once(' ')?;
+      Some(Record {
+        timestamp: timestamp.parse().ok()?,
+        words: words.parse().ok()?,
+      })
+    })
+    .collect();
+  Some((path, records))
+}
+
+fn read_log(log: &Path) -> Option<(String, Vec<Record>)> {
+  parse_log(&std::fs::read_to_string(log).ok()?)
+}
+
+// Append a record to the log of `path` in `dir`, trimming the log to its newest records
+// (three quarters of MAX_LOG_BYTES) when it has grown past the cap
+fn append_record(dir: &Path, path: &str, record: Record) -> std::io::Result<()> {
+  let _lock = HISTORY_LOCK.lock().unwrap();
+  std::fs::create_dir_all(dir)?;
+  let log = dir.join(log_file_name(path));
+  let mut file = std::fs::OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(&log)?;
+  if file.metadata()?.len() == 0 {
+    file.write_all(format!("{}\n", path).as_bytes())?;
+  }
+  file.write_all(format_record(&record).as_bytes())?;
+  if file.metadata()?.len() <= MAX_LOG_BYTES {
+    return Ok(());
+  }
+  drop(file);
+
+  let Some((path, records)) = read_log(&log) else {
+    return Ok(());
+  };
+  let mut kept = Vec::new();
+  let mut size = path.len() as u64 + 1;
+  for record in records.iter().rev() {
+    let line = format_record(record);
+    size += line.len() as u64;
+    if size > MAX_LOG_BYTES * 3 / 4 {
+      break;
+    }
+    kept.push(line);
+  }
+  kept.reverse();
+  write_atomically(&log, format!("{}\n{}", path, kept.concat()).as_bytes())
+}
+
+// Daily aggregates of `records` (oldest first) from `since` on, `date_of` giving each
+// record's date. Records before `since` only serve as the baseline for words added.
+fn daily(records: &[Record], since: u64, date_of: impl Fn(u64) -> String) -> Vec<DailyWords> {
+  let mut days: Vec<DailyWords> = Vec::new();
+  let mut previous: Option<u64> = None;
+  let mut day_baseline = 0;
+  for record in records {
+    if record.timestamp < since {
+      previous = Some(record.words);
+      continue;
+    }
+    let date = date_of(record.timestamp);
+    match days.last_mut().filter(|day| day.date == date) {
+      Some(day) => {
+        day.word_count = day.word_count.max(record.words);
+        day.words_added = record.words as i64 - day_baseline as i64;
+      }
+      None => {
+        day_baseline = previous.unwrap_or(record.words);
+        days.push(DailyWords {
+          date,
+          word_count: record.words,
+          words_added: record.words as i64 - day_baseline as i64,
+        });
+      }
+    }
+    previous = Some(record.words);
+  }
+  days
+}
+
+// Log the word count of a file that was just saved, counted with count_words on the
+// content already in memory. The append happens in the background (errors are logged).
+pub fn record_save(app: &AppHandle, path: &Path, words: u64) {
+  let record = Record {
+    timestamp: now_millis(),
+    words,
+  };
+  let path = normalize_recent_path(&path.to_string_lossy());
+  let dir = match history_dir(app) {
+    Ok(dir) => dir,
+    Err(e) => {
+      log::warn!("Failed to record writing history: {}", e);
+      return;
+    }
+  };
+  tauri::async_runtime::spawn_blocking(move || {
+    if let Err(e) = append_record(&dir, &path, record) {
+      log::warn!("Failed to record writing history: {}", e);
+    }
+  });
+}
+
+// Words written per day in a file, oldest first. `since` is in milliseconds since the epoch.
+#[tauri::command]
+pub async fn get_writing_history(
+  app: AppHandle,
+  path: String,
+  since: Option<u64>,
+) -> CommandResult<Vec<DailyWords>> {
+  let path = normalize_recent_path(&path);
+  let log = history_dir(&app)?.join(log_file_name(&path));
+  let records = read_log(&log)
+    .map(|(_, records)| records)
+    .unwrap_or_default();
+  Ok(daily(&records, since.unwrap_or(0), local_date))
+}
+
+// Words written per day in every file with a history, most recently saved first
+#[tauri::command]
+pub async fn get_writing_history_all(
+  app: AppHandle,
+  since: Option<u64>,
+) -> CommandResult<Vec<FileWritingHistory>> {
+  let dir = history_dir(&app)?;
+  let Ok(entries) = std::fs::read_dir(&dir) else {
+    return Ok(Vec::new());
+  };
+  let mut histories: Vec<(u64, FileWritingHistory)> = entries
+    .flatten()
+    .filter_map(|entry| read_log(&entry.path()))
+    .filter_map(|(path, records)| {
+      let last_saved = records.last()?.timestamp;
+      let days = daily(&records, since.unwrap_or(0), local_date);
+      (!days.is_empty()).then_some((last_saved, FileWritingHistory { path, days }))
+    })
+    .collect();
+  histories.sort_by(|a, b| b.0.cmp(&a.0));
+  Ok(histories.into_iter().map(|(_, history)| history).collect())
+}
+
+#[tauri::command]
+pub async fn clear_writing_history(app: AppHandle, path: String) -> CommandResult<()> {
+  let log = history_dir(&app)?.join(log_file_name(&normalize_recent_path(&path)));
+  let _lock = HISTORY_LOCK.lock().unwrap();
+  match std::fs::remove_file(&log) {
+    Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(CommandError::from_io(
+      &e,
+      &log,
+      "Failed to clear writing history",
+    )),
+    _ => Ok(()),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use tempfile::TempDir;
+
+  const DAY: u64 = 24 * 60 * 60 * 1000;
+
+  fn record(timestamp: u64, words: u64) -> Record {
+    Record { timestamp, words }
+  }
+
+  #[test]
+  fn test_daily_aggregates() {
+    let date_of = |timestamp: u64| format!("day {}", timestamp / DAY);
+    let records = [
+      record(0, 100),
+      record(DAY, 150),
+      record(DAY + 1, 400),
+      record(DAY + 2, 300),
+      record(3 * DAY, 250),
+    ];
+    let summary: Vec<(String, u64, i64)> = daily(&records, 0, date_of)
+      .into_iter()
+      .map(|day| (day.date, day.word_count, day.words_added))
+      .collect();
+    assert_eq!(
+      summary,
+      vec![
+        ("day 0".to_string(), 100, 0),
+        ("day 1".to_string(), 400, 200),
+        ("day 3".to_string(), 250, -50),
+      ]
+    );
+
+    // Earlier records still count as the baseline
+    let since = daily(&records, DAY, date_of);
+    assert_eq!(since.len(), 2);
+    assert_eq!(since[0].words_added, 200);
+    assert_eq!(count_words("# Title\n\nsome  words\there"), 5);
+  }
+
+  #[test]
+  fn test_log_is_appended_and_trimmed() {
+    let dir = TempDir::new().unwrap();
+    let path = "/notes/book.md";
+    append_record(dir.path(), path, record(1, 10)).unwrap();
+    append_record(dir.path(), path, record(2, 20)).unwrap();
+    let log = dir.path().join(log_file_name(path));
+    let (logged_path, records) = read_log(&log).unwrap();
+    assert_eq!(logged_path, path);
+    assert_eq!(records, vec![record(1, 10), record(2, 20)]);
+
+    let count = MAX_LOG_BYTES / 8;
+    for i in 0..count {
+      append_record(dir.path(), path, record(1000 + i, i)).unwrap();
+    }
+    assert!(std::fs::metadata(&log).unwrap().len() <= MAX_LOG_BYTES);
+    let (_, records) = read_log(&log).unwrap();
+    assert_eq!(records.last(), Some(&record(1000 + count - 1, count - 1)));
+    assert!(records.len() < count as usize);
+  }
+}
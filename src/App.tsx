@@ -5,6 +5,29 @@ import { listen } from '@tauri-apps/api/event'
 import { useMermaid } from './hooks/useMermaid'
 import { useMath } from './hooks/useMath'
 import { renderMarkdownToHtml } from './utils/markdown'
+import { resolveAssetUrls } from './utils/assets'
+import { loadRevealRuntime, prerenderForSlides } from './utils/slides'
+import { appPalette, exportPalette, type ExportTheme } from './utils/theme'
+import { revealItemInDir } from '@tauri-apps/plugin-opener'
+import { errorMessage, isCommandError } from './utils/errors'
+import { invokeInScope } from './utils/scope'
+import {
+  cursorPosition,
+  locationViewState,
+  offsetForPosition,
+  toPendingOpen,
+  type FileViewState,
+  type PendingOpen,
+} from './utils/viewState'
+import { setFrontMatterField } from './utils/frontMatter'
+import { isBareUrl, markdownLink } from './utils/links'
+import { replaceLine, taskLineNumbers, toggleTaskLine } from './utils/tasks'
+import {
+  DEFAULT_EDITOR_SETTINGS,
+  editorStyle,
+  indentText,
+  type EditorSettings,
+} from './utils/editorSettings'
 import {
   FolderOpen,
   Save,
@@ -23,26 +46,244 @@ import {
   ChevronDown,
   ChevronUp,
   CaseSensitive,
+  Lock,
 } from 'lucide-react'
 import { ThemeToggle } from './components/ThemeToggle'
+import { ShortcutsDialog } from './components/ShortcutsDialog'
+import { LogsDialog } from './components/LogsDialog'
+import { UnsavedChangesDialog, type CloseAction } from './components/UnsavedChangesDialog'
 import './App.css'
 
 // Toast notification types
 type ToastType = 'error' | 'success' | 'info'
 
+// Button shown in a toast, e.g. "Show in folder"
+interface ToastAction {
+  label: string
+  onClick: () => void
+}
+
+// What export_html and export_print_html return after writing a file
+interface ExportResult {
+  output_path: string
+  copied_assets: string[]
+  // What was left out of the export, and why (custom stylesheets, missing images)
+  warnings: string[]
+}
+
+// What get_export_defaults returns: the document's `export:` frontmatter merged over the
+// settings, passed back as-is to the export commands
+interface ExportDefaults {
+  page_size: string
+  stylesheet: string | null
+  title: string
+  author: string | null
+  table_of_contents: boolean
+  file_name: string
+  warnings: string[]
+}
+
+// What shift_headings returns
+interface HeadingShift {
+  text: string
+  // Some heading couldn't go past H1 or H6
+  clamped: boolean
+}
+
+// The custom CSS from get_effective_styles
+interface EffectiveStyles {
+  css: string
+  sources: string[]
+  warnings: string[]
+}
+
+type UpdateStatus =
+  | { status: 'up_to_date'; version: string }
+  | { status: 'available'; version: string; notes: string | null }
+  | { status: 'error'; message: string }
+
+// Returned by open_document: the file's content and where the user left off in it
+interface OpenedDocument {
+  content: string
+  view_state: FileViewState | null
+  mtime: number | null
+}
+
+// A document as it was when it left the window, returned by reopen_closed
+interface ClosedDocument {
+  path: string | null
+  title: string
+  closed_at: number
+  content: string | null
+}
+
+// What the window shows, kept to record it as closed once it's replaced
+interface ShownDocument {
+  path: string | null
+  title: string
+  content: string
+  dirty: boolean
+  draftId: string
+}
+
+// Sent by the backend (confirm-close event) before a window with unsaved changes closes
+interface ConfirmClose {
+  quitting: boolean
+}
+
 interface Toast {
   id: number
   message: string
   type: ToastType
+  action?: ToastAction
+}
+
+// Sent by the backend (file-access event) when a file is opened or made writable
+interface FileAccess {
+  path: string
+  writable: boolean
+  mtime: number | null
+}
+
+// Unsaved document that didn't come from a file (e.g. piped to `markdowner -`)
+interface PendingContent {
+  content: string
+  title: string
+  // Cut off at the size limit or the read timeout
+  partial: boolean
+}
+
+interface ImportedDocument {
+  content: string
+  title: string
+  format: 'org' | 'asciidoc' | 'textile'
+  // What had no markdown equivalent and was kept as a fenced block of the original
+  warnings: { line: number; message: string }[]
+}
+
+// Returned by split_by_heading: the files of the parts, planned (dry run) or written
+interface SplitResult {
+  output_dir: string
+  files: { path: string; title: string | null; exists: boolean }[]
+  rewritten_links: number
+  written: boolean
+}
+
+// Returned by write_file. content is set when the file was written with different text than
+// the editor sent (smart typography on save).
+interface WriteResult {
+  queue_depth: number
+  content?: string
+  mtime: number | null
+}
+
+// Returned by toggle_task
+interface ToggledTask {
+  line: string
+  mtime: number | null
+}
+
+// Modification time of a file when the editor last read or saved it
+interface DiskMtime {
+  path: string
+  mtime: number | null
+}
+
+// Sent when a file handed to another app (File > Open With) changed on disk
+interface ExternalChange {
+  path: string
+  mtime: number
+}
+
+// Sent by batch_rename for every file it renamed
+interface FileRenamed {
+  old_path: string
+  new_path: string
+}
+
+// UTF-16 offsets into the text given to speak_text
+interface SpeechProgress {
+  start: number
+  end: number
+}
+
+// A footnote or reference link label in tidy_references' report
+interface ReferenceLabel {
+  kind: 'footnote' | 'link'
+  label: string
+  line: number
+}
+
+interface TidiedReferences {
+  content: string
+  report: {
+    relabeled: { kind: 'footnote' | 'link'; from: string; to: string }[]
+    removed_orphans: ReferenceLabel[]
+    removed_duplicates: ReferenceLabel[]
+    undefined: ReferenceLabel[]
+  }
+}
+
+// Draft snapshot of unsaved changes returned by list_drafts
+interface DraftInfo {
+  doc_id: string
+  original_path: string | null
+  saved_at: number
+  original_mtime: number | null
+  recoverable: boolean
 }
 
+// How long after the last edit unsaved changes are snapshotted while autosave is off
+const DRAFT_SNAPSHOT_DELAY_MS = 3000
+
+// How long after the cursor or scroll position last moved it's remembered for the file
+const VIEW_STATE_SAVE_DELAY_MS = 1000
+
+// Draft id for a document that hasn't been saved yet (saved documents use their path)
+const newUntitledDraftId = () =>
+  `untitled-${Date.now()}-${Math.random().toString(36).slice(2, 8)}`
+
+// The `expectedMtime` for a write to `path`, so a change made there by another app since the
+// editor read it is refused instead of overwritten
+const expectedMtime = (disk: DiskMtime | null, path: string) =>
+  disk?.path === path ? (disk.mtime ?? undefined) : undefined
+
+// Recent file entry returned by the backend; missing files are kept but flagged
+interface RecentFile {
+  path: string
+  display_path: string
+  exists: boolean
+}
+
+// Often opened files that aren't among the recents, best first
+// Where a missing recent file may have moved (relocate_recent)
+interface RelocationCandidate {
+  path: string
+  display_path: string
+  size: number
+  modified: number | null
+}
+
+interface FrequentFile extends RecentFile {
+  open_count: number
+  score: number
+}
+
+// How many frequent files the Recents menu lists below the recent ones
+const FREQUENT_FILES_SHOWN = 5
+
 function App() {
   const [markdown, setMarkdown] = useState<string>(
     '# Welcome to Markdown Editor\n\nStart typing your markdown here...\n\n## Features\n\n- **Live preview** - See your changes in real-time\n- **File operations** - Open and save markdown files\n- **Drag & drop** - Drop markdown files to open them\n- **Mermaid diagrams** - Render flowcharts and diagrams\n- **Math support** - LaTeX-style math expressions\n- **Syntax highlighting** - Code blocks with GitHub-style highlighting\n- **Clean interface** - Focus on your writing\n\n## Code Example\n\n```typescript\n// Example TypeScript code with syntax highlighting\ninterface User {\n  id: number;\n  name: string;\n  email: string;\n}\n\nfunction greetUser(user: User): string {\n  return `Hello, ${user.name}!`;\n}\n\nconst user: User = {\n  id: 1,\n  name: "Alice",\n  email: "alice@example.com"\n};\n\nconsole.log(greetUser(user));\n```\n\n## Math Expressions\n\nThis editor supports LaTeX-style math expressions using KaTeX.\n\n### Inline Math\nYou can write inline math like $E = mc^2$ or $\\frac{d}{dx}(x^2) = 2x$ right in your sentences.\n\n### Display Math\nFor more complex equations, use display math:\n\n$$\\int_{-\\infty}^{\\infty} e^{-x^2} dx = \\sqrt{\\pi}$$\n\n$$\\sum_{i=1}^{n} i = \\frac{n(n+1)}{2}$$\n\n$$\\begin{bmatrix} a & b \\\\ c & d \\end{bmatrix}$$\n\n## Mermaid Diagram Example\n\n```mermaid\nflowchart TD\n    A[Start] --> B{Is it working?}\n    B -->|Yes| C[Great!]\n    B -->|No| D[Debug]\n    D --> B\n    C --> E[Deploy]\n```\n\n> Tip: Use the toolbar buttons to open or save files, or drag and drop a markdown file onto the window!'
   )
   const [currentFile, setCurrentFile] = useState<string | null>(null)
+  // Shown in place of a file name for unsaved documents that came from elsewhere
+  const [untitledTitle, setUntitledTitle] = useState<string | null>(null)
+  const [fileAccess, setFileAccess] = useState<FileAccess | null>(null)
+  const isReadOnly = fileAccess !== null && fileAccess.path === currentFile && !fileAccess.writable
   const [isDirty, setIsDirty] = useState(false)
-  const [recentFiles, setRecentFiles] = useState<string[]>([])
+  const [recentFiles, setRecentFiles] = useState<RecentFile[]>([])
+  const [frequentFiles, setFrequentFiles] = useState<FrequentFile[]>([])
   const [showRecents, setShowRecents] = useState(false)
   const [isDragging, setIsDragging] = useState(false)
   const [toasts, setToasts] = useState<Toast[]>([])
@@ -50,9 +291,15 @@ function App() {
   const [viewMode, setViewMode] = useState<ViewMode>('split')
   const recentsRef = useRef<HTMLDivElement>(null)
   const toastIdRef = useRef(0)
+  const untitledDraftIdRef = useRef(newUntitledDraftId())
+  const draftId = currentFile ?? untitledDraftIdRef.current
+  const previousDraftIdRef = useRef(draftId)
 
   // Search state
   const [showSearch, setShowSearch] = useState(false)
+  const [showShortcuts, setShowShortcuts] = useState(false)
+  const [showLogs, setShowLogs] = useState(false)
+  const [closeRequest, setCloseRequest] = useState<ConfirmClose | null>(null)
   const [searchQuery, setSearchQuery] = useState('')
   const [replaceQuery, setReplaceQuery] = useState('')
   const [caseSensitive, setCaseSensitive] = useState(false)
@@ -63,12 +310,35 @@ function App() {
 
   // Debounced markdown rendering with sanitization
   const [html, setHtml] = useState<string>('')
+  const [customCss, setCustomCss] = useState('')
+  const [editorSettings, setEditorSettings] = useState<EditorSettings>(DEFAULT_EDITOR_SETTINGS)
   const previewRef = useRef<HTMLDivElement>(null)
   const editorRef = useRef<HTMLTextAreaElement>(null)
+  // Where the text being read aloud starts in the document, or null when not speaking
+  const speechBaseRef = useRef<number | null>(null)
 
   // Synchronized scrolling state
   const isScrolling = useRef(false)
   const scrollTimeout = useRef<number | null>(null)
+  // View state of the file just opened, applied once its content is in the editor
+  const restoreViewStateRef = useRef<FileViewState | null>(null)
+  const diskMtimeRef = useRef<DiskMtime | null>(null)
+  const viewStateTimer = useRef<number | null>(null)
+
+  // Settles once the open document's folder may serve its images to the preview
+  const assetScopeRef = useRef<Promise<unknown>>(Promise.resolve())
+
+  useEffect(() => {
+    if (!currentFile) return
+    assetScopeRef.current = invoke('allow_document_assets', { documentPath: currentFile }).catch(
+      error => console.error('Failed to allow document assets:', error)
+    )
+    return () => {
+      invoke('revoke_document_assets', { documentPath: currentFile }).catch(error =>
+        console.error('Failed to revoke document assets:', error)
+      )
+    }
+  }, [currentFile])
 
   useEffect(() => {
     const renderMarkdown = async () => {
@@ -80,10 +350,16 @@ function App() {
         caseSensitive,
         currentMatchIndex
       )
-      setHtml(sanitizedHtml)
+      if (!currentFile) {
+        setHtml(sanitizedHtml)
+        return
+      }
+      // Local images load through the asset scheme, after sanitizing (which drops its URLs)
+      await assetScopeRef.current
+      setHtml(await resolveAssetUrls(sanitizedHtml, currentFile))
     }
     renderMarkdown()
-  }, [markdown, showSearch, searchQuery, caseSensitive, currentMatchIndex])
+  }, [markdown, currentFile, showSearch, searchQuery, caseSensitive, currentMatchIndex])
 
   // Scroll active search highlight into view in preview
   useEffect(() => {
@@ -115,19 +391,220 @@ function App() {
   useMath(previewRef, html)
 
   // Toast notification helper
-  const showToast = useCallback((message: string, type: ToastType = 'info') => {
-    const id = ++toastIdRef.current
-    setToasts(prev => [...prev, { id, message, type }])
-    // Auto-remove toast after 5 seconds
-    setTimeout(() => {
-      setToasts(prev => prev.filter(t => t.id !== id))
-    }, 5000)
-  }, [])
+  const showToast = useCallback(
+    (message: string, type: ToastType = 'info', action?: ToastAction) => {
+      const id = ++toastIdRef.current
+      setToasts(prev => [...prev, { id, message, type, action }])
+      // Auto-remove toast after 5 seconds
+      setTimeout(() => {
+        setToasts(prev => prev.filter(t => t.id !== id))
+      }, 5000)
+    },
+    []
+  )
+
+  // A stylesheet left out of an export doesn't fail it, but the user should know why their
+  // styles are missing
+  const showStyleWarnings = useCallback(
+    (warnings: string[]) => {
+      warnings.forEach(warning => showToast(warning, 'info'))
+    },
+    [showToast]
+  )
+
+  // Report a file that couldn't be opened. Binary files (e.g. an image renamed to .md)
+  // get a button to show them in the file manager instead.
+  const showOpenError = useCallback(
+    (error: unknown) => {
+      console.error('Failed to open file:', error)
+      if (isCommandError(error) && error.code === 'binary_file') {
+        const path = String(error.details?.path ?? '')
+        showToast(error.message, 'error', {
+          label: 'Show in folder',
+          onClick: () => revealItemInDir(path),
+        })
+        return
+      }
+      showToast(`Failed to open file: ${errorMessage(error)}`, 'error')
+    },
+    [showToast]
+  )
 
   const removeToast = useCallback((id: number) => {
     setToasts(prev => prev.filter(t => t.id !== id))
   }, [])
 
+  // Track whether the open file can be saved (shown as a lock in the title bar)
+  useEffect(() => {
+    const unlistenFileAccess = listen<FileAccess>('file-access', event => {
+      setFileAccess(event.payload)
+    })
+
+    return () => {
+      unlistenFileAccess.then(fn => fn())
+    }
+  }, [])
+
+  const handleMakeWritable = useCallback(
+    async (path: string) => {
+      try {
+        await invoke('make_writable', { path })
+        showToast(`${path.split('/').pop()} is now writable`, 'success')
+      } catch (error) {
+        showToast(`Failed to make file writable: ${errorMessage(error)}`, 'error')
+      }
+    },
+    [showToast]
+  )
+
+  const discardDraft = useCallback(async (docId: string) => {
+    try {
+      await invoke('discard_draft', { docId })
+    } catch (error) {
+      console.error('Failed to discard draft:', error)
+    }
+  }, [])
+
+  // Snapshot unsaved changes an autosave interval after the last edit so they survive a
+  // crash. Turning autosave off only goes back to the default delay.
+  const { autosave_enabled: autosaveEnabled, autosave_interval_secs: autosaveIntervalSecs } =
+    editorSettings
+  useEffect(() => {
+    if (!isDirty) return
+    const timer = setTimeout(async () => {
+      try {
+        await invoke('save_draft', { docId: draftId, content: markdown, originalPath: currentFile })
+      } catch (error) {
+        console.error('Failed to save draft:', error)
+      }
+    }, autosaveEnabled ? autosaveIntervalSecs * 1000 : DRAFT_SNAPSHOT_DELAY_MS)
+    return () => clearTimeout(timer)
+  }, [isDirty, markdown, currentFile, draftId, autosaveEnabled, autosaveIntervalSecs])
+
+  // A document replaced by New, Open or a Save As under another name is done with its draft
+  useEffect(() => {
+    if (previousDraftIdRef.current !== draftId) {
+      discardDraft(previousDraftIdRef.current)
+      previousDraftIdRef.current = draftId
+    }
+  }, [draftId, discardDraft])
+
+  // Remember a document leaving the window for Reopen Closed. Its content is only kept
+  // when it has unsaved changes; an untouched untitled document isn't worth reopening.
+  const recordClosedDocument = useCallback((shown: ShownDocument) => {
+    if (!shown.path && !shown.dirty) return
+    invoke('record_closed_document', {
+      path: shown.path,
+      title: shown.title,
+      content: shown.dirty ? shown.content : null,
+    }).catch(error => console.error('Failed to record closed document:', error))
+  }, [])
+
+  const shownDocumentRef = useRef<ShownDocument | null>(null)
+  useEffect(() => {
+    const shown = shownDocumentRef.current
+    if (shown && shown.draftId !== draftId) {
+      // Save As of an untitled document keeps it open under its new path
+      const savedAs = !shown.path && currentFile !== null && shown.content === markdown
+      if (!savedAs) {
+        recordClosedDocument(shown)
+      }
+    }
+    shownDocumentRef.current = {
+      path: currentFile,
+      title: currentFile
+        ? (currentFile.split('/').pop() ?? currentFile)
+        : (untitledTitle ?? 'Untitled'),
+      content: markdown,
+      dirty: isDirty,
+      draftId,
+    }
+  })
+
+  // Closing the window is an explicit close, so don't offer the changes again next time.
+  // With unsaved changes the backend asks first (confirm-close) and closes once answered.
+  useEffect(() => {
+    const unlistenClose = getCurrentWindow().onCloseRequested(async event => {
+      if (isDirty) {
+        event.preventDefault()
+        return
+      }
+      if (shownDocumentRef.current) {
+        recordClosedDocument(shownDocumentRef.current)
+      }
+      await discardDraft(draftId)
+    })
+
+    return () => {
+      unlistenClose.then(fn => fn())
+    }
+  }, [isDirty, draftId, discardDraft, recordClosedDocument])
+
+  // Title the window after the open file (and on macOS, give the title bar its proxy icon)
+  useEffect(() => {
+    invoke('set_window_document', {
+      windowLabel: getCurrentWindow().label,
+      path: currentFile,
+    }).catch(error => console.error('Failed to update the window title:', error))
+  }, [currentFile])
+
+  // Let the backend know about unsaved changes so closing the window or quitting asks first
+  useEffect(() => {
+    invoke('set_dirty', { windowLabel: getCurrentWindow().label, dirty: isDirty }).catch(error =>
+      console.error('Failed to report unsaved changes:', error)
+    )
+  }, [isDirty])
+
+  useEffect(() => {
+    const unlistenConfirmClose = getCurrentWindow().listen<ConfirmClose>('confirm-close', event => {
+      setCloseRequest(event.payload)
+    })
+
+    return () => {
+      unlistenConfirmClose.then(fn => fn())
+    }
+  }, [])
+
+  const restoreDraft = useCallback(
+    async (draft: DraftInfo) => {
+      try {
+        const content = await invoke<string>('read_draft', { docId: draft.doc_id })
+        if (!draft.original_path) {
+          untitledDraftIdRef.current = draft.doc_id
+        }
+        setMarkdown(content)
+        setCurrentFile(draft.original_path)
+        setIsDirty(true)
+        showToast('Unsaved changes recovered', 'success')
+      } catch (error) {
+        showToast(`Failed to recover changes: ${errorMessage(error)}`, 'error')
+      }
+    },
+    [showToast]
+  )
+
+  // Offer to recover changes left unsaved by a crash, unless launched with `--new`
+  useEffect(() => {
+    const checkDrafts = async () => {
+      try {
+        const options = await invoke<{ new_document: boolean } | null>('get_launch_options')
+        if (options?.new_document) return
+        const drafts = await invoke<DraftInfo[] | null>('list_drafts')
+        const draft = drafts?.find(d => d.recoverable)
+        if (draft) {
+          const name = draft.original_path?.split('/').pop() ?? 'an untitled document'
+          showToast(`Recover unsaved changes to ${name}?`, 'info', {
+            label: 'Recover',
+            onClick: () => restoreDraft(draft),
+          })
+        }
+      } catch (error) {
+        console.error('Failed to list drafts:', error)
+      }
+    }
+    checkDrafts()
+  }, [showToast, restoreDraft])
+
   // Search functionality - helper to escape regex
   const escapeRegExp = (string: string) => {
     return string.replace(/[.*+?^${}()|[\]\\]/g, '\\$&')
@@ -314,6 +791,17 @@ function App() {
     loadRecentFiles()
   }, [])
 
+  // Show a one-time notice when the backend had to recover a corrupted store file
+  useEffect(() => {
+    invoke<{ backup_path: string; salvaged_keys: string[] } | null>('take_store_recovery')
+      .then(recovery => {
+        if (recovery) {
+          showToast('Saved app data was damaged and has been reset (a backup was kept)', 'info')
+        }
+      })
+      .catch(error => console.error('Failed to check store recovery:', error))
+  }, [showToast])
+
   // Close recents dropdown when clicking outside
   useEffect(() => {
     const handleClickOutside = (event: MouseEvent) => {
@@ -330,42 +818,57 @@ function App() {
 
   const loadRecentFiles = async () => {
     try {
-      const files = await invoke<string[]>('get_recent_files')
+      const [files, frequent] = await Promise.all([
+        invoke<RecentFile[]>('get_recent_files'),
+        invoke<FrequentFile[]>('get_frequent_files', { limit: FREQUENT_FILES_SHOWN }),
+      ])
       setRecentFiles(files)
+      setFrequentFiles(frequent)
     } catch (error) {
       console.error('Failed to load recent files:', error)
     }
   }
 
   const handleNewFile = useCallback(() => {
+    untitledDraftIdRef.current = newUntitledDraftId()
     setMarkdown('# New Document\n\nStart writing here...')
     setCurrentFile(null)
+    setUntitledTitle(null)
     setIsDirty(false)
     showToast('New document created', 'success')
   }, [showToast])
 
   const handleOpenFile = useCallback(async () => {
     try {
-      const filePath = await invoke<string | null>('open_file_dialog')
+      const filePaths = await invoke<string[]>('open_files_dialog')
+      const [filePath, ...others] = filePaths ?? []
       if (filePath) {
-        const content = await invoke<string>('read_file', { path: filePath })
-        setMarkdown(content)
+        const opened = await invokeInScope<OpenedDocument>('open_document', { path: filePath })
+        restoreViewStateRef.current = opened.view_state
+        diskMtimeRef.current = { path: filePath, mtime: opened.mtime }
+        setMarkdown(opened.content)
         setCurrentFile(filePath)
         setIsDirty(false)
         loadRecentFiles()
-        showToast(`Opened: ${filePath.split('/').pop()}`, 'success')
+        // There's a single editor, so the other picked files wait in Recent Files
+        const queued = others.length ? ` (${others.length} more in Recent Files)` : ''
+        showToast(`Opened: ${filePath.split('/').pop()}${queued}`, 'success')
       }
     } catch (error) {
-      console.error('Failed to open file:', error)
-      showToast(`Failed to open file: ${error}`, 'error')
+      showOpenError(error)
     }
-  }, [showToast])
+  }, [showOpenError])
 
   const handleOpenRecentFile = useCallback(
-    async (filePath: string) => {
+    async (filePath: string, location?: PendingOpen) => {
       try {
-        const content = await invoke<string>('read_file', { path: filePath })
-        setMarkdown(content)
+        const opened = await invokeInScope<OpenedDocument>('open_document', { path: filePath })
+        // A location given with the file (`notes.md:120`, `notes.md#setup`) beats where the
+        // user left off
+        restoreViewStateRef.current =
+          (location && locationViewState(opened.content, location)) ?? opened.view_state
+        diskMtimeRef.current = { path: filePath, mtime: opened.mtime }
+        setMarkdown(opened.content)
         setCurrentFile(filePath)
         setIsDirty(false)
         // Add to recents and reload the list
@@ -374,15 +877,35 @@ function App() {
         setShowRecents(false)
         showToast(`Opened: ${filePath.split('/').pop()}`, 'success')
       } catch (error) {
-        console.error('Failed to open file:', error)
-        showToast(`Failed to open file: ${error}`, 'error')
-        // Remove from recents if file no longer exists or is inaccessible
-        if (String(error).includes('does not exist') || String(error).includes('not readable')) {
-          loadRecentFiles() // Refresh list which will filter out invalid files
+        showOpenError(error)
+        // Refresh the list so a file that's gone or inaccessible is shown as missing
+        const unavailable = ['not_found', 'permission_denied']
+        if (isCommandError(error) && unavailable.includes(error.code)) {
+          loadRecentFiles()
+        }
+        // Offer the likeliest new location of a moved file; nothing changes unless accepted
+        if (isCommandError(error) && error.code === 'not_found') {
+          const [candidate] = await invoke<RelocationCandidate[]>('relocate_recent', {
+            path: filePath,
+            workspace: null,
+          }).catch(() => [])
+          if (candidate) {
+            showToast(`This file seems to have moved to ${candidate.display_path}`, 'info', {
+              label: 'Update entry',
+              onClick: async () => {
+                try {
+                  await invoke('update_recent_path', { old: filePath, new: candidate.path })
+                  loadRecentFiles()
+                } catch (updateError) {
+                  showToast(`Failed to update recent file: ${errorMessage(updateError)}`, 'error')
+                }
+              },
+            })
+          }
         }
       }
     },
-    [showToast]
+    [showToast, showOpenError]
   )
 
   // Check for pending file (when app is opened via file association)
@@ -390,8 +913,25 @@ function App() {
   useEffect(() => {
     const checkPendingFile = async () => {
       try {
-        const pendingFile = await invoke<string | null>('get_pending_file')
-        if (pendingFile) {
+        // Content piped to `markdowner -` opens as an unsaved document
+        const pendingContent = await invoke<PendingContent | null>('get_pending_content')
+        if (pendingContent) {
+          setMarkdown(pendingContent.content)
+          setCurrentFile(null)
+          setUntitledTitle(pendingContent.title)
+          setIsDirty(true)
+          showToast(
+            pendingContent.partial
+              ? 'Opened piped input (incomplete: it was cut off)'
+              : 'Opened piped input',
+            pendingContent.partial ? 'info' : 'success'
+          )
+        }
+
+        const pending = await invoke<PendingOpen | string | null>('get_pending_file')
+        if (pending) {
+          const pendingOpen = toPendingOpen(pending)
+          const pendingFile = pendingOpen.path
           console.log('Pending file found:', pendingFile)
           // Validate it's a markdown file
           const isMarkdown =
@@ -400,13 +940,14 @@ function App() {
             pendingFile.toLowerCase().endsWith('.mdx')
 
           if (isMarkdown) {
-            await handleOpenRecentFile(pendingFile)
+            await handleOpenRecentFile(pendingFile, pendingOpen)
           } else {
             showToast('Please open a markdown file (.md, .markdown, or .mdx)', 'error')
           }
         }
       } catch (error) {
         console.error('Failed to check for pending file:', error)
+        showToast(`Failed to open pending document: ${errorMessage(error)}`, 'error')
       }
     }
 
@@ -422,26 +963,73 @@ function App() {
     try {
       let filePath = currentFile
       if (!filePath) {
-        filePath = await invoke<string | null>('save_file_dialog')
+        filePath = await invoke<string | null>('save_file_dialog', { content: markdown })
       }
       if (filePath) {
-        await invoke('write_file', { path: filePath, content: markdown })
+        const written = await invokeInScope<WriteResult>('write_file', {
+          path: filePath,
+          content: markdown,
+          expectedMtime: expectedMtime(diskMtimeRef.current, filePath),
+        })
+        diskMtimeRef.current = { path: filePath, mtime: written.mtime }
+        if (written.content !== undefined) setMarkdown(written.content)
+        discardDraft(filePath)
         setCurrentFile(filePath)
         setIsDirty(false)
         loadRecentFiles()
         showToast(`Saved: ${filePath.split('/').pop()}`, 'success')
+        return true
       }
     } catch (error) {
       console.error('Failed to save file:', error)
-      showToast(`Failed to save file: ${error}`, 'error')
+      if (isCommandError(error) && error.code === 'read_only' && currentFile) {
+        showToast(error.message, 'error', {
+          label: 'Make writable',
+          onClick: () => handleMakeWritable(currentFile),
+        })
+        return false
+      }
+      if (isCommandError(error) && error.code === 'conflict') {
+        showToast(error.message, 'error', {
+          label: 'Overwrite',
+          onClick: () => {
+            diskMtimeRef.current = null
+            handleSaveFile()
+          },
+        })
+        return false
+      }
+      showToast(`Failed to save file: ${errorMessage(error)}`, 'error')
     }
-  }, [currentFile, markdown, showToast])
+    return false
+  }, [currentFile, markdown, showToast, handleMakeWritable, discardDraft])
 
   const handleSaveAsFile = useCallback(async () => {
     try {
-      const filePath = await invoke<string | null>('save_file_dialog')
+      const filePath = await invoke<string | null>('save_file_dialog', { content: markdown })
       if (filePath) {
-        await invoke('write_file', { path: filePath, content: markdown })
+        // Relative links and images keep pointing at the same files from the new folder
+        let content = markdown
+        if (currentFile) {
+          const relocated = await invoke<{ content: string; unpreserved: string[] }>(
+            'rewrite_links_for_save_as',
+            { content: markdown, oldPath: currentFile, newPath: filePath },
+          )
+          content = relocated.content
+          if (relocated.unpreserved.length > 0) {
+            showToast(
+              `${relocated.unpreserved.length} link(s) point outside the workspace and may no longer work`,
+              'info',
+            )
+          }
+        }
+        const written = await invokeInScope<WriteResult>('write_file', {
+          path: filePath,
+          content,
+        })
+        diskMtimeRef.current = { path: filePath, mtime: written.mtime }
+        setMarkdown(written.content ?? content)
+        discardDraft(filePath)
         setCurrentFile(filePath)
         setIsDirty(false)
         loadRecentFiles()
@@ -449,18 +1037,33 @@ function App() {
       }
     } catch (error) {
       console.error('Failed to save file:', error)
-      showToast(`Failed to save file: ${error}`, 'error')
+      showToast(`Failed to save file: ${errorMessage(error)}`, 'error')
     }
-  }, [markdown, showToast])
+  }, [currentFile, markdown, showToast, discardDraft])
 
   const handleClearRecents = useCallback(async () => {
     try {
       await invoke('clear_recent_files')
       setRecentFiles([])
-      showToast('Recent files cleared', 'info')
+      // Open counts are kept unless the user asks; the frequent list shows what's left
+      const frequent = await invoke<FrequentFile[]>('get_frequent_files', {
+        limit: FREQUENT_FILES_SHOWN,
+      })
+      setFrequentFiles(frequent)
+      showToast('Recent files cleared', 'info', {
+        label: 'Clear Frequent Too',
+        onClick: () => {
+          invoke('clear_frequent_files')
+            .then(() => {
+              setFrequentFiles([])
+              showToast('Frequent files cleared', 'info')
+            })
+            .catch(error => showToast(errorMessage(error), 'error'))
+        },
+      })
     } catch (error) {
       console.error('Failed to clear recent files:', error)
-      showToast(`Failed to clear recent files: ${error}`, 'error')
+      showToast(`Failed to clear recent files: ${errorMessage(error)}`, 'error')
     }
   }, [showToast])
 
@@ -469,6 +1072,60 @@ function App() {
     setIsDirty(true)
   }, [])
 
+  // Pasting a bare URL turns it into `[Page Title](url)` once the title arrives. The URL is
+  // pasted as-is first and stays that way if the title can't be fetched or the text around
+  // it changed in the meantime.
+  //
+  // Pasting a URL over selected text makes `[selection](url)` with the URL cleaned up by
+  // the backend. Text that isn't a linkable URL replaces the selection as usual.
+  const handleEditorPaste = useCallback((e: React.ClipboardEvent<HTMLTextAreaElement>) => {
+    const url = e.clipboardData.getData('text/plain')
+    const editor = e.currentTarget
+    const start = editor.selectionStart
+    const end = editor.selectionEnd
+    const selection = editor.value.slice(start, end)
+    if (selection && !selection.includes('\n') && url.trim() && !/\s/.test(url.trim())) {
+      e.preventDefault()
+      const insert = (text: string) => {
+        // The selection may have moved while the backend answered
+        if (editor.value.slice(start, end) !== selection) return
+        editor.focus()
+        editor.setSelectionRange(start, end)
+        document.execCommand('insertText', false, text)
+      }
+      invoke<boolean>('is_probable_url', { text: url })
+        .then(probable => {
+          if (!probable) throw new Error('Not a URL')
+          return invoke<string>('normalize_url', { input: url })
+        })
+        .then(normalized => insert(markdownLink(selection, normalized)))
+        .catch(() => insert(url))
+      return
+    }
+    const insideLink = /(\]\(|<)$/.test(editor.value.slice(Math.max(0, start - 2), start))
+    if (!isBareUrl(url) || start !== editor.selectionEnd || insideLink) return
+    invoke<string>('fetch_link_title', { url })
+      .then(title => {
+        setMarkdown(current =>
+          current.slice(start, start + url.length) === url
+            ? current.slice(0, start) + markdownLink(title, url) + current.slice(start + url.length)
+            : current
+        )
+      })
+      .catch(() => {})
+  }, [])
+
+  // Tab indents by the configured amount instead of leaving the editor. execCommand keeps
+  // the edit on the undo stack.
+  const handleEditorKeyDown = useCallback(
+    (e: React.KeyboardEvent<HTMLTextAreaElement>) => {
+      if (e.key !== 'Tab' || e.shiftKey || e.altKey || e.ctrlKey || e.metaKey) return
+      e.preventDefault()
+      document.execCommand('insertText', false, indentText(editorSettings))
+    },
+    [editorSettings]
+  )
+
   // Synchronized scroll handler
   const syncScroll = useCallback((source: 'editor' | 'preview') => {
     if (isScrolling.current) return
@@ -509,16 +1166,99 @@ function App() {
     }, 50)
   }, [])
 
+  // Put the cursor and scroll position back where they were when the file was last open
+  useEffect(() => {
+    const viewState = restoreViewStateRef.current
+    const editor = editorRef.current
+    if (!viewState || !editor) return
+    restoreViewStateRef.current = null
+    const offset = offsetForPosition(markdown, viewState.cursor_line, viewState.cursor_col)
+    editor.setSelectionRange(offset, offset)
+    editor.scrollTop = viewState.scroll_percent * (editor.scrollHeight - editor.clientHeight)
+  }, [markdown])
+
+  // Remember the cursor and scroll position shortly after they stop changing
+  const scheduleViewStateSave = useCallback(() => {
+    if (!currentFile) return
+    if (viewStateTimer.current) {
+      window.clearTimeout(viewStateTimer.current)
+    }
+    viewStateTimer.current = window.setTimeout(() => {
+      viewStateTimer.current = null
+      const editor = editorRef.current
+      if (!editor) return
+      const { line, col } = cursorPosition(editor.value, editor.selectionStart)
+      const scrollable = editor.scrollHeight - editor.clientHeight
+      const state: FileViewState = {
+        cursor_line: line,
+        cursor_col: col,
+        scroll_percent: scrollable > 0 ? editor.scrollTop / scrollable : 0,
+        folded_sections: [],
+      }
+      invoke('save_file_view_state', { path: currentFile, state }).catch(error =>
+        console.error('Failed to save view state:', error)
+      )
+    }, VIEW_STATE_SAVE_DELAY_MS)
+  }, [currentFile])
+
+  // A pending save belongs to the file that was open when it was scheduled
+  useEffect(() => {
+    return () => {
+      if (viewStateTimer.current) {
+        window.clearTimeout(viewStateTimer.current)
+        viewStateTimer.current = null
+      }
+    }
+  }, [currentFile])
+
   // Handle editor scroll
   const handleEditorScroll = useCallback(() => {
     syncScroll('editor')
-  }, [syncScroll])
+    scheduleViewStateSave()
+  }, [syncScroll, scheduleViewStateSave])
 
   // Handle preview scroll
   const handlePreviewScroll = useCallback(() => {
     syncScroll('preview')
   }, [syncScroll])
 
+  // Preview checkboxes are live: ticking one toggles its task line. A saved document is
+  // updated on disk right away; with unsaved changes only the editor text changes.
+  useEffect(() => {
+    previewRef.current
+      ?.querySelectorAll('input[type="checkbox"][disabled]')
+      .forEach(checkbox => checkbox.removeAttribute('disabled'))
+  }, [html])
+
+  const handlePreviewClick = useCallback(
+    async (e: React.MouseEvent<HTMLDivElement>) => {
+      const target = e.target
+      if (!(target instanceof HTMLInputElement) || target.type !== 'checkbox') return
+      // The preview re-renders from the toggled markdown
+      e.preventDefault()
+      const checkboxes = Array.from(e.currentTarget.querySelectorAll('input[type="checkbox"]'))
+      const lineNumber = taskLineNumbers(markdown)[checkboxes.indexOf(target)]
+      if (lineNumber === undefined) return
+      if (!currentFile || isDirty) {
+        setMarkdown(toggleTaskLine(markdown, lineNumber))
+        setIsDirty(true)
+        return
+      }
+      try {
+        const toggled = await invokeInScope<ToggledTask>('toggle_task', {
+          path: currentFile,
+          lineNumber,
+          expectedMtime: expectedMtime(diskMtimeRef.current, currentFile),
+        })
+        diskMtimeRef.current = { path: currentFile, mtime: toggled.mtime }
+        setMarkdown(current => replaceLine(current, lineNumber, toggled.line))
+      } catch (error) {
+        showToast(`Failed to update task: ${errorMessage(error)}`, 'error')
+      }
+    },
+    [markdown, currentFile, isDirty, showToast]
+  )
+
   // Cleanup scroll timeout on unmount
   useEffect(() => {
     return () => {
@@ -550,19 +1290,20 @@ function App() {
         }
 
         try {
-          const content = await invoke<string>('read_file', { path: filePath })
-          setMarkdown(content)
+          const opened = await invokeInScope<OpenedDocument>('open_document', { path: filePath })
+          restoreViewStateRef.current = opened.view_state
+          diskMtimeRef.current = { path: filePath, mtime: opened.mtime }
+          setMarkdown(opened.content)
           setCurrentFile(filePath)
           setIsDirty(false)
           loadRecentFiles()
           showToast(`Opened: ${filePath.split('/').pop()}`, 'success')
         } catch (error) {
-          console.error('Failed to open file:', error)
-          showToast(`Failed to open file: ${error}`, 'error')
+          showOpenError(error)
         }
       }
     },
-    [showToast]
+    [showToast, showOpenError]
   )
 
   // Set up Tauri drag-drop event listeners
@@ -587,24 +1328,28 @@ function App() {
 
   // Set up dock drag-drop event listener (macOS)
   useEffect(() => {
-    // Listen for dock-open-file event from Rust
-    const unlistenDockFile = listen<string>('dock-open-file', event => {
-      console.log('Received dock-open-file event:', event.payload)
-      const filePath = event.payload
-      if (filePath) {
-        // Validate it's a markdown file
-        const isMarkdown =
-          filePath.toLowerCase().endsWith('.md') ||
-          filePath.toLowerCase().endsWith('.markdown') ||
-          filePath.toLowerCase().endsWith('.mdx')
+    // Listen for dock-open-file event from Rust, sent only to the window a file is routed to
+    const unlistenDockFile = getCurrentWindow().listen<PendingOpen | string>(
+      'dock-open-file',
+      event => {
+        console.log('Received dock-open-file event:', event.payload)
+        const pendingOpen = event.payload ? toPendingOpen(event.payload) : null
+        const filePath = pendingOpen?.path
+        if (pendingOpen && filePath) {
+          // Validate it's a markdown file
+          const isMarkdown =
+            filePath.toLowerCase().endsWith('.md') ||
+            filePath.toLowerCase().endsWith('.markdown') ||
+            filePath.toLowerCase().endsWith('.mdx')
 
-        if (isMarkdown) {
-          handleOpenRecentFile(filePath)
-        } else {
-          showToast('Please open a markdown file (.md, .markdown, or .mdx)', 'error')
+          if (isMarkdown) {
+            handleOpenRecentFile(filePath, pendingOpen)
+          } else {
+            showToast('Please open a markdown file (.md, .markdown, or .mdx)', 'error')
+          }
         }
       }
-    })
+    )
 
     return () => {
       unlistenDockFile.then(fn => fn())
@@ -642,29 +1387,839 @@ function App() {
     }
   }, [handleNewFile, handleOpenFile, handleSaveFile, handleSaveAsFile])
 
-  // HTML5 drag and drop handlers for visual feedback
-  const handleDragEnter = useCallback((e: React.DragEvent) => {
-    e.preventDefault()
-    e.stopPropagation()
-  }, [])
+  // Set up app data export/import menu listeners (the backend shows the file dialogs)
+  useEffect(() => {
+    const unlistenExport = listen<void>('menu-export-app-data', async () => {
+      try {
+        const path = await invoke<string | null>('export_app_data', { outputPath: null })
+        if (path) {
+          showToast(`App data exported to ${path.split('/').pop()}`, 'success')
+        }
+      } catch (error) {
+        showToast(`Failed to export app data: ${errorMessage(error)}`, 'error')
+      }
+    })
 
-  const handleDragLeave = useCallback((e: React.DragEvent) => {
-    e.preventDefault()
-    e.stopPropagation()
-  }, [])
+    const unlistenImport = listen<void>('menu-import-app-data', async () => {
+      try {
+        const report = await invoke<{ imported: string[]; skipped: unknown[] } | null>(
+          'import_app_data',
+          { path: null, mode: 'merge' }
+        )
+        if (report) {
+          loadRecentFiles()
+          const skipped = report.skipped.length ? `, ${report.skipped.length} skipped` : ''
+          showToast(`Imported ${report.imported.length} item(s)${skipped}`, 'success')
+        }
+      } catch (error) {
+        showToast(`Failed to import app data: ${errorMessage(error)}`, 'error')
+      }
+    })
 
-  const handleDragOver = useCallback((e: React.DragEvent) => {
-    e.preventDefault()
-    e.stopPropagation()
-  }, [])
+    return () => {
+      unlistenExport.then(fn => fn())
+      unlistenImport.then(fn => fn())
+    }
+  }, [showToast])
 
-  const handleDrop = useCallback((e: React.DragEvent) => {
-    // Prevent default to allow the Tauri native event to handle it
-    e.preventDefault()
-    e.stopPropagation()
-  }, [])
+  // File > Import Document: convert an org, AsciiDoc or Textile file into a new unsaved
+  // document, leaving the original alone
+  useEffect(() => {
+    const unlistenImportDocument = listen<void>('menu-import-document', async () => {
+      try {
+        const imported = await invoke<ImportedDocument | null>('import_document', { path: null })
+        if (!imported) return
+        setMarkdown(imported.content)
+        setCurrentFile(null)
+        setUntitledTitle(imported.title)
+        setIsDirty(true)
+        const count = imported.warnings.length
+        if (count > 0) {
+          const first = imported.warnings[0]
+          const more = count > 1 ? ` (and ${count - 1} more)` : ''
+          showToast(`Imported with changes: line ${first.line}: ${first.message}${more}`, 'info')
+        } else {
+          showToast(`Imported ${imported.title}`, 'success')
+        }
+      } catch (error) {
+        showToast(`Failed to import: ${errorMessage(error)}`, 'error')
+      }
+    })
 
-  // Get toast icon based on type
+    return () => {
+      unlistenImportDocument.then(fn => fn())
+    }
+  }, [showToast])
+
+  // Start an unsaved document from whatever is on the clipboard
+  useEffect(() => {
+    const unlistenNewFromClipboard = listen<void>('menu-new-from-clipboard', async () => {
+      try {
+        const result = await invoke<{ kind: 'markdown' | 'empty'; markdown?: string } | null>(
+          'new_from_clipboard'
+        )
+        if (result?.kind === 'markdown' && result.markdown !== undefined) {
+          setMarkdown(result.markdown)
+          setCurrentFile(null)
+          setUntitledTitle(null)
+          setIsDirty(true)
+          showToast('New document created from clipboard', 'success')
+        } else if (result?.kind === 'empty') {
+          showToast('The clipboard is empty', 'info')
+        }
+      } catch (error) {
+        showToast(`Failed to read clipboard: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenNewFromClipboard.then(fn => fn())
+    }
+  }, [showToast])
+
+  // Text sent from another app (the macOS Services menu) opens as an unsaved document
+  useEffect(() => {
+    const unlistenPendingContent = listen<void>('pending-content', async () => {
+      try {
+        const pendingContent = await invoke<PendingContent | null>('get_pending_content')
+        if (pendingContent) {
+          setMarkdown(pendingContent.content)
+          setCurrentFile(null)
+          setUntitledTitle(pendingContent.title)
+          setIsDirty(true)
+          showToast(`Opened ${pendingContent.title.toLowerCase()}`, 'success')
+        }
+      } catch (error) {
+        showToast(`Failed to open shared text: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenPendingContent.then(fn => fn())
+    }
+  }, [showToast])
+
+  // The custom CSS (global setting, then the document's `stylesheet:` frontmatter key) for
+  // the preview. Reloaded when the file changes or is saved, since the backend reads the
+  // frontmatter from disk.
+  useEffect(() => {
+    if (isDirty) return
+    let cancelled = false
+    invoke<EffectiveStyles>('get_effective_styles', { documentPath: currentFile })
+      .then(styles => {
+        if (!cancelled) setCustomCss(styles.css)
+      })
+      .catch(error => console.error('Failed to load custom styles:', error))
+    return () => {
+      cancelled = true
+    }
+  }, [currentFile, isDirty])
+
+  // Export the rendered preview as HTML, copying referenced images next to it. A selection
+  // in the preview exports just that part.
+  useEffect(() => {
+    const unlistenExportHtml = listen<void>('menu-export-html', async () => {
+      let selectionHtml: string | null = null
+      const selection = window.getSelection()
+      const preview = previewRef.current
+      if (selection && !selection.isCollapsed && preview?.contains(selection.anchorNode)) {
+        const container = document.createElement('div')
+        container.appendChild(selection.getRangeAt(0).cloneContents())
+        selectionHtml = container.innerHTML
+      }
+      try {
+        const defaults = await invoke<ExportDefaults>('get_export_defaults', {
+          documentPath: currentFile,
+          markdown,
+        })
+        const result = await invoke<ExportResult | null>('export_html', {
+          documentPath: currentFile,
+          markdown,
+          html,
+          outputPath: null,
+          options: { copyAssets: true, selectionHtml, appTheme: appPalette(), defaults },
+        })
+        if (result) {
+          const assets = result.copied_assets.length
+            ? ` + ${result.copied_assets.length} asset(s)`
+            : ''
+          showToast(`Exported 1 HTML file${assets}`, 'success')
+          showStyleWarnings(result.warnings)
+        }
+      } catch (error) {
+        showToast(`Failed to export HTML: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenExportHtml.then(fn => fn())
+    }
+  }, [currentFile, markdown, html, showToast, showStyleWarnings])
+
+  // File > Export > Print Layout as HTML: the page as it would print, in one file with its
+  // images inlined
+  useEffect(() => {
+    const unlistenExportPrintHtml = listen<void>('menu-export-print-html', async () => {
+      const title = currentFile
+        ? (currentFile.split('/').pop() ?? currentFile).replace(/\.[^.]+$/, '')
+        : (untitledTitle ?? 'Untitled')
+      try {
+        const defaults = await invoke<ExportDefaults>('get_export_defaults', {
+          documentPath: currentFile,
+          markdown,
+        })
+        const result = await invoke<ExportResult | null>('export_print_html', {
+          title,
+          htmlContent: html,
+          documentPath: currentFile,
+          appTheme: appPalette(),
+          defaults,
+        })
+        if (result) {
+          showToast(`Exported print layout to ${result.output_path}`, 'success')
+          showStyleWarnings(result.warnings)
+        }
+      } catch (error) {
+        showToast(`Failed to export print layout: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenExportPrintHtml.then(fn => fn())
+    }
+  }, [currentFile, untitledTitle, markdown, html, showToast, showStyleWarnings])
+
+  // File > Export > Slides: a reveal.js deck, split on `---` rules or H2 headings as set in
+  // the settings
+  useEffect(() => {
+    const unlistenExportSlides = listen<void>('menu-export-slides', async () => {
+      try {
+        const { print } = await invoke<{ print: { theme: ExportTheme } }>('get_settings')
+        const palette = exportPalette(print.theme)
+        const [runtime, prerendered] = await Promise.all([
+          loadRevealRuntime(palette),
+          prerenderForSlides(markdown, palette),
+        ])
+        const result = await invoke<{ output_path: string; slide_count: number } | null>(
+          'export_slides',
+          {
+            documentPath: currentFile,
+            markdownContent: markdown,
+            outputPath: null,
+            options: { runtime, prerendered, appTheme: appPalette() },
+          }
+        )
+        if (result) {
+          showToast(`Exported ${result.slide_count} slide(s)`, 'success')
+        }
+      } catch (error) {
+        showToast(`Failed to export slides: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenExportSlides.then(fn => fn())
+    }
+  }, [currentFile, markdown, showToast])
+
+  // File > Export > LaTeX: a standalone .tex document with its images copied next to it
+  useEffect(() => {
+    const unlistenExportLatex = listen<void>('menu-export-latex', async () => {
+      try {
+        const result = await invoke<ExportResult | null>('export_latex', {
+          documentPath: currentFile,
+          markdownContent: markdown,
+          outputPath: null,
+          options: null,
+        })
+        if (result) {
+          const assets = result.copied_assets.length
+            ? ` + ${result.copied_assets.length} image(s)`
+            : ''
+          showToast(`Exported LaTeX to ${result.output_path}${assets}`, 'success')
+          result.warnings.forEach(warning => showToast(warning, 'info'))
+        }
+      } catch (error) {
+        showToast(`Failed to export LaTeX: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenExportLatex.then(fn => fn())
+    }
+  }, [currentFile, markdown, showToast])
+
+  // File > Export > Plain Text and Edit > Copy as Plain Text: the document with its
+  // markdown syntax stripped
+  useEffect(() => {
+    const unlistenExportPlainText = listen<void>('menu-export-plain-text', async () => {
+      try {
+        const result = await invoke<ExportResult | null>('export_plain_text_to_file', {
+          documentPath: currentFile,
+          markdownContent: markdown,
+          outputPath: null,
+          options: null,
+        })
+        if (result) {
+          showToast(`Exported plain text to ${result.output_path}`, 'success')
+        }
+      } catch (error) {
+        showToast(`Failed to export plain text: ${errorMessage(error)}`, 'error')
+      }
+    })
+    const unlistenCopyPlainText = listen<void>('menu-copy-plain-text', async () => {
+      try {
+        await invoke('copy_as_plain_text', { markdownContent: markdown, options: null })
+        showToast('Copied as plain text', 'success')
+      } catch (error) {
+        showToast(`Failed to copy: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenExportPlainText.then(fn => fn())
+      unlistenCopyPlainText.then(fn => fn())
+    }
+  }, [currentFile, markdown, showToast])
+
+  // Edit > Copy for Email: the preview with inline styles and embedded images, which mail
+  // clients keep when pasted
+  useEffect(() => {
+    const unlistenCopyForEmail = listen<void>('menu-copy-for-email', async () => {
+      try {
+        const result = await invoke<{ skipped_images: string[] }>('copy_for_email', {
+          htmlContent: html,
+          documentPath: currentFile,
+        })
+        showToast('Copied for email', 'success')
+        if (result.skipped_images.length > 0) {
+          const count = result.skipped_images.length
+          showToast(`${count} image${count === 1 ? ' was' : 's were'} too large to include`, 'info')
+        }
+      } catch (error) {
+        showToast(`Failed to copy: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenCopyForEmail.then(fn => fn())
+    }
+  }, [currentFile, html, showToast])
+
+  // File > Publish as Gist. The gist id goes into the frontmatter so publishing again
+  // updates the same gist.
+  useEffect(() => {
+    const unlistenPublishGist = listen<void>('menu-publish-gist', async () => {
+      const filename = currentFile
+        ? (currentFile.split('/').pop() ?? currentFile)
+        : `${untitledTitle ?? 'Untitled'}.md`
+      try {
+        showToast('Publishing gist...', 'info')
+        const gist = await invoke<{ id: string; url: string; updated: boolean }>(
+          'publish_gist',
+          { content: markdown, filename, description: filename, public: false }
+        )
+        await navigator.clipboard.writeText(gist.url)
+        const published = setFrontMatterField(markdown, 'gist_id', gist.id)
+        if (published !== markdown) {
+          setMarkdown(published)
+          setIsDirty(true)
+        }
+        showToast(`${gist.updated ? 'Updated' : 'Published'} gist, link copied`, 'success')
+      } catch (error) {
+        if (isCommandError(error) && error.code === 'auth_failed') {
+          showToast(`${error.message}. Save a GitHub token with the gist scope.`, 'error')
+        } else {
+          showToast(`Failed to publish gist: ${errorMessage(error)}`, 'error')
+        }
+      }
+    })
+
+    return () => {
+      unlistenPublishGist.then(fn => fn())
+    }
+  }, [currentFile, untitledTitle, markdown, showToast])
+
+  // Run a text command over the selection, or the whole document without a selection, and
+  // put the result in its place. `output` picks the text out of commands that return more
+  // than a string; the command's result is passed back.
+  const transformSelection = useCallback(
+    async (
+      command: string,
+      args: Record<string, unknown>,
+      text: 'text' | 'content' | 'markdown',
+      output: (result: unknown) => string = result => result as string
+    ) => {
+      const editor = editorRef.current
+      if (!editor) return
+      const hasSelection = editor.selectionStart !== editor.selectionEnd
+      const start = hasSelection ? editor.selectionStart : 0
+      const end = hasSelection ? editor.selectionEnd : markdown.length
+      const result = await invoke<unknown>(command, {
+        ...args,
+        [text]: markdown.slice(start, end),
+      })
+      const transformed = output(result)
+      if (transformed !== markdown.slice(start, end)) {
+        setMarkdown(markdown.slice(0, start) + transformed + markdown.slice(end))
+        setIsDirty(true)
+        requestAnimationFrame(() => editor.setSelectionRange(start, start + transformed.length))
+      }
+      return result
+    },
+    [markdown]
+  )
+
+  // Edit > Lines
+  useEffect(() => {
+    const unlistenTransformLines = listen<string>('menu-transform-lines', async event => {
+      try {
+        await transformSelection(
+          'edit_transform',
+          { operation: event.payload, options: null },
+          'text'
+        )
+      } catch (error) {
+        showToast(`Failed to transform lines: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenTransformLines.then(fn => fn())
+    }
+  }, [transformSelection, showToast])
+
+  // Format > Smart Punctuation / Straight Punctuation
+  useEffect(() => {
+    const unlistenSmarten = listen<void>('menu-smarten-typography', async () => {
+      try {
+        await transformSelection('smarten_typography', { options: null }, 'content')
+      } catch (error) {
+        showToast(`Failed to convert punctuation: ${errorMessage(error)}`, 'error')
+      }
+    })
+    const unlistenStraighten = listen<void>('menu-straighten-typography', async () => {
+      try {
+        await transformSelection('straighten_typography', {}, 'content')
+      } catch (error) {
+        showToast(`Failed to convert punctuation: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenSmarten.then(fn => fn())
+      unlistenStraighten.then(fn => fn())
+    }
+  }, [transformSelection, showToast])
+
+  // Format > Change Case and Promote / Demote Headings
+  useEffect(() => {
+    const unlistenCase = listen<string>('menu-transform-case', async event => {
+      try {
+        await transformSelection('transform_text', { operation: event.payload }, 'text')
+      } catch (error) {
+        showToast(`Failed to change case: ${errorMessage(error)}`, 'error')
+      }
+    })
+    const unlistenHeadings = listen<number>('menu-shift-headings', async event => {
+      try {
+        const result = (await transformSelection(
+          'shift_headings',
+          { delta: event.payload },
+          'markdown',
+          result => (result as HeadingShift).text
+        )) as HeadingShift | undefined
+        if (result?.clamped) {
+          showToast('Some headings were already at the top or bottom level', 'info')
+        }
+      } catch (error) {
+        showToast(`Failed to change heading levels: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenCase.then(fn => fn())
+      unlistenHeadings.then(fn => fn())
+    }
+  }, [transformSelection, showToast])
+
+  // Format > Tidy Footnotes and Links
+  useEffect(() => {
+    const unlistenTidy = listen<void>('menu-tidy-references', async () => {
+      try {
+        const tidied = await invoke<TidiedReferences>('tidy_references', {
+          content: markdown,
+          options: null,
+        })
+        if (tidied.content !== markdown) {
+          setMarkdown(tidied.content)
+          setIsDirty(true)
+        }
+        const { undefined: missing } = tidied.report
+        if (missing.length > 0) {
+          const labels = missing.map(at => `${at.label} (line ${at.line})`).join(', ')
+          showToast(`Undefined references: ${labels}`, 'error')
+        } else {
+          const { removed_orphans, removed_duplicates } = tidied.report
+          const removed = removed_orphans.length + removed_duplicates.length
+          showToast(
+            removed > 0 ? `References tidied, ${removed} unused removed` : 'References tidied',
+            'success'
+          )
+        }
+      } catch (error) {
+        showToast(`Failed to tidy references: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenTidy.then(fn => fn())
+    }
+  }, [markdown, showToast])
+
+  // File > Copy Path puts the document's path, name, file URL or a markdown link to it on the
+  // clipboard (the backend does the copying)
+  useEffect(() => {
+    const unlistenCopyReference = listen<string>('menu-copy-document-reference', async event => {
+      if (!currentFile) {
+        showToast('Save the document to copy its path', 'info')
+        return
+      }
+      try {
+        await invoke<string>('copy_document_reference', {
+          path: currentFile,
+          kind: event.payload,
+          markdown,
+        })
+        showToast('Copied', 'success')
+      } catch (error) {
+        showToast(`Failed to copy: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenCopyReference.then(fn => fn())
+    }
+  }, [currentFile, markdown, showToast])
+
+  // File > Open With hands the document to another app (null for the default one)
+  useEffect(() => {
+    const unlistenOpenWith = listen<string | null>('menu-open-with', async event => {
+      if (!currentFile) return
+      try {
+        if (event.payload === null) {
+          await invoke('open_with_default', { path: currentFile })
+        } else {
+          await invoke('open_with', { path: currentFile, appIdentifier: event.payload })
+        }
+      } catch (error) {
+        showToast(`Failed to open in another app: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenOpenWith.then(fn => fn())
+    }
+  }, [currentFile, showToast])
+
+  // File > Open in Terminal starts a terminal in the document's folder
+  useEffect(() => {
+    const unlistenOpenTerminal = listen<void>('menu-open-terminal', async () => {
+      if (!currentFile) {
+        showToast('Save the document to open a terminal in its folder', 'info')
+        return
+      }
+      try {
+        await invoke('open_terminal_at', { path: currentFile })
+      } catch (error) {
+        showToast(errorMessage(error), 'error')
+      }
+    })
+
+    return () => {
+      unlistenOpenTerminal.then(fn => fn())
+    }
+  }, [currentFile, showToast])
+
+  // File > Split by Heading: plan the parts first, then split once the user confirms
+  useEffect(() => {
+    const split = async (level: number, overwrite: boolean) => {
+      try {
+        const result = await invoke<SplitResult>('split_by_heading', {
+          path: currentFile,
+          level,
+          outputDir: null,
+          options: { dryRun: false, index: true, overwrite },
+        })
+        showToast(`Split into ${result.files.length} files in ${result.output_dir}`, 'success')
+      } catch (error) {
+        showToast(errorMessage(error), 'error')
+      }
+    }
+
+    const unlistenSplit = listen<number>('menu-split-by-heading', async event => {
+      if (!currentFile || isDirty) {
+        showToast('Save the document before splitting it', 'info')
+        return
+      }
+      const level = event.payload
+      try {
+        const plan = await invoke<SplitResult>('split_by_heading', {
+          path: currentFile,
+          level,
+          outputDir: null,
+          options: { dryRun: true, index: true, overwrite: false },
+        })
+        if (plan.files.length < 2) {
+          showToast(`There are no level ${level} headings to split at`, 'info')
+          return
+        }
+        const existing = plan.files.filter(file => file.exists).length
+        if (existing > 0) {
+          showToast(
+            `${existing} of the ${plan.files.length} files already exist in ${plan.output_dir}`,
+            'info',
+            { label: 'Overwrite', onClick: () => split(level, true) }
+          )
+        } else {
+          showToast(`Split into ${plan.files.length} files in ${plan.output_dir}?`, 'info', {
+            label: 'Split',
+            onClick: () => split(level, false),
+          })
+        }
+      } catch (error) {
+        showToast(errorMessage(error), 'error')
+      }
+    })
+
+    return () => {
+      unlistenSplit.then(fn => fn())
+    }
+  }, [currentFile, isDirty, showToast])
+
+  // Edit > Speech > Start Speaking: read the selection, or the whole document
+  useEffect(() => {
+    const unlistenStartSpeaking = listen<void>('menu-start-speaking', async () => {
+      const editor = editorRef.current
+      if (!editor) return
+      const hasSelection = editor.selectionStart !== editor.selectionEnd
+      const start = hasSelection ? editor.selectionStart : 0
+      const end = hasSelection ? editor.selectionEnd : markdown.length
+      speechBaseRef.current = start
+      try {
+        await invoke('speak_text', { text: markdown.slice(start, end), options: null })
+      } catch (error) {
+        speechBaseRef.current = null
+        showToast(errorMessage(error), 'error')
+      }
+    })
+
+    return () => {
+      unlistenStartSpeaking.then(fn => fn())
+    }
+  }, [markdown, showToast])
+
+  // Select what's being spoken so the reader can follow along
+  useEffect(() => {
+    const unlistenProgress = listen<SpeechProgress>('speech-progress', event => {
+      const editor = editorRef.current
+      const base = speechBaseRef.current
+      if (!editor || base === null) return
+      editor.setSelectionRange(base + event.payload.start, base + event.payload.end)
+    })
+    const unlistenEnded = listen<void>('speech-ended', () => {
+      speechBaseRef.current = null
+    })
+
+    return () => {
+      unlistenProgress.then(fn => fn())
+      unlistenEnded.then(fn => fn())
+    }
+  }, [])
+
+  // The other app saved the document: offer to load its version
+  useEffect(() => {
+    const unlistenExternalChange = listen<ExternalChange>('file-changed-externally', event => {
+      const path = event.payload.path
+      if (path !== currentFile) return
+      showToast(`${path.split('/').pop()} was changed in another app`, 'info', {
+        label: 'Reload',
+        onClick: async () => {
+          try {
+            const opened = await invokeInScope<OpenedDocument>('open_document', { path })
+            diskMtimeRef.current = { path, mtime: opened.mtime }
+            setMarkdown(opened.content)
+            setIsDirty(false)
+          } catch (error) {
+            showOpenError(error)
+          }
+        },
+      })
+    })
+
+    return () => {
+      unlistenExternalChange.then(fn => fn())
+    }
+  }, [currentFile, showToast, showOpenError])
+
+  // A batch rename moved the open document: keep editing it under its new name
+  useEffect(() => {
+    const unlistenFileRenamed = listen<FileRenamed>('file-renamed', event => {
+      if (event.payload.old_path !== currentFile) return
+      setCurrentFile(event.payload.new_path)
+    })
+
+    return () => {
+      unlistenFileRenamed.then(fn => fn())
+    }
+  }, [currentFile])
+
+  // Help > Keyboard Shortcuts
+  useEffect(() => {
+    const unlistenShowShortcuts = listen<void>('menu-show-shortcuts', () => {
+      setShowShortcuts(true)
+    })
+
+    return () => {
+      unlistenShowShortcuts.then(fn => fn())
+    }
+  }, [])
+
+  const closeShortcuts = useCallback(() => setShowShortcuts(false), [])
+
+  // Editor font, tabs, wrapping and autosave, kept up to date as the preferences change
+  useEffect(() => {
+    invoke<{ editor: EditorSettings }>('get_settings')
+      .then(settings => setEditorSettings(settings.editor))
+      .catch(error => console.error('Failed to load settings:', error))
+
+    const unlistenSettings = listen<{ editor: EditorSettings }>('settings-changed', event => {
+      setEditorSettings(event.payload.editor)
+    })
+
+    return () => {
+      unlistenSettings.then(fn => fn())
+    }
+  }, [])
+
+  // Help > Show Logs
+  useEffect(() => {
+    const unlistenShowLogs = listen<void>('menu-show-logs', () => {
+      setShowLogs(true)
+    })
+
+    return () => {
+      unlistenShowLogs.then(fn => fn())
+    }
+  }, [])
+
+  const closeLogs = useCallback(() => setShowLogs(false), [])
+
+  // Answer to confirm-close. Save goes through the normal save flow; the backend closes the
+  // window once it's clean, and a save that didn't happen cancels the close.
+  const handleCloseAction = useCallback(
+    async (action: CloseAction) => {
+      setCloseRequest(null)
+      const windowLabel = getCurrentWindow().label
+      try {
+        if (action === 'discard') {
+          if (shownDocumentRef.current) {
+            recordClosedDocument(shownDocumentRef.current)
+          }
+          await discardDraft(draftId)
+        }
+        await invoke('confirm_close_response', { windowLabel, action })
+        if (action === 'save' && !(await handleSaveFile())) {
+          await invoke('confirm_close_response', { windowLabel, action: 'cancel' })
+        }
+      } catch (error) {
+        showToast(`Failed to close: ${errorMessage(error)}`, 'error')
+      }
+    },
+    [draftId, discardDraft, recordClosedDocument, handleSaveFile, showToast]
+  )
+
+  // File > Reopen Closed brings back the most recently closed document: from disk, or the
+  // unsaved changes it had when it was closed
+  useEffect(() => {
+    const unlistenReopenClosed = listen<void>('menu-reopen-closed', async () => {
+      try {
+        const closed = await invoke<ClosedDocument | null>('reopen_closed', { index: 0 })
+        if (!closed) {
+          showToast('No recently closed documents', 'info')
+        } else if (closed.content === null && closed.path) {
+          await handleOpenRecentFile(closed.path)
+        } else if (closed.content !== null) {
+          if (!closed.path) {
+            untitledDraftIdRef.current = newUntitledDraftId()
+          }
+          setMarkdown(closed.content)
+          setCurrentFile(closed.path)
+          setUntitledTitle(closed.path ? null : closed.title)
+          setIsDirty(true)
+          showToast(`Reopened: ${closed.title}`, 'success')
+        }
+      } catch (error) {
+        showToast(`Failed to reopen document: ${errorMessage(error)}`, 'error')
+      }
+    })
+
+    return () => {
+      unlistenReopenClosed.then(fn => fn())
+    }
+  }, [handleOpenRecentFile, showToast])
+
+  // Results of update checks. Automatic checks only report an available update.
+  useEffect(() => {
+    const unlistenUpdateStatus = listen<UpdateStatus>('update-status', event => {
+      const update = event.payload
+      if (update.status === 'up_to_date') {
+        showToast(`Markdowner ${update.version} is up to date`, 'info')
+      } else if (update.status === 'available') {
+        showToast(`Markdowner ${update.version} is available`, 'info', {
+          label: 'Install and restart',
+          onClick: async () => {
+            showToast('Downloading update...', 'info')
+            try {
+              await invoke('install_update')
+            } catch (error) {
+              showToast(`Failed to install update: ${errorMessage(error)}`, 'error')
+            }
+          },
+        })
+      } else {
+        showToast(update.message, 'error')
+      }
+    })
+
+    return () => {
+      unlistenUpdateStatus.then(fn => fn())
+    }
+  }, [showToast])
+
+  // HTML5 drag and drop handlers for visual feedback
+  const handleDragEnter = useCallback((e: React.DragEvent) => {
+    e.preventDefault()
+    e.stopPropagation()
+  }, [])
+
+  const handleDragLeave = useCallback((e: React.DragEvent) => {
+    e.preventDefault()
+    e.stopPropagation()
+  }, [])
+
+  const handleDragOver = useCallback((e: React.DragEvent) => {
+    e.preventDefault()
+    e.stopPropagation()
+  }, [])
+
+  const handleDrop = useCallback((e: React.DragEvent) => {
+    // Prevent default to allow the Tauri native event to handle it
+    e.preventDefault()
+    e.stopPropagation()
+  }, [])
+
+  // Get toast icon based on type
   const getToastIcon = (type: ToastType) => {
     switch (type) {
       case 'error':
@@ -694,6 +2249,18 @@ function App() {
           >
             {getToastIcon(toast.type)}
             <span className="toast-message">{toast.message}</span>
+            {toast.action && (
+              <button
+                className="toast-action"
+                onClick={event => {
+                  event.stopPropagation()
+                  toast.action?.onClick()
+                  removeToast(toast.id)
+                }}
+              >
+                {toast.action.label}
+              </button>
+            )}
             <XCircle size={16} className="toast-close" />
           </div>
         ))}
@@ -707,6 +2274,21 @@ function App() {
             <span className="file-path">
               {currentFile.split('/').pop()}
               {isDirty && ' *'}
+              {isReadOnly && (
+                <button
+                  className="file-lock"
+                  title="Read-only. Click to make writable"
+                  onClick={() => handleMakeWritable(currentFile)}
+                >
+                  <Lock size={12} />
+                </button>
+              )}
+            </span>
+          )}
+          {!currentFile && untitledTitle && (
+            <span className="file-path">
+              {untitledTitle}
+              {isDirty && ' *'}
             </span>
           )}
         </div>
@@ -771,7 +2353,7 @@ function App() {
             </button>
             {showRecents && (
               <div className="recents-menu">
-                {recentFiles.length === 0 ? (
+                {recentFiles.length === 0 && frequentFiles.length === 0 ? (
                   <div className="recents-empty">No recent files</div>
                 ) : (
                   <>
@@ -779,12 +2361,26 @@ function App() {
                       {recentFiles.map((file, index) => (
                         <button
                           key={index}
-                          className="recents-item"
-                          onClick={() => handleOpenRecentFile(file)}
-                          title={file}
+                          className={`recents-item${file.exists ? '' : ' recents-item-missing'}`}
+                          onClick={() => handleOpenRecentFile(file.path)}
+                          title={file.exists ? file.path : `${file.path} (not found)`}
+                        >
+                          <span className="recents-item-name">{getFileName(file.path)}</span>
+                          <span className="recents-item-path">{file.display_path}</span>
+                        </button>
+                      ))}
+                      {frequentFiles.length > 0 && (
+                        <div className="recents-section-title">Frequent</div>
+                      )}
+                      {frequentFiles.map(file => (
+                        <button
+                          key={file.path}
+                          className={`recents-item${file.exists ? '' : ' recents-item-missing'}`}
+                          onClick={() => handleOpenRecentFile(file.path)}
+                          title={`${file.path}, opened ${file.open_count} times`}
                         >
-                          <span className="recents-item-name">{getFileName(file)}</span>
-                          <span className="recents-item-path">{file}</span>
+                          <span className="recents-item-name">{getFileName(file.path)}</span>
+                          <span className="recents-item-path">{file.display_path}</span>
                         </button>
                       ))}
                     </div>
@@ -905,7 +2501,11 @@ function App() {
             className="markdown-input"
             value={markdown}
             onChange={handleMarkdownChange}
+            onPaste={handleEditorPaste}
+            onKeyDown={handleEditorKeyDown}
+            style={editorStyle(editorSettings)}
             onScroll={handleEditorScroll}
+            onSelect={scheduleViewStateSave}
             placeholder="Type your markdown here..."
             spellCheck={false}
           />
@@ -914,10 +2514,12 @@ function App() {
         {/* Preview Pane */}
         <div className="preview-pane">
           <div className="pane-header">Preview</div>
+          {customCss && <style>{`@scope (.markdown-preview) {\n${customCss}\n}`}</style>}
           <div
             ref={previewRef}
             className="markdown-preview"
             onScroll={handlePreviewScroll}
+            onClick={handlePreviewClick}
             dangerouslySetInnerHTML={{ __html: html }}
           />
         </div>
@@ -930,6 +2532,17 @@ function App() {
         <span>{isDirty ? 'Unsaved' : 'Saved'}</span>
       </div>
 
+      {showShortcuts && <ShortcutsDialog onClose={closeShortcuts} />}
+      {showLogs && <LogsDialog onClose={closeLogs} />}
+
+      {closeRequest && (
+        <UnsavedChangesDialog
+          fileName={currentFile ? (currentFile.split('/').pop() ?? currentFile) : 'Untitled'}
+          quitting={closeRequest.quitting}
+          onAction={handleCloseAction}
+        />
+      )}
+
       {/* Drag and Drop Overlay */}
       {isDragging && (
         <div className="drag-overlay">
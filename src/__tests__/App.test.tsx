@@ -1,6 +1,7 @@
 import { describe, it, expect, vi, beforeEach } from 'vitest'
 import { render, screen, fireEvent, waitFor as waitForRTL } from '@testing-library/react'
 import { invoke } from '@tauri-apps/api/core'
+import { revealItemInDir } from '@tauri-apps/plugin-opener'
 import App from '../App'
 
 // Mock the Tauri invoke function
@@ -13,9 +14,9 @@ describe('App', () => {
 
   beforeEach(() => {
     mockInvoke.mockClear()
-    // Default mock for get_recent_files
+    // Default mock for get_recent_files and get_frequent_files
     mockInvoke.mockImplementation((cmd: string) => {
-      if (cmd === 'get_recent_files') {
+      if (cmd === 'get_recent_files' || cmd === 'get_frequent_files') {
         return Promise.resolve([])
       }
       return Promise.resolve(null)
@@ -150,8 +151,9 @@ describe('App', () => {
   it('calls open file dialog when Open button is clicked', async () => {
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve([])
-      if (cmd === 'open_file_dialog') return Promise.resolve('/path/to/file.md')
-      if (cmd === 'read_file') return Promise.resolve('# File Content')
+      if (cmd === 'open_files_dialog') return Promise.resolve(['/path/to/file.md'])
+      if (cmd === 'open_document')
+        return Promise.resolve({ content: '# File Content', view_state: null })
       return Promise.resolve(null)
     })
 
@@ -163,14 +165,18 @@ describe('App', () => {
     })
 
     await waitForRTL(() => {
-      expect(mockInvoke).toHaveBeenCalledWith('open_file_dialog')
+      expect(mockInvoke).toHaveBeenCalledWith('open_files_dialog')
     })
   })
 
   it('displays recent files when available', async () => {
-    const recentFiles = ['/path/to/file1.md', '/path/to/file2.md']
+    const recentFiles = [
+      { path: '/path/to/file1.md', display_path: '/path/to/file1.md', exists: true },
+      { path: '/path/to/file2.md', display_path: '/path/to/file2.md', exists: true },
+    ]
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve(recentFiles)
+      if (cmd === 'get_frequent_files') return Promise.resolve([])
       return Promise.resolve(null)
     })
 
@@ -187,6 +193,38 @@ describe('App', () => {
     })
   })
 
+  it('lists frequent files below the recent ones', async () => {
+    const recentFiles = [
+      { path: '/path/to/file1.md', display_path: '/path/to/file1.md', exists: true },
+    ]
+    const frequentFiles = [
+      {
+        path: '/path/to/often.md',
+        display_path: '/path/to/often.md',
+        exists: true,
+        open_count: 12,
+        score: 3.5,
+      },
+    ]
+    mockInvoke.mockImplementation((cmd: string) => {
+      if (cmd === 'get_recent_files') return Promise.resolve(recentFiles)
+      if (cmd === 'get_frequent_files') return Promise.resolve(frequentFiles)
+      return Promise.resolve(null)
+    })
+
+    render(<App />)
+
+    await waitForRTL(() => {
+      const recentsButton = screen.getByTitle('Recent Files')
+      fireEvent.click(recentsButton)
+    })
+
+    await waitForRTL(() => {
+      expect(screen.getByText('Frequent')).toBeInTheDocument()
+      expect(screen.getByTitle('/path/to/often.md, opened 12 times')).toBeInTheDocument()
+    })
+  })
+
   it('shows theme toggle component', async () => {
     render(<App />)
 
@@ -201,8 +239,9 @@ describe('App', () => {
   it('opens a file and displays content', async () => {
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve([])
-      if (cmd === 'open_file_dialog') return Promise.resolve('/path/to/test.md')
-      if (cmd === 'read_file') return Promise.resolve('# Test Content')
+      if (cmd === 'open_files_dialog') return Promise.resolve(['/path/to/test.md'])
+      if (cmd === 'open_document')
+        return Promise.resolve({ content: '# Test Content', view_state: null })
       if (cmd === 'add_to_recents') return Promise.resolve()
       return Promise.resolve(null)
     })
@@ -248,7 +287,9 @@ describe('App', () => {
     })
 
     await waitForRTL(() => {
-      expect(mockInvoke).toHaveBeenCalledWith('save_file_dialog')
+      expect(mockInvoke).toHaveBeenCalledWith('save_file_dialog', {
+        content: 'New content to save',
+      })
       expect(mockInvoke).toHaveBeenCalledWith('write_file', {
         path: '/new/path/save.md',
         content: 'New content to save',
@@ -261,8 +302,8 @@ describe('App', () => {
   it('displays error toast when file open fails', async () => {
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve([])
-      if (cmd === 'open_file_dialog') return Promise.resolve('/nonexistent/file.md')
-      if (cmd === 'read_file') return Promise.reject('File does not exist')
+      if (cmd === 'open_files_dialog') return Promise.resolve(['/nonexistent/file.md'])
+      if (cmd === 'open_document') return Promise.reject('File does not exist')
       return Promise.resolve(null)
     })
 
@@ -278,6 +319,107 @@ describe('App', () => {
     })
   })
 
+  it('offers to reveal binary files instead of opening them', async () => {
+    mockInvoke.mockImplementation((cmd: string) => {
+      if (cmd === 'get_recent_files') return Promise.resolve([])
+      if (cmd === 'open_files_dialog') return Promise.resolve(['/notes/image.md'])
+      if (cmd === 'open_document')
+        return Promise.reject({
+          code: 'binary_file',
+          message: 'This looks like a PNG image, not a text file: /notes/image.md',
+          details: { path: '/notes/image.md', mime: 'image/png' },
+        })
+      return Promise.resolve(null)
+    })
+
+    render(<App />)
+
+    await waitForRTL(() => {
+      fireEvent.click(screen.getByTitle('Open File'))
+    })
+
+    await waitForRTL(() => {
+      expect(screen.getByText(/This looks like a PNG image/)).toBeInTheDocument()
+    })
+    fireEvent.click(screen.getByText('Show in folder'))
+    expect(vi.mocked(revealItemInDir)).toHaveBeenCalledWith('/notes/image.md')
+  })
+
+  it('refuses to overwrite a file changed on disk unless asked to', async () => {
+    mockInvoke.mockImplementation((cmd: string, args?: { expectedMtime?: number }) => {
+      if (cmd === 'get_recent_files') return Promise.resolve([])
+      if (cmd === 'open_files_dialog') return Promise.resolve(['/notes/todo.md'])
+      if (cmd === 'open_document')
+        return Promise.resolve({ content: '# Todo', view_state: null, mtime: 1000 })
+      if (cmd === 'write_file') {
+        if (args?.expectedMtime === 1000)
+          return Promise.reject({
+            code: 'conflict',
+            message: 'File was changed on disk since it was opened',
+            details: { disk_mtime: 2000 },
+          })
+        return Promise.resolve({ queue_depth: 0, mtime: 3000 })
+      }
+      return Promise.resolve(null)
+    })
+
+    render(<App />)
+
+    await waitForRTL(() => {
+      fireEvent.click(screen.getByTitle('Open File'))
+    })
+    await waitForRTL(() => {
+      expect(screen.getByText('todo.md')).toBeInTheDocument()
+    })
+    fireEvent.click(screen.getByTitle('Save File'))
+
+    await waitForRTL(() => {
+      expect(screen.getByText('File was changed on disk since it was opened')).toBeInTheDocument()
+    })
+    fireEvent.click(screen.getByText('Overwrite'))
+
+    await waitForRTL(() => {
+      expect(mockInvoke).toHaveBeenCalledWith('write_file', {
+        path: '/notes/todo.md',
+        content: '# Todo',
+        expectedMtime: undefined,
+      })
+    })
+  })
+
+  it('offers to recover unsaved changes from a draft', async () => {
+    mockInvoke.mockImplementation((cmd: string) => {
+      if (cmd === 'get_recent_files') return Promise.resolve([])
+      if (cmd === 'list_drafts')
+        return Promise.resolve([
+          {
+            doc_id: '/notes/todo.md',
+            original_path: '/notes/todo.md',
+            saved_at: 2000,
+            original_mtime: 1000,
+            recoverable: true,
+          },
+        ])
+      if (cmd === 'read_draft') return Promise.resolve('# Recovered draft')
+      return Promise.resolve(null)
+    })
+
+    render(<App />)
+
+    await waitForRTL(() => {
+      expect(screen.getByText('Recover unsaved changes to todo.md?')).toBeInTheDocument()
+    })
+    fireEvent.click(screen.getByText('Recover'))
+
+    await waitForRTL(() => {
+      expect(mockInvoke).toHaveBeenCalledWith('read_draft', { docId: '/notes/todo.md' })
+      const textarea = screen.getByPlaceholderText(
+        'Type your markdown here...'
+      ) as HTMLTextAreaElement
+      expect(textarea.value).toBe('# Recovered draft')
+    })
+  })
+
   it('displays error toast when file save fails', async () => {
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve([])
@@ -307,8 +449,9 @@ describe('App', () => {
   it('handles opening non-markdown files', async () => {
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve([])
-      if (cmd === 'open_file_dialog') return Promise.resolve('/path/to/file.txt')
-      if (cmd === 'read_file') return Promise.resolve('Plain text')
+      if (cmd === 'open_files_dialog') return Promise.resolve(['/path/to/file.txt'])
+      if (cmd === 'open_document')
+        return Promise.resolve({ content: 'Plain text', view_state: null })
       if (cmd === 'add_to_recents') return Promise.resolve()
       return Promise.resolve(null)
     })
@@ -331,10 +474,13 @@ describe('App', () => {
 
   // Recent Files Tests
   it('opens file from recent files list', async () => {
-    const recentFiles = ['/path/to/file1.md']
+    const recentFiles = [
+      { path: '/path/to/file1.md', display_path: '/path/to/file1.md', exists: true },
+    ]
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve(recentFiles)
-      if (cmd === 'read_file') return Promise.resolve('# File 1')
+      if (cmd === 'get_frequent_files') return Promise.resolve([])
+      if (cmd === 'open_document') return Promise.resolve({ content: '# File 1', view_state: null })
       if (cmd === 'add_to_recents') return Promise.resolve()
       return Promise.resolve(null)
     })
@@ -361,10 +507,18 @@ describe('App', () => {
   })
 
   it('removes non-existent recent file', async () => {
-    const recentFiles = ['/nonexistent/file.md']
+    const recentFiles = [
+      { path: '/nonexistent/file.md', display_path: '/nonexistent/file.md', exists: false },
+    ]
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve(recentFiles)
-      if (cmd === 'read_file') return Promise.reject('File does not exist')
+      if (cmd === 'get_frequent_files') return Promise.resolve([])
+      if (cmd === 'open_document')
+        return Promise.reject({
+          code: 'not_found',
+          message: 'File does not exist: /nonexistent/file.md',
+          details: { path: '/nonexistent/file.md' },
+        })
       if (cmd === 'add_to_recents') return Promise.resolve()
       return Promise.resolve(null)
     })
@@ -376,20 +530,32 @@ describe('App', () => {
       fireEvent.click(recentsButton)
     })
 
+    const recentLoads = () => mockInvoke.mock.calls.filter(([cmd]) => cmd === 'get_recent_files')
+    let loadsBefore = 0
     await waitForRTL(() => {
       const fileButton = screen.getByText('file.md')
+      expect(fileButton.closest('button')).toHaveClass('recents-item-missing')
+      loadsBefore = recentLoads().length
       fireEvent.click(fileButton)
     })
 
     await waitForRTL(() => {
-      expect(screen.getByText('Failed to open file: File does not exist')).toBeInTheDocument()
+      expect(
+        screen.getByText('Failed to open file: File does not exist: /nonexistent/file.md')
+      ).toBeInTheDocument()
+      // Reloaded so the entry is shown as missing
+      expect(recentLoads().length).toBeGreaterThan(loadsBefore)
     })
   })
 
   it('clears recent files', async () => {
-    const recentFiles = ['/path/to/file1.md', '/path/to/file2.md']
+    const recentFiles = [
+      { path: '/path/to/file1.md', display_path: '/path/to/file1.md', exists: true },
+      { path: '/path/to/file2.md', display_path: '/path/to/file2.md', exists: true },
+    ]
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve(recentFiles)
+      if (cmd === 'get_frequent_files') return Promise.resolve([])
       if (cmd === 'clear_recent_files') return Promise.resolve()
       return Promise.resolve(null)
     })
@@ -476,6 +642,7 @@ describe('App', () => {
     vi.mock('@tauri-apps/api/window', () => ({
       getCurrentWindow: vi.fn(() => ({
         onDragDropEvent: vi.fn(() => Promise.resolve(() => {})),
+        onCloseRequested: vi.fn(() => Promise.resolve(() => {})),
       })),
     }))
 
@@ -502,8 +669,8 @@ describe('App', () => {
   it('shows success toast with correct icon', async () => {
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve([])
-      if (cmd === 'open_file_dialog') return Promise.resolve('/path/to/test.md')
-      if (cmd === 'read_file') return Promise.resolve('# Test')
+      if (cmd === 'open_files_dialog') return Promise.resolve(['/path/to/test.md'])
+      if (cmd === 'open_document') return Promise.resolve({ content: '# Test', view_state: null })
       if (cmd === 'add_to_recents') return Promise.resolve()
       return Promise.resolve(null)
     })
@@ -526,8 +693,8 @@ describe('App', () => {
   it('shows error toast with correct icon', async () => {
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve([])
-      if (cmd === 'open_file_dialog') return Promise.resolve('/nonexistent.md')
-      if (cmd === 'read_file') return Promise.reject('File not found')
+      if (cmd === 'open_files_dialog') return Promise.resolve(['/nonexistent.md'])
+      if (cmd === 'open_document') return Promise.reject('File not found')
       return Promise.resolve(null)
     })
 
@@ -549,8 +716,8 @@ describe('App', () => {
   it('removes toast when clicked', async () => {
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve([])
-      if (cmd === 'open_file_dialog') return Promise.resolve('/path/to/test.md')
-      if (cmd === 'read_file') return Promise.resolve('# Test')
+      if (cmd === 'open_files_dialog') return Promise.resolve(['/path/to/test.md'])
+      if (cmd === 'open_document') return Promise.resolve({ content: '# Test', view_state: null })
       if (cmd === 'add_to_recents') return Promise.resolve()
       return Promise.resolve(null)
     })
@@ -653,9 +820,12 @@ describe('App', () => {
 
   // UI Interaction Tests
   it('recent files dropdown is togglable', async () => {
-    const recentFiles = ['/path/to/file1.md']
+    const recentFiles = [
+      { path: '/path/to/file1.md', display_path: '/path/to/file1.md', exists: true },
+    ]
     mockInvoke.mockImplementation((cmd: string) => {
       if (cmd === 'get_recent_files') return Promise.resolve(recentFiles)
+      if (cmd === 'get_frequent_files') return Promise.resolve([])
       return Promise.resolve(null)
     })
 
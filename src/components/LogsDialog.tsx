@@ -0,0 +1,118 @@
+import { useCallback, useEffect, useState } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { X } from 'lucide-react'
+import { errorMessage } from '../utils/errors'
+import './LogsDialog.css'
+
+interface LogEntry {
+  timestamp: string
+  level: string
+  target: string
+  message: string
+}
+
+interface RecentLogs {
+  entries: LogEntry[]
+  level: string
+  path: string
+}
+
+const LEVELS = ['error', 'warn', 'info', 'debug', 'trace']
+
+interface LogsDialogProps {
+  onClose: () => void
+}
+
+export function LogsDialog({ onClose }: LogsDialogProps) {
+  const [logs, setLogs] = useState<RecentLogs | null>(null)
+  const [filter, setFilter] = useState('')
+  const [error, setError] = useState<string | null>(null)
+
+  const refresh = useCallback(() => {
+    invoke<RecentLogs>('get_recent_logs', { levelFilter: filter || null })
+      .then(result => {
+        setLogs(result)
+        setError(null)
+      })
+      .catch(e => setError(errorMessage(e)))
+  }, [filter])
+
+  useEffect(() => {
+    refresh()
+  }, [refresh])
+
+  useEffect(() => {
+    const handleKeyDown = (e: KeyboardEvent) => {
+      if (e.key === 'Escape') {
+        onClose()
+      }
+    }
+    document.addEventListener('keydown', handleKeyDown)
+    return () => document.removeEventListener('keydown', handleKeyDown)
+  }, [onClose])
+
+  const changeLevel = (level: string) => {
+    invoke('set_log_level', { level })
+      .then(refresh)
+      .catch(e => setError(errorMessage(e)))
+  }
+
+  const openFolder = () => {
+    invoke('open_log_folder').catch(e => setError(errorMessage(e)))
+  }
+
+  return (
+    <div className="logs-overlay" onClick={onClose}>
+      <div
+        className="logs-dialog"
+        role="dialog"
+        aria-label="Logs"
+        onClick={e => e.stopPropagation()}
+      >
+        <div className="logs-header">
+          <h2>Logs</h2>
+          <button className="logs-close" onClick={onClose} aria-label="Close">
+            <X size={16} />
+          </button>
+        </div>
+        <div className="logs-toolbar">
+          <label>
+            Show{' '}
+            <select value={filter} onChange={e => setFilter(e.target.value)}>
+              <option value="">All</option>
+              {LEVELS.map(level => (
+                <option key={level} value={level}>
+                  {level}
+                </option>
+              ))}
+            </select>
+          </label>
+          <label>
+            Record{' '}
+            <select value={logs?.level ?? ''} onChange={e => changeLevel(e.target.value)}>
+              {LEVELS.map(level => (
+                <option key={level} value={level}>
+                  {level}
+                </option>
+              ))}
+            </select>
+          </label>
+          <button onClick={refresh}>Refresh</button>
+          <button onClick={openFolder}>Open Log Folder</button>
+        </div>
+        {error && <div className="logs-failure">{error}</div>}
+        <div className="logs-entries">
+          {logs && logs.entries.length === 0 && <div className="logs-empty">Nothing logged</div>}
+          {logs?.entries.map((entry, index) => (
+            <div key={index} className={`logs-entry logs-level-${entry.level.toLowerCase()}`}>
+              <span className="logs-time">{entry.timestamp}</span>{' '}
+              <span className="logs-level">{entry.level}</span>{' '}
+              <span className="logs-target">{entry.target}</span> {entry.message}
+            </div>
+          ))}
+        </div>
+        {logs && <div className="logs-footer">{logs.path}</div>}
+      </div>
+    </div>
+  )
+}
@@ -0,0 +1,86 @@
+import { useEffect, useState } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { X } from 'lucide-react'
+import './ShortcutsDialog.css'
+
+interface AppInfo {
+  version: string
+  tauri_version: string
+  os: string
+  arch: string
+}
+
+const isMac = navigator.userAgent.includes('Mac')
+const mod = isMac ? '⌘' : 'Ctrl'
+
+const SHORTCUTS: { keys: string; action: string }[] = [
+  { keys: `${mod}+N`, action: 'New document' },
+  { keys: `${mod}+Shift+N`, action: 'New from clipboard' },
+  { keys: `${mod}+O`, action: 'Open file' },
+  { keys: `${mod}+S`, action: 'Save' },
+  { keys: `${mod}+Shift+S`, action: 'Save as' },
+  { keys: `${mod}+F`, action: 'Find and replace' },
+  { keys: 'Enter / Shift+Enter', action: 'Next / previous match' },
+  { keys: 'Esc', action: 'Close search' },
+  { keys: `${mod}+/`, action: 'Show this list' },
+]
+
+interface ShortcutsDialogProps {
+  onClose: () => void
+}
+
+export function ShortcutsDialog({ onClose }: ShortcutsDialogProps) {
+  const [appInfo, setAppInfo] = useState<AppInfo | null>(null)
+
+  useEffect(() => {
+    invoke<AppInfo>('get_app_info')
+      .then(setAppInfo)
+      .catch(() => setAppInfo(null))
+  }, [])
+
+  useEffect(() => {
+    const handleKeyDown = (e: KeyboardEvent) => {
+      if (e.key === 'Escape') {
+        onClose()
+      }
+    }
+    document.addEventListener('keydown', handleKeyDown)
+    return () => document.removeEventListener('keydown', handleKeyDown)
+  }, [onClose])
+
+  return (
+    <div className="shortcuts-overlay" onClick={onClose}>
+      <div
+        className="shortcuts-dialog"
+        role="dialog"
+        aria-label="Keyboard shortcuts"
+        onClick={e => e.stopPropagation()}
+      >
+        <div className="shortcuts-header">
+          <h2>Keyboard Shortcuts</h2>
+          <button className="shortcuts-close" onClick={onClose} aria-label="Close">
+            <X size={16} />
+          </button>
+        </div>
+        <table className="shortcuts-table">
+          <tbody>
+            {SHORTCUTS.map(shortcut => (
+              <tr key={shortcut.action}>
+                <td>{shortcut.action}</td>
+                <td>
+                  <kbd>{shortcut.keys}</kbd>
+                </td>
+              </tr>
+            ))}
+          </tbody>
+        </table>
+        {appInfo && (
+          <div className="shortcuts-footer">
+            Markdowner {appInfo.version} · Tauri {appInfo.tauri_version} · {appInfo.os}{' '}
+            {appInfo.arch}
+          </div>
+        )}
+      </div>
+    </div>
+  )
+}
@@ -0,0 +1,45 @@
+import { useEffect } from 'react'
+import './UnsavedChangesDialog.css'
+
+export type CloseAction = 'save' | 'discard' | 'cancel'
+
+interface UnsavedChangesDialogProps {
+  fileName: string
+  // The whole app is quitting, not just this window closing
+  quitting: boolean
+  onAction: (action: CloseAction) => void
+}
+
+export function UnsavedChangesDialog({ fileName, quitting, onAction }: UnsavedChangesDialogProps) {
+  useEffect(() => {
+    const handleKeyDown = (e: KeyboardEvent) => {
+      if (e.key === 'Escape') {
+        onAction('cancel')
+      }
+    }
+    document.addEventListener('keydown', handleKeyDown)
+    return () => document.removeEventListener('keydown', handleKeyDown)
+  }, [onAction])
+
+  return (
+    <div className="unsaved-overlay">
+      <div className="unsaved-dialog" role="alertdialog" aria-label="Unsaved changes">
+        <h2>Save changes to {fileName}?</h2>
+        <p>
+          {quitting
+            ? 'Your changes will be lost if you quit without saving.'
+            : 'Your changes will be lost if you close the window without saving.'}
+        </p>
+        <div className="unsaved-actions">
+          <button className="unsaved-discard" onClick={() => onAction('discard')}>
+            Don&apos;t Save
+          </button>
+          <button onClick={() => onAction('cancel')}>Cancel</button>
+          <button className="unsaved-save" onClick={() => onAction('save')} autoFocus>
+            Save
+          </button>
+        </div>
+      </div>
+    </div>
+  )
+}
@@ -0,0 +1,56 @@
+import { describe, it, expect, vi } from 'vitest'
+import { render, screen, fireEvent, waitFor } from '@testing-library/react'
+import { invoke } from '@tauri-apps/api/core'
+import { LogsDialog } from '../LogsDialog'
+
+const LOGS = {
+  entries: [
+    {
+      timestamp: '2026-03-01T09:15:07.003',
+      level: 'ERROR',
+      target: 'markdown_editor_lib::tray',
+      message: 'Failed to update tray menu',
+    },
+  ],
+  level: 'info',
+  path: '/logs/markdowner.log',
+}
+
+describe('LogsDialog', () => {
+  it('lists recent entries and filters them by level', async () => {
+    vi.mocked(invoke).mockResolvedValue(LOGS)
+    render(<LogsDialog onClose={vi.fn()} />)
+
+    expect(
+      await screen.findByText('Failed to update tray menu', { exact: false })
+    ).toBeInTheDocument()
+    expect(screen.getByText('/logs/markdowner.log')).toBeInTheDocument()
+    expect(invoke).toHaveBeenCalledWith('get_recent_logs', { levelFilter: null })
+
+    fireEvent.change(screen.getByLabelText(/Show/), { target: { value: 'warn' } })
+    await waitFor(() =>
+      expect(invoke).toHaveBeenCalledWith('get_recent_logs', { levelFilter: 'warn' })
+    )
+  })
+
+  it('changes the level and opens the log folder', async () => {
+    vi.mocked(invoke).mockResolvedValue(LOGS)
+    render(<LogsDialog onClose={vi.fn()} />)
+    await screen.findByText('/logs/markdowner.log')
+
+    fireEvent.change(screen.getByLabelText(/Record/), { target: { value: 'debug' } })
+    expect(invoke).toHaveBeenCalledWith('set_log_level', { level: 'debug' })
+    fireEvent.click(screen.getByText('Open Log Folder'))
+    expect(invoke).toHaveBeenCalledWith('open_log_folder')
+  })
+
+  it('closes on Escape and on the close button', () => {
+    vi.mocked(invoke).mockResolvedValue(LOGS)
+    const onClose = vi.fn()
+    render(<LogsDialog onClose={onClose} />)
+
+    fireEvent.keyDown(document, { key: 'Escape' })
+    fireEvent.click(screen.getByLabelText('Close'))
+    expect(onClose).toHaveBeenCalledTimes(2)
+  })
+})
@@ -0,0 +1,30 @@
+import { describe, it, expect, vi } from 'vitest'
+import { render, screen, fireEvent } from '@testing-library/react'
+import { invoke } from '@tauri-apps/api/core'
+import { ShortcutsDialog } from '../ShortcutsDialog'
+
+describe('ShortcutsDialog', () => {
+  it('lists shortcuts and the app version', async () => {
+    vi.mocked(invoke).mockResolvedValueOnce({
+      version: '1.2.0',
+      tauri_version: '2.5.1',
+      os: 'macos',
+      arch: 'aarch64',
+    })
+    render(<ShortcutsDialog onClose={vi.fn()} />)
+
+    expect(screen.getByText('Find and replace')).toBeInTheDocument()
+    expect(invoke).toHaveBeenCalledWith('get_app_info')
+    expect(await screen.findByText(/Markdowner 1\.2\.0/)).toBeInTheDocument()
+  })
+
+  it('closes on Escape and on the close button', () => {
+    vi.mocked(invoke).mockResolvedValueOnce(null)
+    const onClose = vi.fn()
+    render(<ShortcutsDialog onClose={onClose} />)
+
+    fireEvent.keyDown(document, { key: 'Escape' })
+    fireEvent.click(screen.getByLabelText('Close'))
+    expect(onClose).toHaveBeenCalledTimes(2)
+  })
+})
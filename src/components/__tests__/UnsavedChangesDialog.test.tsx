@@ -0,0 +1,24 @@
+import { describe, it, expect, vi } from 'vitest'
+import { render, screen, fireEvent } from '@testing-library/react'
+import { UnsavedChangesDialog } from '../UnsavedChangesDialog'
+
+describe('UnsavedChangesDialog', () => {
+  it('names the file and reports the chosen action', () => {
+    const onAction = vi.fn()
+    render(<UnsavedChangesDialog fileName="todo.md" quitting={false} onAction={onAction} />)
+
+    expect(screen.getByText('Save changes to todo.md?')).toBeInTheDocument()
+    fireEvent.click(screen.getByText('Save'))
+    fireEvent.click(screen.getByText("Don't Save"))
+    expect(onAction.mock.calls).toEqual([['save'], ['discard']])
+  })
+
+  it('cancels on Escape', () => {
+    const onAction = vi.fn()
+    render(<UnsavedChangesDialog fileName="Untitled" quitting onAction={onAction} />)
+
+    expect(screen.getByText(/if you quit without saving/)).toBeInTheDocument()
+    fireEvent.keyDown(document, { key: 'Escape' })
+    expect(onAction).toHaveBeenCalledWith('cancel')
+  })
+})
@@ -34,7 +34,10 @@ vi.mock('@tauri-apps/api/core', () => ({
 
 vi.mock('@tauri-apps/api/window', () => ({
   getCurrentWindow: vi.fn(() => ({
+    label: 'main',
+    listen: vi.fn(() => Promise.resolve(vi.fn())),
     onDragDropEvent: vi.fn(() => Promise.resolve(vi.fn())),
+    onCloseRequested: vi.fn(() => Promise.resolve(vi.fn())),
   })),
 }))
 
@@ -42,6 +45,10 @@ vi.mock('@tauri-apps/api/event', () => ({
   listen: vi.fn(() => Promise.resolve(vi.fn())),
 }))
 
+vi.mock('@tauri-apps/plugin-opener', () => ({
+  revealItemInDir: vi.fn(() => Promise.resolve()),
+}))
+
 // Cleanup after each test
 import { cleanup } from '@testing-library/react'
 import { afterEach } from 'vitest'
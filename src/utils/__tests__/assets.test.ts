@@ -0,0 +1,39 @@
+import { describe, it, expect, vi, beforeEach } from 'vitest'
+import { invoke } from '@tauri-apps/api/core'
+import { resolveAssetUrls } from '../assets'
+
+describe('resolveAssetUrls', () => {
+  const mockInvoke = vi.mocked(invoke)
+
+  beforeEach(() => {
+    mockInvoke.mockReset()
+    mockInvoke.mockImplementation((_cmd, args) => {
+      const { relative } = args as { relative: string }
+      if (relative === 'gone.png') return Promise.reject(new Error('invalid'))
+      return Promise.resolve(`mdasset://localhost/%2Fnotes%2F${encodeURIComponent(relative)}`)
+    })
+  })
+
+  it('points local images at the asset scheme', async () => {
+    const html =
+      '<p><img src="img/a.png" alt="A"> <img src="img/a.png"> ' +
+      '<img src="https://example.com/b.png"> <img src="data:image/png;base64,AA=="></p>'
+    const resolved = await resolveAssetUrls(html, '/notes/doc.md')
+
+    expect(resolved).toContain('<img src="mdasset://localhost/%2Fnotes%2Fimg%2Fa.png" alt="A">')
+    expect(resolved).toContain('src="https://example.com/b.png"')
+    expect(resolved).toContain('src="data:image/png;base64,AA=="')
+    // Each source is resolved once
+    expect(mockInvoke).toHaveBeenCalledTimes(1)
+    expect(mockInvoke).toHaveBeenCalledWith('resolve_asset_url', {
+      documentPath: '/notes/doc.md',
+      relative: 'img/a.png',
+    })
+  })
+
+  it('leaves images it cannot resolve alone', async () => {
+    const html = '<p><img src="gone.png"></p>'
+    expect(await resolveAssetUrls(html, '/notes/doc.md')).toBe(html)
+    expect(await resolveAssetUrls('<p>No images</p>', '/notes/doc.md')).toBe('<p>No images</p>')
+  })
+})
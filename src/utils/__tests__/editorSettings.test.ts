@@ -0,0 +1,22 @@
+import { describe, it, expect } from 'vitest'
+import { DEFAULT_EDITOR_SETTINGS, editorStyle, indentText } from '../editorSettings'
+
+describe('editor settings', () => {
+  it('styles the editor from the settings', () => {
+    const style = editorStyle({
+      ...DEFAULT_EDITOR_SETTINGS,
+      font_family: 'JetBrains "Mono"',
+      font_size: 16,
+      word_wrap: true,
+    })
+    expect(style.fontFamily).toBe('"JetBrains Mono", monospace')
+    expect(style.fontSize).toBe('16px')
+    expect(style.whiteSpace).toBe('pre-wrap')
+    expect(editorStyle(DEFAULT_EDITOR_SETTINGS).fontFamily).toBeUndefined()
+  })
+
+  it('indents with spaces or a tab', () => {
+    expect(indentText({ ...DEFAULT_EDITOR_SETTINGS, tab_size: 4 })).toBe('    ')
+    expect(indentText({ ...DEFAULT_EDITOR_SETTINGS, insert_spaces: false })).toBe('\t')
+  })
+})
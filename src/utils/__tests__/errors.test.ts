@@ -0,0 +1,16 @@
+import { describe, it, expect } from 'vitest'
+import { errorMessage, isCommandError } from '../errors'
+
+describe('error utils', () => {
+  it('recognizes structured command errors', () => {
+    const error = { code: 'not_found', message: 'File does not exist: /a.md', details: null }
+    expect(isCommandError(error)).toBe(true)
+    expect(errorMessage(error)).toBe('File does not exist: /a.md')
+  })
+
+  it('falls back to the string form of other errors', () => {
+    expect(isCommandError('Permission denied')).toBe(false)
+    expect(errorMessage('Permission denied')).toBe('Permission denied')
+    expect(errorMessage(new Error('boom'))).toBe('Error: boom')
+  })
+})
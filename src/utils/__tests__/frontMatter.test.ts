@@ -0,0 +1,19 @@
+import { describe, it, expect } from 'vitest'
+import { setFrontMatterField } from '../frontMatter'
+
+describe('setFrontMatterField', () => {
+  it('adds a frontmatter block to a document without one', () => {
+    expect(setFrontMatterField('# Notes\n', 'gist_id', 'abc123')).toBe(
+      '---\ngist_id: abc123\n---\n\n# Notes\n'
+    )
+  })
+
+  it('adds or replaces the field in existing frontmatter', () => {
+    const doc = '---\ntitle: Notes\n---\n# Notes\n'
+    const published = setFrontMatterField(doc, 'gist_id', 'abc123')
+    expect(published).toBe('---\ntitle: Notes\ngist_id: abc123\n---\n# Notes\n')
+    expect(setFrontMatterField(published, 'gist_id', 'def456')).toBe(
+      '---\ntitle: Notes\ngist_id: def456\n---\n# Notes\n'
+    )
+  })
+})
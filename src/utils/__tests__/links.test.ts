@@ -0,0 +1,18 @@
+import { describe, it, expect } from 'vitest'
+import { isBareUrl, markdownLink } from '../links'
+
+describe('link utils', () => {
+  it('recognizes a pasted bare URL', () => {
+    expect(isBareUrl('https://example.com/docs?page=2#intro')).toBe(true)
+    expect(isBareUrl('HTTP://EXAMPLE.COM')).toBe(true)
+    expect(isBareUrl('see https://example.com')).toBe(false)
+    expect(isBareUrl('[x](https://example.com)')).toBe(false)
+    expect(isBareUrl('ftp://example.com')).toBe(false)
+  })
+
+  it('escapes brackets in link titles', () => {
+    expect(markdownLink('Docs [beta]', 'https://example.com')).toBe(
+      '[Docs \\[beta\\]](https://example.com)'
+    )
+  })
+})
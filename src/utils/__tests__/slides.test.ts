@@ -0,0 +1,10 @@
+import { describe, it, expect } from 'vitest'
+import { mermaidSources } from '../slides'
+
+describe('slide utils', () => {
+  it('finds mermaid blocks only', () => {
+    const markdown =
+      '# Deck\n\n```mermaid\ngraph TD\n  A --> B\n```\n\n```js\nlet a = 1\n```\n\n```mermaid title\npie\n```\n'
+    expect(mermaidSources(markdown)).toEqual(['graph TD\n  A --> B', 'pie'])
+  })
+})
@@ -0,0 +1,16 @@
+import { describe, it, expect } from 'vitest'
+import { replaceLine, taskLineNumbers, toggleTaskLine } from '../tasks'
+
+describe('task utils', () => {
+  it('finds task items outside code blocks', () => {
+    const markdown = '# Todo\n- [ ] a\n  * [x] b\n```\n- [ ] code\n```\n1. [X] c\n- [] not'
+    expect(taskLineNumbers(markdown)).toEqual([2, 3, 7])
+  })
+
+  it('toggles a task line and keeps line endings', () => {
+    const markdown = '- [ ] a\r\n  - [x] b\r\n'
+    expect(toggleTaskLine(markdown, 1)).toBe('- [x] a\r\n  - [x] b\r\n')
+    expect(toggleTaskLine(markdown, 2)).toBe('- [ ] a\r\n  - [ ] b\r\n')
+    expect(replaceLine(markdown, 2, '  - [ ] b')).toBe('- [ ] a\r\n  - [ ] b\r\n')
+  })
+})
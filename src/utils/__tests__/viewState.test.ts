@@ -0,0 +1,45 @@
+import { describe, it, expect } from 'vitest'
+import {
+  cursorPosition,
+  headingLine,
+  locationViewState,
+  offsetForPosition,
+  toPendingOpen,
+} from '../viewState'
+
+describe('view state utils', () => {
+  it('converts between offsets and line/column', () => {
+    const text = '# Title\n\nSome text'
+    expect(cursorPosition(text, 0)).toEqual({ line: 0, col: 0 })
+    expect(cursorPosition(text, 14)).toEqual({ line: 2, col: 5 })
+    expect(offsetForPosition(text, 2, 5)).toBe(14)
+  })
+
+  it('clamps positions past the end of a changed file', () => {
+    const text = 'short\nfile'
+    expect(offsetForPosition(text, 0, 40)).toBe(5)
+    expect(offsetForPosition(text, 10, 0)).toBe(text.length)
+  })
+
+  it('finds headings by text or anchor', () => {
+    const text = '# Notes\n\n## Getting Started!\n\nText\n\n## Setup ##\n'
+    expect(headingLine(text, 'getting-started')).toBe(2)
+    expect(headingLine(text, 'Getting Started!')).toBe(2)
+    expect(headingLine(text, '#setup')).toBe(6)
+    expect(headingLine(text, 'missing')).toBeNull()
+  })
+
+  it('turns a pending location into a view state', () => {
+    const text = 'one\ntwo\nthree\n## Four\nfive'
+    const open = toPendingOpen('/notes/a.md')
+    expect(open).toEqual({ path: '/notes/a.md', line: null, column: null, heading: null })
+    expect(locationViewState(text, open)).toBeNull()
+    expect(locationViewState(text, { ...open, line: 3, column: 2 })).toMatchObject({
+      cursor_line: 2,
+      cursor_col: 1,
+      scroll_percent: 0.5,
+    })
+    expect(locationViewState(text, { ...open, heading: 'four' })?.cursor_line).toBe(3)
+    expect(locationViewState(text, { ...open, heading: 'nope' })).toBeNull()
+  })
+})
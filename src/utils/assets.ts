@@ -0,0 +1,38 @@
+import { invoke } from '@tauri-apps/api/core'
+
+// Image sources the preview loads as written: URLs with a scheme, protocol-relative URLs
+// and anchors. A one-letter "scheme" is a Windows drive, which is a local file.
+function isExternal(src: string): boolean {
+  return /^([a-z][a-z0-9+.-]+:|\/\/|#)/i.test(src)
+}
+
+// The preview's HTML with the local images of the document at `documentPath` pointed at
+// the mdasset scheme, which serves them once allow_document_assets has scoped the
+// document. Images that can't be resolved are left as they are.
+export async function resolveAssetUrls(html: string, documentPath: string): Promise<string> {
+  // An inert document, so nothing starts loading before the sources are rewritten
+  const parsed = new DOMParser().parseFromString(html, 'text/html')
+  const images = Array.from(parsed.body.querySelectorAll('img[src]')).filter(
+    image => !isExternal(image.getAttribute('src') ?? '')
+  )
+  if (images.length === 0) return html
+
+  const urls = new Map<string, Promise<string | null>>()
+  const resolve = (relative: string) => {
+    let url = urls.get(relative)
+    if (!url) {
+      url = invoke<string | null>('resolve_asset_url', { documentPath, relative }).catch(
+        () => null
+      )
+      urls.set(relative, url)
+    }
+    return url
+  }
+  await Promise.all(
+    images.map(async image => {
+      const url = await resolve(image.getAttribute('src') ?? '')
+      if (url) image.setAttribute('src', url)
+    })
+  )
+  return parsed.body.innerHTML
+}
@@ -0,0 +1,50 @@
+import type { CSSProperties } from 'react'
+
+// The editor section of the settings (see EditorSettings in src-tauri/src/settings.rs)
+export interface EditorSettings {
+  font_family: string | null
+  font_size: number
+  line_height: number
+  tab_size: number
+  insert_spaces: boolean
+  word_wrap: boolean
+  show_line_numbers: boolean
+  autosave_enabled: boolean
+  autosave_interval_secs: number
+  format_on_save: boolean
+  rewrite_links_on_save_as: boolean
+}
+
+// Used until the settings load, matching the backend's defaults
+export const DEFAULT_EDITOR_SETTINGS: EditorSettings = {
+  font_family: null,
+  font_size: 14,
+  line_height: 1.6,
+  tab_size: 2,
+  insert_spaces: true,
+  word_wrap: false,
+  show_line_numbers: false,
+  autosave_enabled: true,
+  autosave_interval_secs: 5,
+  format_on_save: false,
+  rewrite_links_on_save_as: true,
+}
+
+// Inline styles for the editor textarea, overriding the defaults in App.css. A chosen font
+// falls back to the usual monospace stack if it's been uninstalled since.
+export function editorStyle(settings: EditorSettings): CSSProperties {
+  const family = settings.font_family?.replace(/["\\]/g, '')
+  return {
+    fontFamily: family ? `"${family}", monospace` : undefined,
+    fontSize: `${settings.font_size}px`,
+    lineHeight: settings.line_height,
+    tabSize: settings.tab_size,
+    whiteSpace: settings.word_wrap ? 'pre-wrap' : 'pre',
+    overflowWrap: settings.word_wrap ? 'break-word' : 'normal',
+  }
+}
+
+// What the Tab key inserts
+export function indentText(settings: EditorSettings): string {
+  return settings.insert_spaces ? ' '.repeat(settings.tab_size) : '\t'
+}
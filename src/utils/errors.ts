@@ -0,0 +1,20 @@
+// Error returned by backend commands (see src-tauri/src/error.rs)
+export interface CommandError {
+  code: string
+  message: string
+  details: Record<string, unknown> | null
+}
+
+export function isCommandError(error: unknown): error is CommandError {
+  return (
+    typeof error === 'object' &&
+    error !== null &&
+    typeof (error as CommandError).code === 'string' &&
+    typeof (error as CommandError).message === 'string'
+  )
+}
+
+// Human-readable text for anything a command rejected with
+export function errorMessage(error: unknown): string {
+  return isCommandError(error) ? error.message : String(error)
+}
@@ -0,0 +1,21 @@
+// Set `key: value` in a document's YAML frontmatter, replacing an existing entry or adding
+// a frontmatter block when the document has none
+export function setFrontMatterField(markdown: string, key: string, value: string): string {
+  const entry = `${key}: ${value}`
+  const lines = markdown.split('\n')
+  if (lines[0]?.trimEnd() === '---') {
+    for (let i = 1; i < lines.length; i++) {
+      const line = lines[i].trimEnd()
+      if (line === '---' || line === '...') {
+        lines.splice(i, 0, entry)
+        return lines.join('\n')
+      }
+      const separator = line.indexOf(':')
+      if (separator > 0 && line.slice(0, separator).trim() === key) {
+        lines[i] = entry
+        return lines.join('\n')
+      }
+    }
+  }
+  return `---\n${entry}\n---\n\n${markdown}`
+}
@@ -0,0 +1,9 @@
+// A single http(s) URL with nothing around it, as pasted from a browser's address bar
+export function isBareUrl(text: string): boolean {
+  return /^https?:\/\/[^\s<>()[\]]+$/i.test(text)
+}
+
+// `[title](url)`, escaping characters in the title that would end the link text
+export function markdownLink(title: string, url: string): string {
+  return `[${title.replace(/([[\]\\])/g, '\\$1')}](${url})`
+}
@@ -62,7 +62,7 @@ const MATH_DISPLAY_PREFIX = 'MATH_DISPLAY_PLACEHOLDER_'
  * Extract and replace math expressions with placeholders
  * Returns the processed text and the extracted math expressions
  */
-function extractMathExpressions(text: string): {
+export function extractMathExpressions(text: string): {
   text: string
   mathExpressions: Map<string, { type: 'inline' | 'display'; content: string }>
 } {
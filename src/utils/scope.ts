@@ -0,0 +1,19 @@
+import { invoke, type InvokeArgs } from '@tauri-apps/api/core'
+import { isCommandError } from './errors'
+
+// Run a file command on `args.path`. When the path is outside the folders the user opened
+// or approved (e.g. a recent file from another folder), ask them with request_scope and
+// try once more if they allow it.
+export async function invokeInScope<T>(
+  command: string,
+  args: InvokeArgs & { path: string }
+): Promise<T> {
+  try {
+    return await invoke<T>(command, args)
+  } catch (error) {
+    if (!isCommandError(error) || error.code !== 'scope_denied') throw error
+    const allowed = await invoke<boolean>('request_scope', { path: args.path })
+    if (!allowed) throw error
+    return invoke<T>(command, args)
+  }
+}
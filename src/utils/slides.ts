@@ -0,0 +1,79 @@
+import { extractMathExpressions } from './markdown'
+import { appPalette, type Palette } from './theme'
+
+// Math and mermaid rendered here for export_slides, keyed by their trimmed source
+export interface Prerendered {
+  math: Record<string, string>
+  displayMath: Record<string, string>
+  mermaid: Record<string, string>
+}
+
+// The source of every ```mermaid block, trimmed
+export function mermaidSources(markdown: string): string[] {
+  const sources: string[] = []
+  for (const match of markdown.matchAll(/^```mermaid[^\n]*\n([\s\S]*?)^```/gm)) {
+    sources.push(match[1].trim())
+  }
+  return sources
+}
+
+// Render a deck's math and diagrams with the preview's renderers, diagrams in the deck's
+// `palette`. Math is MathML only, so the deck needs no KaTeX stylesheet or fonts to work
+// offline.
+export async function prerenderForSlides(
+  markdown: string,
+  palette: Palette
+): Promise<Prerendered> {
+  const [{ default: katex }, { default: mermaid }] = await Promise.all([
+    import('katex'),
+    import('mermaid'),
+  ])
+  const prerendered: Prerendered = { math: {}, displayMath: {}, mermaid: {} }
+  for (const { type, content } of extractMathExpressions(markdown).mathExpressions.values()) {
+    const displayMode = type === 'display'
+    const rendered = katex.renderToString(content, {
+      throwOnError: false,
+      displayMode,
+      output: 'mathml',
+    })
+    if (displayMode) prerendered.displayMath[content] = rendered
+    else prerendered.math[content] = rendered
+  }
+  const sources = mermaidSources(markdown)
+  const mermaidTheme = (palette: Palette) => (palette === 'dark' ? 'dark' : 'default')
+  mermaid.initialize({ theme: mermaidTheme(palette) })
+  try {
+    for (const [index, source] of sources.entries()) {
+      try {
+        const { svg } = await mermaid.render(`slides-mermaid-${index}`, source)
+        prerendered.mermaid[source] = svg
+      } catch (error) {
+        // Left as source in the deck
+        console.error('Failed to render mermaid diagram for slides:', error)
+      }
+    }
+  } finally {
+    // Back to the preview's theme
+    mermaid.initialize({ theme: mermaidTheme(appPalette()) })
+  }
+  return prerendered
+}
+
+// reveal.js and its notes plugin, embedded in exported decks so they work offline, with
+// the reveal theme for `palette`
+export async function loadRevealRuntime(palette: Palette) {
+  const [script, notesPlugin, stylesheet, theme] = await Promise.all([
+    import('reveal.js/dist/reveal.js?raw'),
+    import('reveal.js/plugin/notes/notes.js?raw'),
+    import('reveal.js/dist/reveal.css?raw'),
+    palette === 'dark'
+      ? import('reveal.js/dist/theme/black.css?raw')
+      : import('reveal.js/dist/theme/white.css?raw'),
+  ])
+  return {
+    script: script.default,
+    notesPlugin: notesPlugin.default,
+    stylesheet: stylesheet.default,
+    theme: theme.default,
+  }
+}
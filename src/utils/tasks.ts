@@ -0,0 +1,35 @@
+// Task list items (see src-tauri/src/tasks.rs): a list marker then `[ ]` or `[x]`
+const TASK_ITEM = /^([ \t]*(?:[-*+]|\d{1,9}[.)])[ \t]+\[)([ xX])(\](?:[ \t]|$))/
+
+// 1-based line numbers of the task items outside code blocks, in the order the preview
+// renders their checkboxes
+export function taskLineNumbers(markdown: string): number[] {
+  const lines: number[] = []
+  let inFence = false
+  markdown.split('\n').forEach((line, index) => {
+    const trimmed = line.trimStart()
+    if (trimmed.startsWith('```') || trimmed.startsWith('~~~')) {
+      inFence = !inFence
+    } else if (!inFence && TASK_ITEM.test(line)) {
+      lines.push(index + 1)
+    }
+  })
+  return lines
+}
+
+// Replace 1-based line `lineNumber` of `markdown` with `text`
+export function replaceLine(markdown: string, lineNumber: number, text: string): string {
+  const lines = markdown.split('\n')
+  const ending = lines[lineNumber - 1]?.endsWith('\r') ? '\r' : ''
+  lines[lineNumber - 1] = text + ending
+  return lines.join('\n')
+}
+
+// Tick or untick the task item on 1-based `lineNumber`
+export function toggleTaskLine(markdown: string, lineNumber: number): string {
+  const line = markdown.split('\n')[lineNumber - 1] ?? ''
+  const toggled = line.replace(TASK_ITEM, (_, before: string, state: string, after: string) =>
+    [before, state === ' ' ? 'x' : ' ', after].join('')
+  )
+  return replaceLine(markdown, lineNumber, toggled.replace(/\r$/, ''))
+}
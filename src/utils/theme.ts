@@ -0,0 +1,18 @@
+// Light or dark colors, as the backend's Palette
+export type Palette = 'light' | 'dark'
+
+// The print settings' export theme
+export type ExportTheme = 'light' | 'dark' | 'match-app'
+
+// What the stored theme preference (see ThemeToggle) resolves to right now. Exports pass it
+// so the backend can resolve the `match-app` theme.
+export function appPalette(): Palette {
+  const stored = localStorage.getItem('theme')
+  if (stored === 'light' || stored === 'dark') return stored
+  return window.matchMedia('(prefers-color-scheme: dark)').matches ? 'dark' : 'light'
+}
+
+// The colors an export with `theme` gets, resolved the way the backend does
+export function exportPalette(theme: ExportTheme): Palette {
+  return theme === 'match-app' ? appPalette() : theme
+}
@@ -0,0 +1,82 @@
+// Where the user left off in a file (see src-tauri/src/view_state.rs)
+export interface FileViewState {
+  cursor_line: number
+  cursor_col: number
+  scroll_percent: number
+  folded_sections: number[]
+}
+
+// Zero-based line and column of a character offset
+export function cursorPosition(text: string, offset: number): { line: number; col: number } {
+  const before = text.slice(0, offset)
+  const lineStart = before.lastIndexOf('\n') + 1
+  return { line: before.split('\n').length - 1, col: offset - lineStart }
+}
+
+// Character offset of a line and column, clamped to the text (the file may have changed)
+export function offsetForPosition(text: string, line: number, col: number): number {
+  const lines = text.split('\n')
+  if (line >= lines.length) return text.length
+  let offset = 0
+  for (let i = 0; i < line; i++) {
+    offset += lines[i].length + 1
+  }
+  return offset + Math.min(col, lines[line].length)
+}
+
+// A file to open and where to put the cursor (see PendingOpen in src-tauri/src/launch.rs).
+// `line` and `column` are 1-based.
+export interface PendingOpen {
+  path: string
+  line: number | null
+  column: number | null
+  heading: string | null
+}
+
+// Older backends (and plain set_pending_file calls) send just the path
+export function toPendingOpen(payload: string | PendingOpen): PendingOpen {
+  return typeof payload === 'string'
+    ? { path: payload, line: null, column: null, heading: null }
+    : payload
+}
+
+// GitHub-style anchor of a heading's text: `Getting Started!` -> `getting-started`
+function headingAnchor(text: string): string {
+  return text
+    .trim()
+    .toLowerCase()
+    .replace(/[^\p{L}\p{N}\s_-]/gu, '')
+    .replace(/\s/g, '-')
+}
+
+// Zero-based line of the first ATX heading whose text or anchor is `heading`
+export function headingLine(text: string, heading: string): number | null {
+  const wanted = headingAnchor(heading.replace(/^#/, ''))
+  const lines = text.split('\n')
+  for (let i = 0; i < lines.length; i++) {
+    const match = /^ {0,3}#{1,6}\s+(.*?)(?:\s+#+)?\s*$/.exec(lines[i])
+    if (match && headingAnchor(match[1]) === wanted) {
+      return i
+    }
+  }
+  return null
+}
+
+// View state putting the cursor at the location asked for in `open`, or null without one
+// (or when the heading isn't in the file)
+export function locationViewState(text: string, open: PendingOpen): FileViewState | null {
+  let line: number | null = null
+  if (open.line) {
+    line = open.line - 1
+  } else if (open.heading) {
+    line = headingLine(text, open.heading)
+  }
+  if (line === null) return null
+  const lineCount = text.split('\n').length
+  return {
+    cursor_line: line,
+    cursor_col: open.column ? open.column - 1 : 0,
+    scroll_percent: lineCount > 1 ? Math.min(line, lineCount - 1) / (lineCount - 1) : 0,
+    folded_sections: [],
+  }
+}